@@ -0,0 +1,82 @@
+use crate::database::Todo;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+/// The open todos due on one local day, and how much work they add up to
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgendaDay {
+    pub date: NaiveDate,
+    /// By due time; on the first day, overdue todos come first
+    pub todo_ids: Vec<i64>,
+    pub estimated_minutes: i64,
+    /// How many of the day's todos have no estimate
+    pub unestimated: usize,
+}
+
+impl AgendaDay {
+    /// How far the day's estimates go over `capacity_minutes`, if they do
+    pub fn overbooked_by(&self, capacity_minutes: i64) -> Option<i64> {
+        Some(self.estimated_minutes - capacity_minutes).filter(|over| *over > 0)
+    }
+}
+
+/// An estimate as minutes: "90", "45m", "2h", "1h30m" or "1.5h"
+pub fn parse_estimate(input: &str) -> Option<i64> {
+    let input: String = input.trim().to_lowercase().split_whitespace().collect();
+    if let Ok(minutes) = input.parse::<i64>() {
+        return Some(minutes).filter(|minutes| *minutes > 0);
+    }
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    for c in input.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' if !number.is_empty() => {
+                let value: f64 = number.parse().ok()?;
+                minutes += if c == 'h' { value * 60.0 } else { value };
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() || minutes <= 0.0 {
+        return None;
+    }
+    Some(minutes.round() as i64)
+}
+
+/// Minutes as "45m", "2h" or "1h30m"
+pub fn format_estimate(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h{:02}m", h, m),
+    }
+}
+
+/// Open todos due from `today` through the next `days - 1` days, one entry per local day
+/// that has any. Overdue todos are still to be done, so they count towards today.
+pub fn agenda(todos: &[Todo], estimates: &HashMap<i64, i64>, today: NaiveDate, days: i64) -> Vec<AgendaDay> {
+    let last = today + Duration::days(days.max(1) - 1);
+    let mut due: Vec<(&Todo, DateTime<Utc>)> = todos
+        .iter()
+        .filter(|todo| !todo.is_completed())
+        .filter_map(|todo| todo.due_by.map(|due_by| (todo, due_by)))
+        .collect();
+    due.sort_by_key(|(todo, due_by)| (*due_by, todo.id));
+
+    let mut by_day: BTreeMap<NaiveDate, AgendaDay> = BTreeMap::new();
+    for (todo, due_by) in due {
+        let date = due_by.with_timezone(&Local).date_naive().max(today);
+        if date > last {
+            continue;
+        }
+        let day = by_day.entry(date).or_insert_with(|| AgendaDay { date, todo_ids: Vec::new(), estimated_minutes: 0, unestimated: 0 });
+        day.todo_ids.push(todo.id);
+        match estimates.get(&todo.id) {
+            Some(minutes) => day.estimated_minutes += minutes,
+            None => day.unestimated += 1,
+        }
+    }
+    by_day.into_values().collect()
+}
@@ -0,0 +1,231 @@
+use crate::config::HookConfig;
+use crate::database::{Database, Todo};
+use crate::http::{self, Request};
+use crate::passphrase;
+use crate::rpc::{self, RpcError};
+use crate::site::escape_html;
+use crate::tree::{TodoTreeManager, TreeNode};
+use chrono::Local;
+use serde_json::{json, Value};
+use std::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone, Default)]
+pub struct ApiOptions {
+    /// Allow POST, PATCH and DELETE; without it the API only reads
+    pub writable: bool,
+    /// Required as a bearer token or `?token=` when set
+    pub token: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Self {
+        Response { status, content_type: "application/json", body: value.to_string() }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+}
+
+/// Serve the web view and the JSON API for `database` until interrupted. Addresses
+/// other than loopback need an `[api] token`.
+pub fn serve(database: &Database, listen: &str, options: &ApiOptions, hooks: Vec<HookConfig>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).map_err(|e| anyhow::anyhow!("Cannot listen on {}: {}", listen, e))?;
+    let addr = listener.local_addr()?;
+    http::require_token_off_loopback(addr, options.token.as_deref(), "[api] token")?;
+    let access = if options.writable { "read-write" } else { "read-only" };
+    println!("Serving todos on http://{} ({})", addr, access);
+    if options.token.is_none() {
+        let can = if options.writable { "read and change" } else { "read" };
+        println!("Warning: no [api] token set; anyone who can connect can {} your todos", can);
+    }
+    let mut server = rpc::Server::new(database, hooks);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+        if let Err(e) = handle_connection(&mut server, stream, options) {
+            eprintln!("Request from {} failed: {}", peer, e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(server: &mut rpc::Server, mut stream: TcpStream, options: &ApiOptions) -> anyhow::Result<()> {
+    let request = http::read_request(&stream)?;
+    let response = route(server, &request, options);
+    http::respond(&mut stream, response.status, response.content_type, &response.body)
+}
+
+/// Answer one request:
+///
+/// ```text
+/// GET    /                    web view of the open todos (?q= searches)
+/// GET    /api/todos           all todos (?filter= takes a filter expression)
+/// GET    /api/search?q=...    title and description search
+/// GET    /api/todos/<id>      one todo
+/// POST   /api/todos           create: {"title", "description", "parent_id", "due"}
+/// PATCH  /api/todos/<id>      update: {"title", "description", "due", "completed"}
+/// DELETE /api/todos/<id>      delete a todo without subtasks
+/// ```
+pub fn route(server: &mut rpc::Server, request: &Request, options: &ApiOptions) -> Response {
+    if let Some(token) = &options.token {
+        let bearer = http::bearer_matches(request.authorization.as_deref(), token);
+        let in_query = request.query_param("token").is_some_and(|given| passphrase::constant_time_eq(given.as_bytes(), token.as_bytes()));
+        if !bearer && !in_query {
+            return Response::error(401, "Missing or wrong token");
+        }
+    }
+
+    let path = request.path().trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let is_write = matches!(request.method.as_str(), "POST" | "PATCH" | "DELETE");
+    if is_write && !options.writable {
+        return Response::error(403, "Read-only; start with --writable to allow changes");
+    }
+    // Browsers send text/plain and form posts from other sites without asking first;
+    // a JSON body needs a preflight that this server never answers
+    if matches!(request.method.as_str(), "POST" | "PATCH") && !is_json(request) {
+        return Response::error(415, "Send the body as Content-Type: application/json");
+    }
+
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", []) => {
+            return match web_view(server.database(), request, options) {
+                Ok(html) => Response { status: 200, content_type: "text/html; charset=utf-8", body: html },
+                Err(e) => Response::error(500, &e.to_string()),
+            };
+        }
+        ("GET", ["api", "todos"]) => server.call("list", &json!({ "filter": request.query_param("filter") })),
+        ("GET", ["api", "search"]) => server.call("search", &json!({ "query": request.query_param("q") })),
+        ("GET", ["api", "todos", id]) => with_id(id, json!({})).and_then(|params| server.call("get", &params)),
+        ("POST", ["api", "todos"]) => {
+            return match json_body(request).and_then(|params| server.call("create", &params)) {
+                Ok(todo) => Response::json(201, &todo),
+                Err(e) => error_response(e),
+            };
+        }
+        ("PATCH", ["api", "todos", id]) => json_body(request).and_then(|body| with_id(id, body)).and_then(|params| server.call("update", &params)),
+        ("DELETE", ["api", "todos", id]) => with_id(id, json!({})).and_then(|params| server.call("delete", &params)),
+        (_, ["api", "todos"] | ["api", "todos", _] | ["api", "search"]) => return Response::error(405, "Method not allowed here"),
+        _ => return Response::error(404, "Not found"),
+    };
+    match result {
+        Ok(value) => Response::json(200, &value),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(RpcError(code, message): RpcError) -> Response {
+    let status = match code {
+        rpc::INVALID_PARAMS => 400,
+        rpc::TODO_NOT_FOUND | rpc::METHOD_NOT_FOUND => 404,
+        _ => 500,
+    };
+    Response::error(status, &message)
+}
+
+fn is_json(request: &Request) -> bool {
+    request
+        .content_type
+        .as_deref()
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/json"))
+}
+
+fn json_body(request: &Request) -> Result<Value, RpcError> {
+    let body = request.body.as_deref().ok_or_else(|| RpcError(rpc::INVALID_PARAMS, "Request too large".to_string()))?;
+    match serde_json::from_slice(body) {
+        Ok(value @ Value::Object(_)) => Ok(value),
+        Ok(_) => Err(RpcError(rpc::INVALID_PARAMS, "Expected a JSON object".to_string())),
+        Err(e) => Err(RpcError(rpc::INVALID_PARAMS, format!("Invalid JSON: {}", e))),
+    }
+}
+
+fn with_id(id: &str, mut params: Value) -> Result<Value, RpcError> {
+    let id: i64 = id.parse().map_err(|_| RpcError(rpc::TODO_NOT_FOUND, format!("Invalid todo id '{}'", id)))?;
+    params["id"] = json!(id);
+    Ok(params)
+}
+
+/// A plain page for phones: the open todos as the app's tree, or search results.
+/// In writable mode, checkboxes complete todos and a field adds new ones.
+fn web_view(database: &Database, request: &Request, options: &ApiOptions) -> anyhow::Result<String> {
+    let query = request.query_param("q").unwrap_or_default();
+    let token_field = match request.query_param("token") {
+        Some(token) => format!(r#"<input type="hidden" name="token" value="{}">"#, escape_html(&token)),
+        None => String::new(),
+    };
+
+    let mut items = String::new();
+    if query.trim().is_empty() {
+        let mut tree = TodoTreeManager::new();
+        tree.rebuild_from_todos(database.get_all_todos()?);
+        render_nodes(&tree, &tree.tree, options.writable, &mut items);
+    } else {
+        for todo in database.search_todos(&query)? {
+            items.push_str(&render_todo(&todo, options.writable));
+            items.push_str("</li>");
+        }
+    }
+
+    let add_form = if options.writable {
+        r#"<form onsubmit="add(event)"><input id="title" placeholder="New todo (p1 #tag)" autocomplete="off"><button>Add</button></form>"#
+    } else {
+        ""
+    };
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1"><title>TodoDB</title>
+<style>body{{font-family:system-ui,sans-serif;background:#303446;color:#c6d0f5;margin:1em}}ul{{list-style:none;padding-left:1.2em}}li{{margin:.3em 0}}
+small{{color:#a5adce}}.done{{text-decoration:line-through;color:#737994}}input,button{{font-size:1em}}form{{margin-bottom:.8em}}</style></head>
+<body><form>{token}<input name="q" value="{query}" placeholder="Search"><button>Search</button></form>{add}<ul>{items}</ul>
+<script>
+const token = new URLSearchParams(location.search).get('token');
+function api(method, path, body) {{
+  const headers = {{'Content-Type': 'application/json'}};
+  if (token) headers['Authorization'] = 'Bearer ' + token;
+  fetch(path, {{method, headers, body: JSON.stringify(body)}})
+    .then(r => r.ok ? location.reload() : r.json().then(e => alert(e.error)));
+}}
+function toggle(id, completed) {{ api('PATCH', '/api/todos/' + id, {{completed}}); }}
+function add(event) {{ event.preventDefault(); api('POST', '/api/todos', {{title: document.getElementById('title').value}}); }}
+</script></body></html>"#,
+        token = token_field,
+        query = escape_html(&query),
+        add = add_form,
+        items = items,
+    ))
+}
+
+/// Open todos and their open subtasks, nested like the tree view
+fn render_nodes(tree: &TodoTreeManager, nodes: &[TreeNode], writable: bool, out: &mut String) {
+    for node in nodes {
+        let Some(todo) = tree.get_todo_by_id(node.id).filter(|todo| !todo.is_completed()) else { continue };
+        out.push_str(&render_todo(todo, writable));
+        if !node.children.is_empty() {
+            out.push_str("<ul>");
+            render_nodes(tree, &node.children, writable, out);
+            out.push_str("</ul>");
+        }
+        out.push_str("</li>");
+    }
+}
+
+/// An unclosed `<li>` for one todo, so subtasks can go inside it
+fn render_todo(todo: &Todo, writable: bool) -> String {
+    let checkbox = if writable {
+        format!(r#"<input type="checkbox" {}onchange="toggle({}, this.checked)"> "#, if todo.is_completed() { "checked " } else { "" }, todo.id)
+    } else {
+        String::new()
+    };
+    let due = todo.due_by.map_or(String::new(), |due| format!(" <small>due {}</small>", due.with_timezone(&Local).format("%Y-%m-%d")));
+    let class = if todo.is_completed() { r#" class="done""# } else { "" };
+    format!("<li>{}<span{}>{}</span>{}", checkbox, class, escape_html(&todo.title), due)
+}
@@ -0,0 +1,84 @@
+use crate::database::Database;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// Hash of the (empty) chain before the first event
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One entry in the completion log: a todo being completed or reopened
+#[derive(Debug, Clone)]
+pub struct CompletionEvent {
+    pub id: i64,
+    pub todo_id: i64,
+    /// "complete" or "uncomplete"
+    pub event: String,
+    pub title: String,
+    /// RFC 3339 timestamp, hashed exactly as stored
+    pub at: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Each event's hash covers its contents and the previous hash, so rewriting or
+/// dropping any earlier event changes every hash after it
+pub fn event_hash(prev_hash: &str, todo_id: i64, event: &str, title: &str, at: &str) -> String {
+    let mut hasher = Sha256::new();
+    for field in [prev_hash, &todo_id.to_string(), event, title, at] {
+        hasher.update(field.as_bytes());
+        hasher.update([0u8]); // Separator so fields can't run into each other
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub events: usize,
+    /// Hash of the last event; compare it out-of-band to detect a rewritten chain
+    pub head: Option<String>,
+    pub problems: Vec<String>,
+    /// Completed todos with no logged completion (e.g. completed before logging was enabled)
+    pub unlogged: Vec<i64>,
+}
+
+/// Recompute the hash chain and check it against the completion state of each todo
+pub fn verify(database: &Database) -> anyhow::Result<VerifyReport> {
+    let events = database.get_completion_events()?;
+    let mut report = VerifyReport { events: events.len(), ..Default::default() };
+
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for event in &events {
+        if event.prev_hash != prev_hash {
+            report.problems.push(format!("Event {}: chain broken (previous event missing or altered)", event.id));
+        }
+        let expected = event_hash(&event.prev_hash, event.todo_id, &event.event, &event.title, &event.at);
+        if event.hash != expected {
+            report.problems.push(format!("Event {}: contents do not match its hash", event.id));
+        }
+        prev_hash = event.hash.clone();
+    }
+    report.head = events.last().map(|event| event.hash.clone());
+
+    // The latest logged event for each todo must agree with its current completion state
+    for todo in database.get_all_todos()? {
+        match events.iter().rev().find(|event| event.todo_id == todo.id) {
+            Some(event) if event.event == "complete" => {
+                let logged_at = DateTime::parse_from_rfc3339(&event.at).map(|at| at.with_timezone(&Utc)).ok();
+                if todo.completed_at != logged_at {
+                    report.problems.push(format!("Todo {}: completion time differs from the log", todo.id));
+                }
+            }
+            Some(_) => {
+                if todo.completed_at.is_some() {
+                    report.problems.push(format!("Todo {}: completed without a logged completion", todo.id));
+                }
+            }
+            None => {
+                if todo.completed_at.is_some() {
+                    report.unlogged.push(todo.id);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
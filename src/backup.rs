@@ -0,0 +1,98 @@
+use crate::config::Config;
+use crate::database::Database;
+use chrono::{NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+const STAMP_LEN: usize = 15;
+
+/// Back up the open database into `dir` as `<name>-<YYYYMMDD-HHMMSS>[-<reason>].db`, then
+/// delete all but its newest `keep` backups there (0 keeps them all). Returns the new backup.
+pub fn backup(database: &Database, dir: &Path, reason: Option<&str>, keep: usize) -> anyhow::Result<PathBuf> {
+    let db_path = database.path().ok_or_else(|| anyhow::anyhow!("An in-memory database has no file to back up"))?;
+    let dest = new_backup_path(Path::new(db_path), dir, reason)?;
+    database.backup_to(&dest)?;
+    rotate(Path::new(db_path), dir, keep)?;
+    Ok(dest)
+}
+
+/// The automatic backup taken before an import or a restore, when `[backup] auto` is on
+pub fn auto_backup(database: &Database, config: &Config, reason: &str) -> anyhow::Result<Option<PathBuf>> {
+    let Some(db_path) = database.path().filter(|_| config.backup.auto) else {
+        return Ok(None);
+    };
+    backup(database, &config.backup_dir(Path::new(db_path)), Some(reason), config.backup.keep).map(Some)
+}
+
+/// Back up the database file at `db_path` before opening it, if opening it would upgrade
+/// its schema and `[backup] auto` is on
+pub fn backup_before_upgrade(db_path: &str, config: &Config) -> anyhow::Result<Option<PathBuf>> {
+    if !config.backup.auto || !Database::needs_upgrade(db_path)? {
+        return Ok(None);
+    }
+    let path = Path::new(db_path);
+    let dir = config.backup_dir(path);
+    let dest = new_backup_path(path, &dir, Some("upgrade"))?;
+    Database::backup_file(db_path, &dest)?;
+    rotate(path, &dir, config.backup.keep)?;
+    Ok(Some(dest))
+}
+
+/// Backups of the database at `db_path` found in `dir`, oldest first
+pub fn list_backups(db_path: &Path, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}-", database_name(db_path));
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Another database's backups can share the prefix ("todos-work-..."), but not the stamp
+        let Some(stamp) = name.strip_prefix(&prefix).and_then(|rest| rest.get(..STAMP_LEN)) else { continue };
+        if !name.ends_with(".db") || NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).is_err() {
+            continue;
+        }
+        // Backups taken within the same second are told apart by when they were written
+        backups.push((stamp.to_string(), entry.metadata()?.modified()?, entry.path()));
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, _, path)| path).collect())
+}
+
+/// Delete all but the newest `keep` backups of the database at `db_path` (0 keeps them
+/// all). Returns how many were deleted.
+pub fn rotate(db_path: &Path, dir: &Path, keep: usize) -> anyhow::Result<usize> {
+    if keep == 0 {
+        return Ok(0);
+    }
+    let backups = list_backups(db_path, dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(excess)
+}
+
+/// A path in `dir`, created if needed, for a backup taken now that no other backup uses
+fn new_backup_path(db_path: &Path, dir: &Path, reason: Option<&str>) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let mut base = format!("{}-{}", database_name(db_path), Utc::now().format(STAMP_FORMAT));
+    if let Some(reason) = reason {
+        base.push('-');
+        base.push_str(reason);
+    }
+    let mut dest = dir.join(format!("{}.db", base));
+    let mut n = 2;
+    while dest.exists() {
+        dest = dir.join(format!("{}-{}.db", base, n));
+        n += 1;
+    }
+    Ok(dest)
+}
+
+/// The database file's name without its extension: `todos` for `~/todos.db`
+fn database_name(db_path: &Path) -> String {
+    db_path.file_stem().map_or_else(|| "todos".to_string(), |stem| stem.to_string_lossy().to_string())
+}
@@ -0,0 +1,83 @@
+use crate::database::Database;
+use crate::http;
+use crate::ical::{self, VTodo};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PushReport {
+    /// Todos created or updated on the server
+    pub uploaded: usize,
+    /// Deleted todos removed from the server
+    pub deleted: usize,
+}
+
+/// Upload every todo changed since the last push to the CalDAV task collection at
+/// `collection` (one `<uid>.ics` resource per todo) and remove the resources of
+/// deleted todos. One-way: changes made on the server are overwritten, not read.
+pub fn push(database: &Database, collection: &str, credentials: Option<(&str, &str)>) -> anyhow::Result<PushReport> {
+    let collection = format!("{}/", collection.trim_end_matches('/'));
+    let pushed = database.get_caldav_pushed(&collection)?;
+    let mut headers = vec![("Content-Type", "text/calendar; charset=utf-8".to_string())];
+    if let Some((username, password)) = credentials {
+        headers.push(("Authorization", http::basic_auth(username, password)));
+    }
+
+    let mut report = PushReport::default();
+    for record in database.get_sync_records()? {
+        let resource = format!("{}{}.ics", collection, record.uid);
+        match record.todo {
+            Some(todo) => {
+                if pushed.get(&record.uid) == Some(&record.updated_at) {
+                    continue;
+                }
+                let vtodo = VTodo {
+                    uid: Some(record.uid.clone()),
+                    summary: todo.title,
+                    description: todo.description,
+                    created: Some(todo.created_at),
+                    due: todo.due_by,
+                    completed: todo.completed_at,
+                    is_completed: todo.completed_at.is_some(),
+                    parent_uid: todo.parent_uid,
+                    ..VTodo::default()
+                };
+                let (status, body) = http::request("PUT", &resource, &headers, &ical::write_vcalendar(&vtodo, record.updated_at))?;
+                if !(200..300).contains(&status) {
+                    return Err(server_error(status, &body, &resource));
+                }
+                database.set_caldav_pushed(&collection, &record.uid, Some(record.updated_at))?;
+                report.uploaded += 1;
+            }
+            None => {
+                if !pushed.contains_key(&record.uid) {
+                    continue;
+                }
+                let (status, body) = http::request("DELETE", &resource, &headers, "")?;
+                // Already gone (deleted on the phone, say) is as good as deleted
+                if !(200..300).contains(&status) && status != 404 {
+                    return Err(server_error(status, &body, &resource));
+                }
+                database.set_caldav_pushed(&collection, &record.uid, None)?;
+                report.deleted += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn server_error(status: u16, body: &str, resource: &str) -> anyhow::Error {
+    let hint = match status {
+        401 | 403 => " (check [caldav] username and password)",
+        404 | 405 | 409 => " (is the url a task collection?)",
+        _ => "",
+    };
+    // Nextcloud and other sabre/dav servers explain in <s:message>
+    let message = body
+        .split_once("message>")
+        .and_then(|(_, rest)| rest.split_once("</"))
+        .map(|(message, _)| message.trim())
+        .or_else(|| body.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('<')));
+    match message {
+        Some(detail) => anyhow::anyhow!("CalDAV server returned {} for {}{}: {}", status, resource, hint, detail),
+        None => anyhow::anyhow!("CalDAV server returned {} for {}{}", status, resource, hint),
+    }
+}
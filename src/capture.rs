@@ -0,0 +1,125 @@
+use crate::database::Todo;
+use crate::dates;
+use chrono::{DateTime, NaiveTime, Utc};
+
+/// A todo typed as one quick-capture line
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedTodo {
+    pub title: String,
+    pub due_by: Option<DateTime<Utc>>,
+    /// `start:<date>`: keep the todo out of the tree and list until then
+    pub start_at: Option<DateTime<Utc>>,
+    /// The `^` parent reference as typed: a todo id or a short code like `WEB-12`
+    pub parent: Option<String>,
+    /// `>search`: the rest of the line, matched against titles to find the parent
+    pub parent_search: Option<String>,
+}
+
+/// Parse a quick-capture line such as `Book flights !p1 @travel due:2d`:
+/// `!pN` becomes the `pN` title prefix, `@tag` is appended as `#tag` and
+/// `due:<date>` takes anything the due date fields accept (`2d`, `2025-10-20`),
+/// and `start:<date>` defers the todo until then.
+/// `^WEB-12` or `^42` files the todo under that todo instead of the highlighted one,
+/// and a trailing `>search` under the todo whose title best matches the search.
+/// A due day without a time is due at `due_time`.
+pub fn parse_capture_line(line: &str, due_time: NaiveTime) -> anyhow::Result<CapturedTodo> {
+    let (line, parent_search) = split_parent_search(line);
+    let mut priority = None;
+    let mut tags = Vec::new();
+    let mut due_by = None;
+    let mut start_at = None;
+    let mut parent = None;
+    let mut words = Vec::new();
+
+    for word in line.split_whitespace() {
+        if let Some(level) = word.strip_prefix("!p").or_else(|| word.strip_prefix("!P"))
+            && !level.is_empty()
+            && level.chars().all(|c| c.is_ascii_digit()) {
+            priority = Some(level.to_string());
+        } else if let Some(tag) = word.strip_prefix('@')
+            && !tag.is_empty() {
+            tags.push(format!("#{}", tag));
+        } else if let Some(reference) = word.strip_prefix('^')
+            && !reference.is_empty() {
+            parent = Some(reference.to_string());
+        } else if let Some(due) = word.strip_prefix("due:") {
+            due_by = Some(dates::parse_due_date_at(due, due_time)
+                .ok_or_else(|| anyhow::anyhow!("Invalid due date '{}': use 2d, 1w, 3h or YYYY-MM-DD", due))?);
+        } else if let Some(start) = word.strip_prefix("start:") {
+            start_at = Some(dates::parse_start_date(start)
+                .ok_or_else(|| anyhow::anyhow!("Invalid start date '{}': use 2d, 1w, 3h or YYYY-MM-DD", start))?);
+        } else {
+            words.push(word);
+        }
+    }
+
+    if words.is_empty() {
+        return Err(anyhow::anyhow!("Title cannot be empty"));
+    }
+
+    let mut title = words.join(" ");
+    if let Some(level) = priority {
+        title = format!("p{} {}", level, title);
+    }
+    for tag in tags {
+        title.push(' ');
+        title.push_str(&tag);
+    }
+
+    Ok(CapturedTodo { title, due_by, start_at, parent, parent_search: parent_search.map(str::to_string) })
+}
+
+/// Split a capture line at its `>search`, which runs to the end of the line: the line
+/// before it and the search, if any
+pub fn split_parent_search(line: &str) -> (&str, Option<&str>) {
+    let start = line.char_indices().find(|&(i, c)| {
+        c == '>'
+            && line[..i].chars().next_back().is_none_or(char::is_whitespace)
+            && line[i + 1..].chars().next().is_some_and(|next| !next.is_whitespace())
+    });
+    match start {
+        Some((i, _)) => (&line[..i], Some(line[i + 1..].trim())),
+        None => (line, None),
+    }
+}
+
+/// The todo a `>search` files a captured todo under: an exact title match (ignoring case)
+/// before a title starting with the search before one containing it, open todos before
+/// completed ones, then the shortest title
+pub fn find_parent<'a>(todos: impl IntoIterator<Item = &'a Todo>, search: &str) -> Option<&'a Todo> {
+    let search = search.trim().to_lowercase();
+    if search.is_empty() {
+        return None;
+    }
+    todos
+        .into_iter()
+        .filter_map(|todo| {
+            let title = todo.title.to_lowercase();
+            let rank = if title == search {
+                0
+            } else if title.starts_with(&search) {
+                1
+            } else if title.contains(&search) {
+                2
+            } else {
+                return None;
+            };
+            Some(((rank, todo.is_completed(), todo.title.len(), todo.id), todo))
+        })
+        .min_by_key(|(key, _)| *key)
+        .map(|(_, todo)| todo)
+}
+
+/// Parse everything typed or pasted into the capture prompt: one todo per line, with
+/// `;` separating several on one line. Blank entries are skipped.
+pub fn parse_capture_input(input: &str, due_time: NaiveTime) -> anyhow::Result<Vec<CapturedTodo>> {
+    let entries: Vec<&str> = input.split(['\n', ';']).map(str::trim).filter(|entry| !entry.is_empty()).collect();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| match parse_capture_line(entry, due_time) {
+            Err(e) if entries.len() > 1 => Err(anyhow::anyhow!("Todo {} ('{}'): {}", i + 1, entry, e)),
+            result => result,
+        })
+        .collect()
+}
@@ -0,0 +1,773 @@
+use crate::audit;
+use crate::backup;
+use crate::caldav;
+use crate::completions;
+use crate::config::Config;
+use crate::database::{Database, Todo};
+use crate::doctor;
+use crate::export::{self, CsvColumn, SnapshotOptions};
+use crate::filter::Filter;
+use crate::history;
+use crate::import;
+use crate::merge::{self, Conflict, ConflictKind};
+use crate::mirror;
+use crate::org;
+use crate::report::{self, ReportFormat};
+use crate::rpc;
+use crate::site;
+use crate::sync;
+use crate::template;
+use chrono::{Local, Utc};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Non-interactive subcommands, run instead of the TUI
+pub const COMMANDS: &[&str] = &["list", "query", "export", "verify", "doctor", "import", "template", "encrypt", "move", "mirror", "rebuild", "sync", "caldav", "serve", "prefix", "backup", "restore", "pick", "completions", "report", "merge", "lock-hash"];
+
+/// Position of the subcommand in `args`: `tododb list ...` or `tododb <db_path> list ...`
+pub fn command_index(args: &[String]) -> Option<usize> {
+    (1..args.len().min(3)).find(|&i| COMMANDS.contains(&args[i].as_str()))
+}
+
+/// Run a subcommand; `args` starts with the command name
+pub fn run(database: &mut Database, args: &[String]) -> anyhow::Result<()> {
+    match args[0].as_str() {
+        "list" => list(database, &args[1..]),
+        "query" => query(database, &args[1..]),
+        "export" => export(database, &args[1..]),
+        "verify" => verify(database),
+        "doctor" => doctor_command(database, &args[1..]),
+        "import" => import(database, &args[1..]),
+        "template" => template(database, &args[1..]),
+        "encrypt" => encrypt(database, &args[1..]),
+        "move" => move_subtree(database, &args[1..]),
+        "mirror" => mirror(database, &args[1..]),
+        "rebuild" => rebuild(database, &args[1..]),
+        "sync" => sync(database, &args[1..]),
+        "caldav" => caldav(database, &args[1..]),
+        "serve" => serve(database, &args[1..]),
+        "prefix" => prefix(database, &args[1..]),
+        "backup" => backup_command(database, &args[1..]),
+        "restore" => restore(database, &args[1..]),
+        "pick" => pick(database, &args[1..]),
+        "completions" => completions(&args[1..]),
+        "report" => report_command(database, &args[1..]),
+        "merge" => merge_command(database, &args[1..]),
+        "lock-hash" => lock_hash(),
+        other => Err(anyhow::anyhow!("Unknown command '{}'", other)),
+    }
+}
+
+/// `tododb list [expression]`: print todos matching a filter expression (all todos if omitted)
+fn list(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let expression = args.join(" ");
+    let todos = if expression.trim().is_empty() {
+        database.get_all_todos()?
+    } else {
+        let filter = Filter::parse(&expression).map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+        database.query_todos(&filter)?
+    };
+
+    let codes = database.refresh_short_codes()?;
+    let mut out = io::stdout().lock();
+    for todo in &todos {
+        writeln!(out, "{}", format_todo_line(todo, &codes))?;
+    }
+    Ok(())
+}
+
+/// `tododb query <expression> [--json]`: like `list`, but `--json` prints one JSON object per
+/// todo for scripts (`jq`, `fzf`)
+fn query(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let expression = args.iter().filter(|arg| *arg != "--json").cloned().collect::<Vec<_>>().join(" ");
+    if !json {
+        return list(database, &[expression]);
+    }
+    let mut out = io::stdout().lock();
+    for line in query_json_lines(database, &expression)? {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct TodoJson<'a> {
+    #[serde(flatten)]
+    todo: &'a Todo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+}
+
+/// Todos matching `expression` (all todos if empty) as JSON lines, with their short codes
+pub fn query_json_lines(database: &Database, expression: &str) -> anyhow::Result<Vec<String>> {
+    let todos = if expression.trim().is_empty() {
+        database.get_all_todos()?
+    } else {
+        let filter = Filter::parse(expression).map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+        database.query_todos(&filter)?
+    };
+    let codes = database.refresh_short_codes()?;
+    todos
+        .iter()
+        .map(|todo| Ok(serde_json::to_string(&TodoJson { todo, code: codes.get(&todo.id).map(String::as_str) })?))
+        .collect()
+}
+
+/// `tododb pick [expression] [--list]`: choose an open todo with fzf and print its id, for
+/// `git commit -m "$(tododb pick)"`; `--list` prints the choices for another picker instead
+fn pick(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let list_only = args.iter().any(|arg| arg == "--list");
+    let expression = args.iter().filter(|arg| *arg != "--list").cloned().collect::<Vec<_>>().join(" ");
+    let choices = pick_lines(database, &expression)?;
+    if list_only {
+        let mut out = io::stdout().lock();
+        for line in &choices {
+            writeln!(out, "{}", line)?;
+        }
+        return Ok(());
+    }
+
+    let mut child = std::process::Command::new("fzf")
+        .args(["--delimiter", "\t", "--with-nth", "2..", "--prompt", "todo> "])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Could not run fzf ({}); `tododb pick --list` prints the choices for another picker", e))?;
+    // fzf reads keys from the terminal, so the choices can go to its stdin
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("fzf has no stdin"))?;
+    for line in &choices {
+        // fzf quits without reading everything when the user picks early
+        if writeln!(stdin, "{}", line).is_err() {
+            break;
+        }
+    }
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    let id = chosen.split('\t').next().map(str::trim).filter(|id| !id.is_empty());
+    match id {
+        Some(id) if output.status.success() => {
+            println!("{}", id);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!("No todo picked")),
+    }
+}
+
+/// Open todos matching `expression` (all if empty) as `id<TAB>title  (parent path)` lines,
+/// with the short code before the title when codes are on
+pub fn pick_lines(database: &Database, expression: &str) -> anyhow::Result<Vec<String>> {
+    let all = database.get_all_todos()?;
+    let todos = if expression.trim().is_empty() {
+        all.clone()
+    } else {
+        let filter = Filter::parse(expression).map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+        database.query_todos(&filter)?
+    };
+    let by_id: HashMap<i64, &Todo> = all.iter().map(|todo| (todo.id, todo)).collect();
+    let codes = database.refresh_short_codes()?;
+    let clean = |text: &str| text.replace(['\t', '\n'], " ");
+
+    Ok(todos
+        .iter()
+        .filter(|todo| !todo.is_completed())
+        .map(|todo| {
+            let mut line = format!("{}\t", todo.id);
+            if let Some(code) = codes.get(&todo.id) {
+                line.push_str(&format!("[{}] ", code));
+            }
+            line.push_str(&clean(&todo.title));
+            let mut path = Vec::new();
+            let mut parent_id = todo.parent_id;
+            while let Some(parent) = parent_id.and_then(|id| by_id.get(&id)) {
+                path.push(clean(&parent.title));
+                parent_id = parent.parent_id;
+                if path.len() > all.len() {
+                    break;
+                }
+            }
+            if !path.is_empty() {
+                path.reverse();
+                line.push_str(&format!("  ({})", path.join(" / ")));
+            }
+            line
+        })
+        .collect())
+}
+
+const REPORT_USAGE: &str = "Usage: tododb report [--since <1w|3d|YYYY-MM-DD>] [--format markdown|text]";
+
+/// `tododb report [--since 1w] [--format markdown]`: what was completed, by project and day
+fn report_command(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let mut since = "1w".to_string();
+    let mut format = ReportFormat::Markdown;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--since" => since = args.next().ok_or_else(|| anyhow::anyhow!(REPORT_USAGE))?.clone(),
+            "--format" => format = ReportFormat::parse(args.next().ok_or_else(|| anyhow::anyhow!(REPORT_USAGE))?)?,
+            _ => return Err(anyhow::anyhow!(REPORT_USAGE)),
+        }
+    }
+    let since = report::parse_since(&since, Utc::now()).ok_or_else(|| anyhow::anyhow!("Invalid --since '{}'", since))?;
+    print!("{}", report::completed_report(&database.get_all_todos()?, &database.get_cancelled_ids()?, since, format));
+    Ok(())
+}
+
+/// `tododb completions <bash|zsh|fish>`: print the shell completion script
+pub fn completions(args: &[String]) -> anyhow::Result<()> {
+    let [shell] = args else {
+        return Err(anyhow::anyhow!("Usage: tododb completions <{}>", completions::SHELLS.join("|")));
+    };
+    print!("{}", completions::script(shell)?);
+    Ok(())
+}
+
+/// `tododb doctor [--fix]`: report (and repair) dates that break sorting and due colors
+fn doctor_command(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let fix = match args {
+        [] => false,
+        [flag] if flag == "--fix" => true,
+        _ => return Err(anyhow::anyhow!("Usage: tododb doctor [--fix]")),
+    };
+    let now = Utc::now();
+    let findings = doctor::check(database, now)?;
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    if findings.is_empty() {
+        println!("OK: no date anomalies");
+        Ok(())
+    } else if fix {
+        let fixed = doctor::fix(database, now)?;
+        println!("Fixed {} todos", fixed);
+        database.checkpoint()
+    } else {
+        Err(anyhow::anyhow!("{} date anomalies found; run 'tododb doctor --fix' to repair them", findings.len()))
+    }
+}
+
+const EXPORT_USAGE: &str =
+    "Usage: tododb export <file.db|file.json> | --html <dir> | --csv [file.csv] [--columns a,b] | --org [file.org] [--no-descriptions] [--no-hidden] [--root <id>]";
+
+/// `tododb export <file.db|file.json> [--no-descriptions] [--no-hidden] [--root <id>]`:
+/// write a sanitized, read-only snapshot for sharing; `--html <dir>` writes static pages
+/// `--csv` a spreadsheet and `--org` an Emacs org outline (both to stdout without a file)
+fn export(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let mut options = SnapshotOptions::default();
+    let mut output = None;
+    let mut html_dir = None;
+    let mut csv = false;
+    let mut org = false;
+    let mut columns = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-descriptions" => options.strip_descriptions = true,
+            "--no-hidden" => options.exclude_hidden = true,
+            "--html" => html_dir = Some(args.next().ok_or_else(|| anyhow::anyhow!("--html needs a directory"))?),
+            "--csv" => csv = true,
+            "--org" => org = true,
+            "--columns" => {
+                let list = args.next().ok_or_else(|| anyhow::anyhow!("--columns needs a list such as id,title,completed"))?;
+                columns = Some(CsvColumn::parse_list(list)?);
+            }
+            "--root" => {
+                let id = args.next().ok_or_else(|| anyhow::anyhow!("--root needs a todo id"))?;
+                options.root = Some(resolve_todo(database, id)?);
+            }
+            flag if flag.starts_with("--") => return Err(anyhow::anyhow!("Unknown export option '{}'", flag)),
+            path if output.is_none() => output = Some(path),
+            extra => return Err(anyhow::anyhow!("Unexpected argument '{}'", extra)),
+        }
+    }
+
+    if let Some(dir) = html_dir {
+        if output.is_some() || csv || org {
+            return Err(anyhow::anyhow!(EXPORT_USAGE));
+        }
+        let count = site::export_site(database, Path::new(dir), &options)?;
+        println!("Exported {} todos to {}", count, Path::new(dir).join("index.html").display());
+        return Ok(());
+    }
+    let has_extension = |extension: &str| output.is_some_and(|path| Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension)));
+    csv |= has_extension("csv");
+    org |= has_extension("org");
+    if csv && org {
+        return Err(anyhow::anyhow!(EXPORT_USAGE));
+    }
+    if columns.is_some() && !csv {
+        return Err(anyhow::anyhow!("--columns only applies to --csv"));
+    }
+    if csv || org {
+        let todos = export::snapshot_todos(database, &options)?;
+        let text = if csv {
+            export::todos_to_csv(&todos, columns.as_deref().unwrap_or(CsvColumn::DEFAULT))
+        } else {
+            org::write_org(&todos)
+        };
+        return match output {
+            Some(path) if Path::new(path).exists() => Err(anyhow::anyhow!("{} already exists", path)),
+            Some(path) => {
+                std::fs::write(path, text)?;
+                println!("Exported {} todos to {}", todos.len(), path);
+                Ok(())
+            }
+            None => Ok(io::stdout().lock().write_all(text.as_bytes())?),
+        };
+    }
+    let output = output.ok_or_else(|| anyhow::anyhow!(EXPORT_USAGE))?;
+    let count = export::export_snapshot(database, Path::new(output), &options)?;
+    println!("Exported {} todos to {}", count, output);
+    Ok(())
+}
+
+const MOVE_USAGE: &str = "Usage: tododb move <todo_id> <profile|file.db> [--parent <id>] [--copy]";
+
+/// `tododb move <id> <target>`: move a todo and its subtree into another database,
+/// given as a profile name or a database file; `--copy` leaves the original in place
+fn move_subtree(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let mut positional = Vec::new();
+    let mut new_parent = None;
+    let mut keep_original = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--parent" => new_parent = Some(parse_todo_id(args.next().ok_or_else(|| anyhow::anyhow!(MOVE_USAGE))?)?),
+            "--copy" => keep_original = true,
+            flag if flag.starts_with("--") => return Err(anyhow::anyhow!("Unknown move option '{}'", flag)),
+            value => positional.push(value),
+        }
+    }
+    let [id, target] = positional.as_slice() else {
+        return Err(anyhow::anyhow!(MOVE_USAGE));
+    };
+    let id = resolve_todo(database, id)?;
+
+    let config = Config::load()?;
+    let target_path = match config.profile(target) {
+        Ok(profile) => profile.open_path()?,
+        Err(_) if Path::new(target).exists() => target.to_string(),
+        Err(_) => return Err(anyhow::anyhow!("No profile or database file named '{}'", target)),
+    };
+    let same_file = database
+        .path()
+        .and_then(|source| Some(std::fs::canonicalize(source).ok()? == std::fs::canonicalize(&target_path).ok()?));
+    if same_file == Some(true) {
+        return Err(anyhow::anyhow!("{} is the open database; use m in the app to move todos within it", target));
+    }
+
+    let mut target_db = Database::new(&target_path)?;
+    if config.audit.completion_log {
+        target_db.enable_completion_log();
+    }
+    if let Some(dir) = config.mirror_dir(Some(target)) {
+        target_db.set_mirror_dir(dir);
+    }
+    let (new_id, count) = export::transfer_subtree(database, &target_db, id, new_parent, keep_original)?;
+    target_db.checkpoint_and_close()?;
+    database.checkpoint()?;
+    println!(
+        "{} {} todos to {} (top todo is now {})",
+        if keep_original { "Copied" } else { "Moved" },
+        count,
+        target,
+        new_id
+    );
+    Ok(())
+}
+
+/// `tododb lock-hash`: ask for a passphrase and print the `[lock]` line that unlocks
+/// the app with it
+fn lock_hash() -> anyhow::Result<()> {
+    let passphrase = crate::passphrase::new_passphrase()?;
+    println!("passphrase_hash = \"{}\"", crate::passphrase::hash_lock_passphrase(&passphrase));
+    Ok(())
+}
+
+/// `tododb encrypt <file>`: write an encrypted copy of a plaintext database,
+/// to be opened with `tododb <file> --encrypted` from then on
+fn encrypt(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let [output] = args else {
+        return Err(anyhow::anyhow!("Usage: tododb encrypt <new_file.db>"));
+    };
+    #[cfg(feature = "encryption")]
+    {
+        let passphrase = crate::passphrase::new_passphrase()?;
+        database.export_encrypted(output, &passphrase)?;
+        println!("Wrote encrypted copy to {}; open it with: tododb {} --encrypted", output, output);
+        println!("The original is unchanged - delete it once you've checked the copy.");
+        Ok(())
+    }
+    #[cfg(not(feature = "encryption"))]
+    {
+        let _ = (database, output);
+        Err(anyhow::anyhow!("{}", crate::NO_ENCRYPTION))
+    }
+}
+
+/// `tododb verify`: check the completion log hash chain against the todos
+fn verify(database: &Database) -> anyhow::Result<()> {
+    let report = audit::verify(database)?;
+
+    if report.events == 0 {
+        println!("No completion log in this database (enable it with [audit] completion_log = true)");
+        return Ok(());
+    }
+
+    println!("Completion log: {} events", report.events);
+    if let Some(head) = &report.head {
+        println!("Chain head: {}", head);
+    }
+    if !report.unlogged.is_empty() {
+        println!("{} completed todos predate the log and are unverified", report.unlogged.len());
+    }
+
+    if report.problems.is_empty() {
+        println!("OK: completion history is intact");
+        Ok(())
+    } else {
+        for problem in &report.problems {
+            println!("FAIL: {}", problem);
+        }
+        Err(anyhow::anyhow!("Completion history has been altered ({} problems)", report.problems.len()))
+    }
+}
+
+/// `tododb import [path ...]`: import todo.txt, Taskwarrior, .ics, Google Tasks or org data.
+/// Without paths, imports every source found in the usual locations
+fn import(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let sources = if args.is_empty() {
+        let sources = import::scan_sources();
+        if sources.is_empty() {
+            println!("Nothing found to import (looked for todo.txt, ~/.task and {})", import::default_import_dir().display());
+        }
+        sources
+    } else {
+        args.iter().map(|path| import::detect_source(Path::new(path))).collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    if !sources.is_empty()
+        && let Some(path) = backup::auto_backup(database, &Config::load()?, "import")? {
+        println!("Backed up to {}", path.display());
+    }
+    for source in &sources {
+        let count = import::import_source(database, source)?;
+        println!("Imported {} todos from {} ({})", count, source.path.display(), source.kind.label());
+    }
+    // Checkpoint so a configured mirror picks up the new todos
+    database.checkpoint()
+}
+
+/// `tododb mirror [dir]`: write the plain-text mirror now, to `dir` or the configured one
+fn mirror(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let dir = match args {
+        [dir] => Path::new(dir),
+        [] => database
+            .mirror_dir()
+            .ok_or_else(|| anyhow::anyhow!("No mirror configured for this database; give a directory or set [mirror] dir"))?,
+        _ => return Err(anyhow::anyhow!("Usage: tododb mirror [dir]")),
+    };
+    let changed = mirror::write_mirror(database, dir)?;
+    println!("Mirrored {} todos to {} ({} files changed)", database.get_all_todos()?.len(), dir.display(), changed);
+    Ok(())
+}
+
+const SYNC_USAGE: &str = "Usage: tododb sync [url] | sync serve [address]";
+
+/// `tododb sync [url]`: one sync round with the configured (or given) server;
+/// `tododb sync serve [address]`: serve this database to other machines
+fn sync(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let token = config.sync.token.as_deref();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["serve"] => sync::serve(database, &config.sync.listen, token),
+        ["serve", address] => sync::serve(database, address, token),
+        [flag, ..] if flag.starts_with("--") => Err(anyhow::anyhow!(SYNC_USAGE)),
+        [url] => sync_once(database, url, token),
+        [] => {
+            let url = database
+                .sync_url()
+                .ok_or_else(|| anyhow::anyhow!("No sync server configured for this database; give a url or set [sync] url"))?;
+            sync_once(database, url, token)
+        }
+        _ => Err(anyhow::anyhow!(SYNC_USAGE)),
+    }
+}
+
+fn sync_once(database: &Database, url: &str, token: Option<&str>) -> anyhow::Result<()> {
+    let report = sync::sync(database, url, token)?;
+    println!("Synced with {}: {} changes received, {} sent", url, report.received, report.sent);
+    database.checkpoint()
+}
+
+const MERGE_USAGE: &str = "Usage: tododb merge <other.db> [--yes]";
+
+/// `tododb merge <other.db> [--yes]`: three-way merge of a copy edited elsewhere (say a
+/// Syncthing conflict file) into this database. Conflicts are asked about one by one, the
+/// newer side being the default; `--yes` takes the newer side without asking.
+fn merge_command(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let mut positional = Vec::new();
+    let mut ask = true;
+    for arg in args {
+        match arg.as_str() {
+            "--yes" => ask = false,
+            flag if flag.starts_with("--") => return Err(anyhow::anyhow!(MERGE_USAGE)),
+            path => positional.push(path),
+        }
+    }
+    let [path] = positional.as_slice() else {
+        return Err(anyhow::anyhow!(MERGE_USAGE));
+    };
+    if !Path::new(path).exists() {
+        return Err(anyhow::anyhow!("{} does not exist", path));
+    }
+    let same_file = database
+        .path()
+        .and_then(|source| Some(std::fs::canonicalize(source).ok()? == std::fs::canonicalize(path).ok()?));
+    if same_file == Some(true) {
+        return Err(anyhow::anyhow!("{} is the open database", path));
+    }
+
+    let other = Database::new(path)?;
+    let mut plan = merge::plan(database, &other)?;
+    drop(other);
+    let taken = plan.changes.len();
+    let conflicts = plan.conflicts.len();
+    let titles = std::mem::take(&mut plan.titles);
+    let mut failed = None;
+    let changes = plan.resolve(|conflict| {
+        if !ask || failed.is_some() {
+            return conflict.there_is_newer();
+        }
+        review_conflict(conflict, &titles).unwrap_or_else(|e| {
+            failed = Some(e);
+            conflict.there_is_newer()
+        })
+    });
+    if let Some(e) = failed {
+        return Err(e);
+    }
+    database.apply_merge(&changes)?;
+    println!("Merged {}: {} changes taken, {} conflicts", path, taken, conflicts);
+    database.checkpoint()
+}
+
+/// Show one conflict and ask which side to keep. Enter (or the end of input) takes the
+/// newer side.
+fn review_conflict(conflict: &Conflict, titles: &HashMap<String, String>) -> anyhow::Result<bool> {
+    let at = |at: chrono::DateTime<Utc>| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
+    let newer = |there: bool| if conflict.there_is_newer() == there { ", newer" } else { "" };
+    let (what, here, there) = match &conflict.kind {
+        ConflictKind::Field { field, here, there } => {
+            let value = |value: &Option<String>| match value {
+                Some(uid) if *field == "parent" => titles.get(uid).cloned().unwrap_or_else(|| uid.clone()),
+                Some(value) => history::format_value(field, value).lines().next().unwrap_or_default().to_string(),
+                None if *field == "parent" => "the top level".to_string(),
+                None => "none".to_string(),
+            };
+            (merge::field_label(field).to_string(), value(here), value(there))
+        }
+        ConflictKind::DeletedHere(_) => ("Deleted".to_string(), "deleted".to_string(), "edited".to_string()),
+        ConflictKind::DeletedThere => ("Deleted".to_string(), "edited".to_string(), "deleted".to_string()),
+    };
+    let mut out = io::stdout().lock();
+    writeln!(out, "\"{}\": {}", conflict.title, what)?;
+    writeln!(out, "  here:  {}  ({}{})", here, at(conflict.here_at), newer(false))?;
+    writeln!(out, "  there: {}  ({}{})", there, at(conflict.there_at), newer(true))?;
+    write!(out, "Keep [h]ere or take [t]here? [{}] ", if conflict.there_is_newer() { "t" } else { "h" })?;
+    out.flush()?;
+    drop(out);
+    loop {
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        match answer.trim().to_lowercase().as_str() {
+            "" => return Ok(conflict.there_is_newer()),
+            "h" | "here" => return Ok(false),
+            "t" | "there" => return Ok(true),
+            _ => print!("Please answer h or t: "),
+        }
+        io::stdout().flush()?;
+    }
+}
+
+/// `tododb caldav [url]`: push changed todos to the configured (or given) CalDAV task collection
+fn caldav(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let url = match args {
+        [url] if !url.starts_with("--") => url.as_str(),
+        [] => database
+            .caldav_url()
+            .ok_or_else(|| anyhow::anyhow!("No CalDAV collection configured for this database; give a url or set [caldav] url"))?,
+        _ => return Err(anyhow::anyhow!("Usage: tododb caldav [collection-url]")),
+    };
+    let credentials = config.caldav.username.as_deref().map(|username| (username, config.caldav.password.as_deref().unwrap_or_default()));
+    let report = caldav::push(database, url, credentials)?;
+    println!("Pushed to {}: {} todos uploaded, {} removed", url, report.uploaded, report.deleted);
+    Ok(())
+}
+
+/// `tododb serve`: answer JSON-RPC / MCP requests on stdin and stdout, for assistants and editor plugins
+fn serve(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    if !args.is_empty() {
+        return Err(anyhow::anyhow!("Usage: tododb [db_path] serve"));
+    }
+    let config = Config::load()?;
+    rpc::Server::new(database, config.hooks).serve_stdio()
+}
+
+/// `tododb rebuild <dir>`: restore an empty database from a plain-text mirror
+fn rebuild(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let [dir] = args else {
+        return Err(anyhow::anyhow!("Usage: tododb <new.db> rebuild <dir>"));
+    };
+    let count = mirror::rebuild(database, Path::new(dir))?;
+    println!("Rebuilt {} todos from {}", count, dir);
+    Ok(())
+}
+
+const BACKUP_USAGE: &str = "Usage: tododb backup [dir] | backup list";
+
+/// `tododb backup [dir]`: copy the database to a timestamped file in the configured (or
+/// given) backup directory, keeping the newest `[backup] keep`; `backup list` shows them
+fn backup_command(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let db_path = Path::new(database.path().ok_or_else(|| anyhow::anyhow!("An in-memory database has no file to back up"))?);
+    match args {
+        [command] if command == "list" => {
+            let mut out = io::stdout().lock();
+            for path in backup::list_backups(db_path, &config.backup_dir(db_path))? {
+                writeln!(out, "{}", path.display())?;
+            }
+            Ok(())
+        }
+        [dir] if !dir.starts_with("--") => {
+            let path = backup::backup(database, Path::new(dir), None, config.backup.keep)?;
+            println!("Backed up to {}", path.display());
+            Ok(())
+        }
+        [] => {
+            let path = backup::backup(database, &config.backup_dir(db_path), None, config.backup.keep)?;
+            println!("Backed up to {}", path.display());
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!(BACKUP_USAGE)),
+    }
+}
+
+/// `tododb restore <file>`: replace the database with a backup, backing up its current
+/// state first
+fn restore(database: &mut Database, args: &[String]) -> anyhow::Result<()> {
+    let [file] = args else {
+        return Err(anyhow::anyhow!("Usage: tododb restore <backup.db>"));
+    };
+    if !Path::new(file).is_file() {
+        return Err(anyhow::anyhow!("No backup file {}", file));
+    }
+    if let Some(path) = backup::auto_backup(database, &Config::load()?, "restore")? {
+        println!("Backed up the current database to {}", path.display());
+    }
+    database.restore_from(Path::new(file))?;
+    database.checkpoint()?;
+    println!("Restored {} todos from {}", database.get_all_todos()?.len(), file);
+    Ok(())
+}
+
+const TEMPLATE_USAGE: &str = "Usage: tododb template list | save <name> <todo_id> | use <name> [--parent <id>] | delete <name>";
+
+/// `tododb template ...`: save a subtree as a reusable template and instantiate it later
+fn template(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["list"] | [] => {
+            let mut out = io::stdout().lock();
+            for saved in database.get_templates()? {
+                let items = template::parse_items(&saved)?;
+                writeln!(out, "{}  ({} todos, saved {})", saved.name, items.count(), saved.created_at.with_timezone(&Local).format("%Y-%m-%d"))?;
+            }
+            Ok(())
+        }
+        ["save", name, id] => {
+            let count = template::save_template(database, name, resolve_todo(database, id)?)?;
+            println!("Saved template '{}' ({} todos)", name, count);
+            Ok(())
+        }
+        ["use", name, rest @ ..] => {
+            let parent_id = match rest {
+                [] => None,
+                ["--parent", id] => Some(resolve_todo(database, id)?),
+                _ => return Err(anyhow::anyhow!(TEMPLATE_USAGE)),
+            };
+            let saved = template::find_template(database, name)?;
+            let root_id = template::instantiate(database, &saved, parent_id)?;
+            println!("Created {} todos from '{}' (top todo {})", template::parse_items(&saved)?.count(), name, root_id);
+            database.checkpoint()
+        }
+        ["delete", name] => {
+            let saved = template::find_template(database, name)?;
+            database.delete_template(saved.id)?;
+            println!("Deleted template '{}'", name);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!(TEMPLATE_USAGE)),
+    }
+}
+
+const PREFIX_USAGE: &str = "Usage: tododb prefix [<todo> <PREFIX> | <todo> --clear]";
+
+/// `tododb prefix [<todo> <PREFIX>]`: list the short code prefixes, or give a todo one so
+/// its subtasks are numbered `PREFIX-1`, `PREFIX-2`, ... (`--clear` removes it)
+fn prefix(database: &Database, args: &[String]) -> anyhow::Result<()> {
+    match args {
+        [] => {
+            let mut out = io::stdout().lock();
+            for (todo_id, prefix) in database.get_code_prefixes()? {
+                let title = database.get_todo_by_id(todo_id)?.map(|todo| todo.title).unwrap_or_default();
+                writeln!(out, "{:<8} {:>5} {}", prefix, todo_id, title)?;
+            }
+            Ok(())
+        }
+        [id, flag] if flag == "--clear" => {
+            let todo_id = resolve_todo(database, id)?;
+            database.set_code_prefix(todo_id, None)?;
+            println!("Cleared the prefix of todo {}", todo_id);
+            Ok(())
+        }
+        [id, prefix] if !prefix.starts_with("--") => {
+            let todo_id = resolve_todo(database, id)?;
+            database.set_code_prefix(todo_id, Some(prefix))?;
+            database.refresh_short_codes()?;
+            println!("Todo {} now numbers its subtasks {}-1, {}-2, ...", todo_id, prefix.to_uppercase(), prefix.to_uppercase());
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!(PREFIX_USAGE)),
+    }
+}
+
+/// A numeric id, for todos in another database
+fn parse_todo_id(id: &str) -> anyhow::Result<i64> {
+    id.parse().map_err(|_| anyhow::anyhow!("Invalid todo id '{}'", id))
+}
+
+/// A todo in `database` by id or short code
+fn resolve_todo(database: &Database, reference: &str) -> anyhow::Result<i64> {
+    database.resolve_todo_reference(reference)?.ok_or_else(|| anyhow::anyhow!("No todo '{}'", reference))
+}
+
+fn format_todo_line(todo: &Todo, codes: &HashMap<i64, String>) -> String {
+    let checkbox = if todo.is_completed() { "[x]" } else { "[ ]" };
+    let mut line = match codes.get(&todo.id) {
+        Some(code) => format!("{:>5} {} [{}] {}", todo.id, checkbox, code, todo.title),
+        None => format!("{:>5} {} {}", todo.id, checkbox, todo.title),
+    };
+    if let Some(parent_id) = todo.parent_id {
+        line.push_str(&format!("  (parent {})", parent_id));
+    }
+    if let Some(due) = todo.due_by {
+        line.push_str(&format!("  (due {})", due.with_timezone(&Local).format("%Y-%m-%d %H:%M")));
+    }
+    line
+}
@@ -0,0 +1,136 @@
+use crate::database::{Database, Todo};
+use std::collections::HashMap;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Render a todo and all its descendants as a markdown task list, ready to paste
+/// into chat or a pull request:
+///
+/// ```text
+/// - [ ] Release 1.2
+///   - [x] Update changelog
+///   - [ ] Tag the release
+/// ```
+pub fn subtree_to_markdown(database: &Database, root_id: i64) -> anyhow::Result<String> {
+    let all_todos = database.get_all_todos()?;
+    let root = all_todos
+        .iter()
+        .find(|todo| todo.id == root_id)
+        .ok_or_else(|| anyhow::anyhow!("Todo {} not found", root_id))?;
+
+    let mut children: HashMap<i64, Vec<&Todo>> = HashMap::new();
+    for todo in &all_todos {
+        if let Some(parent_id) = todo.parent_id {
+            children.entry(parent_id).or_default().push(todo);
+        }
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|todo| todo.id);
+    }
+
+    let mut output = String::new();
+    push_task_line(&mut output, root, &children, 0);
+    Ok(output)
+}
+
+fn push_task_line(output: &mut String, todo: &Todo, children: &HashMap<i64, Vec<&Todo>>, depth: usize) {
+    let check = if todo.is_completed() { "x" } else { " " };
+    output.push_str(&format!("{}- [{}] {}\n", "  ".repeat(depth), check, todo.title));
+    for child in children.get(&todo.id).into_iter().flatten() {
+        push_task_line(output, child, children, depth + 1);
+    }
+}
+
+/// Put `text` on the system clipboard and return how it was copied.
+/// Over SSH the local clipboard is out of reach, so the text is sent to the
+/// terminal as an OSC 52 sequence instead; the same is used as a fallback when
+/// no clipboard tool (wl-copy, xclip, xsel, pbcopy, clip.exe) is installed.
+pub fn copy(text: &str) -> anyhow::Result<&'static str> {
+    let over_ssh = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+    if !over_ssh {
+        let tools: [(&str, &[&str]); 5] = [
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+            ("pbcopy", &[]),
+            ("clip.exe", &[]),
+        ];
+        for (tool, args) in tools {
+            if tool == "wl-copy" && env::var_os("WAYLAND_DISPLAY").is_none() {
+                continue;
+            }
+            if pipe_to(tool, args, text).is_ok() {
+                return Ok(tool);
+            }
+        }
+    }
+
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok("OSC 52")
+}
+
+/// Read text from the system clipboard with the first clipboard tool that works
+/// (wl-paste, xclip, xsel, pbpaste, PowerShell on WSL). Over SSH there is no
+/// portable way to read the local clipboard, so that is an error.
+pub fn paste() -> anyhow::Result<String> {
+    if env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+        return Err(anyhow::anyhow!("The local clipboard can't be read over SSH"));
+    }
+    let tools: [(&str, &[&str]); 5] = [
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-selection", "clipboard", "-o"]),
+        ("xsel", &["--clipboard", "--output"]),
+        ("pbpaste", &[]),
+        ("powershell.exe", &["-NoProfile", "-Command", "Get-Clipboard"]),
+    ];
+    for (tool, args) in tools {
+        if tool == "wl-paste" && env::var_os("WAYLAND_DISPLAY").is_none() {
+            continue;
+        }
+        if let Ok(output) = Command::new(tool).args(args).stdin(Stdio::null()).stderr(Stdio::null()).output()
+            && output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n"));
+        }
+    }
+    Err(anyhow::anyhow!("No clipboard tool found (wl-paste, xclip, xsel, pbpaste)"))
+}
+
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("{} has no stdin", tool))?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", tool, status));
+    }
+    Ok(())
+}
+
+/// Standard base64 with padding, as OSC 52 expects
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
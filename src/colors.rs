@@ -4,6 +4,7 @@ use ratatui::style::Color;
 /// A warm, cozy color scheme perfect for terminal applications
 pub struct CatppuccinFrappe;
 
+#[allow(dead_code)] // Full palette is kept even where not every accent is used yet
 impl CatppuccinFrappe {
     // Base colors
     pub const BASE: Color = Color::Rgb(48, 52, 70);      // #303446
@@ -13,6 +14,9 @@ impl CatppuccinFrappe {
     pub const SUBTEXT1: Color = Color::Rgb(181, 191, 226); // #b5bfe2
     pub const SUBTEXT0: Color = Color::Rgb(165, 173, 203); // #a5adcb
 
+    // Overlay colors
+    pub const OVERLAY1: Color = Color::Rgb(131, 139, 167); // #838ba7
+
     // Surface colors
     pub const SURFACE2: Color = Color::Rgb(87, 96, 134); // #575e86
     pub const SURFACE0: Color = Color::Rgb(54, 58, 79);  // #363a4f
@@ -37,5 +41,13 @@ impl CatppuccinFrappe {
     pub const INCOMPLETE: Color = Self::TEXT;
     pub const PARENT_INDICATOR: Color = Self::LAVENDER;
     pub const CREATION_TIME: Color = Self::SUBTEXT0;
+    pub const STALE: Color = Self::OVERLAY1;
     pub const ERROR: Color = Self::RED;
+
+    // Status colors (see `status::Status`)
+    pub const STATUS_OPEN: Color = Self::SUBTEXT1;
+    pub const STATUS_IN_PROGRESS: Color = Self::SAPPHIRE;
+    pub const STATUS_BLOCKED: Color = Self::PEACH;
+    pub const STATUS_DONE: Color = Self::COMPLETED;
+    pub const STATUS_CANCELLED: Color = Self::OVERLAY1;
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use crate::cli::COMMANDS;
+
+/// Options taken before (or instead of) a subcommand
+const GLOBAL_FLAGS: &[&str] = &["--demo", "--profile", "--encrypted", "--api", "--writable", "--test", "--tree-test"];
+
+/// Options of each subcommand that has some
+const COMMAND_FLAGS: &[(&str, &[&str])] = &[
+    ("query", &["--json"]),
+    ("pick", &["--list"]),
+    ("export", &["--no-descriptions", "--no-hidden", "--html", "--csv", "--org", "--columns", "--root"]),
+    ("doctor", &["--fix"]),
+    ("move", &["--parent", "--copy"]),
+    ("prefix", &["--clear"]),
+    ("report", &["--since", "--format"]),
+    ("merge", &["--yes"]),
+];
+
+/// Subcommands whose arguments are files
+const FILE_COMMANDS: &[&str] = &["export", "import", "encrypt", "mirror", "backup", "restore", "merge"];
+
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// The completion script for `shell` (`bash`, `zsh` or `fish`)
+pub fn script(shell: &str) -> anyhow::Result<String> {
+    match shell {
+        "bash" => Ok(bash()),
+        "zsh" => Ok(zsh()),
+        "fish" => Ok(fish()),
+        other => Err(anyhow::anyhow!("Unknown shell '{}' (expected {})", other, SHELLS.join(", "))),
+    }
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for (command, flags) in COMMAND_FLAGS {
+        let files = if FILE_COMMANDS.contains(command) { " -f" } else { "" };
+        cases.push_str(&format!(
+            "        {}) COMPREPLY=($(compgen{} -W \"{}\" -- \"$cur\")) ;;\n",
+            command,
+            files,
+            flags.join(" ")
+        ));
+    }
+    for command in FILE_COMMANDS.iter().filter(|command| !COMMAND_FLAGS.iter().any(|(c, _)| c == *command)) {
+        cases.push_str(&format!("        {}) COMPREPLY=($(compgen -f -- \"$cur\")) ;;\n", command));
+    }
+    format!(
+        r#"# tododb completion for bash: eval "$(tododb completions bash)"
+_tododb() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" command="" word
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        case " {commands} " in *" $word "*) command="$word"; break ;; esac
+    done
+    case "$command" in
+        "") COMPREPLY=($(compgen -W "{commands} {flags}" -- "$cur")) ;;
+{cases}        *) COMPREPLY=() ;;
+    esac
+}}
+complete -o default -F _tododb tododb
+"#,
+        commands = COMMANDS.join(" "),
+        flags = GLOBAL_FLAGS.join(" "),
+        cases = cases,
+    )
+}
+
+fn zsh() -> String {
+    let mut cases = String::new();
+    for (command, flags) in COMMAND_FLAGS {
+        let files = if FILE_COMMANDS.contains(command) { "; _files" } else { "" };
+        cases.push_str(&format!("        {}) compadd -- {}{} ;;\n", command, flags.join(" "), files));
+    }
+    for command in FILE_COMMANDS.iter().filter(|command| !COMMAND_FLAGS.iter().any(|(c, _)| c == *command)) {
+        cases.push_str(&format!("        {}) _files ;;\n", command));
+    }
+    format!(
+        r#"#compdef tododb
+# tododb completion for zsh: eval "$(tododb completions zsh)"
+_tododb() {{
+    local command word
+    for word in ${{words[2,CURRENT-1]}}; do
+        if [[ " {commands} " == *" $word "* ]]; then
+            command=$word
+            break
+        fi
+    done
+    case $command in
+        "") compadd -- {commands} {flags}; _files ;;
+{cases}    esac
+}}
+compdef _tododb tododb
+"#,
+        commands = COMMANDS.join(" "),
+        flags = GLOBAL_FLAGS.join(" "),
+        cases = cases,
+    )
+}
+
+fn fish() -> String {
+    let mut script = String::from("# tododb completion for fish: tododb completions fish | source\ncomplete -c tododb -f\n");
+    script.push_str(&format!(
+        "complete -c tododb -n 'not __fish_seen_subcommand_from {}' -a '{}'\n",
+        COMMANDS.join(" "),
+        COMMANDS.join(" ")
+    ));
+    for flag in GLOBAL_FLAGS {
+        script.push_str(&format!("complete -c tododb -n 'not __fish_seen_subcommand_from {}' -l {}\n", COMMANDS.join(" "), &flag[2..]));
+    }
+    for (command, flags) in COMMAND_FLAGS {
+        for flag in *flags {
+            script.push_str(&format!("complete -c tododb -n '__fish_seen_subcommand_from {}' -l {}\n", command, &flag[2..]));
+        }
+    }
+    for command in FILE_COMMANDS {
+        script.push_str(&format!("complete -c tododb -n '__fish_seen_subcommand_from {}' -F\n", command));
+    }
+    script
+}
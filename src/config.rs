@@ -0,0 +1,651 @@
+use crate::dates;
+use crate::status::Status;
+use chrono::{NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{env, fs, path::PathBuf};
+
+/// User configuration loaded from `~/.config/tododb/config.toml`
+/// Every section is optional; missing keys fall back to their defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub lock: LockConfig,
+    pub audit: AuditConfig,
+    pub layout: LayoutConfig,
+    pub display: DisplayConfig,
+    pub columns: ColumnsConfig,
+    pub views: ViewsConfig,
+    pub parents: ParentsConfig,
+    pub mirror: MirrorConfig,
+    pub sync: SyncConfig,
+    pub caldav: CalDavConfig,
+    pub api: ApiConfig,
+    pub checkpoint: CheckpointConfig,
+    pub backup: BackupConfig,
+    pub editor: EditorConfig,
+    pub metrics: MetricsConfig,
+    pub review: ReviewConfig,
+    pub pomodoro: PomodoroConfig,
+    pub agenda: AgendaConfig,
+    pub dates: DatesConfig,
+    pub contexts: ContextsConfig,
+    pub waiting: WaitingConfig,
+    pub statuses: StatusesConfig,
+    pub keys: KeysConfig,
+    pub profiles: Vec<ProfileConfig>,
+    pub hooks: Vec<HookConfig>,
+}
+
+/// A named database, opened with `--profile <name>` or switched to in the app with Ctrl+P
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileConfig {
+    pub name: String,
+    /// Database file; a leading `~/` is the home directory
+    pub path: String,
+    /// Plain-text mirror directory for this profile's database (see `[mirror]`)
+    #[serde(default)]
+    pub mirror: Option<String>,
+    /// Sync server for this profile's database (see `[sync]`)
+    #[serde(default)]
+    pub sync_url: Option<String>,
+    /// CalDAV task collection for this profile's database (see `[caldav]`)
+    #[serde(default)]
+    pub caldav_url: Option<String>,
+}
+
+impl ProfileConfig {
+    pub fn db_path(&self) -> PathBuf {
+        expand_home(&self.path)
+    }
+
+    /// The database path to open, creating its directory if needed
+    pub fn open_path(&self) -> anyhow::Result<String> {
+        let path = self.db_path();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(path.to_string_lossy().to_string())
+    }
+}
+
+/// Tab bar and view naming; views are referred to as "tree", "list", "completed" and "scheduled"
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ViewsConfig {
+    /// Views shown in the tab bar, in order; the first is the one shown at startup.
+    /// Empty means every view in the default order.
+    pub tabs: Vec<String>,
+    /// Custom titles by view name, used for the tab and the view's border title
+    pub titles: HashMap<String, String>,
+    /// Custom tab icons by view name
+    pub icons: HashMap<String, String>,
+}
+
+/// A path from the config file, with a leading `~/` expanded to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// Directory the default database is mirrored to as one markdown file per todo
+    /// on every checkpoint, for syncing and reviewing changes with git.
+    /// Profiles set their own `mirror` instead.
+    pub dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Server the default database syncs with, e.g. "https://todos.example.com"
+    /// (https goes through curl). Profiles set their own `sync_url` instead.
+    pub url: Option<String>,
+    /// Shared secret sent by clients and required by `tododb sync serve`
+    pub token: Option<String>,
+    /// Address `tododb sync serve` listens on
+    pub listen: String,
+    /// Minutes between background syncs while the app runs; 0 turns them off
+    pub interval_minutes: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            listen: "127.0.0.1:7373".to_string(),
+            interval_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CalDavConfig {
+    /// Task collection the default database is pushed to by `tododb caldav`, e.g.
+    /// "https://cloud.example.com/remote.php/dav/calendars/me/tasks/".
+    /// Profiles set their own `caldav_url` instead.
+    pub url: Option<String>,
+    pub username: Option<String>,
+    /// Password for basic auth; prefer an app password over the account password
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Secret `tododb --api` requires, as a bearer token or a `?token=` query parameter
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    /// Pages the write-ahead log grows by before SQLite copies it into the database
+    /// file on its own; smaller is more durable, larger is faster. SQLite's default is 1000.
+    pub wal_pages: u32,
+    /// Checkpoint before handing the terminal to $EDITOR
+    pub on_editor: bool,
+    /// Checkpoint before the app is suspended (Ctrl+Z or SIGTSTP)
+    pub on_suspend: bool,
+    /// While the app is open, checkpoint changes this often; 0 turns it off
+    pub interval_minutes: u64,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            wal_pages: 5000,
+            on_editor: true,
+            on_suspend: true,
+            interval_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Directory backups are written to; by default `backups/` next to the database
+    pub dir: Option<String>,
+    /// Backups kept per database, newest first; older ones are deleted after each
+    /// backup. 0 keeps them all.
+    pub keep: usize,
+    /// Back up before a schema upgrade, an import or a restore
+    pub auto: bool,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self { dir: None, keep: 10, auto: true }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EditorConfig {
+    /// Directory for the markdown files opened in $EDITOR; by default each session
+    /// gets a private directory under $XDG_RUNTIME_DIR (or the system temp directory)
+    pub dir: Option<String>,
+    /// Keep each file after its changes are saved, as a markdown export of the
+    /// todos edited, instead of deleting it
+    pub keep_files: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Record each session's length and the todos created and completed in it, in
+    /// the database itself, for the trends in the stats view. Nothing leaves the machine.
+    pub enabled: bool,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    /// Open todos untouched for this many days come up as stale in the review (R)
+    pub stale_days: i64,
+    /// How far ahead snoozing in the review defers a todo: `1w`, `3d`, `YYYY-MM-DD`
+    pub snooze: String,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self { stale_days: 30, snooze: "1w".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: i64,
+    pub break_minutes: i64,
+    /// Ring the terminal bell when work or a break ends
+    pub bell: bool,
+    /// Shell command run with the message as its last argument when work or a break
+    /// ends, e.g. `notify-send TodoDB`
+    pub notify: Option<String>,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self { work_minutes: 25, break_minutes: 5, bell: true, notify: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AgendaConfig {
+    /// Minutes of estimated work a day can take before the agenda (W) warns it is overbooked
+    pub capacity_minutes: i64,
+    /// How many days, from today, the agenda shows
+    pub days: i64,
+}
+
+impl Default for AgendaConfig {
+    fn default() -> Self {
+        Self { capacity_minutes: 360, days: 14 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatesConfig {
+    /// Local time of day a due date typed as a day (`2025-10-20`) is due, e.g. "17:00";
+    /// the end of the day when unset
+    pub due_time: Option<NaiveTime>,
+    /// How rows show the day of a date, as a chrono format: "%m/%d", "%d/%m", "%d %b"
+    #[serde(deserialize_with = "date_format")]
+    pub date_format: String,
+    /// "24h" (13:05) or "12h" (1:05pm)
+    pub clock: Clock,
+    /// The day weeks start on, for "this week" in the completed view, the agenda and stats
+    pub week_start: Weekday,
+}
+
+impl Default for DatesConfig {
+    fn default() -> Self {
+        Self { due_time: None, date_format: "%m/%d".to_string(), clock: Clock::default(), week_start: Weekday::Mon }
+    }
+}
+
+impl DatesConfig {
+    pub fn due_time(&self) -> NaiveTime {
+        self.due_time.unwrap_or(dates::END_OF_DAY)
+    }
+
+    /// The chrono format of a date in rows, with the time of day unless `compact`
+    pub fn row_format(&self, compact: bool) -> String {
+        if compact {
+            self.date_format.clone()
+        } else {
+            format!("{} {}", self.date_format, self.clock.format())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum Clock {
+    #[default]
+    #[serde(rename = "24h")]
+    TwentyFour,
+    #[serde(rename = "12h")]
+    Twelve,
+}
+
+impl Clock {
+    pub fn format(self) -> &'static str {
+        match self {
+            Self::TwentyFour => "%H:%M",
+            Self::Twelve => "%-I:%M%P",
+        }
+    }
+}
+
+/// A chrono format, refused when chrono can't print it for a date without a time zone
+/// (an unknown specifier, or `%z`/`%Z`, which `dates::format_width` would panic on)
+fn date_format<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    use std::fmt::Write;
+    let format = String::deserialize(deserializer)?;
+    if write!(String::new(), "{}", NaiveDateTime::default().format(&format)).is_err() {
+        return Err(serde::de::Error::custom(format!("invalid date format '{}'", format)));
+    }
+    Ok(format)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ContextsConfig {
+    /// The contexts @ cycles a todo through and C filters by, in order
+    pub names: Vec<String>,
+}
+
+impl Default for ContextsConfig {
+    fn default() -> Self {
+        Self { names: ["home", "computer", "errands", "calls"].map(String::from).to_vec() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WaitingConfig {
+    /// When to follow up on a delegated todo if no date is given: `3d`, `1w`, empty for never
+    pub follow_up: String,
+}
+
+impl Default for WaitingConfig {
+    fn default() -> Self {
+        Self { follow_up: "1w".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatusesConfig {
+    /// The statuses Space steps through (Shift+Space goes back), e.g. `["open", "in_progress", "done"]`
+    pub cycle: Vec<Status>,
+}
+
+impl Default for StatusesConfig {
+    fn default() -> Self {
+        Self { cycle: vec![Status::Open, Status::Done] }
+    }
+}
+
+/// Extra keys for the one-key commands, as `[keys]` entries like `x = "d"`: x does what
+/// d does, and d keeps working. Keys typed into prompts and search fields are left alone.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct KeysConfig {
+    pub aliases: HashMap<char, char>,
+}
+
+/// A shell command or URL told about changes made in the app, configured as
+/// `[[hooks]]`; the event goes to the command on stdin or to the URL as a POST body
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    /// Events that trigger the hook; empty means all of them
+    #[serde(default)]
+    pub events: Vec<HookEvent>,
+    /// Run with `sh -c`
+    #[serde(default)]
+    pub command: Option<String>,
+    /// POSTed to, e.g. a Slack incoming webhook (the payload carries a `text` field)
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    Create,
+    Complete,
+    Uncomplete,
+    Delete,
+    Move,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ParentsConfig {
+    /// What happens when a completed or hidden todo is chosen as a parent in the
+    /// Create form, parent search or move mode: its new children would drop out
+    /// of the default tree along with it
+    pub inactive: ParentRule,
+    /// Whether completing a todo also completes its open subtasks
+    pub complete_children: CascadeRule,
+    /// What happens when a todo with open subtasks is completed on its own (when
+    /// `complete_children` doesn't take them along): "prevent" refuses and offers
+    /// to complete them all instead
+    pub open_children: ParentRule,
+    /// Whether reopening a todo also reopens its completed parents, which would
+    /// otherwise keep it out of the default tree
+    pub reopen_parents: CascadeRule,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParentRule {
+    #[default]
+    Allow,
+    /// Go ahead, but say where the children went
+    Warn,
+    /// Refuse with an error
+    Prevent,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CascadeRule {
+    #[default]
+    Never,
+    /// Ask each time there is something to cascade to
+    Ask,
+    Always,
+}
+
+/// Which metadata columns follow each todo's title, and how wide the text ones are
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ColumnsConfig {
+    /// Extra columns: "priority" (the p0-p9 title prefix) and "tags"
+    pub show: Vec<Column>,
+    /// Columns left out of every view
+    pub hide: Vec<Column>,
+    pub parent_width: usize,
+    pub tags_width: usize,
+}
+
+impl Default for ColumnsConfig {
+    fn default() -> Self {
+        Self {
+            show: Vec::new(),
+            hide: Vec::new(),
+            parent_width: 24,
+            tags_width: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Created,
+    Due,
+    Completed,
+    Parent,
+    Start,
+    Priority,
+    Tags,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// What identifies each todo in the views: "mod" (id % 100, what `g` jumps to),
+    /// "full" (the whole id) or "code" (short codes like WEB-12 where a project has
+    /// a prefix, id % 100 elsewhere)
+    pub ids: IdDisplay,
+    /// How often the screen redraws without a keypress, so due-date colors, started
+    /// todos and background results show up on their own
+    pub tick_ms: u64,
+    /// Seconds a status message stays in the status bar; 0 keeps it until the next key
+    pub message_seconds: u64,
+    /// Continue titles too long for their row on a second line instead of cutting
+    /// them short with an ellipsis
+    pub wrap_titles: bool,
+    /// Show due dates in rows as how far off they are ("in 3d", "2d overdue"), with the
+    /// exact date in the bottom bar for the selected todo; false shows the date itself
+    pub relative_due: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            ids: IdDisplay::default(),
+            tick_ms: 1000,
+            message_seconds: 5,
+            wrap_titles: false,
+            relative_due: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdDisplay {
+    #[default]
+    Mod,
+    Full,
+    Code,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// What very wide terminals show: "single" (one tree column), "columns"
+    /// (the tree continues in a second column) or "stats" (tree + stats panel)
+    pub wide: WideLayout,
+    /// Terminal width, in columns, from which the wide layout applies
+    pub wide_min_width: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            wide: WideLayout::Single,
+            wide_min_width: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WideLayout {
+    Single,
+    Columns,
+    Stats,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Record completions in a hash chain that `tododb verify` can check.
+    /// A database that already has a log keeps extending it regardless.
+    pub completion_log: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LockConfig {
+    /// The unlock passphrase as `tododb lock-hash` prints it (salted PBKDF2). Locking is
+    /// disabled unless this or `passphrase_sha256` is set.
+    pub passphrase_hash: Option<String>,
+    /// Older form: a bare SHA-256 hex digest of the passphrase, quick to brute-force
+    pub passphrase_sha256: Option<String>,
+    /// Minutes without a keypress before the lock screen engages
+    pub idle_minutes: u64,
+    /// Start the app on the lock screen
+    pub lock_on_start: bool,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            passphrase_hash: None,
+            passphrase_sha256: None,
+            idle_minutes: 10,
+            lock_on_start: false,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, returning defaults if it doesn't exist
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    pub fn profile(&self, name: &str) -> anyhow::Result<&ProfileConfig> {
+        self.profiles.iter().find(|profile| profile.name == name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.iter().map(|profile| profile.name.as_str()).collect();
+            anyhow::anyhow!("Unknown profile '{}' (configured: {})", name, if names.is_empty() { "none".to_string() } else { names.join(", ") })
+        })
+    }
+
+    /// Mirror directory for a profile's database, or for the default database with `None`
+    pub fn mirror_dir(&self, profile: Option<&str>) -> Option<PathBuf> {
+        let dir = match profile {
+            Some(name) => self.profiles.iter().find(|p| p.name == name)?.mirror.as_deref(),
+            None => self.mirror.dir.as_deref(),
+        };
+        dir.map(expand_home)
+    }
+
+    /// Where backups of the database at `db_path` go
+    pub fn backup_dir(&self, db_path: &std::path::Path) -> PathBuf {
+        match &self.backup.dir {
+            Some(dir) => expand_home(dir),
+            None => db_path.parent().unwrap_or_else(|| std::path::Path::new("")).join("backups"),
+        }
+    }
+
+    /// Configured directory for editor files, if any
+    pub fn editor_dir(&self) -> Option<PathBuf> {
+        self.editor.dir.as_deref().map(expand_home)
+    }
+
+    /// Sync server for a profile's database, or for the default database with `None`
+    pub fn sync_url(&self, profile: Option<&str>) -> Option<String> {
+        match profile {
+            Some(name) => self.profiles.iter().find(|p| p.name == name)?.sync_url.clone(),
+            None => self.sync.url.clone(),
+        }
+    }
+
+    /// CalDAV task collection for a profile's database, or for the default database with `None`
+    pub fn caldav_url(&self, profile: Option<&str>) -> Option<String> {
+        match profile {
+            Some(name) => self.profiles.iter().find(|p| p.name == name)?.caldav_url.clone(),
+            None => self.caldav.url.clone(),
+        }
+    }
+
+    /// Config file location: $TODODB_CONFIG, else $XDG_CONFIG_HOME/tododb/config.toml,
+    /// else ~/.config/tododb/config.toml
+    pub fn path() -> PathBuf {
+        if let Ok(path) = env::var("TODODB_CONFIG") {
+            return PathBuf::from(path);
+        }
+
+        let mut path = match env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let mut home = PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+                home.push(".config");
+                home
+            }
+        };
+        path.push("tododb");
+        path.push("config.toml");
+        path
+    }
+}
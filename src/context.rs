@@ -0,0 +1,57 @@
+/// How much energy a todo takes, so low-energy moments still have something to pick from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Energy {
+    Low,
+    Medium,
+    High,
+}
+
+impl Energy {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "low" | "l" => Some(Self::Low),
+            "medium" | "med" | "m" => Some(Self::Medium),
+            "high" | "h" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// The next value when cycling with a key: none, low, medium, high, none again
+    pub fn cycle(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(Self::Low),
+            Some(Self::Low) => Some(Self::Medium),
+            Some(Self::Medium) => Some(Self::High),
+            Some(Self::High) => None,
+        }
+    }
+}
+
+/// A todo's context (where or with what it can be done) and the energy it takes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Labels {
+    pub context: Option<String>,
+    pub energy: Option<Energy>,
+}
+
+/// The next context when cycling with a key: through `contexts` in order, then none. A
+/// context no longer in the list starts over at the first one.
+pub fn cycle_context(current: Option<&str>, contexts: &[String]) -> Option<String> {
+    match current.and_then(|current| contexts.iter().position(|context| context == current)) {
+        Some(i) => contexts.get(i + 1).cloned(),
+        None => contexts.first().cloned(),
+    }
+}
+
+/// A context name as written after `@`: lowercase letters, digits, `_` and `-`
+pub fn is_valid_context(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
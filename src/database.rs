@@ -1,9 +1,26 @@
-use chrono::{DateTime, Utc};
+use crate::audit::{self, CompletionEvent};
+use crate::context::{Energy, Labels};
+use crate::flag::Flag;
+use crate::dates;
+use crate::history::{self, HistoryEntry};
+use crate::merge::{self, MergeChange, MergeRow};
+use crate::status::Status;
+use crate::waiting::Waiting;
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
 use regex::RegexBuilder;
-use rusqlite::{params, Connection, Result, Row};
+use crate::filter::{Filter, FilterTerm};
+use crate::metrics::Session;
+use rusqlite::{functions::FunctionFlags, params, params_from_iter, types::{FromSql, Value, ValueRef}, Connection, DatabaseName, OpenFlags, OptionalExtension, Result, Row};
+use crate::mirror;
+use crate::sync::{SyncRecord, SyncTodo};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Todo {
     pub id: i64,
     pub title: String,
@@ -13,6 +30,10 @@ pub struct Todo {
     pub due_by: Option<DateTime<Utc>>,
     pub parent_id: Option<i64>,
     pub hidden: bool,
+    /// Not before: until then an open todo is left out of the tree and list and only
+    /// shows in the Scheduled view
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
 }
 
 impl Todo {
@@ -26,6 +47,7 @@ impl Todo {
             due_by: row.get(5).ok(),
             parent_id: row.get(6)?,
             hidden: row.get(7).unwrap_or(false),
+            start_at: row.get(8).unwrap_or(None),
         })
     }
 
@@ -33,11 +55,87 @@ impl Todo {
         self.completed_at.is_some()
     }
 
+    /// Open and not to be started before some time after `now`
+    pub fn is_deferred(&self, now: DateTime<Utc>) -> bool {
+        !self.is_completed() && self.start_at.is_some_and(|start_at| start_at > now)
+    }
+
     pub fn id_mod(&self) -> i64 {
         self.id % 100
     }
 }
 
+/// A named filter expression saved for reuse (see `filter::Filter`)
+#[derive(Debug, Clone)]
+pub struct SavedFilter {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+}
+
+/// A named, reusable todo structure; `items` is the JSON of its `template::TemplateItem` tree
+#[derive(Debug, Clone)]
+pub struct SavedTemplate {
+    pub id: i64,
+    pub name: String,
+    pub items: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A file path or URL linked to a todo
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub todo_id: i64,
+    pub target: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    pub fn is_url(&self) -> bool {
+        self.target.contains("://") || self.target.starts_with("mailto:")
+    }
+}
+
+/// A timestamped journal entry on a todo, for progress logs kept apart from the description
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub id: i64,
+    pub todo_id: i64,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A date column as stored: None for NULL, the raw value when it isn't a readable date
+pub type StoredDate = std::result::Result<Option<DateTime<Utc>>, String>;
+
+/// A todo's date columns read leniently, so `tododb doctor` can report rows that
+/// `Todo::from_row` would fail on
+#[derive(Debug, Clone)]
+pub struct StoredDates {
+    pub id: i64,
+    pub title: String,
+    pub created_at: StoredDate,
+    pub completed_at: StoredDate,
+    pub due_by: StoredDate,
+}
+
+fn stored_date(value: Value) -> StoredDate {
+    match value {
+        Value::Null => Ok(None),
+        Value::Text(text) => DateTime::<Utc>::column_result(ValueRef::Text(text.as_bytes())).map(Some).map_err(|_| text),
+        other => Err(format!("{:?}", other)),
+    }
+}
+
+/// What the completed view shows: todos completed at or after `since`, within the
+/// subtree of `root` (itself included)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompletedFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub root: Option<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NewTodo {
     pub title: String,
@@ -46,19 +144,332 @@ pub struct NewTodo {
     pub due_by: Option<DateTime<Utc>>,
 }
 
+/// A change to the todos made through a `Database`, sent to everything that called
+/// `subscribe`. Only changes made through this connection are seen, not other processes'.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TodoEvent {
+    Created(i64),
+    /// Title, description, dates or hidden flag changed, or a note was added
+    Updated(i64),
+    Completed(i64),
+    Uncompleted(i64),
+    Moved(i64),
+    /// The todo as it was just before it was deleted
+    Deleted(Todo),
+    /// Many todos changed at once (a sync); anything derived from them should be reloaded
+    Reloaded,
+}
+
+/// Recorded in `PRAGMA user_version`; bump it whenever `create_tables` changes the
+/// schema of an existing database, so `needs_upgrade` knows to back it up first
+const SCHEMA_VERSION: i64 = 13;
+
 pub struct Database {
     conn: Connection,
+    /// Append completion events to the hash-chained `completion_log` (see `audit`)
+    completion_log: bool,
+    /// Rewrite this plain-text mirror on every checkpoint (see `mirror`)
+    mirror_dir: Option<PathBuf>,
+    /// Server this database syncs with (see `sync`)
+    sync_url: Option<String>,
+    /// CalDAV task collection this database is pushed to (see `caldav`)
+    caldav_url: Option<String>,
+    /// Channels handed out by `subscribe`; dropped receivers are pruned on the next send
+    subscribers: RefCell<Vec<mpsc::Sender<TodoEvent>>>,
+    /// Transactions open on the connection (see `begin`)
+    transaction_depth: Cell<usize>,
+    /// Events held back until the outermost transaction commits
+    pending_events: RefCell<Vec<TodoEvent>>,
+    /// `PRAGMA data_version` when last checked by `changed_elsewhere`
+    data_version: Cell<i64>,
+    /// Days without a change after which an open todo is stale (the `stale` filter)
+    stale_days: i64,
+}
+
+/// A filter duration that reaches past the dates chrono can represent, from `query_todos`
+#[derive(Debug)]
+pub struct DurationOutOfRange;
+
+impl std::fmt::Display for DurationOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid duration in filter: too far from now")
+    }
+}
+
+impl std::error::Error for DurationOutOfRange {}
+
+/// A transaction, or a savepoint within the one already open, so operations that commit
+/// their own changes can also be part of `Database::with_transaction`. Rolled back when
+/// dropped without `commit`.
+struct Transaction<'a> {
+    db: &'a Database,
+    /// The savepoint, or None for the outermost transaction
+    savepoint: Option<String>,
+    /// Events emitted before it began, kept if it rolls back
+    events_before: usize,
+    done: bool,
+}
+
+impl Transaction<'_> {
+    fn commit(mut self) -> anyhow::Result<()> {
+        match &self.savepoint {
+            Some(name) => self.db.conn.execute_batch(&format!("RELEASE {}", name))?,
+            None => self.db.conn.execute_batch("COMMIT")?,
+        }
+        self.done = true;
+        self.db.end_transaction(None);
+        Ok(())
+    }
+}
+
+impl Deref for Transaction<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.db.conn
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = match &self.savepoint {
+                Some(name) => self.db.conn.execute_batch(&format!("ROLLBACK TO {0}; RELEASE {0}", name)),
+                None => self.db.conn.execute_batch("ROLLBACK"),
+            };
+            self.db.end_transaction(Some(self.events_before));
+        }
+    }
+}
+
+/// Busy-handler calls before a write gives up on the lock another connection holds
+/// (the background sync, or another tododb on the same file): about 10 seconds
+const BUSY_RETRIES: i32 = 20;
+
+/// Wait a little longer each time SQLite finds the database locked, then retry
+fn wait_while_busy(attempts: i32) -> bool {
+    std::thread::sleep(std::time::Duration::from_millis(10 << attempts.clamp(0, 6)));
+    attempts < BUSY_RETRIES
+}
+
+/// Whether `error` is SQLite still finding the database locked after every retry
+pub fn is_busy(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<rusqlite::Error>().and_then(rusqlite::Error::sqlite_error_code),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
 }
 
 impl Database {
     pub fn new(db_path: &str) -> anyhow::Result<Self> {
+        Self::init(Connection::open(db_path)?)
+    }
+
+    /// Open (or create) a SQLCipher-encrypted database
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted(db_path: &str, passphrase: &str) -> anyhow::Result<Self> {
         let conn = Connection::open(db_path)?;
-        let db = Database { conn };
+        // The key has to be set before anything reads the file
+        conn.pragma_update(None, "key", passphrase)?;
+        if conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)).is_err() {
+            return Err(anyhow::anyhow!("Cannot open {}: wrong passphrase, or the database is not encrypted", db_path));
+        }
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        let mut db = Database { conn, completion_log: false, mirror_dir: None, sync_url: None, caldav_url: None, subscribers: RefCell::default(), transaction_depth: Cell::new(0), pending_events: RefCell::default(), data_version: Cell::new(0), stale_days: 30 };
         db.configure_wal_mode()?;
+        db.register_functions()?;
         db.create_tables()?;
+        // Keep every parent_id pointing at a todo (and clean up attachments and notes)
+        db.conn.pragma_update(None, "foreign_keys", true)?;
+        db.changed_elsewhere()?;
+        // Once a database has a completion log, keep extending it so the chain has no gaps
+        let logged: i64 = db.conn.query_row("SELECT COUNT(*) FROM completion_log", [], |row| row.get(0))?;
+        db.completion_log = logged > 0;
         Ok(db)
     }
 
+    /// Write an encrypted copy of the whole database to `path`, which must not exist yet
+    #[cfg(feature = "encryption")]
+    pub fn export_encrypted(&self, path: &str, passphrase: &str) -> anyhow::Result<()> {
+        if std::path::Path::new(path).exists() {
+            return Err(anyhow::anyhow!("{} already exists", path));
+        }
+        self.conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", params![path, passphrase])?;
+        let exported = self.conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()));
+        self.conn.execute("DETACH DATABASE encrypted", [])?;
+        exported?;
+        Ok(())
+    }
+
+    /// The database file, or None for an in-memory database
+    pub fn path(&self) -> Option<&str> {
+        self.conn.path().filter(|path| !path.is_empty())
+    }
+
+    /// Whether opening the database file at `db_path` will upgrade its schema
+    pub fn needs_upgrade(db_path: &str) -> anyhow::Result<bool> {
+        if !Path::new(db_path).exists() {
+            return Ok(false);
+        }
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let has_todos: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'todos')", [], |row| row.get(0))?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        Ok(has_todos && version < SCHEMA_VERSION)
+    }
+
+    /// Copy the database file at `db_path` to `dest` with SQLite's online backup, without
+    /// opening it as a `Database` (which would upgrade it)
+    pub fn backup_file(db_path: &str, dest: &Path) -> anyhow::Result<()> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        conn.backup(DatabaseName::Main, dest, None)?;
+        Ok(())
+    }
+
+    /// Copy the database to `dest` with SQLite's online backup, changes still in the
+    /// write-ahead log included
+    pub fn backup_to(&self, dest: &Path) -> anyhow::Result<()> {
+        self.conn.backup(DatabaseName::Main, dest, None)?;
+        Ok(())
+    }
+
+    /// Replace the whole database with the backup at `src`, upgrading it if it's older
+    pub fn restore_from(&mut self, src: &Path) -> anyhow::Result<()> {
+        let backup = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let has_todos: bool = backup
+            .query_row("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'todos')", [], |row| row.get(0))
+            .map_err(|e| anyhow::anyhow!("{} is not a TodoDB database: {}", src.display(), e))?;
+        if !has_todos {
+            return Err(anyhow::anyhow!("{} is not a TodoDB database", src.display()));
+        }
+        drop(backup);
+        self.conn.restore(DatabaseName::Main, src, None::<fn(rusqlite::backup::Progress)>)?;
+        self.create_tables()?;
+        self.emit(TodoEvent::Reloaded);
+        Ok(())
+    }
+
+    pub fn enable_completion_log(&mut self) {
+        self.completion_log = true;
+    }
+
+    pub fn set_stale_days(&mut self, days: i64) {
+        self.stale_days = days;
+    }
+
+    pub fn set_mirror_dir(&mut self, dir: PathBuf) {
+        self.mirror_dir = Some(dir);
+    }
+
+    pub fn mirror_dir(&self) -> Option<&Path> {
+        self.mirror_dir.as_deref()
+    }
+
+    pub fn set_sync_url(&mut self, url: String) {
+        self.sync_url = Some(url);
+    }
+
+    pub fn sync_url(&self) -> Option<&str> {
+        self.sync_url.as_deref()
+    }
+
+    pub fn set_caldav_url(&mut self, url: String) {
+        self.caldav_url = Some(url);
+    }
+
+    pub fn caldav_url(&self) -> Option<&str> {
+        self.caldav_url.as_deref()
+    }
+
+    /// Receive a `TodoEvent` for every change made through this database from now on.
+    /// Events queue up until read, so drain the receiver or drop it.
+    pub fn subscribe(&self) -> mpsc::Receiver<TodoEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.borrow_mut().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: TodoEvent) {
+        if self.transaction_depth.get() > 0 {
+            self.pending_events.borrow_mut().push(event);
+            return;
+        }
+        self.subscribers.borrow_mut().retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Run `f` as a single transaction: either everything it changes is kept or, when it
+    /// fails part way, none of it. Events are sent only once it commits.
+    pub fn with_transaction<T>(&self, f: impl FnOnce(&Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let tx = self.begin()?;
+        let result = f(self)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Open a transaction, nested as a savepoint when one is already open. The outermost
+    /// one takes the write lock up front, so it waits for other writers (see
+    /// `wait_while_busy`) instead of failing when it first writes after reading.
+    fn begin(&self) -> anyhow::Result<Transaction<'_>> {
+        let depth = self.transaction_depth.get();
+        let savepoint = (depth > 0).then(|| format!("tx{}", depth));
+        match &savepoint {
+            Some(name) => self.conn.execute_batch(&format!("SAVEPOINT {}", name))?,
+            None => self.conn.execute_batch("BEGIN IMMEDIATE")?,
+        }
+        self.transaction_depth.set(depth + 1);
+        Ok(Transaction { db: self, savepoint, events_before: self.pending_events.borrow().len(), done: false })
+    }
+
+    /// Whether another connection (another tododb, `tododb` commands, a sync) has
+    /// committed changes since the last call
+    pub fn changed_elsewhere(&self) -> anyhow::Result<bool> {
+        let version: i64 = self.conn.pragma_query_value(None, "data_version", |row| row.get(0))?;
+        Ok(self.data_version.replace(version) != version)
+    }
+
+    /// Close the innermost transaction, dropping the events of a rolled back one
+    /// (`rolled_back` holds how many were pending before it began)
+    fn end_transaction(&self, rolled_back: Option<usize>) {
+        if let Some(events_before) = rolled_back {
+            self.pending_events.borrow_mut().truncate(events_before);
+        }
+        let depth = self.transaction_depth.get() - 1;
+        self.transaction_depth.set(depth);
+        if depth == 0 {
+            let events = std::mem::take(&mut *self.pending_events.borrow_mut());
+            for event in events {
+                self.emit(event);
+            }
+        }
+    }
+
+    fn write_mirror(&self) -> anyhow::Result<()> {
+        if let Some(dir) = &self.mirror_dir {
+            mirror::write_mirror(self, dir)
+                .map_err(|e| anyhow::anyhow!("Cannot update mirror {}: {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+
+    fn register_functions(&self) -> Result<()> {
+        // `text REGEXP pattern` calls regexp(pattern, text); used by filter expressions
+        self.conn.create_scalar_function(
+            "regexp",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                // Compile the pattern once per statement rather than once per row
+                let regex: Arc<regex::Regex> = ctx.get_or_create_aux(0, |pattern| -> std::result::Result<_, rusqlite::types::FromSqlError> {
+                    regex::Regex::new(pattern.as_str()?).map_err(|e| rusqlite::types::FromSqlError::Other(Box::new(e)))
+                })?;
+                let text = ctx.get::<Option<String>>(1)?;
+                Ok(text.is_some_and(|text| regex.is_match(&text)))
+            },
+        )
+    }
+
     fn configure_wal_mode(&self) -> anyhow::Result<()> {
         // Enable WAL mode for hybrid memory/disk operation
         self.conn.pragma_update(None, "journal_mode", "WAL")?;
@@ -73,6 +484,10 @@ impl Database {
         // Optimize for performance
         self.conn.pragma_update(None, "cache_size", -64000)?; // 64MB cache
         self.conn.pragma_update(None, "temp_store", "MEMORY")?; // Use memory for temp tables
+
+        // Background sync and other instances write through their own connections; wait
+        // for them instead of failing
+        self.conn.busy_handler(Some(wait_while_busy))?;
         
         Ok(())
     }
@@ -88,7 +503,112 @@ impl Database {
                 due_by TEXT,
                 parent_id INTEGER,
                 hidden INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (parent_id) REFERENCES todos (id)
+                FOREIGN KEY (parent_id) REFERENCES todos (id) ON DELETE RESTRICT
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS filters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                query TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS templates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                items TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id INTEGER NOT NULL,
+                target TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id INTEGER NOT NULL,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Finished pomodoro work intervals (see `pomodoro`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS pomodoros (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id INTEGER NOT NULL,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Small key/value store for UI state that should survive restarts
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS ui_state (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Append-only record of every change to a todo, written by the triggers in
+        // `create_todo_indexes` (see `history`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                action TEXT NOT NULL,
+                field TEXT,
+                old_value TEXT,
+                new_value TEXT,
+                at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_history_todo ON history (todo_id)", [])?;
+        for event in ["UPDATE", "DELETE"] {
+            self.conn.execute(
+                &format!(
+                    "CREATE TRIGGER IF NOT EXISTS history_append_only_{} BEFORE {event} ON history
+                     BEGIN
+                         SELECT RAISE(ABORT, 'history is append-only');
+                     END",
+                    event.to_lowercase()
+                ),
+                [],
+            )?;
+        }
+
+        // Append-only, hash-chained record of completions (see `audit::verify`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS completion_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id INTEGER NOT NULL,
+                event TEXT NOT NULL,
+                title TEXT NOT NULL,
+                at TEXT NOT NULL,
+                prev_hash TEXT NOT NULL,
+                hash TEXT NOT NULL
             )",
             [],
         )?;
@@ -105,140 +625,1343 @@ impl Database {
             [],
         );
 
+        // Add start_at column to existing tables (migration)
+        let _ = self.conn.execute(
+            "ALTER TABLE todos ADD COLUMN start_at TEXT",
+            [],
+        );
+
+        // Sync identity (see `sync`): a uid that is the same on every machine and the
+        // time of the last change. Triggers keep both current so no write path has to.
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN uid TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN updated_at TEXT", []);
+        let _ = self.conn.execute(
+            "UPDATE todos SET uid = lower(hex(randomblob(16))), updated_at = COALESCE(updated_at, completed_at, created_at)
+             WHERE uid IS NULL",
+            [],
+        );
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS sync_tombstones (
+                uid TEXT PRIMARY KEY,
+                deleted_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        // Parents can't be deleted from under their children; older databases get the
+        // table rebuilt with that rule (see `enforce_parent_rule`)
+        if !self.conn.query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'todos'", [], |row| row.get::<_, String>(0))?.contains("ON DELETE") {
+            self.enforce_parent_rule()?;
+        }
+        // Estimated minutes of work (see `agenda`); added after the rebuild, which doesn't copy it
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN estimate_minutes INTEGER", []);
+        // Context and energy labels (see `context`), likewise
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN context TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN energy TEXT", []);
+        // Set aside as someday/maybe: not committed to yet, unlike hidden (not shown)
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN someday INTEGER NOT NULL DEFAULT 0", []);
+        // Delegated: who it's waiting on and when to follow up (see `waiting`)
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN waiting_on TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN follow_up_at TEXT", []);
+        // In progress, blocked or cancelled (see `status`); open and done follow completed_at
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN status TEXT", []);
+        // A color or emoji marking a todo not to lose track of (see `flag`)
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN flag TEXT", []);
+        // Pinned to the section above the tree and list while open
+        let _ = self.conn.execute("ALTER TABLE todos ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0", []);
+        self.create_todo_indexes()?;
+        // Version of each todo last uploaded to a CalDAV collection (see `caldav`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS caldav_pushed (
+                collection TEXT NOT NULL,
+                uid TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (collection, uid)
+            )",
+            [],
+        )?;
+        // Short codes (see `refresh_short_codes`): a prefix set on a project, and the
+        // number each todo under it was given, never reused
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS code_prefixes (
+                todo_id INTEGER PRIMARY KEY,
+                prefix TEXT NOT NULL UNIQUE,
+                next_number INTEGER NOT NULL DEFAULT 1
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS short_codes (
+                todo_id INTEGER PRIMARY KEY,
+                prefix TEXT NOT NULL,
+                number INTEGER NOT NULL,
+                UNIQUE (prefix, number)
+            )",
+            [],
+        )?;
+        // Manual order of siblings placed with `move_after`; siblings without a
+        // position keep the default order
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS todo_positions (
+                todo_id INTEGER PRIMARY KEY,
+                position INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        // The todos picked for each local day (`YYYY-MM-DD`), in the order they were added
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS daily_plan (
+                day TEXT NOT NULL,
+                todo_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (day, todo_id),
+                FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        // Local usage metrics (see `metrics`): one row per run of the app
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                created INTEGER NOT NULL DEFAULT 0,
+                completed INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        // Notes, attachments and pomodoros count as changes to their todo, so
+        // updated_at tells how long a todo has been left alone (stale todos, the review)
+        for (name, event, table, row) in [
+            ("notes_touch_todo", "INSERT", "notes", "NEW"),
+            ("pomodoros_touch_todo", "INSERT", "pomodoros", "NEW"),
+            ("attachments_touch_todo", "INSERT", "attachments", "NEW"),
+            ("attachments_delete_touch_todo", "DELETE", "attachments", "OLD"),
+        ] {
+            self.conn.execute(
+                &format!(
+                    "CREATE TRIGGER IF NOT EXISTS {name} AFTER {event} ON {table}
+                     BEGIN
+                         UPDATE todos SET updated_at = strftime('%Y-%m-%d %H:%M:%f+00:00', 'now') WHERE id = {row}.todo_id;
+                     END"
+                ),
+                [],
+            )?;
+        }
+        self.conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+        Ok(())
+    }
+
+    /// Indices and sync triggers on `todos`, created again after `enforce_parent_rule`
+    /// rebuilds the table
+    fn create_todo_indexes(&self) -> Result<()> {
+        // Indices for the hot lookups: children of a todo, open/completed todos, due dates
+        // and hidden items. Created after the column migrations, so older databases get them too
+        for (name, column) in [("idx_todos_parent", "parent_id"), ("idx_todos_completed", "completed_at"), ("idx_todos_due", "due_by"), ("idx_todos_hidden", "hidden")] {
+            self.conn.execute(&format!("CREATE INDEX IF NOT EXISTS {} ON todos ({})", name, column), [])?;
+        }
+
+        self.conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS idx_todos_uid ON todos (uid)", [])?;
+        self.conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS todos_sync_insert AFTER INSERT ON todos WHEN NEW.uid IS NULL
+             BEGIN
+                 UPDATE todos SET uid = lower(hex(randomblob(16))),
+                     updated_at = COALESCE(NEW.updated_at, strftime('%Y-%m-%d %H:%M:%f+00:00', 'now'))
+                 WHERE id = NEW.id;
+             END",
+            [],
+        )?;
+        // Writes that set updated_at themselves (applying synced changes) keep it
+        self.conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS todos_sync_update AFTER UPDATE ON todos WHEN NEW.updated_at IS OLD.updated_at
+             BEGIN
+                 UPDATE todos SET updated_at = strftime('%Y-%m-%d %H:%M:%f+00:00', 'now') WHERE id = NEW.id;
+             END",
+            [],
+        )?;
+        self.create_history_triggers()?;
+        self.conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS todos_sync_delete AFTER DELETE ON todos WHEN OLD.uid IS NOT NULL
+             BEGIN
+                 INSERT OR REPLACE INTO sync_tombstones (uid, deleted_at)
+                 VALUES (OLD.uid, strftime('%Y-%m-%d %H:%M:%f+00:00', 'now'));
+             END",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Triggers that append every create, edit, completion, move and delete to `history`
+    fn create_history_triggers(&self) -> Result<()> {
+        const NOW: &str = "strftime('%Y-%m-%d %H:%M:%f+00:00', 'now')";
+        self.conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS history_create AFTER INSERT ON todos
+                 BEGIN
+                     INSERT INTO history (todo_id, title, action, at) VALUES (NEW.id, NEW.title, 'create', {NOW});
+                 END"
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS history_delete AFTER DELETE ON todos
+                 BEGIN
+                     INSERT INTO history (todo_id, title, action, at) VALUES (OLD.id, OLD.title, 'delete', {NOW});
+                 END"
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS history_complete AFTER UPDATE OF completed_at ON todos
+                 WHEN (OLD.completed_at IS NULL) <> (NEW.completed_at IS NULL)
+                 BEGIN
+                     INSERT INTO history (todo_id, title, action, old_value, new_value, at)
+                     VALUES (NEW.id, NEW.title, CASE WHEN NEW.completed_at IS NULL THEN 'uncomplete' ELSE 'complete' END,
+                             OLD.completed_at, NEW.completed_at, {NOW});
+                 END"
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS history_move AFTER UPDATE OF parent_id ON todos
+                 WHEN OLD.parent_id IS NOT NEW.parent_id
+                 BEGIN
+                     INSERT INTO history (todo_id, title, action, old_value, new_value, at)
+                     VALUES (NEW.id, NEW.title, 'move', OLD.parent_id, NEW.parent_id, {NOW});
+                 END"
+            ),
+            [],
+        )?;
+        for (field, _) in history::TRACKED_FIELDS {
+            self.conn.execute(
+                &format!(
+                    "CREATE TRIGGER IF NOT EXISTS history_{field} AFTER UPDATE OF {field} ON todos
+                     WHEN OLD.{field} IS NOT NEW.{field}
+                     BEGIN
+                         INSERT INTO history (todo_id, title, action, field, old_value, new_value, at)
+                         VALUES (NEW.id, NEW.title, 'update', '{field}', OLD.{field}, NEW.{field}, {NOW});
+                     END"
+                ),
+                [],
+            )?;
+        }
         Ok(())
     }
 
+    /// Rebuild `todos` with `ON DELETE RESTRICT` on `parent_id` (SQLite can't alter a
+    /// foreign key in place). Children of todos that no longer exist move to the top level.
+    fn enforce_parent_rule(&self) -> Result<()> {
+        // Checking foreign keys has to be off while the table is swapped
+        let enforced: bool = self.conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+        self.conn.pragma_update(None, "foreign_keys", false)?;
+        let rebuilt = self.conn.execute_batch(
+            "BEGIN;
+             UPDATE todos SET parent_id = NULL
+                 WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM todos);
+             CREATE TABLE todos_rebuilt (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 title TEXT NOT NULL,
+                 description TEXT NOT NULL DEFAULT '',
+                 created_at TEXT NOT NULL,
+                 completed_at TEXT,
+                 due_by TEXT,
+                 parent_id INTEGER,
+                 hidden INTEGER NOT NULL DEFAULT 0,
+                 start_at TEXT,
+                 uid TEXT,
+                 updated_at TEXT,
+                 FOREIGN KEY (parent_id) REFERENCES todos (id) ON DELETE RESTRICT
+             );
+             INSERT INTO todos_rebuilt (id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at, uid, updated_at)
+                 SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at, uid, updated_at FROM todos;
+             UPDATE sqlite_sequence SET seq = (SELECT MAX(seq) FROM sqlite_sequence WHERE name IN ('todos', 'todos_rebuilt'))
+                 WHERE name = 'todos_rebuilt';
+             DROP TABLE todos;
+             ALTER TABLE todos_rebuilt RENAME TO todos;
+             COMMIT;",
+        );
+        if rebuilt.is_err() {
+            let _ = self.conn.execute_batch("ROLLBACK");
+        }
+        self.conn.pragma_update(None, "foreign_keys", enforced)?;
+        rebuilt
+    }
+
     pub fn create_todo(&self, new_todo: NewTodo) -> anyhow::Result<i64> {
-        let now = Utc::now();
-        let _id = self.conn.execute(
-            "INSERT INTO todos (title, description, created_at, parent_id, hidden, due_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        self.create_imported_todo(new_todo, Utc::now(), None)
+    }
+
+    /// Create several todos at once; either all of them are added or none
+    pub fn create_todos(&self, new_todos: Vec<NewTodo>) -> anyhow::Result<Vec<i64>> {
+        let tx = self.begin()?;
+        let mut ids = Vec::with_capacity(new_todos.len());
+        for new_todo in new_todos {
+            ids.push(self.insert_todo(new_todo, Utc::now(), None)?);
+        }
+        tx.commit()?;
+        for &id in &ids {
+            self.emit(TodoEvent::Created(id));
+        }
+        Ok(ids)
+    }
+
+    /// Create a todo with timestamps carried over from another tool
+    pub fn create_imported_todo(&self, new_todo: NewTodo, created_at: DateTime<Utc>, completed_at: Option<DateTime<Utc>>) -> anyhow::Result<i64> {
+        let id = self.insert_todo(new_todo, created_at, completed_at)?;
+        self.emit(TodoEvent::Created(id));
+        Ok(id)
+    }
+
+    fn insert_todo(&self, new_todo: NewTodo, created_at: DateTime<Utc>, completed_at: Option<DateTime<Utc>>) -> anyhow::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO todos (title, description, created_at, completed_at, parent_id, hidden, due_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 new_todo.title,
                 new_todo.description,
-                now,
+                created_at,
+                completed_at,
                 new_todo.parent_id,
                 false,
                 new_todo.due_by
             ],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn get_all_todos(&self) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
+             FROM todos
+             ORDER BY created_at DESC"
+        )?;
+
+        let todo_iter = stmt.query_map([], Todo::from_row)?;
+
+        let mut todos = Vec::new();
+        for todo in todo_iter {
+            todos.push(todo?);
+        }
+
+        Ok(todos)
+    }
+
+
+    pub fn get_todo_by_id(&self, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
+             FROM todos
+             WHERE id = ?1"
+        )?;
+
+        let mut rows = stmt.query_map([id], Todo::from_row)?;
+
+        match rows.next() {
+            Some(row) => Ok(Some(row?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn update_todo(&self, id: i64, title: String, description: String, due_by: Option<DateTime<Utc>>) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET title = ?1, description = ?2, due_by = ?3 WHERE id = ?4",
+            params![title, description, due_by, id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Rename a todo, leaving its description and dates alone
+    pub fn update_title(&self, id: i64, title: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET title = ?1 WHERE id = ?2",
+            params![title, id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Set or clear (None) a todo's due date
+    pub fn update_due_by(&self, id: i64, due_by: Option<DateTime<Utc>>) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET due_by = ?1 WHERE id = ?2",
+            params![due_by, id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    pub fn complete_todo(&self, id: i64) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let tx = self.begin()?;
+        tx.execute(
+            "UPDATE todos SET completed_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        if self.completion_log {
+            self.append_completion_event(id, "complete", now)?;
+        }
+        tx.commit()?;
+        self.emit(TodoEvent::Completed(id));
+        Ok(())
+    }
+
+    /// Reopen a todo. A cancelled todo comes back plain open, so completing it later
+    /// counts it as done.
+    pub fn uncomplete_todo(&self, id: i64) -> anyhow::Result<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "UPDATE todos SET completed_at = NULL, status = NULL WHERE id = ?1",
+            params![id],
+        )?;
+        if self.completion_log {
+            self.append_completion_event(id, "uncomplete", Utc::now())?;
+        }
+        tx.commit()?;
+        self.emit(TodoEvent::Uncompleted(id));
+        Ok(())
+    }
+
+    /// Complete a todo and its open descendants in one transaction.
+    /// Returns the ids completed, the todo first.
+    pub fn complete_subtree(&self, id: i64) -> anyhow::Result<Vec<i64>> {
+        let ids: Vec<i64> = self.get_subtree(id)?.into_iter().filter(|todo| !todo.is_completed()).map(|todo| todo.id).collect();
+        let now = Utc::now();
+        let tx = self.begin()?;
+        for &todo_id in &ids {
+            tx.execute("UPDATE todos SET completed_at = ?1 WHERE id = ?2", params![now, todo_id])?;
+            if self.completion_log {
+                self.append_completion_event(todo_id, "complete", now)?;
+            }
+        }
+        tx.commit()?;
+        for &todo_id in &ids {
+            self.emit(TodoEvent::Completed(todo_id));
+        }
+        Ok(ids)
+    }
+
+    /// Completed ancestors of a todo, nearest first
+    pub fn get_completed_ancestors(&self, id: i64) -> anyhow::Result<Vec<Todo>> {
+        let mut ancestors = Vec::new();
+        let mut parent_id = self.get_todo_by_id(id)?.and_then(|todo| todo.parent_id);
+        while let Some(parent) = parent_id.map(|id| self.get_todo_by_id(id)).transpose()?.flatten() {
+            parent_id = parent.parent_id;
+            if parent.is_completed() {
+                ancestors.push(parent);
+            }
+        }
+        Ok(ancestors)
+    }
+
+    /// Reopen a todo and every completed ancestor in one transaction, so it shows
+    /// in the default tree again. Returns the ids reopened, the todo first.
+    pub fn uncomplete_with_ancestors(&self, id: i64) -> anyhow::Result<Vec<i64>> {
+        let mut ids = vec![id];
+        ids.extend(self.get_completed_ancestors(id)?.into_iter().map(|todo| todo.id));
+        let now = Utc::now();
+        let tx = self.begin()?;
+        for &todo_id in &ids {
+            tx.execute("UPDATE todos SET completed_at = NULL, status = NULL WHERE id = ?1", params![todo_id])?;
+            if self.completion_log {
+                self.append_completion_event(todo_id, "uncomplete", now)?;
+            }
+        }
+        tx.commit()?;
+        for &todo_id in &ids {
+            self.emit(TodoEvent::Uncompleted(todo_id));
+        }
+        Ok(ids)
+    }
+
+    /// Every todo and every deletion, in the form exchanged with a sync peer
+    pub fn get_sync_records(&self) -> anyhow::Result<Vec<SyncRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.uid, t.updated_at, p.uid, t.title, t.description, t.created_at, t.completed_at, t.due_by, t.hidden, t.start_at, t.status
+             FROM todos t LEFT JOIN todos p ON p.id = t.parent_id
+             ORDER BY t.id"
+        )?;
+        let mut records = stmt
+            .query_map([], |row| {
+                Ok(SyncRecord {
+                    uid: row.get(0)?,
+                    updated_at: row.get(1)?,
+                    todo: Some(SyncTodo {
+                        parent_uid: row.get(2)?,
+                        title: row.get(3)?,
+                        description: row.get(4)?,
+                        created_at: row.get(5)?,
+                        completed_at: row.get(6)?,
+                        due_by: row.get(7)?,
+                        hidden: row.get(8)?,
+                        start_at: row.get(9)?,
+                        status: row.get(10)?,
+                    }),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = self.conn.prepare("SELECT uid, deleted_at FROM sync_tombstones ORDER BY uid")?;
+        let deletions = stmt.query_map([], |row| Ok(SyncRecord { uid: row.get(0)?, updated_at: row.get(1)?, todo: None }))?;
+        for deletion in deletions {
+            records.push(deletion?);
+        }
+        Ok(records)
+    }
+
+    /// The version of each todo last uploaded to the CalDAV `collection`, by uid
+    pub fn get_caldav_pushed(&self, collection: &str) -> anyhow::Result<HashMap<String, DateTime<Utc>>> {
+        let mut stmt = self.conn.prepare("SELECT uid, updated_at FROM caldav_pushed WHERE collection = ?1")?;
+        let pushed = stmt
+            .query_map([collection], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(pushed)
+    }
+
+    /// Record an upload to `collection`, or with `None` the removal of the resource
+    pub fn set_caldav_pushed(&self, collection: &str, uid: &str, updated_at: Option<DateTime<Utc>>) -> anyhow::Result<()> {
+        match updated_at {
+            Some(updated_at) => self.conn.execute(
+                "INSERT OR REPLACE INTO caldav_pushed (collection, uid, updated_at) VALUES (?1, ?2, ?3)",
+                params![collection, uid, updated_at],
+            )?,
+            None => self.conn.execute("DELETE FROM caldav_pushed WHERE collection = ?1 AND uid = ?2", params![collection, uid])?,
+        };
+        Ok(())
+    }
+
+    /// Write changes received from a sync peer, which `sync::merge` has already found
+    /// to be newer, keeping their timestamps. Parents must come before their children.
+    pub fn apply_sync_records(&self, records: &[SyncRecord]) -> anyhow::Result<()> {
+        let tx = self.begin()?;
+        let id_for = |uid: &str| -> Result<Option<i64>> {
+            tx.query_row("SELECT id FROM todos WHERE uid = ?1", [uid], |row| row.get(0)).optional()
+        };
+
+        for record in records {
+            let local_id = id_for(&record.uid)?;
+            match &record.todo {
+                Some(todo) => {
+                    let mut parent_id = match &todo.parent_uid {
+                        Some(parent_uid) => id_for(parent_uid)?,
+                        None => None,
+                    };
+                    // Moves on both machines can cross; keep the local parent rather than a loop
+                    if let (Some(id), Some(parent)) = (local_id, parent_id)
+                        && self.would_create_cycle(id, parent)? {
+                        parent_id = tx.query_row("SELECT parent_id FROM todos WHERE id = ?1", [id], |row| row.get(0))?;
+                    }
+                    match local_id {
+                        Some(id) => tx.execute(
+                            "UPDATE todos SET title = ?1, description = ?2, created_at = ?3, completed_at = ?4, due_by = ?5,
+                                 parent_id = ?6, hidden = ?7, updated_at = ?8, start_at = ?9, status = ?10
+                             WHERE id = ?11",
+                            params![todo.title, todo.description, todo.created_at, todo.completed_at, todo.due_by,
+                                    parent_id, todo.hidden, record.updated_at, todo.start_at, todo.status, id],
+                        )?,
+                        None => tx.execute(
+                            "INSERT INTO todos (uid, title, description, created_at, completed_at, due_by, parent_id, hidden, updated_at, start_at, status)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                            params![record.uid, todo.title, todo.description, todo.created_at, todo.completed_at,
+                                    todo.due_by, parent_id, todo.hidden, record.updated_at, todo.start_at, todo.status],
+                        )?,
+                    };
+                    tx.execute("DELETE FROM sync_tombstones WHERE uid = ?1", [&record.uid])?;
+                }
+                None => {
+                    if let Some(id) = local_id {
+                        // Subtasks added here since keep their place, one level up
+                        tx.execute(
+                            "UPDATE todos SET parent_id = (SELECT parent_id FROM todos WHERE id = ?1) WHERE parent_id = ?1",
+                            [id],
+                        )?;
+                        tx.execute("DELETE FROM todos WHERE id = ?1", [id])?;
+                    }
+                    tx.execute(
+                        "INSERT OR REPLACE INTO sync_tombstones (uid, deleted_at) VALUES (?1, ?2)",
+                        params![record.uid, record.updated_at],
+                    )?;
+                }
+            }
+        }
+        tx.commit()?;
+        if !records.is_empty() {
+            self.emit(TodoEvent::Reloaded);
+        }
+        Ok(())
+    }
+
+    /// Every todo's id and its `merge::fields()` as text, for a three-way merge
+    pub fn get_merge_rows(&self) -> anyhow::Result<Vec<(i64, MergeRow)>> {
+        let fields: Vec<&'static str> = merge::fields().collect();
+        let columns: Vec<String> = fields
+            .iter()
+            .map(|field| match *field {
+                "parent" => "p.uid".to_string(),
+                column => format!("CAST(t.{} AS TEXT)", column),
+            })
+            .collect();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.id, t.uid, t.updated_at, {} FROM todos t LEFT JOIN todos p ON p.id = t.parent_id ORDER BY t.id",
+            columns.join(", ")
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let mut values = BTreeMap::new();
+            for (i, field) in fields.iter().enumerate() {
+                values.insert(*field, row.get(3 + i)?);
+            }
+            Ok((row.get(0)?, MergeRow { uid: row.get(1)?, updated_at: row.get(2)?, values }))
+        })?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// Write the outcome of `merge::plan` in one transaction. Parents must be created
+    /// before their children; a move that would make a loop is skipped.
+    pub fn apply_merge(&self, changes: &[MergeChange]) -> anyhow::Result<()> {
+        let tx = self.begin()?;
+        let id_for = |uid: &str| -> Result<Option<i64>> {
+            tx.query_row("SELECT id FROM todos WHERE uid = ?1", [uid], |row| row.get(0)).optional()
+        };
+
+        for change in changes {
+            match change {
+                MergeChange::Create(row) => {
+                    let mut columns = vec!["uid", "updated_at"];
+                    let mut values = vec![Value::Text(row.uid.clone()), Value::Text(row.updated_at.format("%F %T%.f%:z").to_string())];
+                    for (field, value) in &row.values {
+                        let value = match (*field, value) {
+                            ("parent", Some(parent_uid)) => id_for(parent_uid)?.map_or(Value::Null, Value::Integer),
+                            (_, Some(value)) => Value::Text(value.clone()),
+                            (_, None) => Value::Null,
+                        };
+                        columns.push(if *field == "parent" { "parent_id" } else { field });
+                        values.push(value);
+                    }
+                    let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+                    tx.execute(
+                        &format!("INSERT INTO todos ({}) VALUES ({})", columns.join(", "), placeholders.join(", ")),
+                        params_from_iter(values),
+                    )?;
+                    tx.execute("DELETE FROM sync_tombstones WHERE uid = ?1", [&row.uid])?;
+                }
+                MergeChange::Update { uid, field: "parent", value } => {
+                    let Some(id) = id_for(uid)? else { continue };
+                    let parent_id = match value {
+                        Some(parent_uid) => id_for(parent_uid)?,
+                        None => None,
+                    };
+                    if let Some(parent) = parent_id
+                        && self.would_create_cycle(id, parent)? {
+                        continue;
+                    }
+                    tx.execute("UPDATE todos SET parent_id = ?1 WHERE id = ?2", params![parent_id, id])?;
+                }
+                MergeChange::Update { uid, field, value } => {
+                    anyhow::ensure!(merge::fields().any(|known| known == *field), "Unknown merge field '{}'", field);
+                    tx.execute(&format!("UPDATE todos SET {} = ?1 WHERE uid = ?2", field), params![value, uid])?;
+                }
+                MergeChange::Delete(uid) => {
+                    if let Some(id) = id_for(uid)? {
+                        // Subtasks added here since keep their place, one level up
+                        tx.execute(
+                            "UPDATE todos SET parent_id = (SELECT parent_id FROM todos WHERE id = ?1) WHERE parent_id = ?1",
+                            [id],
+                        )?;
+                        tx.execute("DELETE FROM todo_positions WHERE todo_id = ?1", [id])?;
+                        tx.execute("DELETE FROM todos WHERE id = ?1", [id])?;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+        if !changes.is_empty() {
+            self.emit(TodoEvent::Reloaded);
+        }
+        Ok(())
+    }
+
+    fn append_completion_event(&self, todo_id: i64, event: &str, at: DateTime<Utc>) -> anyhow::Result<()> {
+        let title: String = self.conn.query_row("SELECT title FROM todos WHERE id = ?1", [todo_id], |row| row.get(0))?;
+        let prev_hash: String = self.conn
+            .query_row("SELECT hash FROM completion_log ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+            .optional()?
+            .unwrap_or_else(|| audit::GENESIS_HASH.to_string());
+        let at = at.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        let hash = audit::event_hash(&prev_hash, todo_id, event, &title, &at);
+
+        self.conn.execute(
+            "INSERT INTO completion_log (todo_id, event, title, at, prev_hash, hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![todo_id, event, title, at, prev_hash, hash],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_completion_events(&self) -> anyhow::Result<Vec<CompletionEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, todo_id, event, title, at, prev_hash, hash FROM completion_log ORDER BY id"
+        )?;
+        let event_iter = stmt.query_map([], |row| {
+            Ok(CompletionEvent {
+                id: row.get(0)?,
+                todo_id: row.get(1)?,
+                event: row.get(2)?,
+                title: row.get(3)?,
+                at: row.get(4)?,
+                prev_hash: row.get(5)?,
+                hash: row.get(6)?,
+            })
+        })?;
+
+        let mut events = Vec::new();
+        for event in event_iter {
+            events.push(event?);
+        }
+        Ok(events)
+    }
+
+    pub fn get_stored_dates(&self) -> anyhow::Result<Vec<StoredDates>> {
+        let mut stmt = self.conn.prepare("SELECT id, title, created_at, completed_at, due_by FROM todos ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(StoredDates {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: stored_date(row.get(2)?),
+                completed_at: stored_date(row.get(3)?),
+                due_by: stored_date(row.get(4)?),
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>>>()?)
+    }
+
+    /// Overwrite all three dates of a todo (repairs made by `tododb doctor`)
+    pub fn set_todo_dates(&self, id: i64, created_at: DateTime<Utc>, completed_at: Option<DateTime<Utc>>, due_by: Option<DateTime<Utc>>) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET created_at = ?1, completed_at = ?2, due_by = ?3 WHERE id = ?4",
+            params![created_at, completed_at, due_by, id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Project prefixes as (todo id, prefix), in prefix order
+    pub fn get_code_prefixes(&self) -> anyhow::Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT todo_id, prefix FROM code_prefixes WHERE todo_id IN (SELECT id FROM todos) ORDER BY prefix"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<Vec<_>>>()?)
+    }
+
+    /// Give the subtree of `todo_id` short codes like `WEB-12`, or stop (None).
+    /// Prefixes are 1-8 letters and digits, starting with a letter.
+    pub fn set_code_prefix(&self, todo_id: i64, prefix: Option<&str>) -> anyhow::Result<()> {
+        let Some(prefix) = prefix else {
+            self.conn.execute(
+                "DELETE FROM short_codes WHERE prefix IN (SELECT prefix FROM code_prefixes WHERE todo_id = ?1)",
+                params![todo_id],
+            )?;
+            self.conn.execute("DELETE FROM code_prefixes WHERE todo_id = ?1", params![todo_id])?;
+            return Ok(());
+        };
+        let prefix = prefix.to_ascii_uppercase();
+        let valid = prefix.len() <= 8
+            && prefix.starts_with(|c: char| c.is_ascii_alphabetic())
+            && prefix.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid {
+            return Err(anyhow::anyhow!("Invalid prefix '{}': use 1-8 letters and digits, starting with a letter", prefix));
+        }
+        if self.get_todo_by_id(todo_id)?.is_none() {
+            return Err(anyhow::anyhow!("Todo {} not found", todo_id));
+        }
+        let owner: Option<i64> = self.conn
+            .query_row("SELECT todo_id FROM code_prefixes WHERE prefix = ?1", params![prefix], |row| row.get(0))
+            .optional()?;
+        if let Some(owner) = owner
+            && owner != todo_id {
+            return Err(anyhow::anyhow!("Prefix {} is already used by todo {}", prefix, owner));
+        }
+        self.conn.execute(
+            "INSERT INTO code_prefixes (todo_id, prefix) VALUES (?1, ?2)
+             ON CONFLICT(todo_id) DO UPDATE SET prefix = excluded.prefix",
+            params![todo_id, prefix],
+        )?;
+        Ok(())
+    }
+
+    /// Short code of every todo that has one: a project with a prefix is shown as the
+    /// prefix (`WEB`), each todo below it as prefix and number (`WEB-12`), taking the
+    /// nearest prefixed ancestor. Numbers are handed out on first sight, in id order,
+    /// and kept while the todo stays in that project.
+    pub fn refresh_short_codes(&self) -> anyhow::Result<HashMap<i64, String>> {
+        let prefixes: HashMap<i64, String> = self.get_code_prefixes()?.into_iter().collect();
+        let mut stmt = self.conn.prepare("SELECT todo_id, prefix, number FROM short_codes")?;
+        let existing: HashMap<i64, (String, i64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<_>>()?;
+        if prefixes.is_empty() && existing.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut stmt = self.conn.prepare("SELECT id, parent_id FROM todos ORDER BY id")?;
+        let parents: Vec<(i64, Option<i64>)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_>>()?;
+        let parent_of: HashMap<i64, Option<i64>> = parents.iter().copied().collect();
+        let mut stmt = self.conn.prepare("SELECT prefix, next_number FROM code_prefixes")?;
+        let mut next_numbers: HashMap<String, i64> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_>>()?;
+
+        let mut codes = HashMap::new();
+        let mut assigned = Vec::new();
+        let mut removed = Vec::new();
+        for &(id, parent_id) in &parents {
+            if let Some(prefix) = prefixes.get(&id) {
+                codes.insert(id, prefix.clone());
+                if existing.contains_key(&id) {
+                    removed.push(id);
+                }
+                continue;
+            }
+            let mut current = parent_id;
+            let mut depth = 0;
+            let project_prefix = loop {
+                match current {
+                    Some(ancestor) if depth <= parents.len() => {
+                        if let Some(prefix) = prefixes.get(&ancestor) {
+                            break Some(prefix);
+                        }
+                        current = parent_of.get(&ancestor).copied().flatten();
+                        depth += 1;
+                    }
+                    _ => break None,
+                }
+            };
+            match (project_prefix, existing.get(&id)) {
+                (Some(prefix), Some((code_prefix, number))) if code_prefix == prefix => {
+                    codes.insert(id, format!("{}-{}", prefix, number));
+                }
+                (Some(prefix), _) => {
+                    let next = next_numbers.entry(prefix.clone()).or_insert(1);
+                    codes.insert(id, format!("{}-{}", prefix, next));
+                    assigned.push((id, prefix.clone(), *next));
+                    *next += 1;
+                }
+                (None, Some(_)) => removed.push(id),
+                (None, None) => {}
+            }
+        }
+        // Codes of deleted todos go too, but their numbers stay used
+        removed.extend(existing.keys().filter(|id| !parent_of.contains_key(id)));
+
+        if !assigned.is_empty() || !removed.is_empty() {
+            let tx = self.begin()?;
+            for id in &removed {
+                tx.execute("DELETE FROM short_codes WHERE todo_id = ?1", params![id])?;
+            }
+            for (id, prefix, number) in &assigned {
+                tx.execute(
+                    "INSERT OR REPLACE INTO short_codes (todo_id, prefix, number) VALUES (?1, ?2, ?3)",
+                    params![id, prefix, number],
+                )?;
+            }
+            for (prefix, next) in &next_numbers {
+                tx.execute("UPDATE code_prefixes SET next_number = ?1 WHERE prefix = ?2", params![next, prefix])?;
+            }
+            tx.commit()?;
+        }
+        Ok(codes)
+    }
+
+    /// The todo a user typed: a numeric id or a short code such as `web-12` (any case)
+    pub fn resolve_todo_reference(&self, reference: &str) -> anyhow::Result<Option<i64>> {
+        let reference = reference.trim();
+        if let Ok(id) = reference.parse::<i64>() {
+            return Ok(self.get_todo_by_id(id)?.map(|todo| todo.id));
+        }
+        Ok(self
+            .refresh_short_codes()?
+            .into_iter()
+            .find(|(_, code)| code.eq_ignore_ascii_case(reference))
+            .map(|(id, _)| id))
+    }
+
+    pub fn toggle_todo_hidden(&self, id: i64) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET hidden = NOT hidden WHERE id = ?1",
+            params![id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Defer a todo until `start_at`, or make it active again (None)
+    pub fn set_start_at(&self, id: i64, start_at: Option<DateTime<Utc>>) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE todos SET start_at = ?1 WHERE id = ?2",
+            params![start_at, id],
+        )?;
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// When each todo was last changed, by id
+    pub fn get_updated_at(&self) -> anyhow::Result<HashMap<i64, DateTime<Utc>>> {
+        let mut stmt = self.conn.prepare("SELECT id, updated_at FROM todos")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// Estimated minutes of work per todo, for todos that have an estimate
+    pub fn get_estimates(&self) -> anyhow::Result<HashMap<i64, i64>> {
+        let mut stmt = self.conn.prepare("SELECT id, estimate_minutes FROM todos WHERE estimate_minutes IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// The flag on each flagged todo
+    pub fn get_flags(&self) -> anyhow::Result<HashMap<i64, Flag>> {
+        let mut stmt = self.conn.prepare("SELECT id, flag FROM todos WHERE flag IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        let mut flags = HashMap::new();
+        for row in rows {
+            let (id, flag) = row?;
+            if let Ok(Some(flag)) = Flag::parse(&flag) {
+                flags.insert(id, flag);
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Flag a todo (or clear its flag, with `None`)
+    pub fn set_flag(&self, id: i64, flag: Option<&Flag>) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET flag = ?1 WHERE id = ?2", params![flag.map(|flag| flag.as_str()), id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Set (or clear, with `None`) how many minutes a todo is expected to take
+    pub fn set_estimate(&self, id: i64, minutes: Option<i64>) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET estimate_minutes = ?1 WHERE id = ?2", params![minutes, id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Who each delegated todo is waiting on, and when to follow up
+    pub fn get_waiting(&self) -> anyhow::Result<HashMap<i64, Waiting>> {
+        let mut stmt = self.conn.prepare("SELECT id, waiting_on, follow_up_at FROM todos WHERE waiting_on IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, Waiting { on: row.get(1)?, follow_up: row.get(2)? })))?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// Mark a todo as delegated, or (with `None`) take it back
+    pub fn set_waiting(&self, id: i64, waiting: Option<&Waiting>) -> anyhow::Result<()> {
+        let changed = self.conn.execute(
+            "UPDATE todos SET waiting_on = ?1, follow_up_at = ?2 WHERE id = ?3",
+            params![waiting.map(|waiting| &waiting.on), waiting.and_then(|waiting| waiting.follow_up), id],
+        )?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
     }
 
-    pub fn get_all_todos(&self) -> anyhow::Result<Vec<Todo>> {
+    /// Recorded changes, newest first: one todo's (including its deletion) or, with
+    /// `None`, everyone's as an activity feed
+    pub fn get_history(&self, todo_id: Option<i64>, limit: usize) -> anyhow::Result<Vec<HistoryEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
-             FROM todos
-             ORDER BY created_at DESC"
+            "SELECT id, todo_id, title, action, field, old_value, new_value, at FROM history
+             WHERE ?1 IS NULL OR todo_id = ?1 ORDER BY id DESC LIMIT ?2",
         )?;
+        let entries = stmt.query_map(params![todo_id, limit as i64], |row| {
+            Ok(HistoryEntry {
+                id: row.get(0)?,
+                todo_id: row.get(1)?,
+                title: row.get(2)?,
+                action: row.get(3)?,
+                field: row.get(4)?,
+                old_value: row.get(5)?,
+                new_value: row.get(6)?,
+                at: row.get(7)?,
+            })
+        })?;
+        Ok(entries.collect::<Result<_>>()?)
+    }
 
-        let todo_iter = stmt.query_map([], |row| Todo::from_row(row))?;
+    /// The stored statuses that completion alone doesn't tell: in progress, blocked and
+    /// cancelled. Resolve with `Status::resolve`, which lets completion win.
+    pub fn get_statuses(&self) -> anyhow::Result<HashMap<i64, Status>> {
+        let mut stmt = self.conn.prepare("SELECT id, status FROM todos WHERE status IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        let mut statuses = HashMap::new();
+        for row in rows {
+            let (id, status) = row?;
+            if let Some(status) = Status::parse(&status) {
+                statuses.insert(id, status);
+            }
+        }
+        Ok(statuses)
+    }
 
-        let mut todos = Vec::new();
-        for todo in todo_iter {
-            todos.push(todo?);
+    pub fn get_status(&self, id: i64) -> anyhow::Result<Status> {
+        let (completed, stored): (bool, Option<String>) = self.conn.query_row(
+            "SELECT completed_at IS NOT NULL, status FROM todos WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(Status::resolve(completed, stored.as_deref().and_then(Status::parse)))
+    }
+
+    /// Ids of the closed todos that were cancelled rather than done, which completion
+    /// counts and reports leave out
+    pub fn get_cancelled_ids(&self) -> anyhow::Result<HashSet<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM todos WHERE status = 'cancelled' AND completed_at IS NOT NULL")?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        Ok(ids.collect::<Result<_>>()?)
+    }
+
+    /// Store a todo's status; completing or reopening it to match is up to the caller
+    pub fn set_status(&self, id: i64, status: Status) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET status = ?1 WHERE id = ?2", params![status.stored(), id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
         }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
 
-        Ok(todos)
+    /// Ids of the todos set aside as someday/maybe
+    pub fn get_someday_ids(&self) -> anyhow::Result<HashSet<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM todos WHERE someday = 1")?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        Ok(ids.collect::<Result<_>>()?)
     }
 
+    /// Set a todo aside as someday/maybe, or promote it back to an active todo
+    pub fn set_someday(&self, id: i64, someday: bool) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET someday = ?1 WHERE id = ?2", params![someday, id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
 
-    pub fn get_todo_by_id(&self, id: i64) -> anyhow::Result<Option<Todo>> {
+    /// The todos planned for `day`, done or not, in the order they were added
+    pub fn get_daily_plan(&self, day: NaiveDate) -> anyhow::Result<Vec<Todo>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
-             FROM todos
-             WHERE id = ?1"
+            "SELECT t.id, t.title, t.description, t.created_at, t.completed_at, t.due_by, t.parent_id, t.hidden, t.start_at
+             FROM daily_plan p JOIN todos t ON t.id = p.todo_id
+             WHERE p.day = ?1
+             ORDER BY p.position"
         )?;
+        let todos = stmt.query_map([day.format("%Y-%m-%d").to_string()], Todo::from_row)?;
+        Ok(todos.collect::<Result<_>>()?)
+    }
 
-        let mut rows = stmt.query_map([id], |row| Todo::from_row(row))?;
-
-        match rows.next() {
-            Some(row) => Ok(Some(row?)),
-            None => Ok(None),
+    /// Add todos to the end of `day`'s plan, skipping ones already in it
+    pub fn add_to_daily_plan(&self, day: NaiveDate, todo_ids: &[i64]) -> anyhow::Result<()> {
+        let day = day.format("%Y-%m-%d").to_string();
+        let tx = self.begin()?;
+        for todo_id in todo_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO daily_plan (day, todo_id, position)
+                 SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM daily_plan WHERE day = ?1",
+                params![day, todo_id],
+            )?;
         }
+        tx.commit()?;
+        Ok(())
     }
 
-    pub fn update_todo(&self, id: i64, title: String, description: String, due_by: Option<DateTime<Utc>>) -> anyhow::Result<()> {
-        self.conn.execute(
-            "UPDATE todos SET title = ?1, description = ?2, due_by = ?3 WHERE id = ?4",
-            params![title, description, due_by, id],
-        )?;
+    pub fn remove_from_daily_plan(&self, day: NaiveDate, todo_id: i64) -> anyhow::Result<()> {
+        self.conn.execute("DELETE FROM daily_plan WHERE day = ?1 AND todo_id = ?2", params![day.format("%Y-%m-%d").to_string(), todo_id])?;
         Ok(())
     }
 
-    pub fn complete_todo(&self, id: i64) -> anyhow::Result<()> {
-        let now = Utc::now();
-        self.conn.execute(
-            "UPDATE todos SET completed_at = ?1 WHERE id = ?2",
-            params![now, id],
+    /// The latest day before `day` that has a plan
+    pub fn last_daily_plan_before(&self, day: NaiveDate) -> anyhow::Result<Option<NaiveDate>> {
+        let last: Option<String> = self.conn.query_row(
+            "SELECT MAX(day) FROM daily_plan WHERE day < ?1",
+            [day.format("%Y-%m-%d").to_string()],
+            |row| row.get(0),
         )?;
+        Ok(last.and_then(|last| NaiveDate::parse_from_str(&last, "%Y-%m-%d").ok()))
+    }
+
+    /// Ids of the pinned todos, open or not
+    pub fn get_pinned_ids(&self) -> anyhow::Result<HashSet<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM todos WHERE pinned = 1")?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        Ok(ids.collect::<Result<_>>()?)
+    }
+
+    /// Pin a todo above the tree and list, or unpin it
+    pub fn set_pinned(&self, id: i64, pinned: bool) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET pinned = ?1 WHERE id = ?2", params![pinned, id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
         Ok(())
     }
 
-    pub fn uncomplete_todo(&self, id: i64) -> anyhow::Result<()> {
-        self.conn.execute(
-            "UPDATE todos SET completed_at = NULL WHERE id = ?1",
-            params![id],
-        )?;
+    /// Context and energy per todo, for todos that have either
+    pub fn get_labels(&self) -> anyhow::Result<HashMap<i64, Labels>> {
+        let mut stmt = self.conn.prepare("SELECT id, context, energy FROM todos WHERE context IS NOT NULL OR energy IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| {
+            let energy: Option<String> = row.get(2)?;
+            Ok((row.get(0)?, Labels { context: row.get(1)?, energy: energy.as_deref().and_then(Energy::parse) }))
+        })?;
+        Ok(rows.collect::<Result<_>>()?)
+    }
+
+    /// Set (or clear, with `None`) the context a todo can be done in
+    pub fn set_context(&self, id: i64, context: Option<&str>) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET context = ?1 WHERE id = ?2", params![context, id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
         Ok(())
     }
 
-    pub fn toggle_todo_hidden(&self, id: i64) -> anyhow::Result<()> {
+    /// Set (or clear, with `None`) how much energy a todo takes
+    pub fn set_energy(&self, id: i64, energy: Option<Energy>) -> anyhow::Result<()> {
+        let changed = self.conn.execute("UPDATE todos SET energy = ?1 WHERE id = ?2", params![energy.map(|energy| energy.as_str()), id])?;
+        if changed == 0 {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        self.emit(TodoEvent::Updated(id));
+        Ok(())
+    }
+
+    /// Mark a todo as looked at without changing it, so it stops counting as stale.
+    /// Nothing about the todo changed, so no event is sent.
+    pub fn touch_todo(&self, id: i64) -> anyhow::Result<()> {
         self.conn.execute(
-            "UPDATE todos SET hidden = NOT hidden WHERE id = ?1",
+            "UPDATE todos SET updated_at = strftime('%Y-%m-%d %H:%M:%f+00:00', 'now') WHERE id = ?1",
             params![id],
         )?;
         Ok(())
     }
 
     pub fn has_children(&self, id: i64) -> anyhow::Result<bool> {
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM todos WHERE parent_id = ?1",
+        // EXISTS stops at the first child found in the parent index
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM todos WHERE parent_id = ?1)",
             params![id],
             |row| row.get(0),
         )?;
-        Ok(count > 0)
+        Ok(exists)
     }
 
     pub fn delete_todo(&self, id: i64) -> anyhow::Result<()> {
-        self.conn.execute("DELETE FROM todos WHERE id = ?1", params![id])?;
+        let todo = self.get_todo_by_id(id)?;
+        let tx = self.begin()?;
+        tx.execute("DELETE FROM todos WHERE id = ?1", params![id]).map_err(|e| match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::ConstraintViolation) => anyhow::anyhow!("Todo {} has subtasks; delete them first", id),
+            _ => e.into(),
+        })?;
+        tx.execute("DELETE FROM todo_positions WHERE todo_id = ?1", params![id])?;
+        tx.commit()?;
+        if let Some(todo) = todo {
+            self.emit(TodoEvent::Deleted(todo));
+        }
         Ok(())
     }
 
     pub fn move_todo(&self, id: i64, new_parent_id: Option<i64>) -> anyhow::Result<()> {
         // Check if the new parent would create a cycle
-        if let Some(parent_id) = new_parent_id {
-            if self.would_create_cycle(id, parent_id)? {
-                return Err(anyhow::anyhow!("Cannot move todo: would create a cycle"));
-            }
+        if let Some(parent_id) = new_parent_id
+            && self.would_create_cycle(id, parent_id)? {
+            return Err(anyhow::anyhow!("Cannot move todo: would create a cycle"));
         }
         
-        self.conn.execute(
+        let tx = self.begin()?;
+        tx.execute(
             "UPDATE todos SET parent_id = ?1 WHERE id = ?2",
             params![new_parent_id, id],
         )?;
+        // Under a new parent it takes the default place again
+        tx.execute("DELETE FROM todo_positions WHERE todo_id = ?1", params![id])?;
+        tx.commit()?;
+        self.emit(TodoEvent::Moved(id));
+        Ok(())
+    }
+
+    /// Move a todo to sit right after `sibling`, under the same parent. `order` is
+    /// the sibling's siblings as shown; they are numbered in that order, with the
+    /// moved todo inserted, so the new order holds from then on.
+    pub fn move_after(&self, id: i64, sibling: i64, order: &[i64]) -> anyhow::Result<()> {
+        if id == sibling {
+            return Ok(());
+        }
+        let parent_id = self.get_todo_by_id(sibling)?
+            .ok_or_else(|| anyhow::anyhow!("Todo {} not found", sibling))?
+            .parent_id;
+        if let Some(parent_id) = parent_id
+            && self.would_create_cycle(id, parent_id)? {
+            return Err(anyhow::anyhow!("Cannot move todo: would create a cycle"));
+        }
+
+        let mut new_order: Vec<i64> = order.iter().copied().filter(|&other| other != id).collect();
+        let index = new_order.iter().position(|&other| other == sibling).map_or(new_order.len(), |index| index + 1);
+        new_order.insert(index, id);
+
+        let tx = self.begin()?;
+        tx.execute(
+            "UPDATE todos SET parent_id = ?1 WHERE id = ?2",
+            params![parent_id, id],
+        )?;
+        for (position, todo_id) in new_order.iter().enumerate() {
+            tx.execute(
+                "INSERT OR REPLACE INTO todo_positions (todo_id, position) VALUES (?1, ?2)",
+                params![todo_id, position as i64],
+            )?;
+        }
+        tx.commit()?;
+        self.emit(TodoEvent::Moved(id));
         Ok(())
     }
 
+    /// The manual position of every todo placed with `move_after`
+    pub fn get_positions(&self) -> anyhow::Result<HashMap<i64, i64>> {
+        let mut stmt = self.conn.prepare("SELECT todo_id, position FROM todo_positions")?;
+        let positions = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<HashMap<_, _>>>()?;
+        Ok(positions)
+    }
+
+    /// A todo and all its descendants, parents before children
+    pub fn get_subtree(&self, id: i64) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE subtree(id, depth) AS (
+                 SELECT id, 0 FROM todos WHERE id = ?1
+                 UNION ALL
+                 SELECT t.id, s.depth + 1 FROM todos t JOIN subtree s ON t.parent_id = s.id
+             )
+             SELECT t.id, t.title, t.description, t.created_at, t.completed_at, t.due_by, t.parent_id, t.hidden, t.start_at
+             FROM todos t JOIN subtree s ON t.id = s.id
+             ORDER BY s.depth, t.id"
+        )?;
+        let todos = stmt.query_map(params![id], Todo::from_row)?.collect::<Result<Vec<_>>>()?;
+        if todos.is_empty() {
+            return Err(anyhow::anyhow!("Todo {} not found", id));
+        }
+        Ok(todos)
+    }
+
+    /// Deep-copy a todo and all its descendants under `new_parent` (None for top level).
+    /// Copies are created now and start uncompleted. Returns the id of the copied top todo.
+    pub fn duplicate_subtree(&self, id: i64, new_parent: Option<i64>) -> anyhow::Result<i64> {
+        // Read the whole subtree up front, so pasting a todo into its own subtree
+        // copies it once rather than chasing the copies
+        let todos = self.get_subtree(id)?;
+
+        let now = Utc::now();
+        let tx = self.begin()?;
+        let mut new_ids: HashMap<i64, i64> = HashMap::new();
+        for todo in &todos {
+            let parent_id = if todo.id == id { new_parent } else { todo.parent_id.and_then(|p| new_ids.get(&p).copied()) };
+            tx.execute(
+                "INSERT INTO todos (title, description, created_at, completed_at, parent_id, hidden, due_by, start_at) VALUES (?1, ?2, ?3, NULL, ?4, ?5, ?6, ?7)",
+                params![todo.title, todo.description, now, parent_id, todo.hidden, todo.due_by, todo.start_at],
+            )?;
+            new_ids.insert(todo.id, tx.last_insert_rowid());
+        }
+        tx.commit()?;
+        for todo in &todos {
+            self.emit(TodoEvent::Created(new_ids[&todo.id]));
+        }
+
+        Ok(new_ids[&id])
+    }
+
+    /// Insert a subtree read from another database (see `get_subtree`) under `new_parent`,
+    /// keeping timestamps, statuses (see `get_statuses`) and attachments but giving every
+    /// todo a new id. Completed todos are added to this database's completion log if it
+    /// keeps one. Returns the new id of the top todo.
+    pub fn import_subtree(&self, todos: &[Todo], statuses: &HashMap<i64, Status>, attachments: &[Attachment], new_parent: Option<i64>) -> anyhow::Result<i64> {
+        let root = todos.first().ok_or_else(|| anyhow::anyhow!("Nothing to import"))?;
+        if let Some(parent_id) = new_parent
+            && self.get_todo_by_id(parent_id)?.is_none() {
+            return Err(anyhow::anyhow!("Todo {} not found", parent_id));
+        }
+
+        let tx = self.begin()?;
+        let mut new_ids: HashMap<i64, i64> = HashMap::new();
+        for todo in todos {
+            let parent_id = if todo.id == root.id { new_parent } else { todo.parent_id.and_then(|p| new_ids.get(&p).copied()) };
+            tx.execute(
+                "INSERT INTO todos (title, description, created_at, completed_at, parent_id, hidden, due_by, start_at, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![todo.title, todo.description, todo.created_at, todo.completed_at, parent_id, todo.hidden, todo.due_by, todo.start_at,
+                        statuses.get(&todo.id).and_then(Status::stored)],
+            )?;
+            let new_id = tx.last_insert_rowid();
+            new_ids.insert(todo.id, new_id);
+            if self.completion_log
+                && let Some(completed_at) = todo.completed_at {
+                self.append_completion_event(new_id, "complete", completed_at)?;
+            }
+        }
+        for attachment in attachments {
+            if let Some(todo_id) = new_ids.get(&attachment.todo_id) {
+                tx.execute(
+                    "INSERT INTO attachments (todo_id, target, created_at) VALUES (?1, ?2, ?3)",
+                    params![todo_id, attachment.target, attachment.created_at],
+                )?;
+            }
+        }
+        tx.commit()?;
+        for todo in todos {
+            self.emit(TodoEvent::Created(new_ids[&todo.id]));
+        }
+
+        Ok(new_ids[&root.id])
+    }
+
+    /// Delete a todo and all its descendants (and their attachments)
+    pub fn delete_subtree(&self, id: i64) -> anyhow::Result<usize> {
+        let todos = self.get_subtree(id)?;
+        let tx = self.begin()?;
+        // Children before parents, so no row is left pointing at a deleted parent
+        for todo in todos.iter().rev() {
+            tx.execute("DELETE FROM todos WHERE id = ?1", params![todo.id])?;
+            tx.execute("DELETE FROM todo_positions WHERE todo_id = ?1", params![todo.id])?;
+        }
+        tx.commit()?;
+        let count = todos.len();
+        for todo in todos.into_iter().rev() {
+            self.emit(TodoEvent::Deleted(todo));
+        }
+        Ok(count)
+    }
+
     fn would_create_cycle(&self, todo_id: i64, potential_parent_id: i64) -> anyhow::Result<bool> {
         // If we're trying to make a todo its own parent, that's obviously a cycle
         if todo_id == potential_parent_id {
             return Ok(true);
         }
 
-        // Walk up the parent chain of the potential parent to see if we encounter the todo we're trying to move
-        let mut current_id = Some(potential_parent_id);
-        while let Some(id) = current_id {
-            if let Some(todo) = self.get_todo_by_id(id)? {
-                current_id = todo.parent_id;
-                if current_id == Some(todo_id) {
-                    return Ok(true);
-                }
-            } else {
-                break;
-            }
-        }
-        
-        Ok(false)
+        // Collect every ancestor of the potential parent in one query, completed and hidden
+        // todos included; UNION stops at a cycle already in the data
+        let cycle = self.conn.query_row(
+            "WITH RECURSIVE ancestors(id) AS (
+                SELECT ?1
+                UNION
+                SELECT todos.parent_id FROM todos JOIN ancestors ON todos.id = ancestors.id
+                WHERE todos.parent_id IS NOT NULL
+            )
+            SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?2)",
+            params![potential_parent_id, todo_id],
+            |row| row.get(0),
+        )?;
+        Ok(cycle)
     }
 
     pub fn get_incomplete_todos(&self, parent_id: Option<i64>) -> anyhow::Result<Vec<Todo>> {
@@ -247,24 +1970,24 @@ impl Database {
         match parent_id {
             Some(pid) => {
                 let mut stmt = self.conn.prepare(
-                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
+                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
                      FROM todos
                      WHERE parent_id = ?1 AND completed_at IS NULL
                      ORDER BY created_at DESC"
                 )?;
-                let todo_iter = stmt.query_map([pid], |row| Todo::from_row(row))?;
+                let todo_iter = stmt.query_map([pid], Todo::from_row)?;
                 for todo in todo_iter {
                     todos.push(todo?);
                 }
             },
             None => {
                 let mut stmt = self.conn.prepare(
-                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
+                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
                      FROM todos
                      WHERE completed_at IS NULL
                      ORDER BY created_at DESC"
                 )?;
-                let todo_iter = stmt.query_map([], |row| Todo::from_row(row))?;
+                let todo_iter = stmt.query_map([], Todo::from_row)?;
                 for todo in todo_iter {
                     todos.push(todo?);
                 }
@@ -274,32 +1997,64 @@ impl Database {
         Ok(todos)
     }
 
+    /// One page of completed todos, most recently completed first
+    pub fn get_completed_todos(&self, filter: &CompletedFilter, offset: usize, limit: usize) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT ?1 UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+             )
+             SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
+             FROM todos
+             WHERE completed_at IS NOT NULL AND (?2 IS NULL OR completed_at >= ?2)
+                 AND (?1 IS NULL OR id IN subtree)
+             ORDER BY completed_at DESC, id DESC
+             LIMIT ?3 OFFSET ?4"
+        )?;
+        let todos = stmt.query_map(params![filter.root, filter.since, limit as i64, offset as i64], Todo::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(todos)
+    }
+
+    pub fn count_completed_todos(&self, filter: &CompletedFilter) -> anyhow::Result<usize> {
+        let count: i64 = self.conn.query_row(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT ?1 UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+             )
+             SELECT COUNT(*) FROM todos
+             WHERE completed_at IS NOT NULL AND (?2 IS NULL OR completed_at >= ?2)
+                 AND (?1 IS NULL OR id IN subtree)",
+            params![filter.root, filter.since],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
     pub fn get_recent_completed_todos(&self, parent_id: Option<i64>, limit: usize) -> anyhow::Result<Vec<Todo>> {
         let mut todos = Vec::new();
 
         match parent_id {
             Some(pid) => {
                 let mut stmt = self.conn.prepare(
-                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
+                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
                      FROM todos
                      WHERE parent_id = ?1 AND completed_at IS NOT NULL
                      ORDER BY completed_at DESC
                      LIMIT ?2"
                 )?;
-                let todo_iter = stmt.query_map(params![pid, limit as i64], |row| Todo::from_row(row))?;
+                let todo_iter = stmt.query_map(params![pid, limit as i64], Todo::from_row)?;
                 for todo in todo_iter {
                     todos.push(todo?);
                 }
             },
             None => {
                 let mut stmt = self.conn.prepare(
-                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
+                    "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
                      FROM todos
                      WHERE completed_at IS NOT NULL
                      ORDER BY completed_at DESC
                      LIMIT ?1"
                 )?;
-                let todo_iter = stmt.query_map([limit as i64], |row| Todo::from_row(row))?;
+                let todo_iter = stmt.query_map([limit as i64], Todo::from_row)?;
                 for todo in todo_iter {
                     todos.push(todo?);
                 }
@@ -311,33 +2066,278 @@ impl Database {
 
     pub fn get_parent_title(&self, parent_id: Option<i64>) -> anyhow::Result<Option<String>> {
         match parent_id {
-            Some(id) => {
-                let mut stmt = self.conn.prepare("SELECT title FROM todos WHERE id = ?1")?;
-                let mut rows = stmt.query_map([id], |row| {
-                    let title: String = row.get(0)?;
-                    Ok(title)
-                })?;
-                
-                match rows.next() {
-                    Some(row) => Ok(Some(row?)),
-                    None => Ok(None),
-                }
-            },
+            Some(id) => Ok(self.conn
+                .prepare_cached("SELECT title FROM todos WHERE id = ?1")?
+                .query_row([id], |row| row.get(0))
+                .optional()?),
             None => Ok(None)
         }
     }
 
+    pub fn get_ui_state(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.conn
+            .query_row("SELECT value FROM ui_state WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Record a new session starting (and, until updated, ending) at `at`
+    pub fn start_session(&self, at: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.conn.execute("INSERT INTO sessions (started_at, ended_at) VALUES (?1, ?1)", params![at])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_session(&self, session: &Session) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET ended_at = ?1, created = ?2, completed = ?3 WHERE id = ?4",
+            params![session.ended_at, session.created, session.completed, session.id],
+        )?;
+        Ok(())
+    }
+
+    /// Sessions that ended at or after `since`, oldest first
+    pub fn get_sessions_since(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, started_at, ended_at, created, completed FROM sessions WHERE ended_at >= ?1 ORDER BY started_at"
+        )?;
+        let rows = stmt.query_map(params![since], |row| {
+            Ok(Session {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                ended_at: row.get(2)?,
+                created: row.get(3)?,
+                completed: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>>>()?)
+    }
+
+    pub fn set_ui_state(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO ui_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_saved_filters(&self) -> anyhow::Result<Vec<SavedFilter>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, query FROM filters ORDER BY name")?;
+        let filter_iter = stmt.query_map([], |row| {
+            Ok(SavedFilter {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                query: row.get(2)?,
+            })
+        })?;
+
+        let mut filters = Vec::new();
+        for filter in filter_iter {
+            filters.push(filter?);
+        }
+        Ok(filters)
+    }
+
+    /// Save a named filter, replacing the query of an existing filter with the same name
+    pub fn save_filter(&self, name: &str, query: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO filters (name, query) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET query = excluded.query",
+            params![name, query],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_filter(&self, id: i64) -> anyhow::Result<()> {
+        self.conn.execute("DELETE FROM filters WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn get_templates(&self) -> anyhow::Result<Vec<SavedTemplate>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, items, created_at FROM templates ORDER BY name")?;
+        let template_iter = stmt.query_map([], |row| {
+            Ok(SavedTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                items: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+
+        let mut templates = Vec::new();
+        for template in template_iter {
+            templates.push(template?);
+        }
+        Ok(templates)
+    }
+
+    /// Create a template, or replace the items of an existing one with the same name
+    pub fn save_template(&self, name: &str, items: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO templates (name, items, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET items = excluded.items, created_at = excluded.created_at",
+            params![name, items, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_template(&self, id: i64) -> anyhow::Result<()> {
+        self.conn.execute("DELETE FROM templates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
     /// Force a checkpoint to write WAL data to main database file
     pub fn checkpoint(&self) -> anyhow::Result<()> {
         let mut stmt = self.conn.prepare("PRAGMA wal_checkpoint(PASSIVE)")?;
         let _rows: Vec<Result<(), rusqlite::Error>> = stmt.query_map([], |_| Ok(()))?.collect();
-        Ok(())
+        self.write_mirror()
     }
 
     /// Force a full checkpoint and truncate WAL file (for app shutdown)
     pub fn checkpoint_and_close(&self) -> anyhow::Result<()> {
         let mut stmt = self.conn.prepare("PRAGMA wal_checkpoint(TRUNCATE)")?;
         let _rows: Vec<Result<(), rusqlite::Error>> = stmt.query_map([], |_| Ok(()))?.collect();
+        self.write_mirror()
+    }
+
+    /// WAL pages written before SQLite checkpoints by itself (`[checkpoint] wal_pages`)
+    pub fn set_wal_autocheckpoint(&self, pages: u32) -> anyhow::Result<()> {
+        self.conn.pragma_update(None, "wal_autocheckpoint", pages)?;
+        Ok(())
+    }
+
+    pub fn wal_autocheckpoint(&self) -> anyhow::Result<u32> {
+        Ok(self.conn.pragma_query_value(None, "wal_autocheckpoint", |row| row.get(0))?)
+    }
+
+    pub fn get_attachments(&self, todo_id: i64) -> anyhow::Result<Vec<Attachment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, todo_id, target, created_at FROM attachments WHERE todo_id = ?1 ORDER BY id"
+        )?;
+        let attachment_iter = stmt.query_map([todo_id], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                todo_id: row.get(1)?,
+                target: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+
+        let mut attachments = Vec::new();
+        for attachment in attachment_iter {
+            attachments.push(attachment?);
+        }
+        Ok(attachments)
+    }
+
+    /// Number of attachments per todo, for row indicators (todos without attachments are absent)
+    pub fn get_attachment_counts(&self) -> anyhow::Result<HashMap<i64, usize>> {
+        let mut stmt = self.conn.prepare("SELECT todo_id, COUNT(*) FROM attachments GROUP BY todo_id")?;
+        let count_iter = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as usize)))?;
+
+        let mut counts = HashMap::new();
+        for count in count_iter {
+            let (todo_id, count) = count?;
+            counts.insert(todo_id, count);
+        }
+        Ok(counts)
+    }
+
+    pub fn add_attachment(&self, todo_id: i64, target: &str) -> anyhow::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO attachments (todo_id, target, created_at) VALUES (?1, ?2, ?3)",
+            params![todo_id, target, Utc::now()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn delete_attachment(&self, id: i64) -> anyhow::Result<()> {
+        self.conn.execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// A todo's notes, oldest first
+    pub fn get_notes(&self, todo_id: i64) -> anyhow::Result<Vec<Note>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, todo_id, body, created_at FROM notes WHERE todo_id = ?1 ORDER BY created_at, id"
+        )?;
+        let notes = stmt.query_map([todo_id], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                todo_id: row.get(1)?,
+                body: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        Ok(notes.collect::<Result<Vec<_>>>()?)
+    }
+
+    /// Number of notes per todo, for row indicators (todos without notes are absent)
+    pub fn get_note_counts(&self) -> anyhow::Result<HashMap<i64, usize>> {
+        let mut stmt = self.conn.prepare("SELECT todo_id, COUNT(*) FROM notes GROUP BY todo_id")?;
+        let counts = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(counts.collect::<Result<HashMap<_, _>>>()?)
+    }
+
+    /// Record a finished pomodoro: `started_at` to `ended_at` spent on the todo
+    pub fn log_pomodoro(&self, todo_id: i64, started_at: DateTime<Utc>, ended_at: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO pomodoros (todo_id, started_at, ended_at) VALUES (?1, ?2, ?3)",
+            params![todo_id, started_at, ended_at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Finished pomodoros per todo, for todos that have any
+    pub fn get_pomodoro_counts(&self) -> anyhow::Result<HashMap<i64, usize>> {
+        let mut stmt = self.conn.prepare("SELECT todo_id, COUNT(*) FROM pomodoros GROUP BY todo_id")?;
+        let counts = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(counts.collect::<Result<HashMap<_, _>>>()?)
+    }
+
+    pub fn add_note(&self, todo_id: i64, body: &str) -> anyhow::Result<i64> {
+        if self.get_todo_by_id(todo_id)?.is_none() {
+            return Err(anyhow::anyhow!("Todo {} not found", todo_id));
+        }
+        self.conn.execute(
+            "INSERT INTO notes (todo_id, body, created_at) VALUES (?1, ?2, ?3)",
+            params![todo_id, body, Utc::now()],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.emit(TodoEvent::Updated(todo_id));
+        Ok(id)
+    }
+
+    /// Insert todos as-is, keeping their ids, timestamps and statuses (used for snapshot
+    /// exports and mirror rebuilds)
+    pub fn insert_todos_with_ids(&self, todos: &[Todo], statuses: &HashMap<i64, Status>) -> anyhow::Result<()> {
+        let tx = self.begin()?;
+        // Children may come before their parents, so only check references at commit
+        tx.pragma_update(None, "defer_foreign_keys", true)?;
+        for todo in todos {
+            tx.execute(
+                "INSERT INTO todos (id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at, status)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    todo.id,
+                    todo.title,
+                    todo.description,
+                    todo.created_at,
+                    todo.completed_at,
+                    todo.due_by,
+                    todo.parent_id,
+                    todo.hidden,
+                    todo.start_at,
+                    statuses.get(&todo.id).and_then(Status::stored)
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Leave WAL mode so the database is a single self-contained file
+    pub fn make_standalone(&self) -> anyhow::Result<()> {
+        self.conn.pragma_update(None, "journal_mode", "DELETE")?;
         Ok(())
     }
 
@@ -348,6 +2348,122 @@ impl Database {
         Ok((0, 0))
     }
 
+    /// Todos matching a filter expression, newest first
+    pub fn query_todos(&self, filter: &Filter) -> anyhow::Result<Vec<Todo>> {
+        let (where_clause, values) = self.filter_to_sql(filter)?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
+             FROM todos
+             WHERE {}
+             ORDER BY created_at DESC",
+            where_clause
+        ))?;
+
+        let todo_iter = stmt.query_map(params_from_iter(values), Todo::from_row)?;
+
+        let mut todos = Vec::new();
+        for todo in todo_iter {
+            todos.push(todo?);
+        }
+
+        Ok(todos)
+    }
+
+    /// Compile a filter into a WHERE clause with positional parameters. Fails on a
+    /// duration that reaches past the dates chrono can represent.
+    fn filter_to_sql(&self, filter: &Filter) -> anyhow::Result<(String, Vec<Value>)> {
+        let now = Utc::now();
+        let out_of_range = || anyhow::Error::new(DurationOutOfRange);
+        // Timestamps are compared as text, so format them the way rusqlite stores DateTime<Utc>
+        let timestamp = |dt: DateTime<Utc>| Value::Text(dt.format("%F %T%.f%:z").to_string());
+
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+
+        for condition in &filter.conditions {
+            let clause = match &condition.term {
+                FilterTerm::DueBefore(duration) => {
+                    values.push(timestamp(now.checked_add_signed(*duration).ok_or_else(out_of_range)?));
+                    "(due_by IS NOT NULL AND due_by < ?)"
+                }
+                FilterTerm::DueAfter(duration) => {
+                    values.push(timestamp(now.checked_add_signed(*duration).ok_or_else(out_of_range)?));
+                    "(due_by IS NOT NULL AND due_by > ?)"
+                }
+                FilterTerm::Overdue => {
+                    values.push(timestamp(now));
+                    "(due_by IS NOT NULL AND due_by < ? AND completed_at IS NULL)"
+                }
+                FilterTerm::DueWithin(days) => {
+                    values.push(timestamp(dates::local_midnight_in(*days)));
+                    "(due_by IS NOT NULL AND due_by < ? AND completed_at IS NULL)"
+                }
+                FilterTerm::NoDueDate => "(due_by IS NULL)",
+                FilterTerm::Stale(period) => {
+                    let period = period.or_else(|| Duration::try_days(self.stale_days)).ok_or_else(out_of_range)?;
+                    values.push(timestamp(now.checked_sub_signed(period).ok_or_else(out_of_range)?));
+                    "(completed_at IS NULL AND updated_at < ?)"
+                }
+                FilterTerm::Context(Some(context)) => {
+                    values.push(Value::Text(context.clone()));
+                    "(context = ?)"
+                }
+                FilterTerm::Context(None) => "(context IS NULL)",
+                FilterTerm::Energy(Some(energy)) => {
+                    values.push(Value::Text(energy.as_str().to_string()));
+                    "(energy = ?)"
+                }
+                FilterTerm::Energy(None) => "(energy IS NULL)",
+                FilterTerm::Someday => "(someday = 1)",
+                FilterTerm::Status(Status::Open) => "(completed_at IS NULL)",
+                FilterTerm::Status(Status::Done) => "(completed_at IS NOT NULL AND (status IS NULL OR status <> 'cancelled'))",
+                FilterTerm::Status(status) => {
+                    values.push(Value::Text(status.as_str().to_string()));
+                    if status.is_closed() { "(completed_at IS NOT NULL AND status = ?)" } else { "(completed_at IS NULL AND status = ?)" }
+                }
+                FilterTerm::Waiting(None) => "(waiting_on IS NOT NULL)",
+                FilterTerm::Waiting(Some(on)) => {
+                    values.push(Value::Text(format!("(?i){}", regex::escape(on))));
+                    "(waiting_on REGEXP ?)"
+                }
+                FilterTerm::Parent(Some(parent_id)) => {
+                    values.push(Value::Integer(*parent_id));
+                    "(parent_id = ?)"
+                }
+                FilterTerm::Parent(None) => "(parent_id IS NULL)",
+                FilterTerm::Tag(tag) => {
+                    // A tag is a `#word` token; the next character must not continue the word
+                    let pattern = format!(r"(?i)(^|\s)#{}([^\w-]|$)", regex::escape(tag));
+                    values.push(Value::Text(pattern.clone()));
+                    values.push(Value::Text(pattern));
+                    "(title REGEXP ? OR description REGEXP ?)"
+                }
+                FilterTerm::Regex(pattern) => {
+                    let pattern = format!("(?i){}", pattern);
+                    values.push(Value::Text(pattern.clone()));
+                    values.push(Value::Text(pattern));
+                    "(title REGEXP ? OR description REGEXP ?)"
+                }
+                FilterTerm::Text(text) => {
+                    let pattern = format!("(?i){}", regex::escape(text));
+                    values.push(Value::Text(pattern.clone()));
+                    values.push(Value::Text(pattern));
+                    "(title REGEXP ? OR description REGEXP ?)"
+                }
+            };
+
+            // A comparison with a NULL column is NULL, and NOT NULL is still NULL: count it
+            // as a non-match first, so `-@home` keeps the todos without a context
+            if condition.negated {
+                clauses.push(format!("NOT COALESCE({}, 0)", clause));
+            } else {
+                clauses.push(clause.to_string());
+            }
+        }
+
+        Ok((clauses.join(" AND "), values))
+    }
+
     /// Search todos by regex pattern (case-insensitive) in title or description
     pub fn search_todos(&self, pattern: &str) -> anyhow::Result<Vec<Todo>> {
         // Return empty if pattern is empty
@@ -371,12 +2487,12 @@ impl Database {
 
         // Get all todos from database
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden
+            "SELECT id, title, description, created_at, completed_at, due_by, parent_id, hidden, start_at
              FROM todos
              ORDER BY created_at DESC"
         )?;
 
-        let todo_iter = stmt.query_map([], |row| Todo::from_row(row))?;
+        let todo_iter = stmt.query_map([], Todo::from_row)?;
 
         let mut matching_todos = Vec::new();
         for todo_result in todo_iter {
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use unicode_width::UnicodeWidthStr;
+
+/// When a due date given as a day without a time is due, unless `[dates] due_time`
+/// says otherwise: the last second of that local day
+pub const END_OF_DAY: NaiveTime = match NaiveTime::from_hms_opt(23, 59, 59) {
+    Some(time) => time,
+    None => NaiveTime::MIN,
+};
+
+/// Parse a due date from user input: relative ("2d", "1w", "3h", "30m", bare days)
+/// or absolute ("YYYY-MM-DD", "YYYY-MM-DD HH:MM"), with a day due at its end
+pub fn parse_due_date(input: &str) -> Option<DateTime<Utc>> {
+    parse_due_date_at(input, END_OF_DAY)
+}
+
+/// Parse a due date, with a day without a time due at `due_time` local time
+pub fn parse_due_date_at(input: &str, due_time: NaiveTime) -> Option<DateTime<Utc>> {
+    parse_due_date_in(input, due_time, &Local)
+}
+
+/// Parse a due date with absolute dates read as wall-clock time in `tz`
+pub fn parse_due_date_in<Tz: TimeZone>(input: &str, due_time: NaiveTime, tz: &Tz) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    // Try relative date parsing first (e.g., "2d", "1w", "3h", "30m")
+    if let Some(duration) = parse_relative_duration(input) {
+        return Utc::now().checked_add_signed(duration);
+    }
+
+    // Try absolute date parsing
+    // Format: "YYYY-MM-DD" or "YYYY-MM-DD HH:MM"
+    if let Ok(day) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return local_to_utc(day, due_time, tz);
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        return local_to_utc(dt.date(), dt.time(), tz);
+    }
+
+    None
+}
+
+/// `day` at `time` on the wall clock in `tz`. A time skipped when the clocks go forward
+/// moves on an hour; one that happens twice when they go back is the first of the two.
+pub fn local_to_utc<Tz: TimeZone>(day: NaiveDate, time: NaiveTime, tz: &Tz) -> Option<DateTime<Utc>> {
+    let at = day.and_time(time);
+    tz.from_local_datetime(&at)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(at + Duration::hours(1))).earliest())
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Parse a relative duration such as "2d", "1w", "3h" or "30m" (a bare number means days).
+/// A duration too long to count forward or back from now is `None`, like any other typo.
+pub fn parse_relative_duration(input: &str) -> Option<Duration> {
+    let duration = parse_duration_units(input)?;
+    let now = Utc::now();
+    now.checked_add_signed(duration)?;
+    now.checked_sub_signed(duration)?;
+    Some(duration)
+}
+
+fn parse_duration_units(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+
+    if input.is_empty() {
+        return None;
+    }
+
+    // First, try parsing as a bare number (default to days)
+    if let Ok(number) = input.parse::<i64>() {
+        return Duration::try_days(number);
+    }
+
+    // Extract number and unit; the unit may be any character, so split on a char boundary
+    let unit = input.chars().last()?;
+    let number_str = &input[..input.len() - unit.len_utf8()];
+    if number_str.is_empty() {
+        return None;
+    }
+
+    let number: i64 = number_str.parse().ok()?;
+
+    match unit {
+        'm' => Duration::try_minutes(number),
+        'h' => Duration::try_hours(number),
+        'd' => Duration::try_days(number),
+        'w' => Duration::try_weeks(number),
+        _ => None,
+    }
+}
+
+/// Parse a start ("not before") date: like `parse_due_date`, but a day without a time
+/// ("YYYY-MM-DD", "3d", "1w") starts at local midnight rather than ending at 23:59:59
+pub fn parse_start_date(input: &str) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    let day = if let Some(duration) = parse_relative_duration(input) {
+        if duration.num_seconds() % Duration::days(1).num_seconds() != 0 {
+            return Utc::now().checked_add_signed(duration);
+        }
+        Local::now().checked_add_signed(duration)?.date_naive()
+    } else if let Ok(day) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        day
+    } else {
+        let dt = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M").ok()?;
+        return local_to_utc(dt.date(), dt.time(), &Local);
+    };
+    local_to_utc(day, NaiveTime::MIN, &Local)
+}
+
+/// How far off a due date is, for rows: "in 3d", "in 5h", "2d overdue" or "due now".
+/// `compact` leaves out the words ("3d", "-2d", "now") for narrow rows.
+pub fn format_relative_due(due: DateTime<Utc>, now: DateTime<Utc>, compact: bool) -> String {
+    let minutes = (due - now).num_minutes();
+    let away = minutes.abs();
+    if away == 0 {
+        return if compact { "now" } else { "due now" }.to_string();
+    }
+    const DAY: i64 = 24 * 60;
+    let amount = if away < 60 {
+        format!("{}m", away)
+    } else if away < DAY {
+        format!("{}h", away / 60)
+    } else if away < 14 * DAY {
+        format!("{}d", away / DAY)
+    } else if away < 365 * DAY {
+        format!("{}w", away / (7 * DAY))
+    } else {
+        format!("{}y", away / (365 * DAY))
+    };
+    match (minutes > 0, compact) {
+        (true, false) => format!("in {}", amount),
+        (true, true) => amount,
+        (false, false) => format!("{} overdue", amount),
+        (false, true) => format!("-{}", amount),
+    }
+}
+
+/// The widest a date printed with the chrono `format` gets, so a column fits every
+/// month and weekday name and both halves of the day
+pub fn format_width(format: &str) -> usize {
+    (1..=12)
+        .filter_map(|month| NaiveDate::from_ymd_opt(2001, month, 22 + month % 7))
+        .flat_map(|day| [9, 22].map(|hour| day.and_hms_opt(hour, 59, 59)))
+        .flatten()
+        .map(|at| UnicodeWidthStr::width(at.format(format).to_string().as_str()))
+        .max()
+        .unwrap_or(0)
+}
+
+/// A start date the way `parse_start_date` reads it back: the local day, with the
+/// time only when it isn't midnight
+pub fn format_start_date(start_at: DateTime<Utc>) -> String {
+    let local = start_at.with_timezone(&Local);
+    if local.time() == NaiveTime::MIN {
+        local.format("%Y-%m-%d").to_string()
+    } else {
+        local.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Local midnight `days` days from today: the end of today for 1, of the coming week for 7
+pub fn local_midnight_in(days: i64) -> DateTime<Utc> {
+    let day = Local::now().date_naive() + Duration::days(days);
+    local_to_utc(day, NaiveTime::MIN, &Local).unwrap_or_else(|| Utc::now() + Duration::days(days))
+}
@@ -0,0 +1,111 @@
+use crate::database::Database;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+
+/// Creation times this far ahead of the clock are tolerated (another machine's skew)
+const CLOCK_SKEW: Duration = Duration::hours(1);
+/// Due dates further away than this, either way, are taken for typos
+const FAR_DUE_YEARS: i64 = 20;
+
+/// Something wrong with one of a todo's dates
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    CreatedUnreadable(String),
+    CreatedInFuture(DateTime<Utc>),
+    CompletedUnreadable(String),
+    /// Completed before it was created
+    CompletedBeforeCreated(DateTime<Utc>),
+    DueUnreadable(String),
+    /// Due decades away, most likely a mistyped year
+    DueFarOff(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub todo_id: i64,
+    pub title: String,
+    pub anomaly: Anomaly,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let date = |at: &DateTime<Utc>| at.format("%Y-%m-%d %H:%M").to_string();
+        let problem = match &self.anomaly {
+            Anomaly::CreatedUnreadable(raw) => format!("created_at '{}' is not a date", raw),
+            Anomaly::CreatedInFuture(at) => format!("created_at {} is in the future", date(at)),
+            Anomaly::CompletedUnreadable(raw) => format!("completed_at '{}' is not a date", raw),
+            Anomaly::CompletedBeforeCreated(at) => format!("completed_at {} is before it was created", date(at)),
+            Anomaly::DueUnreadable(raw) => format!("due_by '{}' is not a date", raw),
+            Anomaly::DueFarOff(at) => format!("due_by {} is decades away", date(at)),
+        };
+        write!(f, "Todo {} '{}': {}", self.todo_id, self.title, problem)
+    }
+}
+
+/// Dates that break sorting and due colors: unreadable values, creation in the
+/// future, completion before creation, and due dates decades away
+pub fn check(database: &Database, now: DateTime<Utc>) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    for row in database.get_stored_dates()? {
+        let finding = |anomaly| Finding { todo_id: row.id, title: row.title.clone(), anomaly };
+        let created_at = match &row.created_at {
+            Ok(Some(at)) if *at > now + CLOCK_SKEW => {
+                findings.push(finding(Anomaly::CreatedInFuture(*at)));
+                None
+            }
+            Ok(Some(at)) => Some(*at),
+            Ok(None) => {
+                findings.push(finding(Anomaly::CreatedUnreadable("NULL".to_string())));
+                None
+            }
+            Err(raw) => {
+                findings.push(finding(Anomaly::CreatedUnreadable(raw.clone())));
+                None
+            }
+        };
+        match &row.completed_at {
+            Ok(Some(done)) if created_at.is_some_and(|created| *done < created) => findings.push(finding(Anomaly::CompletedBeforeCreated(*done))),
+            Err(raw) => findings.push(finding(Anomaly::CompletedUnreadable(raw.clone()))),
+            Ok(_) => {}
+        }
+        match &row.due_by {
+            Ok(Some(due)) if (*due - now).num_days().abs() > FAR_DUE_YEARS * 365 => findings.push(finding(Anomaly::DueFarOff(*due))),
+            Err(raw) => findings.push(finding(Anomaly::DueUnreadable(raw.clone()))),
+            Ok(_) => {}
+        }
+    }
+    Ok(findings)
+}
+
+/// Repair every todo with findings: a bad creation time becomes the completion time
+/// (or now), a bad completion time the creation time, and a bad due date is cleared.
+/// Returns the number of todos changed.
+pub fn fix(database: &Database, now: DateTime<Utc>) -> anyhow::Result<usize> {
+    let findings = check(database, now)?;
+    let mut fixed = 0;
+    for row in database.get_stored_dates()? {
+        let anomalies: Vec<&Anomaly> = findings.iter().filter(|finding| finding.todo_id == row.id).map(|finding| &finding.anomaly).collect();
+        if anomalies.is_empty() {
+            continue;
+        }
+        let has = |predicate: fn(&Anomaly) -> bool| anomalies.iter().any(|anomaly| predicate(anomaly));
+        let readable_completed_at = row.completed_at.clone().ok().flatten();
+        let created_at = match row.created_at.clone() {
+            Ok(Some(created)) if !has(|a| matches!(a, Anomaly::CreatedInFuture(_))) => created,
+            _ => readable_completed_at.filter(|done| *done <= now).unwrap_or(now),
+        };
+        let completed_at = match row.completed_at {
+            Ok(done) => done.map(|done| done.max(created_at)),
+            // Still completed, just at an unknown time
+            Err(_) => Some(created_at),
+        };
+        let due_by = if has(|a| matches!(a, Anomaly::DueUnreadable(_) | Anomaly::DueFarOff(_))) {
+            None
+        } else {
+            row.due_by.clone().ok().flatten()
+        };
+        database.set_todo_dates(row.id, created_at, completed_at, due_by)?;
+        fixed += 1;
+    }
+    Ok(fixed)
+}
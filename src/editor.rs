@@ -0,0 +1,126 @@
+use crate::database::{Note, Todo};
+use crate::dates;
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Create a fresh directory for this session's editor files under $XDG_RUNTIME_DIR,
+/// or the system temp directory, that only the current user can read. Creating it
+/// fails rather than reusing a directory someone else prepared.
+pub fn create_session_dir() -> io::Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let dir = base.join(format!("tododb-{}-{:08x}", std::process::id(), nanos));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Remove the session directory once nothing is left in it; files kept after a
+/// failed save stay, with their directory
+pub fn remove_session_dir(dir: &Path) {
+    let _ = fs::remove_dir(dir);
+}
+
+/// The due date as shown in the editor file (local time, minute precision)
+fn format_due_date(due_by: Option<DateTime<Utc>>) -> String {
+    match due_by {
+        Some(due_by) => due_by.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+        None => "Not set".to_string(),
+    }
+}
+
+/// Render a todo as the markdown document edited in the external editor. Notes are
+/// listed oldest first for reference; they are added in the app, not by editing them here.
+pub fn todo_to_markdown(todo: &Todo, notes: &[Note]) -> String {
+    format!(
+        "# {}\n\n## Due Date\n{}\n\n## Description\n{}\n\n{}## Metadata\n- **ID:** {}\n- **Status:** {}\n- **Created:** {} UTC\n",
+        todo.title,
+        format_due_date(todo.due_by),
+        if todo.description.trim().is_empty() { "(No description)" } else { &todo.description },
+        notes_section(notes),
+        todo.id,
+        if todo.is_completed() { "✓ Completed" } else { "○ Incomplete" },
+        todo.created_at.format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+/// `## Notes` with one bullet per note, continuation lines indented under it
+fn notes_section(notes: &[Note]) -> String {
+    if notes.is_empty() {
+        return String::new();
+    }
+    let mut section = "## Notes\n".to_string();
+    for note in notes {
+        let mut lines = note.body.lines();
+        let first = lines.next().unwrap_or_default();
+        section.push_str(&format!("- **{}** {}\n", note.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"), first));
+        for line in lines {
+            section.push_str(&format!("  {}\n", line));
+        }
+    }
+    section.push('\n');
+    section
+}
+
+/// Read back an edited todo document as (title, description, due date).
+/// An untouched due date line keeps `original`'s exact due date rather than the
+/// minute-rounded text, so saving without edits doesn't shift it. A due day without
+/// a time is due at `due_time`.
+pub fn parse_todo_markdown(content: &str, original: &Todo, due_time: NaiveTime) -> Result<(String, String, Option<DateTime<Utc>>), String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut title = String::new();
+    let mut description = String::new();
+    let mut due_date = None;
+
+    let mut in_description = false;
+    let mut in_due_date = false;
+
+    for line in lines {
+        if line.starts_with("# ") && title.is_empty() {
+            title = line[2..].trim().to_string();
+        } else if line.starts_with("## Due Date") {
+            in_due_date = true;
+            in_description = false;
+        } else if line.starts_with("## Description") {
+            in_description = true;
+            in_due_date = false;
+        } else if line.starts_with("## Notes") || line.starts_with("## Metadata") {
+            // Stop collecting description when we hit the notes or metadata section
+            in_description = false;
+            in_due_date = false;
+        } else if in_due_date && !line.trim().is_empty() {
+            // Parse due date from the line
+            let date_str = line.trim();
+            if date_str == format_due_date(original.due_by) {
+                due_date = original.due_by;
+            } else if date_str != "Not set" {
+                due_date = dates::parse_due_date_at(date_str, due_time);
+                // If parsing failed and it wasn't "Not set", return error
+                if due_date.is_none() {
+                    return Err(format!("Invalid due date format: '{}'. Expected format: 'YYYY-MM-DD HH:MM', '2d', '1w', etc., or 'Not set'", date_str));
+                }
+            }
+            in_due_date = false; // Only parse first non-empty line
+        } else if in_description {
+            // Collect all lines in the description section, including empty lines and headers
+            if !description.is_empty() {
+                description.push('\n');
+            }
+            if line.trim() != "(No description)" {
+                description.push_str(line);
+            } else {
+                // Don't add the "(No description)" placeholder
+                description.pop(); // Remove the newline we just added
+            }
+        }
+    }
+
+    Ok((title, description.trim().to_string(), due_date))
+}
@@ -0,0 +1,249 @@
+use crate::database::{Database, Todo};
+use crate::status::Status;
+use crate::tags;
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::{fs, path::Path};
+
+/// What to leave out of a shared snapshot
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotOptions {
+    /// Replace every description with an empty string
+    pub strip_descriptions: bool,
+    /// Drop hidden todos along with everything beneath them
+    pub exclude_hidden: bool,
+    /// Only export this todo and its descendants
+    pub root: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct JsonSnapshot<'a> {
+    exported_at: DateTime<Utc>,
+    todos: &'a [Todo],
+    /// Short codes (`WEB-12`) by todo id, when the database uses them
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    short_codes: HashMap<i64, String>,
+}
+
+/// Select and sanitize the todos that go into a snapshot
+pub fn snapshot_todos(database: &Database, options: &SnapshotOptions) -> anyhow::Result<Vec<Todo>> {
+    let all_todos = database.get_all_todos()?;
+
+    if let Some(root) = options.root
+        && !all_todos.iter().any(|todo| todo.id == root) {
+        return Err(anyhow::anyhow!("Todo {} not found", root));
+    }
+
+    let mut children: HashMap<Option<i64>, Vec<&Todo>> = HashMap::new();
+    for todo in &all_todos {
+        children.entry(todo.parent_id).or_default().push(todo);
+    }
+
+    // Walk down from the roots so excluded todos take their whole subtree with them
+    let mut included: HashSet<i64> = HashSet::new();
+    let mut frontier: Vec<&Todo> = match options.root {
+        Some(root) => all_todos.iter().filter(|todo| todo.id == root).collect(),
+        None => children.get(&None).cloned().unwrap_or_default(),
+    };
+    while let Some(todo) = frontier.pop() {
+        if (options.exclude_hidden && todo.hidden) || !included.insert(todo.id) {
+            continue;
+        }
+        if let Some(todo_children) = children.get(&Some(todo.id)) {
+            frontier.extend(todo_children);
+        }
+    }
+
+    Ok(all_todos
+        .into_iter()
+        .filter(|todo| included.contains(&todo.id))
+        .map(|mut todo| {
+            if options.strip_descriptions {
+                todo.description.clear();
+            }
+            // The exported root becomes top-level so the snapshot has no dangling parent
+            if Some(todo.id) == options.root {
+                todo.parent_id = None;
+            }
+            todo
+        })
+        .collect())
+}
+
+/// Write a snapshot to `path`: JSON for `.json`, otherwise a standalone SQLite database.
+/// The file is marked read-only once written. Returns the number of exported todos.
+pub fn export_snapshot(database: &Database, path: &Path, options: &SnapshotOptions) -> anyhow::Result<usize> {
+    if path.exists() {
+        return Err(anyhow::anyhow!("{} already exists", path.display()));
+    }
+
+    let todos = snapshot_todos(database, options)?;
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        let mut short_codes = database.refresh_short_codes()?;
+        short_codes.retain(|id, _| todos.iter().any(|todo| todo.id == *id));
+        let snapshot = JsonSnapshot { exported_at: Utc::now(), todos: &todos, short_codes };
+        fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+    } else if let Err(e) = write_sqlite_snapshot(path, &todos, &database.get_statuses()?) {
+        // Don't leave a half-written database behind
+        let _ = fs::remove_file(path);
+        return Err(e);
+    }
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)?;
+
+    Ok(todos.len())
+}
+
+/// A column of the CSV export
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvColumn {
+    Id,
+    Title,
+    /// Titles of the ancestors, outermost first, joined with " / "
+    ParentPath,
+    ParentId,
+    Status,
+    Created,
+    Completed,
+    Due,
+    /// The `#tags` of title and description, space separated
+    Tags,
+    Description,
+}
+
+impl CsvColumn {
+    const ALL: &[(&str, CsvColumn)] = &[
+        ("id", CsvColumn::Id),
+        ("title", CsvColumn::Title),
+        ("parent_path", CsvColumn::ParentPath),
+        ("parent_id", CsvColumn::ParentId),
+        ("status", CsvColumn::Status),
+        ("created", CsvColumn::Created),
+        ("completed", CsvColumn::Completed),
+        ("due", CsvColumn::Due),
+        ("tags", CsvColumn::Tags),
+        ("description", CsvColumn::Description),
+    ];
+
+    /// Columns written when none are chosen
+    pub const DEFAULT: &[CsvColumn] = &[
+        CsvColumn::Id,
+        CsvColumn::Title,
+        CsvColumn::ParentPath,
+        CsvColumn::Created,
+        CsvColumn::Completed,
+        CsvColumn::Due,
+        CsvColumn::Tags,
+    ];
+
+    fn name(self) -> &'static str {
+        CsvColumn::ALL.iter().find(|(_, column)| *column == self).map_or("", |(name, _)| name)
+    }
+
+    /// A comma-separated column list such as `id,title,completed`
+    pub fn parse_list(list: &str) -> anyhow::Result<Vec<CsvColumn>> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                CsvColumn::ALL
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(name) || known.replace('_', "-").eq_ignore_ascii_case(name))
+                    .map(|(_, column)| *column)
+                    .ok_or_else(|| {
+                        let known: Vec<&str> = CsvColumn::ALL.iter().map(|(known, _)| *known).collect();
+                        anyhow::anyhow!("Unknown column '{}' (columns: {})", name, known.join(", "))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The todos as CSV with a header row, oldest first. Dates are local `YYYY-MM-DD HH:MM`,
+/// which spreadsheets read as dates.
+pub fn todos_to_csv(todos: &[Todo], columns: &[CsvColumn]) -> String {
+    let by_id: HashMap<i64, &Todo> = todos.iter().map(|todo| (todo.id, todo)).collect();
+    let date = |at: Option<DateTime<Utc>>| at.map_or(String::new(), |at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string());
+
+    let mut sorted: Vec<&Todo> = todos.iter().collect();
+    sorted.sort_by_key(|todo| (todo.created_at, todo.id));
+
+    let mut csv = csv_row(columns.iter().map(|column| column.name().to_string()));
+    for todo in sorted {
+        csv.push_str(&csv_row(columns.iter().map(|column| match column {
+            CsvColumn::Id => todo.id.to_string(),
+            CsvColumn::Title => todo.title.clone(),
+            CsvColumn::ParentPath => {
+                let mut path = Vec::new();
+                let mut parent_id = todo.parent_id;
+                while let Some(parent) = parent_id.and_then(|id| by_id.get(&id)) {
+                    path.push(parent.title.as_str());
+                    parent_id = parent.parent_id;
+                    if path.len() > todos.len() {
+                        break;
+                    }
+                }
+                path.reverse();
+                path.join(" / ")
+            }
+            CsvColumn::ParentId => todo.parent_id.map_or(String::new(), |id| id.to_string()),
+            CsvColumn::Status => if todo.is_completed() { "done" } else { "open" }.to_string(),
+            CsvColumn::Created => date(Some(todo.created_at)),
+            CsvColumn::Completed => date(todo.completed_at),
+            CsvColumn::Due => date(todo.due_by),
+            CsvColumn::Tags => {
+                let mut todo_tags = tags::extract_tags(&todo.title);
+                todo_tags.extend(tags::extract_tags(&todo.description));
+                let mut seen = HashSet::new();
+                todo_tags.retain(|tag| seen.insert(tag.to_lowercase()));
+                todo_tags.join(" ")
+            }
+            CsvColumn::Description => todo.description.clone(),
+        })));
+    }
+    csv
+}
+
+/// One RFC 4180 line: fields with commas, quotes or line breaks are quoted
+fn csv_row(fields: impl Iterator<Item = String>) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Move a todo and its subtree from `source` into `target` under `new_parent`, or copy it
+/// when `keep_original` is set. The todos get new ids in the target; timestamps, completion
+/// and attachments are kept. The copy is written before the original is deleted, so a
+/// failure part way never loses todos. Returns the new id of the top todo and the number moved.
+pub fn transfer_subtree(source: &Database, target: &Database, id: i64, new_parent: Option<i64>, keep_original: bool) -> anyhow::Result<(i64, usize)> {
+    let todos = source.get_subtree(id)?;
+    let mut attachments = Vec::new();
+    for todo in &todos {
+        attachments.extend(source.get_attachments(todo.id)?);
+    }
+
+    let new_id = target.import_subtree(&todos, &source.get_statuses()?, &attachments, new_parent)?;
+    if !keep_original {
+        source.delete_subtree(id)?;
+    }
+    Ok((new_id, todos.len()))
+}
+
+fn write_sqlite_snapshot(path: &Path, todos: &[Todo], statuses: &HashMap<i64, Status>) -> anyhow::Result<()> {
+    let snapshot_db = Database::new(&path.to_string_lossy())?;
+    snapshot_db.insert_todos_with_ids(todos, statuses)?;
+    snapshot_db.make_standalone()?;
+    Ok(())
+}
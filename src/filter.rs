@@ -0,0 +1,287 @@
+use crate::context::{self, Energy};
+use crate::database::Todo;
+use crate::dates;
+use crate::status::Status;
+use chrono::{DateTime, Duration, Utc};
+use regex::RegexBuilder;
+use std::collections::{HashMap, HashSet};
+
+/// A single condition in a filter expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterTerm {
+    /// `due<7d`: due before now + duration (includes overdue)
+    DueBefore(Duration),
+    /// `due>7d`: due after now + duration
+    DueAfter(Duration),
+    /// `due:overdue`
+    Overdue,
+    /// `due:today` / `due:week`: open and due before local midnight this many days ahead
+    /// (1 for today, 7 for the coming week), overdue included
+    DueWithin(i64),
+    /// `due:none`
+    NoDueDate,
+    /// `stale` / `stale:14d`: open and unchanged for longer than the period (by default
+    /// `[review] stale_days`)
+    Stale(Option<Duration>),
+    /// `context:home` / `@home` / `context:none`
+    Context(Option<String>),
+    /// `energy:low` / `energy:none`
+    Energy(Option<Energy>),
+    /// `tag:work`: title or description contains `#work`
+    Tag(String),
+    /// `status:someday`: set aside as someday/maybe
+    Someday,
+    /// `status:open` (in progress and blocked included), `status:done` (cancelled left
+    /// out), `status:in_progress`, `status:blocked` or `status:cancelled`
+    Status(Status),
+    /// `waiting` / `waiting:alice`: delegated (to someone whose name contains `alice`)
+    Waiting(Option<String>),
+    /// `parent:12` / `parent:none`
+    Parent(Option<i64>),
+    /// `/regex/`: case-insensitive regex over title or description
+    Regex(String),
+    /// Any other word: case-insensitive substring of title or description
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterCondition {
+    pub term: FilterTerm,
+    pub negated: bool,
+}
+
+/// A parsed filter expression: every condition must hold (implicit AND).
+/// Compiled to SQL by `Database::query_todos`
+///
+/// Syntax: whitespace-separated terms, optionally joined with `AND`, each optionally
+/// prefixed with `-` or `NOT` to negate it, e.g. `due<7d AND tag:work -status:done`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub conditions: Vec<FilterCondition>,
+}
+
+impl Filter {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let mut conditions = Vec::new();
+        let mut negate_next = false;
+
+        for word in Self::tokenize(expression)? {
+            if word.eq_ignore_ascii_case("and") {
+                continue;
+            }
+            if word.eq_ignore_ascii_case("not") {
+                negate_next = !negate_next;
+                continue;
+            }
+
+            let (negated, word) = match word.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (!negate_next, rest),
+                _ => (negate_next, word.as_str()),
+            };
+            negate_next = false;
+
+            conditions.push(FilterCondition {
+                term: Self::parse_term(word)?,
+                negated,
+            });
+        }
+
+        if conditions.is_empty() {
+            return Err("Filter expression is empty".to_string());
+        }
+
+        Ok(Filter { conditions })
+    }
+
+    /// Split on whitespace, keeping `/regex with spaces/` together as one token
+    fn tokenize(expression: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut in_regex = false;
+        let mut chars = expression.chars();
+
+        while let Some(c) = chars.next() {
+            if in_regex {
+                current.push(c);
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        current.push(escaped);
+                    }
+                } else if c == '/' {
+                    in_regex = false;
+                }
+            } else if c.is_whitespace() {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            } else {
+                // A regex starts with '/' at the beginning of a token (after an optional '-')
+                if c == '/' && (current.is_empty() || current == "-") {
+                    in_regex = true;
+                }
+                current.push(c);
+            }
+        }
+
+        if in_regex {
+            return Err(format!("Unterminated regex '{}'", current));
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+        Ok(tokens)
+    }
+
+    fn parse_term(word: &str) -> Result<FilterTerm, String> {
+        if let Some(pattern) = word.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            if pattern.is_empty() {
+                return Err("Empty regex in filter".to_string());
+            }
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid regex /{}/: {}", pattern, e))?;
+            return Ok(FilterTerm::Regex(pattern.to_string()));
+        }
+
+        let lower = word.to_lowercase();
+
+        if let Some(value) = lower.strip_prefix("due<") {
+            return dates::parse_relative_duration(value)
+                .map(FilterTerm::DueBefore)
+                .ok_or_else(|| format!("Invalid duration in '{}'", word));
+        }
+        if let Some(value) = lower.strip_prefix("due>") {
+            return dates::parse_relative_duration(value)
+                .map(FilterTerm::DueAfter)
+                .ok_or_else(|| format!("Invalid duration in '{}'", word));
+        }
+        if let Some(value) = lower.strip_prefix("due:") {
+            return match value {
+                "overdue" => Ok(FilterTerm::Overdue),
+                "today" => Ok(FilterTerm::DueWithin(1)),
+                "week" => Ok(FilterTerm::DueWithin(7)),
+                "none" => Ok(FilterTerm::NoDueDate),
+                _ => Err(format!("Unknown due filter '{}' (expected overdue, today, week or none)", value)),
+            };
+        }
+        if lower == "stale" {
+            return Ok(FilterTerm::Stale(None));
+        }
+        if let Some(value) = lower.strip_prefix("stale:") {
+            return dates::parse_relative_duration(value)
+                .map(|period| FilterTerm::Stale(Some(period)))
+                .ok_or_else(|| format!("Invalid duration in '{}'", word));
+        }
+        if let Some(value) = lower.strip_prefix("context:").or_else(|| lower.strip_prefix('@')) {
+            return match value {
+                "none" => Ok(FilterTerm::Context(None)),
+                _ if context::is_valid_context(value) => Ok(FilterTerm::Context(Some(value.to_string()))),
+                _ => Err(format!("Invalid context '{}' (use letters, digits, _ and -)", value)),
+            };
+        }
+        if let Some(value) = lower.strip_prefix("energy:") {
+            return match value {
+                "none" => Ok(FilterTerm::Energy(None)),
+                _ => Energy::parse(value)
+                    .map(|energy| FilterTerm::Energy(Some(energy)))
+                    .ok_or_else(|| format!("Unknown energy '{}' (expected low, medium, high or none)", value)),
+            };
+        }
+        if lower == "waiting" {
+            return Ok(FilterTerm::Waiting(None));
+        }
+        if let Some(value) = lower.strip_prefix("waiting:") {
+            if value.is_empty() {
+                return Err("Empty name in waiting filter".to_string());
+            }
+            return Ok(FilterTerm::Waiting(Some(value.to_string())));
+        }
+        if let Some(value) = lower.strip_prefix("tag:") {
+            let tag = value.trim_start_matches('#');
+            if tag.is_empty() {
+                return Err("Empty tag in filter".to_string());
+            }
+            return Ok(FilterTerm::Tag(tag.to_string()));
+        }
+        if let Some(value) = lower.strip_prefix("status:") {
+            return match value {
+                "open" | "incomplete" => Ok(FilterTerm::Status(Status::Open)),
+                // Cancelled todos are closed but weren't done; `status:cancelled` finds them
+                "done" | "completed" => Ok(FilterTerm::Status(Status::Done)),
+                "someday" | "maybe" => Ok(FilterTerm::Someday),
+                _ => match Status::parse(value) {
+                    Some(status) => Ok(FilterTerm::Status(status)),
+                    None => Err(format!("Unknown status '{}' (expected open, in_progress, blocked, done, cancelled or someday)", value)),
+                },
+            };
+        }
+        if let Some(value) = lower.strip_prefix("parent:") {
+            return match value {
+                "none" | "root" => Ok(FilterTerm::Parent(None)),
+                _ => value
+                    .parse::<i64>()
+                    .map(|id| FilterTerm::Parent(Some(id)))
+                    .map_err(|_| format!("Invalid parent id '{}' (expected a number or none)", value)),
+            };
+        }
+
+        Ok(FilterTerm::Text(lower))
+    }
+}
+
+/// Keep the todos whose ids are in `matching` plus all of their ancestors,
+/// so the tree view still shows the context lines above each match
+pub fn retain_with_ancestors(todos: Vec<Todo>, matching: &HashSet<i64>) -> Vec<Todo> {
+    let parents: HashMap<i64, Option<i64>> = todos.iter().map(|todo| (todo.id, todo.parent_id)).collect();
+
+    let mut keep: HashSet<i64> = HashSet::new();
+    for &id in matching {
+        let mut current = Some(id);
+        while let Some(current_id) = current {
+            if !keep.insert(current_id) {
+                break; // Already walked this branch
+            }
+            current = parents.get(&current_id).copied().flatten();
+        }
+    }
+
+    todos.into_iter().filter(|todo| keep.contains(&todo.id)).collect()
+}
+
+/// `roots` and the ids of everything under them
+pub fn with_descendants(todos: &[Todo], roots: &HashSet<i64>) -> HashSet<i64> {
+    let by_id: HashMap<i64, &Todo> = todos.iter().map(|todo| (todo.id, todo)).collect();
+
+    let mut found = HashSet::new();
+    for todo in todos {
+        let mut current = Some(todo);
+        while let Some(ancestor) = current {
+            if roots.contains(&ancestor.id) {
+                found.insert(todo.id);
+                break;
+            }
+            current = ancestor.parent_id.and_then(|id| by_id.get(&id).copied());
+        }
+    }
+    found
+}
+
+/// Ids of the todos that are deferred at `now` (see `Todo::is_deferred`) and of
+/// everything under them, which the tree and list leave out until they start
+pub fn deferred_ids(todos: &[Todo], now: DateTime<Utc>) -> HashSet<i64> {
+    let by_id: HashMap<i64, &Todo> = todos.iter().map(|todo| (todo.id, todo)).collect();
+
+    let mut deferred = HashSet::new();
+    for todo in todos {
+        let mut current = Some(todo);
+        while let Some(ancestor) = current {
+            if ancestor.is_deferred(now) {
+                deferred.insert(todo.id);
+                break;
+            }
+            current = ancestor.parent_id.and_then(|id| by_id.get(&id).copied());
+        }
+    }
+    deferred
+}
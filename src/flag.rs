@@ -0,0 +1,64 @@
+use crate::colors::CatppuccinFrappe;
+use ratatui::style::Color;
+
+/// The colors a todo can be flagged with, by name
+pub const COLORS: [(&str, Color); 8] = [
+    ("red", CatppuccinFrappe::RED),
+    ("orange", CatppuccinFrappe::PEACH),
+    ("yellow", CatppuccinFrappe::YELLOW),
+    ("green", CatppuccinFrappe::GREEN),
+    ("teal", CatppuccinFrappe::TEAL),
+    ("blue", CatppuccinFrappe::BLUE),
+    ("purple", CatppuccinFrappe::MAUVE),
+    ("pink", CatppuccinFrappe::PINK),
+];
+
+/// A mark for the few todos that must not get lost: a color the open todo is drawn in
+/// instead of its due-date color, or an emoji shown in front of its title in the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flag {
+    Color(&'static str),
+    Emoji(String),
+}
+
+impl Flag {
+    /// Parse the flag prompt: a color name or an emoji. Empty (or `none`) clears the flag.
+    pub fn parse(input: &str) -> Result<Option<Self>, String> {
+        let input = input.trim();
+        if input.is_empty() || input == "none" {
+            return Ok(None);
+        }
+        if let Some((name, _)) = COLORS.iter().find(|(name, _)| name.eq_ignore_ascii_case(input)) {
+            return Ok(Some(Self::Color(name)));
+        }
+        // Emoji, including ones joined from several code points (👩‍💻) or with a
+        // variation selector (⚠️), but not words
+        if input.chars().count() <= 8 && !input.chars().any(|c| c.is_alphanumeric() || c.is_whitespace()) {
+            return Ok(Some(Self::Emoji(input.to_string())));
+        }
+        let names: Vec<&str> = COLORS.iter().map(|(name, _)| *name).collect();
+        Err(format!("Invalid flag '{}': use {} or an emoji", input, names.join(", ")))
+    }
+
+    /// How the flag is stored and shown in the prompt
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Color(name) => name,
+            Self::Emoji(emoji) => emoji,
+        }
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Self::Color(name) => COLORS.iter().find(|(known, _)| known == name).map(|(_, color)| *color),
+            Self::Emoji(_) => None,
+        }
+    }
+
+    pub fn emoji(&self) -> Option<&str> {
+        match self {
+            Self::Color(_) => None,
+            Self::Emoji(emoji) => Some(emoji),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::agenda;
+use chrono::{DateTime, Local, Utc};
+
+/// Columns of `todos` whose changes are recorded in `history` as updates, with the label
+/// they are shown under. Completion and the parent are recorded as their own actions.
+pub const TRACKED_FIELDS: [(&str, &str); 14] = [
+    ("title", "Title"),
+    ("description", "Description"),
+    ("due_by", "Due"),
+    ("start_at", "Start"),
+    ("hidden", "Hidden"),
+    ("status", "Status"),
+    ("estimate_minutes", "Estimate"),
+    ("context", "Context"),
+    ("energy", "Energy"),
+    ("someday", "Someday"),
+    ("waiting_on", "Waiting on"),
+    ("follow_up_at", "Follow up"),
+    ("flag", "Flag"),
+    ("pinned", "Pinned"),
+];
+
+/// One recorded change to a todo, written by triggers on `todos` so every path that
+/// changes a todo (the app, the CLI, sync, the API) is covered
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub todo_id: i64,
+    /// The todo's title when the change was made, so deleted todos still have one
+    pub title: String,
+    /// "create", "update", "complete", "uncomplete", "move" or "delete"
+    pub action: String,
+    /// The changed column, for updates
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// What happened, e.g. `Due: 2025-03-01 → 2025-03-05` or `Moved from #12 to the top level`
+pub fn describe(entry: &HistoryEntry) -> String {
+    let field = entry.field.as_deref().unwrap_or("");
+    let value = |value: &Option<String>| match value {
+        Some(value) => format_value(field, value),
+        None => "none".to_string(),
+    };
+    let parent = |value: &Option<String>| value.as_ref().map_or("the top level".to_string(), |id| format!("#{}", id));
+    match entry.action.as_str() {
+        "create" => "Created".to_string(),
+        "delete" => "Deleted".to_string(),
+        "complete" => "Completed".to_string(),
+        "uncomplete" => "Reopened".to_string(),
+        "move" => format!("Moved from {} to {}", parent(&entry.old_value), parent(&entry.new_value)),
+        _ => {
+            let label = TRACKED_FIELDS.iter().find(|(column, _)| *column == field).map_or(field, |(_, label)| label);
+            // Descriptions are too long to show twice
+            if field == "description" {
+                format!("{} edited", label)
+            } else {
+                format!("{}: {} → {}", label, value(&entry.old_value), value(&entry.new_value))
+            }
+        }
+    }
+}
+
+/// A stored value as it reads in the app: local dates, estimates as `1h30m`, flags as yes/no
+pub fn format_value(field: &str, value: &str) -> String {
+    match field {
+        "due_by" | "start_at" | "follow_up_at" | "created_at" | "completed_at" => DateTime::parse_from_rfc3339(value)
+            .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%:z"))
+            .map_or(value.to_string(), |at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
+        "estimate_minutes" => value.parse().map_or(value.to_string(), agenda::format_estimate),
+        "hidden" | "someday" | "pinned" => if value == "0" { "no" } else { "yes" }.to_string(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,135 @@
+use crate::config::{HookConfig, HookEvent};
+use crate::database::{Database, Todo, TodoEvent};
+use crate::http;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread::JoinHandle;
+
+/// The JSON a hook receives:
+///
+/// ```text
+/// {"event": "complete", "text": "Completed: Tag the release",
+///  "todo": {"id": 12, "title": "Tag the release", "parent_title": "Release 1.2", ...},
+///  "database": "/home/me/todos.db", "profile": null}
+/// ```
+pub fn event_payload(event: HookEvent, todo: &Todo, parent_title: Option<&str>, database: Option<&str>, profile: Option<&str>) -> String {
+    let verb = match event {
+        HookEvent::Create => "Created",
+        HookEvent::Complete => "Completed",
+        HookEvent::Uncomplete => "Reopened",
+        HookEvent::Delete => "Deleted",
+        HookEvent::Move => "Moved",
+    };
+    let text = match (event, parent_title) {
+        (HookEvent::Move, Some(parent)) => format!("{}: {} (to {})", verb, todo.title, parent),
+        (HookEvent::Move, None) => format!("{}: {} (to the top level)", verb, todo.title),
+        _ => format!("{}: {}", verb, todo.title),
+    };
+    serde_json::json!({
+        "event": event,
+        "text": text,
+        "todo": {
+            "id": todo.id,
+            "title": todo.title,
+            "description": todo.description,
+            "created_at": todo.created_at,
+            "completed_at": todo.completed_at,
+            "due_by": todo.due_by,
+            "parent_id": todo.parent_id,
+            "parent_title": parent_title,
+            "hidden": todo.hidden,
+        },
+        "database": database,
+        "profile": profile,
+    })
+    .to_string()
+}
+
+/// Run one hook to completion: the command with `payload` on stdin, then the POST
+pub fn run_hook(hook: &HookConfig, payload: &str) -> anyhow::Result<()> {
+    if hook.command.is_none() && hook.url.is_none() {
+        return Err(anyhow::anyhow!("Hook has neither a command nor a url"));
+    }
+    if let Some(command) = &hook.command {
+        let mut child = Command::new("sh")
+            .args(["-c", command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // A command that doesn't read its input is fine
+        let _ = child.stdin.take().ok_or_else(|| anyhow::anyhow!("Hook has no stdin"))?.write_all(payload.as_bytes());
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("'{}' exited with {}: {}", command, output.status, stderr.lines().next().unwrap_or_default()));
+        }
+    }
+    if let Some(url) = &hook.url {
+        let (status, body) = http::request("POST", url, &[("Content-Type", "application/json".to_string())], payload)?;
+        if !(200..300).contains(&status) {
+            return Err(anyhow::anyhow!("{} returned {}: {}", url, status, body.trim()));
+        }
+    }
+    Ok(())
+}
+
+/// Hooks running in the background, so a slow command or server never holds up the UI
+#[derive(Default)]
+pub struct HookRunner {
+    running: Vec<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl HookRunner {
+    /// Start every hook in `hooks` that listens for `event`
+    pub fn fire(&mut self, hooks: &[HookConfig], event: HookEvent, payload: &str) {
+        for hook in hooks.iter().filter(|hook| hook.events.is_empty() || hook.events.contains(&event)) {
+            let (hook, payload) = (hook.clone(), payload.to_string());
+            self.running.push(std::thread::spawn(move || run_hook(&hook, &payload)));
+        }
+    }
+
+    /// Start the hooks for a change reported by `Database::subscribe`. Changes without
+    /// a hook event (edits, syncs) and todos that are already gone again are skipped.
+    pub fn fire_for_change(&mut self, hooks: &[HookConfig], database: &Database, change: &TodoEvent, profile: Option<&str>) {
+        if hooks.is_empty() {
+            return;
+        }
+        let (event, todo) = match change {
+            TodoEvent::Created(id) => (HookEvent::Create, database.get_todo_by_id(*id)),
+            TodoEvent::Completed(id) => (HookEvent::Complete, database.get_todo_by_id(*id)),
+            TodoEvent::Uncompleted(id) => (HookEvent::Uncomplete, database.get_todo_by_id(*id)),
+            TodoEvent::Moved(id) => (HookEvent::Move, database.get_todo_by_id(*id)),
+            TodoEvent::Deleted(todo) => (HookEvent::Delete, Ok(Some(todo.clone()))),
+            TodoEvent::Updated(_) | TodoEvent::Reloaded => return,
+        };
+        let Ok(Some(todo)) = todo else { return };
+        let parent_title = database.get_parent_title(todo.parent_id).ok().flatten();
+        let payload = event_payload(event, &todo, parent_title.as_deref(), database.path(), profile);
+        self.fire(hooks, event, &payload);
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// Errors of the hooks that finished since the last call
+    pub fn finished_errors(&mut self) -> Vec<String> {
+        let (finished, running) = std::mem::take(&mut self.running).into_iter().partition(|job| job.is_finished());
+        self.running = running;
+        finished.into_iter().filter_map(join_error).collect()
+    }
+
+    /// Wait for every running hook, returning their errors
+    pub fn wait(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.running).into_iter().filter_map(join_error).collect()
+    }
+}
+
+fn join_error(job: JoinHandle<anyhow::Result<()>>) -> Option<String> {
+    match job.join() {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some("hook panicked".to_string()),
+    }
+}
@@ -0,0 +1,278 @@
+use crate::clipboard;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Largest response accepted, as a guard against runaway bodies
+pub const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Send one request and return the status code and response body. Plain `http://`
+/// is spoken directly; `https://` goes through curl, which brings the TLS stack.
+pub fn request(method: &str, url: &str, headers: &[(&str, String)], body: &str) -> anyhow::Result<(u16, String)> {
+    if url.starts_with("https://") {
+        return request_with_curl(method, url, headers, body);
+    }
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| anyhow::anyhow!("Url must start with http:// or https:// (got '{}')", url))?;
+    let (host, path) = rest.split_once('/').map_or((rest, "/".to_string()), |(host, path)| (host, format!("/{}", path)));
+    let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+
+    let mut stream = TcpStream::connect(&address).map_err(|e| anyhow::anyhow!("Cannot reach {}: {}", address, e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+    let extra: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        method,
+        path,
+        host,
+        body.len(),
+        extra,
+        body
+    )?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_BODY_BYTES as u64).read_to_end(&mut response)?;
+    let response = String::from_utf8(response)?;
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| anyhow::anyhow!("Malformed HTTP response"))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Malformed HTTP status line"))?;
+    Ok((status, body.to_string()))
+}
+
+/// The options, credentials included, go to curl on stdin so they never show in `ps`.
+/// The body goes in a file of its own: a config value ends at the first line break,
+/// and request bodies such as iCalendar are made of lines.
+pub fn request_with_curl(method: &str, url: &str, headers: &[(&str, String)], body: &str) -> anyhow::Result<(u16, String)> {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut config = format!(
+        "silent\nshow-error\nrequest = {}\nurl = {}\nwrite-out = \"\\n%{{http_code}}\"\n",
+        quote(method),
+        quote(url)
+    );
+    let body_file = if body.is_empty() { None } else { Some(BodyFile::write(body)?) };
+    if let Some(file) = &body_file {
+        config.push_str(&format!("data-binary = {}\n", quote(&format!("@{}", file.0.display()))));
+    }
+    for (name, value) in headers {
+        config.push_str(&format!("header = {}\n", quote(&format!("{}: {}", name, value))));
+    }
+
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("https needs curl: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("curl has no stdin"))?
+        .write_all(config.as_bytes())?;
+    let output = child.wait_with_output()?;
+    drop(body_file);
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("curl failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let output = String::from_utf8(output.stdout)?;
+    let (body, status) = output.rsplit_once('\n').ok_or_else(|| anyhow::anyhow!("Malformed curl output"))?;
+    Ok((status.trim().parse()?, body.to_string()))
+}
+
+/// A request body in the temp directory, readable only by its owner, removed when dropped
+struct BodyFile(std::path::PathBuf);
+
+impl BodyFile {
+    fn write(body: &str) -> anyhow::Result<Self> {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        let path = std::env::temp_dir().join(format!("tododb-body-{}-{}", std::process::id(), nanos));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut handle = options.open(&path)?;
+        let file = BodyFile(path);
+        handle.write_all(body.as_bytes())?;
+        Ok(file)
+    }
+}
+
+impl Drop for BodyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// `Authorization` value for HTTP basic auth
+pub fn basic_auth(username: &str, password: &str) -> String {
+    format!("Basic {}", clipboard::base64_encode(format!("{}:{}", username, password).as_bytes()))
+}
+
+/// A request as read by the servers here (`tododb sync serve`, `tododb --api`)
+pub struct Request {
+    pub method: String,
+    /// Path and query string, as sent
+    pub target: String,
+    pub authorization: Option<String>,
+    /// Only `--api` looks at it
+    #[cfg_attr(not(feature = "api"), allow(dead_code))]
+    pub content_type: Option<String>,
+    /// None when the body is over `MAX_BODY_BYTES` and was left unread
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    pub fn path(&self) -> &str {
+        self.target.split_once('?').map_or(self.target.as_str(), |(path, _)| path)
+    }
+
+    /// The decoded value of `name` in the query string
+    #[cfg(feature = "api")]
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        let (_, query) = self.target.split_once('?')?;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| percent_decode(value))
+    }
+}
+
+/// How long a client gets to send its whole request. The servers answer one connection
+/// at a time, so a client that connects and goes quiet holds up the others until then.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads from a stream until a deadline, however the time is spread across reads
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "request took too long"));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Read one HTTP/1.1 request from `stream`, within `REQUEST_TIMEOUT`
+pub fn read_request(stream: &TcpStream) -> anyhow::Result<Request> {
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(Deadline { stream, until: Instant::now() + REQUEST_TIMEOUT });
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut content_type = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse()?,
+                "authorization" => authorization = Some(value.trim().to_string()),
+                "content-type" => content_type = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let body = if content_length > MAX_BODY_BYTES {
+        None
+    } else {
+        // Grown as the data arrives rather than sized up front from the header
+        let mut body = Vec::new();
+        reader.take(content_length as u64).read_to_end(&mut body)?;
+        if body.len() < content_length {
+            return Err(anyhow::anyhow!("Connection closed before the end of the body"));
+        }
+        Some(body)
+    };
+    Ok(Request { method, target, authorization, content_type, body })
+}
+
+/// Whether an `Authorization` header carries `Bearer <token>`, compared in constant time
+pub fn bearer_matches(authorization: Option<&str>, token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+    authorization.is_some_and(|given| crate::passphrase::constant_time_eq(given.as_bytes(), expected.as_bytes()))
+}
+
+/// Refuse to serve on an address other machines can reach without a token to check;
+/// `setting` is the config key to point at
+pub fn require_token_off_loopback(addr: SocketAddr, token: Option<&str>, setting: &str) -> anyhow::Result<()> {
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(anyhow::anyhow!(
+            "Refusing to listen on {} without a token: set {} first, or listen on 127.0.0.1",
+            addr,
+            setting
+        ));
+    }
+    Ok(())
+}
+
+/// Write a complete response and close the exchange
+pub fn respond(stream: &mut TcpStream, status: u16, content_type: &str, body: &str) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Decode `%xx` escapes and `+` (a space in form data)
+#[cfg(feature = "api")]
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
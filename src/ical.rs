@@ -0,0 +1,183 @@
+use crate::dates;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// The fields of an iCalendar VTODO that map onto a todo
+#[derive(Debug, Clone, Default)]
+pub struct VTodo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: String,
+    pub created: Option<DateTime<Utc>>,
+    pub due: Option<DateTime<Utc>>,
+    pub completed: Option<DateTime<Utc>>,
+    /// STATUS:COMPLETED (some exporters omit the COMPLETED timestamp)
+    pub is_completed: bool,
+    /// RELATED-TO with the default RELTYPE=PARENT
+    pub parent_uid: Option<String>,
+    /// 1 (highest) to 9 (lowest); 0 or absent means undefined
+    pub priority: u8,
+    pub categories: Vec<String>,
+}
+
+/// Parse every VTODO component in an iCalendar document (Apple Reminders, CalDAV exports, ...)
+pub fn parse_vtodos(text: &str) -> Vec<VTodo> {
+    let mut todos = Vec::new();
+    let mut current: Option<VTodo> = None;
+
+    for line in unfold_lines(text) {
+        let Some((name_and_params, value)) = line.split_once(':') else { continue };
+        let mut parts = name_and_params.split(';');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let params: Vec<&str> = parts.collect();
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VTODO") => current = Some(VTodo::default()),
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VTODO") => todos.extend(current.take()),
+            ("UID", Some(todo)) => todo.uid = Some(value.to_string()),
+            ("SUMMARY", Some(todo)) => todo.summary = unescape(value),
+            ("DESCRIPTION", Some(todo)) => todo.description = unescape(value),
+            ("CREATED", Some(todo)) => todo.created = parse_datetime(value),
+            ("DUE", Some(todo)) => todo.due = parse_datetime(value),
+            ("COMPLETED", Some(todo)) => todo.completed = parse_datetime(value),
+            ("STATUS", Some(todo)) => todo.is_completed = value.eq_ignore_ascii_case("COMPLETED"),
+            ("PRIORITY", Some(todo)) => todo.priority = value.trim().parse().unwrap_or(0),
+            ("CATEGORIES", Some(todo)) => {
+                todo.categories.extend(unescape(value).split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()));
+            }
+            ("RELATED-TO", Some(todo)) => {
+                let is_parent = params.iter().all(|param| {
+                    !param.to_ascii_uppercase().starts_with("RELTYPE=") || param.eq_ignore_ascii_case("RELTYPE=PARENT")
+                });
+                if is_parent {
+                    todo.parent_uid = Some(value.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    todos
+}
+
+/// Write `todo` as a complete iCalendar document holding one VTODO, the form CalDAV
+/// servers store. `modified` becomes DTSTAMP and LAST-MODIFIED.
+pub fn write_vcalendar(todo: &VTodo, modified: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//tododb//tododb//EN".to_string(),
+        "BEGIN:VTODO".to_string(),
+    ];
+    if let Some(uid) = &todo.uid {
+        lines.push(format!("UID:{}", uid));
+    }
+    lines.push(format!("DTSTAMP:{}", format_datetime(modified)));
+    lines.push(format!("LAST-MODIFIED:{}", format_datetime(modified)));
+    if let Some(created) = todo.created {
+        lines.push(format!("CREATED:{}", format_datetime(created)));
+    }
+    lines.push(format!("SUMMARY:{}", escape(&todo.summary)));
+    if !todo.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&todo.description)));
+    }
+    if let Some(due) = todo.due {
+        lines.push(format!("DUE:{}", format_datetime(due)));
+    }
+    if todo.is_completed || todo.completed.is_some() {
+        lines.push("STATUS:COMPLETED".to_string());
+        lines.push("PERCENT-COMPLETE:100".to_string());
+        if let Some(completed) = todo.completed {
+            lines.push(format!("COMPLETED:{}", format_datetime(completed)));
+        }
+    } else {
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+    }
+    if let Some(parent_uid) = &todo.parent_uid {
+        lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", parent_uid));
+    }
+    if todo.priority > 0 {
+        lines.push(format!("PRIORITY:{}", todo.priority));
+    }
+    if !todo.categories.is_empty() {
+        let categories: Vec<String> = todo.categories.iter().map(|category| escape(category)).collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    lines.push("END:VTODO".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_line(line)).collect()
+}
+
+/// RFC 5545 line folding: lines longer than 75 octets continue on lines starting
+/// with a space, never splitting a character. Each line ends in CRLF.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Undo RFC 5545 line folding: a line starting with a space or tab continues the previous one
+fn unfold_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.lines() {
+        let raw = raw.trim_end_matches('\r');
+        match (raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// `20240105T093000Z` (UTC), `20240105T093000` (floating, read as local time)
+/// or `20240105` (a date, read as the end of that local day like typed due dates)
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Local.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    dates::local_to_utc(date, dates::END_OF_DAY, &Local)
+}
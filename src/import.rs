@@ -0,0 +1,575 @@
+use crate::database::{Database, NewTodo};
+use crate::dates;
+use crate::ical;
+use crate::org;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::{env, fs, path::{Path, PathBuf}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    TodoTxt,
+    Taskwarrior,
+    ICalendar,
+    GoogleTasks,
+    Org,
+}
+
+impl SourceKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceKind::TodoTxt => "todo.txt",
+            SourceKind::Taskwarrior => "Taskwarrior",
+            SourceKind::ICalendar => "iCalendar (Apple Reminders)",
+            SourceKind::GoogleTasks => "Google Tasks",
+            SourceKind::Org => "Org mode",
+        }
+    }
+}
+
+/// Something that can be imported: a file, or for Taskwarrior also its data directory
+#[derive(Debug, Clone)]
+pub struct ImportSource {
+    pub kind: SourceKind,
+    pub path: PathBuf,
+}
+
+/// A todo read from another tool. `key`/`parent_key` are source-side ids used to
+/// rebuild the hierarchy once the todos have database ids
+#[derive(Debug, Clone, Default)]
+pub struct ImportedTodo {
+    pub key: Option<String>,
+    pub parent_key: Option<String>,
+    pub title: String,
+    pub description: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_by: Option<DateTime<Utc>>,
+    /// Not before (see `Todo::start_at`)
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+/// Folder where exported files (.ics, Google Tasks .json, todo.txt) can be dropped for import
+pub fn default_import_dir() -> PathBuf {
+    let mut path = PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+    path.push(".local");
+    path.push("share");
+    path.push("tododb");
+    path.push("import");
+    path
+}
+
+/// Look for data from other tools in their usual locations and in the import folder
+pub fn scan_sources() -> Vec<ImportSource> {
+    let home = PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+    let mut sources = Vec::new();
+
+    let mut todo_txt_candidates = vec![home.join("todo.txt"), home.join("todo").join("todo.txt")];
+    if let Ok(dir) = env::var("TODO_DIR") {
+        todo_txt_candidates.insert(0, PathBuf::from(dir).join("todo.txt"));
+    }
+    if let Some(path) = todo_txt_candidates.into_iter().find(|path| path.is_file()) {
+        sources.push(ImportSource { kind: SourceKind::TodoTxt, path });
+    }
+
+    let task_dir = env::var("TASKDATA").map(PathBuf::from).unwrap_or_else(|_| home.join(".task"));
+    if task_dir.join("pending.data").is_file() {
+        sources.push(ImportSource { kind: SourceKind::Taskwarrior, path: task_dir });
+    }
+
+    if let Ok(entries) = fs::read_dir(default_import_dir()) {
+        let mut files: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+        files.sort();
+        sources.extend(files.into_iter().filter_map(|path| detect_source(&path).ok()));
+    }
+
+    sources
+}
+
+/// Work out which importer handles a file (or Taskwarrior data directory)
+pub fn detect_source(path: &Path) -> anyhow::Result<ImportSource> {
+    if path.is_dir() {
+        if path.join("pending.data").is_file() {
+            return Ok(ImportSource { kind: SourceKind::Taskwarrior, path: path.to_path_buf() });
+        }
+        return Err(anyhow::anyhow!("{} is not a Taskwarrior data directory", path.display()));
+    }
+
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+    let kind = match extension.as_str() {
+        "ics" => SourceKind::ICalendar,
+        "txt" => SourceKind::TodoTxt,
+        "org" => SourceKind::Org,
+        "json" => {
+            // Taskwarrior's `task export` is an array of tasks; Google Takeout is an object of task lists
+            let json: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+            if json.is_array() { SourceKind::Taskwarrior } else { SourceKind::GoogleTasks }
+        }
+        _ => return Err(anyhow::anyhow!("Don't know how to import {}", path.display())),
+    };
+    Ok(ImportSource { kind, path: path.to_path_buf() })
+}
+
+pub fn read_source(source: &ImportSource) -> anyhow::Result<Vec<ImportedTodo>> {
+    match source.kind {
+        SourceKind::TodoTxt => {
+            let mut content = fs::read_to_string(&source.path)?;
+            // todo.txt keeps finished tasks in a done.txt alongside it
+            let done = source.path.with_file_name("done.txt");
+            if source.path.file_name().is_some_and(|name| name == "todo.txt") && done.is_file() {
+                content.push('\n');
+                content.push_str(&fs::read_to_string(done)?);
+            }
+            Ok(content.lines().filter_map(parse_todo_txt_line).collect())
+        }
+        SourceKind::Taskwarrior => {
+            let tasks = if source.path.is_dir() {
+                let mut tasks = Vec::new();
+                for file in ["pending.data", "completed.data"] {
+                    if let Ok(content) = fs::read_to_string(source.path.join(file)) {
+                        tasks.extend(content.lines().filter_map(parse_ff4_line));
+                    }
+                }
+                tasks
+            } else {
+                let json: Value = serde_json::from_str(&fs::read_to_string(&source.path)?)?;
+                json.as_array().map(|tasks| tasks.iter().map(task_from_json).collect()).unwrap_or_default()
+            };
+            Ok(taskwarrior_todos(tasks))
+        }
+        SourceKind::ICalendar => {
+            let content = fs::read_to_string(&source.path)?;
+            Ok(ical::parse_vtodos(&content).into_iter().map(vtodo_to_imported).collect())
+        }
+        SourceKind::GoogleTasks => {
+            let json: Value = serde_json::from_str(&fs::read_to_string(&source.path)?)?;
+            Ok(google_tasks_todos(&json))
+        }
+        SourceKind::Org => Ok(org_todos(&fs::read_to_string(&source.path)?)),
+    }
+}
+
+pub fn import_source(database: &Database, source: &ImportSource) -> anyhow::Result<usize> {
+    let todos = read_source(source)?;
+    Ok(import_todos(database, todos, None)?.len())
+}
+
+/// Create the todos, then attach each to its parent once every key has an id.
+/// Todos without a parent key go under `parent_id`. Returns the new ids in order.
+/// Runs as one transaction, so a failed import adds nothing.
+pub fn import_todos(database: &Database, todos: Vec<ImportedTodo>, parent_id: Option<i64>) -> anyhow::Result<Vec<i64>> {
+    database.with_transaction(|database| {
+        let mut ids: HashMap<String, i64> = HashMap::new();
+        let mut created = Vec::with_capacity(todos.len());
+        let mut pending_parents: Vec<(i64, String)> = Vec::new();
+
+        for todo in &todos {
+            // A parent listed earlier already has an id; others are attached afterwards
+            let known_parent = todo.parent_key.as_ref().and_then(|key| ids.get(key).copied());
+            let id = database.create_imported_todo(
+                NewTodo {
+                    title: todo.title.clone(),
+                    description: todo.description.clone(),
+                    parent_id: if todo.parent_key.is_none() { parent_id } else { known_parent },
+                    due_by: todo.due_by,
+                },
+                todo.created_at.unwrap_or_else(Utc::now),
+                todo.completed_at,
+            )?;
+            if todo.start_at.is_some() {
+                database.set_start_at(id, todo.start_at)?;
+            }
+            if let Some(key) = &todo.key {
+                ids.insert(key.clone(), id);
+            }
+            if let Some(parent_key) = &todo.parent_key
+                && known_parent.is_none() {
+                pending_parents.push((id, parent_key.clone()));
+            }
+            created.push(id);
+        }
+
+        for (id, parent_key) in pending_parents {
+            if let Some(&parent_id) = ids.get(&parent_key) {
+                // A cyclic source hierarchy just leaves that todo at the top level
+                let _ = database.move_todo(id, Some(parent_id));
+            }
+        }
+
+        Ok(created)
+    })
+}
+
+/// `p0`-style title prefix used for priorities in this app
+fn with_priority(title: String, priority: Option<u32>) -> String {
+    match priority {
+        Some(p) => format!("p{} {}", p, title),
+        None => title,
+    }
+}
+
+fn with_tags(mut title: String, tags: &[String]) -> String {
+    for tag in tags {
+        let tag: String = tag.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+        if !tag.is_empty() {
+            title.push_str(&format!(" #{}", tag));
+        }
+    }
+    title
+}
+
+fn date_end_of_day(value: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    dates::local_to_utc(date, dates::END_OF_DAY, &Local)
+}
+
+fn date_start_of_day(value: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    dates::local_to_utc(date, NaiveTime::MIN, &Local)
+}
+
+/// todo.txt: `x 2024-01-06 2024-01-01 (A) Call mom +family @phone due:2024-01-05`
+/// `+project` and `@context` become `#tags`; `(A)`..`(Z)` become `p0`..`p25`
+pub fn parse_todo_txt_line(line: &str) -> Option<ImportedTodo> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let mut todo = ImportedTodo::default();
+    let mut priority = None;
+
+    if words[0] == "x" {
+        words.remove(0);
+        if let Some(completed) = words.first().and_then(|word| date_start_of_day(word)) {
+            todo.completed_at = Some(completed);
+            words.remove(0);
+        } else {
+            todo.completed_at = Some(Utc::now());
+        }
+    } else if let Some(letter) = words[0].strip_prefix('(').and_then(|rest| rest.strip_suffix(')'))
+        && letter.len() == 1
+        && let Some(c) = letter.chars().next().filter(|c| c.is_ascii_uppercase()) {
+        priority = Some(c as u32 - 'A' as u32);
+        words.remove(0);
+    }
+    if let Some(created) = words.first().and_then(|word| date_start_of_day(word)) {
+        todo.created_at = Some(created);
+        words.remove(0);
+    }
+
+    let mut title_words = Vec::new();
+    for word in words {
+        if let Some(due) = word.strip_prefix("due:").and_then(date_end_of_day) {
+            todo.due_by = Some(due);
+        } else if let Some(tag) = word.strip_prefix('+').or_else(|| word.strip_prefix('@')).filter(|tag| !tag.is_empty()) {
+            title_words.push(format!("#{}", tag));
+        } else {
+            title_words.push(word.to_string());
+        }
+    }
+    if title_words.is_empty() {
+        return None;
+    }
+
+    todo.title = with_priority(title_words.join(" "), priority);
+    Some(todo)
+}
+
+/// A nested markdown list, as in meeting notes: one todo per bullet (`-`, `*`, `+`
+/// or `1.`), nested by indentation, `[x]` checkboxes completed. Text indented under
+/// a bullet becomes its description; headings and other lines are skipped.
+pub fn parse_markdown_outline(text: &str) -> Vec<ImportedTodo> {
+    let mut todos: Vec<ImportedTodo> = Vec::new();
+    // Indentation and key of each bullet the next one may be nested under
+    let mut open: Vec<(usize, String)> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.replace('\t', "    ");
+        let indent = line.len() - line.trim_start().len();
+        let content = line.trim();
+        let Some(item) = strip_bullet(content) else {
+            if !content.is_empty()
+                && open.last().is_some_and(|(last_indent, _)| indent > *last_indent)
+                && let Some(todo) = todos.last_mut() {
+                if !todo.description.is_empty() {
+                    todo.description.push('\n');
+                }
+                todo.description.push_str(content);
+            }
+            continue;
+        };
+
+        while open.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+            open.pop();
+        }
+        let (completed, title) = match item.get(..4) {
+            Some("[x] " | "[X] ") => (true, &item[4..]),
+            Some("[ ] ") => (false, &item[4..]),
+            _ => (false, item),
+        };
+        if title.trim().is_empty() {
+            continue;
+        }
+        let key = number.to_string();
+        todos.push(ImportedTodo {
+            key: Some(key.clone()),
+            parent_key: open.last().map(|(_, parent)| parent.clone()),
+            title: title.trim().to_string(),
+            completed_at: completed.then(Utc::now),
+            ..ImportedTodo::default()
+        });
+        open.push((indent, key));
+    }
+    todos
+}
+
+/// The item text after a `- `, `* `, `+ `, `1. ` or `1) ` list marker
+fn strip_bullet(line: &str) -> Option<&str> {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(item);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))
+}
+
+/// The Taskwarrior fields we carry over, from either data format
+#[derive(Debug, Default)]
+struct Task {
+    uuid: Option<String>,
+    description: String,
+    status: String,
+    entry: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    due: Option<DateTime<Utc>>,
+    project: Option<String>,
+    priority: Option<String>,
+    tags: Vec<String>,
+    annotations: Vec<String>,
+}
+
+/// A line of Taskwarrior's `pending.data` / `completed.data`: `[key:"value" ...]`, epoch timestamps
+fn parse_ff4_line(line: &str) -> Option<Task> {
+    let body = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let epoch = |value: &str| value.parse::<i64>().ok().and_then(|secs| Utc.timestamp_opt(secs, 0).single());
+    let mut task = Task::default();
+    let mut rest = body;
+
+    while let Some((key, after_key)) = rest.trim_start().split_once(":\"") {
+        // Values end at the next unescaped quote
+        let mut value = String::new();
+        let mut chars = after_key.char_indices();
+        let mut end = after_key.len();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+                '"' => {
+                    end = i + 1;
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+        rest = &after_key[end..];
+        let value = value.replace("&open;", "[").replace("&close;", "]").replace("&dquot;", "\"");
+
+        match key {
+            "uuid" => task.uuid = Some(value),
+            "description" => task.description = value,
+            "status" => task.status = value,
+            "entry" => task.entry = epoch(&value),
+            "end" => task.end = epoch(&value),
+            "due" => task.due = epoch(&value),
+            "project" => task.project = Some(value),
+            "priority" => task.priority = Some(value),
+            "tags" => task.tags = value.split(',').filter(|tag| !tag.is_empty()).map(str::to_string).collect(),
+            key if key.starts_with("annotation_") => task.annotations.push(value),
+            _ => {}
+        }
+    }
+
+    (!task.description.is_empty()).then_some(task)
+}
+
+/// An element of `task export` JSON, which uses `20240105T093000Z` timestamps
+fn task_from_json(json: &Value) -> Task {
+    let text = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+    let date = |key: &str| {
+        let value = json.get(key).and_then(Value::as_str)?;
+        let naive = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+        Some(Utc.from_utc_datetime(&naive))
+    };
+    let strings = |key: &str, field: Option<&str>| -> Vec<String> {
+        json.get(key)
+            .and_then(Value::as_array)
+            .map(|items| {
+                items.iter()
+                    .filter_map(|item| match field {
+                        Some(field) => item.get(field)?.as_str(),
+                        None => item.as_str(),
+                    })
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    Task {
+        uuid: text("uuid"),
+        description: text("description").unwrap_or_default(),
+        status: text("status").unwrap_or_default(),
+        entry: date("entry"),
+        end: date("end"),
+        due: date("due"),
+        project: text("project"),
+        priority: text("priority"),
+        tags: strings("tags", None),
+        annotations: strings("annotations", Some("description")),
+    }
+}
+
+/// Deleted tasks and recurrence templates are skipped; each project becomes a parent todo
+fn taskwarrior_todos(tasks: Vec<Task>) -> Vec<ImportedTodo> {
+    let mut todos = Vec::new();
+    let mut projects: Vec<String> = Vec::new();
+
+    for task in tasks {
+        if task.status == "deleted" || task.status == "recurring" || task.description.is_empty() {
+            continue;
+        }
+        let priority = match task.priority.as_deref() {
+            Some("H") => Some(0),
+            Some("M") => Some(1),
+            Some("L") => Some(2),
+            _ => None,
+        };
+        let parent_key = task.project.as_ref().map(|project| {
+            if !projects.contains(project) {
+                projects.push(project.clone());
+            }
+            format!("project:{}", project)
+        });
+
+        todos.push(ImportedTodo {
+            key: task.uuid,
+            parent_key,
+            title: with_tags(with_priority(task.description, priority), &task.tags),
+            description: task.annotations.join("\n"),
+            created_at: task.entry,
+            completed_at: if task.status == "completed" { task.end.or_else(|| Some(Utc::now())) } else { None },
+            due_by: task.due,
+            start_at: None,
+        });
+    }
+
+    let mut project_todos: Vec<ImportedTodo> = projects
+        .into_iter()
+        .map(|project| ImportedTodo {
+            key: Some(format!("project:{}", project)),
+            title: project,
+            ..Default::default()
+        })
+        .collect();
+    project_todos.extend(todos);
+    project_todos
+}
+
+fn vtodo_to_imported(vtodo: ical::VTodo) -> ImportedTodo {
+    let priority = match vtodo.priority {
+        1..=4 => Some(0),
+        5 => Some(1),
+        6..=9 => Some(2),
+        _ => None,
+    };
+    let completed_at = vtodo.completed.or_else(|| vtodo.is_completed.then(Utc::now));
+
+    ImportedTodo {
+        key: vtodo.uid,
+        parent_key: vtodo.parent_uid,
+        title: with_tags(with_priority(vtodo.summary, priority), &vtodo.categories),
+        description: vtodo.description,
+        created_at: vtodo.created,
+        completed_at,
+        due_by: vtodo.due,
+        start_at: None,
+    }
+}
+
+/// Org headings nest by their number of stars. Headings without a TODO/DONE keyword
+/// are imported too, since outlines often group tasks under plain headings.
+fn org_todos(text: &str) -> Vec<ImportedTodo> {
+    // (level, key) of the headings enclosing the current one
+    let mut ancestors: Vec<(usize, String)> = Vec::new();
+    let mut todos = Vec::new();
+
+    for (index, heading) in org::parse_org(text).into_iter().enumerate() {
+        while ancestors.last().is_some_and(|(level, _)| *level >= heading.level) {
+            ancestors.pop();
+        }
+        let key = format!("org:{}", index);
+        let parent_key = ancestors.last().map(|(_, key)| key.clone());
+        ancestors.push((heading.level, key.clone()));
+
+        let priority = heading.priority.map(|letter| letter as u32 - 'A' as u32);
+        let tags: Vec<String> = heading.tags.iter().map(|tag| tag.trim_start_matches('@').to_string()).collect();
+        let completed_at = heading.is_done().then(|| heading.closed.unwrap_or_else(Utc::now));
+
+        todos.push(ImportedTodo {
+            key: Some(key),
+            parent_key,
+            title: with_tags(with_priority(heading.title, priority), &tags),
+            description: heading.body,
+            created_at: heading.created,
+            completed_at,
+            due_by: heading.deadline,
+            start_at: heading.scheduled,
+        });
+    }
+    todos
+}
+
+/// Google Takeout `Tasks.json`: task lists, each with tasks that may name a parent task.
+/// Every list becomes a parent todo
+fn google_tasks_todos(json: &Value) -> Vec<ImportedTodo> {
+    let rfc3339 = |value: Option<&Value>| {
+        DateTime::parse_from_rfc3339(value?.as_str()?).ok().map(|dt| dt.with_timezone(&Utc))
+    };
+    let text = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+
+    let mut todos = Vec::new();
+    for list in json.get("items").and_then(Value::as_array).into_iter().flatten() {
+        let list_key = format!("list:{}", text(list, "id"));
+        todos.push(ImportedTodo {
+            key: Some(list_key.clone()),
+            title: text(list, "title"),
+            ..Default::default()
+        });
+
+        for task in list.get("items").and_then(Value::as_array).into_iter().flatten() {
+            let title = text(task, "title");
+            if title.trim().is_empty() || task.get("deleted").and_then(Value::as_bool) == Some(true) {
+                continue;
+            }
+            let parent = text(task, "parent");
+            let completed_at = rfc3339(task.get("completed"))
+                .or_else(|| (text(task, "status") == "completed").then(Utc::now));
+            // Google only stores a due date (as UTC midnight); keep the end-of-day convention
+            let due_by = rfc3339(task.get("due")).and_then(|due| dates::local_to_utc(due.date_naive(), dates::END_OF_DAY, &Local));
+
+            todos.push(ImportedTodo {
+                key: Some(text(task, "id")),
+                parent_key: Some(if parent.is_empty() { list_key.clone() } else { parent }),
+                title,
+                description: text(task, "notes"),
+                created_at: rfc3339(task.get("created")),
+                completed_at,
+                due_by,
+                start_at: None,
+            });
+        }
+    }
+    todos
+}
@@ -1,24 +1,95 @@
+mod agenda;
+#[cfg(feature = "api")]
+mod api;
+mod audit;
+mod backup;
+mod caldav;
+mod capture;
+mod cli;
+mod clipboard;
+mod completions;
 mod database;
 mod ui;
+mod waiting;
 mod test;
 mod tree;
 mod tree_test;
 mod colors;
+mod config;
+mod context;
+mod dates;
+mod doctor;
 mod demo_data;
+mod editor;
+mod export;
+mod filter;
+mod flag;
+mod history;
+mod hooks;
+mod http;
+mod ical;
+mod import;
+mod merge;
+mod metrics;
+mod mirror;
+mod opener;
+mod org;
+mod passphrase;
+mod pomodoro;
+mod report;
+mod review;
+mod rpc;
+mod site;
+mod status;
+mod sync;
+mod tags;
+mod template;
+mod text;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
+    event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use config::Config;
 use database::Database;
 use demo_data::DemoDataGenerator;
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{env, io, path::PathBuf, time::Duration};
 use ui::App;
 
 fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // --encrypted may appear anywhere; take it out so the positional handling below is unchanged
+    let encrypted = args.iter().any(|arg| arg == "--encrypted");
+    args.retain(|arg| arg != "--encrypted");
+
+    // --profile <name> opens a database configured under [[profiles]] instead
+    let profile = match args.iter().position(|arg| arg == "--profile") {
+        Some(i) => {
+            let name = args.get(i + 1).cloned().ok_or_else(|| anyhow::anyhow!("--profile needs a profile name"))?;
+            args.drain(i..=i + 1);
+            Some(name)
+        }
+        None => None,
+    };
+
+    // --api <port> serves the database over HTTP instead of starting the app;
+    // --writable lets it change todos too
+    let api_listen = match args.iter().position(|arg| arg == "--api") {
+        Some(i) => {
+            let listen = args.get(i + 1).cloned().ok_or_else(|| anyhow::anyhow!("--api needs a port (or address:port)"))?;
+            args.drain(i..=i + 1);
+            // A bare port stays on this machine; phones on the network need an address
+            // such as 0.0.0.0:<port>, which `api::serve` only accepts with an [api] token
+            Some(if listen.contains(':') { listen } else { format!("127.0.0.1:{}", listen) })
+        }
+        None => None,
+    };
+    let writable = args.iter().any(|arg| arg == "--writable");
+    args.retain(|arg| arg != "--writable");
     
     
     // Check for test mode
@@ -31,6 +102,34 @@ fn main() -> anyhow::Result<()> {
         return tree_test::test_tree_functionality();
     }
     
+    // Check for CLI subcommands - handle both "list ..." and "<db_path> list ..."
+    if let Some(command_index) = cli::command_index(&args) {
+        // Completion scripts don't need a database; don't create one
+        if args[command_index] == "completions" {
+            return cli::completions(&args[command_index + 1..]);
+        }
+        let db_path = match &profile {
+            Some(name) => get_profile_db_path(name)?,
+            None => get_db_path(&args[..command_index])?,
+        };
+        let config = Config::load()?;
+        backup_before_upgrade(&db_path, encrypted, &config)?;
+        let mut database = open_database(&db_path, encrypted)?;
+        database.set_stale_days(config.review.stale_days);
+        if !custom_db_path(profile.as_deref(), &args[..command_index]) {
+            if let Some(dir) = config.mirror_dir(profile.as_deref()) {
+                database.set_mirror_dir(dir);
+            }
+            if let Some(url) = config.sync_url(profile.as_deref()) {
+                database.set_sync_url(url);
+            }
+            if let Some(url) = config.caldav_url(profile.as_deref()) {
+                database.set_caldav_url(url);
+            }
+        }
+        return cli::run(&mut database, &args[command_index..]);
+    }
+    
     // Check for demo mode - handle both "--demo" and "<db_path> --demo"
     let has_demo_flag = (args.len() > 1 && args[1] == "--demo") || 
                         (args.len() > 2 && args[2] == "--demo");
@@ -42,12 +141,34 @@ fn main() -> anyhow::Result<()> {
         return generator.populate_demo_data();
     }
     
-    let db_path = get_db_path(&args)?;
+    let db_path = match &profile {
+        Some(name) => get_profile_db_path(name)?,
+        None => get_db_path(&args)?,
+    };
+    let config = Config::load()?;
 
-    let database = Database::new(&db_path)?;
+    backup_before_upgrade(&db_path, encrypted, &config)?;
+    let mut database = open_database(&db_path, encrypted)?;
+    database.set_wal_autocheckpoint(config.checkpoint.wal_pages)?;
+    if config.audit.completion_log {
+        database.enable_completion_log();
+    }
+    database.set_stale_days(config.review.stale_days);
+    if !custom_db_path(profile.as_deref(), &args) {
+        if let Some(dir) = config.mirror_dir(profile.as_deref()) {
+            database.set_mirror_dir(dir);
+        }
+        if let Some(url) = config.sync_url(profile.as_deref()) {
+            database.set_sync_url(url);
+        }
+    }
+
+    if let Some(listen) = api_listen {
+        return serve_api(&database, &listen, writable, config);
+    }
     
     // Try to initialize terminal UI, fallback to test mode if it fails
-    match try_run_ui(database) {
+    match try_run_ui(database, config) {
         Ok(_) => Ok(()),
         Err(e) => {
             eprintln!("Failed to initialize terminal UI: {}", e);
@@ -58,57 +179,172 @@ fn main() -> anyhow::Result<()> {
     }
 }
 
-fn try_run_ui(database: Database) -> anyhow::Result<()> {
-    let mut app = App::new(database)?;
+#[cfg(not(feature = "encryption"))]
+const NO_ENCRYPTION: &str = "This build has no encryption support; rebuild with: cargo build --release --features encryption";
+
+#[cfg(not(feature = "api"))]
+const NO_API: &str = "This build has no web API; rebuild with: cargo build --release --features api";
+
+/// `--api`: serve the web view and REST API instead of starting the app
+#[cfg_attr(not(feature = "api"), allow(unused_variables))]
+fn serve_api(database: &Database, listen: &str, writable: bool, config: Config) -> anyhow::Result<()> {
+    #[cfg(feature = "api")]
+    {
+        let options = api::ApiOptions { writable, token: config.api.token.clone() };
+        api::serve(database, listen, &options, config.hooks)
+    }
+    #[cfg(not(feature = "api"))]
+    Err(anyhow::anyhow!("{}", NO_API))
+}
+
+/// Open the database, asking for its passphrase first when started with --encrypted
+fn open_database(db_path: &str, encrypted: bool) -> anyhow::Result<Database> {
+    if !encrypted {
+        return Database::new(db_path);
+    }
+    #[cfg(feature = "encryption")]
+    {
+        let passphrase = if std::path::Path::new(db_path).exists() {
+            passphrase::read_passphrase("Passphrase: ")?
+        } else {
+            println!("Creating encrypted database {}", db_path);
+            passphrase::new_passphrase()?
+        };
+        Database::new_encrypted(db_path, &passphrase)
+    }
+    #[cfg(not(feature = "encryption"))]
+    Err(anyhow::anyhow!("{}", NO_ENCRYPTION))
+}
+
+/// Back up a database that opening it is about to upgrade (see `[backup] auto`); an
+/// encrypted one can't be read before its passphrase is given, so it isn't checked
+fn backup_before_upgrade(db_path: &str, encrypted: bool, config: &Config) -> anyhow::Result<()> {
+    if !encrypted
+        && let Some(path) = backup::backup_before_upgrade(db_path, config)? {
+        eprintln!("Upgrading {}; the previous version is backed up to {}", db_path, path.display());
+    }
+    Ok(())
+}
+
+fn try_run_ui(database: Database, config: Config) -> anyhow::Result<()> {
+    let mut app = App::new(database, config)?;
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_app(&mut terminal, &mut app);
+    // A SIGTSTP from outside (`kill -TSTP`) is caught so the app can flush and give
+    // the terminal back before stopping
+    #[cfg(unix)]
+    let stop_signal = {
+        let flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGTSTP, flag.clone())?;
+        flag
+    };
+    #[cfg(not(unix))]
+    let stop_signal = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    let result = run_app(&mut terminal, &mut app, &stop_signal);
 
     // Ensure data is written to disk before exit
+    let _ = app.save_view_state();
+    app.remove_editor_files();
+    app.end_session();
     let _ = app.database.checkpoint_and_close();
 
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
+    for error in app.wait_for_hooks() {
+        eprintln!("Hook failed: {}", error);
+    }
     result
 }
 
 fn run_app<B: ratatui::backend::Backend + std::io::Write>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    stop_signal: &AtomicBool,
 ) -> anyhow::Result<()> {
     loop {
         // Check if editor should be launched
-        if let Some(todo) = app.editor_pending.take() {
-            if let Err(e) = app.launch_editor(&todo, terminal) {
-                app.error_message = Some(format!("Editor error: {}", e));
-            }
+        if let Some(todo) = app.editor_pending.take()
+            && let Err(e) = app.launch_editor(&todo, terminal) {
+            app.error_message = Some(format!("Editor error: {}", e));
         }
         
         terminal.draw(|f| app.draw(f))?;
 
-        // Poll with 60-second timeout to allow periodic redraws for due date color updates
-        if event::poll(Duration::from_secs(60))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    app.handle_key_event(key.code, key.modifiers)?;
-                    if app.should_quit {
-                        break;
-                    }
+        // Wake up every tick to redraw (due date colors, started todos, messages),
+        // or earlier if the idle lock or a background sync is due, or hooks are running
+        let timeout = [app.time_until_lock(), app.time_until_sync(), app.time_until_hooks()]
+            .into_iter()
+            .flatten()
+            .fold(app.tick_rate(), Duration::min)
+            // Polling doesn't return for signals, so look for a SIGTSTP every second
+            .min(Duration::from_secs(1));
+        if event::poll(timeout)? {
+            let handled = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key_event(key.code, key.modifiers),
+                Event::Paste(text) => app.handle_paste(&text),
+                _ => Ok(()),
+            };
+            // Another instance holding the write lock for long isn't a reason to quit
+            match handled {
+                Err(e) if database::is_busy(&e) => {
+                    app.error_message = Some("The database is busy (another tododb is writing to it); try again".to_string());
                 }
+                result => result?,
             }
+            app.process_changes()?;
+            if app.should_quit {
+                break;
+            }
+        }
+        if std::mem::take(&mut app.suspend_pending) | stop_signal.swap(false, Ordering::Relaxed) {
+            suspend(terminal, app)?;
         }
-        // If timeout occurs (no user input for 60 seconds), loop continues and redraws
+        // If timeout occurs (no user input), lock if idle long enough, then loop continues and redraws
+        app.check_idle_lock();
+        app.check_background_sync();
+        app.check_hooks();
+        app.tick()?;
+    }
+    Ok(())
+}
+
+/// Stop like any shell job, flushing first (`[checkpoint] on_suspend`); the terminal
+/// is the shell's until `fg` continues the process
+#[cfg(unix)]
+fn suspend<B: ratatui::backend::Backend + std::io::Write>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<()> {
+    if app.config.checkpoint.on_suspend {
+        app.flush();
+    }
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste)?;
+    terminal.show_cursor()?;
+
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    terminal.clear()?;
+    Ok(())
+}
+
+/// No job control here; Ctrl+Z only flushes
+#[cfg(not(unix))]
+fn suspend<B: ratatui::backend::Backend + std::io::Write>(_terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<()> {
+    if app.config.checkpoint.on_suspend {
+        app.flush();
     }
     Ok(())
 }
@@ -118,6 +354,16 @@ fn get_demo_db_path() -> anyhow::Result<String> {
     Ok("demo_todos.db".to_string())
 }
 
+fn get_profile_db_path(name: &str) -> anyhow::Result<String> {
+    Config::load()?.profile(name)?.open_path()
+}
+
+/// Whether the database was given by path rather than being the default one or a
+/// profile's. Mirror and sync settings only apply to the latter two.
+fn custom_db_path(profile: Option<&str>, args: &[String]) -> bool {
+    profile.is_none() && args.len() > 1 && !args[1].starts_with("--")
+}
+
 fn get_db_path(args: &[String]) -> anyhow::Result<String> {
     let db_path = if args.len() > 1 && !args[1].starts_with("--") {
         // Custom database path provided (not a flag)
@@ -0,0 +1,258 @@
+use crate::database::Database;
+use crate::history::{self, HistoryEntry};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+/// The columns a merge compares, as `history` records them: `parent` is the parent's uid
+/// (ids differ between copies), the rest are `todos` columns
+pub fn fields() -> impl Iterator<Item = &'static str> {
+    ["parent", "created_at", "completed_at"].into_iter().chain(history::TRACKED_FIELDS.iter().map(|(column, _)| *column))
+}
+
+/// How a merge field is shown in the review prompt
+pub fn field_label(field: &str) -> &str {
+    match field {
+        "parent" => "Parent",
+        "created_at" => "Created",
+        "completed_at" => "Completed",
+        _ => history::TRACKED_FIELDS.iter().find(|(column, _)| *column == field).map_or(field, |(_, label)| label),
+    }
+}
+
+/// One todo in one copy, its `fields()` as text
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeRow {
+    pub uid: String,
+    pub updated_at: DateTime<Utc>,
+    pub values: BTreeMap<&'static str, Option<String>>,
+}
+
+impl MergeRow {
+    pub fn value(&self, field: &str) -> Option<&str> {
+        self.values.get(field).and_then(|value| value.as_deref())
+    }
+}
+
+/// A change to write into this copy
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeChange {
+    Create(MergeRow),
+    Update { uid: String, field: &'static str, value: Option<String> },
+    Delete(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictKind {
+    /// Both copies changed the field, to different values
+    Field { field: &'static str, here: Option<String>, there: Option<String> },
+    /// Deleted here, edited in the other copy, which still has this version of it
+    DeletedHere(MergeRow),
+    /// Edited here, deleted in the other copy
+    DeletedThere,
+}
+
+/// Something both copies changed since they diverged, for the review prompt. Unless
+/// overridden, the later change wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub uid: String,
+    pub title: String,
+    pub kind: ConflictKind,
+    pub here_at: DateTime<Utc>,
+    pub there_at: DateTime<Utc>,
+}
+
+impl Conflict {
+    /// Whether the timestamps pick the other copy's side; ties keep this one
+    pub fn there_is_newer(&self) -> bool {
+        self.there_at > self.here_at
+    }
+
+    /// What taking the other copy's side writes here
+    fn take_there(&self) -> MergeChange {
+        match &self.kind {
+            ConflictKind::Field { field, there, .. } => MergeChange::Update { uid: self.uid.clone(), field, value: there.clone() },
+            ConflictKind::DeletedHere(row) => MergeChange::Create(row.clone()),
+            ConflictKind::DeletedThere => MergeChange::Delete(self.uid.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MergePlan {
+    /// Changes made only in the other copy, taken without asking
+    pub changes: Vec<MergeChange>,
+    pub conflicts: Vec<Conflict>,
+    /// Every todo's title in either copy by uid, to show parents in the review prompt
+    pub titles: HashMap<String, String>,
+}
+
+impl MergePlan {
+    /// The changes to write once every conflict is decided by `take_there`, ordered so
+    /// parents are created before their children and deletions come last
+    pub fn resolve(self, mut take_there: impl FnMut(&Conflict) -> bool) -> Vec<MergeChange> {
+        let mut changes = self.changes;
+        for conflict in &self.conflicts {
+            if take_there(conflict) {
+                changes.push(conflict.take_there());
+            }
+        }
+        let parents: HashMap<String, String> = changes
+            .iter()
+            .filter_map(|change| match change {
+                MergeChange::Create(row) => Some((row.uid.clone(), row.value("parent")?.to_string())),
+                _ => None,
+            })
+            .collect();
+        let depth = |uid: &str| {
+            let mut depth = 0;
+            let mut current = uid;
+            while let Some(parent) = parents.get(current) {
+                depth += 1;
+                current = parent;
+                if depth > parents.len() {
+                    break;
+                }
+            }
+            depth
+        };
+        changes.sort_by_key(|change| match change {
+            MergeChange::Create(row) => (0, depth(&row.uid)),
+            MergeChange::Update { .. } => (1, 0),
+            MergeChange::Delete(_) => (2, 0),
+        });
+        changes
+    }
+}
+
+/// Where the two histories part: the number of leading entries they share. Ids are left
+/// out, since an earlier merge numbers todos differently in each copy.
+fn common_prefix(here: &[HistoryEntry], there: &[HistoryEntry]) -> usize {
+    let same = |here: &HistoryEntry, there: &HistoryEntry| HistoryEntry { id: there.id, todo_id: there.todo_id, ..here.clone() } == *there;
+    here.iter().zip(there).take_while(|(here, there)| same(here, there)).count()
+}
+
+/// When each (uid, field) was last changed in `entries`
+fn changed_fields(entries: &[HistoryEntry], uids: &HashMap<i64, String>) -> HashMap<(String, &'static str), DateTime<Utc>> {
+    let mut changed = HashMap::new();
+    for entry in entries {
+        let Some(uid) = uids.get(&entry.todo_id) else {
+            continue;
+        };
+        let field = match entry.action.as_str() {
+            "complete" | "uncomplete" => Some("completed_at"),
+            "move" => Some("parent"),
+            "update" => entry.field.as_deref().and_then(|field| fields().find(|known| *known == field)),
+            _ => None,
+        };
+        if let Some(field) = field {
+            changed.insert((uid.clone(), field), entry.at);
+        }
+    }
+    changed
+}
+
+/// Both copies' state and what changed in each since the histories parted
+struct Side {
+    rows: HashMap<String, MergeRow>,
+    deleted: HashMap<String, DateTime<Utc>>,
+    changed: HashMap<(String, &'static str), DateTime<Utc>>,
+}
+
+impl Side {
+    fn load(database: &Database) -> anyhow::Result<(Self, Vec<HistoryEntry>, HashMap<i64, String>)> {
+        let mut rows = HashMap::new();
+        let mut uids = HashMap::new();
+        for (id, row) in database.get_merge_rows()? {
+            uids.insert(id, row.uid.clone());
+            rows.insert(row.uid.clone(), row);
+        }
+        let deleted = database
+            .get_sync_records()?
+            .into_iter()
+            .filter(|record| record.todo.is_none())
+            .map(|record| (record.uid, record.updated_at))
+            .collect();
+        let mut entries = database.get_history(None, i64::MAX as usize)?;
+        entries.reverse();
+        Ok((Self { rows, deleted, changed: HashMap::new() }, entries, uids))
+    }
+
+    /// The latest change to `uid` since the histories parted
+    fn last_change(&self, uid: &str) -> Option<DateTime<Utc>> {
+        self.changed.iter().filter(|((changed, _), _)| changed == uid).map(|(_, at)| *at).max()
+    }
+}
+
+/// Three-way merge of `other` into `database`, with the shared start of both histories as
+/// the common ancestor. A change made in only one copy is taken as is; a field both copies
+/// changed differently, or a todo edited in one and deleted in the other, is a conflict.
+pub fn plan(database: &Database, other: &Database) -> anyhow::Result<MergePlan> {
+    let (mut here, here_history, here_uids) = Side::load(database)?;
+    let (mut there, there_history, there_uids) = Side::load(other)?;
+    let base = common_prefix(&here_history, &there_history);
+    here.changed = changed_fields(&here_history[base..], &here_uids);
+    there.changed = changed_fields(&there_history[base..], &there_uids);
+
+    let mut plan = MergePlan::default();
+    for row in there.rows.values().chain(here.rows.values()) {
+        plan.titles.insert(row.uid.clone(), row.value("title").unwrap_or_default().to_string());
+    }
+    let mut uids: Vec<&String> = there.rows.keys().collect();
+    uids.sort();
+    for uid in uids {
+        let theirs = &there.rows[uid];
+        let title = theirs.value("title").unwrap_or_default().to_string();
+        let Some(ours) = here.rows.get(uid) else {
+            match (here.deleted.get(uid), there.last_change(uid)) {
+                // Created in the other copy
+                (None, _) => plan.changes.push(MergeChange::Create(theirs.clone())),
+                (Some(&deleted_at), Some(edited_at)) => plan.conflicts.push(Conflict {
+                    uid: uid.clone(),
+                    title,
+                    kind: ConflictKind::DeletedHere(theirs.clone()),
+                    here_at: deleted_at,
+                    there_at: edited_at,
+                }),
+                // Deleted here and left alone there
+                (Some(_), None) => {}
+            }
+            continue;
+        };
+        for field in fields() {
+            let (mine, yours) = (ours.values.get(field).cloned().flatten(), theirs.values.get(field).cloned().flatten());
+            if mine == yours {
+                continue;
+            }
+            let key = (uid.clone(), field);
+            match (here.changed.get(&key), there.changed.get(&key)) {
+                (Some(_), None) => {}
+                (None, Some(_)) => plan.changes.push(MergeChange::Update { uid: uid.clone(), field, value: yours }),
+                // Both changed it, or the histories don't say who did
+                (here_at, there_at) => plan.conflicts.push(Conflict {
+                    uid: uid.clone(),
+                    title: ours.value("title").unwrap_or_default().to_string(),
+                    kind: ConflictKind::Field { field, here: mine, there: yours },
+                    here_at: here_at.copied().unwrap_or(ours.updated_at),
+                    there_at: there_at.copied().unwrap_or(theirs.updated_at),
+                }),
+            }
+        }
+    }
+
+    let mut deleted_there: Vec<(&String, &DateTime<Utc>)> = there.deleted.iter().filter(|(uid, _)| here.rows.contains_key(*uid)).collect();
+    deleted_there.sort();
+    for (uid, &deleted_at) in deleted_there {
+        match here.last_change(uid) {
+            Some(edited_at) => plan.conflicts.push(Conflict {
+                uid: uid.clone(),
+                title: here.rows[uid].value("title").unwrap_or_default().to_string(),
+                kind: ConflictKind::DeletedThere,
+                here_at: edited_at,
+                there_at: deleted_at,
+            }),
+            None => plan.changes.push(MergeChange::Delete(uid.clone())),
+        }
+    }
+    Ok(plan)
+}
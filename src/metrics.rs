@@ -0,0 +1,71 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc, Weekday};
+
+/// One run of the app against a database, kept in its `sessions` table when
+/// `[metrics] enabled` is on. Nothing here is ever sent anywhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    /// Last time the session was saved; the exit time once the app has quit
+    pub ended_at: DateTime<Utc>,
+    pub created: i64,
+    /// Completions minus undone completions, so toggling a todo back and forth counts once
+    pub completed: i64,
+}
+
+impl Session {
+    pub fn duration(&self) -> Duration {
+        (self.ended_at - self.started_at).max(Duration::zero())
+    }
+}
+
+/// Session totals for one week, Monday to Sunday in local time
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekTrend {
+    pub start: NaiveDate,
+    pub sessions: usize,
+    pub time: Duration,
+    pub created: i64,
+    pub completed: i64,
+}
+
+/// The day a local date's week starts on, weeks starting on `first`
+pub fn week_start(date: NaiveDate, first: Weekday) -> NaiveDate {
+    date - Duration::days(date.weekday().days_since(first) as i64)
+}
+
+/// Totals for the last `weeks` weeks including the current one, oldest first. Each
+/// session counts towards the week it started in; weeks without sessions are zero.
+pub fn weekly_trends(sessions: &[Session], now: DateTime<Utc>, weeks: usize, first: Weekday) -> Vec<WeekTrend> {
+    let this_week = week_start(now.with_timezone(&Local).date_naive(), first);
+    let mut trends: Vec<WeekTrend> = (0..weeks)
+        .rev()
+        .map(|ago| WeekTrend {
+            start: this_week - Duration::weeks(ago as i64),
+            sessions: 0,
+            time: Duration::zero(),
+            created: 0,
+            completed: 0,
+        })
+        .collect();
+    for session in sessions {
+        let start = week_start(session.started_at.with_timezone(&Local).date_naive(), first);
+        if let Some(trend) = trends.iter_mut().find(|trend| trend.start == start) {
+            trend.sessions += 1;
+            trend.time += session.duration();
+            trend.created += session.created;
+            trend.completed += session.completed;
+        }
+    }
+    trends
+}
+
+/// "2h 05m", or "45m" under an hour
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
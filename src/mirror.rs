@@ -0,0 +1,164 @@
+use crate::database::{Database, Todo};
+use crate::status::Status;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Front matter of a mirrored todo file
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<i64>,
+    created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<String>,
+}
+
+/// Render a todo as its mirror file: TOML front matter between `+++` lines, then
+/// the title as a heading and the description as the body
+///
+/// ```text
+/// +++
+/// id = 12
+/// parent = 3
+/// created = "2025-01-06T09:30:00Z"
+/// +++
+/// # Tag the release
+///
+/// Push the tag once CI is green.
+/// ```
+pub fn todo_to_file(todo: &Todo, status: Option<Status>, attachments: Vec<String>) -> anyhow::Result<String> {
+    let header = Header {
+        id: todo.id,
+        parent: todo.parent_id,
+        created: todo.created_at,
+        completed: todo.completed_at,
+        due: todo.due_by,
+        start: todo.start_at,
+        hidden: todo.hidden,
+        status,
+        attachments,
+    };
+    let mut file = format!("+++\n{}+++\n# {}\n", toml::to_string(&header)?, todo.title);
+    if !todo.description.is_empty() {
+        file.push('\n');
+        file.push_str(&todo.description);
+        file.push('\n');
+    }
+    Ok(file)
+}
+
+/// Read back a mirror file as the todo, its stored status and its attachment targets
+pub fn parse_todo_file(content: &str) -> anyhow::Result<(Todo, Option<Status>, Vec<String>)> {
+    let rest = content.strip_prefix("+++\n").ok_or_else(|| anyhow::anyhow!("missing +++ front matter"))?;
+    let (front_matter, body) = rest.split_once("\n+++\n").ok_or_else(|| anyhow::anyhow!("unterminated front matter"))?;
+    let header: Header = toml::from_str(front_matter)?;
+
+    let (title_line, description) = body.split_once('\n').unwrap_or((body, ""));
+    let title = title_line.strip_prefix("# ").ok_or_else(|| anyhow::anyhow!("missing '# title' line"))?;
+    let description = description.strip_prefix('\n').unwrap_or(description);
+    let description = description.strip_suffix('\n').unwrap_or(description);
+
+    let todo = Todo {
+        id: header.id,
+        title: title.to_string(),
+        description: description.to_string(),
+        created_at: header.created,
+        completed_at: header.completed,
+        due_by: header.due,
+        parent_id: header.parent,
+        hidden: header.hidden,
+        start_at: header.start,
+    };
+    Ok((todo, header.status, header.attachments))
+}
+
+/// Mirror files are named by todo id, so renames and moves show up as edits
+fn file_name(id: i64) -> String {
+    format!("{}.md", id)
+}
+
+fn is_mirror_file(name: &str) -> bool {
+    name.strip_suffix(".md").is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Write one file per todo into `dir`, leaving unchanged files alone and removing
+/// the files of deleted todos. Returns the number of files written or removed.
+pub fn write_mirror(database: &Database, dir: &Path) -> anyhow::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut changed = 0;
+    let mut current = HashSet::new();
+    let statuses = database.get_statuses()?;
+
+    for todo in database.get_all_todos()? {
+        let attachments = database.get_attachments(todo.id)?.into_iter().map(|attachment| attachment.target).collect();
+        let content = todo_to_file(&todo, statuses.get(&todo.id).copied(), attachments)?;
+        let name = file_name(todo.id);
+        let path = dir.join(&name);
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&path, content)?;
+            changed += 1;
+        }
+        current.insert(name);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if is_mirror_file(&name) && !current.contains(&name) {
+            fs::remove_file(dir.join(&name))?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Load every todo file in `dir` into an empty database, keeping ids.
+/// Returns the number of todos restored.
+pub fn rebuild(database: &Database, dir: &Path) -> anyhow::Result<usize> {
+    let existing = database.get_all_todos()?.len();
+    if existing > 0 {
+        return Err(anyhow::anyhow!("Database already has {} todos; rebuild into a new file: tododb new.db rebuild {}", existing, dir.display()));
+    }
+
+    let mut todos = Vec::new();
+    let mut statuses = HashMap::new();
+    let mut attachments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.file_name().is_some_and(|name| is_mirror_file(&name.to_string_lossy())) {
+            continue;
+        }
+        let (todo, status, targets) = parse_todo_file(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        if let Some(status) = status {
+            statuses.insert(todo.id, status);
+        }
+        attachments.extend(targets.into_iter().map(|target| (todo.id, target)));
+        todos.push(todo);
+    }
+    todos.sort_by_key(|todo| todo.id);
+
+    let ids: HashSet<i64> = todos.iter().map(|todo| todo.id).collect();
+    if let Some(orphan) = todos.iter().find(|todo| todo.parent_id.is_some_and(|parent| !ids.contains(&parent))) {
+        return Err(anyhow::anyhow!("Todo {} refers to missing parent {}", orphan.id, orphan.parent_id.unwrap_or_default()));
+    }
+
+    database.insert_todos_with_ids(&todos, &statuses)?;
+    for (todo_id, target) in attachments {
+        database.add_attachment(todo_id, &target)?;
+    }
+    Ok(todos.len())
+}
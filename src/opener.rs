@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::{env, thread};
+
+/// Open a file or URL with the desktop's default application without blocking the UI
+pub fn open(target: &str) -> anyhow::Result<()> {
+    let program = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+
+    let mut child = Command::new(program)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
+
+    // Reap the opener in the background so it doesn't linger as a zombie
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Turn user input into a stored attachment target: URLs are kept as-is, file paths
+/// are expanded (`~/`) and made absolute so they still resolve from another directory
+pub fn normalize_target(input: &str) -> anyhow::Result<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(anyhow::anyhow!("Attachment path or URL is empty"));
+    }
+    if input.contains("://") || input.starts_with("mailto:") {
+        return Ok(input.to_string());
+    }
+
+    let path = match input.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(rest),
+        None => PathBuf::from(input),
+    };
+    let path = if path.is_absolute() { path } else { env::current_dir()?.join(path) };
+
+    if !path.exists() {
+        return Err(anyhow::anyhow!("File not found: {}", path.display()));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
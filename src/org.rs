@@ -0,0 +1,246 @@
+use crate::database::Todo;
+use crate::dates;
+use crate::tags;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Keywords read as finished; any other keyword (TODO, NEXT, WAITING, ...) is open
+const DONE_KEYWORDS: &[&str] = &["DONE", "CANCELLED", "CANCELED"];
+const OPEN_KEYWORDS: &[&str] = &["TODO", "NEXT", "STARTED", "WAITING", "HOLD", "SOMEDAY"];
+
+/// The parts of an org heading that map onto a todo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrgHeading {
+    /// Number of stars
+    pub level: usize,
+    pub keyword: Option<String>,
+    /// `[#A]` is 'A'
+    pub priority: Option<char>,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub deadline: Option<DateTime<Utc>>,
+    pub scheduled: Option<DateTime<Utc>>,
+    pub closed: Option<DateTime<Utc>>,
+    /// The `:CREATED:` property
+    pub created: Option<DateTime<Utc>>,
+    /// Text under the heading, without planning lines and drawers
+    pub body: String,
+}
+
+impl OrgHeading {
+    pub fn is_done(&self) -> bool {
+        self.keyword.as_deref().is_some_and(|keyword| DONE_KEYWORDS.contains(&keyword))
+    }
+}
+
+/// Every heading in an org document, in order. Text before the first heading is skipped.
+pub fn parse_org(text: &str) -> Vec<OrgHeading> {
+    let heading = Regex::new(r"^(\*+)\s+(.*?)\s*$").expect("valid regex");
+    let tags_suffix = Regex::new(r"\s+:([\w@#%:]+):$").expect("valid regex");
+    let planning = Regex::new(r"(SCHEDULED|DEADLINE|CLOSED):\s*([<\[][^>\]]*[>\]])").expect("valid regex");
+    let drawer = Regex::new(r"^:([\w-]+):\s*(.*)$").expect("valid regex");
+
+    let mut headings: Vec<OrgHeading> = Vec::new();
+    let mut body_lines: Vec<&str> = Vec::new();
+    let mut in_drawer = false;
+
+    for line in text.lines() {
+        if let Some(caps) = heading.captures(line) {
+            if let Some(last) = headings.last_mut() {
+                last.body = dedent(&body_lines);
+            }
+            body_lines.clear();
+            in_drawer = false;
+
+            let mut rest = caps[2].to_string();
+            let mut entry = OrgHeading { level: caps[1].len(), ..OrgHeading::default() };
+            if let Some(tag_caps) = tags_suffix.captures(&rest) {
+                entry.tags = tag_caps[1].split(':').filter(|tag| !tag.is_empty()).map(str::to_string).collect();
+                rest.truncate(tag_caps.get(0).map_or(rest.len(), |m| m.start()));
+            }
+            if let Some((word, after)) = rest.split_once(' ').or(Some((rest.as_str(), "")))
+                && (DONE_KEYWORDS.contains(&word) || OPEN_KEYWORDS.contains(&word)) {
+                entry.keyword = Some(word.to_string());
+                rest = after.trim_start().to_string();
+            }
+            if let Some(after) = rest.strip_prefix("[#")
+                && let Some(letter) = after.chars().next().filter(char::is_ascii_uppercase)
+                && after[1..].starts_with(']') {
+                entry.priority = Some(letter);
+                rest = after[2..].trim_start().to_string();
+            }
+            entry.title = rest.trim().to_string();
+            headings.push(entry);
+            continue;
+        }
+        let Some(current) = headings.last_mut() else { continue };
+        let trimmed = line.trim();
+
+        if in_drawer {
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                in_drawer = false;
+            } else if let Some(caps) = drawer.captures(trimmed)
+                && caps[1].eq_ignore_ascii_case("CREATED") {
+                current.created = parse_timestamp(&caps[2], false);
+            }
+            continue;
+        }
+        // Planning and drawers only count right after the heading, before any body text
+        if body_lines.iter().all(|line| line.trim().is_empty()) {
+            if planning.is_match(trimmed) && planning.replace_all(trimmed, "").trim().is_empty() {
+                for caps in planning.captures_iter(trimmed) {
+                    let at = parse_timestamp(&caps[2], &caps[1] == "DEADLINE");
+                    match &caps[1] {
+                        "SCHEDULED" => current.scheduled = at,
+                        "DEADLINE" => current.deadline = at,
+                        _ => current.closed = at,
+                    }
+                }
+                continue;
+            }
+            if let Some(caps) = drawer.captures(trimmed)
+                && caps[2].is_empty()
+                && !caps[1].eq_ignore_ascii_case("END") {
+                in_drawer = true;
+                continue;
+            }
+        }
+        body_lines.push(line);
+    }
+    if let Some(last) = headings.last_mut() {
+        last.body = dedent(&body_lines);
+    }
+    headings
+}
+
+/// `<2025-03-01 Sat 17:00>` or `[2025-03-01 Sat]`, in local time. A deadline without
+/// a time is the end of that day, like due dates typed in the app; other dates start it.
+fn parse_timestamp(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let inner = value.trim().trim_start_matches(['<', '[']).trim_end_matches(['>', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    // The day name is optional, and repeaters (+1w) and ranges are ignored
+    let time = parts.find(|part| part.contains(':')).and_then(|time| time.split('-').next());
+    match time {
+        Some(time) => {
+            let at = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").ok()?;
+            Local.from_local_datetime(&at).earliest().map(|at| at.with_timezone(&Utc))
+        }
+        None if end_of_day => dates::local_to_utc(date, dates::END_OF_DAY, &Local),
+        None => dates::local_to_utc(date, NaiveTime::MIN, &Local),
+    }
+}
+
+/// Body lines without the indentation they share, and without blank lines around them
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: Vec<&str> = lines.iter().map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start())).collect();
+    text.join("\n").trim_matches('\n').trim_end().to_string()
+}
+
+fn timestamp(at: DateTime<Utc>, active: bool) -> String {
+    let (open, close) = if active { ('<', '>') } else { ('[', ']') };
+    format!("{}{}{}", open, at.with_timezone(&Local).format("%Y-%m-%d %a %H:%M"), close)
+}
+
+/// The todos as an org outline: one heading per todo, nested like the tree, oldest
+/// first. `p1` title prefixes become `[#B]`, `#tags` org tags, due dates DEADLINE,
+/// start dates SCHEDULED, completion CLOSED, and the creation time a CREATED property. Descriptions are
+/// indented under their heading so a line starting with `*` stays body text.
+pub fn write_org(todos: &[Todo]) -> String {
+    let ids: std::collections::HashSet<i64> = todos.iter().map(|todo| todo.id).collect();
+    let mut children: HashMap<Option<i64>, Vec<&Todo>> = HashMap::new();
+    for todo in todos {
+        // A todo whose parent isn't exported starts a tree of its own
+        let parent = todo.parent_id.filter(|id| ids.contains(id));
+        children.entry(parent).or_default().push(todo);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|todo| (todo.created_at, todo.id));
+    }
+
+    let mut org = String::new();
+    let mut stack: Vec<(&Todo, usize)> = children.get(&None).map_or(Vec::new(), |roots| roots.iter().rev().map(|todo| (*todo, 1)).collect());
+    while let Some((todo, level)) = stack.pop() {
+        write_heading(&mut org, todo, level);
+        if let Some(subtasks) = children.get(&Some(todo.id)) {
+            stack.extend(subtasks.iter().rev().map(|subtask| (*subtask, level + 1)));
+        }
+    }
+    org
+}
+
+fn write_heading(org: &mut String, todo: &Todo, level: usize) {
+    let mut words: Vec<&str> = todo.title.split_whitespace().collect();
+    let mut priority = None;
+    if let Some(level) = words.first().and_then(|word| word.strip_prefix('p').or_else(|| word.strip_prefix('P')))
+        && let Ok(level) = level.parse::<u8>()
+        && level < 26 {
+        priority = Some((b'A' + level) as char);
+        words.remove(0);
+    }
+    // Tags org can hold move into the tag list; others (with a '-') stay in the title
+    let mut org_tags = Vec::new();
+    words.retain(|word| match tags::extract_tags(word).first() {
+        Some(tag) if *word == format!("#{}", tag) && tag.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+            org_tags.push(tag.to_string());
+            false
+        }
+        _ => true,
+    });
+
+    org.push_str(&"*".repeat(level));
+    org.push_str(if todo.is_completed() { " DONE" } else { " TODO" });
+    if let Some(priority) = priority {
+        org.push_str(&format!(" [#{}]", priority));
+    }
+    org.push(' ');
+    org.push_str(&words.join(" "));
+    if !org_tags.is_empty() {
+        org.push_str(&format!(" :{}:", org_tags.join(":")));
+    }
+    org.push('\n');
+
+    let indent = " ".repeat(level + 1);
+    let mut planning = Vec::new();
+    if let Some(completed_at) = todo.completed_at {
+        planning.push(format!("CLOSED: {}", timestamp(completed_at, false)));
+    }
+    if let Some(due) = todo.due_by {
+        // Due dates set without a time are due at the end of the local day; keep them date-only
+        let local = due.with_timezone(&Local);
+        let deadline = if local.time() == dates::END_OF_DAY {
+            format!("<{}>", local.format("%Y-%m-%d %a"))
+        } else {
+            timestamp(due, true)
+        };
+        planning.push(format!("DEADLINE: {}", deadline));
+    }
+    if let Some(start_at) = todo.start_at {
+        // Start dates set without a time begin at local midnight; keep them date-only
+        let local = start_at.with_timezone(&Local);
+        let scheduled = if local.time() == NaiveTime::MIN {
+            format!("<{}>", local.format("%Y-%m-%d %a"))
+        } else {
+            timestamp(start_at, true)
+        };
+        planning.push(format!("SCHEDULED: {}", scheduled));
+    }
+    if !planning.is_empty() {
+        org.push_str(&format!("{}{}\n", indent, planning.join(" ")));
+    }
+    org.push_str(&format!("{0}:PROPERTIES:\n{0}:CREATED:  {1}\n{0}:END:\n", indent, timestamp(todo.created_at, false)));
+    for line in todo.description.lines() {
+        if line.trim().is_empty() {
+            org.push('\n');
+        } else {
+            org.push_str(&format!("{}{}\n", indent, line));
+        }
+    }
+}
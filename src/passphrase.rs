@@ -0,0 +1,114 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::env;
+use std::io::{self, Write};
+
+/// Ask for a new passphrase twice. TODODB_PASSPHRASE, when set, is used instead of prompting.
+pub fn new_passphrase() -> anyhow::Result<String> {
+    if let Ok(passphrase) = env::var("TODODB_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = read_passphrase("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("Passphrase cannot be empty"));
+    }
+    if read_passphrase("Repeat passphrase: ")? != passphrase {
+        return Err(anyhow::anyhow!("Passphrases don't match"));
+    }
+    Ok(passphrase)
+}
+
+/// Read a passphrase from the terminal without echoing it, or from TODODB_PASSPHRASE for scripts
+pub fn read_passphrase(prompt: &str) -> anyhow::Result<String> {
+    if let Ok(passphrase) = env::var("TODODB_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    enable_raw_mode()?;
+    let mut passphrase = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(anyhow::anyhow!("Cancelled")),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Err(anyhow::anyhow!("Cancelled")),
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Char(c) => passphrase.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    disable_raw_mode()?;
+    eprintln!();
+    result.map(|_| passphrase)
+}
+
+/// PBKDF2-HMAC-SHA256 rounds for a new `[lock] passphrase_hash`
+const LOCK_HASH_ROUNDS: u32 = 600_000;
+
+/// A `[lock] passphrase_hash` value for `passphrase`, salted and slow to brute-force:
+/// `pbkdf2-sha256$<rounds>$<salt>$<hash>`, salt and hash in hex
+pub fn hash_lock_passphrase(passphrase: &str) -> String {
+    hash_lock_passphrase_with(passphrase, &new_salt(), LOCK_HASH_ROUNDS)
+}
+
+pub fn hash_lock_passphrase_with(passphrase: &str, salt: &[u8], rounds: u32) -> String {
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, rounds, &mut hash);
+    format!("pbkdf2-sha256${}${}${}", rounds, to_hex(salt), to_hex(&hash))
+}
+
+/// Whether `passphrase` is the one a `passphrase_hash` value was made from; a value
+/// that doesn't parse matches nothing
+pub fn verify_lock_passphrase(passphrase: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.trim().split('$').collect();
+    let ["pbkdf2-sha256", rounds, salt, hash] = parts.as_slice() else {
+        return false;
+    };
+    let (Ok(rounds), Some(salt), Some(expected)) = (rounds.parse::<u32>(), from_hex(salt), from_hex(hash)) else {
+        return false;
+    };
+    if rounds == 0 || expected.is_empty() {
+        return false;
+    }
+    let mut hash = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), &salt, rounds, &mut hash);
+    constant_time_eq(&hash, &expected)
+}
+
+/// Compare secrets without returning early at the first difference, so the time taken
+/// doesn't tell how much of a guess was right
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 16 bytes that differ on every call. A salt only has to be unique, and the standard
+/// library's hasher keys come from the operating system's random source.
+fn new_salt() -> [u8; 16] {
+    use std::hash::{BuildHasher, Hasher};
+    let mut salt = [0u8; 16];
+    for (i, chunk) in salt.chunks_mut(8).enumerate() {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_usize(i);
+        hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    salt
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
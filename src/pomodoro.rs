@@ -0,0 +1,75 @@
+use chrono::{DateTime, Duration, Utc};
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+/// A pomodoro cycle bound to one todo: a work interval, then a break
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pomodoro {
+    pub todo_id: i64,
+    pub phase: Phase,
+    /// When the current phase started
+    pub phase_started: DateTime<Utc>,
+    pub phase_length: Duration,
+}
+
+/// What happened when a phase ran out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// The work interval that started at `work_started` is done; the break has begun
+    BreakStarted { work_started: DateTime<Utc> },
+    /// The break is over, and with it the cycle
+    Finished,
+}
+
+impl Pomodoro {
+    pub fn start(todo_id: i64, work: Duration, now: DateTime<Utc>) -> Self {
+        Self { todo_id, phase: Phase::Work, phase_started: now, phase_length: work }
+    }
+
+    /// Time left in the current phase, never negative
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.phase_started + self.phase_length - now).max(Duration::zero())
+    }
+
+    /// Move on once the current phase has run out: from work to a break of
+    /// `break_length`, or from the break to the end of the cycle
+    pub fn advance(&mut self, break_length: Duration, now: DateTime<Utc>) -> Option<Transition> {
+        let ended = self.phase_started + self.phase_length;
+        if now < ended {
+            return None;
+        }
+        match self.phase {
+            Phase::Work => {
+                let work_started = self.phase_started;
+                // The break starts when the work ended, even if the app noticed late
+                *self = Self { todo_id: self.todo_id, phase: Phase::Break, phase_started: ended, phase_length: break_length };
+                Some(Transition::BreakStarted { work_started })
+            }
+            Phase::Break => Some(Transition::Finished),
+        }
+    }
+}
+
+/// A countdown as `MM:SS`, rounded up so it never shows 00:00 while time is left
+pub fn format_countdown(remaining: Duration) -> String {
+    let seconds = (remaining.num_milliseconds() + 999) / 1000;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Run the `[pomodoro] notify` command with `message` as its last argument, without
+/// waiting for it
+pub fn notify(command: &str, message: &str) -> anyhow::Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", &format!("{} \"$1\"", command), "sh", message])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::database::Todo;
+use crate::dates;
+use crate::metrics;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Utc, Weekday};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Text,
+}
+
+impl ReportFormat {
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        match input.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "text" | "txt" => Ok(Self::Text),
+            other => Err(anyhow::anyhow!("Unknown report format '{}' (expected markdown or text)", other)),
+        }
+    }
+}
+
+/// A completed todo and its ancestors below the project
+type ReportItem<'a> = (&'a Todo, Vec<&'a Todo>);
+
+/// How far back the completed view goes: everything, or since the start of the local
+/// day, week or month
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompletedRange {
+    #[default]
+    All,
+    Today,
+    Week,
+    Month,
+}
+
+impl CompletedRange {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::All => "All time",
+            Self::Today => "Today",
+            Self::Week => "This week",
+            Self::Month => "This month",
+        }
+    }
+
+    /// The next range when cycling with a key, back to all time after the month
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Today,
+            Self::Today => Self::Week,
+            Self::Week => Self::Month,
+            Self::Month => Self::All,
+        }
+    }
+
+    /// Local midnight starting the range, `None` for all time. Weeks start on `week_start`.
+    pub fn since(self, now: DateTime<Local>, week_start: Weekday) -> Option<DateTime<Utc>> {
+        let today = now.date_naive();
+        let first = match self {
+            Self::All => return None,
+            Self::Today => today,
+            Self::Week => metrics::week_start(today, week_start),
+            Self::Month => today.with_day(1)?,
+        };
+        dates::local_to_utc(first, NaiveTime::MIN, &Local)
+    }
+}
+
+/// Where each local day starts in `todos`, listed most recently completed first: the day
+/// and the index of its first todo, for the completed view's headers
+pub fn completed_days(todos: &[Todo]) -> Vec<(NaiveDate, usize)> {
+    let mut days: Vec<(NaiveDate, usize)> = Vec::new();
+    for (i, todo) in todos.iter().enumerate() {
+        let day = todo.completed_at.unwrap_or(todo.created_at).with_timezone(&Local).date_naive();
+        if days.last().is_none_or(|(last, _)| *last != day) {
+            days.push((day, i));
+        }
+    }
+    days
+}
+
+/// A day header: "Today", "Yesterday" or the date
+pub fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    match (today - day).num_days() {
+        0 => "Today".to_string(),
+        1 => "Yesterday".to_string(),
+        _ => day.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// The start of a report period: a duration back from `now` ("1w", "3d") or a local
+/// day ("YYYY-MM-DD", from its midnight)
+pub fn parse_since(input: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(duration) = dates::parse_relative_duration(input) {
+        return now.checked_sub_signed(duration);
+    }
+    let day = NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d").ok()?;
+    dates::local_to_utc(day, NaiveTime::MIN, &Local)
+}
+
+/// What was completed since `since`, grouped by project (the top-level todo it belongs
+/// to, in title order) and then by local day, oldest first. `todos` needs the completed
+/// todos' ancestors as well, so it is normally every todo; `cancelled` ones are left out.
+pub fn completed_report(todos: &[Todo], cancelled: &HashSet<i64>, since: DateTime<Utc>, format: ReportFormat) -> String {
+    let by_id: HashMap<i64, &Todo> = todos.iter().map(|todo| (todo.id, todo)).collect();
+    // Titles from the project down to the todo's parent; the project itself comes first
+    let ancestors = |todo: &Todo| {
+        let mut path = Vec::new();
+        let mut parent_id = todo.parent_id;
+        while let Some(parent) = parent_id.and_then(|id| by_id.get(&id)) {
+            path.push(*parent);
+            parent_id = parent.parent_id;
+            if path.len() > todos.len() {
+                break;
+            }
+        }
+        path.reverse();
+        path
+    };
+
+    let mut completed: Vec<&Todo> = todos
+        .iter()
+        .filter(|todo| todo.completed_at.is_some_and(|at| at >= since) && !cancelled.contains(&todo.id))
+        .collect();
+    completed.sort_by_key(|todo| (todo.completed_at, todo.id));
+
+    // project id -> local day -> completed todos with their path below the project
+    let mut projects: HashMap<i64, BTreeMap<NaiveDate, Vec<ReportItem>>> = HashMap::new();
+    for todo in &completed {
+        let path = ancestors(todo);
+        let (project, path) = match path.split_first() {
+            Some((root, rest)) => (root.id, rest.to_vec()),
+            None => (todo.id, Vec::new()),
+        };
+        let day = todo.completed_at.unwrap_or(since).with_timezone(&Local).date_naive();
+        projects.entry(project).or_default().entry(day).or_default().push((todo, path));
+    }
+    let mut order: Vec<i64> = projects.keys().copied().collect();
+    order.sort_by_key(|id| (by_id.get(id).map(|todo| todo.title.to_lowercase()), *id));
+
+    let since_day = since.with_timezone(&Local).format("%Y-%m-%d");
+    let mut report = match format {
+        ReportFormat::Markdown => format!("# Completed since {}\n", since_day),
+        ReportFormat::Text => format!("Completed since {}\n", since_day),
+    };
+    if completed.is_empty() {
+        report.push_str("\nNothing completed.\n");
+        return report;
+    }
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    report.push_str(&format!("\n{} completed in {}\n", plural(completed.len(), "todo"), plural(order.len(), "project")));
+
+    for project in order {
+        let title = by_id.get(&project).map_or("(unknown)", |todo| todo.title.as_str());
+        match format {
+            ReportFormat::Markdown => report.push_str(&format!("\n## {}\n", title)),
+            ReportFormat::Text => report.push_str(&format!("\n{}\n", title)),
+        }
+        for (day, items) in &projects[&project] {
+            match format {
+                ReportFormat::Markdown => report.push_str(&format!("\n### {}\n\n", day.format("%a %Y-%m-%d"))),
+                ReportFormat::Text => report.push_str(&format!("  {}\n", day.format("%a %Y-%m-%d"))),
+            }
+            for (todo, path) in items {
+                let mut line = todo.title.clone();
+                if !path.is_empty() {
+                    let titles: Vec<&str> = path.iter().map(|todo| todo.title.as_str()).collect();
+                    line.push_str(&format!(" ({})", titles.join(" / ")));
+                }
+                match format {
+                    ReportFormat::Markdown => report.push_str(&format!("- [x] {}\n", line)),
+                    ReportFormat::Text => report.push_str(&format!("    - {}\n", line)),
+                }
+            }
+        }
+    }
+    report
+}
@@ -0,0 +1,67 @@
+use crate::database::Todo;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Why a todo comes up in the weekly review
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewReason {
+    Overdue,
+    /// Untouched for longer than `[review] stale_days`
+    Stale,
+    /// An open top-level todo, looked at to check it still has a next step
+    Project,
+}
+
+impl ReviewReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Overdue => "Overdue",
+            Self::Stale => "Stale",
+            Self::Project => "Project",
+        }
+    }
+}
+
+/// A todo waiting in the review queue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReviewItem {
+    pub id: i64,
+    pub reason: ReviewReason,
+    /// When the todo was last changed (or reviewed), as of building the queue
+    pub last_touched: DateTime<Utc>,
+}
+
+/// The todos to walk through in a review: overdue ones (most overdue first), then stale
+/// ones (least recently touched first), then the root projects by title. Each todo comes
+/// up once, for its first reason. Completed, hidden and deferred todos are left out.
+pub fn review_queue(
+    todos: &[Todo],
+    updated_at: &HashMap<i64, DateTime<Utc>>,
+    stale_days: i64,
+    now: DateTime<Utc>,
+) -> Vec<ReviewItem> {
+    let open: Vec<&Todo> = todos
+        .iter()
+        .filter(|todo| !todo.is_completed() && !todo.hidden && todo.start_at.is_none_or(|start| start <= now))
+        .collect();
+    let stale_before = now - Duration::days(stale_days);
+    let last_touched = |todo: &Todo| updated_at.get(&todo.id).copied().unwrap_or(todo.created_at);
+
+    let mut overdue: Vec<&Todo> = open.iter().copied().filter(|todo| todo.due_by.is_some_and(|due| due < now)).collect();
+    overdue.sort_by_key(|todo| (todo.due_by, todo.id));
+    let mut stale: Vec<&Todo> = open.iter().copied().filter(|todo| last_touched(todo) < stale_before).collect();
+    stale.sort_by_key(|todo| (last_touched(todo), todo.id));
+    let mut projects: Vec<&Todo> = open.iter().copied().filter(|todo| todo.parent_id.is_none()).collect();
+    projects.sort_by_key(|todo| (todo.title.to_lowercase(), todo.id));
+
+    let mut seen = HashSet::new();
+    let mut queue = Vec::new();
+    for (reason, todos) in [(ReviewReason::Overdue, overdue), (ReviewReason::Stale, stale), (ReviewReason::Project, projects)] {
+        for todo in todos {
+            if seen.insert(todo.id) {
+                queue.push(ReviewItem { id: todo.id, reason, last_touched: last_touched(todo) });
+            }
+        }
+    }
+    queue
+}
@@ -0,0 +1,289 @@
+use crate::config::HookConfig;
+use crate::database::{Database, DurationOutOfRange, NewTodo, Todo, TodoEvent};
+use crate::dates;
+use crate::filter::Filter;
+use crate::hooks::HookRunner;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
+
+/// MCP protocol revision answered when the client doesn't ask for one
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// The operations offered, as MCP tools. The same names without the `_todos` /
+/// `_todo` suffix (`list`, `search`, `create`, `complete`) are plain JSON-RPC methods,
+/// as are `get`, `update` and `delete`.
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "list_todos",
+            "description": "List todos, optionally narrowed by a tododb filter expression such as \"status:open due<7d\", \"parent:12\" or \"/regex/\"",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "filter": { "type": "string", "description": "Filter expression; omit for every todo" } }
+            }
+        },
+        {
+            "name": "search_todos",
+            "description": "Find todos whose title or description matches a case-insensitive pattern (regex or plain text)",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string" } },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "create_todo",
+            "description": "Create a todo, optionally as a subtask of parent_id and with a due date (\"2d\", \"1w\", \"2025-03-01\")",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string" },
+                    "description": { "type": "string" },
+                    "parent_id": { "type": "integer" },
+                    "due": { "type": "string" }
+                },
+                "required": ["title"]
+            }
+        },
+        {
+            "name": "complete_todo",
+            "description": "Mark a todo as completed, or as open again with completed=false",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "completed": { "type": "boolean", "default": true }
+                },
+                "required": ["id"]
+            }
+        }
+    ])
+}
+
+/// The operations the MCP tools map to; the other plain methods aren't offered as tools
+const TOOL_OPERATIONS: &[&str] = &["list", "search", "create", "complete"];
+
+/// Unknown method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Missing or malformed parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// The todo asked for doesn't exist
+pub const TODO_NOT_FOUND: i64 = -32001;
+
+/// A JSON-RPC error: the code and message sent back to the client
+pub struct RpcError(pub i64, pub String);
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError(INVALID_PARAMS, message.into())
+    }
+}
+
+/// Serves requests for one database. Changes made here are told to `[[hooks]]` like
+/// changes made in the app.
+pub struct Server<'a> {
+    database: &'a Database,
+    hooks: Vec<HookConfig>,
+    hook_runner: HookRunner,
+    changes: mpsc::Receiver<TodoEvent>,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(database: &'a Database, hooks: Vec<HookConfig>) -> Self {
+        Server { database, hooks, hook_runner: HookRunner::default(), changes: database.subscribe() }
+    }
+
+    /// Answer newline-delimited JSON-RPC messages on stdin until it closes (the MCP
+    /// stdio transport). Diagnostics go to stderr; stdout carries only responses.
+    pub fn serve_stdio(&mut self) -> anyhow::Result<()> {
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        for line in stdin.lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(&line) {
+                writeln!(stdout, "{}", response)?;
+                stdout.flush()?;
+            }
+            for error in self.hook_runner.finished_errors() {
+                eprintln!("Hook failed: {}", error);
+            }
+        }
+        for error in self.hook_runner.wait() {
+            eprintln!("Hook failed: {}", error);
+        }
+        self.database.checkpoint()
+    }
+
+    /// Handle one JSON-RPC message, returning the response to send (none for notifications)
+    pub fn handle_message(&mut self, message: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError(-32700, format!("Parse error: {}", e)))),
+        };
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = self.dispatch(method, &params);
+        // Notifications (no id) get no reply, not even for errors
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params.get("protocolVersion").and_then(Value::as_str).unwrap_or(PROTOCOL_VERSION),
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "tododb", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "notifications/initialized" | "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).ok_or_else(|| RpcError::invalid_params("Missing tool name"))?;
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                let operation = name.strip_suffix("_todos").or_else(|| name.strip_suffix("_todo")).unwrap_or(name);
+                if !TOOL_OPERATIONS.contains(&operation) || operation == name {
+                    return Err(RpcError::invalid_params(format!("Unknown tool '{}'", name)));
+                }
+                // Tool failures are results the assistant gets to read, not protocol errors
+                let (text, is_error) = match self.call(operation, &arguments) {
+                    Ok(value) => (value.to_string(), false),
+                    Err(RpcError(_, message)) => (message, true),
+                };
+                Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+            }
+            _ => self.call(method, params),
+        }
+    }
+
+    #[cfg(feature = "api")]
+    pub fn database(&self) -> &'a Database {
+        self.database
+    }
+
+    /// Run one operation: `list`, `search`, `get`, `create`, `update`, `complete` or `delete`
+    pub fn call(&mut self, operation: &str, params: &Value) -> Result<Value, RpcError> {
+        let result = self.apply(operation, params);
+        for change in self.changes.try_iter() {
+            self.hook_runner.fire_for_change(&self.hooks, self.database, &change, None);
+        }
+        result
+    }
+
+    fn apply(&mut self, operation: &str, params: &Value) -> Result<Value, RpcError> {
+        let database_error = |e: anyhow::Error| RpcError(-32000, e.to_string());
+        match operation {
+            "list" => {
+                let todos = match params.get("filter").and_then(Value::as_str).filter(|filter| !filter.trim().is_empty()) {
+                    Some(expression) => {
+                        let filter = Filter::parse(expression).map_err(|e| RpcError::invalid_params(format!("Invalid filter: {}", e)))?;
+                        self.database.query_todos(&filter).map_err(|e| match e.downcast_ref::<DurationOutOfRange>() {
+                            Some(out_of_range) => RpcError::invalid_params(out_of_range.to_string()),
+                            None => database_error(e),
+                        })?
+                    }
+                    None => self.database.get_all_todos().map_err(database_error)?,
+                };
+                Ok(json!(todos))
+            }
+            "search" => {
+                let query = string_param(params, "query")?;
+                Ok(json!(self.database.search_todos(query).map_err(database_error)?))
+            }
+            "get" => Ok(json!(self.todo(id_param(params)?)?)),
+            "create" => {
+                let title = string_param(params, "title")?.trim();
+                if title.is_empty() {
+                    return Err(RpcError::invalid_params("The title is empty"));
+                }
+                let parent_id = params.get("parent_id").and_then(Value::as_i64);
+                if let Some(parent_id) = parent_id {
+                    self.todo(parent_id)?;
+                }
+                let due_by = match params.get("due").and_then(Value::as_str) {
+                    Some(due) => Some(dates::parse_due_date(due).ok_or_else(|| RpcError::invalid_params(format!("Invalid due date '{}'", due)))?),
+                    None => None,
+                };
+                let description = params.get("description").and_then(Value::as_str).unwrap_or_default().to_string();
+                let id = self
+                    .database
+                    .create_todo(NewTodo { title: title.to_string(), description, parent_id, due_by })
+                    .map_err(database_error)?;
+                Ok(json!(self.todo(id)?))
+            }
+            "update" => {
+                let id = id_param(params)?;
+                let todo = self.todo(id)?;
+                let title = params.get("title").and_then(Value::as_str).map_or(todo.title.clone(), |title| title.trim().to_string());
+                if title.is_empty() {
+                    return Err(RpcError::invalid_params("The title is empty"));
+                }
+                let description = params.get("description").and_then(Value::as_str).map_or(todo.description.clone(), str::to_string);
+                // "due": null clears the due date, a missing "due" keeps it
+                let due_by = match params.get("due") {
+                    None => todo.due_by,
+                    Some(Value::Null) => None,
+                    Some(due) => {
+                        let due = due.as_str().unwrap_or_default();
+                        Some(dates::parse_due_date(due).ok_or_else(|| RpcError::invalid_params(format!("Invalid due date '{}'", due)))?)
+                    }
+                };
+                self.database.update_todo(id, title, description, due_by).map_err(database_error)?;
+                if params.get("completed").and_then(Value::as_bool).is_some() {
+                    return self.apply("complete", params);
+                }
+                Ok(json!(self.todo(id)?))
+            }
+            "delete" => {
+                let todo = self.todo(id_param(params)?)?;
+                if self.database.has_children(todo.id).map_err(database_error)? {
+                    return Err(RpcError::invalid_params(format!("Todo {} has subtasks; delete them first", todo.id)));
+                }
+                self.database.delete_todo(todo.id).map_err(database_error)?;
+                Ok(json!(todo))
+            }
+            "complete" => {
+                let id = id_param(params)?;
+                let completed = params.get("completed").and_then(Value::as_bool).unwrap_or(true);
+                let todo = self.todo(id)?;
+                if todo.is_completed() != completed {
+                    if completed {
+                        self.database.complete_todo(id).map_err(database_error)?;
+                    } else {
+                        self.database.uncomplete_todo(id).map_err(database_error)?;
+                    }
+                    return Ok(json!(self.todo(id)?));
+                }
+                Ok(json!(todo))
+            }
+            _ => Err(RpcError(METHOD_NOT_FOUND, format!("Method '{}' not found", operation))),
+        }
+    }
+
+    fn todo(&self, id: i64) -> Result<Todo, RpcError> {
+        self.database
+            .get_todo_by_id(id)
+            .map_err(|e| RpcError(-32000, e.to_string()))?
+            .ok_or_else(|| RpcError(TODO_NOT_FOUND, format!("Todo {} not found", id)))
+    }
+}
+
+fn id_param(params: &Value) -> Result<i64, RpcError> {
+    params.get("id").and_then(Value::as_i64).ok_or_else(|| RpcError::invalid_params("Missing todo id"))
+}
+
+fn string_param<'p>(params: &'p Value, name: &str) -> Result<&'p str, RpcError> {
+    params.get(name).and_then(Value::as_str).ok_or_else(|| RpcError::invalid_params(format!("Missing '{}'", name)))
+}
+
+fn error_response(id: Value, RpcError(code, message): RpcError) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
@@ -0,0 +1,276 @@
+use crate::database::{Database, Todo};
+use crate::export::{self, SnapshotOptions};
+use crate::tree::{TodoTreeManager, TreeNode};
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;background:#303446;color:#c6d0f5;margin:1em auto;max-width:60em;padding:0 1em}\
+a{color:#8caaee;text-decoration:none}a:hover{text-decoration:underline}ul{list-style:none;padding-left:1.2em}li{margin:.3em 0}\
+small,.meta{color:#a5adce}.done{text-decoration:line-through;color:#737994}.overdue{color:#e78284}\
+pre,code{background:#292c3c;border-radius:4px}pre{padding:.6em;overflow-x:auto}code{padding:0 .2em}\
+blockquote{border-left:3px solid #81c8be;margin-left:0;padding-left:.8em;color:#81c8be}.description ul{list-style:disc}.description ol{padding-left:1.6em}";
+
+/// Write a browsable, static copy of the snapshot into `dir`: `index.html` with the tree
+/// and a `todo-<id>.html` page per todo with its description rendered from markdown.
+/// Pages left by an earlier export of todos no longer included are removed.
+/// Returns the number of exported todos.
+pub fn export_site(database: &Database, dir: &Path, options: &SnapshotOptions) -> anyhow::Result<usize> {
+    let todos = export::snapshot_todos(database, options)?;
+    fs::create_dir_all(dir)?;
+
+    let mut tree = TodoTreeManager::new();
+    tree.rebuild_from_todos_with_hidden_filter(todos.clone(), true);
+    let by_id: HashMap<i64, &Todo> = todos.iter().map(|todo| (todo.id, todo)).collect();
+    let mut children: HashMap<i64, Vec<&Todo>> = HashMap::new();
+    for todo in &todos {
+        if let Some(parent_id) = todo.parent_id {
+            children.entry(parent_id).or_default().push(todo);
+        }
+    }
+    let now = Utc::now();
+
+    let mut items = String::new();
+    render_nodes(&tree, &tree.tree, now, &mut items);
+    // The tree leaves out finished projects, which a roadmap wants to show off
+    let in_tree: HashSet<i64> = tree.tree.iter().map(|node| node.id).collect();
+    let finished: String = todos
+        .iter()
+        .filter(|todo| todo.parent_id.is_none() && !in_tree.contains(&todo.id))
+        .map(|todo| format!("{}</li>", todo_item(todo, now)))
+        .collect();
+    let finished = if finished.is_empty() { String::new() } else { format!("<h2>Completed</h2><ul>{}</ul>", finished) };
+    let generated = format!("Updated {}", Local::now().format("%Y-%m-%d %H:%M"));
+    fs::write(dir.join("index.html"), page("Todos", &format!("<h1>Todos</h1><p class=\"meta\">{}</p><ul>{}</ul>{}", generated, items, finished)))?;
+
+    for todo in &todos {
+        let mut trail = Vec::new();
+        let mut parent_id = todo.parent_id;
+        while let Some(parent) = parent_id.and_then(|id| by_id.get(&id)) {
+            trail.push(format!("<a href=\"{}\">{}</a>", page_name(parent.id), escape_html(&parent.title)));
+            parent_id = parent.parent_id;
+        }
+        trail.push("<a href=\"index.html\">Todos</a>".to_string());
+        trail.reverse();
+
+        let mut meta = vec![format!("Created {}", local_date(todo.created_at))];
+        if let Some(completed_at) = todo.completed_at {
+            meta.push(format!("completed {}", local_date(completed_at)));
+        }
+        if let Some(due) = todo.due_by {
+            meta.push(format!("due {}", local_date(due)));
+        }
+        let class = if todo.is_completed() { " class=\"done\"" } else { "" };
+        let mut body = format!(
+            "<nav class=\"meta\">{}</nav><h1{}>{}</h1><p class=\"meta\">{}</p>",
+            trail.join(" › "),
+            class,
+            escape_html(&todo.title),
+            meta.join(", ")
+        );
+        if !todo.description.trim().is_empty() {
+            body.push_str(&format!("<div class=\"description\">{}</div>", markdown_to_html(&todo.description)));
+        }
+        if let Some(subtasks) = children.get(&todo.id) {
+            let items: String = subtasks.iter().map(|subtask| format!("{}</li>", todo_item(subtask, now))).collect();
+            body.push_str(&format!("<h2>Subtasks</h2><ul>{}</ul>", items));
+        }
+        fs::write(dir.join(page_name(todo.id)), page(&todo.title, &body))?;
+    }
+
+    let current: HashSet<String> = todos.iter().map(|todo| page_name(todo.id)).collect();
+    let stale_page = Regex::new(r"^todo-\d+\.html$")?;
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if stale_page.is_match(&name) && !current.contains(&name) {
+            fs::remove_file(dir.join(&name))?;
+        }
+    }
+    Ok(todos.len())
+}
+
+fn page_name(id: i64) -> String {
+    format!("todo-{}.html", id)
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title><style>{}</style></head>\n<body>{}</body></html>\n",
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+fn local_date(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+fn render_nodes(tree: &TodoTreeManager, nodes: &[TreeNode], now: DateTime<Utc>, out: &mut String) {
+    for node in nodes {
+        let Some(todo) = tree.get_todo_by_id(node.id) else { continue };
+        out.push_str(&todo_item(todo, now));
+        if !node.children.is_empty() {
+            out.push_str("<ul>");
+            render_nodes(tree, &node.children, now, out);
+            out.push_str("</ul>");
+        }
+        out.push_str("</li>");
+    }
+}
+
+/// An unclosed `<li>` linking to the todo's page, so subtasks can go inside it
+fn todo_item(todo: &Todo, now: DateTime<Utc>) -> String {
+    let (mark, class) = if todo.is_completed() { ("☑", " class=\"done\"") } else { ("☐", "") };
+    let due = match todo.due_by {
+        Some(due) if !todo.is_completed() && due < now => format!(" <small class=\"overdue\">due {}</small>", local_date(due)),
+        Some(due) => format!(" <small>due {}</small>", local_date(due)),
+        None => String::new(),
+    };
+    format!("<li>{} <a href=\"{}\"{}>{}</a>{}", mark, page_name(todo.id), class, escape_html(&todo.title), due)
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+/// The markdown used in descriptions as HTML: headings, paragraphs, bullet, numbered and
+/// task lists, block quotes, code blocks and rules, with inline code, bold, italics,
+/// strikethrough and links. Raw HTML is escaped, not passed through.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut code: Option<String> = None;
+
+    let flush = |html: &mut String, paragraph: &mut Vec<&str>, quote: &mut Vec<&str>, list: &mut Option<&str>| {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>", inline_html(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+        if !quote.is_empty() {
+            html.push_str(&format!("<blockquote>{}</blockquote>", inline_html(&quote.join("\n"))));
+            quote.clear();
+        }
+        if let Some(tag) = list.take() {
+            html.push_str(&format!("</{}>", tag));
+        }
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if let Some(block) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                html.push_str(&format!("<pre><code>{}</code></pre>", escape_html(block)));
+                code = None;
+            } else {
+                block.push_str(line);
+                block.push('\n');
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            code = Some(String::new());
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            continue;
+        }
+
+        let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            // The page title is the h1, so description headings start one level down
+            let level = (hashes + 1).min(6);
+            html.push_str(&format!("<h{}>{}</h{}>", level, inline_html(trimmed[hashes..].trim()), level));
+        } else if matches!(trimmed, "---" | "***" | "___") {
+            flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            html.push_str("<hr>");
+        } else if let Some(text) = trimmed.strip_prefix('>') {
+            if quote.is_empty() {
+                flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            }
+            quote.push(text.trim_start());
+        } else if let Some((tag, item)) = list_item(trimmed) {
+            if list != Some(tag) {
+                flush(&mut html, &mut paragraph, &mut quote, &mut list);
+                html.push_str(&format!("<{}>", tag));
+                list = Some(tag);
+            }
+            let item = match item.strip_prefix("[ ] ") {
+                Some(rest) => format!("<input type=\"checkbox\" disabled> {}", inline_html(rest)),
+                None => match item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] ")) {
+                    Some(rest) => format!("<input type=\"checkbox\" checked disabled> {}", inline_html(rest)),
+                    None => inline_html(item),
+                },
+            };
+            html.push_str(&format!("<li>{}</li>", item));
+        } else if list.is_some() && line.starts_with([' ', '\t']) {
+            // A continuation line of the last list item
+            if let Some(end) = html.rfind("</li>") {
+                html.insert_str(end, &format!(" {}", inline_html(trimmed)));
+            }
+        } else {
+            if !quote.is_empty() || list.is_some() {
+                flush(&mut html, &mut paragraph, &mut quote, &mut list);
+            }
+            paragraph.push(trimmed);
+        }
+    }
+    if let Some(block) = code {
+        html.push_str(&format!("<pre><code>{}</code></pre>", escape_html(&block)));
+    }
+    flush(&mut html, &mut paragraph, &mut quote, &mut list);
+    html
+}
+
+/// `- item`, `* item`, `+ item` (`ul`) or `1. item` (`ol`)
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(("ul", item));
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some(("ol", &line[digits + 2..]));
+    }
+    None
+}
+
+/// Inline markdown in one block of text; code spans are left exactly as written
+fn inline_html(text: &str) -> String {
+    let link = Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").expect("valid regex");
+    let bold = Regex::new(r"\*\*([^*]+)\*\*").expect("valid regex");
+    let italic = Regex::new(r"\*([^*\s][^*]*)\*").expect("valid regex");
+    let strike = Regex::new(r"~~([^~]+)~~").expect("valid regex");
+
+    let parts: Vec<&str> = text.split('`').collect();
+    let mut html = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        // Odd parts sit between backticks, unless the last backtick is never closed
+        if i % 2 == 1 && i + 1 < parts.len() {
+            html.push_str(&format!("<code>{}</code>", escape_html(part)));
+            continue;
+        }
+        if i % 2 == 1 {
+            html.push('`');
+        }
+        let part = escape_html(part);
+        let part = link.replace_all(&part, |caps: &regex::Captures| {
+            let url = &caps[2];
+            // No javascript: and similar; relative links are fine
+            let safe = ["http://", "https://", "mailto:"].iter().any(|scheme| url.starts_with(scheme)) || !url.contains(':');
+            if safe { format!("<a href=\"{}\">{}</a>", url, &caps[1]) } else { caps[1].to_string() }
+        });
+        let part = bold.replace_all(&part, "<strong>$1</strong>");
+        let part = italic.replace_all(&part, "<em>$1</em>");
+        let part = strike.replace_all(&part, "<del>$1</del>");
+        html.push_str(&part);
+    }
+    html.replace('\n', "<br>")
+}
@@ -0,0 +1,105 @@
+use crate::colors::CatppuccinFrappe;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// Where a todo stands. Done and cancelled todos are closed: they have `completed_at` set
+/// and leave the tree and list like any completed todo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Open,
+    InProgress,
+    Blocked,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "open" | "todo" => Some(Self::Open),
+            "in_progress" | "doing" | "started" => Some(Self::InProgress),
+            "blocked" => Some(Self::Blocked),
+            "done" => Some(Self::Done),
+            "cancelled" | "canceled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::InProgress => "in_progress",
+            Self::Blocked => "blocked",
+            Self::Done => "done",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// The `status` column value: open and done are told apart by `completed_at` alone
+    pub fn stored(&self) -> Option<&'static str> {
+        match self {
+            Self::Open | Self::Done => None,
+            status => Some(status.as_str()),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Open => "Open",
+            Self::InProgress => "In Progress",
+            Self::Blocked => "Blocked",
+            Self::Done => "Done",
+            Self::Cancelled => "Cancelled",
+        }
+    }
+
+    /// The checkbox shown in front of the title
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Open => "[ ]",
+            Self::InProgress => "[~]",
+            Self::Blocked => "[!]",
+            Self::Done => "[✓]",
+            Self::Cancelled => "[-]",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Open => CatppuccinFrappe::STATUS_OPEN,
+            Self::InProgress => CatppuccinFrappe::STATUS_IN_PROGRESS,
+            Self::Blocked => CatppuccinFrappe::STATUS_BLOCKED,
+            Self::Done => CatppuccinFrappe::STATUS_DONE,
+            Self::Cancelled => CatppuccinFrappe::STATUS_CANCELLED,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Done | Self::Cancelled)
+    }
+
+    /// A todo's status from its completion and the stored `todos.status`. Completion wins,
+    /// so a todo completed or reopened some other way never shows a stale status.
+    pub fn resolve(completed: bool, stored: Option<Self>) -> Self {
+        match (completed, stored) {
+            (true, Some(Self::Cancelled)) => Self::Cancelled,
+            (true, _) => Self::Done,
+            (false, Some(status @ (Self::InProgress | Self::Blocked))) => status,
+            (false, _) => Self::Open,
+        }
+    }
+
+    /// The next (or previous) status in `cycle`, wrapping around. A status not in the
+    /// cycle starts over at its first entry.
+    pub fn cycle(current: Self, cycle: &[Self], forward: bool) -> Self {
+        let Some(first) = cycle.first() else {
+            return current;
+        };
+        match cycle.iter().position(|status| *status == current) {
+            Some(i) if forward => cycle[(i + 1) % cycle.len()],
+            Some(i) => cycle[(i + cycle.len() - 1) % cycle.len()],
+            None => *first,
+        }
+    }
+}
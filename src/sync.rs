@@ -0,0 +1,196 @@
+use crate::database::Database;
+use crate::http;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{TcpListener, TcpStream};
+
+/// One todo as exchanged between machines, identified by its uid rather than the
+/// local integer id. A deleted todo is sent as a record without `todo`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncRecord {
+    pub uid: String,
+    /// Time of the last change (or of the deletion); the later one wins
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub todo: Option<SyncTodo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncTodo {
+    pub parent_uid: Option<String>,
+    pub title: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_by: Option<DateTime<Utc>>,
+    pub hidden: bool,
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
+    /// The stored `status` column: in_progress, blocked or cancelled (see `Status::stored`)
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// Request and response body of `POST /sync`
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncMessage {
+    records: Vec<SyncRecord>,
+    /// Records the receiver took over (responses only)
+    #[serde(default)]
+    applied: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    /// Changes taken over from the server
+    pub received: usize,
+    /// Changes the server took over from here
+    pub sent: usize,
+}
+
+/// Last writer wins: whether `incoming` replaces `local`. Exact ties are broken on
+/// the record contents so both sides pick the same one.
+fn is_newer(incoming: &SyncRecord, local: Option<&SyncRecord>) -> bool {
+    let Some(local) = local else {
+        return true;
+    };
+    match incoming.updated_at.cmp(&local.updated_at) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => {
+            let fingerprint = |record: &SyncRecord| serde_json::to_string(record).unwrap_or_default();
+            incoming != local && fingerprint(incoming) > fingerprint(local)
+        }
+    }
+}
+
+/// Order changes so parents are written before their children, then deletions
+fn parents_first(records: Vec<SyncRecord>) -> Vec<SyncRecord> {
+    let parents: HashMap<&str, &str> = records
+        .iter()
+        .filter_map(|record| Some((record.uid.as_str(), record.todo.as_ref()?.parent_uid.as_deref()?)))
+        .collect();
+    let depth = |uid: &str| {
+        let mut depth = 0;
+        let mut current = uid;
+        while let Some(parent) = parents.get(current) {
+            depth += 1;
+            current = parent;
+            if depth > parents.len() {
+                break;
+            }
+        }
+        depth
+    };
+    let mut keyed: Vec<(bool, usize, SyncRecord)> = records
+        .iter()
+        .map(|record| (record.todo.is_none(), depth(&record.uid), record.clone()))
+        .collect();
+    keyed.sort_by_key(|(deleted, depth, _)| (*deleted, *depth));
+    keyed.into_iter().map(|(_, _, record)| record).collect()
+}
+
+/// Take over every record in `incoming` that is newer than what this database has.
+/// Returns the number of records applied.
+pub fn merge(database: &Database, incoming: Vec<SyncRecord>) -> anyhow::Result<usize> {
+    let local: HashMap<String, SyncRecord> = database
+        .get_sync_records()?
+        .into_iter()
+        .map(|record| (record.uid.clone(), record))
+        .collect();
+    let newer: Vec<SyncRecord> = incoming
+        .into_iter()
+        .filter(|record| is_newer(record, local.get(&record.uid)))
+        .collect();
+    let count = newer.len();
+    database.apply_sync_records(&parents_first(newer))?;
+    Ok(count)
+}
+
+/// One round with the server at `url`: send every local record, merge its reply.
+/// Both sides hold the same todos afterwards.
+pub fn sync(database: &Database, url: &str, token: Option<&str>) -> anyhow::Result<SyncReport> {
+    let request = SyncMessage { records: database.get_sync_records()?, applied: 0 };
+    let endpoint = format!("{}/sync", url.trim_end_matches('/'));
+    let mut headers = vec![("Content-Type", "application/json".to_string())];
+    if let Some(token) = token {
+        headers.push(("Authorization", format!("Bearer {}", token)));
+    }
+    let (status, body) = http::request("POST", &endpoint, &headers, &serde_json::to_string(&request)?)?;
+    if status != 200 {
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|value| value.get("error")?.as_str().map(str::to_string))
+            .unwrap_or(body);
+        return Err(anyhow::anyhow!("Sync server returned {}: {}", status, message.trim()));
+    }
+
+    let response: SyncMessage = serde_json::from_str(&body)
+        .map_err(|e| anyhow::anyhow!("Unexpected reply from the sync server: {}", e))?;
+    let received = merge(database, response.records)?;
+    Ok(SyncReport { received, sent: response.applied })
+}
+
+/// Serve `POST /sync` for `database` until interrupted. Without TLS of its own,
+/// put it behind a reverse proxy (or an SSH tunnel) when it leaves the machine;
+/// addresses other than loopback need a `[sync] token`.
+pub fn serve(database: &Database, listen: &str, token: Option<&str>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).map_err(|e| anyhow::anyhow!("Cannot listen on {}: {}", listen, e))?;
+    let addr = listener.local_addr()?;
+    http::require_token_off_loopback(addr, token, "[sync] token")?;
+    println!("Serving sync on http://{}", addr);
+    println!("Note: plain HTTP only; put it behind a TLS-terminating reverse proxy or an SSH tunnel to sync over the internet");
+    if token.is_none() {
+        println!("Warning: no [sync] token set; anyone who can connect on this machine can read and change your todos");
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+        match handle_connection(database, stream, token) {
+            Ok(Some((received, sent))) => println!("Synced with {}: {} changes in, {} out", peer, received, sent),
+            Ok(None) => {}
+            Err(e) => eprintln!("Sync with {} failed: {}", peer, e),
+        }
+    }
+    Ok(())
+}
+
+/// Answer one request. Returns the changes taken over and sent back for a sync.
+pub fn handle_connection(database: &Database, mut stream: TcpStream, token: Option<&str>) -> anyhow::Result<Option<(usize, usize)>> {
+    let request = http::read_request(&stream)?;
+    if (request.method.as_str(), request.path()) != ("POST", "/sync") {
+        http::respond(&mut stream, 404, "application/json", &error_body("Only POST /sync is served"))?;
+        return Ok(None);
+    }
+    if let Some(token) = token
+        && !http::bearer_matches(request.authorization.as_deref(), token) {
+        http::respond(&mut stream, 401, "application/json", &error_body("Missing or wrong sync token"))?;
+        return Ok(None);
+    }
+    let Some(body) = request.body else {
+        http::respond(&mut stream, 413, "application/json", &error_body("Request too large"))?;
+        return Ok(None);
+    };
+
+    let request: SyncMessage = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            http::respond(&mut stream, 400, "application/json", &error_body(&format!("Invalid sync request: {}", e)))?;
+            return Ok(None);
+        }
+    };
+
+    let client_versions: HashMap<String, DateTime<Utc>> = request.records.iter().map(|record| (record.uid.clone(), record.updated_at)).collect();
+    let applied = merge(database, request.records)?;
+    let records = database.get_sync_records()?;
+    // What the client doesn't have yet, for the log line
+    let sent = records.iter().filter(|record| client_versions.get(&record.uid) != Some(&record.updated_at)).count();
+    let response = SyncMessage { records, applied };
+    http::respond(&mut stream, 200, "application/json", &serde_json::to_string(&response)?)?;
+    Ok(Some((applied, sent)))
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
@@ -0,0 +1,91 @@
+use crate::database::Todo;
+use std::collections::HashMap;
+
+/// Most completions shown at once
+const MAX_COMPLETIONS: usize = 8;
+
+/// The `#tag` words in `text` (without the `#`), as matched by `tag:` filters
+pub fn extract_tags(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(|tag| tag.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_' && c != '-'))
+        .filter(|tag| is_valid_tag(tag))
+        .collect()
+}
+
+/// Whether `tag` (without the `#`) is a word `extract_tags` reads back as a tag
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `title` with its `#tag` words replaced by `tags`, which go at the end
+pub fn replace_tags(title: &str, tags: &[&str]) -> String {
+    title
+        .split_whitespace()
+        .filter(|word| extract_tags(word).is_empty())
+        .map(str::to_string)
+        .chain(tags.iter().map(|tag| format!("#{}", tag)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Every tag used in the todos' titles and descriptions with the number of todos using it,
+/// most used first
+pub fn tag_frequencies(todos: &[Todo]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for todo in todos {
+        let mut tags = extract_tags(&todo.title);
+        tags.extend(extract_tags(&todo.description));
+        tags.sort_unstable();
+        tags.dedup();
+        for tag in tags {
+            *counts.entry(tag.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut frequencies: Vec<(String, usize)> = counts.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies
+}
+
+/// The partial tag being typed at the end of `input`: the last word, if it starts
+/// with one of `sigils` and the cursor is still in it
+fn tag_prefix<'a>(input: &'a str, sigils: &[char]) -> Option<&'a str> {
+    if input.ends_with(char::is_whitespace) {
+        return None;
+    }
+    let word = input.rsplit(char::is_whitespace).next()?;
+    let mut chars = word.chars();
+    sigils.contains(&chars.next()?).then_some(chars.as_str())
+}
+
+/// Known tags completing the tag typed at the end of `input`, in frequency order.
+/// A tag already typed out in full isn't offered again.
+pub fn completions<'a>(known: &'a [(String, usize)], input: &str, sigils: &[char]) -> Vec<&'a (String, usize)> {
+    let Some(prefix) = tag_prefix(input, sigils) else {
+        return Vec::new();
+    };
+    let prefix = prefix.to_lowercase();
+    known
+        .iter()
+        .filter(|(tag, _)| {
+            let tag = tag.to_lowercase();
+            tag.starts_with(&prefix) && tag != prefix
+        })
+        .take(MAX_COMPLETIONS)
+        .collect()
+}
+
+/// Replace the partial tag at the end of `input` with `tag`, followed by a space
+pub fn apply_completion(input: &mut String, tag: &str) {
+    let word_start = input
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    // Keep the sigil the user typed (`#`, or `@` in quick capture)
+    let sigil_len = input[word_start..].chars().next().map_or(0, char::len_utf8);
+    input.truncate(word_start + sigil_len);
+    input.push_str(tag);
+    input.push(' ');
+}
@@ -0,0 +1,105 @@
+use crate::database::{Database, NewTodo, SavedTemplate, Todo};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One todo of a template, with its subtasks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateItem {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Seconds from instantiation to the due date. Saved as the todo's due date
+    /// minus the template root's creation time, so a checklist made on the 1st
+    /// with a step due on the 10th is due 9 days after each instantiation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_offset: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TemplateItem>,
+}
+
+impl TemplateItem {
+    /// Number of todos the template creates
+    pub fn count(&self) -> usize {
+        1 + self.children.iter().map(TemplateItem::count).sum::<usize>()
+    }
+}
+
+/// Capture a todo and all its descendants (completed ones included) as a template tree
+pub fn template_from_subtree(database: &Database, root_id: i64) -> anyhow::Result<TemplateItem> {
+    let all_todos = database.get_all_todos()?;
+    let root = all_todos
+        .iter()
+        .find(|todo| todo.id == root_id)
+        .ok_or_else(|| anyhow::anyhow!("Todo {} not found", root_id))?;
+
+    let mut children: HashMap<i64, Vec<&Todo>> = HashMap::new();
+    for todo in &all_todos {
+        if let Some(parent_id) = todo.parent_id {
+            children.entry(parent_id).or_default().push(todo);
+        }
+    }
+    // Keep subtasks in creation order
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|todo| todo.id);
+    }
+
+    Ok(build_item(root, &children, root.created_at))
+}
+
+fn build_item(todo: &Todo, children: &HashMap<i64, Vec<&Todo>>, anchor: DateTime<Utc>) -> TemplateItem {
+    TemplateItem {
+        title: todo.title.clone(),
+        description: todo.description.clone(),
+        due_offset: todo.due_by.map(|due| (due - anchor).num_seconds()),
+        children: children
+            .get(&todo.id)
+            .map(|todos| todos.iter().map(|child| build_item(child, children, anchor)).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Save the subtree under `root_id` as template `name` (replacing one with the same name).
+/// Returns the number of todos in the template.
+pub fn save_template(database: &Database, name: &str, root_id: i64) -> anyhow::Result<usize> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Template name cannot be empty"));
+    }
+    let root = template_from_subtree(database, root_id)?;
+    database.save_template(name, &serde_json::to_string(&root)?)?;
+    Ok(root.count())
+}
+
+pub fn find_template(database: &Database, name: &str) -> anyhow::Result<SavedTemplate> {
+    database
+        .get_templates()?
+        .into_iter()
+        .find(|template| template.name == name)
+        .ok_or_else(|| anyhow::anyhow!("No template named '{}'", name))
+}
+
+pub fn parse_items(template: &SavedTemplate) -> anyhow::Result<TemplateItem> {
+    serde_json::from_str(&template.items)
+        .map_err(|e| anyhow::anyhow!("Template '{}' is corrupt: {}", template.name, e))
+}
+
+/// Create the template's todos under `parent_id`, resolving due offsets from now.
+/// Returns the id of the new top todo.
+pub fn instantiate(database: &Database, template: &SavedTemplate, parent_id: Option<i64>) -> anyhow::Result<i64> {
+    let root = parse_items(template)?;
+    create_items(database, &root, parent_id, Utc::now())
+}
+
+fn create_items(database: &Database, item: &TemplateItem, parent_id: Option<i64>, now: DateTime<Utc>) -> anyhow::Result<i64> {
+    let id = database.create_todo(NewTodo {
+        title: item.title.clone(),
+        description: item.description.clone(),
+        parent_id,
+        due_by: item.due_offset.map(|offset| now + Duration::seconds(offset)),
+    })?;
+    for child in &item.children {
+        create_items(database, child, Some(id), now)?;
+    }
+    Ok(id)
+}
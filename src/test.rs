@@ -1,4 +1,46 @@
-use crate::database::{Database, NewTodo};
+#[cfg(feature = "api")]
+use crate::api::{self, ApiOptions};
+use crate::agenda;
+use crate::audit;
+use crate::backup;
+use crate::caldav::{self, PushReport};
+use crate::capture;
+use crate::cli;
+use crate::clipboard;
+use crate::completions;
+use crate::context::{self, Energy};
+use crate::config::{CascadeRule, Column, Config, HookEvent, IdDisplay, ParentRule};
+use crate::database::{CompletedFilter, Database, NewTodo, Todo, TodoEvent};
+use crate::doctor::{self, Anomaly};
+use crate::editor;
+use crate::export::{self, CsvColumn, SnapshotOptions};
+use crate::import::{self, SourceKind};
+use crate::metrics::{self, Session};
+use crate::mirror;
+use crate::org;
+use crate::passphrase;
+use crate::pomodoro::{self, Pomodoro};
+use crate::report::{self, ReportFormat};
+use crate::review;
+use crate::rpc;
+use crate::site;
+use crate::filter::{self, Filter};
+use crate::flag::Flag;
+use crate::history;
+use crate::hooks;
+use crate::http;
+use crate::ical;
+use crate::merge;
+use crate::sync::{self, SyncReport};
+use crate::tags;
+use crate::tree::{self, TodoTreeManager};
+use crate::template;
+use crate::status::Status;
+use crate::ui;
+use crate::waiting;
+use chrono::{Local, Utc};
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::{HashMap, HashSet};
 
 pub fn test_functionality() -> anyhow::Result<()> {
     println!("Testing todo database functionality...");
@@ -50,9 +92,242 @@ pub fn test_functionality() -> anyhow::Result<()> {
         println!("  - {}: {} (completed: {:?})", todo.id, todo.title, todo.completed_at);
     }
     
+    // Test paging through completed todos: pages join up with no gaps or repeats
+    println!("Testing completed todo pages...");
+    let page_db = Database::new(":memory:")?;
+    for n in 0..5 {
+        let id = page_db.create_todo(NewTodo { title: format!("Done {}", n), description: String::new(), parent_id: None, due_by: None })?;
+        page_db.complete_todo(id)?;
+    }
+    page_db.create_todo(NewTodo { title: "Still open".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    anyhow::ensure!(page_db.count_completed_todos(&CompletedFilter::default())? == 5, "completed count should skip open todos");
+    let mut paged = page_db.get_completed_todos(&CompletedFilter::default(), 0, 2)?;
+    paged.extend(page_db.get_completed_todos(&CompletedFilter::default(), 2, 2)?);
+    paged.extend(page_db.get_completed_todos(&CompletedFilter::default(), 4, 2)?);
+    let all_pages = page_db.get_completed_todos(&CompletedFilter::default(), 0, 10)?;
+    anyhow::ensure!(paged.iter().map(|todo| todo.id).eq(all_pages.iter().map(|todo| todo.id)), "pages should join into the full list");
+    anyhow::ensure!(all_pages.len() == 5 && all_pages.windows(2).all(|pair| pair[0].completed_at >= pair[1].completed_at), "completed todos should be newest first");
+    anyhow::ensure!(page_db.get_completed_todos(&CompletedFilter::default(), 5, 2)?.is_empty(), "a page past the end should be empty");
+    println!("Completed todo pages OK");
+
     // Test getting incomplete todos again
     let incomplete = db.get_incomplete_todos(None)?;
     println!("Root incomplete todos after completion: {}", incomplete.len());
+
+    // Test the editor round-trip: an edited due date must reach the database
+    println!("Testing editor round-trip...");
+    let due = Utc::now() + chrono::Duration::days(3);
+    let edited_id = db.create_todo(NewTodo {
+        title: "Renew passport".to_string(),
+        description: "Bring photos".to_string(),
+        parent_id: None,
+        due_by: Some(due),
+    })?;
+    let original = db.get_todo_by_id(edited_id)?.ok_or_else(|| anyhow::anyhow!("todo {} missing", edited_id))?;
+    let markdown = editor::todo_to_markdown(&original, &[]);
+    let unchanged = editor::parse_todo_markdown(&markdown, &original, crate::dates::END_OF_DAY).map_err(anyhow::Error::msg)?;
+    anyhow::ensure!(unchanged == (original.title.clone(), original.description.clone(), original.due_by), "unedited file changed the todo: {:?}", unchanged);
+
+    let new_due = original.due_by.map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+    let markdown = markdown.replace(&new_due, "2031-04-05 09:30").replace("Bring photos", "Bring photos\n\nAnd the form");
+    let (title, description, due_by) = editor::parse_todo_markdown(&markdown, &original, crate::dates::END_OF_DAY).map_err(anyhow::Error::msg)?;
+    db.update_todo(edited_id, title, description, due_by)?;
+    let edited = db.get_todo_by_id(edited_id)?.ok_or_else(|| anyhow::anyhow!("todo {} missing", edited_id))?;
+    anyhow::ensure!(
+        edited.due_by.map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()).as_deref() == Some("2031-04-05 09:30"),
+        "edited due date not persisted: {:?}", edited.due_by
+    );
+    anyhow::ensure!(edited.description == "Bring photos\n\nAnd the form", "edited description lost: {:?}", edited.description);
+
+    let cleared = editor::todo_to_markdown(&edited, &[]).replace("2031-04-05 09:30", "Not set");
+    let (title, description, due_by) = editor::parse_todo_markdown(&cleared, &edited, crate::dates::END_OF_DAY).map_err(anyhow::Error::msg)?;
+    db.update_todo(edited_id, title, description, due_by)?;
+    anyhow::ensure!(db.get_todo_by_id(edited_id)?.is_some_and(|t| t.due_by.is_none()), "cleared due date not persisted");
+    anyhow::ensure!(editor::parse_todo_markdown("# X\n\n## Due Date\nsoonish\n", &edited, crate::dates::END_OF_DAY).is_err(), "invalid due date accepted");
+    db.delete_todo(edited_id)?;
+    println!("Editor round-trip OK");
+
+    // Test the editor's session directory: private, fresh each time, removed once empty
+    println!("Testing editor files...");
+    let session_dir = editor::create_session_dir()?;
+    let other_dir = editor::create_session_dir()?;
+    anyhow::ensure!(session_dir.is_dir() && session_dir != other_dir, "session directories not distinct");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        anyhow::ensure!(std::fs::metadata(&session_dir)?.permissions().mode() & 0o077 == 0, "session directory readable by others");
+    }
+    std::fs::write(session_dir.join("1_Kept.md"), "# Kept\n")?;
+    editor::remove_session_dir(&session_dir);
+    editor::remove_session_dir(&other_dir);
+    anyhow::ensure!(session_dir.join("1_Kept.md").is_file() && !other_dir.exists(), "session directory cleanup wrong");
+    std::fs::remove_dir_all(&session_dir)?;
+    anyhow::ensure!(Config::default().editor.dir.is_none() && !Config::default().editor.keep_files, "editor files should be temporary by default");
+    let config: Config = toml::from_str("[editor]\ndir = \"~/todo-export\"\nkeep_files = true\n")?;
+    anyhow::ensure!(config.editor.keep_files && config.editor_dir().is_some_and(|dir| dir.ends_with("todo-export") && !dir.starts_with("~")), "[editor] not read");
+    println!("Editor files OK");
+
+    // Test quick-capture line syntax
+    println!("Testing quick capture...");
+    let captured = capture::parse_capture_line("Book flights !p1 @travel due:2d @work", crate::dates::END_OF_DAY)?;
+    anyhow::ensure!(captured.title == "p1 Book flights #travel #work", "capture title wrong: {}", captured.title);
+    anyhow::ensure!(captured.due_by.is_some_and(|due| due > Utc::now() + chrono::Duration::days(1)), "capture due date wrong");
+    let plain = capture::parse_capture_line("  email bob@example.com about !important  ", crate::dates::END_OF_DAY)?;
+    anyhow::ensure!(plain.title == "email bob@example.com about !important" && plain.due_by.is_none(), "plain capture altered: {:?}", plain);
+    anyhow::ensure!(capture::parse_capture_line("Call due:someday", crate::dates::END_OF_DAY).is_err(), "invalid capture due date accepted");
+    anyhow::ensure!(capture::parse_capture_line("!p0 @home", crate::dates::END_OF_DAY).is_err(), "capture without a title accepted");
+    let several = capture::parse_capture_input("Buy milk; Call mom !p2\n\n  Pay rent due:1w ;", crate::dates::END_OF_DAY)?;
+    let titles: Vec<&str> = several.iter().map(|captured| captured.title.as_str()).collect();
+    anyhow::ensure!(titles == ["Buy milk", "p2 Call mom", "Pay rent"] && several[2].due_by.is_some(), "multi-todo capture: {:?}", several);
+    let bad = capture::parse_capture_input("Fine\n!p1", crate::dates::END_OF_DAY).err().map(|e| e.to_string()).unwrap_or_default();
+    anyhow::ensure!(bad.starts_with("Todo 2 ('!p1')"), "multi-todo error should name the entry: {}", bad);
+    let batch = db.create_todos(several.into_iter().map(|captured| NewTodo { title: captured.title, description: String::new(), parent_id: None, due_by: captured.due_by }).collect())?;
+    anyhow::ensure!(batch.len() == 3 && batch.windows(2).all(|ids| ids[0] < ids[1]), "batch create ids: {:?}", batch);
+    for id in batch {
+        db.delete_todo(id)?;
+    }
+    println!("Quick capture OK");
+
+    // Test tag completion: tags ranked by how many todos use them, completing the word being typed
+    println!("Testing tag completion...");
+    let tagged = |id: i64, title: &str, description: &str| Todo {
+        id,
+        title: title.to_string(),
+        description: description.to_string(),
+        created_at: Utc::now(),
+        completed_at: None,
+        due_by: None,
+        parent_id: None,
+        hidden: false,
+        start_at: None,
+    };
+    let known = tags::tag_frequencies(&[
+        tagged(1, "Plan sprint #work #weekly", "also #work"),
+        tagged(2, "Standup #work,", ""),
+        tagged(5, "Retro #work", ""),
+        tagged(3, "Groceries #home", "see #weekly list"),
+        tagged(4, "Issue #42 and C# notes", "#"),
+    ]);
+    let expected_known = [("work", 3), ("weekly", 2), ("42", 1), ("home", 1)];
+    anyhow::ensure!(known.iter().map(|(tag, n)| (tag.as_str(), *n)).eq(expected_known), "tag frequencies wrong: {:?}", known);
+    let names = |input: &str, sigils: &[char]| -> Vec<String> {
+        tags::completions(&known, input, sigils).into_iter().map(|(tag, _)| tag.clone()).collect()
+    };
+    anyhow::ensure!(names("Review #W", &['#']) == ["work", "weekly"], "completions should be case-insensitive and by frequency");
+    anyhow::ensure!(names("Review #work", &['#']).is_empty(), "fully typed tag offered again");
+    anyhow::ensure!(names("Review #w ", &['#']).is_empty() && names("Review @w", &['#']).is_empty(), "completion outside a tag");
+    anyhow::ensure!(names("Review @ho", &['#', '@']) == ["home"], "capture @ completion missing");
+    let mut input = "Review the plan @we".to_string();
+    tags::apply_completion(&mut input, "weekly");
+    anyhow::ensure!(input == "Review the plan @weekly ", "completion not applied: {:?}", input);
+    println!("Tag completion OK");
+
+    // Test parent search ranking: open before completed, then nearest the highlighted todo, then newest
+    println!("Testing parent search ranking...");
+    let node = |id: i64, parent_id: Option<i64>, days_old: i64, completed: bool| Todo {
+        id,
+        title: format!("todo {}", id),
+        description: String::new(),
+        created_at: Utc::now() - chrono::Duration::days(days_old),
+        completed_at: completed.then(Utc::now),
+        due_by: None,
+        parent_id,
+        hidden: false,
+        start_at: None,
+    };
+    // 1 ─┬─ 2 ── 4        10 ── 11
+    //    └─ 3 (done)
+    let family = [node(1, None, 9, false), node(2, Some(1), 5, false), node(3, Some(1), 1, true), node(4, Some(2), 3, false), node(10, None, 0, false), node(11, Some(10), 2, false)];
+    let ranked_ids = |anchor: Option<i64>, exclude: Option<i64>| -> Vec<i64> {
+        tree::rank_parent_candidates(family.to_vec(), &family, anchor, exclude).iter().map(|todo| todo.id).collect()
+    };
+    anyhow::ensure!(ranked_ids(Some(4), None) == [4, 2, 1, 10, 11, 3], "ranking near 4 wrong: {:?}", ranked_ids(Some(4), None));
+    anyhow::ensure!(ranked_ids(None, None) == [10, 1, 11, 2, 4, 3], "ranking without anchor wrong: {:?}", ranked_ids(None, None));
+    anyhow::ensure!(ranked_ids(Some(11), Some(2)) == [11, 10, 1, 3], "moved subtree not excluded: {:?}", ranked_ids(Some(11), Some(2)));
+    println!("Parent search ranking OK");
+
+    // Test move target summaries: open subtasks and the nearest due date below each todo
+    println!("Testing move target summaries...");
+    let soon = Utc::now() + chrono::Duration::days(1);
+    let later = Utc::now() + chrono::Duration::days(5);
+    let mut summary_todos = family.to_vec();
+    summary_todos[3].due_by = Some(later); // 4
+    summary_todos[2].due_by = Some(soon); // 3, completed
+    summary_todos[1].due_by = Some(soon); // 2
+    let mut summary_tree = TodoTreeManager::new();
+    summary_tree.rebuild_from_todos(summary_todos);
+    let summaries = summary_tree.subtree_summaries();
+    let summary = |id: i64| summaries.get(&id).copied().unwrap_or_default();
+    anyhow::ensure!(summary(1) == tree::SubtreeSummary { children: 1, next_due: Some(soon) }, "summary of 1 wrong: {:?}", summary(1));
+    anyhow::ensure!(summary(2) == tree::SubtreeSummary { children: 1, next_due: Some(later) }, "own due date counted: {:?}", summary(2));
+    anyhow::ensure!(summary(4) == tree::SubtreeSummary::default(), "leaf summary wrong: {:?}", summary(4));
+    println!("Move target summaries OK");
+
+    // Test the move preview: where the ghost line goes for each kind of target
+    println!("Testing move preview...");
+    let mut preview_tree = TodoTreeManager::new();
+    preview_tree.rebuild_from_todos(family.to_vec());
+    // Lines: 10, 11, 1, 3, 2, 4
+    let preview = |moved: i64, target: Option<i64>, as_sibling: bool| preview_tree.move_preview(moved, target, as_sibling);
+    anyhow::ensure!(preview(11, Some(1), false) == Some(tree::MovePreview { after_line: Some(3), depth: 1 }), "11 under 1 should land after 3: {:?}", preview(11, Some(1), false));
+    anyhow::ensure!(preview(4, None, false) == Some(tree::MovePreview { after_line: Some(1), depth: 0 }), "4 at top level should land after 10's subtree: {:?}", preview(4, None, false));
+    anyhow::ensure!(preview(11, Some(2), true) == Some(tree::MovePreview { after_line: Some(5), depth: 1 }), "11 after 2 should follow 2's subtree: {:?}", preview(11, Some(2), true));
+    anyhow::ensure!(preview(3, Some(2), false) == Some(tree::MovePreview { after_line: Some(4), depth: 2 }), "3 is newer than 4, so goes right under 2: {:?}", preview(3, Some(2), false));
+    anyhow::ensure!(preview_tree.descendant_count(1) == 3 && preview_tree.descendant_count(4) == 0, "descendant counts wrong");
+    println!("Move preview OK");
+
+    // Test collapsed counts: a folded branch shows how many todos it hides
+    println!("Testing collapsed counts...");
+    let line_text = |tree: &TodoTreeManager, id: i64| tree.get_line_index_for_todo(id).map(|line| tree.get_rendered_lines()[line].display_text.clone());
+    anyhow::ensure!(line_text(&preview_tree, 1).is_some_and(|text| !text.contains("(+")), "expanded node should show no count");
+    preview_tree.toggle_expansion(1);
+    anyhow::ensure!(line_text(&preview_tree, 1).is_some_and(|text| text.ends_with(" (+3)")), "collapsed node should count its descendants: {:?}", line_text(&preview_tree, 1));
+    anyhow::ensure!(line_text(&preview_tree, 4).is_none(), "descendants of a collapsed node should be folded away");
+    println!("Collapsed counts OK");
+
+    // Test per-project stats: each root subtree counted as a whole, busiest first
+    println!("Testing project stats...");
+    let mut project_todos = family.to_vec();
+    project_todos[3].due_by = Some(Utc::now() - chrono::Duration::days(1)); // 4, overdue
+    project_todos.push(Todo { completed_at: Some(Utc::now() - chrono::Duration::days(40)), ..node(12, Some(10), 50, true) });
+    let mut project_tree = TodoTreeManager::new();
+    project_tree.rebuild_from_todos(project_todos);
+    let projects = project_tree.project_stats(None, Utc::now());
+    anyhow::ensure!(
+        projects == [(1, tree::ProjectStats { open: 3, overdue: 1, done_30d: 1 }), (10, tree::ProjectStats { open: 2, overdue: 0, done_30d: 0 })],
+        "root project stats wrong: {:?}", projects
+    );
+    anyhow::ensure!(projects[0].1.completion_rate() == Some(25), "completion rate wrong");
+    let subprojects: Vec<i64> = project_tree.project_stats(Some(1), Utc::now()).iter().map(|(id, _)| *id).collect();
+    anyhow::ensure!(subprojects == [2, 3], "subprojects of 1 wrong: {:?}", subprojects);
+    println!("Project stats OK");
+
+    // Test hoisting: the hoisted todo is the only root, with just its subtree below it
+    println!("Testing hoisted tree...");
+    let mut hoisted_tree = TodoTreeManager::new();
+    hoisted_tree.root = Some(2);
+    hoisted_tree.rebuild_from_todos(family.to_vec());
+    let hoisted_ids: Vec<i64> = hoisted_tree.get_rendered_lines().iter().map(|line| line.todo_id).collect();
+    anyhow::ensure!(hoisted_ids == [2, 4], "hoisted tree should show 2 and its subtree: {:?}", hoisted_ids);
+    anyhow::ensure!(hoisted_tree.get_todo_by_id(1).is_some(), "ancestors stay known for the breadcrumb");
+    hoisted_tree.root = None;
+    hoisted_tree.rebuild_from_todos(family.to_vec());
+    anyhow::ensure!(hoisted_tree.get_rendered_lines().iter().any(|line| line.todo_id == 10), "unhoisting should bring the other roots back");
+    println!("Hoisted tree OK");
+
+    // Test tree motions: parent, siblings and top-level todos, by rendered line
+    println!("Testing tree motions...");
+    let mut motion_tree = TodoTreeManager::new();
+    motion_tree.rebuild_from_todos(family.to_vec());
+    let line = |id: i64| motion_tree.get_line_index_for_todo(id);
+    let at = |id: i64| line(id).ok_or_else(|| anyhow::anyhow!("todo {} not shown", id));
+    anyhow::ensure!(motion_tree.parent_line(at(4)?) == line(2) && motion_tree.parent_line(at(1)?).is_none(), "parent motion wrong");
+    anyhow::ensure!(at(10)? < at(1)?, "newer roots should come first");
+    anyhow::ensure!(motion_tree.sibling_line(at(10)?, true) == line(1) && motion_tree.sibling_line(at(1)?, false) == line(10), "root siblings wrong");
+    anyhow::ensure!(motion_tree.sibling_line(at(11)?, true).is_none() && motion_tree.sibling_line(at(4)?, false).is_none(), "only child has no siblings");
+    anyhow::ensure!(motion_tree.root_line(at(4)?, false) == line(1) && motion_tree.root_line(at(11)?, true) == line(1), "top-level motions wrong");
+    anyhow::ensure!(motion_tree.root_line(at(4)?, true).is_none() && motion_tree.root_line(at(10)?, false).is_none(), "no top-level todo past the ends");
+    println!("Tree motions OK");
     
     // Test subtodos
     let subtodos = db.get_incomplete_todos(Some(todo1_id))?;
@@ -61,6 +336,2261 @@ pub fn test_functionality() -> anyhow::Result<()> {
         println!("  - {}: {}", todo.id, todo.title);
     }
     
+    // Test saved filters
+    println!("Testing saved filters...");
+    db.create_todo(NewTodo {
+        title: "Review PR #work".to_string(),
+        description: String::new(),
+        parent_id: Some(todo1_id),
+        due_by: Some(chrono::Utc::now() + chrono::Duration::days(2)),
+    })?;
+    db.save_filter("work week", "due<7d AND tag:work -status:done")?;
+    db.save_filter("work week", "due<7d tag:work")?; // Same name replaces the query
+    let saved = db.get_saved_filters()?;
+    anyhow::ensure!(saved.len() == 1 && saved[0].query == "due<7d tag:work", "saved filter upsert failed");
+
+    let work_week = Filter::parse(&saved[0].query).map_err(anyhow::Error::msg)?;
+    let matching: HashSet<i64> = db.query_todos(&work_week)?.iter().map(|t| t.id).collect();
+    anyhow::ensure!(matching.len() == 1, "expected exactly one todo due this week tagged #work");
+    let with_context = filter::retain_with_ancestors(db.get_all_todos()?, &matching);
+    anyhow::ensure!(with_context.iter().any(|t| t.id == todo1_id), "filtered tree should keep ancestors");
+    anyhow::ensure!(Filter::parse("due<soon").is_err(), "invalid duration should be rejected");
+    for huge in ["due<1000000000d", "due>-1000000000d", "stale:99999999999d", "due<9999999999999", "due<9223372036854775807m"] {
+        anyhow::ensure!(Filter::parse(huge).is_err(), "out-of-range duration accepted: {}", huge);
+    }
+    anyhow::ensure!(crate::dates::parse_relative_duration("1000000000d").is_none() && crate::dates::parse_relative_duration("9999999999999").is_none(), "out-of-range duration parsed");
+    anyhow::ensure!(report::parse_since("1000000000d", chrono::Utc::now()).is_none(), "out-of-range report period parsed");
+
+    // Filter expressions compile to SQL, including regex and parent terms
+    let by_parent = Filter::parse("parent:1 /review\\s+pr/").map_err(anyhow::Error::msg)?;
+    let found = db.query_todos(&by_parent)?;
+    anyhow::ensure!(found.len() == 1 && found[0].title == "Review PR #work", "parent + regex filter failed");
+    let roots = Filter::parse("parent:none status:open -learn").map_err(anyhow::Error::msg)?;
+    let found = db.query_todos(&roots)?;
+    anyhow::ensure!(found.is_empty(), "root filter should exclude completed and negated todos");
+    let tagged = Filter::parse("tag:wor").map_err(anyhow::Error::msg)?;
+    anyhow::ensure!(db.query_todos(&tagged)?.is_empty(), "tag filter must match whole tags only");
+    anyhow::ensure!(Filter::parse("/unterminated").is_err(), "unterminated regex should be rejected");
+
+    db.delete_filter(saved[0].id)?;
+    anyhow::ensure!(db.get_saved_filters()?.is_empty(), "saved filter was not deleted");
+    println!("Saved filters OK");
+
+    // Test snapshot export: hidden todos drop their subtree, descriptions can be stripped
+    println!("Testing snapshot export...");
+    db.toggle_todo_hidden(todo1_id)?;
+    let options = SnapshotOptions { strip_descriptions: true, exclude_hidden: true, root: None };
+    let snapshot = export::snapshot_todos(&db, &options)?;
+    anyhow::ensure!(
+        snapshot.iter().all(|t| t.id != todo1_id && t.id != subtodo_id),
+        "hidden todo and its children should be excluded"
+    );
+    anyhow::ensure!(snapshot.iter().all(|t| t.description.is_empty()), "descriptions should be stripped");
+    let subtree = export::snapshot_todos(&db, &SnapshotOptions { root: Some(todo1_id), ..Default::default() })?;
+    anyhow::ensure!(subtree.len() == 3 && subtree.iter().any(|t| t.id == subtodo_id), "root export should include descendants");
+    let snapshot_db = Database::new(":memory:")?;
+    snapshot_db.insert_todos_with_ids(&subtree, &HashMap::from([(subtodo_id, Status::Blocked)]))?;
+    anyhow::ensure!(snapshot_db.get_todo_by_id(subtodo_id)?.is_some(), "snapshot should keep todo ids");
+    anyhow::ensure!(snapshot_db.get_statuses()? == HashMap::from([(subtodo_id, Status::Blocked)]), "snapshot should keep statuses");
+    db.toggle_todo_hidden(todo1_id)?;
+    println!("Snapshot export OK");
+
+    // Test the static HTML export: a page per todo, markdown rendered, stale pages removed
+    println!("Testing HTML export...");
+    let html = site::markdown_to_html("# Plan\n\nShip **v2** with `<b>` and [docs](https://x.io) [bad](javascript:alert(1))\n\n- [x] done\n- [ ] open\n\n```\nfn main() {}\n```");
+    anyhow::ensure!(html.starts_with("<h2>Plan</h2><p>Ship <strong>v2</strong> with <code>&lt;b&gt;</code>"), "markdown paragraph: {}", html);
+    anyhow::ensure!(html.contains("<a href=\"https://x.io\">docs</a>") && !html.contains("javascript:"), "markdown links: {}", html);
+    anyhow::ensure!(html.contains("<li><input type=\"checkbox\" checked disabled> done</li>"), "markdown task list: {}", html);
+    anyhow::ensure!(html.ends_with("<pre><code>fn main() {}\n</code></pre>"), "markdown code block: {}", html);
+    let site_dir = std::env::temp_dir().join(format!("tododb_site_test_{}", std::process::id()));
+    std::fs::create_dir_all(&site_dir)?;
+    std::fs::write(site_dir.join("todo-999999.html"), "stale")?;
+    let count = site::export_site(&db, &site_dir, &SnapshotOptions { root: Some(todo1_id), ..Default::default() })?;
+    anyhow::ensure!(count == 3, "expected 3 todos in the HTML export, got {}", count);
+    let index = std::fs::read_to_string(site_dir.join("index.html"))?;
+    anyhow::ensure!(index.contains(&format!("href=\"todo-{}.html\"", subtodo_id)), "index lacks the subtask link");
+    let subtodo_page = std::fs::read_to_string(site_dir.join(format!("todo-{}.html", subtodo_id)))?;
+    anyhow::ensure!(subtodo_page.contains(&format!("<a href=\"todo-{}.html\">", todo1_id)), "subtask page lacks its breadcrumb");
+    anyhow::ensure!(!site_dir.join("todo-999999.html").exists(), "stale page left behind");
+    std::fs::remove_dir_all(&site_dir)?;
+    println!("HTML export OK");
+
+    // Test the CSV export: chosen columns, parent paths and quoting
+    println!("Testing CSV export...");
+    let subtree = export::snapshot_todos(&db, &SnapshotOptions { root: Some(todo1_id), ..Default::default() })?;
+    let columns = CsvColumn::parse_list("id, title, parent-path, status")?;
+    anyhow::ensure!(CsvColumn::parse_list("id,hours").is_err(), "unknown CSV column accepted");
+    let csv = export::todos_to_csv(&subtree, &columns);
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    anyhow::ensure!(lines[0] == "id,title,parent_path,status" && lines.len() == subtree.len() + 2, "CSV header or row count wrong: {:?}", lines);
+    let parent = subtree.iter().find(|t| t.id == todo1_id).map(|t| t.title.clone()).unwrap_or_default();
+    anyhow::ensure!(lines.iter().any(|line| line.starts_with(&format!("{},", subtodo_id)) && line.contains(&parent)), "CSV parent path missing: {:?}", lines);
+    let tricky = Todo { title: "Say \"hi\", then #leave".to_string(), description: "#leave #later".to_string(), ..subtree[0].clone() };
+    let csv = export::todos_to_csv(&[tricky], &CsvColumn::parse_list("title,tags")?);
+    anyhow::ensure!(csv.ends_with("\"Say \"\"hi\"\", then #leave\",leave later\r\n"), "CSV quoting or tags wrong: {}", csv);
+    println!("CSV export OK");
+
+    // Test org mode: the outline round-trips, and hand-written org files import
+    println!("Testing org mode...");
+    let org_db = Database::new(":memory:")?;
+    let project_id = org_db.create_todo(NewTodo {
+        title: "p0 Launch site #work".to_string(),
+        description: "* not a heading\n\nSecond paragraph".to_string(),
+        parent_id: None,
+        due_by: crate::dates::parse_due_date("2030-05-01"),
+    })?;
+    let step_id = org_db.create_todo(NewTodo {
+        title: "Buy domain #dns-records".to_string(),
+        description: String::new(),
+        parent_id: Some(project_id),
+        due_by: None,
+    })?;
+    org_db.complete_todo(step_id)?;
+    let outline = org::write_org(&org_db.get_all_todos()?);
+    anyhow::ensure!(outline.starts_with("* TODO [#A] Launch site :work:\n") && outline.contains("** DONE Buy domain #dns-records\n"), "org headings wrong: {}", outline);
+    anyhow::ensure!(outline.contains("DEADLINE: <2030-05-01") && outline.contains("CLOSED: [") && outline.contains(":CREATED:"), "org planning wrong: {}", outline);
+    let headings = org::parse_org(&outline);
+    anyhow::ensure!(headings.len() == 2 && headings[0].body == "* not a heading\n\nSecond paragraph", "org body not kept: {:?}", headings);
+
+    let org_path = std::env::temp_dir().join(format!("tododb_org_test_{}.org", std::process::id()));
+    std::fs::write(&org_path, format!("{}* Errands\n** NEXT Call plumber :@home:\n   SCHEDULED: <2030-06-02 Tue>\n** CANCELLED Old idea\n", outline))?;
+    let source = import::detect_source(&org_path)?;
+    anyhow::ensure!(source.kind == SourceKind::Org, "org file not detected");
+    let imported_db = Database::new(":memory:")?;
+    import::import_source(&imported_db, &source)?;
+    std::fs::remove_file(&org_path)?;
+    let imported = imported_db.get_all_todos()?;
+    let find = |title: &str| imported.iter().find(|t| t.title == title).cloned().ok_or_else(|| anyhow::anyhow!("'{}' not imported: {:?}", title, imported));
+    let (project, step, errands, plumber) = (find("p0 Launch site #work")?, find("Buy domain #dns-records")?, find("Errands")?, find("Call plumber #home")?);
+    let original = org_db.get_todo_by_id(project_id)?.ok_or_else(|| anyhow::anyhow!("org project missing"))?;
+    anyhow::ensure!(project.due_by == original.due_by && project.description == original.description
+        && (original.created_at - project.created_at).num_seconds() < 60, "org round trip lost fields: {:?}", project);
+    anyhow::ensure!(step.parent_id == Some(project.id) && step.is_completed() && plumber.parent_id == Some(errands.id), "org hierarchy or status lost");
+    anyhow::ensure!(plumber.due_by.is_none() && plumber.start_at == crate::dates::parse_start_date("2030-06-02")
+        && find("Old idea")?.is_completed(), "org SCHEDULED or CANCELLED not read");
+    println!("Org mode OK");
+
+    // Test attachments: counts per todo, removed along with their todo
+    println!("Testing attachments...");
+    let doc_todo_id = db.create_todo(NewTodo {
+        title: "Write spec".to_string(),
+        description: String::new(),
+        parent_id: None,
+        due_by: None,
+    })?;
+    db.add_attachment(doc_todo_id, "/tmp/spec.pdf")?;
+    db.add_attachment(doc_todo_id, "https://example.com/design")?;
+    let attachments = db.get_attachments(doc_todo_id)?;
+    anyhow::ensure!(attachments.len() == 2 && attachments.iter().all(|a| a.todo_id == doc_todo_id), "attachments not stored");
+    anyhow::ensure!(!attachments[0].is_url() && attachments[1].is_url(), "attachment kind detection failed");
+    anyhow::ensure!(db.get_attachment_counts()?.get(&doc_todo_id) == Some(&2), "attachment count mismatch");
+    db.delete_attachment(attachments[0].id)?;
+    anyhow::ensure!(db.get_attachments(doc_todo_id)?.len() == 1, "attachment was not removed");
+    db.delete_todo(doc_todo_id)?;
+    anyhow::ensure!(db.get_attachment_counts()?.is_empty(), "attachments should be deleted with their todo");
+    println!("Attachments OK");
+
+    // Test notes: a per-todo journal, shown in the editor document but not part of the description
+    println!("Testing notes...");
+    let journal_id = db.create_todo(NewTodo {
+        title: "Migrate billing".to_string(),
+        description: "Move invoices to the new provider".to_string(),
+        parent_id: None,
+        due_by: None,
+    })?;
+    db.add_note(journal_id, "Exported last year's invoices")?;
+    db.add_note(journal_id, "Provider call went well\nThey need a CSV")?;
+    anyhow::ensure!(db.add_note(journal_id + 1000, "orphan").is_err(), "note on a missing todo accepted");
+    let notes = db.get_notes(journal_id)?;
+    anyhow::ensure!(notes.len() == 2 && notes[0].body == "Exported last year's invoices" && notes[1].todo_id == journal_id, "notes not stored in order: {:?}", notes);
+    anyhow::ensure!(db.get_note_counts()?.get(&journal_id) == Some(&2), "note count mismatch");
+    let journal = db.get_todo_by_id(journal_id)?.ok_or_else(|| anyhow::anyhow!("journal todo missing"))?;
+    let document = editor::todo_to_markdown(&journal, &notes);
+    anyhow::ensure!(document.contains("## Notes\n- **") && document.contains(" Provider call went well\n  They need a CSV\n"), "notes not in the document: {}", document);
+    let (_, description, _) = editor::parse_todo_markdown(&document, &journal, crate::dates::END_OF_DAY).map_err(|e| anyhow::anyhow!(e))?;
+    anyhow::ensure!(description == journal.description, "notes leaked into the description: {:?}", description);
+    db.delete_todo(journal_id)?;
+    anyhow::ensure!(db.get_note_counts()?.is_empty(), "notes should be deleted with their todo");
+    println!("Notes OK");
+
+    // Test completion log: the hash chain verifies until history is rewritten
+    println!("Testing completion log...");
+    let audit_path = std::env::temp_dir().join(format!("tododb_audit_test_{}.db", std::process::id()));
+    let mut audit_db = Database::new(&audit_path.to_string_lossy())?;
+    audit_db.enable_completion_log();
+    let audited_id = audit_db.create_todo(NewTodo {
+        title: "Ship release".to_string(),
+        description: String::new(),
+        parent_id: None,
+        due_by: None,
+    })?;
+    audit_db.complete_todo(audited_id)?;
+    audit_db.uncomplete_todo(audited_id)?;
+    audit_db.complete_todo(audited_id)?;
+    let report = audit::verify(&audit_db)?;
+    anyhow::ensure!(report.events == 3 && report.problems.is_empty(), "fresh chain should verify: {:?}", report.problems);
+    let events = audit_db.get_completion_events()?;
+    anyhow::ensure!(events[1].prev_hash == events[0].hash, "events should be chained");
+    // Rewrite the first completion's timestamp behind the app's back
+    rusqlite::Connection::open(&audit_path)?
+        .execute("UPDATE completion_log SET at = '2020-01-01T00:00:00Z' WHERE id = 1", [])?;
+    anyhow::ensure!(!audit::verify(&audit_db)?.problems.is_empty(), "rewritten event should fail verification");
+    drop(audit_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", audit_path.display(), suffix));
+    }
+    println!("Completion log OK");
+
+    // Test backups: timestamped copies rotated down to `keep`, upgrade detection and restore
+    println!("Testing backups...");
+    let backup_root = std::env::temp_dir().join(format!("tododb_backup_test_{}", std::process::id()));
+    std::fs::create_dir_all(&backup_root)?;
+    let backup_path = backup_root.join("todos.db");
+    let backup_dir = backup_root.join("backups");
+    let mut backup_db = Database::new(&backup_path.to_string_lossy())?;
+    anyhow::ensure!(!Database::needs_upgrade(&backup_path.to_string_lossy())?, "fresh database needs an upgrade");
+    let kept_id = backup_db.create_todo(NewTodo { title: "Keep me".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let first = backup::backup(&backup_db, &backup_dir, None, 2)?;
+    backup::backup(&backup_db, &backup_dir, Some("import"), 2)?;
+    let newest = backup::backup(&backup_db, &backup_dir, None, 2)?;
+    std::fs::write(backup_dir.join("todos-work-20260101-000000.db"), "")?;
+    let backups = backup::list_backups(&backup_path, &backup_dir)?;
+    anyhow::ensure!(backups.len() == 2 && backups[1] == newest && !first.exists(), "rotation wrong: {:?}", backups);
+    backup_db.delete_todo(kept_id)?;
+    let changes = backup_db.subscribe();
+    backup_db.restore_from(&newest)?;
+    anyhow::ensure!(backup_db.get_todo_by_id(kept_id)?.is_some(), "restore didn't bring the todo back");
+    anyhow::ensure!(changes.try_iter().collect::<Vec<_>>() == [TodoEvent::Reloaded], "restore should ask for a reload");
+    anyhow::ensure!(backup_db.restore_from(&backup_dir.join("todos-work-20260101-000000.db")).is_err(), "restored from a file that isn't a database");
+    drop(backup_db);
+    rusqlite::Connection::open(&backup_path)?.pragma_update(None, "user_version", 0)?;
+    let mut backup_config = Config::default();
+    backup_config.backup.dir = Some(backup_dir.to_string_lossy().to_string());
+    let upgrade = backup::backup_before_upgrade(&backup_path.to_string_lossy(), &backup_config)?;
+    anyhow::ensure!(upgrade.as_ref().is_some_and(|path| path.to_string_lossy().ends_with("-upgrade.db")), "no backup before the upgrade: {:?}", upgrade);
+    anyhow::ensure!(backup::list_backups(&backup_path, &backup_dir)?.len() == 3, "default keep should hold 10");
+    drop(Database::new(&backup_path.to_string_lossy())?);
+    anyhow::ensure!(backup::backup_before_upgrade(&backup_path.to_string_lossy(), &backup_config)?.is_none(), "upgraded database backed up again");
+    std::fs::remove_dir_all(&backup_root)?;
+    println!("Backups OK");
+
+    // Test doctor: bad dates written behind the app's back are found and repaired
+    println!("Testing doctor...");
+    let doctor_path = std::env::temp_dir().join(format!("tododb_doctor_test_{}.db", std::process::id()));
+    let doctor_db = Database::new(&doctor_path.to_string_lossy())?;
+    for title in ["Fine", "Garbled", "From the future", "Done early", "Far off"] {
+        doctor_db.create_todo(NewTodo { title: title.to_string(), description: String::new(), parent_id: None, due_by: Some(Utc::now()) })?;
+    }
+    let raw = rusqlite::Connection::open(&doctor_path)?;
+    raw.execute("UPDATE todos SET created_at = 'yesterday', completed_at = 'eh' WHERE title = 'Garbled'", [])?;
+    raw.execute("UPDATE todos SET created_at = '2099-01-01T00:00:00Z' WHERE title = 'From the future'", [])?;
+    raw.execute("UPDATE todos SET completed_at = '2001-01-01T00:00:00Z' WHERE title = 'Done early'", [])?;
+    raw.execute("UPDATE todos SET due_by = '2205-03-01T00:00:00Z' WHERE title = 'Far off'", [])?;
+    drop(raw);
+    anyhow::ensure!(doctor_db.get_all_todos().is_err(), "a garbled created_at should break loading");
+    let findings = doctor::check(&doctor_db, Utc::now())?;
+    let anomalies: Vec<(&str, &Anomaly)> = findings.iter().map(|finding| (finding.title.as_str(), &finding.anomaly)).collect();
+    anyhow::ensure!(anomalies.len() == 5, "expected 5 anomalies: {:?}", anomalies);
+    anyhow::ensure!(matches!(anomalies[0], ("Garbled", Anomaly::CreatedUnreadable(raw)) if raw == "yesterday"), "garbled created_at: {:?}", anomalies);
+    anyhow::ensure!(matches!(anomalies[1], ("Garbled", Anomaly::CompletedUnreadable(_))), "garbled completed_at: {:?}", anomalies);
+    anyhow::ensure!(matches!(anomalies[2], ("From the future", Anomaly::CreatedInFuture(_))), "future created_at: {:?}", anomalies);
+    anyhow::ensure!(matches!(anomalies[3], ("Done early", Anomaly::CompletedBeforeCreated(_))), "early completion: {:?}", anomalies);
+    anyhow::ensure!(matches!(anomalies[4], ("Far off", Anomaly::DueFarOff(_))), "far due date: {:?}", anomalies);
+    anyhow::ensure!(doctor::fix(&doctor_db, Utc::now())? == 4, "expected 4 todos fixed");
+    anyhow::ensure!(doctor::check(&doctor_db, Utc::now())?.is_empty(), "anomalies left after fixing");
+    let repaired = doctor_db.get_all_todos()?;
+    let find = |title: &str| repaired.iter().find(|todo| todo.title == title);
+    anyhow::ensure!(find("Garbled").is_some_and(|todo| todo.is_completed()), "repaired todo lost its completion");
+    anyhow::ensure!(find("Far off").is_some_and(|todo| todo.due_by.is_none()), "far-off due date not cleared");
+    anyhow::ensure!(find("Fine").is_some_and(|todo| todo.due_by.is_some()), "healthy todo changed");
+    drop(doctor_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", doctor_path.display(), suffix));
+    }
+    println!("Doctor OK");
+
+    // Test indices: an older database gets them on open, and the hot queries use them
+    println!("Testing indices...");
+    let index_path = std::env::temp_dir().join(format!("tododb_index_test_{}.db", std::process::id()));
+    let raw = rusqlite::Connection::open(&index_path)?;
+    raw.execute(
+        "CREATE TABLE todos (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, description TEXT NOT NULL DEFAULT '',
+         created_at TEXT NOT NULL, completed_at TEXT, parent_id INTEGER)",
+        [],
+    )?;
+    raw.execute("INSERT INTO todos (title, created_at) VALUES ('Old', '2024-01-01T00:00:00Z')", [])?;
+    drop(raw);
+    let index_db = Database::new(&index_path.to_string_lossy())?;
+    anyhow::ensure!(!index_db.has_children(1)? && index_db.get_parent_title(Some(1))?.as_deref() == Some("Old"), "older database not readable");
+    let raw = rusqlite::Connection::open(&index_path)?;
+    let index_count: i64 = raw.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name IN ('idx_todos_parent', 'idx_todos_completed', 'idx_todos_due', 'idx_todos_hidden')",
+        [],
+        |row| row.get(0),
+    )?;
+    anyhow::ensure!(index_count == 4, "expected 4 todo indices after migrating, found {}", index_count);
+    let plan: String = raw.query_row("EXPLAIN QUERY PLAN SELECT 1 FROM todos WHERE parent_id = 1", [], |row| row.get(3))?;
+    anyhow::ensure!(plan.contains("idx_todos_parent"), "children lookup doesn't use the parent index: {}", plan);
+    drop(raw);
+    drop(index_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", index_path.display(), suffix));
+    }
+    println!("Indices OK");
+
+    // Test foreign keys: an older table is rebuilt to refuse deleting a parent, orphans move up
+    println!("Testing foreign keys...");
+    let fk_path = std::env::temp_dir().join(format!("tododb_fk_test_{}.db", std::process::id()));
+    let raw = rusqlite::Connection::open(&fk_path)?;
+    // Written without enforcement, as by a SQLite build that leaves foreign keys off
+    raw.execute_batch(
+        "PRAGMA foreign_keys = OFF;
+         CREATE TABLE todos (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, description TEXT NOT NULL DEFAULT '',
+         created_at TEXT NOT NULL, completed_at TEXT, parent_id INTEGER, FOREIGN KEY (parent_id) REFERENCES todos (id));
+         INSERT INTO todos (id, title, created_at) VALUES (1, 'Parent', '2024-01-01T00:00:00Z');
+         INSERT INTO todos (id, title, created_at, parent_id) VALUES (2, 'Child', '2024-01-01T00:00:00Z', 1);
+         INSERT INTO todos (id, title, created_at, parent_id) VALUES (3, 'Orphan', '2024-01-01T00:00:00Z', 40);
+         INSERT INTO todos (id, title, created_at) VALUES (9, 'Gone', '2024-01-01T00:00:00Z');
+         DELETE FROM todos WHERE id = 9;",
+    )?;
+    drop(raw);
+    anyhow::ensure!(Database::needs_upgrade(&fk_path.to_string_lossy())?, "old schema not detected");
+    let fk_db = Database::new(&fk_path.to_string_lossy())?;
+    anyhow::ensure!(fk_db.get_todo_by_id(3)?.is_some_and(|todo| todo.parent_id.is_none()), "orphan not moved to the top level");
+    anyhow::ensure!(fk_db.get_todo_by_id(2)?.is_some_and(|todo| todo.parent_id == Some(1)), "rebuild lost a parent");
+    let err = fk_db.delete_todo(1).err().map(|e| e.to_string()).unwrap_or_default();
+    anyhow::ensure!(err.contains("has subtasks"), "deleting a parent should be refused: {:?}", err);
+    anyhow::ensure!(fk_db.get_todo_by_id(2)?.is_some_and(|todo| todo.parent_id == Some(1)), "refused delete touched the child");
+    let next_id = fk_db.create_todo(NewTodo { title: "New".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    anyhow::ensure!(next_id == 10, "deleted ids reused after the rebuild: {}", next_id);
+    anyhow::ensure!(fk_db.create_todo(NewTodo { title: "Lost".to_string(), description: String::new(), parent_id: Some(99), due_by: None }).is_err(), "child of a missing todo accepted");
+    fk_db.add_attachment(2, "https://example.com")?;
+    anyhow::ensure!(fk_db.delete_subtree(1)? == 2 && fk_db.get_attachment_counts()?.is_empty(), "subtree delete left rows behind");
+    drop(fk_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", fk_path.display(), suffix));
+    }
+    println!("Foreign keys OK");
+
+    // Test short codes: numbered per prefixed project, stable, and usable as references
+    println!("Testing short codes...");
+    let codes_path = std::env::temp_dir().join(format!("tododb_codes_test_{}.db", std::process::id()));
+    let codes_db = Database::new(&codes_path.to_string_lossy())?;
+    let new_todo = |title: &str, parent_id| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: None };
+    let web = codes_db.create_todo(new_todo("Website", None))?;
+    let ops = codes_db.create_todo(new_todo("Ops", None))?;
+    let header = codes_db.create_todo(new_todo("Header", Some(web)))?;
+    let logo = codes_db.create_todo(new_todo("Logo", Some(header)))?;
+    let backup = codes_db.create_todo(new_todo("Backups", Some(ops)))?;
+    anyhow::ensure!(codes_db.refresh_short_codes()?.is_empty(), "codes without any prefix");
+    anyhow::ensure!(codes_db.set_code_prefix(web, Some("9x")).is_err(), "prefix starting with a digit accepted");
+    codes_db.set_code_prefix(web, Some("web"))?;
+    anyhow::ensure!(codes_db.set_code_prefix(ops, Some("WEB")).is_err(), "duplicate prefix accepted");
+    codes_db.set_code_prefix(ops, Some("ops"))?;
+    let codes = codes_db.refresh_short_codes()?;
+    let code = |codes: &HashMap<i64, String>, id| codes.get(&id).cloned().unwrap_or_default();
+    anyhow::ensure!(code(&codes, web) == "WEB" && code(&codes, header) == "WEB-1" && code(&codes, logo) == "WEB-2", "web codes: {:?}", codes);
+    anyhow::ensure!(code(&codes, backup) == "OPS-1", "ops codes: {:?}", codes);
+    let footer = codes_db.create_todo(new_todo("Footer", Some(web)))?;
+    codes_db.delete_todo(logo)?;
+    let codes = codes_db.refresh_short_codes()?;
+    anyhow::ensure!(code(&codes, header) == "WEB-1" && code(&codes, footer) == "WEB-3", "numbers not stable or reused: {:?}", codes);
+    codes_db.move_todo(header, Some(ops))?;
+    let codes = codes_db.refresh_short_codes()?;
+    anyhow::ensure!(code(&codes, header) == "OPS-2", "moved todo kept its old code: {:?}", codes);
+    anyhow::ensure!(codes_db.resolve_todo_reference("ops-2")? == Some(header), "code not resolved case-insensitively");
+    anyhow::ensure!(codes_db.resolve_todo_reference(&footer.to_string())? == Some(footer), "numeric id not resolved");
+    anyhow::ensure!(codes_db.resolve_todo_reference("WEB-2")?.is_none(), "deleted todo's code resolved");
+    codes_db.set_code_prefix(ops, None)?;
+    anyhow::ensure!(!codes_db.refresh_short_codes()?.contains_key(&backup), "cleared prefix still numbers subtasks");
+    let captured = capture::parse_capture_line("Fix favicon ^web-3 !p2", crate::dates::END_OF_DAY)?;
+    anyhow::ensure!(captured.title == "p2 Fix favicon" && captured.parent.as_deref() == Some("web-3"), "capture parent reference: {:?}", captured);
+    drop(codes_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", codes_path.display(), suffix));
+    }
+    println!("Short codes OK");
+
+    // Test templates: a saved subtree is recreated elsewhere with due dates shifted to now
+    println!("Testing templates...");
+    let checklist_id = db.create_todo(NewTodo {
+        title: "Release checklist".to_string(),
+        description: String::new(),
+        parent_id: None,
+        due_by: None,
+    })?;
+    let checklist = db.get_todo_by_id(checklist_id)?.ok_or_else(|| anyhow::anyhow!("todo {} missing", checklist_id))?;
+    let tag_id = db.create_todo(NewTodo {
+        title: "Tag release".to_string(),
+        description: "git tag".to_string(),
+        parent_id: Some(checklist_id),
+        due_by: Some(checklist.created_at + chrono::Duration::days(9)),
+    })?;
+    db.create_todo(NewTodo {
+        title: "Announce".to_string(),
+        description: String::new(),
+        parent_id: Some(tag_id),
+        due_by: None,
+    })?;
+    db.complete_todo(tag_id)?;
+    anyhow::ensure!(template::save_template(&db, "monthly", checklist_id)? == 3, "template should hold 3 todos");
+
+    let saved = template::find_template(&db, "monthly")?;
+    let copy_id = template::instantiate(&db, &saved, Some(todo1_id))?;
+    let copy_children: Vec<_> = db.get_all_todos()?.into_iter().filter(|t| t.parent_id == Some(copy_id)).collect();
+    anyhow::ensure!(copy_children.len() == 1 && copy_children[0].title == "Tag release", "template children not recreated");
+    let copied_tag = &copy_children[0];
+    anyhow::ensure!(!copied_tag.is_completed() && copied_tag.description == "git tag", "template copy kept completion or lost description");
+    let offset = copied_tag.due_by.map(|due| due - Utc::now());
+    anyhow::ensure!(offset.is_some_and(|o| o > chrono::Duration::days(8) && o <= chrono::Duration::days(9)), "template due offset not resolved: {:?}", offset);
+    anyhow::ensure!(db.get_todo_by_id(copy_id)?.is_some_and(|t| t.parent_id == Some(todo1_id)), "template not created under the parent");
+    anyhow::ensure!(template::find_template(&db, "missing").is_err(), "missing template found");
+    db.delete_template(saved.id)?;
+    anyhow::ensure!(db.get_templates()?.is_empty(), "template not deleted");
+    println!("Templates OK");
+
+    // Test duplicating a subtree, including into itself
+    println!("Testing subtree duplication...");
+    let before = db.get_all_todos()?.len();
+    let dup_id = db.duplicate_subtree(checklist_id, Some(tag_id))?;
+    let after = db.get_all_todos()?;
+    anyhow::ensure!(after.len() == before + 3, "expected 3 copied todos, got {}", after.len() - before);
+    let dup = after.iter().find(|t| t.id == dup_id).ok_or_else(|| anyhow::anyhow!("copy missing"))?;
+    anyhow::ensure!(dup.title == "Release checklist" && dup.parent_id == Some(tag_id), "copy not placed under the new parent");
+    let dup_tag = after.iter().find(|t| t.parent_id == Some(dup_id)).ok_or_else(|| anyhow::anyhow!("copied child missing"))?;
+    anyhow::ensure!(dup_tag.title == "Tag release" && !dup_tag.is_completed(), "copied child kept its completion");
+    anyhow::ensure!(after.iter().any(|t| t.parent_id == Some(dup_tag.id) && t.title == "Announce"), "grandchild not copied");
+    anyhow::ensure!(db.duplicate_subtree(-1, None).is_err(), "duplicating a missing todo should fail");
+    println!("Subtree duplication OK");
+
+    // Test the clipboard text: the subtree as a nested task list, and OSC 52's base64
+    println!("Testing clipboard text...");
+    let expected = "- [ ] Release checklist\n  - [x] Tag release\n    - [ ] Announce\n    - [ ] Release checklist\n      - [ ] Tag release\n        - [ ] Announce\n";
+    let markdown = clipboard::subtree_to_markdown(&db, checklist_id)?;
+    anyhow::ensure!(markdown == expected, "unexpected subtree markdown:\n{}", markdown);
+    anyhow::ensure!(clipboard::subtree_to_markdown(&db, -1).is_err(), "subtree of a missing todo should fail");
+    for (input, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("✓ done", "4pyTIGRvbmU=")] {
+        anyhow::ensure!(clipboard::base64_encode(input.as_bytes()) == encoded, "base64 of {:?} should be {}", input, encoded);
+    }
+    println!("Clipboard text OK");
+
+    // Test help page search: matching entries stay under their heading, a heading match keeps its section
+    println!("Testing help search...");
+    let help = ["NAVIGATION", "  j/k   Navigate todos", "  t     Expand tree nodes", "", "ACTIONS", "  n     Create new todo", "  m     Move todo (tree view only)"];
+    anyhow::ensure!(ui::filter_help_lines(&help, "") == help, "empty help query should keep every line");
+    anyhow::ensure!(ui::filter_help_lines(&help, "TREE") == ["NAVIGATION", "  t     Expand tree nodes", "", "ACTIONS", "  m     Move todo (tree view only)"], "help search should match entries case-insensitively");
+    anyhow::ensure!(ui::filter_help_lines(&help, "actions") == ["ACTIONS", "  n     Create new todo", "  m     Move todo (tree view only)"], "heading match should keep its section");
+    anyhow::ensure!(ui::filter_help_lines(&help, "zzz").is_empty(), "unmatched help query should be empty");
+    println!("Help search OK");
+
+    // Test the help page: it scrolls, and shows the config's aliases and settings
+    println!("Testing help page...");
+    let config: Config = toml::from_str("[keys]\nx = \"d\"\n\n[pomodoro]\nwork_minutes = 50\n")?;
+    anyhow::ensure!(config.keys.aliases.get(&'x') == Some(&'d') && toml::from_str::<Config>("[keys]\nx = \"dd\"\n").is_err(), "[keys] not read");
+    let help_db = Database::new(":memory:")?;
+    help_db.create_todo(NewTodo { title: "Chore".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    help_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(help_db, config)?;
+    app.handle_key_event(KeyCode::Char('a'), KeyModifiers::NONE)?;
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("NAVIGATION") && !screen.contains("tododb prefix"), "help should open at the top");
+    app.handle_key_event(KeyCode::Char('G'), KeyModifiers::NONE)?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("tododb prefix") && !screen.contains("NAVIGATION") && app.help_scroll > 0 && app.help_scroll < usize::MAX, "G should scroll to the last page");
+    let last_page = app.help_scroll;
+    app.handle_key_event(KeyCode::Char('k'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.help_scroll + 1 == last_page, "k should scroll up a line");
+    app.handle_key_event(KeyCode::Char('g'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.help_scroll == 0, "g should scroll to the top");
+    for c in "/delete".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("d or x") && screen.contains("Delete selected todo"), "help should list the x alias for d");
+    for c in "/pomodoro".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("(50") && screen.contains("min work, 5 min break), or stop it"), "help should show the configured pomodoro, wrapped");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('x'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::ConfirmDelete, "x should act as d");
+    println!("Help page OK");
+
+    // Test the footer: it suggests the keys of the mode the app is in, aliases included
+    println!("Testing footer key hints...");
+    let hints_db = Database::new(":memory:")?;
+    hints_db.create_todo(NewTodo { title: "Chore".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    hints_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(hints_db, toml::from_str("[keys]\nx = \"d\"\n")?)?;
+    let footer = |app: &mut ui::App, width: u16| -> anyhow::Result<String> {
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, 12))?;
+        terminal.draw(|f| app.draw(f))?;
+        let buffer = terminal.backend().buffer();
+        Ok((0..width).map(|x| buffer[(x, 11)].symbol()).collect())
+    };
+    let bar = footer(&mut app, 140)?;
+    anyhow::ensure!(bar.contains("Space Done") && bar.contains("d or x Delete") && bar.contains("Tab Views") && bar.contains("a Help"), "tree footer hints wrong: {}", bar);
+    let bar = footer(&mut app, 60)?;
+    anyhow::ensure!(bar.contains("Space Done") && !bar.contains("Tab Views") && bar.contains("a Help"), "narrow footer should drop hints but keep help: {}", bar);
+    app.handle_key_event(KeyCode::Char('x'), KeyModifiers::NONE)?;
+    let bar = footer(&mut app, 140)?;
+    anyhow::ensure!(bar.contains("y Yes") && bar.contains("n No") && !bar.contains("Space Done"), "confirm footer hints wrong: {}", bar);
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::NONE)?;
+    let bar = footer(&mut app, 140)?;
+    anyhow::ensure!(bar.contains("Ctrl+S Save") && !bar.contains("a Help"), "create footer hints wrong: {}", bar);
+    println!("Footer key hints OK");
+
+    // Test lock passphrase hashes: salted PBKDF2, with the old bare digest still accepted
+    println!("Testing lock passphrase hash...");
+    let stored = passphrase::hash_lock_passphrase_with("open sesame", b"0123456789abcdef", 1000);
+    anyhow::ensure!(stored.starts_with("pbkdf2-sha256$1000$30313233") && !stored.contains("open sesame"), "lock hash format wrong: {}", stored);
+    anyhow::ensure!(passphrase::verify_lock_passphrase("open sesame", &stored) && !passphrase::verify_lock_passphrase("open sesame!", &stored), "lock hash doesn't verify");
+    anyhow::ensure!(stored != passphrase::hash_lock_passphrase_with("open sesame", b"fedcba9876543210", 1000), "salt not used");
+    anyhow::ensure!(["", "open sesame", "pbkdf2-sha256$0$00$00", "pbkdf2-sha256$1000$zz$00", "sha256$1000$00$00"].iter().all(|bad| !passphrase::verify_lock_passphrase("open sesame", bad)), "malformed lock hash accepted");
+    anyhow::ensure!(passphrase::constant_time_eq(b"abc", b"abc") && !passphrase::constant_time_eq(b"abc", b"abd") && !passphrase::constant_time_eq(b"abc", b"ab"), "constant_time_eq wrong");
+    let mut config = Config::default();
+    config.lock.passphrase_hash = Some(stored);
+    let lock_db = Database::new(":memory:")?;
+    lock_db.set_ui_state("onboarding_done", "1")?;
+    let app = ui::App::new(lock_db, config)?;
+    anyhow::ensure!(app.verify_passphrase("open sesame") && !app.verify_passphrase("wrong"), "lock screen ignores passphrase_hash");
+    let mut config = Config::default();
+    config.lock.passphrase_sha256 = Some(" 41EF4BB0B23661E66301AAC36066912DAC037827B4AE63A7B1165A5AA93ED4EB\n".to_string());
+    let legacy_db = Database::new(":memory:")?;
+    legacy_db.set_ui_state("onboarding_done", "1")?;
+    let app = ui::App::new(legacy_db, config)?;
+    anyhow::ensure!(app.verify_passphrase("open sesame") && !app.verify_passphrase("open sesame "), "passphrase_sha256 no longer unlocks");
+    println!("Lock passphrase hash OK");
+
+    // Test saved view state: collapsed nodes, the hidden toggle and the selection survive a restart
+    println!("Testing saved view state...");
+    let view_path = std::env::temp_dir().join(format!("tododb_view_state_test_{}.db", std::process::id()));
+    let view_db = Database::new(&view_path.to_string_lossy())?;
+    let project_id = view_db.create_todo(NewTodo { title: "Project".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    view_db.create_todo(NewTodo { title: "Task".to_string(), description: String::new(), parent_id: Some(project_id), due_by: None })?;
+    view_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(view_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('t'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('H'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.tree_manager.expansion_states.get(&project_id) == Some(&false), "t should collapse the selected project");
+    app.save_view_state()?;
+    drop(app);
+    let app = ui::App::new(Database::new(&view_path.to_string_lossy())?, Config::default())?;
+    anyhow::ensure!(app.tree_manager.expansion_states.get(&project_id) == Some(&false), "collapsed project not restored");
+    anyhow::ensure!(app.show_hidden_items && app.use_tree_view, "hidden toggle or tree view not restored");
+    anyhow::ensure!(app.tree_list_state.selected() == Some(0) && app.tree_manager.get_rendered_lines().len() == 1, "restored tree should show just the collapsed project");
+    drop(app);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", view_path.display(), suffix));
+    }
+    println!("Saved view state OK");
+
+    // Test goto by full id: a hoist and the hidden toggle are lifted to reach the todo
+    println!("Testing goto by id...");
+    let goto_db = Database::new(":memory:")?;
+    let shelved_id = goto_db.create_todo(NewTodo { title: "Shelved".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let buried_id = goto_db.create_todo(NewTodo { title: "Buried".to_string(), description: String::new(), parent_id: Some(shelved_id), due_by: None })?;
+    goto_db.create_todo(NewTodo { title: "Current".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    goto_db.toggle_todo_hidden(buried_id)?;
+    goto_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(goto_db, Config::default())?;
+    for c in format!("v:id {}", buried_id).chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.tree_manager.root.is_none() && app.show_hidden_items, "goto should lift the hoist and show hidden todos");
+    anyhow::ensure!(app.tree_list_state.selected().is_some() && app.tree_list_state.selected() == app.tree_manager.get_line_index_for_todo(buried_id), "goto should select the todo");
+    anyhow::ensure!(app.status_message.as_deref() == Some(format!("Went to {} (unhoisted, showing hidden)", buried_id).as_str()), "goto status: {:?}", app.status_message);
+    println!("Goto by id OK");
+
+    // Test quick single-field edits: rename, due date and tags without the editor
+    println!("Testing quick edits...");
+    let edit_db = Database::new(":memory:")?;
+    let edit_id = edit_db.create_todo(NewTodo { title: "Fix teh typo #docs".to_string(), description: "Keep me #later".to_string(), parent_id: None, due_by: None })?;
+    edit_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(edit_db, Config::default())?;
+    let type_keys = |app: &mut ui::App, keys: &str| -> anyhow::Result<()> {
+        for c in keys.chars() {
+            app.handle_key_event(if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) }, KeyModifiers::NONE)?;
+        }
+        Ok(())
+    };
+    type_keys(&mut app, "r")?;
+    anyhow::ensure!(app.quick_edit_input == "Fix teh typo #docs", "rename not prefilled: {:?}", app.quick_edit_input);
+    for _ in 0..app.quick_edit_input.len() {
+        app.handle_key_event(KeyCode::Backspace, KeyModifiers::NONE)?;
+    }
+    type_keys(&mut app, "Fix the typo #docs\n")?;
+    type_keys(&mut app, "e3d\n")?;
+    type_keys(&mut app, "#")?;
+    anyhow::ensure!(app.quick_edit_input == "#docs ", "tags not prefilled: {:?}", app.quick_edit_input);
+    type_keys(&mut app, "@writing\n")?;
+    let edited = app.database.get_todo_by_id(edit_id)?.ok_or_else(|| anyhow::anyhow!("edited todo missing"))?;
+    anyhow::ensure!(edited.title == "Fix the typo #docs #writing" && edited.description == "Keep me #later", "quick edits changed the wrong text: {:?}", edited.title);
+    anyhow::ensure!(edited.due_by.is_some_and(|due| due > Utc::now() + chrono::Duration::days(2)), "due date not set");
+    type_keys(&mut app, "e")?;
+    anyhow::ensure!(crate::dates::parse_due_date(&app.quick_edit_input).map(|due| due.timestamp() / 60) == edited.due_by.map(|due| due.timestamp() / 60), "due date not prefilled: {:?}", app.quick_edit_input);
+    for _ in 0..app.quick_edit_input.len() {
+        app.handle_key_event(KeyCode::Backspace, KeyModifiers::NONE)?;
+    }
+    type_keys(&mut app, "\n")?;
+    app.handle_key_event(KeyCode::Char('#'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::List, "Esc should close the quick edit");
+    type_keys(&mut app, "ebogus\n")?;
+    anyhow::ensure!(app.error_message.is_some() && app.mode == ui::AppMode::QuickEdit, "invalid due date accepted");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    let edited = app.database.get_todo_by_id(edit_id)?.ok_or_else(|| anyhow::anyhow!("edited todo missing"))?;
+    anyhow::ensure!(edited.due_by.is_none(), "empty due date should clear it");
+    anyhow::ensure!(tags::replace_tags("#a Plan trip #b, now", &["c"]) == "Plan trip now #c" && tags::replace_tags("Plan #a", &[]) == "Plan", "tags not replaced");
+    println!("Quick edits OK");
+
+    // Test child and sibling shortcuts: O creates next to the selection, A under it
+    println!("Testing child and sibling creation...");
+    let plan_db = Database::new(":memory:")?;
+    let plan_id = plan_db.create_todo(NewTodo { title: "Plan".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    plan_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(plan_db, Config::default())?;
+    type_keys(&mut app, "A")?;
+    anyhow::ensure!(app.mode == ui::AppMode::Create && app.selected_parent_id == Some(plan_id), "A should create under the selection");
+    let selected_todo = |app: &ui::App| -> anyhow::Result<Todo> {
+        let line = app.tree_list_state.selected().and_then(|index| app.tree_manager.get_rendered_lines().get(index).cloned());
+        line.and_then(|line| app.tree_manager.get_todo_by_id(line.todo_id).cloned()).ok_or_else(|| anyhow::anyhow!("new todo not selected"))
+    };
+    type_keys(&mut app, "Phase 1\n")?;
+    let phase_id = selected_todo(&app)?.id;
+    type_keys(&mut app, "OPhase 2\n")?;
+    let sibling = selected_todo(&app)?;
+    anyhow::ensure!(sibling.title == "Phase 2" && sibling.parent_id == Some(plan_id) && sibling.id != phase_id, "O should create a sibling: {:?}", sibling);
+    type_keys(&mut app, "AStep\n")?;
+    let step = selected_todo(&app)?;
+    anyhow::ensure!(step.title == "Step" && step.parent_id == Some(sibling.id), "A should create a child of the new sibling");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(plan_id));
+    type_keys(&mut app, "O")?;
+    anyhow::ensure!(app.selected_parent_id.is_none() && app.input_parent.is_empty(), "a root's sibling should be top-level");
+    println!("Child and sibling creation OK");
+
+    // Test placing a todo after a sibling in move mode, and the manual order that keeps
+    println!("Testing move after sibling...");
+    let order_db = Database::new(":memory:")?;
+    let project_id = order_db.create_todo(NewTodo { title: "Project".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let mut step_ids = Vec::new();
+    for title in ["A", "B", "C"] {
+        step_ids.push(order_db.create_todo(NewTodo { title: title.to_string(), description: String::new(), parent_id: Some(project_id), due_by: None })?);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    let other_id = order_db.create_todo(NewTodo { title: "Other".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let moved_id = order_db.create_todo(NewTodo { title: "Moved".to_string(), description: String::new(), parent_id: Some(other_id), due_by: None })?;
+    order_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(order_db, Config::default())?;
+    let child_titles = |app: &ui::App, parent_id: i64| -> Vec<String> {
+        app.tree_manager.get_rendered_lines().iter()
+            .filter_map(|line| app.tree_manager.get_todo_by_id(line.todo_id))
+            .filter(|todo| todo.parent_id == Some(parent_id))
+            .map(|todo| todo.title.clone())
+            .collect()
+    };
+    anyhow::ensure!(child_titles(&app, project_id) == ["C", "B", "A"], "default order changed: {:?}", child_titles(&app, project_id));
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(moved_id));
+    type_keys(&mut app, "m")?;
+    app.handle_key_event(KeyCode::Tab, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.move_as_sibling, "Tab should switch to placing after");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(step_ids[1]).map(|index| index + 1));
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(child_titles(&app, project_id) == ["C", "B", "Moved", "A"], "not placed after B: {:?}", child_titles(&app, project_id));
+    anyhow::ensure!(app.last_move_parent == Some(Some(project_id)), "M should repeat into the same parent");
+    // A todo added later goes first, ahead of the ones placed by hand
+    app.database.create_todo(NewTodo { title: "D".to_string(), description: String::new(), parent_id: Some(project_id), due_by: None })?;
+    app.refresh_todos()?;
+    anyhow::ensure!(child_titles(&app, project_id) == ["D", "C", "B", "Moved", "A"], "new sibling misplaced: {:?}", child_titles(&app, project_id));
+    anyhow::ensure!(app.database.move_after(project_id, step_ids[0], &[]).is_err(), "placing a todo after its own child accepted");
+    app.database.move_todo(moved_id, None)?;
+    anyhow::ensure!(!app.database.get_positions()?.contains_key(&moved_id), "re-parenting should drop the manual position");
+    println!("Move after sibling OK");
+
+    // Test cycle detection through completed and hidden todos, in the database and move mode
+    println!("Testing move cycles through completed and hidden todos...");
+    let cycle_db = Database::new(":memory:")?;
+    let top_id = cycle_db.create_todo(NewTodo { title: "Top".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let done_id = cycle_db.create_todo(NewTodo { title: "Done".to_string(), description: String::new(), parent_id: Some(top_id), due_by: None })?;
+    let hidden_id = cycle_db.create_todo(NewTodo { title: "Hidden".to_string(), description: String::new(), parent_id: Some(done_id), due_by: None })?;
+    let leaf_id = cycle_db.create_todo(NewTodo { title: "Leaf".to_string(), description: String::new(), parent_id: Some(hidden_id), due_by: None })?;
+    cycle_db.complete_todo(done_id)?;
+    cycle_db.toggle_todo_hidden(hidden_id)?;
+    anyhow::ensure!(cycle_db.move_todo(top_id, Some(leaf_id)).is_err(), "cycle through completed and hidden todos accepted");
+    anyhow::ensure!(cycle_db.move_todo(top_id, Some(hidden_id)).is_err(), "moving under a hidden descendant accepted");
+    anyhow::ensure!(cycle_db.move_todo(leaf_id, Some(top_id)).is_ok(), "moving up the tree refused");
+    cycle_db.move_todo(leaf_id, Some(hidden_id))?;
+    cycle_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(cycle_db, Config::default())?;
+    app.show_hidden_items = true;
+    app.refresh_todos()?;
+    anyhow::ensure!(app.tree_manager.is_descendant_of(leaf_id, top_id), "tree misses the chain through completed and hidden todos");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(top_id));
+    type_keys(&mut app, "m")?;
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(leaf_id).map(|index| index + 1));
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_todo_by_id(top_id)?.and_then(|todo| todo.parent_id).is_none(), "move mode moved a todo under its own descendant");
+    println!("Move cycles OK");
+
+    // Test multi-byte titles: parent display and relative dates split on character boundaries
+    println!("Testing multi-byte titles...");
+    let wide_db = Database::new(":memory:")?;
+    wide_db.create_todo(NewTodo { title: "日本語のとても長いタイトルです、もっと長くするために続けます".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    wide_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(wide_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.input_parent.ends_with('…') && unicode_width::UnicodeWidthStr::width(app.input_parent.as_str()) <= "ID:1 ".len() + 40, "long parent title not truncated: {}", app.input_parent);
+    anyhow::ensure!(crate::dates::parse_relative_duration("2日").is_none() && crate::dates::parse_relative_duration("日").is_none(), "multi-byte unit accepted");
+    anyhow::ensure!(crate::dates::parse_relative_duration("3w") == Some(chrono::Duration::weeks(3)), "relative duration changed");
+    let config: Config = toml::from_str("[display]\nwrap_titles = true\n")?;
+    anyhow::ensure!(config.display.wrap_titles && !Config::default().display.wrap_titles, "[display] wrap_titles not read");
+    println!("Multi-byte titles OK");
+
+    // Test the shared text helpers on emoji-heavy titles like the demo data's
+    println!("Testing text helpers...");
+    anyhow::ensure!(crate::text::truncate_to_width("🏗️ Setup EKS cluster with Terraform", 10) == "🏗️ Setup …", "emoji title not truncated on a grapheme: {}", crate::text::truncate_to_width("🏗️ Setup EKS cluster with Terraform", 10));
+    anyhow::ensure!(crate::text::truncate_to_width("👨‍👩‍👧 family", 2) == "…" && crate::text::truncate_to_width("👨‍👩‍👧 family", 3) == "👨‍👩‍👧…", "emoji sequence split");
+    anyhow::ensure!(crate::text::truncate_to_width("short", 10) == "short" && crate::text::truncate_to_width("anything", 0).is_empty(), "truncation edge cases");
+    anyhow::ensure!(crate::text::split_at_width("e\u{301}te", 1) == ("e\u{301}", "te"), "combining mark separated from its letter");
+    anyhow::ensure!(crate::text::file_name(12, "☁️ Kubernetes Cluster Migration", "md") == "12_Kubernetes_Cluster_Migration.md", "emoji file name: {}", crate::text::file_name(12, "☁️ Kubernetes Cluster Migration", "md"));
+    anyhow::ensure!(crate::text::file_name(3, "📊 Monitoring: Prometheus & Grafana", "md") == "3_Monitoring_Prometheus_Grafana.md", "punctuation not collapsed");
+    anyhow::ensure!(crate::text::file_name(4, "../../etc/passwd", "md") == "4_etc_passwd.md" && crate::text::file_name(5, "🎉🎉🎉", "md") == "5.md", "unsafe file name");
+    anyhow::ensure!(crate::text::file_name(6, "日本語のタイトル", "md") == "6_日本語のタイトル.md", "letters of other scripts dropped");
+    let long = crate::text::file_name(7, &"語".repeat(200), "md");
+    anyhow::ensure!(long.len() < 255 && long.starts_with("7_語"), "long file name not limited: {} bytes", long.len());
+    anyhow::ensure!(crate::text::file_name(8, "Same title", "md") != crate::text::file_name(9, "Same title", "md"), "same titles collide");
+    println!("Text helpers OK");
+
+    // Test profile config: named databases with ~ expansion
+    println!("Testing profiles...");
+    let config: Config = toml::from_str("[[profiles]]\nname = \"work\"\npath = \"~/work/todos.db\"\n\n[[profiles]]\nname = \"home\"\npath = \"/data/home.db\"\n")?;
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    anyhow::ensure!(config.profile("work")?.db_path() == std::path::Path::new(&home).join("work/todos.db"), "profile ~ not expanded");
+    anyhow::ensure!(config.profile("home")?.db_path() == std::path::Path::new("/data/home.db"), "absolute profile path changed");
+    anyhow::ensure!(config.profile("play").is_err(), "unknown profile accepted");
+    println!("Profiles OK");
+
+    // Test the inactive-parent rule setting
+    println!("Testing parent rules...");
+    anyhow::ensure!(Config::default().parents.inactive == ParentRule::Allow, "inactive parents should be allowed by default");
+    let config: Config = toml::from_str("[parents]\ninactive = \"prevent\"\n")?;
+    anyhow::ensure!(config.parents.inactive == ParentRule::Prevent, "[parents] inactive not read");
+    anyhow::ensure!(toml::from_str::<Config>("[parents]\ninactive = \"sometimes\"\n").is_err(), "unknown parent rule accepted");
+    println!("Parent rules OK");
+
+    // Test completion cascades: a project completes with its open subtasks, a subtask reopens its parents
+    println!("Testing completion cascade...");
+    anyhow::ensure!(Config::default().parents.complete_children == CascadeRule::Never && Config::default().parents.reopen_parents == CascadeRule::Never, "cascades should be off by default");
+    let config: Config = toml::from_str("[parents]\ncomplete_children = \"ask\"\nreopen_parents = \"always\"\n")?;
+    anyhow::ensure!(config.parents.complete_children == CascadeRule::Ask && config.parents.reopen_parents == CascadeRule::Always, "[parents] cascade rules not read");
+    anyhow::ensure!(Config::default().parents.open_children == ParentRule::Allow, "open subtasks should not block completion by default");
+    let config: Config = toml::from_str("[parents]\nopen_children = \"prevent\"\n")?;
+    anyhow::ensure!(config.parents.open_children == ParentRule::Prevent, "[parents] open_children not read");
+    let mut cascade_db = Database::new(":memory:")?;
+    cascade_db.enable_completion_log();
+    let new_todo = |title: &str, parent_id| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: None };
+    let project = cascade_db.create_todo(new_todo("Launch", None))?;
+    let phase = cascade_db.create_todo(new_todo("Phase 1", Some(project)))?;
+    let task = cascade_db.create_todo(new_todo("Write copy", Some(phase)))?;
+    let done_task = cascade_db.create_todo(new_todo("Pick a name", Some(phase)))?;
+    cascade_db.complete_todo(done_task)?;
+    let changes = cascade_db.subscribe();
+    let completed = cascade_db.complete_subtree(project)?;
+    anyhow::ensure!(completed == [project, phase, task], "wrong todos completed: {:?}", completed);
+    anyhow::ensure!(cascade_db.get_all_todos()?.iter().all(|todo| todo.is_completed()), "subtree not fully completed");
+    anyhow::ensure!(changes.try_iter().collect::<Vec<_>>() == [TodoEvent::Completed(project), TodoEvent::Completed(phase), TodoEvent::Completed(task)], "cascade events wrong");
+    anyhow::ensure!(cascade_db.get_completed_ancestors(task)?.iter().map(|todo| todo.id).eq([phase, project]), "completed ancestors wrong");
+    let reopened = cascade_db.uncomplete_with_ancestors(task)?;
+    anyhow::ensure!(reopened == [task, phase, project], "wrong todos reopened: {:?}", reopened);
+    anyhow::ensure!(cascade_db.get_todo_by_id(done_task)?.is_some_and(|todo| todo.is_completed()), "a sibling should stay completed");
+    let report = audit::verify(&cascade_db)?;
+    anyhow::ensure!(report.events == 7 && report.problems.is_empty(), "cascade broke the completion log: {:?}", report.problems);
+    println!("Completion cascade OK");
+
+    // Test the checkpoint settings and the WAL size they apply
+    println!("Testing checkpoint settings...");
+    let defaults = Config::default().checkpoint;
+    anyhow::ensure!(defaults.on_editor && defaults.on_suspend && defaults.wal_pages == 5000 && defaults.interval_minutes == 5, "checkpoint defaults wrong: {:?}", defaults);
+    let display = Config::default().display;
+    anyhow::ensure!(display.tick_ms == 1000 && display.message_seconds == 5, "display tick defaults wrong: {:?}", display);
+    let columns = Config::default().columns;
+    anyhow::ensure!(columns.show.is_empty() && columns.hide.is_empty() && columns.parent_width == 24, "column defaults wrong: {:?}", columns);
+    let config: Config = toml::from_str("[columns]\nshow = [\"tags\", \"priority\"]\nhide = [\"created\"]\ntags_width = 10\n")?;
+    anyhow::ensure!(config.columns.show == [Column::Tags, Column::Priority] && config.columns.hide == [Column::Created] && config.columns.tags_width == 10, "[columns] not read");
+    anyhow::ensure!(toml::from_str::<Config>("[columns]\nshow = [\"spent\"]\n").is_err(), "unknown column accepted");
+    let config: Config = toml::from_str("[display]\ntick_ms = 250\nmessage_seconds = 0\n")?;
+    anyhow::ensure!(config.display.tick_ms == 250 && config.display.message_seconds == 0 && config.display.ids == IdDisplay::Mod, "[display] tick settings not read");
+    let config: Config = toml::from_str("[checkpoint]\nwal_pages = 200\non_suspend = false\n")?;
+    anyhow::ensure!(config.checkpoint.wal_pages == 200 && !config.checkpoint.on_suspend && config.checkpoint.on_editor, "[checkpoint] not read");
+    db.set_wal_autocheckpoint(config.checkpoint.wal_pages)?;
+    anyhow::ensure!(db.wal_autocheckpoint()? == 200, "wal_pages not applied");
+    db.set_wal_autocheckpoint(defaults.wal_pages)?;
+    println!("Checkpoint settings OK");
+
+    // Test session metrics: stored per database, totalled per week, and switchable off
+    println!("Testing session metrics...");
+    anyhow::ensure!(Config::default().metrics.enabled, "metrics should default to on");
+    anyhow::ensure!(!toml::from_str::<Config>("[metrics]\nenabled = false\n")?.metrics.enabled, "[metrics] enabled not read");
+    let metrics_db = Database::new(":memory:")?;
+    let now = Utc::now();
+    let earlier = now - chrono::Duration::weeks(1);
+    let first = metrics_db.start_session(earlier)?;
+    metrics_db.update_session(&Session { id: first, started_at: earlier, ended_at: earlier + chrono::Duration::minutes(90), created: 3, completed: 2 })?;
+    let second = metrics_db.start_session(now - chrono::Duration::minutes(5))?;
+    metrics_db.update_session(&Session { id: second, started_at: now - chrono::Duration::minutes(5), ended_at: now, created: 1, completed: 0 })?;
+    let old = metrics_db.start_session(now - chrono::Duration::weeks(10))?;
+    let sessions = metrics_db.get_sessions_since(now - chrono::Duration::weeks(4))?;
+    anyhow::ensure!(sessions.len() == 2 && sessions[0].id == first && sessions[0].created == 3 && !sessions.iter().any(|s| s.id == old), "sessions not stored: {:?}", sessions);
+    let trends = metrics::weekly_trends(&sessions, now, 4, chrono::Weekday::Mon);
+    anyhow::ensure!(trends.len() == 4 && trends[3].sessions == 1 && trends[3].created == 1, "current week wrong: {:?}", trends);
+    anyhow::ensure!(trends[2].time == chrono::Duration::minutes(90) && trends[2].completed == 2 && trends[0].sessions == 0, "previous weeks wrong: {:?}", trends);
+    anyhow::ensure!(trends.windows(2).all(|w| w[1].start - w[0].start == chrono::Duration::weeks(1)), "weeks not consecutive");
+    anyhow::ensure!(metrics::format_duration(chrono::Duration::minutes(125)) == "2h 05m" && metrics::format_duration(chrono::Duration::minutes(45)) == "45m", "duration format wrong");
+    println!("Session metrics OK");
+
+    // Test change events: every subscriber hears each change once, in order
+    println!("Testing change events...");
+    let events_db = Database::new(":memory:")?;
+    let changes = events_db.subscribe();
+    let dropped = events_db.subscribe();
+    drop(dropped);
+    let new_todo = |title: &str, parent_id| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: None };
+    let parent = events_db.create_todo(new_todo("Parent", None))?;
+    let child = events_db.create_todos(vec![new_todo("Child", None)])?[0];
+    events_db.update_todo(child, "Child!".to_string(), String::new(), None)?;
+    events_db.move_todo(child, Some(parent))?;
+    events_db.complete_todo(child)?;
+    events_db.uncomplete_todo(child)?;
+    let child_todo = events_db.get_todo_by_id(child)?.ok_or_else(|| anyhow::anyhow!("child missing"))?;
+    events_db.delete_todo(child)?;
+    let received: Vec<TodoEvent> = changes.try_iter().collect();
+    anyhow::ensure!(
+        received == [TodoEvent::Created(parent), TodoEvent::Created(child), TodoEvent::Updated(child), TodoEvent::Moved(child),
+                     TodoEvent::Completed(child), TodoEvent::Uncompleted(child), TodoEvent::Deleted(child_todo)],
+        "wrong change events: {:?}", received
+    );
+    let copy = events_db.duplicate_subtree(parent, None)?;
+    anyhow::ensure!(changes.try_iter().collect::<Vec<_>>() == [TodoEvent::Created(copy)], "duplicate should report its copies");
+    events_db.delete_subtree(copy)?;
+    anyhow::ensure!(matches!(changes.try_iter().collect::<Vec<_>>()[..], [TodoEvent::Deleted(ref todo)] if todo.id == copy), "subtree delete not reported");
+    println!("Change events OK");
+
+    // Test transactions: a failure part way keeps nothing, operations nest, events wait for the commit
+    println!("Testing transactions...");
+    let before = events_db.get_all_todos()?.len();
+    let failed: anyhow::Result<()> = events_db.with_transaction(|db| {
+        let project = db.create_todo(new_todo("Project", None))?;
+        db.create_todos(vec![new_todo("Step", Some(project))])?;
+        db.complete_subtree(project)?;
+        anyhow::bail!("disk full")
+    });
+    anyhow::ensure!(failed.is_err() && events_db.get_all_todos()?.len() == before, "failed transaction left todos behind");
+    anyhow::ensure!(changes.try_iter().next().is_none(), "rolled back changes reported");
+    let (project, step) = events_db.with_transaction(|db| {
+        let project = db.create_todo(new_todo("Project", None))?;
+        let step = db.create_todo(new_todo("Step", Some(project)))?;
+        // A failed inner operation is undone on its own
+        anyhow::ensure!(db.move_todo(project, Some(step)).is_err(), "cycle accepted");
+        let inner: anyhow::Result<()> = db.with_transaction(|db| {
+            db.delete_subtree(project)?;
+            anyhow::bail!("changed my mind")
+        });
+        anyhow::ensure!(inner.is_err(), "inner transaction should fail");
+        anyhow::ensure!(changes.try_iter().next().is_none(), "events sent before the commit");
+        Ok((project, step))
+    })?;
+    anyhow::ensure!(events_db.get_subtree(project)?.len() == 2, "inner rollback undid the outer transaction");
+    anyhow::ensure!(changes.try_iter().collect::<Vec<_>>() == [TodoEvent::Created(project), TodoEvent::Created(step)], "committed events wrong");
+    println!("Transactions OK");
+
+    // Test two instances on one file: external changes are noticed, writers wait for the lock
+    println!("Testing concurrent access...");
+    let shared_path = std::env::temp_dir().join(format!("tododb_shared_test_{}.db", std::process::id()));
+    let first_db = Database::new(&shared_path.to_string_lossy())?;
+    let second_db = Database::new(&shared_path.to_string_lossy())?;
+    second_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(second_db, Config::default())?;
+    anyhow::ensure!(!app.database.changed_elsewhere()?, "nothing changed elsewhere yet");
+    let outside_id = first_db.create_todo(new_todo("Added in another terminal", None))?;
+    app.tick()?;
+    anyhow::ensure!(app.tree_manager.get_todo_by_id(outside_id).is_some(), "external change not picked up");
+    app.database.create_todo(new_todo("Added here", None))?;
+    anyhow::ensure!(!app.database.changed_elsewhere()?, "own write counted as external");
+    // Another writer holds the lock for a moment; this write waits for it instead of failing
+    let locker = rusqlite::Connection::open(&shared_path)?;
+    locker.execute_batch("BEGIN IMMEDIATE; INSERT INTO ui_state (key, value) VALUES ('locked', '1');")?;
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        locker.execute_batch("COMMIT")
+    });
+    app.database.with_transaction(|db| db.create_todos(vec![new_todo("Waited", None)]))?;
+    release.join().map_err(|_| anyhow::anyhow!("lock holder panicked"))??;
+    let busy = anyhow::Error::from(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None));
+    anyhow::ensure!(crate::database::is_busy(&busy) && !crate::database::is_busy(&anyhow::anyhow!("other")), "busy errors not recognized");
+    drop(app);
+    drop(first_db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", shared_path.display(), suffix));
+    }
+    println!("Concurrent access OK");
+
+    // Test the scripting query: filtered todos as JSON lines, one object per line
+    println!("Testing JSON query output...");
+    let query_db = Database::new(":memory:")?;
+    let report_id = query_db.create_todo(NewTodo { title: "Write report".to_string(), description: "draft \"v2\"".to_string(), parent_id: None, due_by: None })?;
+    let done_id = query_db.create_todo(NewTodo { title: "File taxes".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    query_db.complete_todo(done_id)?;
+    let lines = cli::query_json_lines(&query_db, "status:open")?;
+    anyhow::ensure!(lines.len() == 1 && !lines[0].contains('\n'), "open query lines: {:?}", lines);
+    let parsed: serde_json::Value = serde_json::from_str(&lines[0])?;
+    anyhow::ensure!(parsed["id"] == report_id && parsed["title"] == "Write report" && parsed["description"] == "draft \"v2\"", "query JSON: {}", parsed);
+    anyhow::ensure!(parsed.get("code").is_none(), "code without short codes: {}", parsed);
+    anyhow::ensure!(cli::query_json_lines(&query_db, "")?.len() == 2, "empty expression should list every todo");
+    anyhow::ensure!(cli::query_json_lines(&query_db, "due<").is_err(), "invalid expression accepted");
+    println!("JSON query output OK");
+
+    // Test the fzf picker lines and the shell completion scripts
+    println!("Testing pick and completions...");
+    let step_id = query_db.create_todo(NewTodo { title: "Outline\tsections".to_string(), description: String::new(), parent_id: Some(report_id), due_by: None })?;
+    let choices = cli::pick_lines(&query_db, "")?;
+    anyhow::ensure!(choices.len() == 2, "completed todos offered: {:?}", choices);
+    anyhow::ensure!(choices.contains(&format!("{}\tOutline sections  (Write report)", step_id)), "pick line: {:?}", choices);
+    anyhow::ensure!(cli::pick_lines(&query_db, &format!("parent:{}", report_id))?.len() == 1, "pick filter ignored");
+    for shell in completions::SHELLS {
+        let script = completions::script(shell)?;
+        anyhow::ensure!(script.contains("tododb") && cli::COMMANDS.iter().all(|command| script.contains(command)), "{} completions miss commands", shell);
+    }
+    anyhow::ensure!(completions::script("tcsh").is_err(), "unknown shell accepted");
+    println!("Pick and completions OK");
+
+    // Test the completed-work report: grouped by project, then by day, with the path in between
+    println!("Testing completed report...");
+    let now = Utc::now();
+    let report_todo = |id: i64, title: &str, parent_id: Option<i64>, days_ago: Option<i64>| Todo {
+        id,
+        title: title.to_string(),
+        description: String::new(),
+        created_at: now - chrono::Duration::days(30),
+        completed_at: days_ago.map(|days| now - chrono::Duration::days(days)),
+        due_by: None,
+        parent_id,
+        hidden: false,
+        start_at: None,
+    };
+    let work = vec![
+        report_todo(1, "Website", None, None),
+        report_todo(2, "Design", Some(1), None),
+        report_todo(3, "Pick fonts", Some(2), Some(2)),
+        report_todo(4, "Launch", Some(1), Some(0)),
+        report_todo(5, "Backlog cleanup", None, Some(1)),
+        report_todo(6, "Old task", Some(1), Some(20)),
+    ];
+    let since = report::parse_since("1w", now).ok_or_else(|| anyhow::anyhow!("1w not parsed"))?;
+    let markdown = report::completed_report(&work, &HashSet::new(), since, ReportFormat::Markdown);
+    anyhow::ensure!(markdown.contains("3 todos completed in 2 projects") && !markdown.contains("Old task"), "report totals: {}", markdown);
+    let backlog = markdown.find("## Backlog cleanup").unwrap_or(usize::MAX);
+    let website = markdown.find("## Website").unwrap_or(usize::MAX);
+    let fonts = markdown.find("- [x] Pick fonts (Design)").unwrap_or(usize::MAX);
+    let launch = markdown.find("- [x] Launch\n").unwrap_or(usize::MAX);
+    anyhow::ensure!(backlog < website && website < fonts && fonts < launch && launch < markdown.len(), "report order: {}", markdown);
+    anyhow::ensure!(markdown.matches("### ").count() == 3, "one heading per project and day: {}", markdown);
+    let text = report::completed_report(&work, &HashSet::new(), now + chrono::Duration::minutes(1), ReportFormat::Text);
+    anyhow::ensure!(text.contains("Nothing completed") && !text.contains('#'), "empty text report: {}", text);
+    anyhow::ensure!(report::parse_since("2026-01-05", now).is_some() && report::parse_since("soon", now).is_none(), "--since parsing");
+    anyhow::ensure!(ReportFormat::parse("md")? == ReportFormat::Markdown && ReportFormat::parse("pdf").is_err(), "format parsing");
+    println!("Completed report OK");
+
+    // Test the review: the queue's order and reasons, then keeping, snoozing and deleting
+    println!("Testing review...");
+    let updated_at: HashMap<i64, chrono::DateTime<Utc>> = [(3, now - chrono::Duration::days(90)), (4, now - chrono::Duration::days(45)), (5, now)].into();
+    let mut queued = vec![
+        Todo { due_by: Some(now - chrono::Duration::days(1)), ..report_todo(1, "Late", Some(4), None) },
+        report_todo(2, "Done", None, Some(1)),
+        report_todo(3, "Forgotten", Some(4), None),
+        report_todo(4, "Garden", None, None),
+        report_todo(5, "Attic", None, None),
+        Todo { hidden: true, ..report_todo(6, "Hidden", None, None) },
+        Todo { start_at: Some(now + chrono::Duration::days(3)), ..report_todo(7, "Deferred", None, None) },
+    ];
+    queued[0].created_at = now;
+    let queue: Vec<(i64, review::ReviewReason)> = review::review_queue(&queued, &updated_at, 30, now).iter().map(|item| (item.id, item.reason)).collect();
+    anyhow::ensure!(
+        queue == [(1, review::ReviewReason::Overdue), (3, review::ReviewReason::Stale), (4, review::ReviewReason::Stale), (5, review::ReviewReason::Project)],
+        "review queue: {:?}", queue
+    );
+
+    let review_db = Database::new(":memory:")?;
+    let late_id = review_db.create_todo(NewTodo { title: "Late".to_string(), description: String::new(), parent_id: None, due_by: Some(now - chrono::Duration::days(2)) })?;
+    let garden_id = review_db.create_todo(NewTodo { title: "Garden".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let weeds_id = review_db.create_todo(NewTodo { title: "Weeds".to_string(), description: String::new(), parent_id: Some(garden_id), due_by: None })?;
+    review_db.set_ui_state("onboarding_done", "1")?;
+    let mut review_config = Config::default();
+    review_config.review.stale_days = 0;
+    let mut app = ui::App::new(review_db, review_config)?;
+    let before = app.database.get_updated_at()?;
+    // updated_at has millisecond precision
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    app.handle_key_event(KeyCode::Char('R'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::Review && app.review_queue.len() == 3 && app.review_queue[0].id == late_id, "review not started: {:?}", app.review_queue);
+    app.handle_key_event(KeyCode::Char('k'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_updated_at()?[&late_id] > before[&late_id], "keeping should mark the todo as touched");
+    app.handle_key_event(KeyCode::Char('s'), KeyModifiers::NONE)?;
+    let garden = app.database.get_todo_by_id(garden_id)?.ok_or_else(|| anyhow::anyhow!("garden missing"))?;
+    anyhow::ensure!(garden.start_at.is_some_and(|start| start > now + chrono::Duration::days(6)), "snooze should defer by a week: {:?}", garden.start_at);
+    app.handle_key_event(KeyCode::Char('d'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.review_confirm_delete, "delete should ask first");
+    app.handle_key_event(KeyCode::Char('y'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_todo_by_id(weeds_id)?.is_none(), "confirmed delete didn't delete");
+    anyhow::ensure!(app.mode == ui::AppMode::List && app.status_message.as_deref() == Some("Review: 3 of 3 todos seen, 3 handled"), "review end: {:?}", app.status_message);
+    println!("Review OK");
+
+    // Test stale todos: notes count as changes, the stale filter and the aging badges
+    println!("Testing stale todos...");
+    let mut stale_db = Database::new(":memory:")?;
+    let idle_id = stale_db.create_todo(NewTodo { title: "Idle".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let finished_id = stale_db.create_todo(NewTodo { title: "Finished".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    stale_db.complete_todo(finished_id)?;
+    let before = stale_db.get_updated_at()?[&idle_id];
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    stale_db.add_note(idle_id, "still thinking")?;
+    anyhow::ensure!(stale_db.get_updated_at()?[&idle_id] > before, "a note should count as a change");
+    anyhow::ensure!(Filter::parse("stale:2w").map_err(anyhow::Error::msg)?.conditions[0].term == filter::FilterTerm::Stale(Some(chrono::Duration::weeks(2))), "stale period not parsed");
+    anyhow::ensure!(Filter::parse("stale:soon").is_err(), "invalid stale period accepted");
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let stale_ids = |db: &Database, expression: &str| -> anyhow::Result<Vec<i64>> {
+        Ok(db.query_todos(&Filter::parse(expression).map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect())
+    };
+    anyhow::ensure!(stale_ids(&stale_db, "stale")?.is_empty(), "nothing is 30 days old yet");
+    anyhow::ensure!(stale_ids(&stale_db, "stale:0m")? == [idle_id], "completed todos are never stale");
+    stale_db.set_stale_days(0);
+    anyhow::ensure!(stale_ids(&stale_db, "stale")? == [idle_id], "bare stale should use the configured days");
+    stale_db.set_ui_state("onboarding_done", "1")?;
+    let mut stale_config = Config::default();
+    stale_config.review.stale_days = 0;
+    let app = ui::App::new(stale_db, stale_config)?;
+    anyhow::ensure!(app.stale_ages.keys().copied().collect::<Vec<_>>() == [idle_id], "stale ages: {:?}", app.stale_ages);
+    println!("Stale todos OK");
+
+    // Negated terms keep the todos whose column is NULL
+    println!("Testing negated filters...");
+    let negation_db = Database::new(":memory:")?;
+    let plain_id = negation_db.create_todo(NewTodo { title: "Plain".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let labelled_id = negation_db.create_todo(NewTodo { title: "Labelled".to_string(), description: String::new(), parent_id: Some(plain_id), due_by: None })?;
+    negation_db.set_context(labelled_id, Some("home"))?;
+    negation_db.set_energy(labelled_id, Some(Energy::Low))?;
+    negation_db.set_status(labelled_id, Status::Blocked)?;
+    let negated_ids = |expression: &str| -> anyhow::Result<Vec<i64>> {
+        Ok(negation_db.query_todos(&Filter::parse(expression).map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect())
+    };
+    for expression in [format!("-parent:{}", plain_id), "-@home".to_string(), "-context:home".to_string(), "-energy:low".to_string(), "-status:blocked".to_string()] {
+        anyhow::ensure!(negated_ids(&expression)? == [plain_id], "{} dropped the todo without that field", expression);
+    }
+    anyhow::ensure!(negated_ids("-parent:none")? == [labelled_id] && negated_ids("-context:none")? == [labelled_id], "negated none filters wrong");
+    anyhow::ensure!(negated_ids("-waiting:acme")?.len() == 2, "-waiting:name dropped todos that aren't waiting");
+    println!("Negated filters OK");
+
+    // Test pomodoros: the work/break cycle, the countdown and the log per todo
+    println!("Testing pomodoros...");
+    let started = Utc::now();
+    let mut cycle = Pomodoro::start(1, chrono::Duration::minutes(25), started);
+    anyhow::ensure!(pomodoro::format_countdown(cycle.remaining(started + chrono::Duration::milliseconds(500))) == "25:00", "countdown rounds up");
+    anyhow::ensure!(cycle.advance(chrono::Duration::minutes(5), started + chrono::Duration::minutes(24)).is_none(), "work ended early");
+    let late = started + chrono::Duration::minutes(27);
+    anyhow::ensure!(cycle.advance(chrono::Duration::minutes(5), late) == Some(pomodoro::Transition::BreakStarted { work_started: started }), "work should end");
+    anyhow::ensure!(cycle.phase == pomodoro::Phase::Break && pomodoro::format_countdown(cycle.remaining(late)) == "03:00", "break should start when work ended");
+    anyhow::ensure!(cycle.advance(chrono::Duration::minutes(5), started + chrono::Duration::minutes(30)) == Some(pomodoro::Transition::Finished), "break should end");
+
+    let pomodoro_db = Database::new(":memory:")?;
+    let focus_id = pomodoro_db.create_todo(NewTodo { title: "Focus".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    pomodoro_db.set_ui_state("onboarding_done", "1")?;
+    let mut pomodoro_config = Config::default();
+    pomodoro_config.pomodoro.bell = false;
+    let mut app = ui::App::new(pomodoro_db, pomodoro_config)?;
+    app.handle_key_event(KeyCode::Char('w'), KeyModifiers::NONE)?;
+    let began = app.pomodoro.as_ref().map(|pomodoro| (pomodoro.todo_id, pomodoro.phase_started)).ok_or_else(|| anyhow::anyhow!("w should start a pomodoro"))?;
+    anyhow::ensure!(began.0 == focus_id, "pomodoro bound to the wrong todo");
+    app.tick_pomodoro(began.1 + chrono::Duration::minutes(25))?;
+    anyhow::ensure!(app.pomodoro_counts.get(&focus_id) == Some(&1) && app.pomodoro.as_ref().is_some_and(|p| p.phase == pomodoro::Phase::Break), "finished work not logged");
+    app.tick_pomodoro(began.1 + chrono::Duration::minutes(30))?;
+    anyhow::ensure!(app.pomodoro.is_none() && app.status_message.as_deref() == Some("Break over"), "cycle should end after the break");
+    app.handle_key_event(KeyCode::Char('w'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('w'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.pomodoro.is_none() && app.database.get_pomodoro_counts()?[&focus_id] == 1, "a stopped pomodoro isn't logged");
+    println!("Pomodoros OK");
+
+    // Test estimates and the agenda: parsing, each day's workload and overbooked days
+    println!("Testing estimates and agenda...");
+    for (input, minutes) in [("90", Some(90)), ("45m", Some(45)), ("2h", Some(120)), ("1h30m", Some(90)), ("1h 30m", Some(90)), ("1.5h", Some(90)), ("0", None), ("h", None), ("2d", None), ("soon", None)] {
+        anyhow::ensure!(agenda::parse_estimate(input) == minutes, "estimate '{}' parsed as {:?}", input, agenda::parse_estimate(input));
+    }
+    anyhow::ensure!(agenda::format_estimate(45) == "45m" && agenda::format_estimate(120) == "2h" && agenda::format_estimate(90) == "1h30m", "estimates formatted wrong");
+
+    let agenda_db = Database::new(":memory:")?;
+    let today = Local::now().date_naive();
+    let noon = |days: i64| {
+        (today + chrono::Duration::days(days)).and_hms_opt(12, 0, 0).and_then(|at| at.and_local_timezone(Local).earliest()).map(|at| at.with_timezone(&Utc))
+    };
+    let due = |title: &str, days: i64, minutes: Option<i64>| -> anyhow::Result<i64> {
+        let id = agenda_db.create_todo(NewTodo { title: title.to_string(), description: String::new(), parent_id: None, due_by: noon(days) })?;
+        agenda_db.set_estimate(id, minutes)?;
+        Ok(id)
+    };
+    let late_id = due("Late report", -1, None)?;
+    due("Write draft", 0, Some(240))?;
+    due("Review PRs", 0, Some(180))?;
+    let done_id = due("Already done", 0, Some(300))?;
+    agenda_db.complete_todo(done_id)?;
+    let tomorrow_id = due("Plan sprint", 1, Some(60))?;
+    due("Far off", 30, Some(600))?;
+    let days = agenda::agenda(&agenda_db.get_all_todos()?, &agenda_db.get_estimates()?, today, 14);
+    anyhow::ensure!(days.len() == 2 && days[0].date == today && days[1].todo_ids == vec![tomorrow_id], "agenda days wrong: {:?}", days);
+    anyhow::ensure!(days[0].todo_ids.len() == 3 && days[0].todo_ids[0] == late_id, "overdue todos should come first today");
+    anyhow::ensure!(days[0].estimated_minutes == 420 && days[0].unestimated == 1, "today's workload wrong: {:?}", days[0]);
+    anyhow::ensure!(days[0].overbooked_by(360) == Some(60) && days[1].overbooked_by(360).is_none(), "overbooking wrong");
+    agenda_db.set_estimate(tomorrow_id, None)?;
+    anyhow::ensure!(!agenda_db.get_estimates()?.contains_key(&tomorrow_id), "estimate not cleared");
+    anyhow::ensure!(agenda_db.set_estimate(9999, Some(30)).is_err(), "estimating a missing todo should fail");
+
+    let estimate_db = Database::new(":memory:")?;
+    let task_id = estimate_db.create_todo(NewTodo { title: "Task".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    estimate_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(estimate_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('E'), KeyModifiers::NONE)?;
+    for c in "1h15m".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.estimates.get(&task_id) == Some(&75), "E should set the estimate");
+    app.handle_key_event(KeyCode::Char('W'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::Agenda, "W should open the agenda");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::List, "Esc should close the agenda");
+    println!("Estimates and agenda OK");
+
+    // Test context and energy labels: cycling, filter terms and the one-key picker
+    println!("Testing contexts and energy...");
+    let names: Vec<String> = ["home", "computer"].map(String::from).to_vec();
+    anyhow::ensure!(context::cycle_context(None, &names).as_deref() == Some("home") && context::cycle_context(Some("home"), &names).as_deref() == Some("computer"), "contexts cycle in order");
+    anyhow::ensure!(context::cycle_context(Some("computer"), &names).is_none() && context::cycle_context(Some("gone"), &names).as_deref() == Some("home"), "contexts cycle back to none");
+    anyhow::ensure!(Energy::cycle(Some(Energy::High)).is_none() && Energy::cycle(None) == Some(Energy::Low), "energy cycles");
+    anyhow::ensure!(Filter::parse("@computer energy:low").map_err(anyhow::Error::msg)?.conditions.iter().map(|c| c.term.clone()).collect::<Vec<_>>()
+        == vec![filter::FilterTerm::Context(Some("computer".to_string())), filter::FilterTerm::Energy(Some(Energy::Low))], "context terms not parsed");
+    anyhow::ensure!(Filter::parse("energy:sleepy").is_err() && Filter::parse("context:a+b").is_err(), "bad labels accepted");
+
+    let context_db = Database::new(":memory:")?;
+    let inbox_id = context_db.create_todo(NewTodo { title: "Inbox zero".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let shop_id = context_db.create_todo(NewTodo { title: "Buy milk".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let refactor_id = context_db.create_todo(NewTodo { title: "Refactor parser".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    context_db.set_context(inbox_id, Some("computer"))?;
+    context_db.set_energy(inbox_id, Some(Energy::Low))?;
+    context_db.set_context(shop_id, Some("errands"))?;
+    context_db.set_context(refactor_id, Some("computer"))?;
+    context_db.set_energy(refactor_id, Some(Energy::High))?;
+    let ids = |db: &Database, expression: &str| -> anyhow::Result<Vec<i64>> {
+        Ok(db.query_todos(&Filter::parse(expression).map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect())
+    };
+    anyhow::ensure!(ids(&context_db, "context:computer energy:low")? == vec![inbox_id], "context and energy filter wrong");
+    anyhow::ensure!(ids(&context_db, "energy:none")? == vec![shop_id] && ids(&context_db, "-@computer")? == vec![shop_id], "unlabelled filter wrong");
+    context_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(context_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('C'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::ContextPicker, "C should open the context picker");
+    app.handle_key_event(KeyCode::Char('2'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('l'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.incomplete_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![inbox_id], "picker should narrow the list at once");
+    anyhow::ensure!(app.active_filter.as_ref().is_some_and(|(name, _)| name == "Now: @computer, low energy"), "picker filter name: {:?}", app.active_filter.as_ref().map(|(name, _)| name));
+    app.handle_key_event(KeyCode::Char('x'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.active_filter.is_none() && app.incomplete_todos.len() == 3 && app.mode == ui::AppMode::List, "x should clear the picker filter");
+    let before = app.labels.clone();
+    app.handle_key_event(KeyCode::Char('%'), KeyModifiers::NONE)?;
+    let changed: Vec<_> = app.labels.iter().filter(|(id, labels)| before.get(id) != Some(labels)).collect();
+    anyhow::ensure!(changed.len() == 1 && changed[0].1.energy == Energy::cycle(before.get(changed[0].0).and_then(|labels| labels.energy)), "% should cycle the selected todo's energy");
+    println!("Contexts and energy OK");
+
+    // Test someday/maybe: set aside with its subtasks, its own view, promoted back
+    println!("Testing someday/maybe...");
+    let someday_db = Database::new(":memory:")?;
+    let active_id = someday_db.create_todo(NewTodo { title: "Pay rent".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let idea_id = someday_db.create_todo(NewTodo { title: "Learn the cello".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let step_id = someday_db.create_todo(NewTodo { title: "Find a teacher".to_string(), description: String::new(), parent_id: Some(idea_id), due_by: None })?;
+    someday_db.set_someday(idea_id, true)?;
+    anyhow::ensure!(someday_db.get_someday_ids()? == HashSet::from([idea_id]), "someday not stored");
+    anyhow::ensure!(someday_db.query_todos(&Filter::parse("status:someday").map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![idea_id], "status:someday filter wrong");
+    let all = someday_db.get_all_todos()?;
+    anyhow::ensure!(filter::with_descendants(&all, &HashSet::from([idea_id])) == HashSet::from([idea_id, step_id]), "subtasks should be set aside too");
+    someday_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(someday_db, Config::default())?;
+    anyhow::ensure!(app.incomplete_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![active_id], "someday todos should leave the list");
+    anyhow::ensure!(app.tree_manager.get_line_index_for_todo(step_id).is_none() && app.tree_manager.get_line_index_for_todo(idea_id).is_none(), "someday todos should leave the tree");
+    app.handle_key_event(KeyCode::Char('L'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::Someday && app.someday_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![idea_id], "L should open the someday view");
+    app.handle_key_event(KeyCode::Char('p'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.someday_todos.is_empty() && app.incomplete_todos.len() == 3, "p should promote the todo");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('B'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.someday_todos.len() == 1 && app.incomplete_todos.len() < 3, "B should set the selected todo aside");
+    println!("Someday/maybe OK");
+
+    // Test waiting-for: delegated todos with a follow-up date, sorted by when to chase them
+    println!("Testing waiting-for...");
+    let parsed = waiting::parse_delegation("ACME support, 2030-06-03", "1w").map_err(anyhow::Error::msg)?;
+    anyhow::ensure!(parsed.on == "ACME support" && parsed.follow_up == crate::dates::parse_start_date("2030-06-03"), "delegation not parsed: {:?}", parsed);
+    anyhow::ensure!(waiting::parse_delegation(&waiting::format_delegation(&parsed), "").map_err(anyhow::Error::msg)? == parsed, "delegation does not round-trip");
+    anyhow::ensure!(waiting::parse_delegation("Alice", "").map_err(anyhow::Error::msg)?.follow_up.is_none(), "empty default should mean no follow-up");
+    anyhow::ensure!(waiting::parse_delegation("Alice", "1w").map_err(anyhow::Error::msg)?.follow_up.is_some(), "default follow-up not used");
+    anyhow::ensure!(waiting::parse_delegation("Alice, whenever", "").is_err() && waiting::parse_delegation(", 3d", "").is_err(), "invalid delegation accepted");
+    let waiting_db = Database::new(":memory:")?;
+    let quote_id = waiting_db.create_todo(NewTodo { title: "Get a quote".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let review_id = waiting_db.create_todo(NewTodo { title: "Review my PR".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let mine_id = waiting_db.create_todo(NewTodo { title: "Write the report".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    waiting_db.set_waiting(quote_id, Some(&parsed))?;
+    anyhow::ensure!(waiting_db.get_waiting()?.get(&quote_id) == Some(&parsed), "waiting not stored");
+    anyhow::ensure!(waiting_db.query_todos(&Filter::parse("waiting:acme").map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![quote_id], "waiting:name filter wrong");
+    waiting_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(waiting_db, Config::default())?;
+    let review_row = app.tree_manager.get_line_index_for_todo(review_id).ok_or_else(|| anyhow::anyhow!("todo missing from tree"))?;
+    app.tree_list_state.select(Some(review_row));
+    app.handle_key_event(KeyCode::Char('>'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::DelegatePrompt && app.delegate_input.is_empty(), "> should open the delegate prompt");
+    for c in "Bob, 2030-01-10".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.waiting.get(&review_id).is_some_and(|waiting| waiting.on == "Bob"), "> should delegate the selected todo");
+    anyhow::ensure!(app.database.query_todos(&Filter::parse("waiting").map_err(anyhow::Error::msg)?)?.len() == 2, "waiting filter wrong");
+    app.handle_key_event(KeyCode::Char('U'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::Waiting && app.waiting_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![review_id, quote_id], "U should open the waiting view, soonest follow-up first");
+    app.handle_key_event(KeyCode::Char('<'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.waiting_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![quote_id] && app.incomplete_todos.iter().any(|todo| todo.id == mine_id), "< should take the todo back");
+    println!("Waiting-for OK");
+
+    // Test statuses: stored beyond open/done, resolved against completion, cycled with Space
+    println!("Testing statuses...");
+    anyhow::ensure!(Status::parse("In Progress") == Some(Status::InProgress) && Status::parse("canceled") == Some(Status::Cancelled), "status names not parsed");
+    anyhow::ensure!(Status::resolve(false, Some(Status::Cancelled)) == Status::Open && Status::resolve(true, Some(Status::Blocked)) == Status::Done, "completion should win over a stale status");
+    let cycle = [Status::Open, Status::InProgress, Status::Done];
+    anyhow::ensure!(Status::cycle(Status::Done, &cycle, true) == Status::Open && Status::cycle(Status::Open, &cycle, false) == Status::Done
+        && Status::cycle(Status::Blocked, &cycle, true) == Status::Open, "status cycle wrong");
+    let status_db = Database::new(":memory:")?;
+    let build_id = status_db.create_todo(NewTodo { title: "Build the shed".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let trip_id = status_db.create_todo(NewTodo { title: "Plan the trip".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    status_db.set_status(trip_id, Status::Cancelled)?;
+    status_db.complete_todo(trip_id)?;
+    anyhow::ensure!(status_db.get_statuses()? == HashMap::from([(trip_id, Status::Cancelled)]), "status not stored");
+    anyhow::ensure!(status_db.query_todos(&Filter::parse("status:cancelled").map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![trip_id], "status:cancelled filter wrong");
+    for (expression, status) in [("status:incomplete", Status::Open), ("status:open", Status::Open), ("status:completed", Status::Done)] {
+        let terms: Vec<filter::FilterTerm> = Filter::parse(expression).map_err(anyhow::Error::msg)?.conditions.into_iter().map(|condition| condition.term).collect();
+        anyhow::ensure!(terms == [filter::FilterTerm::Status(status)], "{} parsed to {:?}", expression, terms);
+    }
+    anyhow::ensure!(Filter::parse("status:sideways").is_err(), "unknown status accepted");
+    anyhow::ensure!(toml::from_str::<Config>("[statuses]\ncycle = [\"open\", \"in_progress\", \"done\"]\n")?.statuses.cycle == cycle, "[statuses] cycle not read");
+    status_db.set_ui_state("onboarding_done", "1")?;
+    let mut config = Config::default();
+    config.statuses.cycle = cycle.to_vec();
+    let mut app = ui::App::new(status_db, config)?;
+    let build_row = app.tree_manager.get_line_index_for_todo(build_id).ok_or_else(|| anyhow::anyhow!("todo missing from tree"))?;
+    app.tree_list_state.select(Some(build_row));
+    app.handle_key_event(KeyCode::Char(' '), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_statuses()?.get(&build_id) == Some(&Status::InProgress) && app.incomplete_todos.iter().any(|todo| todo.id == build_id), "Space should start the todo");
+    anyhow::ensure!(app.tree_manager.rendered_lines[build_row].display_text.contains("[~]"), "in-progress icon not shown");
+    app.handle_key_event(KeyCode::Char(' '), KeyModifiers::SHIFT)?;
+    anyhow::ensure!(!app.database.get_statuses()?.contains_key(&build_id) && !app.database.get_todo_by_id(build_id)?.is_some_and(|todo| todo.is_completed()), "Shift+Space should step back to open");
+    println!("Statuses OK");
+
+    // Test cancelled todos: closed and still queryable, but not counted as done
+    println!("Testing cancelled todos...");
+    let cancel_db = Database::new(":memory:")?;
+    let project_id = cancel_db.create_todo(NewTodo { title: "Garden".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let dropped_id = cancel_db.create_todo(NewTodo { title: "Build a pond".to_string(), description: String::new(), parent_id: Some(project_id), due_by: None })?;
+    let planted_id = cancel_db.create_todo(NewTodo { title: "Plant tulips".to_string(), description: String::new(), parent_id: Some(project_id), due_by: None })?;
+    cancel_db.complete_todo(planted_id)?;
+    cancel_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(cancel_db, Config::default())?;
+    let dropped_row = app.tree_manager.get_line_index_for_todo(dropped_id).ok_or_else(|| anyhow::anyhow!("todo missing from tree"))?;
+    app.tree_list_state.select(Some(dropped_row));
+    app.handle_key_event(KeyCode::Char('X'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_status(dropped_id)? == Status::Cancelled && app.database.get_cancelled_ids()? == HashSet::from([dropped_id]), "X should cancel the todo");
+    let ids = |filter: &str| -> anyhow::Result<Vec<i64>> {
+        Ok(app.database.query_todos(&Filter::parse(filter).map_err(anyhow::Error::msg)?)?.iter().map(|todo| todo.id).collect())
+    };
+    anyhow::ensure!(ids("status:cancelled")? == vec![dropped_id] && ids("status:done")? == vec![planted_id], "cancelled should be queryable but not done");
+    let garden = app.tree_manager.project_stats(None, Utc::now());
+    anyhow::ensure!(garden == [(project_id, tree::ProjectStats { open: 1, overdue: 0, done_30d: 1 })], "cancelled todo counted in project stats: {:?}", garden);
+    let report = report::completed_report(&app.database.get_all_todos()?, &app.database.get_cancelled_ids()?, Utc::now() - chrono::Duration::days(1), ReportFormat::Text);
+    anyhow::ensure!(report.contains("Plant tulips") && !report.contains("Build a pond"), "cancelled todo in the report: {}", report);
+    app.handle_key_event(KeyCode::Char('c'), KeyModifiers::NONE)?;
+    let row = app.completed_todos.iter().position(|todo| todo.id == dropped_id).ok_or_else(|| anyhow::anyhow!("cancelled todo missing from the completed view"))?;
+    app.completed_list_state.select(Some(row));
+    app.handle_key_event(KeyCode::Char('X'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_status(dropped_id)? == Status::Done, "X in the completed view should mark it done instead");
+    // The status waits for the cascade question: Esc leaves the todo as it was
+    let ask_db = Database::new(":memory:")?;
+    let shed_id = ask_db.create_todo(NewTodo { title: "Shed".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let roof_id = ask_db.create_todo(NewTodo { title: "Roof".to_string(), description: String::new(), parent_id: Some(shed_id), due_by: None })?;
+    let gutter_id = ask_db.create_todo(NewTodo { title: "Gutter".to_string(), description: String::new(), parent_id: Some(shed_id), due_by: None })?;
+    ask_db.set_ui_state("onboarding_done", "1")?;
+    let mut config = Config::default();
+    config.parents.complete_children = CascadeRule::Ask;
+    let mut app = ui::App::new(ask_db, config)?;
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(shed_id));
+    app.handle_key_event(KeyCode::Char('X'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::ConfirmCascade && app.database.get_statuses()?.is_empty(), "status saved before the cascade was answered");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_statuses()?.is_empty() && app.database.get_cancelled_ids()?.is_empty(), "Esc left a cancelled status behind");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(shed_id));
+    app.handle_key_event(KeyCode::Char('X'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_cancelled_ids()? == HashSet::from([shed_id]), "answered cascade didn't cancel the todo");
+    // Reopening clears the status, and un-cancelling doesn't take back a done todo from the session
+    app.database.complete_todo(gutter_id)?;
+    app.database.uncomplete_todo(shed_id)?;
+    app.refresh_todos()?;
+    anyhow::ensure!(app.database.get_statuses()?.is_empty(), "reopened todo kept its cancelled status");
+    anyhow::ensure!(app.session.as_ref().is_some_and(|session| session.completed == 1), "un-cancelling counted as un-completing: {:?}", app.session);
+    // Subtasks closed with a cancelled parent are cancelled, not done
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(shed_id));
+    app.handle_key_event(KeyCode::Char('X'), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('y'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_cancelled_ids()? == HashSet::from([shed_id, roof_id]), "cascade didn't cancel the subtasks: {:?}", app.database.get_statuses()?);
+    anyhow::ensure!(app.session.as_ref().is_some_and(|session| session.completed == 1), "cancelled subtasks counted as done: {:?}", app.session);
+    println!("Cancelled todos OK");
+
+    // Test history: triggers record every change with its old and new value
+    println!("Testing history...");
+    let history_db = Database::new(":memory:")?;
+    let first_due = crate::dates::parse_start_date("2030-03-01").ok_or_else(|| anyhow::anyhow!("date not parsed"))?;
+    let second_due = crate::dates::parse_start_date("2030-03-05").ok_or_else(|| anyhow::anyhow!("date not parsed"))?;
+    let project_id = history_db.create_todo(NewTodo { title: "Taxes".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let form_id = history_db.create_todo(NewTodo { title: "File the form".to_string(), description: String::new(), parent_id: None, due_by: Some(first_due) })?;
+    history_db.update_due_by(form_id, Some(second_due))?;
+    history_db.move_todo(form_id, Some(project_id))?;
+    history_db.complete_todo(form_id)?;
+    history_db.set_estimate(form_id, Some(90))?;
+    let entries = history_db.get_history(Some(form_id), 50)?;
+    let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+    anyhow::ensure!(actions == ["update", "complete", "move", "update", "create"], "history actions wrong: {:?}", actions);
+    let due_change = history::describe(&entries[3]);
+    anyhow::ensure!(due_change == "Due: 2030-03-01 00:00 → 2030-03-05 00:00", "due change described as {}", due_change);
+    anyhow::ensure!(history::describe(&entries[0]) == "Estimate: none → 1h30m" && history::describe(&entries[2]) == format!("Moved from the top level to #{}", project_id), "history descriptions wrong");
+    history_db.uncomplete_todo(form_id)?;
+    history_db.move_todo(form_id, None)?;
+    history_db.delete_todo(form_id)?;
+    let deleted = history_db.get_history(Some(form_id), 1)?;
+    anyhow::ensure!(deleted.len() == 1 && deleted[0].action == "delete" && deleted[0].title == "File the form", "deletion not recorded");
+    anyhow::ensure!(history_db.get_history(None, 100)?.iter().any(|entry| entry.todo_id == project_id), "activity feed should cover every todo");
+    anyhow::ensure!(history_db.get_history(Some(project_id), 10)?.len() == 1, "history should be per todo");
+    history_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(history_db, Config::default())?;
+    app.tree_list_state.select(Some(0));
+    app.handle_key_event(KeyCode::Char('V'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::History && app.history_todo == Some(project_id) && app.history_entries.len() == 1, "V should open the selected todo's history");
+    app.handle_key_event(KeyCode::Char('a'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.history_todo.is_none() && app.history_entries.len() > 1, "a should show all activity");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::List, "Esc should close the history");
+    println!("History OK");
+
+    // Test merge: two copies edited apart are merged against their shared history
+    println!("Testing merge...");
+    let merge_path = std::env::temp_dir().join(format!("tododb_merge_test_{}.db", std::process::id()));
+    let copy_path = std::env::temp_dir().join(format!("tododb_merge_copy_test_{}.db", std::process::id()));
+    let here_db = Database::new(&merge_path.to_string_lossy())?;
+    let new_todo = |title: &str, parent_id| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: None };
+    let groceries = here_db.create_todo(new_todo("Groceries", None))?;
+    let plumber = here_db.create_todo(new_todo("Call plumber", None))?;
+    let old_idea = here_db.create_todo(new_todo("Old idea", None))?;
+    let report_id = here_db.create_todo(new_todo("Quarterly report", None))?;
+    here_db.checkpoint()?;
+    std::fs::copy(&merge_path, &copy_path)?;
+    let there_db = Database::new(&copy_path.to_string_lossy())?;
+    here_db.update_title(groceries, "Groceries for the week")?;
+    here_db.update_title(old_idea, "Old idea, revisited")?;
+    here_db.set_estimate(report_id, Some(60))?;
+    there_db.update_title(groceries, "Weekly groceries")?;
+    there_db.complete_todo(plumber)?;
+    there_db.delete_todo(old_idea)?;
+    there_db.update_due_by(report_id, crate::dates::parse_start_date("2030-04-01"))?;
+    there_db.create_todo(new_todo("Collect figures", Some(report_id)))?;
+    let plan = merge::plan(&here_db, &there_db)?;
+    let mut conflicts: Vec<&merge::ConflictKind> = plan.conflicts.iter().map(|conflict| &conflict.kind).collect();
+    conflicts.sort_by_key(|kind| matches!(kind, merge::ConflictKind::DeletedThere));
+    anyhow::ensure!(
+        matches!(conflicts.as_slice(), [merge::ConflictKind::Field { field: "title", .. }, merge::ConflictKind::DeletedThere]),
+        "expected a title and a delete conflict: {:?}",
+        plan.conflicts
+    );
+    anyhow::ensure!(plan.changes.len() == 3, "one-sided changes not all taken: {:?}", plan.changes);
+    here_db.apply_merge(&plan.resolve(|_| true))?;
+    anyhow::ensure!(here_db.get_todo_by_id(groceries)?.is_some_and(|todo| todo.title == "Weekly groceries"), "conflict not resolved to the other side");
+    anyhow::ensure!(here_db.get_todo_by_id(plumber)?.is_some_and(|todo| todo.is_completed()), "completion made elsewhere not merged");
+    anyhow::ensure!(here_db.get_todo_by_id(old_idea)?.is_none(), "deletion made elsewhere not merged");
+    let merged_report = here_db.get_todo_by_id(report_id)?.ok_or_else(|| anyhow::anyhow!("report todo gone"))?;
+    anyhow::ensure!(merged_report.due_by.is_some() && here_db.get_estimates()?.get(&report_id) == Some(&60), "edits to different fields should both survive");
+    anyhow::ensure!(here_db.get_all_todos()?.iter().any(|todo| todo.title == "Collect figures" && todo.parent_id == Some(report_id)), "todo created elsewhere not merged under its parent");
+    let again = merge::plan(&here_db, &there_db)?;
+    anyhow::ensure!(again.changes.is_empty() && again.conflicts.is_empty(), "merging twice should change nothing: {:?}", again);
+    drop(here_db);
+    drop(there_db);
+    for path in [&merge_path, &copy_path] {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+    println!("Merge OK");
+
+    // Test selection stability: a todo leaving the tree hands the cursor to a sibling, not to whatever row moved up
+    println!("Testing selection stability...");
+    let selection_db = Database::new(":memory:")?;
+    let project_id = selection_db.create_todo(new_todo("Move house", None))?;
+    for title in ["Pack boxes", "Book a van", "Hand in keys"] {
+        selection_db.create_todo(new_todo(title, Some(project_id)))?;
+    }
+    selection_db.create_todo(new_todo("Unrelated errand", None))?;
+    selection_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(selection_db, Config::default())?;
+    let selected_id = |app: &ui::App| app.tree_list_state.selected().and_then(|row| Some(app.tree_manager.get_rendered_lines().get(row)?.todo_id));
+    let children = app.tree_manager.get_rendered_lines().iter().map(|line| line.todo_id).filter(|id| app.tree_manager.get_todo_by_id(*id).is_some_and(|todo| todo.parent_id == Some(project_id))).collect::<Vec<_>>();
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(children[1]));
+    app.handle_key_event(KeyCode::Char('h'), KeyModifiers::NONE)?;
+    anyhow::ensure!(selected_id(&app) == Some(children[2]), "hiding a todo should select the next sibling, got {:?}", selected_id(&app));
+    app.handle_key_event(KeyCode::Char('h'), KeyModifiers::NONE)?;
+    anyhow::ensure!(selected_id(&app) == Some(children[0]), "hiding the last sibling should select the one before it");
+    app.handle_key_event(KeyCode::Char('h'), KeyModifiers::NONE)?;
+    anyhow::ensure!(selected_id(&app) == Some(project_id), "hiding the only child left should select its parent");
+    println!("Selection stability OK");
+
+    // Test quick due filters: 1/2/3 prune the tree to matches and their ancestors
+    println!("Testing quick due filters...");
+    let quick_db = Database::new(":memory:")?;
+    let due = |title: &str, parent_id, due_by| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: Some(due_by) };
+    let trip = quick_db.create_todo(new_todo("Plan trip", None))?;
+    let today_id = quick_db.create_todo(due("Renew passport", Some(trip), crate::dates::local_midnight_in(1) - chrono::Duration::minutes(1)))?;
+    let week_id = quick_db.create_todo(due("Book hotel", Some(trip), crate::dates::local_midnight_in(5)))?;
+    let overdue_id = quick_db.create_todo(due("Ask for time off", Some(trip), Utc::now() - chrono::Duration::days(1)))?;
+    let later_id = quick_db.create_todo(due("Renew lease", None, Utc::now() + chrono::Duration::days(30)))?;
+    anyhow::ensure!(Filter::parse("due:today due:week").is_ok() && Filter::parse("due:month").is_err(), "due:today / due:week not parsed");
+    quick_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(quick_db, Config::default())?;
+    app.tree_manager.toggle_expansion(trip);
+    let shown = |app: &ui::App| app.tree_manager.get_rendered_lines().iter().map(|line| line.todo_id).collect::<HashSet<i64>>();
+    app.handle_key_event(KeyCode::Char('1'), KeyModifiers::NONE)?;
+    anyhow::ensure!(shown(&app) == HashSet::from([trip, today_id, overdue_id]), "1 should show today's and overdue todos under their parent: {:?}", shown(&app));
+    anyhow::ensure!(app.filter_context == HashSet::from([trip]), "the parent is only context");
+    app.handle_key_event(KeyCode::Char('2'), KeyModifiers::NONE)?;
+    anyhow::ensure!(shown(&app) == HashSet::from([trip, today_id, week_id, overdue_id]), "2 should add the rest of the week");
+    app.handle_key_event(KeyCode::Char('3'), KeyModifiers::NONE)?;
+    anyhow::ensure!(shown(&app) == HashSet::from([trip, overdue_id]), "3 should show only overdue todos");
+    app.handle_key_event(KeyCode::Char('3'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.active_filter.is_none() && shown(&app) == HashSet::from([trip, later_id]), "3 again should clear the filter and fold the branch back");
+    println!("Quick due filters OK");
+
+    // Test completed view filters: a date range and one project's subtree, both in SQL
+    println!("Testing completed view filters...");
+    let done_db = Database::new(":memory:")?;
+    let now = Utc::now();
+    let website = done_db.create_todo(new_todo("Website", None))?;
+    let garden = done_db.create_todo(new_todo("Garden", None))?;
+    let header_id = done_db.create_imported_todo(new_todo("Fix header", Some(website)), now - chrono::Duration::days(60), Some(now))?;
+    done_db.create_imported_todo(new_todo("Old redesign", Some(website)), now - chrono::Duration::days(100), Some(now - chrono::Duration::days(40)))?;
+    done_db.create_imported_todo(new_todo("Mow lawn", Some(garden)), now - chrono::Duration::days(2), Some(now))?;
+    let recent_website = CompletedFilter { since: Some(now - chrono::Duration::days(1)), root: Some(website) };
+    anyhow::ensure!(done_db.count_completed_todos(&recent_website)? == 1, "date range and project should both apply");
+    anyhow::ensure!(done_db.get_completed_todos(&CompletedFilter { since: None, root: Some(website) }, 0, 10)?.len() == 2, "project filter should cover its subtree");
+    done_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(done_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('c'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::CompletedView && app.completed_total == 3, "completed view should start with everything");
+    app.handle_key_event(KeyCode::Char('t'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.completed_range == report::CompletedRange::Today && app.completed_total == 2, "t should narrow to today: {}", app.completed_total);
+    let row = app.completed_todos.iter().position(|todo| todo.id == header_id).ok_or_else(|| anyhow::anyhow!("today's completion missing"))?;
+    app.completed_list_state.select(Some(row));
+    app.handle_key_event(KeyCode::Char('p'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.completed_root.as_ref().is_some_and(|(id, _)| *id == website) && app.completed_total == 1, "p should narrow to the selected todo's project");
+    for _ in 0..3 {
+        app.handle_key_event(KeyCode::Char('t'), KeyModifiers::NONE)?;
+    }
+    anyhow::ensure!(app.completed_range == report::CompletedRange::All && app.completed_total == 2, "t should cycle back to all time");
+    app.handle_key_event(KeyCode::Char('p'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.completed_root.is_none() && app.completed_total == 3, "p again should show every project");
+    let monday = report::CompletedRange::Week.since(chrono::Local::now(), chrono::Weekday::Mon).ok_or_else(|| anyhow::anyhow!("no week start"))?;
+    anyhow::ensure!(chrono::Datelike::weekday(&monday.with_timezone(&chrono::Local)) == chrono::Weekday::Mon, "weeks should start on Monday");
+    println!("Completed view filters OK");
+
+    // Test day groups: the completed view has a header for each local day
+    println!("Testing completed day groups...");
+    let today = chrono::Local::now().date_naive();
+    anyhow::ensure!(report::day_label(today, today) == "Today" && report::day_label(today - chrono::Duration::days(1), today) == "Yesterday", "recent days should be named");
+    let old_day = today - chrono::Duration::days(5);
+    anyhow::ensure!(report::day_label(old_day, today) == old_day.format("%Y-%m-%d").to_string(), "older days should show the date");
+    let days = report::completed_days(&app.completed_todos);
+    let expected_days: Vec<_> = app.completed_todos.iter().map(|todo| todo.completed_at.unwrap_or(todo.created_at).with_timezone(&chrono::Local).date_naive()).collect();
+    anyhow::ensure!(days.len() == 2 && days[0] == (expected_days[0], 0) && days[1] == (expected_days[2], 2), "two completions today and one older: {:?}", days);
+    let backend = ratatui::backend::TestBackend::new(80, 9);
+    let mut terminal = ratatui::Terminal::new(backend)?;
+    app.completed_list_state.select(Some(2));
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("Old redesign") && !screen.contains("Fix header"), "the older completion should be scrolled into view");
+    anyhow::ensure!(screen.contains(&expected_days[2].format("%Y-%m-%d").to_string()), "the older day's header should show");
+    anyhow::ensure!(screen.contains("Today  2 done"), "today's header should stay pinned above its scrolled todos");
+    println!("Completed day groups OK");
+
+    // Test flags: a color overrides the due-date color, an emoji goes in front of the title
+    println!("Testing flags...");
+    anyhow::ensure!(Flag::parse("Red") == Ok(Some(Flag::Color("red"))) && Flag::parse(" 🔥 ") == Ok(Some(Flag::Emoji("🔥".to_string())))
+        && Flag::parse("⚠️") == Ok(Some(Flag::Emoji("⚠️".to_string()))), "flags not parsed");
+    anyhow::ensure!(Flag::parse("").is_ok_and(|flag| flag.is_none()) && Flag::parse("urgent").is_err(), "empty should clear and words should be rejected");
+    let flag_db = Database::new(":memory:")?;
+    let overdue = flag_db.create_todo(NewTodo { title: "Renew passport".to_string(), description: String::new(), parent_id: None, due_by: Some(Utc::now() - chrono::Duration::days(2)) })?;
+    let taxes = flag_db.create_todo(NewTodo { title: "File taxes".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    flag_db.set_flag(taxes, Some(&Flag::Emoji("🔥".to_string())))?;
+    anyhow::ensure!(flag_db.get_history(Some(taxes), 10)?.iter().any(|entry| entry.field.as_deref() == Some("flag")), "flag change not in the history");
+    flag_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(flag_db, Config::default())?;
+    let taxes_row = app.tree_manager.get_line_index_for_todo(taxes).ok_or_else(|| anyhow::anyhow!("todo missing from tree"))?;
+    anyhow::ensure!(app.tree_manager.rendered_lines[taxes_row].display_text.contains("🔥 File taxes"), "emoji flag not shown in the tree");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(overdue));
+    app.handle_key_event(KeyCode::Char('!'), KeyModifiers::NONE)?;
+    for c in "blue".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.database.get_flags()?.get(&overdue) == Some(&Flag::Color("blue")), "! should flag the selected todo");
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 12))?;
+    terminal.draw(|f| app.draw(f))?;
+    let buffer = terminal.backend().buffer();
+    let title_cell = (0..buffer.area.height).flat_map(|y| (0..buffer.area.width).map(move |x| (x, y)))
+        .find(|&(x, y)| x + 5 <= buffer.area.width && (0..5).map(|i| buffer[(x + i, y)].symbol()).collect::<String>() == "Renew")
+        .ok_or_else(|| anyhow::anyhow!("flagged todo not drawn"))?;
+    anyhow::ensure!(buffer[title_cell].fg == crate::colors::CatppuccinFrappe::BLUE, "a color flag should override the overdue color");
+    app.handle_key_event(KeyCode::Char('!'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.quick_edit_input == "blue", "the prompt should start with the current flag");
+    app.quick_edit_input.clear();
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(!app.database.get_flags()?.contains_key(&overdue), "an empty prompt should clear the flag");
+    println!("Flags OK");
+
+    // Test pinned todos: open pinned todos stay in sight above the tree
+    println!("Testing pinned todos...");
+    let pin_db = Database::new(":memory:")?;
+    let project = pin_db.create_todo(NewTodo { title: "Launch".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let slides = pin_db.create_todo(NewTodo { title: "Write the slides".to_string(), description: String::new(), parent_id: Some(project), due_by: None })?;
+    pin_db.create_todo(NewTodo { title: "Book the room".to_string(), description: String::new(), parent_id: Some(project), due_by: None })?;
+    pin_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(pin_db, Config::default())?;
+    app.tree_manager.expand_paths_to(&[slides]);
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(slides));
+    app.handle_key_event(KeyCode::Char('b'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.pinned_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![slides], "b should pin the selected todo");
+    anyhow::ensure!(app.database.get_history(Some(slides), 10)?.iter().any(|entry| entry.field.as_deref() == Some("pinned")), "pinning not in the history");
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 14))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: Vec<String> = (0..14).map(|y| (0..80).map(|x| terminal.backend().buffer()[(x, y)].symbol()).collect()).collect();
+    let pinned_row = screen.iter().position(|line| line.contains("Write the slides  (Launch)")).ok_or_else(|| anyhow::anyhow!("pinned section not drawn"))?;
+    let tree_row = screen.iter().position(|line| line.contains("Book the room")).ok_or_else(|| anyhow::anyhow!("tree not drawn"))?;
+    anyhow::ensure!(pinned_row < tree_row, "the pinned section should sit above the tree");
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(slides));
+    app.handle_key_event(KeyCode::Char(' '), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.pinned_todos.is_empty() && app.database.get_pinned_ids()?.contains(&slides), "completed todos should leave the section but stay pinned");
+    println!("Pinned todos OK");
+
+    // Test the daily plan: today's picks, and yesterday's unfinished ones offered again
+    println!("Testing daily plan...");
+    let plan_db = Database::new(":memory:")?;
+    let today = chrono::Local::now().date_naive();
+    let yesterday = today - chrono::Duration::days(1);
+    let call = plan_db.create_todo(NewTodo { title: "Call the bank".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let report_id = plan_db.create_todo(NewTodo { title: "Send the report".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let gym = plan_db.create_todo(NewTodo { title: "Go to the gym".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    plan_db.add_to_daily_plan(yesterday, &[call, report_id, call])?;
+    plan_db.complete_todo(report_id)?;
+    anyhow::ensure!(plan_db.get_daily_plan(yesterday)?.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![call, report_id], "plan should keep the order added, once each");
+    anyhow::ensure!(plan_db.last_daily_plan_before(today)? == Some(yesterday), "yesterday's plan not found");
+    plan_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(plan_db, Config::default())?;
+    anyhow::ensure!(app.carry_over.as_ref().is_some_and(|(day, todos)| *day == yesterday && todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![call]),
+        "only yesterday's unfinished todo should be offered");
+    app.handle_key_event(KeyCode::Char('='), KeyModifiers::NONE)?;
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 20))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(app.mode == ui::AppMode::Today && screen.contains("Carry Over") && screen.contains("Call the bank"), "the carry-over offer should show in the Today view");
+    app.handle_key_event(KeyCode::Char('y'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.carry_over.is_none() && app.today_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![call], "y should carry the todo over");
+    app.handle_key_event(KeyCode::Char(' '), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.today_todos.first().is_some_and(|todo| todo.id == call && todo.is_completed()), "a done todo should stay in today's plan");
+    app.handle_key_event(KeyCode::Char('='), KeyModifiers::NONE)?;
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(gym));
+    app.handle_key_event(KeyCode::Char('+'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.today_todos.iter().map(|todo| todo.id).collect::<Vec<_>>() == vec![call, gym], "+ should add the selected todo");
+    app.handle_key_event(KeyCode::Char('+'), KeyModifiers::NONE)?;
+    anyhow::ensure!(app.today_todos.len() == 1, "+ again should take it out");
+    app.refresh_todos()?;
+    anyhow::ensure!(app.carry_over.is_none(), "the offer should not come back once answered");
+    app.database.delete_todo(call)?;
+    anyhow::ensure!(app.database.get_daily_plan(yesterday)?.iter().all(|todo| todo.id != call), "deleted todos should leave the plans");
+    println!("Daily plan OK");
+
+    // Test quick add: Ctrl+N adds a todo under a searched parent and returns to the view
+    println!("Testing quick add...");
+    let quick_db = Database::new(":memory:")?;
+    let website = quick_db.create_todo(NewTodo { title: "Website relaunch".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let web = quick_db.create_todo(NewTodo { title: "Web".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let old_web = quick_db.create_todo(NewTodo { title: "web".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    quick_db.complete_todo(old_web)?;
+    let captured = capture::parse_capture_line("Fix the footer due:2d !p1 #ui >web relaunch", crate::dates::END_OF_DAY)?;
+    anyhow::ensure!(captured.title == "p1 Fix the footer #ui" && captured.due_by.is_some() && captured.parent_search.as_deref() == Some("web relaunch"),
+        "quick add line parsed wrong: {:?}", captured);
+    anyhow::ensure!(capture::split_parent_search("a > b") == ("a > b", None) && capture::split_parent_search("x>y") == ("x>y", None), "> inside a word or alone is part of the title");
+    let todos = quick_db.get_all_todos()?;
+    let found = |search| capture::find_parent(&todos, search).map(|todo| todo.id);
+    anyhow::ensure!(found("WEB") == Some(web) && found("webs") == Some(website) && found("launch") == Some(website) && found("nothing").is_none(),
+        "parent search should prefer exact, open, then prefix matches");
+    quick_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(quick_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('='), KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::CONTROL)?;
+    anyhow::ensure!(app.mode == ui::AppMode::QuickAdd, "Ctrl+N should open quick add");
+    app.handle_paste("Call the printer >relaunch")?;
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 20))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("Quick add under 'Website relaunch'"), "the overlay should show the parent it will use");
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    let added = app.database.get_all_todos()?.into_iter().find(|todo| todo.title == "Call the printer");
+    anyhow::ensure!(added.is_some_and(|todo| todo.parent_id == Some(website)) && app.mode == ui::AppMode::Today, "quick add should file under the match and stay in the view");
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::CONTROL)?;
+    for c in "Orphan >no such project".chars() {
+        app.handle_key_event(KeyCode::Char(c), KeyModifiers::NONE)?;
+    }
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::QuickAdd && app.error_message.is_some(), "an unmatched search should keep the overlay open");
+    app.handle_key_event(KeyCode::Esc, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.mode == ui::AppMode::Today && app.database.get_all_todos()?.iter().all(|todo| todo.title != "Orphan"), "Esc should cancel quick add");
+    println!("Quick add OK");
+
+    // Test the Create form's cursor: fixes mid-title, a multi-line description, Ctrl+S to save
+    println!("Testing create form editing...");
+    anyhow::ensure!(crate::text::previous_boundary("a👨‍👩‍👧", "a👨‍👩‍👧".len()) == 1 && crate::text::next_boundary("a👨‍👩‍👧", 1) == "a👨‍👩‍👧".len(), "cursor should step over a whole emoji");
+    anyhow::ensure!(crate::text::vertical_move("abcd\nxy\nlonger", 3, true) == 7 && crate::text::vertical_move("abcd\nxy\nlonger", 7, true) == 10
+        && crate::text::vertical_move("abcd\nxy", 6, false) == 1 && crate::text::vertical_move("abcd", 2, false) == 2, "up/down should keep the column where the line allows");
+    let form_db = Database::new(":memory:")?;
+    form_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(form_db, Config::default())?;
+    app.handle_key_event(KeyCode::Char('n'), KeyModifiers::NONE)?;
+    type_keys(&mut app, "Buy milkx!")?;
+    for key in [KeyCode::Home, KeyCode::Right, KeyCode::Right, KeyCode::Right, KeyCode::Right] {
+        app.handle_key_event(key, KeyModifiers::NONE)?;
+    }
+    type_keys(&mut app, "oat ")?;
+    app.handle_key_event(KeyCode::End, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Left, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Backspace, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::Delete, KeyModifiers::NONE)?;
+    anyhow::ensure!(app.input_title == "Buy oat milk", "title edited at the cursor: {}", app.input_title);
+    for _ in 0..4 {
+        app.handle_key_event(KeyCode::Tab, KeyModifiers::NONE)?;
+    }
+    type_keys(&mut app, "2 litres")?;
+    app.handle_key_event(KeyCode::Enter, KeyModifiers::NONE)?;
+    type_keys(&mut app, "organic")?;
+    app.handle_key_event(KeyCode::Up, KeyModifiers::NONE)?;
+    app.handle_key_event(KeyCode::End, KeyModifiers::NONE)?;
+    type_keys(&mut app, "!")?;
+    anyhow::ensure!(app.mode == ui::AppMode::Create && app.input_description == "2 litres!\norganic", "Enter in the description should start a line: {:?}", app.input_description);
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 20))?;
+    terminal.draw(|f| app.draw(f))?;
+    let cursor = terminal.get_cursor_position()?;
+    anyhow::ensure!(app.create_cursor == "2 litres!".len() && cursor.x == 10, "the cursor should be drawn after the edit: {:?}", cursor);
+    app.handle_key_event(KeyCode::Char('s'), KeyModifiers::CONTROL)?;
+    let saved = app.database.get_all_todos()?;
+    anyhow::ensure!(app.mode == ui::AppMode::List && saved.len() == 1 && saved[0].title == "Buy oat milk" && saved[0].description == "2 litres!\norganic", "Ctrl+S should save the form");
+    println!("Create form editing OK");
+
+    // Test due dates: a typed day is a local day, and times around DST changes are kept
+    println!("Testing due date time zones...");
+    /// Sydney in 2025: UTC+11 until clocks go back at 03:00 on April 6, UTC+10 until
+    /// they go forward at 02:00 on October 5
+    #[derive(Debug, Clone, Copy)]
+    struct Sydney;
+    impl chrono::TimeZone for Sydney {
+        type Offset = chrono::FixedOffset;
+        fn from_offset(_: &chrono::FixedOffset) -> Self {
+            Sydney
+        }
+        fn offset_from_local_date(&self, local: &chrono::NaiveDate) -> chrono::LocalResult<chrono::FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(chrono::NaiveTime::MIN))
+        }
+        fn offset_from_local_datetime(&self, local: &chrono::NaiveDateTime) -> chrono::LocalResult<chrono::FixedOffset> {
+            let offsets: Vec<chrono::FixedOffset> = [11, 10]
+                .into_iter()
+                .filter_map(|hours| chrono::FixedOffset::east_opt(hours * 3600))
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match offsets[..] {
+                [offset] => chrono::LocalResult::Single(offset),
+                [first, second] => chrono::LocalResult::Ambiguous(first, second),
+                _ => chrono::LocalResult::None,
+            }
+        }
+        fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> chrono::FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(chrono::NaiveTime::MIN))
+        }
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> chrono::FixedOffset {
+            let at = |text| chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap_or_default();
+            let summer = *utc < at("2025-04-05 16:00") || *utc >= at("2025-10-04 16:00");
+            chrono::FixedOffset::east_opt(if summer { 11 } else { 10 } * 3600).unwrap_or_else(|| chrono::Offset::fix(&Utc))
+        }
+    }
+    let utc = |text: &str| chrono::DateTime::parse_from_rfc3339(text).map(|at| at.with_timezone(&Utc)).ok();
+    let due = |input: &str, due_time: &str| crate::dates::parse_due_date_in(input, due_time.parse().unwrap_or(crate::dates::END_OF_DAY), &Sydney);
+    anyhow::ensure!(due("2025-03-01", "23:59:59") == utc("2025-03-01T12:59:59Z"), "a typed day should end on the local day, not in UTC: {:?}", due("2025-03-01", "23:59:59"));
+    anyhow::ensure!(due("2025-03-01", "23:59:59").map(|at| at.with_timezone(&Sydney).date_naive()) == chrono::NaiveDate::from_ymd_opt(2025, 3, 1), "due day shifted");
+    anyhow::ensure!(due("2025-10-04", "17:00") == utc("2025-10-04T07:00:00Z") && due("2025-10-05", "17:00") == utc("2025-10-05T06:00:00Z"),
+        "the due time should stay 17:00 local either side of the clocks going forward");
+    anyhow::ensure!(due("2025-10-05 02:30", "") == utc("2025-10-04T16:30:00Z"), "a time skipped by DST should move on an hour: {:?}", due("2025-10-05 02:30", ""));
+    anyhow::ensure!(due("2025-04-06 02:30", "") == utc("2025-04-05T15:30:00Z"), "a repeated time should be the first of the two");
+    anyhow::ensure!(due("2025-04-06", "") == utc("2025-04-06T13:59:59Z"), "the day the clocks go back ends at its own midnight");
+    anyhow::ensure!(crate::dates::parse_due_date_at("2030-06-03", "09:30".parse()?).map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()).as_deref() == Some("2030-06-03 09:30"),
+        "due time not applied in local time");
+    let config: Config = toml::from_str("[dates]\ndue_time = \"17:00\"\n")?;
+    anyhow::ensure!(config.dates.due_time() == "17:00".parse::<chrono::NaiveTime>()? && Config::default().dates.due_time() == crate::dates::END_OF_DAY, "[dates] due_time not read");
+    anyhow::ensure!(toml::from_str::<Config>("[dates]\ndue_time = \"teatime\"\n").is_err(), "invalid due time accepted");
+    let captured = capture::parse_capture_line("Pay rent due:2030-06-03", config.dates.due_time())?;
+    anyhow::ensure!(captured.due_by.map(|at| at.with_timezone(&Local).format("%H:%M").to_string()).as_deref() == Some("17:00"), "capture should use the due time");
+    println!("Due date time zones OK");
+
+    // Test relative due dates: rows say how far off a due date is, the bottom bar says when
+    println!("Testing relative due dates...");
+    let now = Utc::now();
+    let relative = |offset: chrono::Duration, compact| crate::dates::format_relative_due(now + offset, now, compact);
+    anyhow::ensure!(relative(chrono::Duration::hours(75), false) == "in 3d" && relative(-chrono::Duration::hours(49), false) == "2d overdue"
+        && relative(chrono::Duration::minutes(90), false) == "in 1h" && relative(chrono::Duration::minutes(-20), false) == "20m overdue"
+        && relative(chrono::Duration::seconds(30), false) == "due now" && relative(chrono::Duration::days(30), false) == "in 4w"
+        && relative(-chrono::Duration::days(800), false) == "2y overdue", "relative due text wrong");
+    anyhow::ensure!(relative(chrono::Duration::hours(75), true) == "3d" && relative(-chrono::Duration::hours(49), true) == "-2d", "compact relative due text wrong");
+    let due_db = Database::new(":memory:")?;
+    let report_due = due_db.create_todo(NewTodo { title: "File the report".to_string(), description: String::new(), parent_id: None, due_by: Some(now + chrono::Duration::hours(75)) })?;
+    due_db.set_ui_state("onboarding_done", "1")?;
+    let mut app = ui::App::new(due_db, Config::default())?;
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(report_due));
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 12))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    let exact = (now + chrono::Duration::hours(75)).with_timezone(&Local).format("%a %Y-%m-%d %H:%M").to_string();
+    anyhow::ensure!(screen.contains("Due in 3d") && screen.contains(&format!("File the report  due {}", exact)), "rows should show the relative due date and the bottom bar the exact one");
+    app.config.display.relative_due = false;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    let absolute = (now + chrono::Duration::hours(75)).with_timezone(&Local).format("%m/%d %H:%M").to_string();
+    anyhow::ensure!(screen.contains(&format!("Due {}", absolute)) && !screen.contains("in 3d"), "relative_due = false should show the date");
+    anyhow::ensure!(!toml::from_str::<Config>("[display]\nrelative_due = false\n")?.display.relative_due && Config::default().display.relative_due, "[display] relative_due not read");
+    println!("Relative due dates OK");
+
+    // Test date formats: rows use [dates] date_format and clock, weeks start on week_start
+    println!("Testing date formats...");
+    let config: Config = toml::from_str("[dates]\ndate_format = \"%d/%m\"\nclock = \"12h\"\nweek_start = \"sunday\"\n")?;
+    anyhow::ensure!(config.dates.row_format(false) == "%d/%m %-I:%M%P" && config.dates.row_format(true) == "%d/%m", "row format wrong: {}", config.dates.row_format(false));
+    anyhow::ensure!(config.dates.week_start == chrono::Weekday::Sun && Config::default().dates.week_start == chrono::Weekday::Mon, "[dates] week_start not read");
+    anyhow::ensure!(Config::default().dates.row_format(false) == "%m/%d %H:%M", "default row format changed");
+    anyhow::ensure!(toml::from_str::<Config>("[dates]\ndate_format = \"%Q\"\n").is_err() && toml::from_str::<Config>("[dates]\ndate_format = \"%d/%m %z\"\n").is_err()
+        && toml::from_str::<Config>("[dates]\ndate_format = \"%Z\"\n").is_err() && toml::from_str::<Config>("[dates]\nclock = \"13h\"\n").is_err(), "invalid date settings accepted");
+    anyhow::ensure!(crate::dates::format_width("%m/%d %H:%M") == 11 && crate::dates::format_width("%d %B") == 12 && crate::dates::format_width("%-I:%M%P") == 7, "date format widths wrong");
+    let wednesday = chrono::NaiveDate::from_ymd_opt(2030, 6, 5).ok_or_else(|| anyhow::anyhow!("bad date"))?;
+    anyhow::ensure!(metrics::week_start(wednesday, chrono::Weekday::Sun).to_string() == "2030-06-02" && metrics::week_start(wednesday, chrono::Weekday::Mon).to_string() == "2030-06-03", "week start wrong");
+    let sunday = report::CompletedRange::Week.since(chrono::Local::now(), chrono::Weekday::Sun).ok_or_else(|| anyhow::anyhow!("no week start"))?;
+    anyhow::ensure!(chrono::Datelike::weekday(&sunday.with_timezone(&Local)) == chrono::Weekday::Sun, "completed week should start on week_start");
+    let format_db = Database::new(":memory:")?;
+    let due_at = chrono::TimeZone::with_ymd_and_hms(&Local, 2030, 6, 5, 15, 30, 0).single().ok_or_else(|| anyhow::anyhow!("bad time"))?.with_timezone(&Utc);
+    let renew = format_db.create_todo(NewTodo { title: "Renew lease".to_string(), description: String::new(), parent_id: None, due_by: Some(due_at) })?;
+    format_db.set_ui_state("onboarding_done", "1")?;
+    let mut config = config;
+    config.display.relative_due = false;
+    let mut app = ui::App::new(format_db, config)?;
+    app.tree_list_state.select(app.tree_manager.get_line_index_for_todo(renew));
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 12))?;
+    terminal.draw(|f| app.draw(f))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    anyhow::ensure!(screen.contains("Due 05/06 3:30pm"), "rows should use the configured date format");
+    println!("Date formats OK");
+
+    // Test start dates: a deferred todo and its subtasks wait until they start
+    println!("Testing start dates...");
+    let start_db = Database::new(":memory:")?;
+    let new_todo = |title: &str, parent_id| NewTodo { title: title.to_string(), description: String::new(), parent_id, due_by: None };
+    let someday = start_db.create_todo(new_todo("Learn the cello", None))?;
+    let lesson = start_db.create_todo(new_todo("Book a lesson", Some(someday)))?;
+    let today = start_db.create_todo(new_todo("Water plants", None))?;
+    let monday = crate::dates::parse_start_date("2030-06-03").ok_or_else(|| anyhow::anyhow!("start date not parsed"))?;
+    anyhow::ensure!(monday.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string() == "2030-06-03 00:00", "date-only start should be local midnight");
+    anyhow::ensure!(crate::dates::format_start_date(monday) == "2030-06-03"
+        && crate::dates::parse_start_date(&crate::dates::format_start_date(monday + chrono::Duration::minutes(90))) == Some(monday + chrono::Duration::minutes(90)), "start date format does not round-trip");
+    start_db.set_start_at(someday, Some(monday))?;
+    start_db.set_start_at(today, Some(Utc::now() - chrono::Duration::hours(1)))?;
+    let todos = start_db.get_all_todos()?;
+    let now = Utc::now();
+    anyhow::ensure!(filter::deferred_ids(&todos, now) == HashSet::from([someday, lesson]), "deferred subtree wrong");
+    anyhow::ensure!(filter::deferred_ids(&todos, monday).is_empty(), "todos should start at their start date");
+    start_db.complete_todo(someday)?;
+    anyhow::ensure!(filter::deferred_ids(&start_db.get_all_todos()?, now).is_empty(), "a completed todo is not deferred");
+    start_db.uncomplete_todo(someday)?;
+    let cello = start_db.get_todo_by_id(someday)?.ok_or_else(|| anyhow::anyhow!("deferred todo missing"))?;
+    let (mirrored, _, _) = mirror::parse_todo_file(&mirror::todo_to_file(&cello, None, Vec::new())?)?;
+    anyhow::ensure!(mirrored.start_at == Some(monday), "mirror lost the start date");
+    anyhow::ensure!(org::write_org(&[cello]).contains("SCHEDULED: <2030-06-03"), "org SCHEDULED not written");
+    let captured = capture::parse_capture_line("Renew passport start:2030-06-03 due:2030-07-01", crate::dates::END_OF_DAY)?;
+    anyhow::ensure!(captured.title == "Renew passport" && captured.start_at == Some(monday) && captured.due_by.is_some(), "capture start wrong: {:?}", captured);
+    anyhow::ensure!(capture::parse_capture_line("Renew passport start:later", crate::dates::END_OF_DAY).is_err(), "invalid capture start date accepted");
+    start_db.set_start_at(someday, None)?;
+    anyhow::ensure!(filter::deferred_ids(&start_db.get_all_todos()?, now).is_empty(), "clearing the start date should make the todo active");
+    println!("Start dates OK");
+
+    // Test hooks: matching events run the command with the todo as JSON on stdin
+    println!("Testing hooks...");
+    let hook_out = std::env::temp_dir().join(format!("tododb_hook_test_{}.json", std::process::id()));
+    let config: Config = toml::from_str(&format!(
+        "[[hooks]]\nevents = [\"complete\"]\ncommand = \"cat > '{}'\"\n\n[[hooks]]\ncommand = \"echo nope >&2; exit 3\"\n",
+        hook_out.display()
+    ))?;
+    anyhow::ensure!(config.hooks.len() == 2 && config.hooks[1].events.is_empty(), "[[hooks]] not read");
+    anyhow::ensure!(toml::from_str::<Config>("[[hooks]]\nevents = [\"edit\"]\ncommand = \"true\"\n").is_err(), "unknown hook event accepted");
+    let hook_todo = Todo {
+        id: 7,
+        title: "Tag the release".to_string(),
+        description: String::new(),
+        created_at: Utc::now(),
+        completed_at: Some(Utc::now()),
+        due_by: None,
+        parent_id: Some(3),
+        hidden: false,
+        start_at: None,
+    };
+    let payload = hooks::event_payload(HookEvent::Complete, &hook_todo, Some("Release 1.2"), Some("/tmp/todos.db"), None);
+    let mut runner = hooks::HookRunner::default();
+    runner.fire(&config.hooks, HookEvent::Create, &payload);
+    let errors = runner.wait();
+    anyhow::ensure!(errors.len() == 1 && errors[0].contains("nope"), "catch-all hook should fail with its stderr: {:?}", errors);
+    anyhow::ensure!(!hook_out.exists(), "complete hook ran for a create event");
+    runner.fire(&config.hooks, HookEvent::Complete, &payload);
+    anyhow::ensure!(runner.wait().len() == 1, "failing hook not reported");
+    let received: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&hook_out)?)?;
+    std::fs::remove_file(&hook_out)?;
+    anyhow::ensure!(received["event"] == "complete" && received["text"] == "Completed: Tag the release", "hook payload: {}", received);
+    anyhow::ensure!(received["todo"]["id"] == 7 && received["todo"]["parent_title"] == "Release 1.2", "hook todo fields: {}", received);
+    println!("Hooks OK");
+
+    // Test encrypted databases: a copy opens only with its passphrase
+    #[cfg(feature = "encryption")]
+    {
+        println!("Testing encrypted databases...");
+        let encrypted_path = std::env::temp_dir().join(format!("tododb_encrypted_test_{}.db", std::process::id()));
+        let encrypted_path = encrypted_path.to_string_lossy().to_string();
+        db.export_encrypted(&encrypted_path, "s3cret")?;
+        let encrypted = Database::new_encrypted(&encrypted_path, "s3cret")?;
+        anyhow::ensure!(encrypted.get_all_todos()?.len() == db.get_all_todos()?.len(), "encrypted copy lost todos");
+        drop(encrypted);
+        anyhow::ensure!(Database::new_encrypted(&encrypted_path, "wrong").is_err(), "wrong passphrase accepted");
+        anyhow::ensure!(Database::new(&encrypted_path).is_err(), "encrypted database opened without a passphrase");
+        anyhow::ensure!(db.export_encrypted(&encrypted_path, "s3cret").is_err(), "encrypt overwrote an existing file");
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", encrypted_path, suffix));
+        }
+        println!("Encrypted databases OK");
+    }
+
+    // Test moving a subtree into another database: ids and parents are remapped
+    println!("Testing cross-database move...");
+    let source_db = Database::new(":memory:")?;
+    let target_db = Database::new(":memory:")?;
+    let existing_id = target_db.create_todo(NewTodo { title: "Existing".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let trip_id = source_db.create_todo(NewTodo { title: "Trip".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let tickets_id = source_db.create_todo(NewTodo { title: "Tickets".to_string(), description: String::new(), parent_id: Some(trip_id), due_by: None })?;
+    let seats_id = source_db.create_todo(NewTodo { title: "Seats".to_string(), description: String::new(), parent_id: Some(tickets_id), due_by: None })?;
+    source_db.complete_todo(tickets_id)?;
+    source_db.set_status(seats_id, Status::Blocked)?;
+    source_db.add_attachment(tickets_id, "https://example.com/booking")?;
+
+    let (copied_id, count) = export::transfer_subtree(&source_db, &target_db, trip_id, Some(existing_id), true)?;
+    anyhow::ensure!(count == 3, "expected 3 todos copied, got {}", count);
+    anyhow::ensure!(source_db.get_all_todos()?.len() == 3, "copy removed the original");
+    let (moved_id, _) = export::transfer_subtree(&source_db, &target_db, trip_id, None, false)?;
+    anyhow::ensure!(source_db.get_all_todos()?.is_empty(), "move left the original behind");
+
+    let transferred = target_db.get_all_todos()?;
+    anyhow::ensure!(transferred.len() == 7, "expected 7 todos in the target, got {}", transferred.len());
+    let copied = transferred.iter().find(|t| t.id == copied_id).ok_or_else(|| anyhow::anyhow!("copied root missing"))?;
+    anyhow::ensure!(copied.title == "Trip" && copied.parent_id == Some(existing_id), "copied root not under the new parent");
+    let moved_tickets = transferred.iter().find(|t| t.title == "Tickets" && t.parent_id == Some(moved_id))
+        .ok_or_else(|| anyhow::anyhow!("moved child not remapped"))?;
+    anyhow::ensure!(moved_tickets.is_completed(), "completion lost in the move");
+    let moved_seats = transferred.iter().find(|t| t.title == "Seats" && t.parent_id == Some(moved_tickets.id)).ok_or_else(|| anyhow::anyhow!("grandchild not remapped"))?;
+    anyhow::ensure!(target_db.get_statuses()?.get(&moved_seats.id) == Some(&Status::Blocked), "status lost in the move");
+    anyhow::ensure!(target_db.get_attachments(moved_tickets.id)?.len() == 1, "attachment lost in the move");
+    anyhow::ensure!(export::transfer_subtree(&target_db, &source_db, moved_id, Some(999), true).is_err(), "missing target parent accepted");
+    anyhow::ensure!(source_db.get_all_todos()?.is_empty(), "failed move left partial todos");
+    println!("Cross-database move OK");
+
+    // Test the plain-text mirror: files round-trip and deleted todos lose their file
+    println!("Testing plain-text mirror...");
+    let mirror_dir = std::env::temp_dir().join(format!("tododb_mirror_test_{}", std::process::id()));
+    let mirrored_db = Database::new(":memory:")?;
+    let book_id = mirrored_db.create_todo(NewTodo { title: "Book #travel".to_string(), description: "\nFirst line\n\n+++\n".to_string(), parent_id: None, due_by: Some(Utc::now()) })?;
+    let seat_id = mirrored_db.create_todo(NewTodo { title: "Seat".to_string(), description: String::new(), parent_id: Some(book_id), due_by: None })?;
+    let gone_id = mirrored_db.create_todo(NewTodo { title: "Gone".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    mirrored_db.complete_todo(seat_id)?;
+    mirrored_db.set_status(seat_id, Status::Cancelled)?;
+    mirrored_db.toggle_todo_hidden(book_id)?;
+    mirrored_db.add_attachment(book_id, "https://example.com/flight")?;
+    anyhow::ensure!(mirror::write_mirror(&mirrored_db, &mirror_dir)? == 3, "expected 3 mirror files written");
+    mirrored_db.delete_todo(gone_id)?;
+    anyhow::ensure!(mirror::write_mirror(&mirrored_db, &mirror_dir)? == 1, "unchanged files rewritten or deleted file kept");
+    anyhow::ensure!(!mirror_dir.join(format!("{}.md", gone_id)).exists(), "deleted todo's file left behind");
+
+    let rebuilt_db = Database::new(":memory:")?;
+    anyhow::ensure!(mirror::rebuild(&rebuilt_db, &mirror_dir)? == 2, "expected 2 todos rebuilt");
+    anyhow::ensure!(mirror::rebuild(&rebuilt_db, &mirror_dir).is_err(), "rebuild into a non-empty database accepted");
+    std::fs::remove_dir_all(&mirror_dir)?;
+    let original = mirrored_db.get_all_todos()?;
+    let rebuilt = rebuilt_db.get_all_todos()?;
+    for todo in &original {
+        let copy = rebuilt.iter().find(|t| t.id == todo.id).ok_or_else(|| anyhow::anyhow!("todo {} not rebuilt", todo.id))?;
+        anyhow::ensure!(
+            copy.title == todo.title && copy.description == todo.description && copy.parent_id == todo.parent_id
+                && copy.created_at == todo.created_at && copy.completed_at == todo.completed_at
+                && copy.due_by == todo.due_by && copy.hidden == todo.hidden,
+            "todo {} changed in the round trip: {:?} vs {:?}", todo.id, copy, todo
+        );
+    }
+    anyhow::ensure!(rebuilt_db.get_attachments(book_id)?.len() == 1, "attachment lost in the round trip");
+    anyhow::ensure!(rebuilt_db.get_statuses()? == HashMap::from([(seat_id, Status::Cancelled)]), "status lost in the round trip");
+    println!("Plain-text mirror OK");
+
+    // Test sync: a round trip over HTTP, last writer wins, deletions travel
+    println!("Testing sync...");
+    let server_db = Database::new(":memory:")?;
+    let errands_id = server_db.create_todo(NewTodo { title: "Errands".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let server_post_id = server_db.create_todo(NewTodo { title: "Post office".to_string(), description: String::new(), parent_id: Some(errands_id), due_by: None })?;
+    server_db.set_status(server_post_id, Status::InProgress)?;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let server = std::thread::spawn(move || -> anyhow::Result<Database> {
+        for stream in listener.incoming().take(4) {
+            sync::handle_connection(&server_db, stream?, Some("s3cret"))?;
+        }
+        Ok(server_db)
+    });
+
+    let client_db = Database::new(":memory:")?;
+    anyhow::ensure!(sync::sync(&client_db, &url, Some("wrong")).is_err(), "wrong sync token accepted");
+    anyhow::ensure!(sync::serve(&client_db, "0.0.0.0:0", None).is_err(), "sync served beyond loopback without a token");
+    anyhow::ensure!(http::bearer_matches(Some("Bearer s3cret"), "s3cret") && !http::bearer_matches(Some("Bearer s3cre"), "s3cret") && !http::bearer_matches(None, "s3cret"), "bearer token check wrong");
+    let report = sync::sync(&client_db, &url, Some("s3cret"))?;
+    anyhow::ensure!(report == SyncReport { received: 2, sent: 0 }, "first sync: {:?}", report);
+    let client_todos = client_db.get_all_todos()?;
+    let client_errands = client_todos.iter().find(|t| t.title == "Errands").ok_or_else(|| anyhow::anyhow!("synced parent missing"))?.id;
+    let client_post = client_todos.iter().find(|t| t.title == "Post office").ok_or_else(|| anyhow::anyhow!("synced child missing"))?.id;
+    anyhow::ensure!(client_db.get_todo_by_id(client_post)?.and_then(|t| t.parent_id) == Some(client_errands), "synced hierarchy lost");
+    anyhow::ensure!(client_db.get_statuses()? == HashMap::from([(client_post, Status::InProgress)]), "synced status lost");
+
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    client_db.update_todo(client_post, "Post office (parcel)".to_string(), String::new(), None)?;
+    let bank_id = client_db.create_todo(NewTodo { title: "Bank".to_string(), description: String::new(), parent_id: Some(client_errands), due_by: None })?;
+    let report = sync::sync(&client_db, &url, Some("s3cret"))?;
+    anyhow::ensure!(report == SyncReport { received: 0, sent: 2 }, "sync of local changes: {:?}", report);
+    client_db.delete_todo(bank_id)?;
+    let report = sync::sync(&client_db, &url, Some("s3cret"))?;
+    anyhow::ensure!(report == SyncReport { received: 0, sent: 1 }, "sync of a deletion: {:?}", report);
+    let server_db = server.join().map_err(|_| anyhow::anyhow!("sync server panicked"))??;
+    let mut server_titles: Vec<String> = server_db.get_all_todos()?.into_iter().map(|t| t.title).collect();
+    server_titles.sort();
+    anyhow::ensure!(server_titles == ["Errands", "Post office (parcel)"], "server out of step: {:?}", server_titles);
+
+    // An older copy doesn't overwrite a newer edit
+    let stale = server_db.get_sync_records()?;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    client_db.update_todo(client_post, "Post office (two parcels)".to_string(), String::new(), None)?;
+    anyhow::ensure!(sync::merge(&client_db, stale)? == 0, "stale records applied");
+    // A todo deleted elsewhere goes, but subtasks added here since move up a level
+    let stamps_id = client_db.create_todo(NewTodo { title: "Stamps".to_string(), description: String::new(), parent_id: Some(client_post), due_by: None })?;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    server_db.delete_todo(server_post_id)?;
+    anyhow::ensure!(sync::merge(&client_db, server_db.get_sync_records()?)? == 1, "deletion not applied");
+    anyhow::ensure!(client_db.get_todo_by_id(client_post)?.is_none(), "deleted todo kept");
+    anyhow::ensure!(client_db.get_todo_by_id(stamps_id)?.and_then(|t| t.parent_id) == Some(client_errands), "orphaned subtask not moved up");
+    println!("Sync OK");
+
+    // Test CalDAV push: one VTODO resource per todo, only changes go out, deletions follow
+    println!("Testing CalDAV push...");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let collection = format!("http://{}/dav/calendars/me/tasks", listener.local_addr()?);
+    let caldav_server = std::thread::spawn(move || -> anyhow::Result<Vec<(String, String, String)>> {
+        use std::io::{BufRead, Read, Write};
+        let mut requests = Vec::new();
+        for stream in listener.incoming().take(6) {
+            let mut stream = stream?;
+            let mut reader = std::io::BufReader::new(stream.try_clone()?);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line)?;
+            let (mut content_length, mut authorization) = (0, String::new());
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    break;
+                }
+                match line.split_once(':') {
+                    Some((name, value)) if name.eq_ignore_ascii_case("content-length") => content_length = value.trim().parse()?,
+                    Some((name, value)) if name.eq_ignore_ascii_case("authorization") => authorization = value.trim().to_string(),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            // "me:pw" in base64
+            let status = if authorization == "Basic bWU6cHc=" { "201 Created" } else { "401 Unauthorized" };
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status)?;
+            let mut parts = request_line.split_whitespace();
+            requests.push((parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string(), String::from_utf8(body)?));
+        }
+        Ok(requests)
+    });
+
+    let caldav_db = Database::new(":memory:")?;
+    let trip_id = caldav_db.create_todo(NewTodo { title: "Plan trip; maybe".to_string(), description: "Book\nearly".to_string(), parent_id: None, due_by: None })?;
+    let due = chrono::DateTime::parse_from_rfc3339("2025-03-01T17:00:00Z")?.with_timezone(&Utc);
+    let hotel_id = caldav_db.create_todo(NewTodo { title: "Hotel".to_string(), description: String::new(), parent_id: Some(trip_id), due_by: Some(due) })?;
+    let visa_id = caldav_db.create_todo(NewTodo { title: "Visa".to_string(), description: String::new(), parent_id: Some(trip_id), due_by: None })?;
+    anyhow::ensure!(caldav::push(&caldav_db, &collection, Some(("me", "wrong"))).is_err(), "rejected upload not reported");
+    let report = caldav::push(&caldav_db, &collection, Some(("me", "pw")))?;
+    anyhow::ensure!(report == PushReport { uploaded: 3, deleted: 0 }, "first push: {:?}", report);
+    let report = caldav::push(&caldav_db, &collection, Some(("me", "pw")))?;
+    anyhow::ensure!(report == PushReport::default(), "unchanged todos pushed again: {:?}", report);
+    caldav_db.complete_todo(hotel_id)?;
+    caldav_db.delete_todo(visa_id)?;
+    let report = caldav::push(&caldav_db, &collection, Some(("me", "pw")))?;
+    anyhow::ensure!(report == PushReport { uploaded: 1, deleted: 1 }, "push of a completion and a deletion: {:?}", report);
+
+    let requests = caldav_server.join().map_err(|_| anyhow::anyhow!("CalDAV server panicked"))??;
+    anyhow::ensure!(requests.len() == 6, "expected 6 requests, got {}", requests.len());
+    anyhow::ensure!(requests[1].0 == "PUT" && requests[1].1.starts_with("/dav/calendars/me/tasks/") && requests[1].1.ends_with(".ics"), "unexpected request {:?}", requests[1]);
+    let uploaded: Vec<ical::VTodo> = requests[1..4].iter().flat_map(|(_, _, body)| ical::parse_vtodos(body)).collect();
+    let trip = uploaded.iter().find(|t| t.summary == "Plan trip; maybe").ok_or_else(|| anyhow::anyhow!("escaped title lost: {:?}", uploaded))?;
+    let hotel = uploaded.iter().find(|t| t.summary == "Hotel").ok_or_else(|| anyhow::anyhow!("child not uploaded"))?;
+    anyhow::ensure!(trip.description == "Book\nearly" && !trip.is_completed, "description or status lost: {:?}", trip);
+    anyhow::ensure!(hotel.parent_uid == trip.uid && hotel.due == Some(due), "hierarchy or due date lost: {:?}", hotel);
+    let completed = ical::parse_vtodos(&requests[4].2);
+    anyhow::ensure!(requests[4].0 == "PUT" && completed.first().is_some_and(|t| t.is_completed && t.completed.is_some()), "completion not pushed: {:?}", requests[4]);
+    let visa = uploaded.iter().find(|t| t.summary == "Visa").and_then(|t| t.uid.clone()).unwrap_or_default();
+    anyhow::ensure!(requests[5].0 == "DELETE" && requests[5].1.ends_with(&format!("/{}.ics", visa)), "deletion not pushed: {:?}", requests[5]);
+
+    // https goes through curl: the whole body arrives, line breaks included
+    if std::process::Command::new("curl").arg("--version").output().is_ok() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/dav/tasks/a.ics", listener.local_addr()?);
+        let receiver = std::thread::spawn(move || -> anyhow::Result<http::Request> {
+            let mut stream = listener.accept()?.0;
+            let request = http::read_request(&stream)?;
+            http::respond(&mut stream, 201, "text/plain", "stored")?;
+            Ok(request)
+        });
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:Say \"hi\" \\ wave\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        let (status, reply) = http::request_with_curl("PUT", &url, &[("Authorization", http::basic_auth("me", "pw"))], calendar)?;
+        let request = receiver.join().map_err(|_| anyhow::anyhow!("curl receiver panicked"))??;
+        anyhow::ensure!(status == 201 && reply == "stored", "curl reply wrong: {} {:?}", status, reply);
+        anyhow::ensure!(request.method == "PUT" && request.authorization.as_deref() == Some("Basic bWU6cHc="), "curl request wrong: {} {:?}", request.method, request.authorization);
+        let body = request.body.unwrap_or_default();
+        anyhow::ensure!(body == calendar.as_bytes(), "curl cut the body: {:?}", String::from_utf8_lossy(&body));
+    } else {
+        println!("curl not installed; skipping the https path");
+    }
+    println!("CalDAV push OK");
+
+    // Test importers: each format lands with priorities, tags, dates and hierarchy intact
+    println!("Testing importers...");
+    let import_dir = std::env::temp_dir().join(format!("tododb_import_test_{}", std::process::id()));
+    std::fs::create_dir_all(&import_dir)?;
+    let samples = [
+        ("todo.txt", "(A) 2024-01-01 Call mom +family @phone due:2024-01-05\nx 2024-01-06 2024-01-02 Pay rent\n"),
+        ("reminders.ics", "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:parent-1\r\nSUMMARY:Plan trip\r\nEND:VTODO\r\nBEGIN:VTODO\r\nUID:child-1\r\nSUMMARY:Book\r\n  hotel\r\nDESCRIPTION:Near the\\nstation\r\nRELATED-TO:parent-1\r\nDUE;VALUE=DATE:20240301\r\nSTATUS:COMPLETED\r\nPRIORITY:1\r\nEND:VTODO\r\nEND:VCALENDAR\r\n"),
+        ("Tasks.json", r#"{"kind":"tasks#taskLists","items":[{"id":"L1","title":"Groceries","items":[{"id":"T1","title":"Milk","status":"needsAction","due":"2024-02-01T00:00:00.000Z"},{"id":"T2","title":"Oat","parent":"T1","status":"completed","completed":"2024-01-31T10:00:00.000Z"}]}]}"#),
+        ("task-export.json", r#"[{"uuid":"u1","description":"Fix bike","status":"pending","entry":"20240101T080000Z","project":"home","priority":"H","tags":["outdoor"]},{"uuid":"u2","description":"Old","status":"deleted"}]"#),
+    ];
+    for (name, content) in samples {
+        std::fs::write(import_dir.join(name), content)?;
+    }
+    std::fs::write(import_dir.join("pending.data"), "[description:\"Water &open;plants&close;\" entry:\"1704096000\" status:\"pending\" uuid:\"u3\" tags:\"garden\"]\n")?;
+
+    let import_db = Database::new(":memory:")?;
+    let detected: Vec<SourceKind> = ["todo.txt", "reminders.ics", "Tasks.json", "task-export.json"]
+        .iter()
+        .map(|name| import::detect_source(&import_dir.join(name)).map(|source| source.kind))
+        .collect::<anyhow::Result<_>>()?;
+    anyhow::ensure!(
+        detected == [SourceKind::TodoTxt, SourceKind::ICalendar, SourceKind::GoogleTasks, SourceKind::Taskwarrior],
+        "format detection failed: {:?}", detected
+    );
+    for name in ["todo.txt", "reminders.ics", "Tasks.json", "task-export.json"] {
+        import::import_source(&import_db, &import::detect_source(&import_dir.join(name))?)?;
+    }
+    import::import_source(&import_db, &import::detect_source(&import_dir)?)?;
+    std::fs::remove_dir_all(&import_dir)?;
+
+    let imported = import_db.get_all_todos()?;
+    let find = |title: &str| imported.iter().find(|t| t.title == title);
+    let call = find("p0 Call mom #family #phone").ok_or_else(|| anyhow::anyhow!("todo.txt line not imported"))?;
+    anyhow::ensure!(call.due_by.is_some() && !call.is_completed(), "todo.txt due date lost");
+    anyhow::ensure!(find("Pay rent").is_some_and(|t| t.is_completed()), "todo.txt completion lost");
+    let hotel = find("p0 Book hotel").ok_or_else(|| anyhow::anyhow!("folded ics summary not imported"))?;
+    anyhow::ensure!(hotel.description == "Near the\nstation" && hotel.is_completed(), "ics fields lost");
+    anyhow::ensure!(hotel.parent_id == find("Plan trip").map(|t| t.id), "ics RELATED-TO parent lost");
+    let oat = find("Oat").ok_or_else(|| anyhow::anyhow!("google subtask not imported"))?;
+    anyhow::ensure!(oat.parent_id == find("Milk").map(|t| t.id) && oat.is_completed(), "google hierarchy lost");
+    anyhow::ensure!(find("Milk").and_then(|t| t.parent_id) == find("Groceries").map(|t| t.id), "google list parent lost");
+    let bike = find("p0 Fix bike #outdoor").ok_or_else(|| anyhow::anyhow!("taskwarrior task not imported"))?;
+    anyhow::ensure!(bike.parent_id == find("home").map(|t| t.id), "taskwarrior project parent lost");
+    anyhow::ensure!(find("Old").is_none(), "deleted taskwarrior task should be skipped");
+    anyhow::ensure!(find("Water [plants] #garden").is_some(), "taskwarrior data file not imported");
+    println!("Importers OK");
+
+    // Test markdown outline import: nesting by indentation, checkboxes, notes under a bullet
+    println!("Testing markdown outline import...");
+    let notes = "## Action items\n- Launch prep\n  - [x] Draft post\n  - [ ] Review slides\n    owner: Sam\n\t* Book room\n1. Follow up\n\nRandom remark\n";
+    let outline = import::parse_markdown_outline(notes);
+    let titles: Vec<&str> = outline.iter().map(|t| t.title.as_str()).collect();
+    anyhow::ensure!(titles == ["Launch prep", "Draft post", "Review slides", "Book room", "Follow up"], "outline items: {:?}", titles);
+    let outline_db = Database::new(":memory:")?;
+    let meeting_id = outline_db.create_todo(NewTodo { title: "Weekly sync".to_string(), description: String::new(), parent_id: None, due_by: None })?;
+    let created = import::import_todos(&outline_db, outline, Some(meeting_id))?;
+    anyhow::ensure!(created.len() == 5, "expected 5 todos, got {}", created.len());
+    let outline_todos = outline_db.get_all_todos()?;
+    let find = |title: &str| outline_todos.iter().find(|t| t.title == title);
+    let launch = find("Launch prep").ok_or_else(|| anyhow::anyhow!("outline root missing"))?;
+    anyhow::ensure!(launch.parent_id == Some(meeting_id), "outline not placed under the selected todo");
+    anyhow::ensure!(find("Follow up").and_then(|t| t.parent_id) == Some(meeting_id), "numbered item not a sibling");
+    anyhow::ensure!(find("Draft post").is_some_and(|t| t.is_completed() && t.parent_id == Some(launch.id)), "checked item wrong");
+    let review = find("Review slides").ok_or_else(|| anyhow::anyhow!("nested item missing"))?;
+    anyhow::ensure!(review.description == "owner: Sam" && !review.is_completed(), "note under a bullet lost: {:?}", review.description);
+    anyhow::ensure!(find("Book room").and_then(|t| t.parent_id) == Some(review.id), "tab-indented item misplaced");
+    println!("Markdown outline import OK");
+
+    // Test the JSON-RPC / MCP server: handshake, tool calls, plain methods and errors
+    println!("Testing JSON-RPC server...");
+    let rpc_db = Database::new(":memory:")?;
+    let mut server = rpc::Server::new(&rpc_db, Vec::new());
+    let mut reply = |message: &str| -> anyhow::Result<serde_json::Value> {
+        let response = server.handle_message(message).ok_or_else(|| anyhow::anyhow!("no reply to {}", message))?;
+        Ok(serde_json::from_str(&response)?)
+    };
+    let init = reply(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#)?;
+    anyhow::ensure!(init["result"]["protocolVersion"] == "2025-03-26", "protocol version not echoed: {}", init);
+    let tools = reply(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)?;
+    anyhow::ensure!(tools["result"]["tools"].as_array().is_some_and(|tools| tools.len() == 4), "tools: {}", tools);
+    let created = reply(r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"create_todo","arguments":{"title":"Renew passport","due":"2w"}}}"#)?;
+    anyhow::ensure!(created["result"]["isError"] == false, "create failed: {}", created);
+    let passport: Todo = serde_json::from_str(created["result"]["content"][0]["text"].as_str().unwrap_or_default())?;
+    anyhow::ensure!(passport.title == "Renew passport" && passport.due_by.is_some(), "created todo wrong: {:?}", passport);
+    let child = reply(&format!(r#"{{"jsonrpc":"2.0","id":4,"method":"create","params":{{"title":"Photos","parent_id":{}}}}}"#, passport.id))?;
+    anyhow::ensure!(child["result"]["parent_id"] == passport.id, "plain create lost the parent: {}", child);
+    reply(&format!(r#"{{"jsonrpc":"2.0","id":5,"method":"complete","params":{{"id":{}}}}}"#, child["result"]["id"]))?;
+    let open = reply(r#"{"jsonrpc":"2.0","id":6,"method":"list","params":{"filter":"status:open"}}"#)?;
+    anyhow::ensure!(open["result"].as_array().is_some_and(|todos| todos.len() == 1), "filtered list: {}", open);
+    let found = reply(r#"{"jsonrpc":"2.0","id":7,"method":"search","params":{"query":"PHOTO"}}"#)?;
+    anyhow::ensure!(found["result"][0]["completed_at"].is_string(), "search or complete failed: {}", found);
+    let missing = reply(r#"{"jsonrpc":"2.0","id":8,"method":"tools/call","params":{"name":"complete_todo","arguments":{"id":999}}}"#)?;
+    anyhow::ensure!(missing["result"]["isError"] == true, "tool error not reported in the result: {}", missing);
+    anyhow::ensure!(reply(r#"{"jsonrpc":"2.0","id":9,"method":"archive"}"#)?["error"]["code"] == -32601, "unknown method accepted");
+    for params in [r#"{"title":"Someday","due":"1000000000d"}"#.to_string(), format!(r#"{{"id":{},"due":"9999999999999"}}"#, passport.id)] {
+        let method = if params.contains("title") { "create" } else { "update" };
+        let far = reply(&format!(r#"{{"jsonrpc":"2.0","id":10,"method":"{}","params":{}}}"#, method, params))?;
+        anyhow::ensure!(far["error"]["code"] == rpc::INVALID_PARAMS, "out-of-range due date not rejected: {}", far);
+    }
+    anyhow::ensure!(crate::dates::parse_due_date("1000000000d").is_none() && crate::dates::parse_start_date("1000000000d").is_none(), "out-of-range relative date parsed");
+    anyhow::ensure!(reply("not json")?["error"]["code"] == -32700, "parse error not reported");
+    anyhow::ensure!(server.handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).is_none(), "notification answered");
+    println!("JSON-RPC server OK");
+
+    // Test the REST API: read-only unless writable, token required when set, web view escapes titles
+    #[cfg(feature = "api")]
+    {
+        println!("Testing REST API...");
+        let api_db = Database::new(":memory:")?;
+        let mut api_server = rpc::Server::new(&api_db, Vec::new());
+        let request = |method: &str, target: &str, body: &str| http::Request {
+            method: method.to_string(),
+            target: target.to_string(),
+            authorization: None,
+            content_type: Some("application/json".to_string()),
+            body: Some(body.as_bytes().to_vec()),
+        };
+        let read_only = ApiOptions::default();
+        let writable = ApiOptions { writable: true, token: None };
+        let response = api::route(&mut api_server, &request("POST", "/api/todos", r#"{"title":"Water <plants>"}"#), &read_only);
+        anyhow::ensure!(response.status == 403, "read-only API accepted a write: {:?}", response);
+        let response = api::route(&mut api_server, &request("POST", "/api/todos", r#"{"title":"Water <plants>","due":"1d"}"#), &writable);
+        anyhow::ensure!(response.status == 201, "create failed: {:?}", response);
+        let plants: Todo = serde_json::from_str(&response.body)?;
+        let child = api::route(&mut api_server, &request("POST", "/api/todos", &format!(r#"{{"title":"Buy soil","parent_id":{}}}"#, plants.id)), &writable);
+        anyhow::ensure!(child.status == 201, "child create failed: {:?}", child);
+        let response = api::route(&mut api_server, &request("PATCH", &format!("/api/todos/{}", plants.id), r#"{"title":"Water the plants","due":null}"#), &writable);
+        let updated: Todo = serde_json::from_str(&response.body)?;
+        anyhow::ensure!(updated.title == "Water the plants" && updated.due_by.is_none(), "update failed: {:?}", response);
+        let response = api::route(&mut api_server, &request("DELETE", &format!("/api/todos/{}", plants.id), ""), &writable);
+        anyhow::ensure!(response.status == 400, "todo with subtasks deleted: {:?}", response);
+        let response = api::route(&mut api_server, &request("GET", "/api/todos?filter=parent%3Anone", ""), &read_only);
+        anyhow::ensure!(serde_json::from_str::<Vec<Todo>>(&response.body)?.len() == 1, "filtered list wrong: {:?}", response);
+        anyhow::ensure!(api::route(&mut api_server, &request("GET", "/api/todos/999", ""), &read_only).status == 404, "missing todo found");
+        anyhow::ensure!(api::route(&mut api_server, &request("GET", "/nowhere", ""), &read_only).status == 404, "unknown path served");
+        let page = api::route(&mut api_server, &request("GET", "/", ""), &read_only);
+        anyhow::ensure!(page.body.contains("Water the plants") && page.body.contains("Buy soil") && !page.body.contains("checkbox"), "web view wrong");
+        let search = api::route(&mut api_server, &request("GET", "/?q=soil+%3C", ""), &read_only);
+        anyhow::ensure!(search.body.contains(r#"value="soil &lt;""#), "search query not decoded and escaped");
+        let guarded = ApiOptions { writable: false, token: Some("s3cret".to_string()) };
+        anyhow::ensure!(api::route(&mut api_server, &request("GET", "/api/todos", ""), &guarded).status == 401, "token not required");
+        anyhow::ensure!(api::route(&mut api_server, &request("GET", "/?token=s3cret", ""), &guarded).status == 200, "token in the query refused");
+        anyhow::ensure!(api::route(&mut api_server, &request("GET", "/?token=s3cre", ""), &guarded).status == 401, "token prefix accepted");
+        let far = api::route(&mut api_server, &request("GET", "/api/todos?filter=due%3C1000000000d", ""), &read_only);
+        anyhow::ensure!(far.status == 400 && far.body.contains("Invalid duration"), "out-of-range filter not refused: {:?}", far);
+        anyhow::ensure!(api::serve(&api_db, "0.0.0.0:0", &read_only, Vec::new()).is_err(), "API served beyond loopback without a token");
+        // A page elsewhere can make the browser POST text/plain without asking first; only JSON is taken
+        let form_post = http::Request { content_type: Some("text/plain".to_string()), ..request("POST", "/api/todos", r#"{"title":"Planted"}"#) };
+        let response = api::route(&mut api_server, &form_post, &writable);
+        anyhow::ensure!(response.status == 415 && !api_db.get_all_todos()?.iter().any(|todo| todo.title == "Planted"), "non-JSON write accepted: {:?}", response);
+        let charset = http::Request { content_type: Some("Application/JSON; charset=utf-8".to_string()), ..request("PATCH", &format!("/api/todos/{}", plants.id), r#"{"title":"Water the plants"}"#) };
+        anyhow::ensure!(api::route(&mut api_server, &charset, &writable).status == 200, "JSON with a charset refused");
+        println!("REST API OK");
+    }
+
+    // Test reading requests: bodies arrive in pieces; one cut short is an error, not a hang or a zero-filled body
+    println!("Testing HTTP requests...");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let client = std::thread::spawn(move || -> anyhow::Result<()> {
+        use std::io::Write;
+        let mut whole = std::net::TcpStream::connect(addr)?;
+        whole.write_all(b"POST /api/todos HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello")?;
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        whole.write_all(b" world")?;
+        let mut cut = std::net::TcpStream::connect(addr)?;
+        cut.write_all(b"POST /api/todos HTTP/1.1\r\nContent-Length: 1000000\r\n\r\nhello")?;
+        Ok(())
+    });
+    let whole = http::read_request(&listener.accept()?.0)?;
+    anyhow::ensure!(whole.body.as_deref() == Some(b"hello world".as_slice()), "body read wrong: {:?}", whole.body);
+    let cut = listener.accept()?.0;
+    client.join().map_err(|_| anyhow::anyhow!("HTTP client panicked"))??;
+    anyhow::ensure!(http::read_request(&cut).is_err(), "truncated body accepted");
+    println!("HTTP requests OK");
+
     // Test WAL checkpoint functionality
     println!("Testing WAL checkpoint...");
     db.checkpoint()?;
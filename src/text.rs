@@ -0,0 +1,93 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Most graphemes of a title kept in a file name
+const MAX_FILE_STEM_GRAPHEMES: usize = 50;
+/// Most bytes of a title kept in a file name, well under the usual 255-byte limit
+const MAX_FILE_STEM_BYTES: usize = 120;
+
+/// Cut `text` to at most `width` columns, ending in '…' when anything was dropped
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let (fits, _) = split_at_width(text, width.saturating_sub(1));
+    let mut result = fits.to_string();
+    if width > 0 {
+        result.push('…');
+    }
+    result
+}
+
+/// Split `text` at the last grapheme boundary that fits in `width` columns, so
+/// multi-byte characters and emoji sequences are never cut in half
+pub fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return text.split_at(index);
+        }
+    }
+    (text, "")
+}
+
+/// A file name for a todo: its id, which keeps names of different todos apart,
+/// then the title's letters and digits with everything else (punctuation,
+/// emoji, path separators) collapsed into single underscores
+pub fn file_name(id: i64, title: &str, extension: &str) -> String {
+    let mut stem = String::new();
+    for grapheme in title.graphemes(true).take(MAX_FILE_STEM_GRAPHEMES) {
+        let keep = grapheme.chars().all(char::is_alphanumeric) || grapheme == "-";
+        let part = if keep { grapheme } else { "_" };
+        if stem.len() + part.len() > MAX_FILE_STEM_BYTES {
+            break;
+        }
+        if part != "_" || !stem.ends_with('_') {
+            stem.push_str(part);
+        }
+    }
+    let stem = stem.trim_matches('_');
+    if stem.is_empty() {
+        format!("{}.{}", id, extension)
+    } else {
+        format!("{}_{}.{}", id, stem, extension)
+    }
+}
+
+/// Where a text cursor (a byte index) goes moving left: the start of the grapheme before it
+pub fn previous_boundary(text: &str, cursor: usize) -> usize {
+    text[..cursor].grapheme_indices(true).next_back().map_or(0, |(index, _)| index)
+}
+
+/// Where a text cursor goes moving right: past the grapheme after it
+pub fn next_boundary(text: &str, cursor: usize) -> usize {
+    text[cursor..].graphemes(true).next().map_or(cursor, |grapheme| cursor + grapheme.len())
+}
+
+/// The start and end of the line the cursor is on
+pub fn line_bounds(text: &str, cursor: usize) -> (usize, usize) {
+    let start = text[..cursor].rfind('\n').map_or(0, |index| index + 1);
+    let end = text[cursor..].find('\n').map_or(text.len(), |index| cursor + index);
+    (start, end)
+}
+
+/// The cursor moved to the line above or below, in the same column where that line is
+/// long enough and at its end otherwise. It stays put on the first or last line.
+pub fn vertical_move(text: &str, cursor: usize, down: bool) -> usize {
+    let (start, end) = line_bounds(text, cursor);
+    let column = text[start..cursor].graphemes(true).count();
+    let (target_start, target_end) = if down {
+        if end == text.len() {
+            return cursor;
+        }
+        line_bounds(text, end + 1)
+    } else {
+        if start == 0 {
+            return cursor;
+        }
+        line_bounds(text, start - 1)
+    };
+    let line = &text[target_start..target_end];
+    target_start + line.grapheme_indices(true).nth(column).map_or(line.len(), |(index, _)| index)
+}
@@ -1,4 +1,7 @@
 use crate::database::Todo;
+use crate::flag::Flag;
+use crate::status::Status;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -17,12 +20,58 @@ pub struct RenderedLine {
     pub has_children: bool,
 }
 
+/// What sits under a todo, shown next to move targets
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubtreeSummary {
+    /// Open direct subtasks
+    pub children: usize,
+    /// Earliest due date among its open descendants
+    pub next_due: Option<DateTime<Utc>>,
+}
+
+/// Where a moved todo would land, drawn as a ghost line in move mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovePreview {
+    /// Rendered line the ghost goes below; None puts it above the first line
+    pub after_line: Option<usize>,
+    pub depth: usize,
+}
+
+/// Counts over a project: a todo and everything under it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProjectStats {
+    pub open: usize,
+    pub overdue: usize,
+    /// Completed in the last 30 days
+    pub done_30d: usize,
+}
+
+impl ProjectStats {
+    /// Percentage of the last 30 days' work that got done: done / (done + still open)
+    pub fn completion_rate(&self) -> Option<usize> {
+        let total = self.done_30d + self.open;
+        (total > 0).then(|| self.done_30d * 100 / total)
+    }
+}
+
 pub struct TodoTreeManager {
     pub tree: Vec<TreeNode>,
     pub todos: HashMap<i64, Todo>,
     pub rendered_lines: Vec<RenderedLine>,
     pub id_to_line: HashMap<i64, usize>,
     pub expansion_states: HashMap<i64, bool>,
+    /// What to show instead of id % 100 for some todos (full ids, short codes)
+    pub id_labels: HashMap<i64, String>,
+    /// A hoisted todo shown as the only root, with just its subtree below it
+    pub root: Option<i64>,
+    /// Manual order of siblings placed by hand (see `Database::move_after`)
+    pub positions: HashMap<i64, i64>,
+    /// Stored statuses beyond open and done (see `Database::get_statuses`)
+    pub statuses: HashMap<i64, Status>,
+    /// Flagged todos (see `Database::get_flags`); an emoji flag goes in front of the title
+    pub flags: HashMap<i64, Flag>,
+    /// How many todos sit below each todo with children, counted once per rebuild
+    descendant_counts: HashMap<i64, usize>,
 }
 
 impl TodoTreeManager {
@@ -33,11 +82,17 @@ impl TodoTreeManager {
             rendered_lines: Vec::new(),
             id_to_line: HashMap::new(),
             expansion_states: HashMap::new(),
+            id_labels: HashMap::new(),
+            root: None,
+            positions: HashMap::new(),
+            statuses: HashMap::new(),
+            flags: HashMap::new(),
+            descendant_counts: HashMap::new(),
         }
     }
 
     /// Parse priority from title. Expects format: p0, p1, P0, P1 as first word
-    fn parse_priority(title: &str) -> Option<u32> {
+    pub fn parse_priority(title: &str) -> Option<u32> {
         let first_word = title.split_whitespace().next()?;
         let first_word_lower = first_word.to_lowercase();
 
@@ -88,6 +143,8 @@ impl TodoTreeManager {
 
         self.todos = filtered_todos.iter().map(|todo| (todo.id, todo.clone())).collect();
         self.tree = self.build_tree();
+        self.descendant_counts.clear();
+        Self::count_descendants(&self.tree, &mut self.descendant_counts);
         self.rendered_lines = self.render_tree();
         self.id_to_line = self.rendered_lines
             .iter()
@@ -101,7 +158,10 @@ impl TodoTreeManager {
         
         // Group todos by parent_id
         for todo in self.todos.values() {
-            children_map.entry(todo.parent_id).or_insert_with(Vec::new).push(todo.id);
+            children_map.entry(todo.parent_id).or_default().push(todo.id);
+        }
+        if let Some(root) = self.root.filter(|root| self.todos.contains_key(root)) {
+            children_map.insert(None, vec![root]);
         }
 
         // Build tree starting from root nodes, but only include roots with incomplete work
@@ -131,9 +191,7 @@ impl TodoTreeManager {
                         .unwrap_or(has_incomplete_children);
 
                     // Save the computed state if we didn't have one before
-                    if !self.expansion_states.contains_key(&child_id) {
-                        self.expansion_states.insert(child_id, is_expanded);
-                    }
+                    self.expansion_states.entry(child_id).or_insert(is_expanded);
 
                     // Parse priority from title
                     let priority = if let Some(todo) = self.todos.get(&child_id) {
@@ -152,36 +210,48 @@ impl TodoTreeManager {
             }
         }
 
-        // Sort by priority first (ascending: p0, p1, p2...), then by creation time (descending)
+        // Siblings placed by hand keep their manual order, after any added since (which
+        // take the default order)
         nodes.sort_by(|a, b| {
-            // Compare priorities first
-            match (a.priority, b.priority) {
-                (Some(pa), Some(pb)) => {
-                    // Both have priority - sort by priority ascending (p0 first)
-                    match pa.cmp(&pb) {
-                        std::cmp::Ordering::Equal => {
-                            // Same priority - sort by creation time descending
-                            let todo_a = &self.todos[&a.id];
-                            let todo_b = &self.todos[&b.id];
-                            todo_b.created_at.cmp(&todo_a.created_at)
-                        }
-                        other => other
-                    }
-                }
-                (Some(_), None) => std::cmp::Ordering::Less,    // Priority comes before no priority
-                (None, Some(_)) => std::cmp::Ordering::Greater, // No priority comes after priority
-                (None, None) => {
-                    // Neither has priority - sort by creation time descending
-                    let todo_a = &self.todos[&a.id];
-                    let todo_b = &self.todos[&b.id];
-                    todo_b.created_at.cmp(&todo_a.created_at)
-                }
+            match (self.positions.get(&a.id), self.positions.get(&b.id)) {
+                (Some(pa), Some(pb)) => pa.cmp(pb),
+                (Some(_), None) => std::cmp::Ordering::Greater,
+                (None, Some(_)) => std::cmp::Ordering::Less,
+                (None, None) => self.default_order(a, b),
             }
         });
 
         nodes
     }
 
+    /// Default sibling order: by priority first (ascending: p0, p1, p2...), then by
+    /// creation time (descending)
+    fn default_order(&self, a: &TreeNode, b: &TreeNode) -> std::cmp::Ordering {
+        // Compare priorities first
+        match (a.priority, b.priority) {
+            (Some(pa), Some(pb)) => {
+                // Both have priority - sort by priority ascending (p0 first)
+                match pa.cmp(&pb) {
+                    std::cmp::Ordering::Equal => {
+                        // Same priority - sort by creation time descending
+                        let todo_a = &self.todos[&a.id];
+                        let todo_b = &self.todos[&b.id];
+                        todo_b.created_at.cmp(&todo_a.created_at)
+                    }
+                    other => other
+                }
+            }
+            (Some(_), None) => std::cmp::Ordering::Less,    // Priority comes before no priority
+            (None, Some(_)) => std::cmp::Ordering::Greater, // No priority comes after priority
+            (None, None) => {
+                // Neither has priority - sort by creation time descending
+                let todo_a = &self.todos[&a.id];
+                let todo_b = &self.todos[&b.id];
+                todo_b.created_at.cmp(&todo_a.created_at)
+            }
+        }
+    }
+
     fn should_show_root_node(&self, node: &TreeNode) -> bool {
         if let Some(todo) = self.todos.get(&node.id) {
             // Show root if it's incomplete OR has incomplete descendants
@@ -193,10 +263,9 @@ impl TodoTreeManager {
 
     fn has_incomplete_descendants(&self, children: &[TreeNode]) -> bool {
         for child in children {
-            if let Some(todo) = self.todos.get(&child.id) {
-                if !todo.is_completed() {
-                    return true;
-                }
+            if let Some(todo) = self.todos.get(&child.id)
+                && !todo.is_completed() {
+                return true;
             }
             
             // Recursively check descendants
@@ -231,7 +300,7 @@ impl TodoTreeManager {
             let prefix = self.generate_prefix(&ancestor_continuations, is_last_sibling, depth);
             
             // Format todo display text with expansion indicator and priority
-            let status_icon = if todo.is_completed() { "[✓]" } else { "[ ]" };
+            let status_icon = self.status_of(todo).icon();
             let expansion_indicator = if !node.children.is_empty() {
                 if node.is_expanded { "▼ " } else { "▶ " }
             } else { "" };
@@ -239,7 +308,12 @@ impl TodoTreeManager {
             let priority_str = Self::format_priority(node.priority);
             let title_without_priority = Self::strip_priority_from_title(&todo.title);
 
-            let display_text = format!("{} {} {}{}{}", todo.id_mod(), status_icon, expansion_indicator, priority_str, title_without_priority);
+            let emoji = self.flags.get(&todo.id).and_then(Flag::emoji).map_or(String::new(), |emoji| format!("{} ", emoji));
+            let mut display_text = format!("{} {} {}{}{}{}", self.id_label(todo), status_icon, expansion_indicator, emoji, priority_str, title_without_priority);
+            // A collapsed branch says how much is folded away under it
+            if !node.children.is_empty() && !node.is_expanded {
+                display_text.push_str(&format!(" (+{})", self.descendant_count(node.id)));
+            }
             
             lines.push(RenderedLine {
                 todo_id: node.id,
@@ -290,14 +364,223 @@ impl TodoTreeManager {
         &self.rendered_lines
     }
 
+    /// How the views identify `todo`
+    pub fn status_of(&self, todo: &Todo) -> Status {
+        Status::resolve(todo.is_completed(), self.statuses.get(&todo.id).copied())
+    }
+
+    pub fn id_label(&self, todo: &Todo) -> String {
+        self.id_labels.get(&todo.id).cloned().unwrap_or_else(|| todo.id_mod().to_string())
+    }
+
     pub fn get_todo_by_id(&self, id: i64) -> Option<&Todo> {
         self.todos.get(&id)
     }
 
+    /// Subtask count and nearest due date for every open todo in the tree
+    pub fn subtree_summaries(&self) -> HashMap<i64, SubtreeSummary> {
+        let mut summaries: HashMap<i64, SubtreeSummary> = HashMap::new();
+        for todo in self.todos.values().filter(|todo| !todo.is_completed()) {
+            summaries.entry(todo.id).or_default();
+            if let Some(parent_id) = todo.parent_id {
+                summaries.entry(parent_id).or_default().children += 1;
+            }
+            let Some(due) = todo.due_by else { continue };
+            // Carry the due date up to every ancestor
+            let mut current = todo.parent_id;
+            let mut depth = 0;
+            while let Some(id) = current
+                && depth <= self.todos.len() {
+                let summary = summaries.entry(id).or_default();
+                summary.next_due = Some(summary.next_due.map_or(due, |next| next.min(due)));
+                current = self.todos.get(&id).and_then(|todo| todo.parent_id);
+                depth += 1;
+            }
+        }
+        summaries
+    }
+
+    /// Stats for every project under `parent` (the root projects for None) that has open
+    /// or recent work, busiest first
+    pub fn project_stats(&self, parent: Option<i64>, now: DateTime<Utc>) -> Vec<(i64, ProjectStats)> {
+        let month_ago = now - chrono::Duration::days(30);
+        let mut stats: HashMap<i64, ProjectStats> = HashMap::new();
+        for todo in self.todos.values() {
+            // Every todo counts towards itself and each of its ancestors
+            let mut current = Some(todo.id);
+            let mut depth = 0;
+            while let Some(id) = current
+                && depth <= self.todos.len() {
+                let entry = stats.entry(id).or_default();
+                match todo.completed_at {
+                    // Cancelled work was neither done nor is it still open
+                    Some(_) if self.status_of(todo) == Status::Cancelled => {}
+                    Some(done) => entry.done_30d += usize::from(done >= month_ago),
+                    None => {
+                        entry.open += 1;
+                        entry.overdue += usize::from(todo.due_by.is_some_and(|due| due < now));
+                    }
+                }
+                current = self.todos.get(&id).and_then(|todo| todo.parent_id);
+                depth += 1;
+            }
+        }
+
+        let mut projects: Vec<(i64, ProjectStats)> = self.todos.values()
+            .filter(|todo| todo.parent_id == parent)
+            .filter_map(|todo| Some((todo.id, *stats.get(&todo.id)?)))
+            .filter(|(_, project)| project.open > 0 || project.done_30d > 0)
+            .collect();
+        projects.sort_by(|(a_id, a), (b_id, b)| {
+            b.open.cmp(&a.open).then_with(|| self.todos[a_id].title.cmp(&self.todos[b_id].title))
+        });
+        projects
+    }
+
     pub fn get_line_index_for_todo(&self, todo_id: i64) -> Option<usize> {
         self.id_to_line.get(&todo_id).copied()
     }
 
+    /// Whether `ancestor` sits anywhere above `todo_id`, through completed and hidden
+    /// todos alike (every ancestor of a shown todo is loaded, see `filter::retain_with_ancestors`)
+    pub fn is_descendant_of(&self, todo_id: i64, ancestor: i64) -> bool {
+        let mut current = self.todos.get(&todo_id).and_then(|todo| todo.parent_id);
+        let mut depth = 0;
+        while let Some(id) = current
+            && depth <= self.todos.len() {
+            if id == ancestor {
+                return true;
+            }
+            current = self.todos.get(&id).and_then(|todo| todo.parent_id);
+            depth += 1;
+        }
+        false
+    }
+
+    /// Line of the parent of the todo on `line`, when the parent is shown
+    pub fn parent_line(&self, line: usize) -> Option<usize> {
+        let todo_id = self.rendered_lines.get(line)?.todo_id;
+        let parent_id = self.todos.get(&todo_id)?.parent_id?;
+        self.get_line_index_for_todo(parent_id)
+    }
+
+    /// Line of the next (or previous) todo with the same parent as the one on `line`
+    pub fn sibling_line(&self, line: usize, forward: bool) -> Option<usize> {
+        let todo_id = self.rendered_lines.get(line)?.todo_id;
+        let siblings = Self::siblings_of(&self.tree, todo_id)?;
+        let position = siblings.iter().position(|node| node.id == todo_id)?;
+        let sibling = if forward { siblings.get(position + 1)? } else { siblings.get(position.checked_sub(1)?)? };
+        self.get_line_index_for_todo(sibling.id)
+    }
+
+    /// The todos sharing `todo_id`'s parent, itself included, in the order shown
+    pub fn sibling_ids(&self, todo_id: i64) -> Vec<i64> {
+        Self::siblings_of(&self.tree, todo_id).map_or_else(Vec::new, |siblings| siblings.iter().map(|node| node.id).collect())
+    }
+
+    /// Where the selection goes should `todo_id` disappear from the tree: the siblings
+    /// after it, then the ones before it, nearest first, then its ancestors
+    pub fn selection_fallbacks(&self, todo_id: i64) -> Vec<i64> {
+        let siblings = self.sibling_ids(todo_id);
+        let position = siblings.iter().position(|id| *id == todo_id).unwrap_or(0);
+        let mut fallbacks: Vec<i64> = siblings[position.min(siblings.len())..].iter().skip(1).chain(siblings[..position].iter().rev()).copied().collect();
+        let mut current = self.todos.get(&todo_id).and_then(|todo| todo.parent_id);
+        while let Some(id) = current
+            && fallbacks.len() <= self.todos.len() {
+            fallbacks.push(id);
+            current = self.todos.get(&id).and_then(|todo| todo.parent_id);
+        }
+        fallbacks
+    }
+
+    /// Line of the next top-level todo after `line`, or going back, the top-level todo
+    /// `line` belongs to (the one before when already on it)
+    pub fn root_line(&self, line: usize, forward: bool) -> Option<usize> {
+        let mut roots = self.tree.iter().filter_map(|node| self.get_line_index_for_todo(node.id));
+        if forward {
+            roots.find(|&root| root > line)
+        } else {
+            roots.rev().find(|&root| root < line)
+        }
+    }
+
+    /// Where `moved` would show up once placed under `target` (None = top level), or
+    /// with `as_sibling`, right after `target`
+    pub fn move_preview(&self, moved: i64, target: Option<i64>, as_sibling: bool) -> Option<MovePreview> {
+        let (node, _) = Self::find_node(&self.tree, moved, 0)?;
+        let (siblings, depth, parent_line) = match target {
+            Some(target) => {
+                let (target_node, target_depth) = Self::find_node(&self.tree, target, 0)?;
+                if as_sibling {
+                    return Some(MovePreview { after_line: self.last_line_of(target_node), depth: target_depth });
+                }
+                let target_line = self.get_line_index_for_todo(target);
+                if !target_node.is_expanded {
+                    return Some(MovePreview { after_line: target_line, depth: target_depth + 1 });
+                }
+                (&target_node.children[..], target_depth + 1, target_line)
+            }
+            None => (&self.tree[..], 0, None),
+        };
+        // Re-parenting drops a manual position, so it lands among the default-ordered
+        // siblings, below the last one that sorts ahead of it
+        let before = siblings.iter()
+            .filter(|sibling| sibling.id != moved && !self.positions.contains_key(&sibling.id))
+            .take_while(|sibling| self.default_order(sibling, node) != std::cmp::Ordering::Greater)
+            .last();
+        let after_line = match before {
+            Some(sibling) => self.last_line_of(sibling),
+            None => parent_line,
+        };
+        Some(MovePreview { after_line, depth })
+    }
+
+    /// How many todos sit below `todo_id` in the tree, at any depth
+    pub fn descendant_count(&self, todo_id: i64) -> usize {
+        self.descendant_counts.get(&todo_id).copied().unwrap_or(0)
+    }
+
+    /// Fill `counts` with the size of each subtree below `nodes`; returns their total
+    fn count_descendants(nodes: &[TreeNode], counts: &mut HashMap<i64, usize>) -> usize {
+        nodes
+            .iter()
+            .map(|node| {
+                let below = Self::count_descendants(&node.children, counts);
+                if below > 0 {
+                    counts.insert(node.id, below);
+                }
+                1 + below
+            })
+            .sum()
+    }
+
+    /// The last rendered line of `node`'s subtree
+    fn last_line_of(&self, node: &TreeNode) -> Option<usize> {
+        match node.children.last() {
+            Some(child) if node.is_expanded => self.last_line_of(child),
+            _ => self.get_line_index_for_todo(node.id),
+        }
+    }
+
+    /// The node for `todo_id` and its depth below `nodes`
+    fn find_node(nodes: &[TreeNode], todo_id: i64, depth: usize) -> Option<(&TreeNode, usize)> {
+        nodes.iter().find_map(|node| {
+            if node.id == todo_id {
+                Some((node, depth))
+            } else {
+                Self::find_node(&node.children, todo_id, depth + 1)
+            }
+        })
+    }
+
+    /// The list of nodes `todo_id` is one of
+    fn siblings_of(nodes: &[TreeNode], todo_id: i64) -> Option<&[TreeNode]> {
+        if nodes.iter().any(|node| node.id == todo_id) {
+            return Some(nodes);
+        }
+        nodes.iter().find_map(|node| Self::siblings_of(&node.children, todo_id))
+    }
+
     pub fn update_todo_completion(&mut self, todo_id: i64, is_completed: bool) {
         if let Some(todo) = self.todos.get_mut(&todo_id) {
             if is_completed {
@@ -311,34 +594,33 @@ impl TodoTreeManager {
         self.check_and_auto_collapse_parent(todo_id);
 
         // Update only the affected line's display text (no tree rebuild needed)
-        if let Some(&line_idx) = self.id_to_line.get(&todo_id) {
+        if let Some(&line_idx) = self.id_to_line.get(&todo_id)
+            && let Some(todo) = self.todos.get(&todo_id) {
+            let status_icon = self.status_of(todo).icon();
+            let priority = Self::parse_priority(&todo.title);
+            let priority_str = Self::format_priority(priority);
+            let title_without_priority = Self::strip_priority_from_title(&todo.title);
+            let display_text = format!("{} {} {}{}", self.id_label(todo), status_icon, priority_str, title_without_priority);
             if let Some(line) = self.rendered_lines.get_mut(line_idx) {
-                if let Some(todo) = self.todos.get(&todo_id) {
-                    let status_icon = if todo.is_completed() { "[✓]" } else { "[ ]" };
-                    let priority = Self::parse_priority(&todo.title);
-                    let priority_str = Self::format_priority(priority);
-                    let title_without_priority = Self::strip_priority_from_title(&todo.title);
-                    line.display_text = format!("{} {} {}{}", todo.id_mod(), status_icon, priority_str, title_without_priority);
-                }
+                line.display_text = display_text;
             }
         }
     }
 
     fn check_and_auto_collapse_parent(&mut self, todo_id: i64) {
         // Find the parent of this todo
-        if let Some(todo) = self.todos.get(&todo_id) {
-            if let Some(parent_id) = todo.parent_id {
-                // Check if all siblings and their entire subtrees are completed
-                // Using the same pattern as has_incomplete_descendants but for completion
-                let all_subtrees_completed = !self.has_incomplete_children(parent_id);
-
-                if all_subtrees_completed {
-                    // Auto-collapse the parent
-                    self.expansion_states.insert(parent_id, false);
-
-                    // Recursively check the parent's parent
-                    self.check_and_auto_collapse_parent(parent_id);
-                }
+        if let Some(todo) = self.todos.get(&todo_id)
+            && let Some(parent_id) = todo.parent_id {
+            // Check if all siblings and their entire subtrees are completed
+            // Using the same pattern as has_incomplete_descendants but for completion
+            let all_subtrees_completed = !self.has_incomplete_children(parent_id);
+
+            if all_subtrees_completed {
+                // Auto-collapse the parent
+                self.expansion_states.insert(parent_id, false);
+
+                // Recursively check the parent's parent
+                self.check_and_auto_collapse_parent(parent_id);
             }
         }
     }
@@ -397,6 +679,31 @@ impl TodoTreeManager {
         self.todos.values().any(|todo| todo.parent_id == Some(target_id))
     }
     
+    /// Expand every collapsed ancestor of `todo_ids` with a single rebuild; returns the
+    /// nodes opened
+    pub fn expand_paths_to(&mut self, todo_ids: &[i64]) -> Vec<i64> {
+        let mut opened_nodes = Vec::new();
+        for &todo_id in todo_ids {
+            let mut current = self.todos.get(&todo_id).and_then(|todo| todo.parent_id);
+            while let Some(parent_id) = current
+                && !self.expansion_states.get(&parent_id).copied().unwrap_or(false) {
+                self.expansion_states.insert(parent_id, true);
+                opened_nodes.push(parent_id);
+                current = self.todos.get(&parent_id).and_then(|todo| todo.parent_id);
+            }
+        }
+        if !opened_nodes.is_empty() {
+            self.tree = self.build_tree();
+            self.rendered_lines = self.render_tree();
+            self.id_to_line = self.rendered_lines
+                .iter()
+                .enumerate()
+                .map(|(idx, line)| (line.todo_id, idx))
+                .collect();
+        }
+        opened_nodes
+    }
+
     pub fn expand_path_to_todo(&mut self, todo_id: i64) -> Vec<i64> {
         let mut opened_nodes = Vec::new();
         
@@ -435,4 +742,48 @@ impl TodoTreeManager {
         
         opened_nodes
     }
-}
\ No newline at end of file
+}
+
+/// Order parent search results: open todos first, then those nearest `anchor` in the tree
+/// (the todo highlighted when the Create form opened), then the most recently created.
+/// Without an anchor, shallower todos come first. `exclude_subtree` drops a todo being
+/// re-parented and its descendants, none of which can become its parent.
+pub fn rank_parent_candidates(candidates: Vec<Todo>, all_todos: &[Todo], anchor: Option<i64>, exclude_subtree: Option<i64>) -> Vec<Todo> {
+    let parents: HashMap<i64, Option<i64>> = all_todos.iter().map(|todo| (todo.id, todo.parent_id)).collect();
+    // A todo followed by its ancestors up to the root
+    let ancestry = |id: i64| -> Vec<i64> {
+        let mut chain = vec![id];
+        let mut current = id;
+        while let Some(&Some(parent)) = parents.get(&current) {
+            if chain.contains(&parent) {
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        chain
+    };
+    let anchor_chain = anchor.map(ancestry).unwrap_or_default();
+    // Steps up from the candidate to the closest common ancestor, then down to the anchor;
+    // todos in different top-level trees meet at a virtual root above them
+    let distance = |chain: &[i64]| -> usize {
+        chain
+            .iter()
+            .enumerate()
+            .find_map(|(up, id)| anchor_chain.iter().position(|a| a == id).map(|down| up + down))
+            .unwrap_or(chain.len() + anchor_chain.len())
+    };
+
+    let mut ranked: Vec<(bool, usize, Todo)> = candidates
+        .into_iter()
+        .filter_map(|todo| {
+            let chain = ancestry(todo.id);
+            if exclude_subtree.is_some_and(|root| chain.contains(&root)) {
+                return None;
+            }
+            Some((todo.is_completed(), distance(&chain), todo))
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(b.2.created_at.cmp(&a.2.created_at)));
+    ranked.into_iter().map(|(_, _, todo)| todo).collect()
+}
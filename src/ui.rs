@@ -1,32 +1,421 @@
-use crate::database::{Database, NewTodo, Todo};
-use crate::tree::TodoTreeManager;
+use crate::database::{Attachment, CompletedFilter, Database, NewTodo, SavedFilter, SavedTemplate, Todo, TodoEvent};
+use crate::tree::{self, ProjectStats, SubtreeSummary, TodoTreeManager};
 use crate::colors::CatppuccinFrappe;
-use chrono::{Local, Utc, DateTime, Duration};
+use crate::config::{CascadeRule, Column, Config, IdDisplay, ParentRule, WideLayout};
+use crate::agenda;
+use crate::capture;
+use crate::waiting::{self, Waiting};
+use crate::context::{self, Energy, Labels};
+use crate::clipboard;
+use crate::dates;
+use crate::editor;
+use crate::filter::{self, Filter};
+use crate::flag::Flag;
+use crate::history::{self, HistoryEntry};
+use crate::hooks::HookRunner;
+use crate::import::{self, ImportSource};
+use crate::metrics::{self, Session};
+use crate::opener;
+use crate::passphrase;
+use crate::pomodoro::{self, Phase, Pomodoro, Transition};
+use crate::report::{self, CompletedRange};
+use crate::review::{self, ReviewItem};
+use crate::status::Status;
+use crate::sync::{self, SyncReport};
+use crate::tags;
+use crate::template;
+use crate::text;
+use chrono::{Local, NaiveDate, Utc, DateTime};
 use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
+        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
     },
     Frame,
 };
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppMode {
     List,
     CompletedView,
+    ScheduledView,
+    Someday,
+    Waiting,
+    Today,
+    DelegatePrompt,
     Create,
     ConfirmDelete,
+    ConfirmCascade,
     ListFind,
     TreeSearch,
     ParentSearch,
     Move,
     Help,
     IdModGoto,
+    Locked,
+    FilterPicker,
+    FilterPrompt,
+    DeferPrompt,
+    NotePrompt,
+    QuickEdit,
+    Attachments,
+    Onboarding,
+    Capture,
+    QuickAdd,
+    TemplatePicker,
+    CopyMenu,
+    ProfilePicker,
+    Stats,
+    Review,
+    Agenda,
+    History,
+    ContextPicker,
 }
 
+/// The one field a quick edit prompt changes, without opening $EDITOR
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuickEditField {
+    Title,
+    Due,
+    Tags,
+    Estimate,
+    Flag,
+}
+
+/// Which metadata columns a view shows after each todo's title
+#[derive(Debug, Clone, Copy)]
+struct MetadataColumns {
+    created: bool,
+    due: bool,
+    completed: bool,
+    parent: bool,
+    start: bool,
+    /// Only shown when `[columns] show` asks for them
+    priority: bool,
+    tags: bool,
+}
+
+/// The views whose metadata density is set (and remembered) separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataView {
+    Tree,
+    List,
+    Completed,
+    Scheduled,
+    Someday,
+    Waiting,
+    Today,
+    Find,
+}
+
+impl MetadataView {
+    const ALL: [Self; 8] = [Self::Tree, Self::List, Self::Completed, Self::Scheduled, Self::Someday, Self::Waiting, Self::Today, Self::Find];
+
+    fn state_key(self) -> &'static str {
+        match self {
+            Self::Tree => "metadata_density.tree",
+            Self::List => "metadata_density.list",
+            Self::Completed => "metadata_density.completed",
+            Self::Scheduled => "metadata_density.scheduled",
+            Self::Someday => "metadata_density.someday",
+            Self::Waiting => "metadata_density.waiting",
+            Self::Today => "metadata_density.today",
+            Self::Find => "metadata_density.find",
+        }
+    }
+
+    /// Every column the view shows at full density
+    fn columns(self) -> MetadataColumns {
+        match self {
+            // The tree already shows parents through indentation
+            Self::Tree => MetadataColumns { created: true, due: true, completed: false, parent: false, start: false, priority: false, tags: false },
+            Self::List | Self::Someday | Self::Waiting | Self::Today => MetadataColumns { created: true, due: true, completed: false, parent: true, start: false, priority: false, tags: false },
+            Self::Completed | Self::Find => MetadataColumns { created: true, due: true, completed: true, parent: true, start: false, priority: false, tags: false },
+            Self::Scheduled => MetadataColumns { created: true, due: true, completed: false, parent: true, start: true, priority: false, tags: false },
+        }
+    }
+}
+
+/// The top-level views: the registry behind the tab bar and the `[views]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewKind {
+    Tree,
+    List,
+    Completed,
+    Scheduled,
+    Someday,
+    Waiting,
+    Today,
+}
+
+impl ViewKind {
+    const ALL: [Self; 7] = [Self::Tree, Self::List, Self::Today, Self::Completed, Self::Scheduled, Self::Someday, Self::Waiting];
+
+    /// How the view is named in the config file
+    fn name(self) -> &'static str {
+        match self {
+            Self::Tree => "tree",
+            Self::List => "list",
+            Self::Completed => "completed",
+            Self::Scheduled => "scheduled",
+            Self::Someday => "someday",
+            Self::Waiting => "waiting",
+            Self::Today => "today",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|view| view.name() == name)
+    }
+
+    fn default_title(self) -> &'static str {
+        match self {
+            Self::Tree => "Todo Tree View",
+            Self::List => "Incomplete Todos",
+            Self::Completed => "All Completed Todos",
+            Self::Scheduled => "Scheduled",
+            Self::Someday => "Someday/Maybe",
+            Self::Waiting => "Waiting For",
+            Self::Today => "Today",
+        }
+    }
+
+    fn default_icon(self) -> &'static str {
+        match self {
+            Self::Tree => "🌳",
+            Self::List => "📋",
+            Self::Completed => "✅",
+            Self::Scheduled => "⏳",
+            Self::Someday => "💭",
+            Self::Waiting => "🤝",
+            Self::Today => "📅",
+        }
+    }
+}
+
+/// The help page: section headings followed by indented keybinding entries. `{statuses}`,
+/// `{contexts}` and `{pomodoro}` are filled in from the config.
+const HELP_LINES: &[&str] = &[
+    "NAVIGATION",
+    "  j/k or ↑/↓      Navigate todos",
+    "  Ctrl+d/Ctrl+u   Half-page scroll down/up",
+    "  gg / G          Jump to the first / last todo",
+    "  zz              Scroll the selected todo to the middle of the view",
+    "  h/l or ←/→      Navigate hierarchy levels",
+    "  u               Jump to the parent (tree view)",
+    "  } / {           Next / previous sibling (tree view)",
+    "  ] / [           Next / previous top-level todo (tree view)",
+    "  t               Expand/Collapse tree nodes",
+    "  v               Hoist: show only the selected subtree (Esc unhoists)",
+    "  Tab/Shift+Tab   Switch between views in the tab bar",
+    "  i               Cycle metadata columns: full / due only / none (per view)",
+    "",
+    "ACTIONS",
+    "  Space           Next status ({statuses}); Shift+Space goes back",
+    "  X               Cancel the todo (kept, but not counted as done), or undo that",
+    "  Enter           View/Edit todo in $EDITOR",
+    "  n / A           Create new todo under the selected one (a child; a is help here)",
+    "  O               Create new todo next to the selected one (a sibling; o is attachments)",
+    "  Ctrl+S          Save the Create form (←/→/Home/End move the cursor; Enter adds a description line)",
+    "  N               Quick capture: add todos line by line (!p1 @tag due:2d start:1w ^WEB-3; next)",
+    "  d               Delete selected todo",
+    "  m               Move todo (tree view only; Tab places it after the target instead of under it)",
+    "  M               Move todo to the last move target",
+    "  y / p / P       Yank subtree, paste a copy under / next to selected (tree view)",
+    "  c               Show/hide completed todos (there t: today/week/month, p: this project only)",
+    "  D               Defer: hide the todo and its subtasks until a start date",
+    "  Z               Scheduled view: deferred todos by start date (s starts one now)",
+    "  > / U           Delegate: who it's waiting on and when to follow up / Waiting view",
+    "  b               Pin the todo above the tree and list, or unpin it",
+    "  + / =           Add the todo to today's plan, or take it out / Today view",
+    "  B / L           Set the todo aside as someday/maybe (B again promotes it) / Someday view",
+    "  J               Add a timestamped note to the selected todo's journal",
+    "  w               Start a pomodoro on the selected todo ({pomodoro}), or stop it",
+    "  r / e / #       Quick edit: rename, set the due date, set the tags",
+    "  @ / %           Cycle the context ({contexts}) / energy (low, medium, high)",
+    "  E               Estimate how long the selected todo will take (45m, 2h, 1h30m)",
+    "  !               Flag the todo with a color (overrides the due-date color) or an emoji",
+    "  h               Toggle hidden status (tree view only)",
+    "  H               Toggle showing/hiding hidden todos (tree view only)",
+    "",
+    "SEARCH & MODES",
+    "  /               Tree search with live highlighting",
+    "  f               List search (flat view)",
+    "  1 / 2 / 3       Filter to todos due today / this week / overdue (again to clear)",
+    "  F               Saved filters / smart views",
+    "  Ctrl+P          Switch database profile",
+    "  Ctrl+N          Quick add a todo from any view (due:2d #tag !p1 >parent)",
+    "  Y               Copy title, markdown or subtree to the clipboard",
+    "  I               Add the markdown list on the clipboard as subtasks of selected",
+    "  S               Stats by project (Enter drills into the selected one)",
+    "  R               Review overdue, stale and top-level todos one at a time",
+    "  C               What can I do now? Filter by context (1-9) and energy (l/m/h)",
+    "  W               Agenda: open todos by due day, warning when a day is overbooked",
+    "  V               History of the selected todo's changes; a there shows all activity",
+    "  T               Templates: save selected subtree, create from a template",
+    "  o               Attachments of selected todo (open with xdg-open)",
+    "  :               Filter prompt (due<7d status:open parent:12 /regex/; id 1234 goes to a todo)",
+    "  g               Goto ID mode (tree view only; gg jumps to the top)",
+    "  n/N             Navigate search matches (in search/goto mode)",
+    "",
+    "GENERAL",
+    "  a               Show/hide this help page (j/k scroll, / searches)",
+    "  q               Quit application",
+    "  Ctrl+Z          Suspend to the shell (fg returns)",
+    "  Esc             Cancel current operation",
+    "",
+    "COMMAND LINE",
+    "  tododb list [expression]              Print todos matching a filter",
+    "  tododb export <file> [options]        Write a read-only snapshot (.db or .json)",
+    "  tododb export --html <dir>            Write browsable static pages",
+    "  tododb export --csv [--columns a,b]   Write todos as CSV for spreadsheets",
+    "  tododb export --org                   Write todos as an Emacs org outline",
+    "  tododb import <source> <file>         Import todos from another tool",
+    "  tododb template list|save|use|delete  Manage subtree templates",
+    "  tododb verify                         Check the completion log",
+    "  tododb doctor [--fix]                 Find (and repair) broken dates",
+    "  tododb prefix [<todo> <PREFIX>]       Number a project's todos WEB-1, WEB-2, ...",
+];
+
+/// The help lines matching `query` (case-insensitive), each under its section heading.
+/// A query matching a heading keeps the whole section.
+pub fn filter_help_lines<'a>(lines: &[&'a str], query: &str) -> Vec<&'a str> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return lines.to_vec();
+    }
+
+    let mut result = Vec::new();
+    for section in lines.split(|line| line.is_empty()) {
+        let Some((heading, entries)) = section.split_first() else { continue };
+        let matches: Vec<&str> = if heading.to_lowercase().contains(&query) {
+            entries.to_vec()
+        } else {
+            entries.iter().copied().filter(|entry| entry.to_lowercase().contains(&query)).collect()
+        };
+        if !matches.is_empty() {
+            if !result.is_empty() {
+                result.push("");
+            }
+            result.push(*heading);
+            result.extend(matches);
+        }
+    }
+    result
+}
+
+/// View state kept for a profile's database while another profile is open
+#[derive(Debug, Clone)]
+struct ProfileState {
+    expansion_states: HashMap<i64, bool>,
+    tree_selected: Option<usize>,
+    list_selected: Option<usize>,
+    completed_selected: Option<usize>,
+    scheduled_selected: Option<usize>,
+    use_tree_view: bool,
+    view: ViewKind,
+    show_hidden_items: bool,
+    active_filter: Option<(String, Filter)>,
+    hoisted: Option<i64>,
+}
+
+/// How the views were left, kept in the database's `ui_state` table between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedViewState {
+    view: Option<String>,
+    /// Tree or list, what the completed and scheduled views return to
+    use_tree_view: bool,
+    show_hidden_items: bool,
+    selected: Option<i64>,
+    expansion_states: HashMap<i64, bool>,
+    hoisted: Option<i64>,
+}
+
+const VIEW_STATE_KEY: &str = "view_state";
+
+/// A completion change waiting on the `[parents]` cascade question: the todo, whether
+/// it is being completed, and how many subtasks (or parents) would change with it
+#[derive(Debug, Clone, Copy)]
+struct PendingCascade {
+    todo_id: i64,
+    complete: bool,
+    count: usize,
+    /// Whether changing just the todo is offered (not under `open_children = "prevent"`)
+    single: bool,
+    /// The status that asked for the change (Space, X), given to the todo once it goes through
+    status: Option<Status>,
+}
+
+/// How much of a view's metadata is shown; cycled with `i` for small terminals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataDensity {
+    #[default]
+    Full,
+    DueOnly,
+    None,
+}
+
+impl MetadataDensity {
+    fn next(self) -> Self {
+        match self {
+            Self::Full => Self::DueOnly,
+            Self::DueOnly => Self::None,
+            Self::None => Self::Full,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::DueOnly => "due",
+            Self::None => "none",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [Self::Full, Self::DueOnly, Self::None].into_iter().find(|density| density.as_str() == value)
+    }
+
+    fn apply(self, columns: MetadataColumns) -> MetadataColumns {
+        match self {
+            Self::Full => columns,
+            // The scheduled view is about when todos start, so its start date stays too
+            Self::DueOnly => MetadataColumns { created: false, due: true, completed: false, parent: false, start: columns.start, priority: false, tags: false },
+            Self::None => MetadataColumns { created: false, due: false, completed: false, parent: false, start: false, priority: false, tags: false },
+        }
+    }
+}
+
+const RELATIVE_DUE_WIDTH: usize = 11; // "12d overdue"
+const COMPACT_RELATIVE_DUE_WIDTH: usize = 4; // "-13d"
+const PRIORITY_COLUMN_WIDTH: usize = 3; // "P10"
+const MIN_TITLE_WIDTH: usize = 16;
+
+/// Below this many columns rows drop the time of day and the parent column,
+/// popups take the full width and the Create form stacks its fields
+const NARROW_WIDTH: u16 = 80;
+/// Popups never shrink below this many rows, so their wrapped text stays visible
+const MIN_POPUP_HEIGHT: u16 = 8;
+const STATS_PANEL_WIDTH: u16 = 32;
+/// Completed todos are loaded this many at a time, more as the view scrolls down
+const COMPLETED_PAGE_SIZE: usize = 200;
+/// Most recorded changes the history (V) loads at once
+const HISTORY_LIMIT: usize = 500;
+/// Most pinned todos shown above the tree and list; the title counts the rest
+const MAX_PINNED_ROWS: usize = 5;
+/// One-key due filters: key, the name shown in the title and the filter expression
+const QUICK_FILTERS: [(char, &str, &str); 3] = [('1', "Due today", "due:today"), ('2', "Due this week", "due:week"), ('3', "Overdue", "due:overdue")];
+/// Rows kept between the selection and the top or bottom edge while scrolling
+const SCROLL_MARGIN: usize = 3;
+/// Weeks of session metrics shown under the projects in the stats view
+const TREND_WEEKS: usize = 4;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CreateFieldFocus {
@@ -40,11 +429,37 @@ pub enum CreateFieldFocus {
 pub struct App {
     pub database: Database,
     pub incomplete_todos: Vec<Todo>,
+    /// The loaded pages of completed todos, most recently completed first
     pub completed_todos: Vec<Todo>,
+    /// How many completed todos there are in all, loaded or not
+    pub completed_total: usize,
+    /// How far back the completed view goes (`t` cycles it)
+    pub completed_range: CompletedRange,
+    /// The project the completed view is narrowed to (`p`), with its title
+    pub completed_root: Option<(i64, String)>,
+    /// Deferred todos (see `Todo::is_deferred`), soonest start first
+    pub scheduled_todos: Vec<Todo>,
+    /// Todos set aside as someday/maybe; they and their subtasks stay out of the tree and list
+    pub someday_todos: Vec<Todo>,
+    /// Open pinned todos, soonest due first, shown above the tree and list
+    pub pinned_todos: Vec<Todo>,
+    /// Open delegated todos, soonest follow-up first
+    pub waiting_todos: Vec<Todo>,
+    /// Today's plan, done or not, in the order the todos were added (see `daily_plan`)
+    pub today_todos: Vec<Todo>,
+    /// Unfinished todos of the last earlier plan, offered while today's is still empty
+    pub carry_over: Option<(NaiveDate, Vec<Todo>)>,
     pub tree_manager: TodoTreeManager,
     pub list_state: ListState,
     pub tree_list_state: ListState,
     pub completed_list_state: ListState,
+    /// The completed view as drawn, day headers included; `completed_list_state` indexes
+    /// `completed_todos` only
+    completed_rows_state: ListState,
+    pub scheduled_list_state: ListState,
+    pub someday_list_state: ListState,
+    pub waiting_list_state: ListState,
+    pub today_list_state: ListState,
     pub mode: AppMode,
     pub previous_mode: AppMode,
     pub input_title: String,
@@ -53,7 +468,19 @@ pub struct App {
     pub input_due_date_absolute: String,
     pub current_parent: Option<i64>,
     pub should_quit: bool,
+    /// Rows the current view showed when last drawn, for half-page jumps and zz
+    viewport_rows: usize,
+    /// First key of a two-key jump (gg, zz) waiting for its second
+    pending_prefix: Option<char>,
+    /// Ctrl+Z was pressed; the main loop stops the app like a shell job
+    pub suspend_pending: bool,
+    /// This run's usage metrics, saved as they change (None with `[metrics] enabled = false`)
+    pub session: Option<Session>,
+    /// Changes made through `database`, handled by `dispatch_changes`
+    changes: std::sync::mpsc::Receiver<TodoEvent>,
     pub error_message: Option<String>,
+    /// Confirmation of a completed action, shown above the help bar until the next key
+    pub status_message: Option<String>,
     pub search_query: String,
     pub search_results: Vec<Todo>,
     pub search_list_state: ListState,
@@ -64,10 +491,18 @@ pub struct App {
     pub input_parent: String,
     pub selected_parent_id: Option<i64>,
     pub create_field_focus: CreateFieldFocus,
+    /// Byte offset of the cursor in the focused Create form field
+    pub create_cursor: usize,
     pub use_tree_view: bool,
     pub search_input_mode: bool,
     pub move_todo_id: Option<i64>,
+    /// Move mode places the todo right after the target (Tab) rather than under it
+    pub move_as_sibling: bool,
+    /// Parent chosen in the last move (None = top level), reused by M
+    pub last_move_parent: Option<Option<i64>>,
     pub editor_pending: Option<Todo>,
+    /// Private directory holding this session's editor files, created on first use
+    editor_session_dir: Option<std::path::PathBuf>,
     pub show_hidden_items: bool,
     pub goto_query: String,
     pub goto_matches: Vec<i64>,
@@ -75,49 +510,132 @@ pub struct App {
     pub list_scrollbar_state: ScrollbarState,
     pub tree_scrollbar_state: ScrollbarState,
     pub completed_scrollbar_state: ScrollbarState,
+    pub scheduled_scrollbar_state: ScrollbarState,
+    pub someday_scrollbar_state: ScrollbarState,
+    pub waiting_scrollbar_state: ScrollbarState,
+    pub today_scrollbar_state: ScrollbarState,
+    pub config: Config,
+    pub last_activity: std::time::Instant,
+    /// Background sync in flight, and when the next one starts (see `sync`)
+    sync_job: Option<std::thread::JoinHandle<anyhow::Result<SyncReport>>>,
+    next_sync: Option<std::time::Instant>,
+    /// `[[hooks]]` commands and requests still running (see `hooks`)
+    hook_runner: HookRunner,
+    /// The status message on screen and when it appeared, for `tick` to clear it
+    status_shown: Option<(String, std::time::Instant)>,
+    /// When the next periodic checkpoint is due, and whether anything changed since the last one
+    next_checkpoint: Option<std::time::Instant>,
+    unflushed_changes: bool,
+    pub lock_input: String,
+    pub pre_lock_mode: AppMode,
+    pub saved_filters: Vec<SavedFilter>,
+    pub filter_list_state: ListState,
+    pub filter_input: String,
+    pub defer_input: String,
+    pub note_input: String,
+    pub quick_edit_field: QuickEditField,
+    pub quick_edit_input: String,
+    pub active_filter: Option<(String, Filter)>,
+    /// Todos in the filtered tree only as ancestors of matches, drawn dimmed
+    pub filter_context: HashSet<i64>,
+    /// Tree nodes opened to reveal the active filter's matches, folded back when it changes
+    filter_opened: Vec<i64>,
+    pub attachment_counts: HashMap<i64, usize>,
+    pub note_counts: HashMap<i64, usize>,
+    /// Days since each stale open todo last changed (see `[review] stale_days`)
+    pub stale_ages: HashMap<i64, i64>,
+    pub pomodoro_counts: HashMap<i64, usize>,
+    /// Estimated minutes of work per todo, for todos that have an estimate
+    pub estimates: HashMap<i64, i64>,
+    pub labels: HashMap<i64, Labels>,
+    /// Who each delegated todo is waiting on (see `[waiting]`)
+    pub waiting: HashMap<i64, Waiting>,
+    pub delegate_input: String,
+    /// What the context filter (C) asks for: todos in this context and/or taking this energy
+    pub now_context: Option<String>,
+    pub now_energy: Option<Energy>,
+    /// How far the agenda (W) is scrolled down
+    pub agenda_scroll: u16,
+    /// Whose changes the history (V) shows; None for everyone's
+    pub history_todo: Option<i64>,
+    pub history_entries: Vec<HistoryEntry>,
+    pub history_scroll: u16,
+    /// The running pomodoro cycle, counting down in the footer
+    pub pomodoro: Option<Pomodoro>,
+    pub attachments: Vec<Attachment>,
+    pub attachment_list_state: ListState,
+    pub attachment_todo: Option<(i64, String)>,
+    pub attachment_input: String,
+    pub import_sources: Vec<(ImportSource, bool)>,
+    pub import_list_state: ListState,
+    pub metadata_density: HashMap<MetadataView, MetadataDensity>,
+    pub capture_input: String,
+    pub capture_parent: Option<(i64, String)>,
+    pub capture_count: usize,
+    /// Number of todos in the capture input waiting for Enter to confirm adding them all
+    pub capture_confirm: Option<usize>,
+    /// The one-line quick add overlay (Ctrl+N), and the todos its `>search` picks a parent from
+    pub quick_add_input: String,
+    quick_add_todos: Vec<Todo>,
+    pub tree_column_offset: usize,
+    pub saved_templates: Vec<SavedTemplate>,
+    pub template_list_state: ListState,
+    pub template_input: String,
+    pub template_target: Option<(i64, String)>,
+    pub yanked_todo: Option<(i64, String)>,
+    /// Tags in use with their todo counts, most used first; loaded when Create or quick capture opens
+    pub known_tags: Vec<(String, usize)>,
+    pub tag_completions: Vec<(String, usize)>,
+    pub tag_completion_state: ListState,
+    pub help_query: String,
+    pub help_search_input: bool,
+    /// First help line shown; clamped to the end of the page when it's drawn
+    pub help_scroll: usize,
+    help_height: usize,
+    pub tabs: Vec<ViewKind>,
+    pub current_profile: Option<String>,
+    pub profile_list_state: ListState,
+    /// Project the stats view has drilled into; None lists the root projects
+    pub stats_parent: Option<i64>,
+    pub stats_list_state: ListState,
+    /// Todos the review (R) walks through, one at a time
+    pub review_queue: Vec<ReviewItem>,
+    pub review_index: usize,
+    /// The review's current todo is waiting for y to confirm its deletion
+    pub review_confirm_delete: bool,
+    /// Todos kept, snoozed, completed, deleted or hidden in this review
+    pub review_handled: usize,
+    profile_states: HashMap<String, ProfileState>,
+    cascade_pending: Option<PendingCascade>,
 }
 
 impl App {
-    fn create_markdown_file(&self, todo: &Todo) -> Result<std::path::PathBuf, String> {
+    fn create_markdown_file(&mut self, todo: &Todo) -> Result<std::path::PathBuf, String> {
         use std::fs;
-        use std::path::Path;
-        
-        // Create markdowns directory if it doesn't exist
-        let markdowns_dir = Path::new("markdowns");
-        if !markdowns_dir.exists() {
-            fs::create_dir_all(markdowns_dir)
-                .map_err(|e| format!("Failed to create markdowns directory: {}", e))?;
-        }
-        
-        // Create sanitized filename from id and title
-        let sanitized_title = todo.title
-            .chars()
-            .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
-            .collect::<String>()
-            .replace(' ', "_")
-            .chars()
-            .take(50) // Limit length
-            .collect::<String>();
+
+        let markdowns_dir = match self.config.editor_dir() {
+            Some(dir) => {
+                fs::create_dir_all(&dir)
+                    .map_err(|e| format!("Failed to create editor directory {}: {}", dir.display(), e))?;
+                dir
+            }
+            None => match &self.editor_session_dir {
+                Some(dir) => dir.clone(),
+                None => {
+                    let dir = editor::create_session_dir()
+                        .map_err(|e| format!("Failed to create editor directory: {}", e))?;
+                    self.editor_session_dir = Some(dir.clone());
+                    dir
+                }
+            },
+        };
         
-        let filename = format!("{}_{}.md", todo.id, sanitized_title);
+        let filename = text::file_name(todo.id, &todo.title, "md");
         let file_path = markdowns_dir.join(&filename);
         
-        // Format todo as markdown
-        let due_date_text = if let Some(due_by) = todo.due_by {
-            due_by.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
-        } else {
-            "Not set".to_string()
-        };
-
-        let markdown_content = format!(
-            "# {}\n\n## Due Date\n{}\n\n## Description\n{}\n\n## Metadata\n- **ID:** {}\n- **Status:** {}\n- **Created:** {} UTC\n",
-            todo.title,
-            due_date_text,
-            if todo.description.trim().is_empty() { "(No description)" } else { &todo.description },
-            todo.id,
-            if todo.is_completed() { "✓ Completed" } else { "○ Incomplete" },
-            todo.created_at.format("%Y-%m-%d %H:%M:%S")
-        );
+        let notes = self.database.get_notes(todo.id)
+            .map_err(|e| format!("Failed to read notes: {}", e))?;
+        let markdown_content = editor::todo_to_markdown(todo, &notes);
         
         // Write markdown file
         fs::write(&file_path, &markdown_content)
@@ -125,6 +643,13 @@ impl App {
         
         Ok(file_path)
     }
+
+    /// Delete this session's editor directory if every file in it was saved
+    pub fn remove_editor_files(&mut self) {
+        if let Some(dir) = self.editor_session_dir.take() {
+            editor::remove_session_dir(&dir);
+        }
+    }
     
     fn get_editor_command(&self) -> String {
         std::env::var("EDITOR")
@@ -141,18 +666,34 @@ impl App {
             })
     }
 
+    /// Write the WAL into the database file and refresh the mirror, for when the app
+    /// might not get to exit cleanly
+    pub fn flush(&mut self) {
+        self.save_session();
+        if let Err(e) = self.save_view_state() {
+            self.error_message = Some(format!("View state not saved: {}", e));
+        }
+        if let Err(e) = self.database.checkpoint_and_close() {
+            self.error_message = Some(format!("Checkpoint failed: {}", e));
+        }
+    }
+
     pub fn launch_editor<B: ratatui::backend::Backend + std::io::Write>(&mut self, todo: &Todo, terminal: &mut ratatui::Terminal<B>) -> Result<(), String> {
         use std::process::Command;
         use crossterm::{
             execute,
             terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-            event::{DisableMouseCapture, EnableMouseCapture},
+            event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
         };
         
         // Create the markdown file
         let file_path = self.create_markdown_file(todo)?;
-        
-        
+
+        // The editor may be left open for good (a closed SSH session, a killed terminal)
+        if self.config.checkpoint.on_editor {
+            self.flush();
+        }
+
         // Suspend TUI - restore terminal to normal mode
         disable_raw_mode()
             .map_err(|e| format!("Failed to disable raw mode: {}", e))?;
@@ -160,7 +701,8 @@ impl App {
         execute!(
             terminal.backend_mut(),
             LeaveAlternateScreen,
-            DisableMouseCapture
+            DisableMouseCapture,
+            DisableBracketedPaste
         ).map_err(|e| format!("Failed to leave alternate screen: {}", e))?;
         
         terminal.show_cursor()
@@ -185,7 +727,8 @@ impl App {
         execute!(
             terminal.backend_mut(),
             EnterAlternateScreen,
-            EnableMouseCapture
+            EnableMouseCapture,
+            EnableBracketedPaste
         ).map_err(|e| format!("Failed to enter alternate screen: {}", e))?;
         
         // Force a full redraw
@@ -193,16 +736,20 @@ impl App {
             .map_err(|e| format!("Failed to clear terminal: {}", e))?;
         
         if !status.success() {
+            // Nothing is read back from an editor that failed, so its file is of no use
+            if !self.config.editor.keep_files {
+                let _ = std::fs::remove_file(&file_path);
+            }
             return Err(format!("Editor '{}' exited with error", editor_cmd));
         }
         
         // Read back the edited content and update database
         if let Ok(edited_content) = std::fs::read_to_string(&file_path) {
-            match self.parse_markdown(&edited_content) {
+            match editor::parse_todo_markdown(&edited_content, todo, self.config.dates.due_time()) {
                 Ok((new_title, new_description, new_due_date)) => {
                     if new_title != todo.title || new_description != todo.description || new_due_date != todo.due_by {
                         if let Err(e) = self.database.update_todo(todo.id, new_title, new_description, new_due_date) {
-                            return Err(format!("Failed to update todo: {}", e));
+                            return Err(format!("Failed to update todo: {} (your edit is kept in {})", e, file_path.display()));
                         } else {
                             // Force a checkpoint to ensure changes are written to disk immediately
                             let _ = self.database.checkpoint();
@@ -211,132 +758,44 @@ impl App {
                     }
                 }
                 Err(e) => {
-                    // Show error message to user about parsing failure
-                    return Err(e);
+                    // Show error message to user about parsing failure; the file stays so the edit isn't lost
+                    return Err(format!("{} (your edit is kept in {})", e, file_path.display()));
                 }
             }
         }
+
+        if !self.config.editor.keep_files {
+            let _ = std::fs::remove_file(&file_path);
+        }
         
         Ok(())
     }
     
-    fn parse_markdown(&self, content: &str) -> Result<(String, String, Option<DateTime<Utc>>), String> {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut title = String::new();
-        let mut description = String::new();
-        let mut due_date = None;
-
-        let mut in_description = false;
-        let mut in_due_date = false;
-
-        for line in lines {
-            if line.starts_with("# ") && title.is_empty() {
-                title = line[2..].trim().to_string();
-            } else if line.starts_with("## Due Date") {
-                in_due_date = true;
-                in_description = false;
-            } else if line.starts_with("## Description") {
-                in_description = true;
-                in_due_date = false;
-            } else if line.starts_with("## Metadata") {
-                // Stop collecting description when we hit the metadata section
-                in_description = false;
-                in_due_date = false;
-            } else if in_due_date && !line.trim().is_empty() {
-                // Parse due date from the line
-                let date_str = line.trim();
-                if date_str != "Not set" {
-                    due_date = Self::parse_due_date(date_str);
-                    // If parsing failed and it wasn't "Not set", return error
-                    if due_date.is_none() {
-                        return Err(format!("Invalid due date format: '{}'. Expected format: 'YYYY-MM-DD HH:MM', '2d', '1w', etc., or 'Not set'", date_str));
-                    }
-                }
-                in_due_date = false; // Only parse first non-empty line
-            } else if in_description {
-                // Collect all lines in the description section, including empty lines and headers
-                if !description.is_empty() {
-                    description.push('\n');
-                }
-                if line.trim() != "(No description)" {
-                    description.push_str(line);
-                } else {
-                    // Don't add the "(No description)" placeholder
-                    description.pop(); // Remove the newline we just added
-                }
-            }
-        }
-
-        Ok((title, description.trim().to_string(), due_date))
-    }
-
-    fn parse_due_date(input: &str) -> Option<DateTime<Utc>> {
-        let input = input.trim();
-        if input.is_empty() {
-            return None;
-        }
-
-        // Try relative date parsing first (e.g., "2d", "1w", "3h", "30m")
-        if let Some(duration) = Self::parse_relative_duration(input) {
-            return Some(Utc::now() + duration);
-        }
-
-        // Try absolute date parsing
-        // Format: "YYYY-MM-DD" or "YYYY-MM-DD HH:MM"
-        if let Ok(dt) = chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d") {
-            // Parse date only, set time to end of day (23:59:59)
-            let naive_datetime = dt.and_hms_opt(23, 59, 59)?;
-            return Some(DateTime::<Utc>::from_naive_utc_and_offset(naive_datetime, Utc));
-        }
-
-        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
-            return Some(DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc));
-        }
-
-        None
-    }
-
-    fn parse_relative_duration(input: &str) -> Option<Duration> {
-        let input = input.trim().to_lowercase();
-
-        if input.is_empty() {
-            return None;
-        }
-
-        // First, try parsing as a bare number (default to days)
-        if let Ok(number) = input.parse::<i64>() {
-            return Some(Duration::days(number));
-        }
-
-        // Extract number and unit
-        let len = input.len();
-        if len < 2 {
-            return None;
-        }
-
-        let unit = &input[len - 1..];
-        let number_str = &input[..len - 1];
-
-        let number: i64 = number_str.parse().ok()?;
-
-        match unit {
-            "m" => Some(Duration::minutes(number)),
-            "h" => Some(Duration::hours(number)),
-            "d" => Some(Duration::days(number)),
-            "w" => Some(Duration::weeks(number)),
-            _ => None,
-        }
-    }
-
-    pub fn new(database: Database) -> anyhow::Result<Self> {
+    pub fn new(mut database: Database, config: Config) -> anyhow::Result<Self> {
+        database.set_stale_days(config.review.stale_days);
+        let changes = database.subscribe();
         let mut app = App {
             database,
             incomplete_todos: Vec::new(),
             completed_todos: Vec::new(),
+            completed_total: 0,
+            completed_range: CompletedRange::All,
+            completed_root: None,
+            scheduled_todos: Vec::new(),
+            someday_todos: Vec::new(),
+            pinned_todos: Vec::new(),
+            waiting_todos: Vec::new(),
+            today_todos: Vec::new(),
+            carry_over: None,
             tree_manager: TodoTreeManager::new(),
             list_state: ListState::default(),
             tree_list_state: ListState::default(),
             completed_list_state: ListState::default(),
+            completed_rows_state: ListState::default(),
+            scheduled_list_state: ListState::default(),
+            someday_list_state: ListState::default(),
+            waiting_list_state: ListState::default(),
+            today_list_state: ListState::default(),
             mode: AppMode::List,
             previous_mode: AppMode::List,
             input_title: String::new(),
@@ -345,7 +804,11 @@ impl App {
             input_due_date_absolute: String::new(),
             current_parent: None,
             should_quit: false,
+            suspend_pending: false,
+            session: None,
+            changes,
             error_message: None,
+            status_message: None,
             search_query: String::new(),
             search_results: Vec::new(),
             search_list_state: ListState::default(),
@@ -356,10 +819,14 @@ impl App {
             input_parent: String::new(),
             selected_parent_id: None,
             create_field_focus: CreateFieldFocus::Title,
+            create_cursor: 0,
             use_tree_view: true,
             search_input_mode: false,
             move_todo_id: None,
+            move_as_sibling: false,
+            last_move_parent: None,
             editor_pending: None,
+            editor_session_dir: None,
             show_hidden_items: false,
             goto_query: String::new(),
             goto_matches: Vec::new(),
@@ -367,649 +834,2711 @@ impl App {
             list_scrollbar_state: ScrollbarState::default(),
             tree_scrollbar_state: ScrollbarState::default(),
             completed_scrollbar_state: ScrollbarState::default(),
+            scheduled_scrollbar_state: ScrollbarState::default(),
+            someday_scrollbar_state: ScrollbarState::default(),
+            waiting_scrollbar_state: ScrollbarState::default(),
+            today_scrollbar_state: ScrollbarState::default(),
+            config,
+            last_activity: std::time::Instant::now(),
+            sync_job: None,
+            next_sync: None,
+            status_shown: None,
+            viewport_rows: 0,
+            pending_prefix: None,
+            next_checkpoint: None,
+            unflushed_changes: false,
+            hook_runner: HookRunner::default(),
+            lock_input: String::new(),
+            pre_lock_mode: AppMode::List,
+            saved_filters: Vec::new(),
+            filter_list_state: ListState::default(),
+            filter_input: String::new(),
+            defer_input: String::new(),
+            note_input: String::new(),
+            quick_edit_field: QuickEditField::Title,
+            quick_edit_input: String::new(),
+            active_filter: None,
+            filter_context: HashSet::new(),
+            filter_opened: Vec::new(),
+            attachment_counts: HashMap::new(),
+            note_counts: HashMap::new(),
+            stale_ages: HashMap::new(),
+            pomodoro_counts: HashMap::new(),
+            pomodoro: None,
+            estimates: HashMap::new(),
+            labels: HashMap::new(),
+            waiting: HashMap::new(),
+            delegate_input: String::new(),
+            now_context: None,
+            now_energy: None,
+            agenda_scroll: 0,
+            history_todo: None,
+            history_entries: Vec::new(),
+            history_scroll: 0,
+            attachments: Vec::new(),
+            attachment_list_state: ListState::default(),
+            attachment_todo: None,
+            attachment_input: String::new(),
+            import_sources: Vec::new(),
+            import_list_state: ListState::default(),
+            metadata_density: HashMap::new(),
+            capture_input: String::new(),
+            capture_parent: None,
+            capture_count: 0,
+            capture_confirm: None,
+            quick_add_input: String::new(),
+            quick_add_todos: Vec::new(),
+            tree_column_offset: 0,
+            saved_templates: Vec::new(),
+            template_list_state: ListState::default(),
+            template_input: String::new(),
+            template_target: None,
+            yanked_todo: None,
+            known_tags: Vec::new(),
+            tag_completions: Vec::new(),
+            tag_completion_state: ListState::default(),
+            help_query: String::new(),
+            help_search_input: false,
+            help_scroll: 0,
+            help_height: 0,
+            tabs: Vec::new(),
+            current_profile: None,
+            profile_list_state: ListState::default(),
+            stats_parent: None,
+            stats_list_state: ListState::default(),
+            review_queue: Vec::new(),
+            review_index: 0,
+            review_confirm_delete: false,
+            review_handled: 0,
+            profile_states: HashMap::new(),
+            cascade_pending: None,
         };
+        app.load_metadata_density()?;
         app.refresh_todos()?;
         if !app.incomplete_todos.is_empty() {
             app.list_state.select(Some(0));
         }
+        app.load_tabs()?;
+        app.load_view_state()?;
+        app.current_profile = app.profile_for_current_database();
+        app.next_sync = app.sync_interval().map(|_| std::time::Instant::now());
+        app.start_session();
+        app.start_onboarding()?;
+        if app.is_lock_enabled() && app.config.lock.lock_on_start {
+            app.lock();
+        }
         Ok(app)
     }
 
-    pub fn refresh_todos(&mut self) -> anyhow::Result<()> {
-        self.incomplete_todos = self.database.get_incomplete_todos(self.current_parent)?;
-        // Load ALL completed todos for the completed view (not just recent 5)
-        self.completed_todos = self.get_all_completed_todos()?;
-        
-        // Rebuild tree view with all todos
-        let all_todos = self.database.get_all_todos()?;
-        self.tree_manager.rebuild_from_todos_with_hidden_filter(all_todos, self.show_hidden_items);
-        
-        // Initialize tree selection if we have items
-        if !self.tree_manager.get_rendered_lines().is_empty() && self.tree_list_state.selected().is_none() {
-            self.tree_list_state.select(Some(0));
+    /// Resolve the configured tab bar and open its first view
+    fn load_tabs(&mut self) -> anyhow::Result<()> {
+        let views = &self.config.views;
+        for name in views.tabs.iter().chain(views.titles.keys()).chain(views.icons.keys()) {
+            if ViewKind::from_name(name).is_none() {
+                return Err(anyhow::anyhow!("Unknown view '{}' in [views] config (expected tree, list, completed or scheduled)", name));
+            }
+        }
+
+        self.tabs = if views.tabs.is_empty() {
+            ViewKind::ALL.to_vec()
+        } else {
+            views.tabs.iter().filter_map(|name| ViewKind::from_name(name)).collect()
+        };
+        if let Some(&first) = self.tabs.first() {
+            self.switch_view(first);
         }
-        
         Ok(())
     }
 
-    pub fn update_scrollbar_states(&mut self) {
-        // Update list scrollbar
-        let list_len = self.incomplete_todos.len();
-        self.list_scrollbar_state = self.list_scrollbar_state
-            .content_length(list_len)
-            .position(self.list_state.selected().unwrap_or(0));
-
-        // Update tree scrollbar
-        let tree_len = self.tree_manager.get_rendered_lines().len();
-        self.tree_scrollbar_state = self.tree_scrollbar_state
-            .content_length(tree_len)
-            .position(self.tree_list_state.selected().unwrap_or(0));
+    fn view_title(&self, view: ViewKind) -> &str {
+        self.config.views.titles.get(view.name()).map_or(view.default_title(), String::as_str)
+    }
 
-        // Update completed scrollbar
-        let completed_len = self.completed_todos.len();
-        self.completed_scrollbar_state = self.completed_scrollbar_state
-            .content_length(completed_len)
-            .position(self.completed_list_state.selected().unwrap_or(0));
+    fn view_icon(&self, view: ViewKind) -> &str {
+        self.config.views.icons.get(view.name()).map_or(view.default_icon(), String::as_str)
     }
 
-    fn get_due_date_style(&self, todo: &Todo) -> Color {
-        // Only color incomplete todos based on due date
-        if todo.is_completed() {
-            return CatppuccinFrappe::COMPLETED;
+    fn current_view(&self) -> ViewKind {
+        match self.mode {
+            AppMode::CompletedView => ViewKind::Completed,
+            AppMode::ScheduledView => ViewKind::Scheduled,
+            AppMode::Someday => ViewKind::Someday,
+            AppMode::Waiting => ViewKind::Waiting,
+            AppMode::Today => ViewKind::Today,
+            _ if self.use_tree_view => ViewKind::Tree,
+            _ => ViewKind::List,
         }
+    }
 
-        if let Some(due_by) = todo.due_by {
-            let now = Utc::now();
-            let diff = due_by.signed_duration_since(now);
-
-            if diff.num_seconds() < 0 {
-                // Past due - RED
-                CatppuccinFrappe::RED
-            } else if diff.num_days() < 7 {
-                // Due within 1 week (less than 7 days) - TEAL
-                CatppuccinFrappe::TEAL
-            } else {
-                // More than 1 week away (>= 7 days) - default color
-                CatppuccinFrappe::INCOMPLETE
+    fn switch_view(&mut self, view: ViewKind) {
+        let selected_id = self.get_selected_todo().map(|todo| todo.id);
+        match view {
+            ViewKind::Tree | ViewKind::List => {
+                self.mode = AppMode::List;
+                self.use_tree_view = view == ViewKind::Tree;
+            }
+            ViewKind::Completed => {
+                self.mode = AppMode::CompletedView;
+            }
+            ViewKind::Scheduled => {
+                self.mode = AppMode::ScheduledView;
             }
+            ViewKind::Someday => {
+                self.mode = AppMode::Someday;
+            }
+            ViewKind::Waiting => {
+                self.mode = AppMode::Waiting;
+            }
+            ViewKind::Today => {
+                self.mode = AppMode::Today;
+            }
+        }
+        if !self.reselect_todo(selected_id) {
+            let (todos, list_state) = match self.mode {
+                AppMode::CompletedView => (&self.completed_todos, &mut self.completed_list_state),
+                AppMode::ScheduledView => (&self.scheduled_todos, &mut self.scheduled_list_state),
+                AppMode::Someday => (&self.someday_todos, &mut self.someday_list_state),
+                AppMode::Waiting => (&self.waiting_todos, &mut self.waiting_list_state),
+                AppMode::Today => (&self.today_todos, &mut self.today_list_state),
+                _ => return,
+            };
+            if !todos.is_empty() && list_state.selected().is_none() {
+                list_state.select(Some(0));
+            }
+        }
+    }
+
+    /// Select `todo_id` in the current view, expanding its ancestors in the tree.
+    /// Returns false (leaving the selection alone) when the view doesn't show it.
+    fn reselect_todo(&mut self, todo_id: Option<i64>) -> bool {
+        let Some(todo_id) = todo_id else { return false };
+        if self.mode == AppMode::CompletedView {
+            let Some(index) = self.completed_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.completed_list_state.select(Some(index));
+        } else if self.mode == AppMode::ScheduledView {
+            let Some(index) = self.scheduled_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.scheduled_list_state.select(Some(index));
+        } else if self.mode == AppMode::Someday {
+            let Some(index) = self.someday_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.someday_list_state.select(Some(index));
+        } else if self.mode == AppMode::Waiting {
+            let Some(index) = self.waiting_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.waiting_list_state.select(Some(index));
+        } else if self.mode == AppMode::Today {
+            let Some(index) = self.today_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.today_list_state.select(Some(index));
+        } else if self.use_tree_view {
+            self.tree_manager.expand_path_to_todo(todo_id);
+            let Some(index) = self.tree_manager.get_line_index_for_todo(todo_id) else { return false };
+            self.tree_list_state.select(Some(index));
         } else {
-            // No due date - default color
-            CatppuccinFrappe::INCOMPLETE
+            let Some(index) = self.incomplete_todos.iter().position(|todo| todo.id == todo_id) else { return false };
+            self.list_state.select(Some(index));
         }
+        true
     }
 
-    fn get_all_completed_todos(&self) -> anyhow::Result<Vec<Todo>> {
-        // Use existing database method but without limit
-        let all_todos = self.database.get_all_todos()?;
-        let mut completed_todos: Vec<Todo> = all_todos
-            .into_iter()
-            .filter(|todo| todo.is_completed())
-            .collect();
-        
-        // Sort by completion time (most recent first)
-        completed_todos.sort_by(|a, b| {
-            match (a.completed_at, b.completed_at) {
-                (Some(a_time), Some(b_time)) => b_time.cmp(&a_time),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => std::cmp::Ordering::Equal,
-            }
-        });
-        
-        Ok(completed_todos)
+    /// Return from the completed or scheduled view to whichever of tree or list was open
+    fn leave_flat_view(&mut self) {
+        let selected_id = self.get_selected_todo().map(|todo| todo.id);
+        self.mode = AppMode::List;
+        self.reselect_todo(selected_id);
     }
 
-    fn update_tree_search_matches(&mut self) -> anyhow::Result<()> {
-        if self.search_query.is_empty() {
-            self.search_matches.clear();
-            self.current_match_index = None;
-        } else {
-            let new_matches: Vec<i64> = self.database.search_todos(&self.search_query)?
-                .into_iter()
-                .map(|todo| todo.id)
-                .collect();
-            
-            // Only re-sort if matches have actually changed
-            if new_matches != self.search_matches {
-                self.search_matches = new_matches;
-                // Sort matches by their appearance order in the tree
-                self.sort_matches_by_tree_order();
-                
-                // Find closest match to current selection or start from first match
-                self.current_match_index = if self.search_matches.is_empty() {
-                    None
-                } else {
-                    Some(self.find_closest_match_index())
-                };
+    /// Move to the next (or previous) view in the tab bar
+    fn cycle_view(&mut self, forward: bool) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        let len = self.tabs.len();
+        let next = match self.tabs.iter().position(|&view| view == self.current_view()) {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+        self.switch_view(self.tabs[next]);
+    }
 
-                // Automatically move cursor to the current match
-                if let Some(current_match_index) = self.current_match_index {
-                    if let Some(&match_todo_id) = self.search_matches.get(current_match_index) {
-                        if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                            self.tree_list_state.select(Some(line_index));
-                        }
+    /// The configured profile whose database is open, if any
+    fn profile_for_current_database(&self) -> Option<String> {
+        let current = std::fs::canonicalize(self.database.path()?).ok()?;
+        self.config
+            .profiles
+            .iter()
+            .find(|profile| std::fs::canonicalize(profile.db_path()).is_ok_and(|path| path == current))
+            .map(|profile| profile.name.clone())
+    }
+
+    fn open_profile_picker(&mut self) {
+        if self.config.profiles.is_empty() {
+            self.error_message = Some(format!("No profiles configured; add [[profiles]] entries to {}", Config::path().display()));
+            return;
+        }
+        let current = self.config.profiles.iter().position(|profile| Some(&profile.name) == self.current_profile.as_ref());
+        self.profile_list_state.select(Some(current.unwrap_or(0)));
+        self.mode = AppMode::ProfilePicker;
+    }
+
+    fn handle_profile_picker_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let len = self.config.profiles.len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => self.mode = AppMode::List,
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                let i = self.profile_list_state.selected().map_or(0, |i| (i + 1) % len);
+                self.profile_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                let i = self.profile_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.profile_list_state.select(Some(i));
+            }
+            KeyCode::Enter => {
+                if let Some(index) = self.profile_list_state.selected() {
+                    self.switch_profile(index)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_stats_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let projects = self.tree_manager.project_stats(self.stats_parent, now);
+        let len = projects.len();
+        let selected = self.stats_list_state.selected().and_then(|i| projects.get(i)).map(|(id, _)| *id);
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('S') => self.mode = AppMode::List,
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                let i = self.stats_list_state.selected().map_or(0, |i| (i + 1) % len);
+                self.stats_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                let i = self.stats_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.stats_list_state.select(Some(i));
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                // Drill into the selected project's own subprojects
+                if let Some(id) = selected {
+                    if self.tree_manager.project_stats(Some(id), now).is_empty() {
+                        let title = self.tree_manager.get_todo_by_id(id).map(|todo| todo.title.clone()).unwrap_or_default();
+                        self.status_message = Some(format!("'{}' has no subtasks to break down", title));
+                    } else {
+                        self.stats_parent = Some(id);
+                        self.stats_list_state.select(Some(0));
                     }
                 }
             }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Backspace => {
+                // Back up a level, onto the project we came from
+                if let Some(parent_id) = self.stats_parent {
+                    self.stats_parent = self.tree_manager.get_todo_by_id(parent_id).and_then(|todo| todo.parent_id);
+                    let position = self.tree_manager.project_stats(self.stats_parent, now).iter().position(|(id, _)| *id == parent_id);
+                    self.stats_list_state.select(Some(position.unwrap_or(0)));
+                }
+            }
+            _ => {}
         }
         Ok(())
     }
-    
-    fn sort_matches_by_tree_order(&mut self) {
-        let rendered_lines = self.tree_manager.get_rendered_lines();
-        let line_order: std::collections::HashMap<i64, usize> = rendered_lines
-            .iter()
-            .enumerate()
-            .map(|(idx, line)| (line.todo_id, idx))
-            .collect();
-            
-        self.search_matches.sort_by_key(|&todo_id| {
-            line_order.get(&todo_id).copied().unwrap_or(usize::MAX)
-        });
-    }
-    
-    fn find_closest_match_index(&self) -> usize {
-        if self.search_matches.is_empty() {
-            return 0;
+
+    /// Start a review of the overdue, stale and top-level todos
+    fn handle_agenda_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('W') => self.mode = AppMode::List,
+            KeyCode::Down | KeyCode::Char('j') => self.agenda_scroll = self.agenda_scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.agenda_scroll = self.agenda_scroll.saturating_sub(1),
+            KeyCode::PageDown => self.agenda_scroll = self.agenda_scroll.saturating_add(10),
+            KeyCode::PageUp => self.agenda_scroll = self.agenda_scroll.saturating_sub(10),
+            KeyCode::Home | KeyCode::Char('g') => self.agenda_scroll = 0,
+            _ => {}
         }
-        
-        let current_selection = self.tree_list_state.selected().unwrap_or(0);
-        let rendered_lines = self.tree_manager.get_rendered_lines();
-        
-        if let Some(current_line) = rendered_lines.get(current_selection) {
-            let current_todo_id = current_line.todo_id;
-            
-            // If current selection is a match, use it
-            if let Some(pos) = self.search_matches.iter().position(|&id| id == current_todo_id) {
-                return pos;
-            }
+    }
+
+    /// Show a todo's recorded changes, or with `None` the activity feed of all of them
+    fn open_history(&mut self, todo_id: Option<i64>) -> anyhow::Result<()> {
+        self.history_entries = self.database.get_history(todo_id, HISTORY_LIMIT)?;
+        self.history_todo = todo_id;
+        self.history_scroll = 0;
+        if self.mode != AppMode::History {
+            self.previous_mode = self.mode.clone();
+            self.mode = AppMode::History;
         }
-        
-        // Otherwise, find the closest match by tree position
-        let line_positions: std::collections::HashMap<i64, usize> = rendered_lines
-            .iter()
-            .enumerate()
-            .map(|(idx, line)| (line.todo_id, idx))
-            .collect();
-            
-        let mut best_match = 0;
-        let mut best_distance = usize::MAX;
-        
-        for (idx, &match_id) in self.search_matches.iter().enumerate() {
-            if let Some(&match_pos) = line_positions.get(&match_id) {
-                let distance = if match_pos >= current_selection {
-                    match_pos - current_selection
-                } else {
-                    current_selection - match_pos
+        Ok(())
+    }
+
+    fn handle_history_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('V') => self.mode = self.previous_mode.clone(),
+            // Switch between the selected todo's history and everyone's
+            KeyCode::Char('a') => {
+                let todo_id = match self.history_todo {
+                    Some(_) => None,
+                    None => self.get_selected_todo().map(|todo| todo.id),
                 };
-                
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_match = idx;
-                }
+                self.open_history(todo_id)?;
             }
+            KeyCode::Down | KeyCode::Char('j') => self.history_scroll = self.history_scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.history_scroll = self.history_scroll.saturating_sub(1),
+            KeyCode::PageDown => self.history_scroll = self.history_scroll.saturating_add(10),
+            KeyCode::PageUp => self.history_scroll = self.history_scroll.saturating_sub(10),
+            KeyCode::Home | KeyCode::Char('g') => self.history_scroll = 0,
+            _ => {}
         }
-        
-        best_match
+        Ok(())
     }
-    
-    fn navigate_to_next_match(&mut self) {
-        if self.search_matches.is_empty() {
-            return;
-        }
-        
-        let start_index = self.current_match_index.unwrap_or(0);
-        let matches_len = self.search_matches.len();
-        
-        // Try to find the next visible match, starting from the next position
-        for i in 1..=matches_len {
-            let next_index = (start_index + i) % matches_len;
-            
-            if let Some(&match_todo_id) = self.search_matches.get(next_index) {
-                // First check if it's already visible
-                if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                    self.current_match_index = Some(next_index);
-                    self.tree_list_state.select(Some(line_index));
-                    return;
-                }
-                
-                // If not visible, expand parent nodes to make it visible
-                let opened_nodes = self.expand_path_to_todo(match_todo_id);
-                if !opened_nodes.is_empty() {
-                    // Track which nodes we opened for search
-                    for node_id in opened_nodes {
-                        self.search_opened_nodes.insert(node_id);
-                    }
-                    
-                    // After expanding, it should be visible now
-                    if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                        self.current_match_index = Some(next_index);
-                        self.tree_list_state.select(Some(line_index));
-                        return;
-                    }
-                }
-            }
+
+    fn start_review(&mut self) -> anyhow::Result<()> {
+        let mut todos = self.database.get_all_todos()?;
+        // Someday/maybe todos aren't commitments, so they're not overdue or stale either
+        let set_aside = filter::with_descendants(&todos, &self.database.get_someday_ids()?);
+        todos.retain(|todo| !set_aside.contains(&todo.id));
+        let updated_at = self.database.get_updated_at()?;
+        self.review_queue = review::review_queue(&todos, &updated_at, self.config.review.stale_days, Utc::now());
+        self.review_index = 0;
+        self.review_confirm_delete = false;
+        self.review_handled = 0;
+        if self.review_queue.is_empty() {
+            self.status_message = Some("Nothing to review".to_string());
+        } else {
+            self.mode = AppMode::Review;
         }
+        Ok(())
     }
-    
-    fn navigate_to_previous_match(&mut self) {
-        if self.search_matches.is_empty() {
-            return;
+
+    /// The review's current todo, as it is now; None once it's been completed or deleted
+    fn review_todo(&self) -> Option<(ReviewItem, Todo)> {
+        let item = *self.review_queue.get(self.review_index)?;
+        let todo = self.database.get_todo_by_id(item.id).ok()??;
+        (!todo.is_completed()).then_some((item, todo))
+    }
+
+    /// Skip todos completed or deleted along with an earlier one: nothing is left to review
+    fn skip_done_review_todos(&mut self) {
+        while self.review_index < self.review_queue.len() && self.review_todo().is_none() {
+            self.review_index += 1;
         }
-        
-        let start_index = self.current_match_index.unwrap_or(0);
-        let matches_len = self.search_matches.len();
-        
-        // Try to find the previous visible match, starting from the previous position
-        for i in 1..=matches_len {
-            let prev_index = if start_index >= i {
-                start_index - i
-            } else {
-                matches_len - (i - start_index)
-            };
-            
-            if let Some(&match_todo_id) = self.search_matches.get(prev_index) {
-                // First check if it's already visible
-                if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                    self.current_match_index = Some(prev_index);
-                    self.tree_list_state.select(Some(line_index));
-                    return;
-                }
-                
-                // If not visible, expand parent nodes to make it visible
-                let opened_nodes = self.expand_path_to_todo(match_todo_id);
-                if !opened_nodes.is_empty() {
-                    // Track which nodes we opened for search
-                    for node_id in opened_nodes {
-                        self.search_opened_nodes.insert(node_id);
-                    }
-                    
-                    // After expanding, it should be visible now
-                    if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                        self.current_match_index = Some(prev_index);
-                        self.tree_list_state.select(Some(line_index));
-                        return;
-                    }
-                }
-            }
+    }
+
+    /// Go on to the next todo still open, ending the review after the last one
+    fn next_review_todo(&mut self) -> anyhow::Result<()> {
+        self.review_confirm_delete = false;
+        self.review_index += 1;
+        self.skip_done_review_todos();
+        if self.review_index >= self.review_queue.len() {
+            self.end_review()?;
         }
+        Ok(())
     }
-    
-    
-    fn expand_path_to_todo(&mut self, todo_id: i64) -> Vec<i64> {
-        self.tree_manager.expand_path_to_todo(todo_id)
+
+    fn end_review(&mut self) -> anyhow::Result<()> {
+        let seen = self.review_index.min(self.review_queue.len());
+        self.status_message = Some(format!("Review: {} of {} todos seen, {} handled", seen, self.review_queue.len(), self.review_handled));
+        self.review_queue.clear();
+        self.mode = AppMode::List;
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        Ok(())
     }
-    
-    fn restore_pre_search_expansion_state(&mut self) {
-        let mut needs_rebuild = false;
-        
-        // For each node we opened during search, restore its original state
-        for &node_id in &self.search_opened_nodes {
-            // Get the original state (default to false if it wasn't tracked)
-            let original_state = self.pre_search_expansion_state.get(&node_id).copied().unwrap_or(false);
-            let current_state = self.tree_manager.expansion_states.get(&node_id).copied().unwrap_or(false);
-            
-            // Only change if current state differs from original
-            if current_state != original_state {
-                if original_state {
-                    self.tree_manager.expansion_states.insert(node_id, true);
+
+    fn handle_review_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let Some((_, todo)) = self.review_todo() else {
+            return self.next_review_todo();
+        };
+        if self.review_confirm_delete {
+            self.review_confirm_delete = false;
+            if key == KeyCode::Char('y') {
+                self.database.delete_todo(todo.id)?;
+                self.review_handled += 1;
+                self.next_review_todo()?;
+            }
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => self.end_review()?,
+            KeyCode::Char('k') => {
+                // Still wanted as it is: it won't come up as stale for another stale_days
+                self.database.touch_todo(todo.id)?;
+                self.review_handled += 1;
+                self.next_review_todo()?;
+            }
+            KeyCode::Char('s') => {
+                let snooze = &self.config.review.snooze;
+                let Some(start_at) = dates::parse_start_date(snooze) else {
+                    self.error_message = Some(format!("Invalid [review] snooze '{}': use 2d, 1w or YYYY-MM-DD", snooze));
+                    return Ok(());
+                };
+                self.database.set_start_at(todo.id, Some(start_at))?;
+                self.review_handled += 1;
+                self.next_review_todo()?;
+            }
+            KeyCode::Char('c') | KeyCode::Char(' ') => {
+                // Move on first: completing may stop to ask about subtasks, then comes back here
+                self.review_handled += 1;
+                self.next_review_todo()?;
+                self.set_completion(todo.id, true)?;
+                if self.mode == AppMode::List {
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            KeyCode::Char('d') => {
+                if self.database.has_children(todo.id)? {
+                    self.error_message = Some("Cannot delete: task has children. Delete children first.".to_string());
                 } else {
-                    self.tree_manager.expansion_states.remove(&node_id);
+                    self.review_confirm_delete = true;
                 }
-                needs_rebuild = true;
             }
+            KeyCode::Char('h') => {
+                self.database.toggle_todo_hidden(todo.id)?;
+                self.review_handled += 1;
+                self.next_review_todo()?;
+            }
+            KeyCode::Char('n') | KeyCode::Char('j') | KeyCode::Right => self.next_review_todo()?,
+            KeyCode::Char('p') | KeyCode::Left => {
+                self.review_confirm_delete = false;
+                self.review_index = self.review_index.saturating_sub(1);
+            }
+            _ => {}
         }
-        
-        // Clear tracking data
-        self.search_opened_nodes.clear();
-        self.pre_search_expansion_state.clear();
-        
-        // Rebuild tree if we made changes
-        if needs_rebuild {
-            let all_todos = self.tree_manager.todos.values().cloned().collect();
-            self.tree_manager.rebuild_from_todos_with_hidden_filter(all_todos, self.show_hidden_items);
-        }
+        Ok(())
     }
 
-    pub fn update_search_results(&mut self) -> anyhow::Result<()> {
-        self.search_results = self.database.search_todos(&self.search_query)?;
-        // Reset selection when search results change
-        if !self.search_results.is_empty() {
-            self.search_list_state.select(Some(0));
-        } else {
-            self.search_list_state.select(None);
+    /// Close the current database and open the profile's, keeping each database's view state
+    fn switch_profile(&mut self, index: usize) -> anyhow::Result<()> {
+        let Some(profile) = self.config.profiles.get(index).cloned() else {
+            return Ok(());
+        };
+        self.mode = AppMode::List;
+        if self.current_profile.as_ref() == Some(&profile.name) {
+            return Ok(());
+        }
+
+        let mut database = match profile.open_path().and_then(|path| Database::new(&path)) {
+            Ok(database) => database,
+            Err(e) => {
+                self.error_message = Some(format!("Cannot open profile '{}': {}", profile.name, e));
+                return Ok(());
+            }
+        };
+        if let Err(e) = database.set_wal_autocheckpoint(self.config.checkpoint.wal_pages) {
+            self.error_message = Some(format!("Cannot open profile '{}': {}", profile.name, e));
+            return Ok(());
+        }
+        if self.config.audit.completion_log {
+            database.enable_completion_log();
         }
+        database.set_stale_days(self.config.review.stale_days);
+        if let Some(dir) = self.config.mirror_dir(Some(&profile.name)) {
+            database.set_mirror_dir(dir);
+        }
+        if let Some(url) = self.config.sync_url(Some(&profile.name)) {
+            database.set_sync_url(url);
+        }
+
+        self.save_profile_state();
+        self.save_view_state()?;
+        // Each database keeps the sessions spent in it
+        self.dispatch_changes();
+        self.end_session();
+        self.changes = database.subscribe();
+        let previous = std::mem::replace(&mut self.database, database);
+        previous.checkpoint_and_close()?;
+        drop(previous);
+        self.current_profile = Some(profile.name.clone());
+        self.next_sync = self.sync_interval().map(|_| std::time::Instant::now());
+        self.start_session();
+        self.restore_profile_state()?;
+        self.status_message = Some(format!("Switched to profile '{}'", profile.name));
         Ok(())
     }
 
-    fn update_goto_matches(&mut self) -> anyhow::Result<()> {
-        if self.goto_query.is_empty() {
-            self.goto_matches.clear();
-            self.goto_current_match_index = None;
-        } else {
-            // Parse the goto query as a number
-            if let Ok(target_id_mod) = self.goto_query.parse::<i64>() {
-                // Only search within currently visible todos in the tree
-                let rendered_lines = self.tree_manager.get_rendered_lines();
-                let new_matches: Vec<i64> = rendered_lines
-                    .iter()
-                    .filter_map(|line| {
-                        self.tree_manager.get_todo_by_id(line.todo_id)
-                            .filter(|todo| todo.id_mod() == target_id_mod)
-                            .map(|_| line.todo_id)
-                    })
-                    .collect();
-
-                // Only re-sort if matches have actually changed
-                if new_matches != self.goto_matches {
-                    self.goto_matches = new_matches;
-                    // Sort matches by their appearance order in the tree
-                    self.sort_goto_matches_by_tree_order();
-
-                    // Find closest match to current selection or start from first match
-                    self.goto_current_match_index = if self.goto_matches.is_empty() {
-                        None
-                    } else {
-                        Some(self.find_closest_goto_match_index())
-                    };
+    fn save_profile_state(&mut self) {
+        let Some(path) = self.database.path().map(str::to_string) else {
+            return;
+        };
+        self.profile_states.insert(path, ProfileState {
+            expansion_states: self.tree_manager.expansion_states.clone(),
+            tree_selected: self.tree_list_state.selected(),
+            list_selected: self.list_state.selected(),
+            completed_selected: self.completed_list_state.selected(),
+            scheduled_selected: self.scheduled_list_state.selected(),
+            use_tree_view: self.use_tree_view,
+            view: self.current_view(),
+            show_hidden_items: self.show_hidden_items,
+            active_filter: self.active_filter.clone(),
+            hoisted: self.tree_manager.root,
+        });
+    }
 
-                    // Automatically move cursor to the current match
-                    if let Some(current_match_index) = self.goto_current_match_index {
-                        if let Some(&match_todo_id) = self.goto_matches.get(current_match_index) {
-                            if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                                self.tree_list_state.select(Some(line_index));
-                            }
-                        }
-                    }
+    /// Reset the views for the newly opened database, restoring how it was left if it was open before
+    fn restore_profile_state(&mut self) -> anyhow::Result<()> {
+        // Ids from the previous database mean nothing in this one
+        self.yanked_todo = None;
+        self.move_todo_id = None;
+        self.last_move_parent = None;
+        self.current_parent = None;
+        self.search_query.clear();
+        self.search_results.clear();
+        self.search_matches.clear();
+        self.current_match_index = None;
+        self.goto_query.clear();
+        self.goto_matches.clear();
+        self.goto_current_match_index = None;
+        // Drop the previous database's todos so no selection is matched against them
+        self.tree_manager = TodoTreeManager::new();
+        self.incomplete_todos.clear();
+        self.completed_todos.clear();
+        self.completed_root = None;
+        self.scheduled_todos.clear();
+
+        let state = self.database.path().and_then(|path| self.profile_states.remove(path));
+        let restored = state.is_some();
+        match state {
+            Some(state) => {
+                self.tree_manager.expansion_states = state.expansion_states;
+                self.tree_list_state.select(state.tree_selected);
+                self.list_state.select(state.list_selected);
+                self.completed_list_state.select(state.completed_selected);
+                self.scheduled_list_state.select(state.scheduled_selected);
+                self.show_hidden_items = state.show_hidden_items;
+                self.active_filter = state.active_filter;
+                self.tree_manager.root = state.hoisted;
+                // Tree or list is still what the completed and scheduled views return to
+                self.use_tree_view = state.use_tree_view;
+                self.switch_view(state.view);
+            }
+            None => {
+                self.tree_list_state.select(None);
+                self.list_state.select(None);
+                self.completed_list_state.select(None);
+                self.scheduled_list_state.select(None);
+                self.show_hidden_items = false;
+                self.active_filter = None;
+                if let Some(&first) = self.tabs.first() {
+                    self.switch_view(first);
                 }
-            } else {
-                self.goto_matches.clear();
-                self.goto_current_match_index = None;
             }
         }
+
+        self.load_metadata_density()?;
+        self.refresh_todos()?;
+        // The database may have changed since its state was saved
+        let clamp = |selected: Option<usize>, len: usize| if len == 0 { None } else { Some(selected.unwrap_or(0).min(len - 1)) };
+        self.list_state.select(clamp(self.list_state.selected(), self.incomplete_todos.len()));
+        self.tree_list_state.select(clamp(self.tree_list_state.selected(), self.tree_manager.get_rendered_lines().len()));
+        self.completed_list_state.select(clamp(self.completed_list_state.selected(), self.completed_todos.len()));
+        self.scheduled_list_state.select(clamp(self.scheduled_list_state.selected(), self.scheduled_todos.len()));
+        if !restored {
+            // First time this run: pick up where the last run left it
+            self.load_view_state()?;
+        }
         Ok(())
     }
 
-    fn sort_goto_matches_by_tree_order(&mut self) {
-        let rendered_lines = self.tree_manager.get_rendered_lines();
-        let line_order: std::collections::HashMap<i64, usize> = rendered_lines
-            .iter()
-            .enumerate()
-            .map(|(idx, line)| (line.todo_id, idx))
+    /// Remember the view, selection, expanded nodes and hidden toggle in the database
+    pub fn save_view_state(&self) -> anyhow::Result<()> {
+        // Nodes of deleted todos needn't be remembered
+        let expansion_states = self.tree_manager.expansion_states.iter()
+            .filter(|(id, _)| self.tree_manager.get_todo_by_id(**id).is_some())
+            .map(|(&id, &expanded)| (id, expanded))
             .collect();
-
-        self.goto_matches.sort_by_key(|&todo_id| {
-            line_order.get(&todo_id).copied().unwrap_or(usize::MAX)
-        });
+        let state = SavedViewState {
+            view: Some(self.current_view().name().to_string()),
+            use_tree_view: self.use_tree_view,
+            show_hidden_items: self.show_hidden_items,
+            selected: self.get_selected_todo().map(|todo| todo.id),
+            expansion_states,
+            hoisted: self.tree_manager.root,
+        };
+        self.database.set_ui_state(VIEW_STATE_KEY, &serde_json::to_string(&state)?)
     }
 
-    fn find_closest_goto_match_index(&self) -> usize {
-        if self.goto_matches.is_empty() {
-            return 0;
+    /// Restore what `save_view_state` saved; a state that doesn't parse is ignored
+    fn load_view_state(&mut self) -> anyhow::Result<()> {
+        let Some(state) = self.database.get_ui_state(VIEW_STATE_KEY)?
+            .and_then(|json| serde_json::from_str::<SavedViewState>(&json).ok()) else {
+            return Ok(());
+        };
+        self.tree_manager.expansion_states = state.expansion_states;
+        self.tree_manager.root = state.hoisted;
+        self.show_hidden_items = state.show_hidden_items;
+        self.use_tree_view = state.use_tree_view;
+        self.refresh_todos()?;
+        if let Some(view) = state.view.as_deref().and_then(ViewKind::from_name).filter(|view| self.tabs.contains(view)) {
+            self.switch_view(view);
         }
+        self.reselect_todo(state.selected);
+        Ok(())
+    }
 
-        let current_selection = self.tree_list_state.selected().unwrap_or(0);
-        let rendered_lines = self.tree_manager.get_rendered_lines();
-
-        if let Some(current_line) = rendered_lines.get(current_selection) {
-            let current_todo_id = current_line.todo_id;
-
-            // If current selection is a match, use it
-            if let Some(pos) = self.goto_matches.iter().position(|&id| id == current_todo_id) {
-                return pos;
+    fn load_metadata_density(&mut self) -> anyhow::Result<()> {
+        for view in MetadataView::ALL {
+            if let Some(density) = self.database.get_ui_state(view.state_key())?.as_deref().and_then(MetadataDensity::parse) {
+                self.metadata_density.insert(view, density);
             }
         }
+        Ok(())
+    }
 
-        // Otherwise, find the closest match by tree position
-        let line_positions: std::collections::HashMap<i64, usize> = rendered_lines
-            .iter()
-            .enumerate()
-            .map(|(idx, line)| (line.todo_id, idx))
-            .collect();
+    /// The view whose rows are on screen, for the metadata density toggle
+    fn current_metadata_view(&self) -> MetadataView {
+        match self.mode {
+            AppMode::CompletedView => MetadataView::Completed,
+            AppMode::ScheduledView => MetadataView::Scheduled,
+            AppMode::Someday => MetadataView::Someday,
+            AppMode::Waiting => MetadataView::Waiting,
+            AppMode::Today => MetadataView::Today,
+            AppMode::ListFind => MetadataView::Find,
+            _ if self.use_tree_view => MetadataView::Tree,
+            _ => MetadataView::List,
+        }
+    }
 
-        let mut best_match = 0;
-        let mut best_distance = usize::MAX;
+    fn cycle_metadata_density(&mut self) -> anyhow::Result<()> {
+        let view = self.current_metadata_view();
+        let density = self.metadata_density.get(&view).copied().unwrap_or_default().next();
+        self.metadata_density.insert(view, density);
+        self.database.set_ui_state(view.state_key(), density.as_str())
+    }
 
-        for (idx, &match_id) in self.goto_matches.iter().enumerate() {
-            if let Some(&match_pos) = line_positions.get(&match_id) {
-                let distance = if match_pos >= current_selection {
-                    match_pos - current_selection
-                } else {
-                    current_selection - match_pos
-                };
+    /// On the first run with an empty database, offer to import from other todo tools
+    fn start_onboarding(&mut self) -> anyhow::Result<()> {
+        if self.database.get_ui_state("onboarding_done")?.is_some() {
+            return Ok(());
+        }
+        if !self.database.get_all_todos()?.is_empty() {
+            self.database.set_ui_state("onboarding_done", "1")?;
+            return Ok(());
+        }
 
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_match = idx;
-                }
-            }
+        self.import_sources = import::scan_sources().into_iter().map(|source| (source, true)).collect();
+        if self.import_sources.is_empty() {
+            self.database.set_ui_state("onboarding_done", "1")?;
+        } else {
+            self.import_list_state.select(Some(0));
+            self.mode = AppMode::Onboarding;
         }
+        Ok(())
+    }
 
-        best_match
+    fn finish_onboarding(&mut self) -> anyhow::Result<()> {
+        self.database.set_ui_state("onboarding_done", "1")?;
+        self.import_sources.clear();
+        self.mode = AppMode::List;
+        Ok(())
     }
 
-    fn navigate_to_next_goto_match(&mut self) {
-        if self.goto_matches.is_empty() {
-            return;
+    fn is_lock_enabled(&self) -> bool {
+        self.config.lock.passphrase_hash.is_some() || self.config.lock.passphrase_sha256.is_some()
+    }
+
+    fn idle_lock_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.config.lock.idle_minutes.max(1) * 60)
+    }
+
+    /// How long the event loop may block before the idle lock needs to be checked again
+    pub fn time_until_lock(&self) -> Option<std::time::Duration> {
+        if !self.is_lock_enabled() || self.mode == AppMode::Locked {
+            return None;
         }
+        Some(self.idle_lock_duration().saturating_sub(self.last_activity.elapsed()))
+    }
 
-        let start_index = self.goto_current_match_index.unwrap_or(0);
-        let matches_len = self.goto_matches.len();
-        let next_index = (start_index + 1) % matches_len;
+    /// Engage the lock screen if the app has been idle for longer than the configured period
+    pub fn check_idle_lock(&mut self) {
+        if let Some(remaining) = self.time_until_lock()
+            && remaining.is_zero() {
+            self.lock();
+        }
+    }
 
-        if let Some(&match_todo_id) = self.goto_matches.get(next_index) {
-            if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                self.goto_current_match_index = Some(next_index);
-                self.tree_list_state.select(Some(line_index));
-            }
+    fn sync_interval(&self) -> Option<std::time::Duration> {
+        let minutes = self.config.sync.interval_minutes;
+        (self.database.sync_url().is_some() && minutes > 0).then(|| std::time::Duration::from_secs(minutes * 60))
+    }
+
+    /// How long the event loop may block before background sync needs attention
+    pub fn time_until_sync(&self) -> Option<std::time::Duration> {
+        if self.sync_job.is_some() {
+            // Poll for the result while a sync is running
+            return Some(std::time::Duration::from_millis(200));
         }
+        self.next_sync.map(|at| at.saturating_duration_since(std::time::Instant::now()))
     }
 
-    fn navigate_to_previous_goto_match(&mut self) {
-        if self.goto_matches.is_empty() {
+    /// Tell the `[[hooks]]` listening for `event` about a change to `todo_id`
+    fn start_session(&mut self) {
+        if !self.config.metrics.enabled {
             return;
         }
+        let now = Utc::now();
+        match self.database.start_session(now) {
+            Ok(id) => self.session = Some(Session { id, started_at: now, ended_at: now, created: 0, completed: 0 }),
+            Err(e) => self.error_message = Some(format!("Session not recorded: {}", e)),
+        }
+    }
 
-        let start_index = self.goto_current_match_index.unwrap_or(0);
-        let matches_len = self.goto_matches.len();
-        let prev_index = if start_index == 0 {
-            matches_len - 1
-        } else {
-            start_index - 1
-        };
-
-        if let Some(&match_todo_id) = self.goto_matches.get(prev_index) {
-            if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
-                self.goto_current_match_index = Some(prev_index);
-                self.tree_list_state.select(Some(line_index));
-            }
+    /// Store the session's counts and how long it has lasted so far
+    fn save_session(&mut self) {
+        let Some(session) = self.session.as_mut() else { return };
+        session.ended_at = Utc::now();
+        if let Err(e) = self.database.update_session(session) {
+            self.error_message = Some(format!("Session not recorded: {}", e));
         }
     }
 
-    fn get_current_todos(&self) -> &Vec<Todo> {
-        match self.mode {
-            AppMode::CompletedView => &self.completed_todos,
-            _ => &self.incomplete_todos,
+    /// Close the session for good, on exit or before switching databases
+    pub fn end_session(&mut self) {
+        self.save_session();
+        self.session = None;
+    }
+
+    fn count_in_session(&mut self, change: &TodoEvent) {
+        let Some(session) = self.session.as_mut() else { return };
+        match change {
+            TodoEvent::Created(_) => session.created += 1,
+            // A cancelled todo is closed, but it wasn't done
+            TodoEvent::Completed(id) if self.database.get_status(*id).is_ok_and(|status| status == Status::Cancelled) => return,
+            TodoEvent::Completed(_) => session.completed += 1,
+            // `statuses` is refreshed after dispatch, so it still says whether this one was cancelled
+            TodoEvent::Uncompleted(id) if self.tree_manager.statuses.get(id) == Some(&Status::Cancelled) => return,
+            TodoEvent::Uncompleted(_) => session.completed = (session.completed - 1).max(0),
+            _ => return,
         }
+        self.save_session();
     }
 
-    fn get_current_list_state(&self) -> &ListState {
-        match self.mode {
-            AppMode::CompletedView => &self.completed_list_state,
-            _ if self.use_tree_view => &self.tree_list_state,
-            _ => &self.list_state,
+    /// Handle the changes the database reported since the last call: run `[[hooks]]`
+    /// and count them in the session. Returns how many there were.
+    fn dispatch_changes(&mut self) -> usize {
+        let changes: Vec<TodoEvent> = self.changes.try_iter().collect();
+        self.unflushed_changes |= !changes.is_empty();
+        for change in &changes {
+            self.count_in_session(change);
+            self.hook_runner.fire_for_change(&self.config.hooks, &self.database, change, self.current_profile.as_deref());
         }
+        changes.len()
     }
 
-    fn get_current_list_state_mut(&mut self) -> &mut ListState {
-        match self.mode {
-            AppMode::CompletedView => &mut self.completed_list_state,
-            _ if self.use_tree_view => &mut self.tree_list_state,
-            _ => &mut self.list_state,
+    /// Reload the views after changes nothing has refreshed for yet. Called by the main
+    /// loop after every key; handlers that need the new rows at once call `refresh_todos`.
+    pub fn process_changes(&mut self) -> anyhow::Result<()> {
+        if self.dispatch_changes() > 0 {
+            self.refresh_todos()?;
         }
+        Ok(())
     }
 
-    fn get_selected_todo(&self) -> Option<&Todo> {
-        match self.mode {
-            AppMode::ListFind => self.get_selected_search_todo(),
-            AppMode::CompletedView => {
-                // Completed view should always use completed_todos list, not tree
-                let selected = self.completed_list_state.selected()?;
-                self.completed_todos.get(selected)
-            }
-            AppMode::TreeSearch => {
-                // In tree search mode, still use tree selection
-                if self.use_tree_view {
-                    let selected = self.tree_list_state.selected()?;
-                    let rendered_lines = self.tree_manager.get_rendered_lines();
-                    let line = rendered_lines.get(selected)?;
-                    self.tree_manager.get_todo_by_id(line.todo_id)
-                } else {
-                    let todos = self.get_current_todos();
-                    let selected = self.get_current_list_state().selected()?;
-                    todos.get(selected)
+    /// How long the event loop waits for a key before redrawing anyway (`[display] tick_ms`)
+    pub fn tick_rate(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.display.tick_ms.max(50))
+    }
+
+    fn checkpoint_interval(&self) -> Option<std::time::Duration> {
+        let minutes = self.config.checkpoint.interval_minutes;
+        (minutes > 0).then(|| std::time::Duration::from_secs(minutes * 60))
+    }
+
+    /// Housekeeping between keypresses: bring in deferred todos whose start has come,
+    /// clear an old status message and checkpoint changes every `[checkpoint] interval_minutes`
+    pub fn tick(&mut self) -> anyhow::Result<()> {
+        let now = std::time::Instant::now();
+        // Another instance or a `tododb` command changed the database: show its changes
+        if self.database.changed_elsewhere()?
+            || self.scheduled_todos.first().is_some_and(|todo| !todo.is_deferred(Utc::now())) {
+            self.refresh_todos()?;
+            self.update_selection_after_refresh();
+        }
+
+        let lifetime = std::time::Duration::from_secs(self.config.display.message_seconds);
+        match (&self.status_message, &self.status_shown) {
+            (None, _) => self.status_shown = None,
+            (Some(message), Some((shown, since))) if message == shown => {
+                if !lifetime.is_zero() && now.duration_since(*since) >= lifetime {
+                    self.status_message = None;
+                    self.status_shown = None;
                 }
             }
-            _ => {
-                if self.use_tree_view {
-                    let selected = self.tree_list_state.selected()?;
+            (Some(message), _) => self.status_shown = Some((message.clone(), now)),
+        }
 
-                    // In move mode, index 0 is the virtual ROOT entry
-                    if self.mode == AppMode::Move && selected == 0 {
-                        return None; // ROOT is not a real todo
-                    }
+        self.tick_pomodoro(Utc::now())?;
 
-                    let rendered_lines = self.tree_manager.get_rendered_lines();
-                    let tree_index = if self.mode == AppMode::Move { selected - 1 } else { selected };
-                    let line = rendered_lines.get(tree_index)?;
-                    self.tree_manager.get_todo_by_id(line.todo_id)
-                } else {
-                    let todos = self.get_current_todos();
-                    let selected = self.get_current_list_state().selected()?;
-                    todos.get(selected)
+        match self.next_checkpoint {
+            None => self.next_checkpoint = self.checkpoint_interval().map(|interval| now + interval),
+            Some(at) if at <= now => {
+                self.next_checkpoint = self.checkpoint_interval().map(|interval| now + interval);
+                if std::mem::take(&mut self.unflushed_changes)
+                    && let Err(e) = self.database.checkpoint() {
+                    self.error_message = Some(format!("Checkpoint failed: {}", e));
                 }
             }
+            Some(_) => {}
         }
+        Ok(())
     }
 
-    pub fn handle_key_event(&mut self, key: KeyCode, modifiers: KeyModifiers) -> anyhow::Result<()> {
-        self.error_message = None;
+    /// Start a pomodoro on the selected todo, or stop the one running
+    fn toggle_pomodoro(&mut self) {
+        if self.pomodoro.take().is_some() {
+            self.status_message = Some("Pomodoro stopped".to_string());
+            return;
+        }
+        let Some((todo_id, title)) = self.get_selected_todo().map(|todo| (todo.id, todo.title.clone())) else { return };
+        let minutes = self.config.pomodoro.work_minutes;
+        self.pomodoro = Some(Pomodoro::start(todo_id, chrono::Duration::minutes(minutes), Utc::now()));
+        self.status_message = Some(format!("Pomodoro started: {} minutes on '{}'", minutes, title));
+    }
 
-        // Global help key - available from any mode except Help itself and text input modes
-        let is_in_text_input_mode = match self.mode {
-            AppMode::Create => true,
-            AppMode::ListFind if self.search_input_mode => true,
-            AppMode::TreeSearch if self.search_input_mode => true,
-            AppMode::IdModGoto if self.search_input_mode => true,
-            AppMode::ParentSearch => true,
-            _ => false,
+    /// Log the work and start the break when the work interval is up, end the cycle
+    /// when the break is
+    pub fn tick_pomodoro(&mut self, now: DateTime<Utc>) -> anyhow::Result<()> {
+        let break_length = chrono::Duration::minutes(self.config.pomodoro.break_minutes);
+        let Some(transition) = self.pomodoro.as_mut().and_then(|pomodoro| pomodoro.advance(break_length, now)) else {
+            return Ok(());
+        };
+        let message = match transition {
+            Transition::BreakStarted { work_started } => {
+                let Some(pomodoro) = &self.pomodoro else { return Ok(()) };
+                let todo_id = pomodoro.todo_id;
+                // The todo may have been deleted while it was being worked on
+                if self.database.get_todo_by_id(todo_id)?.is_some() {
+                    self.database.log_pomodoro(todo_id, work_started, pomodoro.phase_started)?;
+                    self.pomodoro_counts = self.database.get_pomodoro_counts()?;
+                }
+                format!("Pomodoro done: take a {} minute break", self.config.pomodoro.break_minutes)
+            }
+            Transition::Finished => {
+                self.pomodoro = None;
+                "Break over".to_string()
+            }
         };
+        if self.config.pomodoro.bell {
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        if let Some(command) = &self.config.pomodoro.notify
+            && let Err(e) = pomodoro::notify(command, &message) {
+            self.error_message = Some(format!("Pomodoro notify failed: {}", e));
+        }
+        self.status_message = Some(message);
+        Ok(())
+    }
 
-        if key == KeyCode::Char('a') && self.mode != AppMode::Help && !is_in_text_input_mode {
-            self.previous_mode = self.mode.clone();
-            self.mode = AppMode::Help;
-            return Ok(());
+    /// How long the event loop may block before finished hooks need reporting
+    pub fn time_until_hooks(&self) -> Option<std::time::Duration> {
+        self.hook_runner.is_running().then(|| std::time::Duration::from_millis(200))
+    }
+
+    /// Report hooks that failed since the last check
+    pub fn check_hooks(&mut self) {
+        if let Some(error) = self.hook_runner.finished_errors().first() {
+            self.error_message = Some(format!("Hook failed: {}", error));
         }
+    }
 
-        // Handle Ctrl+d: half-page scroll down
-        if key == KeyCode::Char('d') && modifiers.contains(KeyModifiers::CONTROL) && !is_in_text_input_mode {
-            self.half_page_down();
-            return Ok(());
+    /// Let running hooks finish before exiting, returning their errors
+    pub fn wait_for_hooks(&mut self) -> Vec<String> {
+        self.hook_runner.wait()
+    }
+
+    /// Start a background sync when one is due, and pick up the result of a finished one
+    pub fn check_background_sync(&mut self) {
+        if let Some(job) = self.sync_job.take_if(|job| job.is_finished()) {
+            match job.join() {
+                Ok(Ok(report)) => {
+                    if report.received > 0 {
+                        if let Err(e) = self.refresh_todos() {
+                            self.error_message = Some(format!("Failed to reload after sync: {}", e));
+                        }
+                        self.status_message = Some(format!("Synced: {} changes received", report.received));
+                    }
+                }
+                Ok(Err(e)) => self.error_message = Some(format!("Sync failed: {}", e)),
+                Err(_) => self.error_message = Some("Sync failed".to_string()),
+            }
         }
 
-        // Handle Ctrl+u: half-page scroll up
-        if key == KeyCode::Char('u') && modifiers.contains(KeyModifiers::CONTROL) && !is_in_text_input_mode {
-            self.half_page_up();
-            return Ok(());
+        if self.sync_job.is_none() && self.next_sync.is_some_and(|at| at <= std::time::Instant::now()) {
+            self.next_sync = self.sync_interval().map(|interval| std::time::Instant::now() + interval);
+            let (Some(url), Some(path)) = (self.database.sync_url(), self.database.path()) else {
+                return;
+            };
+            let (url, path, token) = (url.to_string(), path.to_string(), self.config.sync.token.clone());
+            // A connection of its own, so the UI stays responsive while the server answers
+            self.sync_job = Some(std::thread::spawn(move || {
+                let database = Database::new(&path)?;
+                sync::sync(&database, &url, token.as_deref())
+            }));
         }
+    }
 
-        // Handle 'h' key: toggle hidden status of selected todo in tree view
-        if key == KeyCode::Char('h') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
-            if let Some(todo) = self.get_selected_todo() {
-                let todo_id = todo.id;
-                if let Err(e) = self.database.toggle_todo_hidden(todo_id) {
-                    self.error_message = Some(format!("Failed to toggle hidden status: {}", e));
-                } else {
-                    self.refresh_todos()?;
-                    self.update_selection_after_refresh();
-                }
+    fn lock(&mut self) {
+        if self.mode != AppMode::Locked {
+            self.pre_lock_mode = self.mode.clone();
+        }
+        self.mode = AppMode::Locked;
+        self.lock_input.clear();
+        self.error_message = None;
+    }
+
+    pub fn verify_passphrase(&self, passphrase: &str) -> bool {
+        use sha2::{Digest, Sha256};
+
+        match (&self.config.lock.passphrase_hash, &self.config.lock.passphrase_sha256) {
+            (Some(stored), _) => passphrase::verify_lock_passphrase(passphrase, stored),
+            (None, Some(expected)) => {
+                let digest = format!("{:x}", Sha256::digest(passphrase.as_bytes()));
+                passphrase::constant_time_eq(digest.as_bytes(), expected.trim().to_ascii_lowercase().as_bytes())
             }
-            return Ok(());
+            (None, None) => true,
         }
+    }
 
-        // Handle 'H' key: toggle showing/hiding hidden items in tree view
-        if key == KeyCode::Char('H') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
-            self.show_hidden_items = !self.show_hidden_items;
-            self.refresh_todos()?;
-            self.update_selection_after_refresh();
-            return Ok(());
+    pub fn refresh_todos(&mut self) -> anyhow::Result<()> {
+        self.dispatch_changes();
+        // Remember which todo each view has selected, to follow it to its new row, and
+        // where to go instead if it's gone (completed, hidden, moved out of the view)
+        let root_row = usize::from(self.mode == AppMode::Move);
+        let list_selected = self.list_state.selected().map(|row| (row, selection_candidates(&self.incomplete_todos, row)));
+        let completed_selected = self.completed_list_state.selected().map(|row| (row, selection_candidates(&self.completed_todos, row)));
+        let scheduled_selected = self.scheduled_list_state.selected().map(|row| (row, selection_candidates(&self.scheduled_todos, row)));
+        let someday_selected = self.someday_list_state.selected().map(|row| (row, selection_candidates(&self.someday_todos, row)));
+        let waiting_selected = self.waiting_list_state.selected().map(|row| (row, selection_candidates(&self.waiting_todos, row)));
+        let today_selected = self.today_list_state.selected().map(|row| (row, selection_candidates(&self.today_todos, row)));
+        let tree_selected = self.tree_list_state.selected().and_then(|row| {
+            let todo_id = self.tree_manager.get_rendered_lines().get(row.checked_sub(root_row)?)?.todo_id;
+            Some((row, std::iter::once(todo_id).chain(self.tree_manager.selection_fallbacks(todo_id)).collect::<Vec<_>>()))
+        });
+
+        self.incomplete_todos = self.database.get_incomplete_todos(self.current_parent)?;
+        // Reload as many completed todos as the view has paged in so far
+        let completed_loaded = self.completed_todos.len().max(COMPLETED_PAGE_SIZE);
+        let completed_filter = self.completed_filter();
+        self.completed_todos = self.database.get_completed_todos(&completed_filter, 0, completed_loaded)?;
+        self.completed_total = self.database.count_completed_todos(&completed_filter)?;
+        
+        self.attachment_counts = self.database.get_attachment_counts()?;
+        self.note_counts = self.database.get_note_counts()?;
+        self.pomodoro_counts = self.database.get_pomodoro_counts()?;
+        self.estimates = self.database.get_estimates()?;
+        self.labels = self.database.get_labels()?;
+        self.waiting = self.database.get_waiting()?;
+
+        // Rebuild tree view with all todos
+        let mut all_todos = self.database.get_all_todos()?;
+
+        // Deferred todos and their subtasks wait in the scheduled view until they start
+        let now = Utc::now();
+        let stale_before = now - chrono::Duration::days(self.config.review.stale_days);
+        let updated_at = self.database.get_updated_at()?;
+        self.stale_ages = all_todos
+            .iter()
+            .filter(|todo| !todo.is_completed())
+            .filter_map(|todo| updated_at.get(&todo.id).filter(|at| **at < stale_before).map(|at| (todo.id, (now - *at).num_days())))
+            .collect();
+        // Someday/maybe todos wait in their own view, subtasks and all, until promoted
+        let someday = self.database.get_someday_ids()?;
+        let set_aside = filter::with_descendants(&all_todos, &someday);
+        self.someday_todos = all_todos.iter().filter(|todo| someday.contains(&todo.id) && !todo.is_completed()).cloned().collect();
+        self.someday_todos.sort_by_key(|todo| (todo.title.to_lowercase(), todo.id));
+        self.incomplete_todos.retain(|todo| !set_aside.contains(&todo.id));
+        all_todos.retain(|todo| !set_aside.contains(&todo.id));
+
+        // Delegated todos stay in the tree and list, and are also gathered by follow-up date
+        self.waiting_todos = all_todos.iter().filter(|todo| self.waiting.contains_key(&todo.id) && !todo.is_completed()).cloned().collect();
+        self.waiting_todos.sort_by_key(|todo| {
+            let follow_up = self.waiting.get(&todo.id).and_then(|waiting| waiting.follow_up);
+            (follow_up.is_none(), follow_up, todo.id)
+        });
+
+        self.refresh_daily_plan()?;
+
+        let pinned = self.database.get_pinned_ids()?;
+        self.pinned_todos = all_todos.iter().filter(|todo| pinned.contains(&todo.id) && !todo.is_completed()).cloned().collect();
+        self.pinned_todos.sort_by_key(|todo| (todo.due_by.is_none(), todo.due_by, todo.id));
+
+        let deferred = filter::deferred_ids(&all_todos, now);
+        self.scheduled_todos = all_todos.iter().filter(|todo| todo.is_deferred(now)).cloned().collect();
+        self.scheduled_todos.sort_by_key(|todo| (todo.start_at, todo.id));
+        self.incomplete_todos.retain(|todo| !deferred.contains(&todo.id));
+        all_todos.retain(|todo| !deferred.contains(&todo.id));
+
+        // An active saved filter constrains the list and tree views (tree keeps ancestors for context)
+        if let Some((_, active_filter)) = &self.active_filter {
+            let matching: HashSet<i64> = self.database.query_todos(active_filter)?
+                .iter()
+                .map(|todo| todo.id)
+                .collect();
+            self.incomplete_todos.retain(|todo| matching.contains(&todo.id));
+            all_todos = filter::retain_with_ancestors(all_todos, &matching);
+            self.filter_context = all_todos.iter().map(|todo| todo.id).filter(|id| !matching.contains(id)).collect();
+        } else {
+            self.filter_context.clear();
         }
 
-        // Handle 'g' key: goto mode for id_mod navigation in tree view
-        if key == KeyCode::Char('g') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
-            self.mode = AppMode::IdModGoto;
-            self.goto_query.clear();
-            self.goto_matches.clear();
-            self.goto_current_match_index = None;
-            self.search_input_mode = true;
-            return Ok(());
+        self.tree_manager.id_labels = match self.config.display.ids {
+            IdDisplay::Mod => HashMap::new(),
+            IdDisplay::Full => all_todos.iter().map(|todo| (todo.id, todo.id.to_string())).collect(),
+            IdDisplay::Code => self.database.refresh_short_codes()?,
+        };
+        self.tree_manager.positions = self.database.get_positions()?;
+        self.tree_manager.statuses = self.database.get_statuses()?;
+        self.tree_manager.flags = self.database.get_flags()?;
+        let hoisted = self.tree_manager.root;
+        self.tree_manager.rebuild_from_todos_with_hidden_filter(all_todos, self.show_hidden_items);
+        // A hoisted todo that was deleted, completed or filtered away gives the whole tree back
+        if let Some(root) = hoisted
+            && self.tree_manager.get_line_index_for_todo(root).is_none() {
+            self.tree_manager.root = None;
+            self.tree_manager.rebuild_from_todos_with_hidden_filter(self.tree_manager.todos.values().cloned().collect(), true);
         }
 
-        match self.mode {
-            AppMode::List => self.handle_list_key(key)?,
-            AppMode::CompletedView => self.handle_completed_view_key(key)?,
-            AppMode::Create => self.handle_create_key(key)?,
-            AppMode::ConfirmDelete => self.handle_delete_key(key)?,
-            AppMode::ListFind => self.handle_list_find_key(key)?,
-            AppMode::TreeSearch => self.handle_tree_search_key(key)?,
-            AppMode::ParentSearch => self.handle_parent_search_key(key)?,
-            AppMode::Move => self.handle_move_key(key)?,
-            AppMode::Help => self.handle_help_key(key)?,
-            AppMode::IdModGoto => self.handle_idmod_goto_key(key)?,
+        follow_todo(&mut self.list_state, list_selected, &self.incomplete_todos);
+        follow_todo(&mut self.completed_list_state, completed_selected, &self.completed_todos);
+        follow_todo(&mut self.scheduled_list_state, scheduled_selected, &self.scheduled_todos);
+        follow_todo(&mut self.someday_list_state, someday_selected, &self.someday_todos);
+        follow_todo(&mut self.waiting_list_state, waiting_selected, &self.waiting_todos);
+        follow_todo(&mut self.today_list_state, today_selected, &self.today_todos);
+        if let Some((row, candidates)) = tree_selected
+            && let Some(line) = candidates.iter().find_map(|id| self.tree_manager.get_line_index_for_todo(*id)) {
+            follow_selection(&mut self.tree_list_state, row, line + root_row);
+        }
+        
+        // Initialize tree selection if we have items
+        if !self.tree_manager.get_rendered_lines().is_empty() && self.tree_list_state.selected().is_none() {
+            self.tree_list_state.select(Some(0));
         }
+        
         Ok(())
     }
 
-    fn handle_list_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
-        match key {
-            KeyCode::Char('q') => self.should_quit = true,
-            KeyCode::Char('t') => {
-                if self.use_tree_view {
-                    // Branch-level toggle: expand/collapse the selected item
-                    if let Some(selected) = self.tree_list_state.selected() {
-                        if let Some(line) = self.tree_manager.get_rendered_lines().get(selected) {
-                            if line.has_children {
-                                self.tree_manager.toggle_expansion(line.todo_id);
-                                // Maintain selection after toggle
-                                self.update_tree_selection_after_toggle(selected);
-                            }
-                        }
-                    }
-                } else {
-                    // Switch to tree view
-                    self.use_tree_view = true;
-                    if !self.tree_manager.get_rendered_lines().is_empty() {
-                        self.tree_list_state.select(Some(0));
-                    }
-                }
-            }
-            KeyCode::Char('f') => {
-                // List Find: flat search results view
-                self.mode = AppMode::ListFind;
-                self.search_query.clear();
+    pub fn update_scrollbar_states(&mut self) {
+        // Update list scrollbar
+        let list_len = self.incomplete_todos.len();
+        self.list_scrollbar_state = self.list_scrollbar_state
+            .content_length(list_len)
+            .position(self.list_state.selected().unwrap_or(0));
+
+        // Update tree scrollbar
+        let tree_len = self.tree_manager.get_rendered_lines().len();
+        self.tree_scrollbar_state = self.tree_scrollbar_state
+            .content_length(tree_len)
+            .position(self.tree_list_state.selected().unwrap_or(0));
+
+        // Update completed scrollbar
+        let completed_len = self.completed_todos.len();
+        self.completed_scrollbar_state = self.completed_scrollbar_state
+            .content_length(completed_len)
+            .position(self.completed_list_state.selected().unwrap_or(0));
+
+        // Update scheduled scrollbar
+        let scheduled_len = self.scheduled_todos.len();
+        self.scheduled_scrollbar_state = self.scheduled_scrollbar_state
+            .content_length(scheduled_len)
+            .position(self.scheduled_list_state.selected().unwrap_or(0));
+
+        // Update someday scrollbar
+        let someday_len = self.someday_todos.len();
+        self.someday_scrollbar_state = self.someday_scrollbar_state
+            .content_length(someday_len)
+            .position(self.someday_list_state.selected().unwrap_or(0));
+
+        // Update waiting scrollbar
+        let waiting_len = self.waiting_todos.len();
+        self.waiting_scrollbar_state = self.waiting_scrollbar_state
+            .content_length(waiting_len)
+            .position(self.waiting_list_state.selected().unwrap_or(0));
+
+        // Update today scrollbar
+        let today_len = self.today_todos.len();
+        self.today_scrollbar_state = self.today_scrollbar_state
+            .content_length(today_len)
+            .position(self.today_list_state.selected().unwrap_or(0));
+    }
+
+    fn get_due_date_style(&self, todo: &Todo) -> Color {
+        // Only color incomplete todos based on due date; closed ones by how they closed
+        let status = self.tree_manager.status_of(todo);
+        if todo.is_completed() {
+            return status.color();
+        }
+        if let Some(color) = self.tree_manager.flags.get(&todo.id).and_then(Flag::color) {
+            return color;
+        }
+
+        if let Some(due_by) = todo.due_by {
+            let now = Utc::now();
+            let diff = due_by.signed_duration_since(now);
+
+            if diff.num_seconds() < 0 {
+                // Past due - RED
+                CatppuccinFrappe::RED
+            } else if diff.num_days() < 7 {
+                // Due within 1 week (less than 7 days) - TEAL
+                CatppuccinFrappe::TEAL
+            } else {
+                // More than 1 week away (>= 7 days) - default color
+                CatppuccinFrappe::INCOMPLETE
+            }
+        } else if matches!(status, Status::InProgress | Status::Blocked) {
+            status.color()
+        } else if self.stale_ages.contains_key(&todo.id) {
+            // Untouched for longer than [review] stale_days - dimmed
+            CatppuccinFrappe::STALE
+        } else {
+            // No due date - default color
+            CatppuccinFrappe::INCOMPLETE
+        }
+    }
+
+    /// Append the next page of completed todos; returns whether any were loaded
+    fn load_more_completed(&mut self) -> anyhow::Result<bool> {
+        if self.completed_todos.len() >= self.completed_total {
+            return Ok(false);
+        }
+        let page = self.database.get_completed_todos(&self.completed_filter(), self.completed_todos.len(), COMPLETED_PAGE_SIZE)?;
+        let loaded = !page.is_empty();
+        self.completed_todos.extend(page);
+        Ok(loaded)
+    }
+
+    fn completed_filter(&self) -> CompletedFilter {
+        CompletedFilter { since: self.completed_range.since(Local::now(), self.config.dates.week_start), root: self.completed_root.as_ref().map(|(id, _)| *id) }
+    }
+
+    /// Narrow (or widen) the completed view and reload it from the first page
+    fn set_completed_filter(&mut self, range: CompletedRange, root: Option<(i64, String)>) -> anyhow::Result<()> {
+        let selected_id = self.get_selected_todo().map(|todo| todo.id);
+        self.completed_range = range;
+        self.completed_root = root;
+        self.completed_todos.clear();
+        self.refresh_todos()?;
+        self.completed_list_state.select(if self.completed_todos.is_empty() { None } else { Some(0) });
+        self.reselect_todo(selected_id);
+        Ok(())
+    }
+
+    /// `p` in the completed view: narrow it to the top-level project the selected todo
+    /// belongs to, or back to every project
+    fn toggle_completed_project(&mut self) -> anyhow::Result<()> {
+        if self.completed_root.is_some() {
+            return self.set_completed_filter(self.completed_range, None);
+        }
+        let Some(mut project) = self.get_selected_todo().cloned() else {
+            return Ok(());
+        };
+        while let Some(parent) = project.parent_id.map(|id| self.database.get_todo_by_id(id)).transpose()?.flatten() {
+            project = parent;
+        }
+        self.set_completed_filter(self.completed_range, Some((project.id, project.title)))
+    }
+
+    fn update_tree_search_matches(&mut self) -> anyhow::Result<()> {
+        if self.search_query.is_empty() {
+            self.search_matches.clear();
+            self.current_match_index = None;
+        } else {
+            let new_matches: Vec<i64> = self.database.search_todos(&self.search_query)?
+                .into_iter()
+                .map(|todo| todo.id)
+                .collect();
+            
+            // Only re-sort if matches have actually changed
+            if new_matches != self.search_matches {
+                self.search_matches = new_matches;
+                // Sort matches by their appearance order in the tree
+                self.sort_matches_by_tree_order();
+                
+                // Find closest match to current selection or start from first match
+                self.current_match_index = if self.search_matches.is_empty() {
+                    None
+                } else {
+                    Some(self.find_closest_match_index())
+                };
+
+                // Automatically move cursor to the current match
+                if let Some(current_match_index) = self.current_match_index
+                    && let Some(&match_todo_id) = self.search_matches.get(current_match_index)
+                    && let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                    self.tree_list_state.select(Some(line_index));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn sort_matches_by_tree_order(&mut self) {
+        let rendered_lines = self.tree_manager.get_rendered_lines();
+        let line_order: std::collections::HashMap<i64, usize> = rendered_lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (line.todo_id, idx))
+            .collect();
+            
+        self.search_matches.sort_by_key(|&todo_id| {
+            line_order.get(&todo_id).copied().unwrap_or(usize::MAX)
+        });
+    }
+    
+    fn find_closest_match_index(&self) -> usize {
+        if self.search_matches.is_empty() {
+            return 0;
+        }
+        
+        let current_selection = self.tree_list_state.selected().unwrap_or(0);
+        let rendered_lines = self.tree_manager.get_rendered_lines();
+        
+        if let Some(current_line) = rendered_lines.get(current_selection) {
+            let current_todo_id = current_line.todo_id;
+            
+            // If current selection is a match, use it
+            if let Some(pos) = self.search_matches.iter().position(|&id| id == current_todo_id) {
+                return pos;
+            }
+        }
+        
+        // Otherwise, find the closest match by tree position
+        let line_positions: std::collections::HashMap<i64, usize> = rendered_lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (line.todo_id, idx))
+            .collect();
+            
+        let mut best_match = 0;
+        let mut best_distance = usize::MAX;
+        
+        for (idx, &match_id) in self.search_matches.iter().enumerate() {
+            if let Some(&match_pos) = line_positions.get(&match_id) {
+                let distance = match_pos.abs_diff(current_selection);
+                
+                if distance < best_distance {
+                    best_distance = distance;
+                    best_match = idx;
+                }
+            }
+        }
+        
+        best_match
+    }
+    
+    fn navigate_to_next_match(&mut self) {
+        if self.search_matches.is_empty() {
+            return;
+        }
+        
+        let start_index = self.current_match_index.unwrap_or(0);
+        let matches_len = self.search_matches.len();
+        
+        // Try to find the next visible match, starting from the next position
+        for i in 1..=matches_len {
+            let next_index = (start_index + i) % matches_len;
+            
+            if let Some(&match_todo_id) = self.search_matches.get(next_index) {
+                // First check if it's already visible
+                if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                    self.current_match_index = Some(next_index);
+                    self.tree_list_state.select(Some(line_index));
+                    return;
+                }
+                
+                // If not visible, expand parent nodes to make it visible
+                let opened_nodes = self.expand_path_to_todo(match_todo_id);
+                if !opened_nodes.is_empty() {
+                    // Track which nodes we opened for search
+                    for node_id in opened_nodes {
+                        self.search_opened_nodes.insert(node_id);
+                    }
+                    
+                    // After expanding, it should be visible now
+                    if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                        self.current_match_index = Some(next_index);
+                        self.tree_list_state.select(Some(line_index));
+                        return;
+                    }
+                }
+            }
+        }
+    }
+    
+    fn navigate_to_previous_match(&mut self) {
+        if self.search_matches.is_empty() {
+            return;
+        }
+        
+        let start_index = self.current_match_index.unwrap_or(0);
+        let matches_len = self.search_matches.len();
+        
+        // Try to find the previous visible match, starting from the previous position
+        for i in 1..=matches_len {
+            let prev_index = if start_index >= i {
+                start_index - i
+            } else {
+                matches_len - (i - start_index)
+            };
+            
+            if let Some(&match_todo_id) = self.search_matches.get(prev_index) {
+                // First check if it's already visible
+                if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                    self.current_match_index = Some(prev_index);
+                    self.tree_list_state.select(Some(line_index));
+                    return;
+                }
+                
+                // If not visible, expand parent nodes to make it visible
+                let opened_nodes = self.expand_path_to_todo(match_todo_id);
+                if !opened_nodes.is_empty() {
+                    // Track which nodes we opened for search
+                    for node_id in opened_nodes {
+                        self.search_opened_nodes.insert(node_id);
+                    }
+                    
+                    // After expanding, it should be visible now
+                    if let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                        self.current_match_index = Some(prev_index);
+                        self.tree_list_state.select(Some(line_index));
+                        return;
+                    }
+                }
+            }
+        }
+    }
+    
+    
+    fn expand_path_to_todo(&mut self, todo_id: i64) -> Vec<i64> {
+        self.tree_manager.expand_path_to_todo(todo_id)
+    }
+    
+    fn restore_pre_search_expansion_state(&mut self) {
+        let mut needs_rebuild = false;
+        
+        // For each node we opened during search, restore its original state
+        for &node_id in &self.search_opened_nodes {
+            // Get the original state (default to false if it wasn't tracked)
+            let original_state = self.pre_search_expansion_state.get(&node_id).copied().unwrap_or(false);
+            let current_state = self.tree_manager.expansion_states.get(&node_id).copied().unwrap_or(false);
+            
+            // Only change if current state differs from original
+            if current_state != original_state {
+                if original_state {
+                    self.tree_manager.expansion_states.insert(node_id, true);
+                } else {
+                    self.tree_manager.expansion_states.remove(&node_id);
+                }
+                needs_rebuild = true;
+            }
+        }
+        
+        // Clear tracking data
+        self.search_opened_nodes.clear();
+        self.pre_search_expansion_state.clear();
+        
+        // Rebuild tree if we made changes
+        if needs_rebuild {
+            let all_todos = self.tree_manager.todos.values().cloned().collect();
+            self.tree_manager.rebuild_from_todos_with_hidden_filter(all_todos, self.show_hidden_items);
+        }
+    }
+
+    pub fn update_search_results(&mut self) -> anyhow::Result<()> {
+        self.search_results = self.database.search_todos(&self.search_query)?;
+        if self.mode == AppMode::ParentSearch {
+            let anchor = self.get_selected_todo().map(|todo| todo.id);
+            let results = std::mem::take(&mut self.search_results);
+            self.search_results = tree::rank_parent_candidates(results, &self.database.get_all_todos()?, anchor, self.move_todo_id);
+        }
+        // Reset selection when search results change
+        if !self.search_results.is_empty() {
+            self.search_list_state.select(Some(0));
+        } else {
+            self.search_list_state.select(None);
+        }
+        Ok(())
+    }
+
+    fn update_goto_matches(&mut self) -> anyhow::Result<()> {
+        if self.goto_query.is_empty() {
+            self.goto_matches.clear();
+            self.goto_current_match_index = None;
+        } else {
+            // A number matches the id shown (`id % 100` by default); with short codes,
+            // the code itself does, case-insensitively
+            let target_id_mod = self.goto_query.parse::<i64>().ok();
+            let rendered_lines = self.tree_manager.get_rendered_lines();
+            let mut new_matches: Vec<i64> = rendered_lines
+                .iter()
+                .filter_map(|line| {
+                    self.tree_manager.get_todo_by_id(line.todo_id)
+                        .filter(|todo| {
+                            Some(todo.id_mod()) == target_id_mod
+                                || self.tree_manager.id_label(todo).eq_ignore_ascii_case(&self.goto_query)
+                        })
+                        .map(|_| line.todo_id)
+                })
+                .collect();
+
+            // A code (or an id past 99) names exactly one todo, so it's worth opening
+            // the tree to reach it
+            if new_matches.is_empty()
+                && target_id_mod.is_none_or(|number| number >= 100)
+                && let Some(todo_id) = self.database.resolve_todo_reference(&self.goto_query)?
+                && self.tree_manager.get_todo_by_id(todo_id).is_some()
+            {
+                self.expand_path_to_todo(todo_id);
+                if self.tree_manager.get_line_index_for_todo(todo_id).is_some() {
+                    new_matches.push(todo_id);
+                }
+            }
+
+            // Only re-sort if matches have actually changed
+            if new_matches != self.goto_matches {
+                self.goto_matches = new_matches;
+                // Sort matches by their appearance order in the tree
+                self.sort_goto_matches_by_tree_order();
+
+                // Find closest match to current selection or start from first match
+                self.goto_current_match_index = if self.goto_matches.is_empty() {
+                    None
+                } else {
+                    Some(self.find_closest_goto_match_index())
+                };
+
+                // Automatically move cursor to the current match
+                if let Some(current_match_index) = self.goto_current_match_index
+                    && let Some(&match_todo_id) = self.goto_matches.get(current_match_index)
+                    && let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+                    self.tree_list_state.select(Some(line_index));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sort_goto_matches_by_tree_order(&mut self) {
+        let rendered_lines = self.tree_manager.get_rendered_lines();
+        let line_order: std::collections::HashMap<i64, usize> = rendered_lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (line.todo_id, idx))
+            .collect();
+
+        self.goto_matches.sort_by_key(|&todo_id| {
+            line_order.get(&todo_id).copied().unwrap_or(usize::MAX)
+        });
+    }
+
+    fn find_closest_goto_match_index(&self) -> usize {
+        if self.goto_matches.is_empty() {
+            return 0;
+        }
+
+        let current_selection = self.tree_list_state.selected().unwrap_or(0);
+        let rendered_lines = self.tree_manager.get_rendered_lines();
+
+        if let Some(current_line) = rendered_lines.get(current_selection) {
+            let current_todo_id = current_line.todo_id;
+
+            // If current selection is a match, use it
+            if let Some(pos) = self.goto_matches.iter().position(|&id| id == current_todo_id) {
+                return pos;
+            }
+        }
+
+        // Otherwise, find the closest match by tree position
+        let line_positions: std::collections::HashMap<i64, usize> = rendered_lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (line.todo_id, idx))
+            .collect();
+
+        let mut best_match = 0;
+        let mut best_distance = usize::MAX;
+
+        for (idx, &match_id) in self.goto_matches.iter().enumerate() {
+            if let Some(&match_pos) = line_positions.get(&match_id) {
+                let distance = match_pos.abs_diff(current_selection);
+
+                if distance < best_distance {
+                    best_distance = distance;
+                    best_match = idx;
+                }
+            }
+        }
+
+        best_match
+    }
+
+    fn navigate_to_next_goto_match(&mut self) {
+        if self.goto_matches.is_empty() {
+            return;
+        }
+
+        let start_index = self.goto_current_match_index.unwrap_or(0);
+        let matches_len = self.goto_matches.len();
+        let next_index = (start_index + 1) % matches_len;
+
+        if let Some(&match_todo_id) = self.goto_matches.get(next_index)
+            && let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+            self.goto_current_match_index = Some(next_index);
+            self.tree_list_state.select(Some(line_index));
+        }
+    }
+
+    fn navigate_to_previous_goto_match(&mut self) {
+        if self.goto_matches.is_empty() {
+            return;
+        }
+
+        let start_index = self.goto_current_match_index.unwrap_or(0);
+        let matches_len = self.goto_matches.len();
+        let prev_index = if start_index == 0 {
+            matches_len - 1
+        } else {
+            start_index - 1
+        };
+
+        if let Some(&match_todo_id) = self.goto_matches.get(prev_index)
+            && let Some(line_index) = self.tree_manager.get_line_index_for_todo(match_todo_id) {
+            self.goto_current_match_index = Some(prev_index);
+            self.tree_list_state.select(Some(line_index));
+        }
+    }
+
+    fn get_current_todos(&self) -> &Vec<Todo> {
+        match self.mode {
+            AppMode::CompletedView => &self.completed_todos,
+            AppMode::ScheduledView => &self.scheduled_todos,
+            AppMode::Someday => &self.someday_todos,
+            AppMode::Waiting => &self.waiting_todos,
+            AppMode::Today => &self.today_todos,
+            _ => &self.incomplete_todos,
+        }
+    }
+
+    fn get_current_list_state(&self) -> &ListState {
+        match self.mode {
+            AppMode::CompletedView => &self.completed_list_state,
+            AppMode::ScheduledView => &self.scheduled_list_state,
+            AppMode::Someday => &self.someday_list_state,
+            AppMode::Waiting => &self.waiting_list_state,
+            AppMode::Today => &self.today_list_state,
+            _ if self.use_tree_view => &self.tree_list_state,
+            _ => &self.list_state,
+        }
+    }
+
+    fn get_current_list_state_mut(&mut self) -> &mut ListState {
+        match self.mode {
+            AppMode::CompletedView => &mut self.completed_list_state,
+            AppMode::ScheduledView => &mut self.scheduled_list_state,
+            AppMode::Someday => &mut self.someday_list_state,
+            AppMode::Waiting => &mut self.waiting_list_state,
+            AppMode::Today => &mut self.today_list_state,
+            _ if self.use_tree_view => &mut self.tree_list_state,
+            _ => &mut self.list_state,
+        }
+    }
+
+    fn get_selected_todo(&self) -> Option<&Todo> {
+        match self.mode {
+            AppMode::ListFind => self.get_selected_search_todo(),
+            AppMode::CompletedView => {
+                // Completed view should always use completed_todos list, not tree
+                let selected = self.completed_list_state.selected()?;
+                self.completed_todos.get(selected)
+            }
+            AppMode::ScheduledView => {
+                let selected = self.scheduled_list_state.selected()?;
+                self.scheduled_todos.get(selected)
+            }
+            AppMode::Someday => {
+                let selected = self.someday_list_state.selected()?;
+                self.someday_todos.get(selected)
+            }
+            AppMode::Waiting => {
+                let selected = self.waiting_list_state.selected()?;
+                self.waiting_todos.get(selected)
+            }
+            AppMode::Today => {
+                let selected = self.today_list_state.selected()?;
+                self.today_todos.get(selected)
+            }
+            // Prompts act on the todo selected in the view they were opened from
+            AppMode::DeferPrompt | AppMode::NotePrompt | AppMode::QuickEdit | AppMode::DelegatePrompt if self.previous_mode == AppMode::ScheduledView => {
+                let selected = self.scheduled_list_state.selected()?;
+                self.scheduled_todos.get(selected)
+            }
+            AppMode::DeferPrompt | AppMode::NotePrompt | AppMode::QuickEdit | AppMode::DelegatePrompt if self.previous_mode == AppMode::Someday => {
+                let selected = self.someday_list_state.selected()?;
+                self.someday_todos.get(selected)
+            }
+            AppMode::DeferPrompt | AppMode::NotePrompt | AppMode::QuickEdit | AppMode::DelegatePrompt if self.previous_mode == AppMode::Waiting => {
+                let selected = self.waiting_list_state.selected()?;
+                self.waiting_todos.get(selected)
+            }
+            AppMode::DeferPrompt | AppMode::NotePrompt | AppMode::QuickEdit | AppMode::DelegatePrompt if self.previous_mode == AppMode::Today => {
+                let selected = self.today_list_state.selected()?;
+                self.today_todos.get(selected)
+            }
+            AppMode::NotePrompt | AppMode::QuickEdit | AppMode::History if self.previous_mode == AppMode::CompletedView => {
+                let selected = self.completed_list_state.selected()?;
+                self.completed_todos.get(selected)
+            }
+            AppMode::TreeSearch => {
+                // In tree search mode, still use tree selection
+                if self.use_tree_view {
+                    let selected = self.tree_list_state.selected()?;
+                    let rendered_lines = self.tree_manager.get_rendered_lines();
+                    let line = rendered_lines.get(selected)?;
+                    self.tree_manager.get_todo_by_id(line.todo_id)
+                } else {
+                    let todos = self.get_current_todos();
+                    let selected = self.get_current_list_state().selected()?;
+                    todos.get(selected)
+                }
+            }
+            _ => {
+                if self.use_tree_view {
+                    let selected = self.tree_list_state.selected()?;
+
+                    // In move mode, index 0 is the virtual ROOT entry
+                    if self.mode == AppMode::Move && selected == 0 {
+                        return None; // ROOT is not a real todo
+                    }
+
+                    let rendered_lines = self.tree_manager.get_rendered_lines();
+                    let tree_index = if self.mode == AppMode::Move { selected - 1 } else { selected };
+                    let line = rendered_lines.get(tree_index)?;
+                    self.tree_manager.get_todo_by_id(line.todo_id)
+                } else {
+                    let todos = self.get_current_todos();
+                    let selected = self.get_current_list_state().selected()?;
+                    todos.get(selected)
+                }
+            }
+        }
+    }
+
+    /// Text pasted into the terminal. Quick capture takes it whole, so a pasted list
+    /// becomes several todos at once; everywhere else it's typed key by key.
+    pub fn handle_paste(&mut self, text: &str) -> anyhow::Result<()> {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if self.mode == AppMode::Capture {
+            self.error_message = None;
+            self.last_activity = std::time::Instant::now();
+            self.capture_input.push_str(&text);
+            self.capture_confirm = None;
+            self.refresh_tag_completions();
+            return Ok(());
+        }
+        // Quick add is a single line
+        if self.mode == AppMode::QuickAdd {
+            self.error_message = None;
+            self.last_activity = std::time::Instant::now();
+            self.quick_add_input.push_str(&text.replace('\n', " "));
+            self.refresh_tag_completions();
+            return Ok(());
+        }
+        for c in text.chars() {
+            let key = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+            self.handle_key_event(key, KeyModifiers::NONE)?;
+        }
+        Ok(())
+    }
+
+    /// Modes that take typed text (or their own letter keys), where the global one-key
+    /// commands like `a` for help are off
+    fn is_in_text_input_mode(&self) -> bool {
+        match self.mode {
+            AppMode::Create => true,
+            AppMode::ListFind if self.search_input_mode => true,
+            AppMode::TreeSearch if self.search_input_mode => true,
+            AppMode::IdModGoto if self.search_input_mode => true,
+            AppMode::ParentSearch => true,
+            AppMode::FilterPicker => true,
+            AppMode::FilterPrompt => true,
+            AppMode::DeferPrompt => true,
+            AppMode::DelegatePrompt => true,
+            AppMode::NotePrompt => true,
+            AppMode::QuickEdit => true,
+            AppMode::Attachments => true,
+            AppMode::Onboarding => true,
+            AppMode::Capture => true,
+            AppMode::QuickAdd => true,
+            AppMode::TemplatePicker => true,
+            AppMode::CopyMenu => true,
+            AppMode::ProfilePicker => true,
+            AppMode::Stats => true,
+            AppMode::Review => true,
+            AppMode::Agenda => true,
+            AppMode::History => true,
+            AppMode::ContextPicker => true,
+            AppMode::Help if self.help_search_input => true,
+            _ => false,
+        }
+    }
+
+    /// The key a `[keys]` alias stands for, or the key itself
+    fn resolve_key_alias(&self, c: char) -> char {
+        self.config.keys.aliases.get(&c).copied().unwrap_or(c)
+    }
+
+    /// `keys` as the help page and footer show them, e.g. "d" as "d or x" with `x = "d"`
+    /// in `[keys]`
+    fn keys_with_aliases(&self, keys: &str) -> String {
+        let mut aliases: Vec<char> = self
+            .config
+            .keys
+            .aliases
+            .iter()
+            .filter(|(_, target)| keys.split([' ', '/']).any(|key| key.chars().eq(std::iter::once(**target))))
+            .map(|(alias, _)| *alias)
+            .collect();
+        if aliases.is_empty() {
+            return keys.to_string();
+        }
+        aliases.sort();
+        format!("{} or {}", keys, aliases.iter().map(char::to_string).collect::<Vec<_>>().join("/"))
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyCode, modifiers: KeyModifiers) -> anyhow::Result<()> {
+        self.error_message = None;
+        self.status_message = None;
+        self.last_activity = std::time::Instant::now();
+
+        // The lock screen swallows every key until the passphrase is entered
+        if self.mode == AppMode::Locked {
+            return self.handle_lock_key(key);
+        }
+
+        // Raw mode delivers Ctrl+Z as a key instead of stopping the process
+        if key == KeyCode::Char('z') && modifiers.contains(KeyModifiers::CONTROL) {
+            self.suspend_pending = true;
+            return Ok(());
+        }
+
+        // Global help key - available from any mode except Help itself and text input modes
+        let is_in_text_input_mode = self.is_in_text_input_mode();
+
+        // `[keys]` aliases, wherever keys are commands rather than text
+        let is_typing = is_in_text_input_mode && !matches!(self.mode, AppMode::Stats | AppMode::Review | AppMode::Agenda | AppMode::History | AppMode::ContextPicker);
+        let key = match key {
+            KeyCode::Char(c) if !is_typing && !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                KeyCode::Char(self.resolve_key_alias(c))
+            }
+            key => key,
+        };
+
+        if key == KeyCode::Char('a') && self.mode != AppMode::Help && !is_in_text_input_mode {
+            self.previous_mode = self.mode.clone();
+            self.mode = AppMode::Help;
+            return Ok(());
+        }
+
+        // Handle Ctrl+d: half-page scroll down
+        if key == KeyCode::Char('d') && modifiers.contains(KeyModifiers::CONTROL) && !is_in_text_input_mode {
+            if self.mode == AppMode::Help {
+                self.help_scroll += self.help_height / 2;
+                return Ok(());
+            }
+            self.half_page_down();
+            return Ok(());
+        }
+
+        // Handle Ctrl+u: half-page scroll up
+        if key == KeyCode::Char('u') && modifiers.contains(KeyModifiers::CONTROL) && !is_in_text_input_mode {
+            if self.mode == AppMode::Help {
+                self.help_scroll = self.help_scroll.saturating_sub(self.help_height / 2);
+                return Ok(());
+            }
+            self.half_page_up();
+            return Ok(());
+        }
+
+        // Vim-style jumps in the tree, list and flat views. In the tree a
+        // lone g opens goto mode, which takes the second g of gg.
+        if matches!(self.mode, AppMode::List | AppMode::CompletedView | AppMode::ScheduledView | AppMode::Someday | AppMode::Waiting | AppMode::Today) && !modifiers.contains(KeyModifiers::CONTROL) {
+            match (self.pending_prefix.take(), key) {
+                (Some('g'), KeyCode::Char('g')) => {
+                    self.select_row(0);
+                    return Ok(());
+                }
+                (Some('z'), KeyCode::Char('z')) => {
+                    self.center_selection();
+                    return Ok(());
+                }
+                (_, KeyCode::Char('G')) => {
+                    self.jump_to_bottom()?;
+                    return Ok(());
+                }
+                (_, KeyCode::Char(prefix @ ('g' | 'z'))) if prefix == 'z' || !(self.mode == AppMode::List && self.use_tree_view) => {
+                    self.pending_prefix = Some(prefix);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        // Shift+Space steps the status back (terminals that can't tell it apart send a plain Space)
+        if key == KeyCode::Char(' ') && modifiers.contains(KeyModifiers::SHIFT) && self.mode == AppMode::List {
+            return self.cycle_status(false);
+        }
+
+        // Handle Ctrl+p: switch to another database profile
+        if key == KeyCode::Char('p') && modifiers.contains(KeyModifiers::CONTROL) && matches!(self.mode, AppMode::List | AppMode::CompletedView | AppMode::ScheduledView | AppMode::Someday | AppMode::Waiting | AppMode::Today) {
+            self.open_profile_picker();
+            return Ok(());
+        }
+
+        // Handle Ctrl+s: save the Create form, where Enter in the description starts a new line
+        if key == KeyCode::Char('s') && modifiers.contains(KeyModifiers::CONTROL) && self.mode == AppMode::Create {
+            return self.submit_create_form();
+        }
+
+        // Handle Ctrl+n: quick add a todo without leaving the current view
+        if key == KeyCode::Char('n') && modifiers.contains(KeyModifiers::CONTROL) && matches!(self.mode, AppMode::List | AppMode::CompletedView | AppMode::ScheduledView | AppMode::Someday | AppMode::Waiting | AppMode::Today) {
+            self.open_quick_add()?;
+            return Ok(());
+        }
+
+        // Handle 'h' key: toggle hidden status of selected todo in tree view
+        if key == KeyCode::Char('h') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
+            if let Some(todo) = self.get_selected_todo() {
+                let todo_id = todo.id;
+                if let Err(e) = self.database.toggle_todo_hidden(todo_id) {
+                    self.error_message = Some(format!("Failed to toggle hidden status: {}", e));
+                } else {
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            return Ok(());
+        }
+
+        // Handle 'H' key: toggle showing/hiding hidden items in tree view
+        if key == KeyCode::Char('H') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
+            self.show_hidden_items = !self.show_hidden_items;
+            self.refresh_todos()?;
+            self.update_selection_after_refresh();
+            return Ok(());
+        }
+
+        // Handle 'i' key: cycle how much metadata the current view shows
+        if key == KeyCode::Char('i') && self.mode != AppMode::Help && !is_in_text_input_mode {
+            self.cycle_metadata_density()?;
+            return Ok(());
+        }
+
+        // Tab / Shift+Tab: switch between the views in the tab bar
+        if matches!(key, KeyCode::Tab | KeyCode::BackTab) && matches!(self.mode, AppMode::List | AppMode::CompletedView | AppMode::ScheduledView | AppMode::Someday | AppMode::Waiting | AppMode::Today) {
+            self.cycle_view(key == KeyCode::Tab);
+            return Ok(());
+        }
+
+        // Handle 'g' key: goto mode for id_mod navigation in tree view
+        if key == KeyCode::Char('g') && self.mode != AppMode::Help && !is_in_text_input_mode && self.use_tree_view {
+            self.mode = AppMode::IdModGoto;
+            self.goto_query.clear();
+            self.goto_matches.clear();
+            self.goto_current_match_index = None;
+            self.search_input_mode = true;
+            return Ok(());
+        }
+
+        match self.mode {
+            AppMode::List => self.handle_list_key(key)?,
+            AppMode::CompletedView => self.handle_completed_view_key(key)?,
+            AppMode::ScheduledView => self.handle_scheduled_view_key(key)?,
+            AppMode::Someday => self.handle_someday_view_key(key)?,
+            AppMode::Waiting => self.handle_waiting_view_key(key)?,
+            AppMode::Today => self.handle_today_view_key(key)?,
+            AppMode::Create => self.handle_create_key(key)?,
+            AppMode::ConfirmDelete => self.handle_delete_key(key)?,
+            AppMode::ConfirmCascade => self.handle_confirm_cascade_key(key)?,
+            AppMode::ListFind => self.handle_list_find_key(key)?,
+            AppMode::TreeSearch => self.handle_tree_search_key(key)?,
+            AppMode::ParentSearch => self.handle_parent_search_key(key)?,
+            AppMode::Move => self.handle_move_key(key)?,
+            AppMode::Help => self.handle_help_key(key)?,
+            AppMode::IdModGoto => self.handle_idmod_goto_key(key)?,
+            AppMode::Locked => self.handle_lock_key(key)?,
+            AppMode::FilterPicker => self.handle_filter_picker_key(key)?,
+            AppMode::FilterPrompt => self.handle_filter_prompt_key(key)?,
+            AppMode::DeferPrompt => self.handle_defer_prompt_key(key)?,
+            AppMode::DelegatePrompt => self.handle_delegate_prompt_key(key)?,
+            AppMode::NotePrompt => self.handle_note_prompt_key(key)?,
+            AppMode::QuickEdit => self.handle_quick_edit_key(key)?,
+            AppMode::Attachments => self.handle_attachments_key(key)?,
+            AppMode::Onboarding => self.handle_onboarding_key(key)?,
+            AppMode::Capture => self.handle_capture_key(key)?,
+            AppMode::QuickAdd => self.handle_quick_add_key(key)?,
+            AppMode::CopyMenu => self.handle_copy_menu_key(key)?,
+            AppMode::ProfilePicker => self.handle_profile_picker_key(key)?,
+            AppMode::TemplatePicker => self.handle_template_picker_key(key)?,
+            AppMode::Stats => self.handle_stats_key(key)?,
+            AppMode::Agenda => self.handle_agenda_key(key),
+            AppMode::History => self.handle_history_key(key)?,
+            AppMode::ContextPicker => self.handle_context_picker_key(key)?,
+            AppMode::Review => self.handle_review_key(key)?,
+        }
+        Ok(())
+    }
+
+    fn open_filter_picker(&mut self) -> anyhow::Result<()> {
+        self.saved_filters = self.database.get_saved_filters()?;
+        self.filter_list_state.select(if self.saved_filters.is_empty() { None } else { Some(0) });
+        self.filter_input.clear();
+        self.search_input_mode = false;
+        self.mode = AppMode::FilterPicker;
+        Ok(())
+    }
+
+    /// Create the nested markdown list on the clipboard under the selected todo
+    fn paste_outline(&mut self) -> anyhow::Result<()> {
+        let parent = self.get_selected_todo().map(|todo| (todo.id, todo.title.clone()));
+        let text = match clipboard::paste() {
+            Ok(text) => text,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read the clipboard: {}", e));
+                return Ok(());
+            }
+        };
+        let outline = import::parse_markdown_outline(&text);
+        if outline.is_empty() {
+            self.error_message = Some("The clipboard holds no markdown list".to_string());
+            return Ok(());
+        }
+        let parent_id = parent.as_ref().map(|(id, _)| *id);
+        if !self.check_parent_rule(parent_id)? {
+            return Ok(());
+        }
+
+        let created = import::import_todos(&self.database, outline, parent_id)?;
+        self.refresh_todos()?;
+        self.reselect_todo(created.first().copied());
+        let target = parent.map_or_else(|| "at the top level".to_string(), |(_, title)| format!("under '{}'", title));
+        // Keep a [parents] warning if there is one
+        self.status_message.get_or_insert_with(|| format!("Added {} todos from the clipboard {}", created.len(), target));
+        Ok(())
+    }
+
+    fn open_template_picker(&mut self) -> anyhow::Result<()> {
+        self.saved_templates = self.database.get_templates()?;
+        self.template_list_state.select(if self.saved_templates.is_empty() { None } else { Some(0) });
+        self.template_target = self.get_selected_todo().map(|todo| (todo.id, todo.title.clone()));
+        self.template_input.clear();
+        self.search_input_mode = false;
+        self.mode = AppMode::TemplatePicker;
+        Ok(())
+    }
+
+    /// Select `todo_id` wherever it is: in the tree with its ancestors expanded, lifting a
+    /// hoist, filter or the hidden toggle if those keep it out, or else in the completed
+    /// or scheduled view
+    fn goto_todo(&mut self, todo_id: i64) -> anyhow::Result<()> {
+        self.mode = AppMode::List;
+        self.switch_view(ViewKind::Tree);
+        let mut lifted = Vec::new();
+        if !self.reselect_todo(Some(todo_id)) && self.tree_manager.root.is_some() {
+            self.tree_manager.root = None;
+            self.refresh_todos()?;
+            lifted.push("unhoisted");
+        }
+        if !self.reselect_todo(Some(todo_id)) && self.active_filter.is_some() {
+            self.active_filter = None;
+            self.refresh_todos()?;
+            lifted.push("filter cleared");
+        }
+        if !self.reselect_todo(Some(todo_id)) && !self.show_hidden_items {
+            self.show_hidden_items = true;
+            self.refresh_todos()?;
+            lifted.push("showing hidden");
+        }
+        if !self.reselect_todo(Some(todo_id)) {
+            // Completed roots and deferred todos have views of their own
+            if self.database.get_todo_by_id(todo_id)?.is_some_and(|todo| todo.is_completed()) {
+                if self.completed_filter() != CompletedFilter::default() {
+                    self.completed_range = CompletedRange::All;
+                    self.completed_root = None;
+                    self.completed_todos.clear();
+                    self.refresh_todos()?;
+                }
+                self.switch_view(ViewKind::Completed);
+                while !self.reselect_todo(Some(todo_id)) && self.load_more_completed()? {}
+            } else {
+                self.switch_view(ViewKind::Scheduled);
+                // A subtask waits with its deferred ancestor
+                let mut current = Some(todo_id);
+                while let Some(id) = current
+                    && !self.reselect_todo(Some(id)) {
+                    current = self.database.get_todo_by_id(id)?.and_then(|todo| todo.parent_id);
+                }
+            }
+        }
+        if !lifted.is_empty() {
+            self.status_message = Some(format!("Went to {} ({})", todo_id, lifted.join(", ")));
+        }
+        Ok(())
+    }
+
+    fn activate_filter(&mut self, active_filter: Option<(String, Filter)>) -> anyhow::Result<()> {
+        let selected_id = self.get_selected_todo().map(|todo| todo.id);
+        for id in std::mem::take(&mut self.filter_opened) {
+            self.tree_manager.expansion_states.insert(id, false);
+        }
+        self.active_filter = active_filter;
+        self.refresh_todos()?;
+        // Matches inside collapsed branches would be filtered in but out of sight
+        if self.active_filter.is_some() {
+            let matches: Vec<i64> = self.tree_manager.todos.keys().filter(|id| !self.filter_context.contains(id)).copied().collect();
+            self.filter_opened = self.tree_manager.expand_paths_to(&matches);
+        }
+        self.list_state.select(if self.incomplete_todos.is_empty() { None } else { Some(0) });
+        self.tree_list_state.select(if self.tree_manager.get_rendered_lines().is_empty() { None } else { Some(0) });
+        // Stay on the same todo when it's still in the filtered view
+        self.reselect_todo(selected_id);
+        Ok(())
+    }
+
+    fn open_attachments(&mut self) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else { return Ok(()) };
+        let (todo_id, todo_title) = (todo.id, todo.title.clone());
+
+        self.attachments = self.database.get_attachments(todo_id)?;
+        self.attachment_list_state.select(if self.attachments.is_empty() { None } else { Some(0) });
+        self.attachment_todo = Some((todo_id, todo_title));
+        self.attachment_input.clear();
+        // Start typing straight away when there is nothing to pick from yet
+        self.search_input_mode = self.attachments.is_empty();
+        self.mode = AppMode::Attachments;
+        Ok(())
+    }
+
+    fn close_attachments(&mut self) -> anyhow::Result<()> {
+        self.search_input_mode = false;
+        self.attachment_todo = None;
+        self.attachment_counts = self.database.get_attachment_counts()?;
+        self.mode = AppMode::List;
+        Ok(())
+    }
+
+    fn handle_attachments_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let Some((todo_id, _)) = self.attachment_todo.clone() else {
+            self.mode = AppMode::List;
+            return Ok(());
+        };
+
+        if self.search_input_mode {
+            // Typing a new attachment path or URL
+            match key {
+                KeyCode::Esc => {
+                    self.attachment_input.clear();
+                    if self.attachments.is_empty() {
+                        self.close_attachments()?;
+                    } else {
+                        self.search_input_mode = false;
+                    }
+                }
+                KeyCode::Enter => match opener::normalize_target(&self.attachment_input) {
+                    Ok(target) => {
+                        self.database.add_attachment(todo_id, &target)?;
+                        self.attachments = self.database.get_attachments(todo_id)?;
+                        self.attachment_list_state.select(Some(self.attachments.len() - 1));
+                        self.attachment_input.clear();
+                        self.search_input_mode = false;
+                    }
+                    Err(e) => self.error_message = Some(e.to_string()),
+                },
+                KeyCode::Backspace => {
+                    self.attachment_input.pop();
+                }
+                KeyCode::Char(c) => self.attachment_input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => self.close_attachments()?,
+            KeyCode::Down | KeyCode::Char('j') if !self.attachments.is_empty() => {
+                let i = self.attachment_list_state.selected().map_or(0, |i| (i + 1) % self.attachments.len());
+                self.attachment_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.attachments.is_empty() => {
+                let len = self.attachments.len();
+                let i = self.attachment_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.attachment_list_state.select(Some(i));
+            }
+            KeyCode::Enter => {
+                if let Some(attachment) = self.attachment_list_state.selected().and_then(|i| self.attachments.get(i))
+                    && let Err(e) = opener::open(&attachment.target) {
+                    self.error_message = Some(e.to_string());
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('a') => {
+                self.search_input_mode = true;
+                self.attachment_input.clear();
+            }
+            KeyCode::Char('d') => {
+                if let Some(attachment) = self.attachment_list_state.selected().and_then(|i| self.attachments.get(i)) {
+                    self.database.delete_attachment(attachment.id)?;
+                    self.attachments = self.database.get_attachments(todo_id)?;
+                    let len = self.attachments.len();
+                    self.attachment_list_state.select(if len == 0 { None } else { self.attachment_list_state.selected().map(|i| i.min(len - 1)) });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_onboarding_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('q') => self.finish_onboarding()?,
+            KeyCode::Down | KeyCode::Char('j') => {
+                let i = self.import_list_state.selected().map_or(0, |i| (i + 1) % self.import_sources.len());
+                self.import_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                let len = self.import_sources.len();
+                let i = self.import_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.import_list_state.select(Some(i));
+            }
+            KeyCode::Char(' ') => {
+                if let Some((_, checked)) = self.import_list_state.selected().and_then(|i| self.import_sources.get_mut(i)) {
+                    *checked = !*checked;
+                }
+            }
+            KeyCode::Enter => {
+                let mut imported = 0;
+                let mut failures = Vec::new();
+                for (source, _) in self.import_sources.iter().filter(|(_, checked)| *checked) {
+                    match import::import_source(&self.database, source) {
+                        Ok(count) => imported += count,
+                        Err(e) => failures.push(format!("{}: {}", source.path.display(), e)),
+                    }
+                }
+                // Bringing in existing todos isn't news for [[hooks]] or this session's counts
+                self.changes.try_iter().for_each(drop);
+                self.finish_onboarding()?;
+                self.refresh_todos()?;
+                if !self.incomplete_todos.is_empty() {
+                    self.list_state.select(Some(0));
+                }
+                if !self.tree_manager.get_rendered_lines().is_empty() {
+                    self.tree_list_state.select(Some(0));
+                }
+                if !failures.is_empty() {
+                    self.error_message = Some(format!("Imported {} todos; failed: {}", imported, failures.join(", ")));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_filter_prompt_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc => {
+                self.filter_input.clear();
+                self.mode = AppMode::List;
+            }
+            KeyCode::Enter => {
+                let expression = self.filter_input.trim().to_string();
+                if let Some(reference) = expression.strip_prefix("id ") {
+                    // `:id 1234` (or a short code) goes to that todo instead of filtering
+                    match self.database.resolve_todo_reference(reference)? {
+                        Some(todo_id) => {
+                            self.filter_input.clear();
+                            self.goto_todo(todo_id)?;
+                        }
+                        None => self.error_message = Some(format!("No todo '{}'", reference.trim())),
+                    }
+                } else if expression.is_empty() {
+                    // Submitting an empty prompt clears the active filter
+                    self.activate_filter(None)?;
+                    self.mode = AppMode::List;
+                } else {
+                    match Filter::parse(&expression) {
+                        Ok(parsed) => {
+                            self.activate_filter(Some((expression, parsed)))?;
+                            self.filter_input.clear();
+                            self.mode = AppMode::List;
+                        }
+                        Err(e) => self.error_message = Some(format!("Invalid filter: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.filter_input.pop();
+            }
+            KeyCode::Char(c) => self.filter_input.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_capture_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.handle_tag_completion_key(key) {
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc => {
+                self.capture_input.clear();
+                self.mode = AppMode::List;
+            }
+            KeyCode::Enter if !self.capture_input.trim().is_empty() => {
+                let captured = match capture::parse_capture_input(&self.capture_input, self.config.dates.due_time()) {
+                    Ok(captured) => captured,
+                    Err(e) => {
+                        self.error_message = Some(e.to_string());
+                        return Ok(());
+                    }
+                };
+                // Several todos at once (a pasted list, or `;`) are added on the second Enter
+                if captured.len() > 1 && self.capture_confirm != Some(captured.len()) {
+                    self.capture_confirm = Some(captured.len());
+                    return Ok(());
+                }
+                let mut new_todos = Vec::with_capacity(captured.len());
+                let start_dates: Vec<_> = captured.iter().map(|captured| captured.start_at).collect();
+                let default_parent = self.capture_parent.as_ref().map(|(id, _)| *id);
+                for captured in captured {
+                    let parent_id = match self.captured_parent(&captured, default_parent)? {
+                        Ok(parent_id) => parent_id,
+                        Err(message) => {
+                            self.capture_confirm = None;
+                            self.error_message = Some(message);
+                            return Ok(());
+                        }
+                    };
+                    new_todos.push(NewTodo { title: captured.title, description: String::new(), parent_id, due_by: captured.due_by });
+                }
+                let todo_ids = self.database.create_todos(new_todos)?;
+                for (&todo_id, start_at) in todo_ids.iter().zip(start_dates) {
+                    if start_at.is_some() {
+                        self.database.set_start_at(todo_id, start_at)?;
+                    }
+                }
+                self.refresh_todos()?;
+                // Keep the new todos in sight under their parent
+                for &todo_id in &todo_ids {
+                    self.tree_manager.expand_path_to_todo(todo_id);
+                }
+                if todo_ids.len() > 1 {
+                    self.status_message = Some(format!("Added {} todos", todo_ids.len()));
+                }
+                self.capture_count += todo_ids.len();
+                self.capture_confirm = None;
+                self.capture_input.clear();
+                // New tags are offered for the following lines too
+                self.load_known_tags()?;
+            }
+            KeyCode::Backspace => {
+                self.capture_input.pop();
+                self.capture_confirm = None;
+                self.refresh_tag_completions();
+            }
+            KeyCode::Char(c) => {
+                self.capture_input.push(c);
+                self.capture_confirm = None;
+                self.refresh_tag_completions();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Where a captured todo is filed: under its `^` reference, else the best title match
+    /// for its `>search`, else `default`. Err is the message when neither finds a todo.
+    fn captured_parent(&self, captured: &capture::CapturedTodo, default: Option<i64>) -> anyhow::Result<Result<Option<i64>, String>> {
+        if let Some(reference) = &captured.parent {
+            return Ok(self.database.resolve_todo_reference(reference)?.map(Some).ok_or_else(|| format!("No todo '{}' to add under", reference)));
+        }
+        if let Some(search) = &captured.parent_search {
+            let todos = self.database.get_all_todos()?;
+            return Ok(capture::find_parent(&todos, search).map(|todo| Some(todo.id)).ok_or_else(|| format!("No todo matching '{}' to add under", search)));
+        }
+        Ok(Ok(default))
+    }
+
+    fn open_quick_add(&mut self) -> anyhow::Result<()> {
+        self.load_known_tags()?;
+        self.quick_add_todos = self.database.get_all_todos()?;
+        self.quick_add_input.clear();
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::QuickAdd;
+        Ok(())
+    }
+
+    /// The todo the quick add line's `>search` currently picks, for the overlay
+    fn quick_add_parent(&self) -> Option<&Todo> {
+        let search = capture::split_parent_search(&self.quick_add_input).1?;
+        capture::find_parent(&self.quick_add_todos, search)
+    }
+
+    fn handle_quick_add_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.handle_tag_completion_key(key) {
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc => {
+                self.quick_add_input.clear();
+                self.quick_add_todos.clear();
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Enter if !self.quick_add_input.trim().is_empty() => {
+                let captured = match capture::parse_capture_line(&self.quick_add_input, self.config.dates.due_time()) {
+                    Ok(captured) => captured,
+                    Err(e) => {
+                        self.error_message = Some(e.to_string());
+                        return Ok(());
+                    }
+                };
+                let parent_id = match self.captured_parent(&captured, None)? {
+                    Ok(parent_id) => parent_id,
+                    Err(message) => {
+                        self.error_message = Some(message);
+                        return Ok(());
+                    }
+                };
+                let title = captured.title.clone();
+                let todo_id = self.database.create_todo(NewTodo { title: captured.title, description: String::new(), parent_id, due_by: captured.due_by })?;
+                if captured.start_at.is_some() {
+                    self.database.set_start_at(todo_id, captured.start_at)?;
+                }
+                self.quick_add_input.clear();
+                self.quick_add_todos.clear();
+                self.mode = self.previous_mode.clone();
+                self.refresh_todos()?;
+                let parent = parent_id.and_then(|id| self.database.get_todo_by_id(id).ok().flatten());
+                self.status_message = Some(match parent {
+                    Some(parent) => format!("Added '{}' under '{}'", title, parent.title),
+                    None => format!("Added '{}'", title),
+                });
+            }
+            KeyCode::Backspace => {
+                self.quick_add_input.pop();
+                self.refresh_tag_completions();
+            }
+            KeyCode::Char(c) => {
+                self.quick_add_input.push(c);
+                self.refresh_tag_completions();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn load_known_tags(&mut self) -> anyhow::Result<()> {
+        self.known_tags = tags::tag_frequencies(&self.database.get_all_todos()?);
+        self.tag_completions.clear();
+        Ok(())
+    }
+
+    /// Offer completions for a tag being typed in the quick capture line (`#` or `@`),
+    /// the Create form's title or a quick title / tags edit (`#`)
+    fn refresh_tag_completions(&mut self) {
+        let (input, sigils): (&str, &[char]) = match self.mode {
+            AppMode::Capture => (&self.capture_input, &['#', '@']),
+            AppMode::QuickAdd => (&self.quick_add_input, &['#', '@']),
+            AppMode::Create if self.create_field_focus == CreateFieldFocus::Title => (&self.input_title, &['#']),
+            AppMode::QuickEdit if matches!(self.quick_edit_field, QuickEditField::Title | QuickEditField::Tags) => (&self.quick_edit_input, &['#']),
+            _ => ("", &[]),
+        };
+        self.tag_completions = tags::completions(&self.known_tags, input, sigils).into_iter().cloned().collect();
+        self.tag_completion_state.select(if self.tag_completions.is_empty() { None } else { Some(0) });
+    }
+
+    /// Keys for an open tag completion list: Tab/↓ and Shift+Tab/↑ move, Enter inserts
+    /// the tag, Esc closes the list. Returns false for keys the list doesn't use.
+    fn handle_tag_completion_key(&mut self, key: KeyCode) -> bool {
+        let len = self.tag_completions.len();
+        if len == 0 {
+            return false;
+        }
+        let selected = self.tag_completion_state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Tab | KeyCode::Down => self.tag_completion_state.select(Some((selected + 1) % len)),
+            KeyCode::BackTab | KeyCode::Up => self.tag_completion_state.select(Some((selected + len - 1) % len)),
+            KeyCode::Enter => {
+                let tag = self.tag_completions[selected].0.clone();
+                let input = match self.mode {
+                    AppMode::Capture => &mut self.capture_input,
+                    AppMode::QuickAdd => &mut self.quick_add_input,
+                    AppMode::QuickEdit => &mut self.quick_edit_input,
+                    _ => &mut self.input_title,
+                };
+                tags::apply_completion(input, &tag);
+                self.tag_completions.clear();
+            }
+            KeyCode::Esc => self.tag_completions.clear(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_template_picker_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.search_input_mode {
+            // Naming a template saved from the selected todo's subtree
+            match key {
+                KeyCode::Esc => {
+                    self.search_input_mode = false;
+                    self.template_input.clear();
+                }
+                KeyCode::Enter => {
+                    if let Some((todo_id, _)) = self.template_target.clone() {
+                        match template::save_template(&self.database, &self.template_input, todo_id) {
+                            Ok(_) => {
+                                let name = self.template_input.trim().to_string();
+                                self.saved_templates = self.database.get_templates()?;
+                                let index = self.saved_templates.iter().position(|t| t.name == name);
+                                self.template_list_state.select(index);
+                                self.search_input_mode = false;
+                                self.template_input.clear();
+                            }
+                            Err(e) => self.error_message = Some(e.to_string()),
+                        }
+                    }
+                }
+                KeyCode::Backspace => {
+                    self.template_input.pop();
+                }
+                KeyCode::Char(c) => self.template_input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('T') => self.mode = AppMode::List,
+            KeyCode::Down | KeyCode::Char('j') if !self.saved_templates.is_empty() => {
+                let i = self.template_list_state.selected().map_or(0, |i| (i + 1) % self.saved_templates.len());
+                self.template_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.saved_templates.is_empty() => {
+                let len = self.saved_templates.len();
+                let i = self.template_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.template_list_state.select(Some(i));
+            }
+            KeyCode::Enter | KeyCode::Char('r') => {
+                // Enter creates the template under the selected todo, r at the top level
+                if let Some(saved) = self.template_list_state.selected().and_then(|i| self.saved_templates.get(i)) {
+                    let parent_id = if key == KeyCode::Enter { self.template_target.as_ref().map(|(id, _)| *id) } else { None };
+                    match template::instantiate(&self.database, saved, parent_id) {
+                        Ok(todo_id) => {
+                            self.refresh_todos()?;
+                            self.tree_manager.expand_path_to_todo(todo_id);
+                            self.mode = AppMode::List;
+                        }
+                        Err(e) => self.error_message = Some(e.to_string()),
+                    }
+                }
+            }
+            KeyCode::Char('s') if self.template_target.is_some() => {
+                self.search_input_mode = true;
+                self.template_input.clear();
+            }
+            KeyCode::Char('d') => {
+                if let Some(saved) = self.template_list_state.selected().and_then(|i| self.saved_templates.get(i)) {
+                    self.database.delete_template(saved.id)?;
+                    self.saved_templates = self.database.get_templates()?;
+                    let len = self.saved_templates.len();
+                    self.template_list_state.select(if len == 0 { None } else { self.template_list_state.selected().map(|i| i.min(len - 1)) });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_filter_picker_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.search_input_mode {
+            // Typing a new filter as "name: expression"
+            match key {
+                KeyCode::Esc => {
+                    self.search_input_mode = false;
+                    self.filter_input.clear();
+                }
+                KeyCode::Enter => {
+                    match self.filter_input.split_once(':') {
+                        Some((name, query)) if !name.trim().is_empty() => {
+                            let (name, query) = (name.trim(), query.trim());
+                            match Filter::parse(query) {
+                                Ok(_) => {
+                                    self.database.save_filter(name, query)?;
+                                    self.saved_filters = self.database.get_saved_filters()?;
+                                    let index = self.saved_filters.iter().position(|f| f.name == name);
+                                    self.filter_list_state.select(index);
+                                    self.search_input_mode = false;
+                                    self.filter_input.clear();
+                                }
+                                Err(e) => self.error_message = Some(format!("Invalid filter: {}", e)),
+                            }
+                        }
+                        _ => {
+                            self.error_message = Some("Expected 'name: expression', e.g. 'work week: due<7d tag:work'".to_string());
+                        }
+                    }
+                }
+                KeyCode::Backspace => {
+                    self.filter_input.pop();
+                }
+                KeyCode::Char(c) => self.filter_input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('F') => self.mode = AppMode::List,
+            KeyCode::Down | KeyCode::Char('j') if !self.saved_filters.is_empty() => {
+                let i = self.filter_list_state.selected().map_or(0, |i| (i + 1) % self.saved_filters.len());
+                self.filter_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.saved_filters.is_empty() => {
+                let len = self.saved_filters.len();
+                let i = self.filter_list_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.filter_list_state.select(Some(i));
+            }
+            KeyCode::Enter => {
+                if let Some(saved) = self.filter_list_state.selected().and_then(|i| self.saved_filters.get(i)) {
+                    match Filter::parse(&saved.query) {
+                        Ok(parsed) => {
+                            let name = saved.name.clone();
+                            self.activate_filter(Some((name, parsed)))?;
+                            self.mode = AppMode::List;
+                        }
+                        Err(e) => self.error_message = Some(format!("Invalid filter: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('n') => {
+                self.search_input_mode = true;
+                self.filter_input.clear();
+            }
+            KeyCode::Char('d') => {
+                if let Some(saved) = self.filter_list_state.selected().and_then(|i| self.saved_filters.get(i)) {
+                    let deleted_name = saved.name.clone();
+                    self.database.delete_filter(saved.id)?;
+                    self.saved_filters = self.database.get_saved_filters()?;
+                    let len = self.saved_filters.len();
+                    self.filter_list_state.select(if len == 0 { None } else { self.filter_list_state.selected().map(|i| i.min(len - 1)) });
+                    if self.active_filter.as_ref().is_some_and(|(name, _)| *name == deleted_name) {
+                        self.activate_filter(None)?;
+                    }
+                }
+            }
+            KeyCode::Char('x') => {
+                self.activate_filter(None)?;
+                self.mode = AppMode::List;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_lock_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Enter => {
+                if self.verify_passphrase(&self.lock_input) {
+                    self.mode = self.pre_lock_mode.clone();
+                } else {
+                    self.error_message = Some("Incorrect passphrase".to_string());
+                }
+                self.lock_input.clear();
+            }
+            KeyCode::Esc => self.lock_input.clear(),
+            KeyCode::Backspace => {
+                self.lock_input.pop();
+            }
+            KeyCode::Char(c) => self.lock_input.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_list_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Char('t') => {
+                if self.use_tree_view {
+                    // Branch-level toggle: expand/collapse the selected item
+                    if let Some(selected) = self.tree_list_state.selected()
+                        && let Some(line) = self.tree_manager.get_rendered_lines().get(selected)
+                        && line.has_children {
+                        self.tree_manager.toggle_expansion(line.todo_id);
+                        // Maintain selection after toggle
+                        self.update_tree_selection_after_toggle(selected);
+                    }
+                } else {
+                    // Switch to tree view, keeping the selected todo in sight
+                    let selected_id = self.get_selected_todo().map(|todo| todo.id);
+                    self.use_tree_view = true;
+                    if !self.reselect_todo(selected_id) && !self.tree_manager.get_rendered_lines().is_empty() {
+                        self.tree_list_state.select(Some(0));
+                    }
+                }
+            }
+            KeyCode::Char('F') => {
+                // Saved filters picker
+                self.open_filter_picker()?;
+            }
+            KeyCode::Char('o') => {
+                // Attachments (files / URLs) of the selected todo
+                self.open_attachments()?;
+            }
+            KeyCode::Char('T') => {
+                // Templates: instantiate under, or save from, the selected todo
+                self.open_template_picker()?;
+            }
+            KeyCode::Char(':') => {
+                // Ad-hoc filter expression prompt
+                self.filter_input.clear();
+                self.mode = AppMode::FilterPrompt;
+            }
+            KeyCode::Char('f') => {
+                // List Find: flat search results view
+                self.mode = AppMode::ListFind;
+                self.search_query.clear();
                 self.search_results.clear();
                 self.search_list_state.select(None);
                 self.search_input_mode = true;
             }
             KeyCode::Char('c') => {
                 if self.mode == AppMode::CompletedView {
-                    self.mode = AppMode::List;
+                    self.leave_flat_view();
                 } else {
-                    self.mode = AppMode::CompletedView;
-                    if !self.completed_todos.is_empty() && self.completed_list_state.selected().is_none() {
-                        self.completed_list_state.select(Some(0));
-                    }
+                    self.switch_view(ViewKind::Completed);
+                }
+            }
+            KeyCode::Char(c @ '1'..='3') => self.toggle_quick_filter(c)?,
+            KeyCode::Char('Z') => self.switch_view(ViewKind::Scheduled),
+            KeyCode::Char('L') => self.switch_view(ViewKind::Someday),
+            KeyCode::Char('B') => self.toggle_someday()?,
+            KeyCode::Char('b') => self.toggle_pinned()?,
+            KeyCode::Char('+') => self.toggle_daily_plan()?,
+            KeyCode::Char('=') => self.switch_view(ViewKind::Today),
+            KeyCode::Char('X') => self.toggle_cancelled()?,
+            KeyCode::Char('U') => self.switch_view(ViewKind::Waiting),
+            KeyCode::Char('>') => self.open_delegate_prompt(),
+            KeyCode::Char('D') => self.open_defer_prompt(),
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            KeyCode::Char('E') => self.open_quick_edit(QuickEditField::Estimate)?,
+            KeyCode::Char('!') => self.open_quick_edit(QuickEditField::Flag)?,
+            KeyCode::Char('@') => self.cycle_context()?,
+            KeyCode::Char('%') => self.cycle_energy()?,
+            KeyCode::Char('C') => {
+                // The choices only stand while their filter is the active one
+                if !self.active_filter.as_ref().is_some_and(|(name, _)| name.starts_with("Now: ")) {
+                    self.now_context = None;
+                    self.now_energy = None;
                 }
+                self.mode = AppMode::ContextPicker;
             }
             KeyCode::Char('/') => {
                 // Tree Search: live highlighting in tree view
@@ -1024,44 +3553,95 @@ impl App {
                 
                 self.search_input_mode = true;
             }
-            KeyCode::Char('n') => {
-                self.mode = AppMode::Create;
-                self.input_title.clear();
-                self.input_description.clear();
-                self.input_due_date_relative.clear();
-                self.input_due_date_absolute.clear();
-                self.create_field_focus = CreateFieldFocus::Title;
-                
+            KeyCode::Char('n') | KeyCode::Char('A') => {
                 // Auto-fill parent field with currently highlighted task
-                if let Some(selected_todo) = self.get_selected_todo() {
-                    let todo_id = selected_todo.id;
-                    let todo_title = selected_todo.title.clone();
-                    
-                    self.selected_parent_id = Some(todo_id);
-                    let parent_display = if todo_title.len() > 40 {
-                        format!("{}...", &todo_title[..37])
-                    } else {
-                        todo_title
-                    };
-                    self.input_parent = format!("ID:{} {}", todo_id, parent_display);
-                } else {
-                    // No selection, clear parent fields
-                    self.input_parent.clear();
-                    self.selected_parent_id = None;
+                let parent_id = self.get_selected_todo().map(|todo| todo.id);
+                self.open_create_form(parent_id)?;
+            }
+            KeyCode::Char('O') => {
+                // A sibling: same parent as the highlighted task (top level for a root)
+                let parent_id = self.get_selected_todo().and_then(|todo| todo.parent_id);
+                self.open_create_form(parent_id)?;
+            }
+            KeyCode::Char('N') => {
+                // Quick capture: one todo per line under the highlighted todo, like the Create form
+                self.capture_parent = self.get_selected_todo().map(|todo| (todo.id, todo.title.clone()));
+                self.capture_input.clear();
+                self.capture_count = 0;
+                self.capture_confirm = None;
+                self.load_known_tags()?;
+                self.mode = AppMode::Capture;
+            }
+            KeyCode::Char('d') if self.get_current_list_state().selected().is_some() => {
+                self.mode = AppMode::ConfirmDelete;
+            }
+            KeyCode::Char('m') => {
+                if self.use_tree_view
+                    && let Some(todo) = self.get_selected_todo() {
+                    self.move_todo_id = Some(todo.id);
+                    self.move_as_sibling = false;
+                    self.mode = AppMode::Move;
+                    // Find and highlight the current parent (or first valid parent if root)
+                    self.highlight_current_parent_for_move();
                 }
             }
-            KeyCode::Char('d') => {
-                if self.get_current_list_state().selected().is_some() {
-                    self.mode = AppMode::ConfirmDelete;
+            KeyCode::Char('M') => {
+                if self.use_tree_view
+                    && let Some(new_parent_id) = self.last_move_parent
+                    && let Some(todo) = self.get_selected_todo().cloned() {
+                    self.repeat_move(todo, new_parent_id)?;
                 }
             }
-            KeyCode::Char('m') => {
-                if self.use_tree_view {
-                    if let Some(todo) = self.get_selected_todo() {
-                        self.move_todo_id = Some(todo.id);
-                        self.mode = AppMode::Move;
-                        // Find and highlight the current parent (or first valid parent if root)
-                        self.highlight_current_parent_for_move();
+            KeyCode::Char('Y') if self.get_selected_todo().is_some() => {
+                self.mode = AppMode::CopyMenu;
+            }
+            KeyCode::Char('S') => {
+                // Stats broken down by root project
+                self.stats_parent = None;
+                self.stats_list_state.select(Some(0));
+                self.mode = AppMode::Stats;
+            }
+            KeyCode::Char('R') => self.start_review()?,
+            KeyCode::Char('W') => {
+                // Open todos by due day, with each day's estimated workload
+                self.agenda_scroll = 0;
+                self.mode = AppMode::Agenda;
+            }
+            KeyCode::Char('w') => self.toggle_pomodoro(),
+            KeyCode::Char('V') => {
+                let todo_id = self.get_selected_todo().map(|todo| todo.id);
+                self.open_history(todo_id)?;
+            }
+            KeyCode::Char('I') => {
+                // Turn a nested bullet list (meeting notes) into a subtree
+                self.paste_outline()?;
+            }
+            KeyCode::Char('y') => {
+                // Yank the selected subtree for pasting with p / P
+                if self.use_tree_view
+                    && let Some(todo) = self.get_selected_todo() {
+                    self.yanked_todo = Some((todo.id, todo.title.clone()));
+                }
+            }
+            KeyCode::Char('p') | KeyCode::Char('P') => {
+                // p pastes a copy under the selected todo, P next to it
+                if self.use_tree_view
+                    && let Some((yanked_id, _)) = self.yanked_todo.clone() {
+                    let selected = self.get_selected_todo().map(|todo| (todo.id, todo.parent_id));
+                    let new_parent = match (key, selected) {
+                        (KeyCode::Char('p'), Some((id, _))) => Some(id),
+                        (_, Some((_, parent_id))) => parent_id,
+                        (_, None) => None,
+                    };
+                    match self.database.duplicate_subtree(yanked_id, new_parent) {
+                        Ok(copy_id) => {
+                            self.refresh_todos()?;
+                            self.tree_manager.expand_path_to_todo(copy_id);
+                            if let Some(line) = self.tree_manager.get_line_index_for_todo(copy_id) {
+                                self.tree_list_state.select(Some(line));
+                            }
+                        }
+                        Err(e) => self.error_message = Some(format!("Failed to paste: {}", e)),
                     }
                 }
             }
@@ -1079,85 +3659,621 @@ impl App {
                     self.previous_todo();
                 }
             }
-            KeyCode::Char(' ') => {
+            KeyCode::Char(' ') => self.cycle_status(true)?,
+            KeyCode::Enter => {
                 if let Some(todo) = self.get_selected_todo() {
-                    let todo_id = todo.id;
-                    let is_currently_completed = todo.is_completed();
-                    
-                    if is_currently_completed {
-                        self.database.uncomplete_todo(todo_id)?;
-                    } else {
-                        self.database.complete_todo(todo_id)?;
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.current_parent = Some(todo.id);
+                    self.refresh_todos()?;
+                    if !self.incomplete_todos.is_empty() {
+                        self.list_state.select(Some(0));
+                        if self.use_tree_view {
+                            self.tree_list_state.select(Some(0));
+                        }
                     }
-                    
+                }
+            }
+            KeyCode::Char(motion @ ('u' | '}' | '{' | ']' | '[')) if self.use_tree_view => {
+                // Tree motions: parent, next/previous sibling, next/previous top-level todo
+                if let Some(line) = self.tree_list_state.selected() {
+                    let target = match motion {
+                        'u' => self.tree_manager.parent_line(line),
+                        '}' | '{' => self.tree_manager.sibling_line(line, motion == '}'),
+                        _ => self.tree_manager.root_line(line, motion == ']'),
+                    };
+                    if let Some(target) = target {
+                        self.tree_list_state.select(Some(target));
+                    }
+                }
+            }
+            KeyCode::Char('v') if self.use_tree_view => {
+                // Hoist: the selected todo becomes the tree's only root
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.tree_manager.root = Some(todo_id);
+                    self.tree_manager.expansion_states.insert(todo_id, true);
+                    self.refresh_todos()?;
+                    self.tree_list_state.select(Some(0));
+                }
+            }
+            KeyCode::Esc if self.use_tree_view && self.tree_manager.root.is_some() => {
+                let hoisted = self.tree_manager.root.take();
+                self.refresh_todos()?;
+                self.reselect_todo(hoisted);
+            }
+            KeyCode::Left | KeyCode::Char('h') if self.current_parent.is_some() => {
+                self.current_parent = None;
+                self.refresh_todos()?;
+                if !self.incomplete_todos.is_empty() {
+                    self.list_state.select(Some(0));
                     if self.use_tree_view {
-                        // Update tree manager directly for visual feedback
-                        self.tree_manager.update_todo_completion(todo_id, !is_currently_completed);
+                        self.tree_list_state.select(Some(0));
                     }
-                    
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_completed_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('c') => self.leave_flat_view(),
+            KeyCode::Char('X') => self.toggle_cancelled()?,
+            KeyCode::Char('V') => {
+                let todo_id = self.get_selected_todo().map(|todo| todo.id);
+                self.open_history(todo_id)?;
+            }
+            KeyCode::Char('t') => self.set_completed_filter(self.completed_range.next(), self.completed_root.clone())?,
+            KeyCode::Char('p') => self.toggle_completed_project()?,
+            KeyCode::Down | KeyCode::Char('j') => {
+                // Page in more instead of wrapping around while older ones are left
+                let last = self.completed_todos.len().checked_sub(1);
+                if self.completed_list_state.selected() == last && self.load_more_completed()? {
+                    self.completed_list_state.select(Some(last.map_or(0, |row| row + 1)));
+                } else {
+                    self.next_todo();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Enter => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Char(' ') => {
+                // Allow uncompleting todos from completed view
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.set_completion(todo_id, false)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_scheduled_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Z') => self.leave_flat_view(),
+            KeyCode::Down | KeyCode::Char('j') => self.next_todo(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Enter => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.set_completion(todo_id, true)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            KeyCode::Char('D') => self.open_defer_prompt(),
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            KeyCode::Char('E') => self.open_quick_edit(QuickEditField::Estimate)?,
+            KeyCode::Char('!') => self.open_quick_edit(QuickEditField::Flag)?,
+            KeyCode::Char('@') => self.cycle_context()?,
+            KeyCode::Char('%') => self.cycle_energy()?,
+            KeyCode::Char('>') => self.open_delegate_prompt(),
+            KeyCode::Char('s') => {
+                // Start now: back into the tree and list
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.database.set_start_at(todo_id, None)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                    self.status_message = Some("Started".to_string());
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_someday_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => self.leave_flat_view(),
+            KeyCode::Down | KeyCode::Char('j') => self.next_todo(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Enter => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.set_completion(todo_id, true)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            KeyCode::Char('>') => self.open_delegate_prompt(),
+            KeyCode::Char('B') | KeyCode::Char('p') => self.toggle_someday()?,
+            KeyCode::Char('D') => self.open_defer_prompt(),
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            KeyCode::Char('E') => self.open_quick_edit(QuickEditField::Estimate)?,
+            KeyCode::Char('!') => self.open_quick_edit(QuickEditField::Flag)?,
+            KeyCode::Char('@') => self.cycle_context()?,
+            KeyCode::Char('%') => self.cycle_energy()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_waiting_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('U') => self.leave_flat_view(),
+            KeyCode::Down | KeyCode::Char('j') => self.next_todo(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Enter => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.set_completion(todo_id, true)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                }
+            }
+            KeyCode::Char('>') => self.open_delegate_prompt(),
+            KeyCode::Char('<') => {
+                // Take it back: no longer waiting on anyone
+                if let Some(todo) = self.get_selected_todo() {
+                    let todo_id = todo.id;
+                    self.database.set_waiting(todo_id, None)?;
+                    self.refresh_todos()?;
+                    self.update_selection_after_refresh();
+                    self.status_message = Some("Taken back".to_string());
+                }
+            }
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_today_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        // The carry-over offer is answered before anything else
+        if let Some((_, todos)) = &self.carry_over {
+            match key {
+                KeyCode::Char('y') => {
+                    let todo_ids: Vec<i64> = todos.iter().map(|todo| todo.id).collect();
+                    self.database.add_to_daily_plan(Local::now().date_naive(), &todo_ids)?;
+                    self.answer_carry_over()?;
+                    self.status_message = Some(format!("Carried over {} todo(s)", todo_ids.len()));
+                }
+                KeyCode::Char('n') | KeyCode::Esc => self.answer_carry_over()?,
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('=') => self.leave_flat_view(),
+            KeyCode::Down | KeyCode::Char('j') => self.next_todo(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Enter => {
+                if let Some(todo) = self.get_selected_todo() {
+                    self.editor_pending = Some(todo.clone());
+                }
+            }
+            KeyCode::Char(' ') => {
+                // Done todos stay in the plan, checked off, so Space reopens them too
+                if let Some(todo) = self.get_selected_todo() {
+                    let (todo_id, complete) = (todo.id, !todo.is_completed());
+                    self.set_completion(todo_id, complete)?;
                     self.refresh_todos()?;
                     self.update_selection_after_refresh();
                 }
             }
+            KeyCode::Char('+') | KeyCode::Char('x') => self.toggle_daily_plan()?,
+            KeyCode::Char('J') => self.open_note_prompt(),
+            KeyCode::Char('r') => self.open_quick_edit(QuickEditField::Title)?,
+            KeyCode::Char('e') => self.open_quick_edit(QuickEditField::Due)?,
+            KeyCode::Char('#') => self.open_quick_edit(QuickEditField::Tags)?,
+            KeyCode::Char('E') => self.open_quick_edit(QuickEditField::Estimate)?,
+            KeyCode::Char('!') => self.open_quick_edit(QuickEditField::Flag)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Load today's plan, and what the last earlier plan left unfinished until the
+    /// carry-over offer has been answered today
+    fn refresh_daily_plan(&mut self) -> anyhow::Result<()> {
+        let today = Local::now().date_naive();
+        self.today_todos = self.database.get_daily_plan(today)?;
+        self.carry_over = None;
+        if self.database.get_ui_state("daily_plan.carry_over_answered")? == Some(today.format("%Y-%m-%d").to_string()) {
+            return Ok(());
+        }
+        if let Some(day) = self.database.last_daily_plan_before(today)? {
+            let unfinished: Vec<Todo> = self
+                .database
+                .get_daily_plan(day)?
+                .into_iter()
+                .filter(|todo| !todo.is_completed() && !self.today_todos.iter().any(|planned| planned.id == todo.id))
+                .collect();
+            if !unfinished.is_empty() {
+                self.carry_over = Some((day, unfinished));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop offering the carry-over for the rest of the day, whichever way it went
+    fn answer_carry_over(&mut self) -> anyhow::Result<()> {
+        self.database.set_ui_state("daily_plan.carry_over_answered", &Local::now().date_naive().format("%Y-%m-%d").to_string())?;
+        self.refresh_todos()?;
+        if self.today_list_state.selected().is_none() && !self.today_todos.is_empty() {
+            self.today_list_state.select(Some(0));
+        }
+        Ok(())
+    }
+
+    /// Add the selected todo to today's plan, or take it out when it is already there
+    fn toggle_daily_plan(&mut self) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else { return Ok(()) };
+        let (todo_id, title) = (todo.id, todo.title.clone());
+        let today = Local::now().date_naive();
+        let planned = self.today_todos.iter().any(|todo| todo.id == todo_id);
+        if planned {
+            self.database.remove_from_daily_plan(today, todo_id)?;
+        } else {
+            self.database.add_to_daily_plan(today, &[todo_id])?;
+        }
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        self.status_message = Some(if planned { format!("Took '{}' out of today's plan", title) } else { format!("Added '{}' to today's plan", title) });
+        Ok(())
+    }
+
+    /// Ask who the selected todo is waiting on and when to follow up, prefilled with the
+    /// current delegation
+    fn open_delegate_prompt(&mut self) {
+        let Some(todo) = self.get_selected_todo() else {
+            return;
+        };
+        self.delegate_input = self.waiting.get(&todo.id).map_or(String::new(), waiting::format_delegation);
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::DelegatePrompt;
+    }
+
+    fn handle_delegate_prompt_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc => {
+                self.delegate_input.clear();
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Enter => {
+                let Some(todo_id) = self.get_selected_todo().map(|todo| todo.id) else {
+                    self.mode = self.previous_mode.clone();
+                    return Ok(());
+                };
+                // Submitting an empty prompt takes the todo back
+                let waiting = if self.delegate_input.trim().is_empty() {
+                    None
+                } else {
+                    match waiting::parse_delegation(&self.delegate_input, &self.config.waiting.follow_up) {
+                        Ok(waiting) => Some(waiting),
+                        Err(e) => {
+                            self.error_message = Some(e);
+                            return Ok(());
+                        }
+                    }
+                };
+                self.database.set_waiting(todo_id, waiting.as_ref())?;
+                self.delegate_input.clear();
+                self.mode = self.previous_mode.clone();
+                self.refresh_todos()?;
+                self.update_selection_after_refresh();
+                self.status_message = Some(match &waiting {
+                    Some(Waiting { on, follow_up: Some(follow_up) }) => format!("Waiting on {}, follow up {}", on, dates::format_start_date(*follow_up)),
+                    Some(Waiting { on, follow_up: None }) => format!("Waiting on {}", on),
+                    None => "Taken back".to_string(),
+                });
+            }
+            KeyCode::Backspace => {
+                self.delegate_input.pop();
+            }
+            KeyCode::Char(c) => self.delegate_input.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Set the selected todo aside as someday/maybe, or promote it back into the tree and
+    /// list when it already is
+    fn toggle_someday(&mut self) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else { return Ok(()) };
+        let (todo_id, title) = (todo.id, todo.title.clone());
+        let promote = self.someday_todos.iter().any(|todo| todo.id == todo_id);
+        self.database.set_someday(todo_id, !promote)?;
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        self.status_message = Some(if promote {
+            format!("Promoted '{}' to the active list", title)
+        } else {
+            format!("Moved '{}' to Someday/Maybe", title)
+        });
+        Ok(())
+    }
+
+    /// Pin the selected todo above the tree and list, or unpin it
+    fn toggle_pinned(&mut self) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else { return Ok(()) };
+        let (todo_id, title) = (todo.id, todo.title.clone());
+        let pinned = self.database.get_pinned_ids()?.contains(&todo_id);
+        self.database.set_pinned(todo_id, !pinned)?;
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        self.status_message = Some(if pinned { format!("Unpinned '{}'", title) } else { format!("Pinned '{}'", title) });
+        Ok(())
+    }
+
+    /// Ask for a start date for the selected todo, prefilled with its current one
+    fn open_defer_prompt(&mut self) {
+        let Some(todo) = self.get_selected_todo() else {
+            return;
+        };
+        self.defer_input = todo.start_at.map_or(String::new(), dates::format_start_date);
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::DeferPrompt;
+    }
+
+    fn handle_defer_prompt_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc => {
+                self.defer_input.clear();
+                self.mode = self.previous_mode.clone();
+            }
             KeyCode::Enter => {
-                if let Some(todo) = self.get_selected_todo() {
-                    self.editor_pending = Some(todo.clone());
-                }
-            }
-            KeyCode::Right | KeyCode::Char('l') => {
-                if let Some(todo) = self.get_selected_todo() {
-                    self.current_parent = Some(todo.id);
-                    self.refresh_todos()?;
-                    if !self.incomplete_todos.is_empty() {
-                        self.list_state.select(Some(0));
-                        if self.use_tree_view {
-                            self.tree_list_state.select(Some(0));
+                let Some(todo_id) = self.get_selected_todo().map(|todo| todo.id) else {
+                    self.mode = self.previous_mode.clone();
+                    return Ok(());
+                };
+                let input = self.defer_input.trim().to_string();
+                // Submitting an empty prompt makes the todo active again
+                let start_at = if input.is_empty() {
+                    None
+                } else {
+                    match dates::parse_start_date(&input) {
+                        Some(start_at) => Some(start_at),
+                        None => {
+                            self.error_message = Some(format!("Invalid start date '{}': use 2d, 1w, YYYY-MM-DD or YYYY-MM-DD HH:MM", input));
+                            return Ok(());
                         }
                     }
-                }
+                };
+                self.database.set_start_at(todo_id, start_at)?;
+                self.defer_input.clear();
+                self.mode = self.previous_mode.clone();
+                self.refresh_todos()?;
+                self.update_selection_after_refresh();
+                self.status_message = Some(match start_at {
+                    Some(start_at) if start_at > Utc::now() => format!("Deferred until {}", dates::format_start_date(start_at)),
+                    _ => "Started".to_string(),
+                });
             }
-            KeyCode::Left | KeyCode::Char('h') => {
-                if self.current_parent.is_some() {
-                    self.current_parent = None;
-                    self.refresh_todos()?;
-                    if !self.incomplete_todos.is_empty() {
-                        self.list_state.select(Some(0));
-                        if self.use_tree_view {
-                            self.tree_list_state.select(Some(0));
-                        }
-                    }
-                }
+            KeyCode::Backspace => {
+                self.defer_input.pop();
             }
+            KeyCode::Char(c) => self.defer_input.push(c),
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_completed_view_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+
+    /// Ask for a note to append to the selected todo's journal
+    fn open_note_prompt(&mut self) {
+        if self.get_selected_todo().is_none() {
+            return;
+        }
+        self.note_input.clear();
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::NotePrompt;
+    }
+
+    fn handle_note_prompt_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
         match key {
-            KeyCode::Esc | KeyCode::Char('q') => self.mode = AppMode::List,
-            KeyCode::Char('c') => self.mode = AppMode::List,
-            KeyCode::Down | KeyCode::Char('j') => self.next_todo(),
-            KeyCode::Up | KeyCode::Char('k') => self.previous_todo(),
+            KeyCode::Esc => {
+                self.note_input.clear();
+                self.mode = self.previous_mode.clone();
+            }
             KeyCode::Enter => {
-                if let Some(todo) = self.get_selected_todo() {
-                    self.editor_pending = Some(todo.clone());
+                let body = self.note_input.trim().to_string();
+                if let Some(todo_id) = self.get_selected_todo().map(|todo| todo.id)
+                    && !body.is_empty() {
+                    self.database.add_note(todo_id, &body)?;
+                    self.status_message = Some("Note added".to_string());
                 }
+                self.note_input.clear();
+                self.mode = self.previous_mode.clone();
+                self.refresh_todos()?;
             }
-            KeyCode::Char(' ') => {
-                // Allow uncompleting todos from completed view
-                if let Some(todo) = self.get_selected_todo() {
-                    let todo_id = todo.id;
-                    self.database.uncomplete_todo(todo_id)?;
-                    self.refresh_todos()?;
-                    self.update_selection_after_refresh();
-                }
+            KeyCode::Backspace => {
+                self.note_input.pop();
             }
+            KeyCode::Char(c) => self.note_input.push(c),
             _ => {}
         }
         Ok(())
     }
 
+    /// Ask for a new value of one field of the selected todo, prefilled with the current one
+    fn open_quick_edit(&mut self, field: QuickEditField) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else {
+            return Ok(());
+        };
+        self.quick_edit_input = match field {
+            QuickEditField::Title => todo.title.clone(),
+            QuickEditField::Due => todo.due_by.map_or(String::new(), |due_by| due_by.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
+            QuickEditField::Tags => tags::extract_tags(&todo.title).iter().map(|tag| format!("#{} ", tag)).collect(),
+            QuickEditField::Estimate => self.estimates.get(&todo.id).map_or(String::new(), |minutes| agenda::format_estimate(*minutes)),
+            QuickEditField::Flag => self.tree_manager.flags.get(&todo.id).map_or(String::new(), |flag| flag.as_str().to_string()),
+        };
+        self.quick_edit_field = field;
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::QuickEdit;
+        if matches!(field, QuickEditField::Title | QuickEditField::Tags) {
+            self.load_known_tags()?;
+        }
+        Ok(())
+    }
+
+    fn handle_quick_edit_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.handle_tag_completion_key(key) {
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc => {
+                self.quick_edit_input.clear();
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Enter => {
+                let Some(todo) = self.get_selected_todo().cloned() else {
+                    self.mode = self.previous_mode.clone();
+                    return Ok(());
+                };
+                let input = self.quick_edit_input.trim().to_string();
+                let status = match self.quick_edit_field {
+                    QuickEditField::Title => {
+                        if input.is_empty() {
+                            self.error_message = Some("A todo needs a title".to_string());
+                            return Ok(());
+                        }
+                        self.database.update_title(todo.id, &input)?;
+                        "Renamed".to_string()
+                    }
+                    QuickEditField::Due => {
+                        // Submitting an empty prompt clears the due date
+                        let due_by = if input.is_empty() {
+                            None
+                        } else {
+                            match dates::parse_due_date_at(&input, self.config.dates.due_time()) {
+                                Some(due_by) => Some(due_by),
+                                None => {
+                                    self.error_message = Some(format!("Invalid due date '{}': use 2d, 1w, YYYY-MM-DD or YYYY-MM-DD HH:MM", input));
+                                    return Ok(());
+                                }
+                            }
+                        };
+                        self.database.update_due_by(todo.id, due_by)?;
+                        match due_by {
+                            Some(due_by) => format!("Due {}", due_by.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+                            None => "Due date cleared".to_string(),
+                        }
+                    }
+                    QuickEditField::Tags => {
+                        let new_tags: Vec<&str> = input.split_whitespace().map(|word| word.trim_start_matches(['#', '@'])).filter(|tag| !tag.is_empty()).collect();
+                        if let Some(bad) = new_tags.iter().find(|tag| !tags::is_valid_tag(tag)) {
+                            self.error_message = Some(format!("Invalid tag '{}': use letters, digits, _ and -", bad));
+                            return Ok(());
+                        }
+                        let title = tags::replace_tags(&todo.title, &new_tags);
+                        if title.is_empty() {
+                            self.error_message = Some("A todo needs a title".to_string());
+                            return Ok(());
+                        }
+                        self.database.update_title(todo.id, &title)?;
+                        if new_tags.is_empty() { "Tags removed".to_string() } else { "Tags set".to_string() }
+                    }
+                    QuickEditField::Estimate => {
+                        // Submitting an empty prompt clears the estimate
+                        let minutes = if input.is_empty() {
+                            None
+                        } else {
+                            match agenda::parse_estimate(&input) {
+                                Some(minutes) => Some(minutes),
+                                None => {
+                                    self.error_message = Some(format!("Invalid estimate '{}': use 45m, 2h, 1h30m or minutes", input));
+                                    return Ok(());
+                                }
+                            }
+                        };
+                        self.database.set_estimate(todo.id, minutes)?;
+                        match minutes {
+                            Some(minutes) => format!("Estimated {}", agenda::format_estimate(minutes)),
+                            None => "Estimate cleared".to_string(),
+                        }
+                    }
+                    QuickEditField::Flag => {
+                        let flag = match Flag::parse(&input) {
+                            Ok(flag) => flag,
+                            Err(message) => {
+                                self.error_message = Some(message);
+                                return Ok(());
+                            }
+                        };
+                        self.database.set_flag(todo.id, flag.as_ref())?;
+                        flag.map_or("Flag cleared".to_string(), |flag| format!("Flagged {}", flag.as_str()))
+                    }
+                };
+                self.quick_edit_input.clear();
+                self.tag_completions.clear();
+                self.mode = self.previous_mode.clone();
+                self.refresh_todos()?;
+                self.update_selection_after_refresh();
+                self.status_message = Some(status);
+            }
+            KeyCode::Backspace => {
+                self.quick_edit_input.pop();
+                self.refresh_tag_completions();
+            }
+            KeyCode::Char(c) => {
+                self.quick_edit_input.push(c);
+                self.refresh_tag_completions();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
     fn update_selection_after_refresh(&mut self) {
         match self.mode {
@@ -1171,6 +4287,46 @@ impl App {
                     }
                 }
             }
+            AppMode::ScheduledView => {
+                if self.scheduled_todos.is_empty() {
+                    self.scheduled_list_state.select(None);
+                } else {
+                    let selected = self.scheduled_list_state.selected().unwrap_or(0);
+                    if selected >= self.scheduled_todos.len() {
+                        self.scheduled_list_state.select(Some(self.scheduled_todos.len() - 1));
+                    }
+                }
+            }
+            AppMode::Someday => {
+                if self.someday_todos.is_empty() {
+                    self.someday_list_state.select(None);
+                } else {
+                    let selected = self.someday_list_state.selected().unwrap_or(0);
+                    if selected >= self.someday_todos.len() {
+                        self.someday_list_state.select(Some(self.someday_todos.len() - 1));
+                    }
+                }
+            }
+            AppMode::Waiting => {
+                if self.waiting_todos.is_empty() {
+                    self.waiting_list_state.select(None);
+                } else {
+                    let selected = self.waiting_list_state.selected().unwrap_or(0);
+                    if selected >= self.waiting_todos.len() {
+                        self.waiting_list_state.select(Some(self.waiting_todos.len() - 1));
+                    }
+                }
+            }
+            AppMode::Today => {
+                if self.today_todos.is_empty() {
+                    self.today_list_state.select(None);
+                } else {
+                    let selected = self.today_list_state.selected().unwrap_or(0);
+                    if selected >= self.today_todos.len() {
+                        self.today_list_state.select(Some(self.today_todos.len() - 1));
+                    }
+                }
+            }
             _ => {
                 if self.use_tree_view {
                     let lines_len = self.tree_manager.get_rendered_lines().len();
@@ -1197,141 +4353,442 @@ impl App {
     }
 
 
-
-    fn handle_create_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
-        match key {
-            KeyCode::Esc => self.mode = AppMode::List,
-            KeyCode::Enter => {
-                if !self.input_title.trim().is_empty() {
-                    // Try parsing from relative field first, then absolute field
-                    let due_by = if !self.input_due_date_relative.trim().is_empty() {
-                        Self::parse_due_date(&self.input_due_date_relative)
-                    } else if !self.input_due_date_absolute.trim().is_empty() {
-                        Self::parse_due_date(&self.input_due_date_absolute)
-                    } else {
-                        None
-                    };
-                    let new_todo = NewTodo {
-                        title: self.input_title.clone(),
-                        description: self.input_description.clone(),
-                        parent_id: self.selected_parent_id,
-                        due_by,
-                    };
-                    self.database.create_todo(new_todo)?;
-                    self.refresh_todos()?;
-                    self.mode = AppMode::List;
-                    self.input_title.clear();
-                    self.input_parent.clear();
-                    self.input_description.clear();
-                    self.input_due_date_relative.clear();
-                    self.input_due_date_absolute.clear();
-                    self.selected_parent_id = None;
-                    self.create_field_focus = CreateFieldFocus::Title;
-                } else {
-                    self.error_message = Some("Title cannot be empty".to_string());
-                }
-            }
-            KeyCode::Tab => {
-                match self.create_field_focus {
-                    CreateFieldFocus::Title => {
-                        self.create_field_focus = CreateFieldFocus::DueDateRelative;
-                    }
-                    CreateFieldFocus::DueDateRelative => {
-                        self.create_field_focus = CreateFieldFocus::DueDateAbsolute;
-                    }
-                    CreateFieldFocus::DueDateAbsolute => {
-                        self.create_field_focus = CreateFieldFocus::Parent;
-                    }
-                    CreateFieldFocus::Parent => {
-                        self.create_field_focus = CreateFieldFocus::Description;
-                    }
-                    CreateFieldFocus::Description => {
-                        self.create_field_focus = CreateFieldFocus::Title;
-                    }
-                }
+
+    /// Open an empty Create form with its parent field set to `parent_id`
+    fn open_create_form(&mut self, parent_id: Option<i64>) -> anyhow::Result<()> {
+        self.mode = AppMode::Create;
+        self.load_known_tags()?;
+        self.input_title.clear();
+        self.input_description.clear();
+        self.input_due_date_relative.clear();
+        self.input_due_date_absolute.clear();
+        self.create_field_focus = CreateFieldFocus::Title;
+        self.create_cursor = 0;
+
+        let parent = match parent_id {
+            Some(parent_id) => self.database.get_todo_by_id(parent_id)?,
+            None => None,
+        };
+        if let Some(parent) = parent {
+            self.selected_parent_id = Some(parent.id);
+            let parent_display = text::truncate_to_width(&parent.title, 40);
+            self.input_parent = format!("ID:{} {}", parent.id, parent_display);
+        } else {
+            // No parent: a top-level todo
+            self.input_parent.clear();
+            self.selected_parent_id = None;
+        }
+        Ok(())
+    }
+
+    fn handle_create_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let title_len = self.input_title.len();
+        if self.handle_tag_completion_key(key) {
+            // An inserted tag leaves the cursor after it
+            if self.input_title.len() != title_len {
+                self.create_cursor = self.input_title.len();
+            }
+            return Ok(());
+        }
+        match key {
+            KeyCode::Esc => self.mode = AppMode::List,
+            KeyCode::Enter if self.create_field_focus == CreateFieldFocus::Description => {
+                self.input_description.insert(self.create_cursor, '\n');
+                self.create_cursor += 1;
+            }
+            KeyCode::Enter => self.submit_create_form()?,
+            KeyCode::Tab => {
+                self.create_field_focus = match self.create_field_focus {
+                    CreateFieldFocus::Title => CreateFieldFocus::DueDateRelative,
+                    CreateFieldFocus::DueDateRelative => CreateFieldFocus::DueDateAbsolute,
+                    CreateFieldFocus::DueDateAbsolute => CreateFieldFocus::Parent,
+                    CreateFieldFocus::Parent => CreateFieldFocus::Description,
+                    CreateFieldFocus::Description => CreateFieldFocus::Title,
+                };
+                self.create_cursor = self.create_field_text().map_or(0, str::len);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End | KeyCode::Up | KeyCode::Down => {
+                let cursor = self.create_cursor;
+                let Some(text) = self.create_field_text() else { return Ok(()) };
+                self.create_cursor = match key {
+                    KeyCode::Left => text::previous_boundary(text, cursor),
+                    KeyCode::Right => text::next_boundary(text, cursor),
+                    KeyCode::Home => text::line_bounds(text, cursor).0,
+                    KeyCode::End => text::line_bounds(text, cursor).1,
+                    _ if self.create_field_focus == CreateFieldFocus::Description => text::vertical_move(text, cursor, key == KeyCode::Down),
+                    _ => cursor,
+                };
+            }
+            KeyCode::Char(c) if self.create_field_focus == CreateFieldFocus::Parent => {
+                if c == 'r' {
+                    // Clear parent field on 'r' key
+                    self.input_parent.clear();
+                    self.selected_parent_id = None;
+                } else {
+                    // Enter parent search mode when typing in parent field
+                    self.mode = AppMode::ParentSearch;
+                    self.search_query.clear();
+                    self.search_query.push(c);
+                    self.update_search_results()?;
+                }
+            }
+            KeyCode::Backspace | KeyCode::Delete if self.create_field_focus == CreateFieldFocus::Parent => {
+                // Clear parent selection
+                self.input_parent.clear();
+                self.selected_parent_id = None;
+            }
+            KeyCode::Char(c) => {
+                let cursor = self.create_cursor;
+                if let Some(text) = self.create_field_text_mut() {
+                    text.insert(cursor, c);
+                    self.create_cursor += c.len_utf8();
+                }
+                self.sync_create_due_dates(false);
+            }
+            KeyCode::Backspace | KeyCode::Delete => {
+                let cursor = self.create_cursor;
+                if let Some(text) = self.create_field_text_mut() {
+                    let range = if key == KeyCode::Backspace {
+                        text::previous_boundary(text, cursor)..cursor
+                    } else {
+                        cursor..text::next_boundary(text, cursor)
+                    };
+                    text.replace_range(range.clone(), "");
+                    self.create_cursor = range.start;
+                }
+                self.sync_create_due_dates(true);
+            }
+            _ => {}
+        }
+        if matches!(key, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete) {
+            self.refresh_tag_completions();
+        }
+        Ok(())
+    }
+
+    /// The text of the focused Create form field; the parent is picked by search instead
+    fn create_field_text(&self) -> Option<&str> {
+        match self.create_field_focus {
+            CreateFieldFocus::Title => Some(&self.input_title),
+            CreateFieldFocus::DueDateRelative => Some(&self.input_due_date_relative),
+            CreateFieldFocus::DueDateAbsolute => Some(&self.input_due_date_absolute),
+            CreateFieldFocus::Description => Some(&self.input_description),
+            CreateFieldFocus::Parent => None,
+        }
+    }
+
+    fn create_field_text_mut(&mut self) -> Option<&mut String> {
+        match self.create_field_focus {
+            CreateFieldFocus::Title => Some(&mut self.input_title),
+            CreateFieldFocus::DueDateRelative => Some(&mut self.input_due_date_relative),
+            CreateFieldFocus::DueDateAbsolute => Some(&mut self.input_due_date_absolute),
+            CreateFieldFocus::Description => Some(&mut self.input_description),
+            CreateFieldFocus::Parent => None,
+        }
+    }
+
+    /// After an edit to one due date field, fill in the other from it. A deletion that
+    /// leaves the field unreadable clears the other one as well.
+    fn sync_create_due_dates(&mut self, clear_unparsed: bool) {
+        match self.create_field_focus {
+            CreateFieldFocus::DueDateRelative => {
+                if let Some(due_date) = dates::parse_due_date_at(&self.input_due_date_relative, self.config.dates.due_time()) {
+                    self.input_due_date_absolute = due_date.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
+                } else if clear_unparsed {
+                    self.input_due_date_absolute.clear();
+                }
+            }
+            CreateFieldFocus::DueDateAbsolute => {
+                // Calculate the time difference in days (default unit)
+                if let Some(due_date) = dates::parse_due_date_at(&self.input_due_date_absolute, self.config.dates.due_time()) {
+                    let days = due_date.signed_duration_since(Utc::now()).num_days();
+                    // Default to days, show 0 if less than a day
+                    self.input_due_date_relative = format!("{}", days.max(0));
+                } else if clear_unparsed {
+                    self.input_due_date_relative.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Create the todo in the Create form (Ctrl+S, or Enter outside the description)
+    fn submit_create_form(&mut self) -> anyhow::Result<()> {
+        if self.input_title.trim().is_empty() {
+            self.error_message = Some("Title cannot be empty".to_string());
+            return Ok(());
+        }
+        if !self.check_parent_rule(self.selected_parent_id)? {
+            return Ok(());
+        }
+        // Try parsing from relative field first, then absolute field
+        let due_by = if !self.input_due_date_relative.trim().is_empty() {
+            dates::parse_due_date_at(&self.input_due_date_relative, self.config.dates.due_time())
+        } else if !self.input_due_date_absolute.trim().is_empty() {
+            dates::parse_due_date_at(&self.input_due_date_absolute, self.config.dates.due_time())
+        } else {
+            None
+        };
+        let new_todo = NewTodo {
+            title: self.input_title.clone(),
+            description: self.input_description.clone(),
+            parent_id: self.selected_parent_id,
+            due_by,
+        };
+        let todo_id = self.database.create_todo(new_todo)?;
+        self.refresh_todos()?;
+        self.mode = AppMode::List;
+        // Select the new todo, so the next one can go under or next to it
+        self.reselect_todo(Some(todo_id));
+        self.input_title.clear();
+        self.input_parent.clear();
+        self.input_description.clear();
+        self.input_due_date_relative.clear();
+        self.input_due_date_absolute.clear();
+        self.selected_parent_id = None;
+        self.create_field_focus = CreateFieldFocus::Title;
+        self.create_cursor = 0;
+        Ok(())
+    }
+
+    /// Copy the selected todo's title (t), markdown document (m) or subtree task list (s)
+    /// Move the selected todo on to the next of the configured contexts (`[contexts] names`)
+    fn cycle_context(&mut self) -> anyhow::Result<()> {
+        let Some(todo_id) = self.get_selected_todo().map(|todo| todo.id) else { return Ok(()) };
+        let current = self.labels.get(&todo_id).and_then(|labels| labels.context.clone());
+        let next = context::cycle_context(current.as_deref(), &self.config.contexts.names);
+        self.database.set_context(todo_id, next.as_deref())?;
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        self.status_message = Some(next.map_or("Context cleared".to_string(), |context| format!("Context: @{}", context)));
+        Ok(())
+    }
+
+    fn cycle_energy(&mut self) -> anyhow::Result<()> {
+        let Some(todo_id) = self.get_selected_todo().map(|todo| todo.id) else { return Ok(()) };
+        let next = Energy::cycle(self.labels.get(&todo_id).and_then(|labels| labels.energy));
+        self.database.set_energy(todo_id, next)?;
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        self.status_message = Some(next.map_or("Energy cleared".to_string(), |energy| format!("Energy: {}", energy.as_str())));
+        Ok(())
+    }
+
+    /// Each key narrows (or widens) the views at once: a digit toggles that context, l/m/h
+    /// an energy level, x clears both
+    fn handle_context_picker_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('C') => {
+                self.mode = AppMode::List;
+                return Ok(());
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let Some(context) = self.config.contexts.names.get(c as usize - '1' as usize).cloned() else { return Ok(()) };
+                self.now_context = if self.now_context.as_ref() == Some(&context) { None } else { Some(context) };
+            }
+            KeyCode::Char(c @ ('l' | 'm' | 'h')) => {
+                let energy = Energy::parse(&c.to_string());
+                self.now_energy = if self.now_energy == energy { None } else { energy };
+            }
+            KeyCode::Char('x') => {
+                self.now_context = None;
+                self.now_energy = None;
+            }
+            _ => return Ok(()),
+        }
+        self.apply_context_filter()
+    }
+
+    /// Apply the `1` / `2` / `3` due filter (today, this week, overdue), or clear it when
+    /// it's the active one
+    fn toggle_quick_filter(&mut self, key: char) -> anyhow::Result<()> {
+        let Some((_, name, expression)) = QUICK_FILTERS.iter().find(|(quick_key, _, _)| *quick_key == key) else {
+            return Ok(());
+        };
+        if self.active_filter.as_ref().is_some_and(|(active, _)| active == name) {
+            return self.activate_filter(None);
+        }
+        let filter = Filter::parse(expression).map_err(anyhow::Error::msg)?;
+        self.activate_filter(Some((name.to_string(), filter)))
+    }
+
+    /// Make the context picker's choice the active filter, or drop it when nothing is chosen
+    fn apply_context_filter(&mut self) -> anyhow::Result<()> {
+        let mut terms = Vec::new();
+        let mut names = Vec::new();
+        if let Some(context) = &self.now_context {
+            terms.push(format!("context:{}", context));
+            names.push(format!("@{}", context));
+        }
+        if let Some(energy) = self.now_energy {
+            terms.push(format!("energy:{}", energy.as_str()));
+            names.push(format!("{} energy", energy.as_str()));
+        }
+        if terms.is_empty() {
+            return self.activate_filter(None);
+        }
+        let filter = Filter::parse(&terms.join(" ")).map_err(anyhow::Error::msg)?;
+        self.activate_filter(Some((format!("Now: {}", names.join(", ")), filter)))
+    }
+
+    fn handle_copy_menu_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo().cloned() else {
+            self.mode = AppMode::List;
+            return Ok(());
+        };
+        let copied = match key {
+            KeyCode::Char('t') => Some(("title", Ok(todo.title.clone()))),
+            KeyCode::Char('m') => Some(("markdown", self.database.get_notes(todo.id).map(|notes| editor::todo_to_markdown(&todo, &notes)))),
+            KeyCode::Char('s') => Some(("subtree", clipboard::subtree_to_markdown(&self.database, todo.id))),
+            KeyCode::Esc => {
+                self.mode = AppMode::List;
+                None
+            }
+            _ => None,
+        };
+
+        if let Some((what, text)) = copied {
+            self.mode = AppMode::List;
+            match text.and_then(|text| clipboard::copy(&text)) {
+                Ok(method) => self.status_message = Some(format!("Copied {} of '{}' to the clipboard ({})", what, todo.title, method)),
+                Err(e) => self.error_message = Some(format!("Failed to copy: {}", e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the selected todo to the next (or previous) status in `[statuses] cycle`
+    fn cycle_status(&mut self, forward: bool) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else {
+            return Ok(());
+        };
+        let next = Status::cycle(self.tree_manager.status_of(todo), &self.config.statuses.cycle, forward);
+        self.set_status(todo.id, next)
+    }
+
+    /// Give a todo a status, completing or reopening it when that changes whether it's closed
+    fn set_status(&mut self, todo_id: i64, status: Status) -> anyhow::Result<()> {
+        let Some(todo) = self.database.get_todo_by_id(todo_id)? else {
+            return Ok(());
+        };
+        if status.is_closed() == todo.is_completed() {
+            self.database.set_status(todo_id, status)?;
+        } else {
+            // Saved with the completion change, which may wait on the cascade question
+            self.change_completion(todo_id, status.is_closed(), Some(status))?;
+            if self.use_tree_view && self.mode != AppMode::ConfirmCascade {
+                // Update tree manager directly for visual feedback
+                self.tree_manager.update_todo_completion(todo_id, status.is_closed());
+            }
+        }
+        if self.status_message.is_none() && self.mode != AppMode::ConfirmCascade {
+            self.status_message = Some(format!("Status: {}", status.label()));
+        }
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
+        Ok(())
+    }
+
+    /// Cancel the selected todo, or undo that: back to open, or to done in the completed view
+    fn toggle_cancelled(&mut self) -> anyhow::Result<()> {
+        let Some(todo) = self.get_selected_todo() else {
+            return Ok(());
+        };
+        let next = match self.tree_manager.status_of(todo) {
+            Status::Cancelled if self.mode == AppMode::CompletedView => Status::Done,
+            Status::Cancelled => Status::Open,
+            _ => Status::Cancelled,
+        };
+        self.set_status(todo.id, next)
+    }
+
+    /// Complete or reopen a todo, taking its open subtasks or completed parents along
+    /// as `[parents] complete_children` / `reopen_parents` say (asking first with "ask")
+    fn set_completion(&mut self, todo_id: i64, complete: bool) -> anyhow::Result<()> {
+        self.change_completion(todo_id, complete, None)
+    }
+
+    /// `set_completion`, then give the todo `status` if the change goes through
+    fn change_completion(&mut self, todo_id: i64, complete: bool, status: Option<Status>) -> anyhow::Result<()> {
+        let (rule, count) = if complete {
+            let open = self.database.get_subtree(todo_id)?.iter().filter(|todo| todo.id != todo_id && !todo.is_completed()).count();
+            (self.config.parents.complete_children, open)
+        } else {
+            (self.config.parents.reopen_parents, self.database.get_completed_ancestors(todo_id)?.len())
+        };
+        let open_children = if complete && count > 0 { self.config.parents.open_children } else { ParentRule::Allow };
+        match rule {
+            CascadeRule::Always if count > 0 => self.apply_completion(todo_id, complete, true, status),
+            _ if open_children == ParentRule::Prevent => {
+                self.ask_cascade(PendingCascade { todo_id, complete, count, single: false, status });
+                Ok(())
+            }
+            CascadeRule::Ask if count > 0 => {
+                self.ask_cascade(PendingCascade { todo_id, complete, count, single: true, status });
+                Ok(())
+            }
+            _ => {
+                self.apply_completion(todo_id, complete, false, status)?;
+                if open_children == ParentRule::Warn {
+                    self.status_message = Some(format!("Note: {} subtask(s) are still open", count));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn ask_cascade(&mut self, pending: PendingCascade) {
+        self.cascade_pending = Some(pending);
+        self.previous_mode = self.mode.clone();
+        self.mode = AppMode::ConfirmCascade;
+    }
+
+    fn apply_completion(&mut self, todo_id: i64, complete: bool, cascade: bool, status: Option<Status>) -> anyhow::Result<()> {
+        let changed = match (complete, cascade) {
+            (true, true) => self.database.complete_subtree(todo_id)?,
+            (false, true) => self.database.uncomplete_with_ancestors(todo_id)?,
+            (true, false) => {
+                self.database.complete_todo(todo_id)?;
+                vec![todo_id]
             }
-            KeyCode::Char(c) => {
-                match self.create_field_focus {
-                    CreateFieldFocus::Title => {
-                        self.input_title.push(c);
-                    }
-                    CreateFieldFocus::DueDateRelative => {
-                        self.input_due_date_relative.push(c);
-                        // Sync to absolute field
-                        if let Some(due_date) = Self::parse_due_date(&self.input_due_date_relative) {
-                            self.input_due_date_absolute = due_date.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
-                        }
-                    }
-                    CreateFieldFocus::DueDateAbsolute => {
-                        self.input_due_date_absolute.push(c);
-                        // Sync to relative field - calculate time difference in days (default unit)
-                        if let Some(due_date) = Self::parse_due_date(&self.input_due_date_absolute) {
-                            let now = Utc::now();
-                            let diff = due_date.signed_duration_since(now);
-                            let days = diff.num_days();
-
-                            // Default to days, show 0 if less than a day
-                            self.input_due_date_relative = format!("{}", days.max(0));
-                        }
-                    }
-                    CreateFieldFocus::Description => {
-                        self.input_description.push(c);
-                    }
-                    CreateFieldFocus::Parent => {
-                        if c == 'r' {
-                            // Clear parent field on 'r' key
-                            self.input_parent.clear();
-                            self.selected_parent_id = None;
-                        } else {
-                            // Enter parent search mode when typing in parent field
-                            self.mode = AppMode::ParentSearch;
-                            self.search_query.clear();
-                            self.search_query.push(c);
-                            self.update_search_results()?;
-                        }
-                    }
-                }
+            (false, false) => {
+                self.database.uncomplete_todo(todo_id)?;
+                vec![todo_id]
             }
-            KeyCode::Backspace => {
-                match self.create_field_focus {
-                    CreateFieldFocus::Title => {
-                        self.input_title.pop();
-                    }
-                    CreateFieldFocus::DueDateRelative => {
-                        self.input_due_date_relative.pop();
-                        // Sync to absolute field
-                        if let Some(due_date) = Self::parse_due_date(&self.input_due_date_relative) {
-                            self.input_due_date_absolute = due_date.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
-                        } else {
-                            self.input_due_date_absolute.clear();
-                        }
-                    }
-                    CreateFieldFocus::DueDateAbsolute => {
-                        self.input_due_date_absolute.pop();
-                        // Sync to relative field - calculate time difference in days (default unit)
-                        if let Some(due_date) = Self::parse_due_date(&self.input_due_date_absolute) {
-                            let now = Utc::now();
-                            let diff = due_date.signed_duration_since(now);
-                            let days = diff.num_days();
-
-                            // Default to days, show 0 if less than a day
-                            self.input_due_date_relative = format!("{}", days.max(0));
-                        } else {
-                            self.input_due_date_relative.clear();
-                        }
-                    }
-                    CreateFieldFocus::Description => {
-                        self.input_description.pop();
-                    }
-                    CreateFieldFocus::Parent => {
-                        // Clear parent selection
-                        self.input_parent.clear();
-                        self.selected_parent_id = None;
-                    }
-                }
+        };
+        // Subtasks closed along with a cancelled parent are cancelled too, not done;
+        // reopened parents just come back open
+        let status_ids = if complete { changed.clone() } else { vec![todo_id] };
+        if let Some(status) = status {
+            for id in status_ids {
+                self.database.set_status(id, status)?;
             }
-            _ => {}
         }
+        if changed.len() > 1 {
+            self.status_message = Some(match (complete, status) {
+                (true, Some(Status::Cancelled)) => format!("Cancelled {} subtask(s) too", changed.len() - 1),
+                (true, _) => format!("Completed {} subtask(s) too", changed.len() - 1),
+                (false, _) => format!("Reopened {} parent(s) too", changed.len() - 1),
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_cascade_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        let Some(pending) = self.cascade_pending else {
+            self.mode = self.previous_mode.clone();
+            return Ok(());
+        };
+        match key {
+            KeyCode::Char('y') => self.apply_completion(pending.todo_id, pending.complete, true, pending.status)?,
+            KeyCode::Char('n') if pending.single => self.apply_completion(pending.todo_id, pending.complete, false, pending.status)?,
+            KeyCode::Esc => {}
+            _ => return Ok(()),
+        }
+        self.cascade_pending = None;
+        self.mode = self.previous_mode.clone();
+        self.refresh_todos()?;
+        self.update_selection_after_refresh();
         Ok(())
     }
 
@@ -1436,76 +4893,59 @@ impl App {
         self.tree_list_state.select(Some(i));
     }
 
-    fn half_page_down(&mut self) {
-        if self.use_tree_view {
-            self.half_page_down_tree();
-        } else {
-            self.half_page_down_list();
-        }
-    }
-
-    fn half_page_up(&mut self) {
-        if self.use_tree_view {
-            self.half_page_up_tree();
-        } else {
-            self.half_page_up_list();
+    /// Rows in the current view, counting the ROOT row of move mode
+    fn current_view_len(&self) -> usize {
+        match self.mode {
+            AppMode::CompletedView => self.completed_todos.len(),
+            AppMode::ScheduledView => self.scheduled_todos.len(),
+            AppMode::Someday => self.someday_todos.len(),
+            AppMode::Waiting => self.waiting_todos.len(),
+            AppMode::Today => self.today_todos.len(),
+            _ if self.use_tree_view => self.tree_manager.get_rendered_lines().len() + usize::from(self.mode == AppMode::Move),
+            _ => self.incomplete_todos.len(),
         }
     }
 
-    fn half_page_down_tree(&mut self) {
-        let lines_len = self.tree_manager.get_rendered_lines().len();
-        if lines_len == 0 {
+    /// Select `row` in the current view, or its last row when there are fewer
+    fn select_row(&mut self, row: usize) {
+        let len = self.current_view_len();
+        if len == 0 {
             return;
         }
+        self.get_current_list_state_mut().select(Some(row.min(len - 1)));
+    }
 
-        let current = self.tree_list_state.selected().unwrap_or(0);
-        let jump_size = 10; // Half page size - could be made configurable
-        let new_pos = std::cmp::min(current + jump_size, lines_len.saturating_sub(1));
-        self.tree_list_state.select(Some(new_pos));
+    /// Half of what the view showed when last drawn
+    fn half_page(&self) -> usize {
+        (self.viewport_rows / 2).max(1)
     }
 
-    fn half_page_up_tree(&mut self) {
-        let lines_len = self.tree_manager.get_rendered_lines().len();
-        if lines_len == 0 {
-            return;
-        }
+    fn half_page_down(&mut self) {
+        let current = self.get_current_list_state().selected().unwrap_or(0);
+        self.select_row(current + self.half_page());
+    }
 
-        let current = self.tree_list_state.selected().unwrap_or(0);
-        let jump_size = 10; // Half page size - could be made configurable
-        let new_pos = if current >= jump_size {
-            current - jump_size
-        } else {
-            0
-        };
-        self.tree_list_state.select(Some(new_pos));
+    fn half_page_up(&mut self) {
+        let current = self.get_current_list_state().selected().unwrap_or(0);
+        self.select_row(current.saturating_sub(self.half_page()));
     }
 
-    fn half_page_down_list(&mut self) {
-        let list_len = self.incomplete_todos.len();
-        if list_len == 0 {
-            return;
+    fn jump_to_bottom(&mut self) -> anyhow::Result<()> {
+        // The oldest completed todos may not be paged in yet
+        if self.mode == AppMode::CompletedView {
+            while self.load_more_completed()? {}
         }
-
-        let current = self.list_state.selected().unwrap_or(0);
-        let jump_size = 10; // Half page size - could be made configurable
-        let new_pos = std::cmp::min(current + jump_size, list_len.saturating_sub(1));
-        self.list_state.select(Some(new_pos));
+        self.select_row(usize::MAX);
+        Ok(())
     }
 
-    fn half_page_up_list(&mut self) {
-        let list_len = self.incomplete_todos.len();
-        if list_len == 0 {
-            return;
+    /// Scroll so the selection sits in the middle of the view
+    fn center_selection(&mut self) {
+        let half = self.viewport_rows / 2;
+        let state = self.get_current_list_state_mut();
+        if let Some(selected) = state.selected() {
+            *state.offset_mut() = selected.saturating_sub(half);
         }
-
-        let current = self.list_state.selected().unwrap_or(0);
-        let jump_size = 10; // Half page size - could be made configurable
-        let new_pos = if current >= jump_size {
-            current - jump_size
-        } else {
-            0
-        };
-        self.list_state.select(Some(new_pos));
     }
 
     fn update_tree_selection_after_toggle(&mut self, previous_selected: usize) {
@@ -1538,18 +4978,15 @@ impl App {
                     self.update_search_results()?;
                 } else {
                     // If there's a selected result, view/edit it with editor
-                    if let Some(selected) = self.search_list_state.selected() {
-                        if let Some(todo) = self.search_results.get(selected) {
-                            self.editor_pending = Some(todo.clone());
-                        }
+                    if let Some(selected) = self.search_list_state.selected()
+                        && let Some(todo) = self.search_results.get(selected) {
+                        self.editor_pending = Some(todo.clone());
                     }
                 }
             }
-            KeyCode::Backspace => {
-                if self.search_input_mode {
-                    self.search_query.pop();
-                    self.update_search_results()?;
-                }
+            KeyCode::Backspace if self.search_input_mode => {
+                self.search_query.pop();
+                self.update_search_results()?;
             }
             KeyCode::Char(c) => {
                 if self.search_input_mode {
@@ -1572,15 +5009,11 @@ impl App {
                 }
             }
             // Arrow keys always work for navigation regardless of mode
-            KeyCode::Down => {
-                if !self.search_input_mode {
-                    self.next_search_result();
-                }
+            KeyCode::Down if !self.search_input_mode => {
+                self.next_search_result();
             }
-            KeyCode::Up => {
-                if !self.search_input_mode {
-                    self.previous_search_result();
-                }
+            KeyCode::Up if !self.search_input_mode => {
+                self.previous_search_result();
             }
             _ => {}
         }
@@ -1611,11 +5044,9 @@ impl App {
                     }
                 }
             }
-            KeyCode::Backspace => {
-                if self.search_input_mode {
-                    self.search_query.pop();
-                    self.update_tree_search_matches()?;
-                }
+            KeyCode::Backspace if self.search_input_mode => {
+                self.search_query.pop();
+                self.update_tree_search_matches()?;
             }
             KeyCode::Char(c) => {
                 if self.search_input_mode {
@@ -1667,15 +5098,12 @@ impl App {
                         }
                         't' => {
                             // Allow tree expansion/collapse during search with 't' key
-                            if self.use_tree_view {
-                                if let Some(selected) = self.tree_list_state.selected() {
-                                    if let Some(line) = self.tree_manager.get_rendered_lines().get(selected) {
-                                        if line.has_children {
-                                            self.tree_manager.toggle_expansion(line.todo_id);
-                                            self.update_tree_selection_after_toggle(selected);
-                                        }
-                                    }
-                                }
+                            if self.use_tree_view
+                                && let Some(selected) = self.tree_list_state.selected()
+                                && let Some(line) = self.tree_manager.get_rendered_lines().get(selected)
+                                && line.has_children {
+                                self.tree_manager.toggle_expansion(line.todo_id);
+                                self.update_tree_selection_after_toggle(selected);
                             }
                         }
                         'n' => {
@@ -1687,25 +5115,9 @@ impl App {
                             self.navigate_to_previous_match();
                         }
                         ' ' => {
-                            // Allow toggling completion during search
-                            if let Some(todo) = self.get_selected_todo() {
-                                let todo_id = todo.id;
-                                let is_currently_completed = todo.is_completed();
-                                
-                                if is_currently_completed {
-                                    self.database.uncomplete_todo(todo_id)?;
-                                } else {
-                                    self.database.complete_todo(todo_id)?;
-                                }
-                                
-                                if self.use_tree_view {
-                                    self.tree_manager.update_todo_completion(todo_id, !is_currently_completed);
-                                }
-                                
-                                self.refresh_todos()?;
-                                self.update_selection_after_refresh();
-                                self.update_tree_search_matches()?;
-                            }
+                            // Allow changing status during search
+                            self.cycle_status(true)?;
+                            self.update_tree_search_matches()?;
                         }
                         _ => {
                             // Any other character goes to search input when not in input mode
@@ -1718,27 +5130,35 @@ impl App {
                 }
             }
             // Arrow keys always work for navigation regardless of mode
-            KeyCode::Down => {
-                if !self.search_input_mode {
-                    if self.use_tree_view {
-                        self.next_tree_item();
-                    } else {
-                        self.next_todo();
-                    }
+            KeyCode::Down if !self.search_input_mode => {
+                if self.use_tree_view {
+                    self.next_tree_item();
+                } else {
+                    self.next_todo();
                 }
             }
-            KeyCode::Up => {
-                if !self.search_input_mode {
+            KeyCode::Up if !self.search_input_mode => {
+                if self.use_tree_view {
+                    self.previous_tree_item();
+                } else {
+                    self.previous_todo();
+                }
+            }
+            KeyCode::Left if !self.search_input_mode && self.current_parent.is_some() => {
+                self.current_parent = None;
+                self.refresh_todos()?;
+                self.update_tree_search_matches()?;
+                if !self.incomplete_todos.is_empty() {
+                    self.list_state.select(Some(0));
                     if self.use_tree_view {
-                        self.previous_tree_item();
-                    } else {
-                        self.previous_todo();
+                        self.tree_list_state.select(Some(0));
                     }
                 }
             }
-            KeyCode::Left => {
-                if !self.search_input_mode && self.current_parent.is_some() {
-                    self.current_parent = None;
+            KeyCode::Right => {
+                if !self.search_input_mode
+                    && let Some(todo) = self.get_selected_todo() {
+                    self.current_parent = Some(todo.id);
                     self.refresh_todos()?;
                     self.update_tree_search_matches()?;
                     if !self.incomplete_todos.is_empty() {
@@ -1749,21 +5169,6 @@ impl App {
                     }
                 }
             }
-            KeyCode::Right => {
-                if !self.search_input_mode {
-                    if let Some(todo) = self.get_selected_todo() {
-                        self.current_parent = Some(todo.id);
-                        self.refresh_todos()?;
-                        self.update_tree_search_matches()?;
-                        if !self.incomplete_todos.is_empty() {
-                            self.list_state.select(Some(0));
-                            if self.use_tree_view {
-                                self.tree_list_state.select(Some(0));
-                            }
-                        }
-                    }
-                }
-            }
             _ => {}
         }
         Ok(())
@@ -1821,19 +5226,16 @@ impl App {
             }
             KeyCode::Enter => {
                 // Select the highlighted parent
-                if let Some(selected) = self.search_list_state.selected() {
-                    if let Some(todo) = self.search_results.get(selected) {
-                        self.selected_parent_id = Some(todo.id);
-                        // Truncate to 40 characters
-                        let parent_display = if todo.title.len() > 40 {
-                            format!("{}...", &todo.title[..37])
-                        } else {
-                            todo.title.clone()
-                        };
-                        self.input_parent = format!("ID:{} {}", todo.id, parent_display);
-                        self.mode = AppMode::Create;
-                        self.create_field_focus = CreateFieldFocus::Parent;
+                if let Some(selected) = self.search_list_state.selected()
+                    && let Some(todo) = self.search_results.get(selected).cloned() {
+                    if !self.check_parent_rule(Some(todo.id))? {
+                        return Ok(());
                     }
+                    self.selected_parent_id = Some(todo.id);
+                    let parent_display = text::truncate_to_width(&todo.title, 40);
+                    self.input_parent = format!("ID:{} {}", todo.id, parent_display);
+                    self.mode = AppMode::Create;
+                    self.create_field_focus = CreateFieldFocus::Parent;
                 }
             }
             KeyCode::Down | KeyCode::Char('j') => self.next_search_result(),
@@ -1872,16 +5274,20 @@ impl App {
                     }
                 }
             }
-            KeyCode::Backspace => {
-                if self.search_input_mode {
-                    self.goto_query.pop();
-                    self.update_goto_matches()?;
-                }
+            KeyCode::Backspace if self.search_input_mode => {
+                self.goto_query.pop();
+                self.update_goto_matches()?;
+            }
+            KeyCode::Char('g') if self.search_input_mode && self.goto_query.is_empty() => {
+                // gg: the top of the tree rather than an id
+                self.mode = AppMode::List;
+                self.search_input_mode = false;
+                self.select_row(0);
             }
             KeyCode::Char(c) => {
                 if self.search_input_mode {
-                    // Only allow digits
-                    if c.is_ascii_digit() {
+                    // Only allow digits, or short codes when those are shown
+                    if c.is_ascii_digit() || (self.config.display.ids == IdDisplay::Code && (c.is_ascii_alphabetic() || c == '-')) {
                         self.goto_query.push(c);
                         self.update_goto_matches()?;
                     }
@@ -1907,25 +5313,9 @@ impl App {
                             self.navigate_to_previous_goto_match();
                         }
                         ' ' => {
-                            // Allow toggling completion during goto
-                            if let Some(todo) = self.get_selected_todo() {
-                                let todo_id = todo.id;
-                                let is_currently_completed = todo.is_completed();
-
-                                if is_currently_completed {
-                                    self.database.uncomplete_todo(todo_id)?;
-                                } else {
-                                    self.database.complete_todo(todo_id)?;
-                                }
-
-                                if self.use_tree_view {
-                                    self.tree_manager.update_todo_completion(todo_id, !is_currently_completed);
-                                }
-
-                                self.refresh_todos()?;
-                                self.update_selection_after_refresh();
-                                self.update_goto_matches()?;
-                            }
+                            // Allow changing status during goto
+                            self.cycle_status(true)?;
+                            self.update_goto_matches()?;
                         }
                         _ => {
                             // Any other character goes to goto input when not in input mode
@@ -1940,25 +5330,57 @@ impl App {
                 }
             }
             // Arrow keys always work for navigation regardless of mode
-            KeyCode::Down => {
-                if !self.search_input_mode {
-                    if self.use_tree_view {
-                        self.next_tree_item();
-                    }
-                }
+            KeyCode::Down if !self.search_input_mode && self.use_tree_view => {
+                self.next_tree_item();
             }
-            KeyCode::Up => {
-                if !self.search_input_mode {
-                    if self.use_tree_view {
-                        self.previous_tree_item();
-                    }
-                }
+            KeyCode::Up if !self.search_input_mode && self.use_tree_view => {
+                self.previous_tree_item();
             }
             _ => {}
         }
         Ok(())
     }
 
+    /// Move `todo` to the parent chosen in the last move. The cursor stays on the same
+    /// row, so in a run of todos going to one place the next one is already selected.
+    fn repeat_move(&mut self, todo: Todo, new_parent_id: Option<i64>) -> anyhow::Result<()> {
+        let target = match new_parent_id {
+            Some(parent_id) => match self.database.get_todo_by_id(parent_id)? {
+                Some(parent) => format!("'{}'", parent.title),
+                None => {
+                    self.last_move_parent = None;
+                    self.error_message = Some("The last move target no longer exists".to_string());
+                    return Ok(());
+                }
+            },
+            None => "the top level".to_string(),
+        };
+        if todo.parent_id == new_parent_id {
+            self.status_message = Some(format!("'{}' is already under {}", todo.title, target));
+            return Ok(());
+        }
+        if !self.check_parent_rule(new_parent_id)? {
+            return Ok(());
+        }
+
+        match self.database.move_todo(todo.id, new_parent_id) {
+            Ok(()) => {
+                // Stay on the same row, so M can be pressed again for the todo below
+                let (row, offset) = (self.tree_list_state.selected(), self.tree_list_state.offset());
+                self.refresh_todos()?;
+                let last_line = self.tree_manager.get_rendered_lines().len().checked_sub(1);
+                if let (Some(row), Some(last_line)) = (row, last_line) {
+                    self.tree_list_state.select(Some(row.min(last_line)));
+                    *self.tree_list_state.offset_mut() = offset;
+                }
+                // Keep a [parents] warning if there is one
+                self.status_message.get_or_insert_with(|| format!("Moved '{}' to {}", todo.title, target));
+            }
+            Err(e) => self.error_message = Some(format!("Cannot move todo: {}", e)),
+        }
+        Ok(())
+    }
+
     fn handle_move_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
         match key {
             KeyCode::Esc | KeyCode::Char('q') => {
@@ -1973,6 +5395,28 @@ impl App {
                 // Move to previous valid parent candidate in tree
                 self.move_to_previous_valid_parent();
             }
+            KeyCode::Tab => self.move_as_sibling = !self.move_as_sibling,
+            KeyCode::Enter if self.move_as_sibling && !self.is_highlighting_root_position() => {
+                if let Some(move_todo_id) = self.move_todo_id
+                    && let Some(target) = self.get_selected_todo().cloned() {
+                    if !self.check_parent_rule(target.parent_id)? {
+                        return Ok(());
+                    }
+                    let order = self.tree_manager.sibling_ids(target.id);
+                    match self.database.move_after(move_todo_id, target.id, &order) {
+                        Ok(()) => {
+                            self.mode = AppMode::List;
+                            self.refresh_todos()?;
+                            self.reselect_todo(Some(move_todo_id));
+                            self.move_todo_id = None;
+                            self.last_move_parent = Some(target.parent_id);
+                        }
+                        Err(e) => {
+                            self.error_message = Some(format!("Cannot move todo: {}", e));
+                        }
+                    }
+                }
+            }
             KeyCode::Enter => {
                 if let Some(move_todo_id) = self.move_todo_id {
                     let new_parent_id = if self.is_highlighting_root_position() {
@@ -1982,12 +5426,18 @@ impl App {
                     } else {
                         return Ok(()); // No valid selection
                     };
+                    if !self.check_parent_rule(new_parent_id)? {
+                        return Ok(());
+                    }
 
                     match self.database.move_todo(move_todo_id, new_parent_id) {
                         Ok(()) => {
-                            self.refresh_todos()?;
                             self.mode = AppMode::List;
+                            self.refresh_todos()?;
+                            // Back on the todo that moved, with its new parent opened up
+                            self.reselect_todo(Some(move_todo_id));
                             self.move_todo_id = None;
+                            self.last_move_parent = Some(new_parent_id);
                         }
                         Err(e) => {
                             self.error_message = Some(format!("Cannot move todo: {}", e));
@@ -2001,10 +5451,42 @@ impl App {
     }
 
     fn handle_help_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        if self.help_search_input {
+            match key {
+                KeyCode::Esc => {
+                    self.help_query.clear();
+                    self.help_search_input = false;
+                }
+                KeyCode::Enter => self.help_search_input = false,
+                KeyCode::Backspace => {
+                    self.help_query.pop();
+                }
+                KeyCode::Char(c) => self.help_query.push(c),
+                _ => return Ok(()),
+            }
+            self.help_scroll = 0;
+            return Ok(());
+        }
+
+        let page = self.help_height.max(1);
         match key {
+            KeyCode::Char('/') => {
+                self.help_query.clear();
+                self.help_search_input = true;
+                self.help_scroll = 0;
+            }
             KeyCode::Esc | KeyCode::Char('a') | KeyCode::Char('q') => {
+                self.help_query.clear();
+                self.help_scroll = 0;
                 self.mode = self.previous_mode.clone();
             }
+            KeyCode::Char('j') | KeyCode::Down => self.help_scroll += 1,
+            KeyCode::Char('k') | KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.help_scroll += page,
+            KeyCode::PageUp => self.help_scroll = self.help_scroll.saturating_sub(page),
+            KeyCode::Char('g') | KeyCode::Home => self.help_scroll = 0,
+            // Drawing clamps this to the last page
+            KeyCode::Char('G') | KeyCode::End => self.help_scroll = usize::MAX,
             _ => {}
         }
         Ok(())
@@ -2104,17 +5586,40 @@ impl App {
                 let todo_id = line.todo_id;
 
                 // Cannot move to itself or its descendants
-                return todo_id != move_todo_id && !self.is_descendant_of(todo_id, move_todo_id);
+                return todo_id != move_todo_id && !self.tree_manager.is_descendant_of(todo_id, move_todo_id);
             }
         }
         false
     }
 
+    /// The ghost line previewing where the todo being moved lands with the highlighted
+    /// target, and the tree line it goes below (None = right under ROOT)
+    fn move_ghost(&self) -> Option<(Option<usize>, Line<'static>)> {
+        let move_todo_id = self.move_todo_id?;
+        let selected = self.tree_list_state.selected()?;
+        if !self.is_valid_parent_candidate_at_index(selected) {
+            return None;
+        }
+        let target = if self.is_highlighting_root_position() { None } else { Some(self.get_selected_todo()?.id) };
+        let preview = self.tree_manager.move_preview(move_todo_id, target, self.move_as_sibling && target.is_some())?;
+        let todo = self.tree_manager.get_todo_by_id(move_todo_id)?;
+
+        let style = Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::ITALIC | Modifier::DIM);
+        let connector = match preview.depth {
+            0 => String::new(),
+            depth => format!("{}└╌╌ ", "    ".repeat(depth - 1)),
+        };
+        let subtasks = subtask_count_suffix(self.tree_manager.descendant_count(move_todo_id));
+        Some((preview.after_line, Line::from(vec![
+            Span::styled(connector, style),
+            Span::styled(format!("⇢ {}{}", todo.title, subtasks), style),
+        ])))
+    }
+
     fn is_highlighting_root_position(&self) -> bool {
-        if self.mode == AppMode::Move {
-            if let Some(selected) = self.tree_list_state.selected() {
-                return selected == 0; // First item is the virtual ROOT
-            }
+        if self.mode == AppMode::Move
+            && let Some(selected) = self.tree_list_state.selected() {
+            return selected == 0; // First item is the virtual ROOT
         }
         false
     }
@@ -2131,26 +5636,30 @@ impl App {
         None
     }
 
-    fn is_descendant_of(&self, potential_descendant: i64, ancestor: i64) -> bool {
-        // Check if potential_descendant is a descendant of ancestor
-        for todo in &self.incomplete_todos {
-            if todo.id == potential_descendant {
-                let mut current_parent = todo.parent_id;
-                while let Some(parent_id) = current_parent {
-                    if parent_id == ancestor {
-                        return true;
-                    }
-                    // Find the parent todo
-                    if let Some(parent_todo) = self.incomplete_todos.iter().find(|t| t.id == parent_id) {
-                        current_parent = parent_todo.parent_id;
-                    } else {
-                        break;
-                    }
-                }
-                break;
-            }
+    /// Apply the `[parents] inactive` rule to a chosen parent. Returns false when a
+    /// completed or hidden parent is refused; with "warn" it's accepted with a note.
+    fn check_parent_rule(&mut self, parent_id: Option<i64>) -> anyhow::Result<bool> {
+        let rule = self.config.parents.inactive;
+        if rule == ParentRule::Allow {
+            return Ok(true);
         }
-        false
+        let Some(parent) = parent_id.map(|id| self.database.get_todo_by_id(id)).transpose()?.flatten() else {
+            return Ok(true);
+        };
+        let state = if parent.is_completed() {
+            "completed"
+        } else if parent.hidden {
+            "hidden"
+        } else {
+            return Ok(true);
+        };
+
+        if rule == ParentRule::Prevent {
+            self.error_message = Some(format!("'{}' is {}; choose an open parent", parent.title, state));
+            return Ok(false);
+        }
+        self.status_message = Some(format!("Note: '{}' is {}, so its subtasks won't show in the default tree", parent.title, state));
+        Ok(true)
     }
 
 
@@ -2158,16 +5667,54 @@ impl App {
         // Update scrollbar states before drawing
         self.update_scrollbar_states();
 
+        if self.mode == AppMode::Locked {
+            // Lock screen takes full screen and hides all todo content
+            self.draw_lock_screen(f, f.area());
+            return;
+        }
+
         if self.mode == AppMode::Help {
             // Help mode takes full screen
             self.draw_help_page(f, f.area());
             return;
         }
 
-        let chunks = Layout::default()
+        let mut chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(0), Constraint::Length(3)])
-            .split(f.area());
+            .split(f.area())
+            .to_vec();
+
+        // A tab bar of the configured views, when there is more than one to switch between
+        if self.tabs.len() > 1 {
+            let tab_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(chunks[0]);
+            self.draw_tab_bar(f, tab_chunks[0]);
+            chunks[0] = tab_chunks[1];
+        }
+
+        // Very wide terminals can keep a stats panel beside whatever the mode draws
+        if self.wide_layout(f.area().width) == WideLayout::Stats {
+            let stats_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(STATS_PANEL_WIDTH)])
+                .split(chunks[0]);
+            self.draw_stats_panel(f, stats_chunks[1]);
+            chunks[0] = stats_chunks[0];
+        }
+
+        // Pinned todos stay in sight above the tree and list
+        if matches!(self.mode, AppMode::List | AppMode::TreeSearch | AppMode::IdModGoto | AppMode::Move) && !self.pinned_todos.is_empty() {
+            let rows = self.pinned_todos.len().min(MAX_PINNED_ROWS) as u16;
+            let pinned_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(rows + 2), Constraint::Min(0)])
+                .split(chunks[0]);
+            self.draw_pinned(f, pinned_chunks[0]);
+            chunks[0] = pinned_chunks[1];
+        }
 
         match self.mode {
             AppMode::List => {
@@ -2192,10 +5739,84 @@ impl App {
                 }
             }
             AppMode::CompletedView => self.draw_completed_view(f, chunks[0]),
+            AppMode::ScheduledView => self.draw_scheduled_view(f, chunks[0]),
+            AppMode::Someday => self.draw_someday_view(f, chunks[0]),
+            AppMode::Waiting => self.draw_waiting_view(f, chunks[0]),
+            AppMode::Today => self.draw_today_view(f, chunks[0]),
             AppMode::Create => self.draw_create_mode(f, chunks[0]),
             AppMode::ConfirmDelete => self.draw_confirm_delete(f, chunks[0]),
+            AppMode::ConfirmCascade => {
+                match self.previous_mode {
+                    AppMode::ScheduledView => self.draw_scheduled_view(f, chunks[0]),
+                    AppMode::Someday => self.draw_someday_view(f, chunks[0]),
+                    AppMode::Waiting => self.draw_waiting_view(f, chunks[0]),
+                    AppMode::Today => self.draw_today_view(f, chunks[0]),
+                    AppMode::CompletedView => self.draw_completed_view(f, chunks[0]),
+                    AppMode::Review => self.draw_review(f, chunks[0]),
+                    _ => self.draw_split_todo_lists(f, chunks[0]),
+                }
+                self.draw_confirm_cascade(f, chunks[0]);
+            }
             AppMode::ListFind => self.draw_list_find_mode(f, chunks[0]),
             AppMode::ParentSearch => self.draw_parent_search_mode(f, chunks[0]),
+            AppMode::FilterPicker => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_filter_picker(f, chunks[0]);
+            }
+            AppMode::FilterPrompt => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_filter_prompt(f, chunks[0]);
+            }
+            AppMode::DeferPrompt | AppMode::NotePrompt | AppMode::QuickEdit | AppMode::DelegatePrompt | AppMode::QuickAdd => {
+                match self.previous_mode {
+                    AppMode::ScheduledView => self.draw_scheduled_view(f, chunks[0]),
+                    AppMode::Someday => self.draw_someday_view(f, chunks[0]),
+                    AppMode::Waiting => self.draw_waiting_view(f, chunks[0]),
+                    AppMode::Today => self.draw_today_view(f, chunks[0]),
+                    AppMode::CompletedView => self.draw_completed_view(f, chunks[0]),
+                    _ => self.draw_split_todo_lists(f, chunks[0]),
+                }
+                if self.mode == AppMode::NotePrompt {
+                    self.draw_note_prompt(f, chunks[0]);
+                } else if self.mode == AppMode::QuickEdit {
+                    self.draw_quick_edit_prompt(f, chunks[0]);
+                } else if self.mode == AppMode::DelegatePrompt {
+                    self.draw_delegate_prompt(f, chunks[0]);
+                } else if self.mode == AppMode::QuickAdd {
+                    self.draw_quick_add(f, chunks[0]);
+                } else {
+                    self.draw_defer_prompt(f, chunks[0]);
+                }
+            }
+            AppMode::Attachments => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_attachments(f, chunks[0]);
+            }
+            AppMode::Onboarding => self.draw_onboarding(f, chunks[0]),
+            AppMode::Capture => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_capture_prompt(f, chunks[0]);
+            }
+            AppMode::TemplatePicker => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_template_picker(f, chunks[0]);
+            }
+            AppMode::CopyMenu => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_copy_menu(f, chunks[0]);
+            }
+            AppMode::ProfilePicker => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_profile_picker(f, chunks[0]);
+            }
+            AppMode::ContextPicker => {
+                self.draw_split_todo_lists(f, chunks[0]);
+                self.draw_context_picker(f, chunks[0]);
+            }
+            AppMode::Stats => self.draw_stats_view(f, chunks[0]),
+            AppMode::Agenda => self.draw_agenda(f, chunks[0]),
+            AppMode::History => self.draw_history(f, chunks[0]),
+            AppMode::Review => self.draw_review(f, chunks[0]),
             AppMode::Move => {
                 // In move mode, just draw the tree view with special highlighting
                 if self.use_tree_view {
@@ -2204,8 +5825,8 @@ impl App {
                     self.draw_split_todo_lists(f, chunks[0]);
                 }
             }
-            AppMode::Help => {
-                // This case is handled above, but needed for exhaustive matching
+            AppMode::Help | AppMode::Locked => {
+                // These cases are handled above, but needed for exhaustive matching
                 unreachable!();
             }
         }
@@ -2213,6 +5834,44 @@ impl App {
         self.draw_help(f, chunks[1]);
     }
 
+    fn draw_pinned(&self, f: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self
+            .pinned_todos
+            .iter()
+            .take(MAX_PINNED_ROWS)
+            .map(|todo| {
+                let mut row = vec![
+                    Span::styled(format!("{} ", self.tree_manager.id_label(todo)), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+                    Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
+                ];
+                if let Some(due_by) = todo.due_by {
+                    let due = if self.config.display.relative_due {
+                        // "due in 3d", "2d overdue", "due now"
+                        let relative = dates::format_relative_due(due_by, Utc::now(), false);
+                        if relative.starts_with("in ") { format!("due {}", relative) } else { relative }
+                    } else {
+                        format!("due {}", due_by.with_timezone(&Local).format(&self.config.dates.row_format(false)))
+                    };
+                    row.push(Span::styled(format!("  {}", due), Style::default().fg(self.get_due_date_style(todo))));
+                }
+                let path = self.path_titles(todo.parent_id);
+                if !path.is_empty() {
+                    row.push(Span::styled(format!("  ({})", path.join(" › ")), Style::default().fg(CatppuccinFrappe::SUBTEXT0)));
+                }
+                Line::from(row)
+            })
+            .collect();
+        let title = match self.pinned_todos.len().saturating_sub(MAX_PINNED_ROWS) {
+            0 => "📌 Pinned".to_string(),
+            more => format!("📌 Pinned · +{} more", more),
+        };
+        let pinned = Paragraph::new(lines).block(Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(pinned, area);
+    }
+
     fn draw_split_todo_lists(&mut self, f: &mut Frame, area: Rect) {
         // Use the full area for incomplete todos (or tree view)
         if self.use_tree_view {
@@ -2223,33 +5882,24 @@ impl App {
     }
 
     fn draw_incomplete_todos(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
         let items: Vec<ListItem> = self
             .incomplete_todos
             .iter()
             .map(|todo| {
-                let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                let due_by_text = if let Some(due_by) = todo.due_by {
-                    format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
-                };
-                let parent_title = self.database.get_parent_title(todo.parent_id)
-                    .unwrap_or(None)
-                    .unwrap_or_else(|| "null".to_string());
 
-                ListItem::new(Line::from(vec![
-                    Span::styled(format!("{} [ ] ", todo.id_mod()), Style::default().fg(CatppuccinFrappe::SUBTEXT1)),
+                ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()), Style::default().fg(self.tree_manager.status_of(todo).color())),
                     Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
-                    Span::styled(format!(" | Created: {}{} | Parent: {}", created_time, due_by_text, parent_title),
-                               Style::default().fg(CatppuccinFrappe::CREATION_TIME)),
-                ]))
+                    Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                ], todo, MetadataView::List, area.width))
             })
             .collect();
 
         let title = if let Some(parent_id) = self.current_parent {
-            format!("Incomplete Todos (Parent: {})", parent_id)
+            format!("{} (Parent: {}){}", self.view_title(ViewKind::List), parent_id, self.active_filter_title_suffix())
         } else {
-            "Incomplete Todos".to_string()
+            format!("{}{}", self.view_title(ViewKind::List), self.active_filter_title_suffix())
         };
 
         let highlight_style = Style::default()
@@ -2262,7 +5912,8 @@ impl App {
                 .title(title)
                 .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
             .highlight_style(highlight_style)
-            .highlight_symbol("▶ ");
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
 
         // Split area to make room for scrollbar
         let chunks = Layout::default()
@@ -2285,17 +5936,27 @@ impl App {
 
 
     fn draw_tree_view(&mut self, f: &mut Frame, area: Rect) {
+        let two_columns = self.wide_layout(f.area().width) == WideLayout::Columns;
+        self.viewport_rows = visible_rows(area) * if two_columns { 2 } else { 1 };
         let rendered_lines = self.tree_manager.get_rendered_lines();
+        let row_width = if two_columns { area.width / 2 } else { area.width };
 
         let mut items: Vec<ListItem> = Vec::new();
+        let move_summaries = if self.mode == AppMode::Move { self.tree_manager.subtree_summaries() } else { HashMap::new() };
+        // Preview of where the moved todo lands with the highlighted target
+        let move_ghost = if self.mode == AppMode::Move { self.move_ghost() } else { None };
 
         // Add virtual ROOT entry at the top in move mode
         if self.mode == AppMode::Move {
             let root_style = Style::default().fg(CatppuccinFrappe::GREEN).add_modifier(Modifier::BOLD);
-            items.push(ListItem::new(Line::from(vec![
+            let mut root = Text::from(Line::from(vec![
                 Span::styled("ROOT", root_style),
                 Span::styled(" (Move here to make top-level)", Style::default().fg(CatppuccinFrappe::SUBTEXT1)),
-            ])));
+            ]));
+            if let Some((None, ghost)) = &move_ghost {
+                root.lines.push(ghost.clone());
+            }
+            items.push(ListItem::new(root));
         }
 
         // Add the regular tree items, adjusting index for move mode
@@ -2305,18 +5966,12 @@ impl App {
             .map(|(tree_index, line)| {
                 let index = if self.mode == AppMode::Move { tree_index + 1 } else { tree_index };
                 if let Some(todo) = self.tree_manager.get_todo_by_id(line.todo_id) {
-                    let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                    let due_by_text = if let Some(due_by) = todo.due_by {
-                        format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                    } else {
-                        String::new()
-                    };
 
                     let (display_style, prefix_style) = if todo.hidden && self.show_hidden_items {
                         // Hidden items shown with italic styling
                         if todo.is_completed() {
                             (
-                                Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::ITALIC),
+                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::ITALIC),
                                 Style::default().fg(CatppuccinFrappe::SURFACE2).add_modifier(Modifier::ITALIC)
                             )
                         } else {
@@ -2327,7 +5982,7 @@ impl App {
                         }
                     } else if todo.is_completed() {
                         (
-                            Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT),
+                            Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT),
                             Style::default().fg(CatppuccinFrappe::SURFACE2)
                         )
                     } else {
@@ -2342,6 +5997,12 @@ impl App {
                                 Style::default().fg(CatppuccinFrappe::YELLOW), // Yellow for item being moved
                                 Style::default().fg(CatppuccinFrappe::YELLOW)
                             )
+                        } else if self.filter_context.contains(&todo.id) {
+                            // Shown for context above a filter match, not a match itself
+                            (
+                                Style::default().fg(CatppuccinFrappe::OVERLAY1),
+                                Style::default().fg(CatppuccinFrappe::PARENT_INDICATOR)
+                            )
                         } else {
                             (
                                 Style::default().fg(self.get_due_date_style(todo)),
@@ -2350,12 +6011,22 @@ impl App {
                         }
                     };
 
-                    ListItem::new(Line::from(vec![
+                    let mut spans = vec![
                         Span::styled(&line.prefix, prefix_style),
                         Span::styled(&line.display_text, display_style),
-                        Span::styled(format!(" | Created: {}{}", created_time, due_by_text),
-                                   Style::default().fg(CatppuccinFrappe::CREATION_TIME)),
-                    ]))
+                        Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                    ];
+                    // Let move targets show what's already under them
+                    if self.mode == AppMode::Move && self.is_valid_parent_candidate_at_index(index) {
+                        let summary = move_summaries.get(&todo.id).copied().unwrap_or_default();
+                        spans.push(Span::styled(format_subtree_summary(summary, &self.config.dates.date_format), Style::default().fg(CatppuccinFrappe::SUBTEXT0)));
+                    }
+                    let mut row = self.aligned_row(spans, todo, MetadataView::Tree, row_width);
+                    if let Some((Some(after_line), ghost)) = &move_ghost
+                        && *after_line == tree_index {
+                        row.lines.push(ghost.clone());
+                    }
+                    ListItem::new(row)
                 } else {
                     ListItem::new(Line::from(Span::styled(
                         format!("{}ERROR: Todo not found", line.prefix),
@@ -2369,22 +6040,579 @@ impl App {
         items.extend(tree_items);
 
         let title = if self.mode == AppMode::Move {
-            if let Some(move_todo_id) = self.move_todo_id {
-                if let Some(todo) = self.incomplete_todos.iter().find(|t| t.id == move_todo_id) {
-                    format!("Move '{}' - Green=Valid Parents, j/k=Navigate, Enter=Confirm", todo.title)
-                } else {
-                    "Move Mode - Green=Valid Parents, j/k=Navigate, Enter=Confirm".to_string()
-                }
+            let (targets, action) = if self.move_as_sibling {
+                ("Green=Valid Targets", "Enter=Place After, Tab=Make Parent")
             } else {
-                "Move Mode - Green=Valid Parents, j/k=Navigate, Enter=Confirm".to_string()
+                ("Green=Valid Parents", "Enter=Make Parent, Tab=Place After")
+            };
+            match self.move_todo_id.and_then(|move_todo_id| self.incomplete_todos.iter().find(|t| t.id == move_todo_id)) {
+                Some(todo) => format!("Move '{}'{} - {}, j/k=Navigate, {}", todo.title, subtask_count_suffix(self.tree_manager.descendant_count(todo.id)), targets, action),
+                None => format!("Move Mode - {}, j/k=Navigate, {}", targets, action),
             }
         } else {
+            let yanked = match &self.yanked_todo {
+                Some((_, title)) => format!(" [Yanked: {}]", title),
+                None => String::new(),
+            };
             if self.show_hidden_items {
-                "Todo Tree View (All Items + Hidden)".to_string()
+                format!("{} (All Items + Hidden){}{}{}", self.view_title(ViewKind::Tree), self.hoist_title_suffix(), self.active_filter_title_suffix(), yanked)
+            } else {
+                format!("{} (All Items){}{}{}", self.view_title(ViewKind::Tree), self.hoist_title_suffix(), self.active_filter_title_suffix(), yanked)
+            }
+        };
+        if two_columns {
+            Self::draw_tree_columns(f, area, items, title, self.tree_list_state.selected(), &mut self.tree_column_offset, &mut self.tree_scrollbar_state);
+            return;
+        }
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        // Split area to make room for scrollbar
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        f.render_stateful_widget(list, chunks[0], &mut self.tree_list_state);
+
+        // Draw scrollbar
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.tree_scrollbar_state);
+    }
+
+    /// The tree in two side-by-side columns, the second continuing where the first ends.
+    /// Takes the state it updates explicitly because the items still borrow the tree manager.
+    fn draw_tree_columns(
+        f: &mut Frame,
+        area: Rect,
+        items: Vec<ListItem>,
+        title: String,
+        selected: Option<usize>,
+        column_offset: &mut usize,
+        scrollbar_state: &mut ScrollbarState,
+    ) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2), Constraint::Length(1)])
+            .split(area);
+
+        // Scroll a whole column at a time so the selection stays within the two visible columns
+        let rows_per_column = (chunks[0].height.saturating_sub(2) as usize).max(1);
+        if let Some(selected) = selected {
+            if selected < *column_offset {
+                *column_offset = selected / rows_per_column * rows_per_column;
+            } else if selected >= *column_offset + 2 * rows_per_column {
+                *column_offset = (selected / rows_per_column).saturating_sub(1) * rows_per_column;
+            }
+        }
+
+        let mut items = items.into_iter().skip(*column_offset);
+        for (column, column_area) in chunks[..2].iter().enumerate() {
+            let start = *column_offset + column * rows_per_column;
+            let column_items: Vec<ListItem> = items.by_ref().take(rows_per_column).collect();
+            let mut state = ListState::default()
+                .with_selected(selected.and_then(|selected| selected.checked_sub(start)).filter(|i| *i < rows_per_column));
+
+            let list = List::new(column_items)
+                .block(Block::default()
+                    .borders(Borders::ALL)
+                    .title(if column == 0 { title.clone() } else { "(continued)".to_string() })
+                    .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+                .highlight_style(Style::default()
+                    .bg(CatppuccinFrappe::SELECTED_BG)
+                    .fg(CatppuccinFrappe::SELECTED))
+                .highlight_symbol("▶ ")
+                // Both columns keep the symbol's space so their rows line up
+                .highlight_spacing(HighlightSpacing::Always);
+            f.render_stateful_widget(list, *column_area, &mut state);
+        }
+
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+        f.render_stateful_widget(scrollbar, chunks[2], scrollbar_state);
+    }
+
+    /// The configured wide layout, or Single when the terminal isn't wide enough for it
+    fn wide_layout(&self, terminal_width: u16) -> WideLayout {
+        if terminal_width >= self.config.layout.wide_min_width {
+            self.config.layout.wide
+        } else {
+            WideLayout::Single
+        }
+    }
+
+    fn draw_stats_panel(&self, f: &mut Frame, area: Rect) {
+        let now = Utc::now();
+        // Cancelled todos count as neither open nor completed
+        let todos: Vec<&Todo> = self.tree_manager.todos.values().filter(|todo| self.tree_manager.status_of(todo) != Status::Cancelled).collect();
+        let open: Vec<&Todo> = todos.iter().copied().filter(|todo| !todo.is_completed()).collect();
+        let due_within = |days: i64| {
+            open.iter().filter(|todo| todo.due_by.is_some_and(|due| due >= now && due < now + chrono::Duration::days(days))).count()
+        };
+        let overdue = open.iter().filter(|todo| todo.due_by.is_some_and(|due| due < now)).count();
+        let done_this_week = todos.iter()
+            .filter(|todo| todo.completed_at.is_some_and(|done| done >= now - chrono::Duration::days(7)))
+            .count();
+        let progress = if todos.is_empty() { 0 } else { (todos.len() - open.len()) * 100 / todos.len() };
+
+        let row = |label: &str, value: String, color: Color| {
+            Line::from(vec![
+                Span::styled(format!("{:<19}", label), Style::default().fg(CatppuccinFrappe::SUBTEXT1)),
+                Span::styled(value, Style::default().fg(color)),
+            ])
+        };
+        let lines = vec![
+            row("Open", open.len().to_string(), CatppuccinFrappe::TEXT),
+            row("Completed", (todos.len() - open.len()).to_string(), CatppuccinFrappe::COMPLETED),
+            row("Progress", format!("{}%", progress), CatppuccinFrappe::TEXT),
+            Line::from(""),
+            row("Overdue", overdue.to_string(), if overdue > 0 { CatppuccinFrappe::RED } else { CatppuccinFrappe::TEXT }),
+            row("Due in 24h", due_within(1).to_string(), CatppuccinFrappe::TEXT),
+            row("Due in 7 days", due_within(7).to_string(), CatppuccinFrappe::TEXT),
+            Line::from(""),
+            row("Done, last 7 days", done_this_week.to_string(), CatppuccinFrappe::COMPLETED),
+        ];
+
+        let stats = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Stats{}", self.active_filter_title_suffix()))
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(stats, area);
+    }
+
+    /// Full-screen stats: totals for the current scope, then one row per project in it
+    fn draw_stats_view(&mut self, f: &mut Frame, area: Rect) {
+        let now = Utc::now();
+        let projects = self.tree_manager.project_stats(self.stats_parent, now);
+        if self.stats_list_state.selected().is_some_and(|i| i >= projects.len()) {
+            self.stats_list_state.select(projects.len().checked_sub(1));
+        }
+        // Trends need the metrics being recorded, and are hidden when they aren't
+        let trends_height = if self.config.metrics.enabled { TREND_WEEKS as u16 + 3 } else { 0 };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(trends_height)])
+            .split(area);
+
+        // The scope is everything, or the drilled-into project including itself
+        let path = self.path_titles(self.stats_parent);
+        let scope = match self.stats_parent {
+            Some(id) => {
+                let grandparent = self.tree_manager.get_todo_by_id(id).and_then(|todo| todo.parent_id);
+                self.tree_manager.project_stats(grandparent, now).into_iter().find(|(project, _)| *project == id).map(|(_, stats)| stats)
+            }
+            None => Some(projects.iter().fold(ProjectStats::default(), |total, (_, stats)| ProjectStats {
+                open: total.open + stats.open,
+                overdue: total.overdue + stats.overdue,
+                done_30d: total.done_30d + stats.done_30d,
+            })),
+        }
+        .unwrap_or_default();
+
+        let label = |text: &str| Span::styled(text.to_string(), Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+        let summary = Paragraph::new(Line::from(vec![
+            label("Open "),
+            Span::styled(scope.open.to_string(), Style::default().fg(CatppuccinFrappe::TEXT)),
+            label("   Overdue "),
+            Span::styled(scope.overdue.to_string(), Style::default().fg(if scope.overdue > 0 { CatppuccinFrappe::RED } else { CatppuccinFrappe::TEXT })),
+            label("   Done, last 30 days "),
+            Span::styled(scope.done_30d.to_string(), Style::default().fg(CatppuccinFrappe::COMPLETED)),
+            label("   Completion rate "),
+            Span::styled(scope.completion_rate().map_or("-".to_string(), |rate| format!("{}%", rate)), Style::default().fg(CatppuccinFrappe::TEXT)),
+        ]))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(if path.is_empty() {
+                format!("Stats{}", self.active_filter_title_suffix())
             } else {
-                "Todo Tree View (All Items)".to_string()
+                format!("Stats: {}{}", path.join(" › "), self.active_filter_title_suffix())
+            })
+            .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(summary, chunks[0]);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(if self.stats_parent.is_some() { "Subprojects" } else { "Projects" })
+            .title_bottom("j/k=Select, Enter=Drill in, h=Back, Esc=Close")
+            .border_style(Style::default().fg(CatppuccinFrappe::BORDER));
+        let inner = block.inner(chunks[1]);
+        f.render_widget(block, chunks[1]);
+        let table_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+
+        // Highlight symbol, then the title, then four right-aligned numbers
+        let title_width = (inner.width as usize).saturating_sub(2 + 31).max(10);
+        let header = format!("  {:<title_width$}{:>6}{:>9}{:>9}{:>7}", "Project", "Open", "Overdue", "Done 30d", "Rate");
+        f.render_widget(Paragraph::new(header).style(Style::default().fg(CatppuccinFrappe::SUBTEXT0)), table_chunks[0]);
+
+        let items: Vec<ListItem> = projects
+            .iter()
+            .map(|(id, stats)| {
+                let title = self.tree_manager.get_todo_by_id(*id).map(|todo| todo.title.as_str()).unwrap_or_default();
+                let title = text::truncate_to_width(title, title_width);
+                let padding = " ".repeat(title_width.saturating_sub(title.width()));
+                let rate = stats.completion_rate().map_or("-".to_string(), |rate| format!("{}%", rate));
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}{}", title, padding), Style::default().fg(CatppuccinFrappe::TEXT)),
+                    Span::styled(format!("{:>6}", stats.open), Style::default().fg(CatppuccinFrappe::TEXT)),
+                    Span::styled(format!("{:>9}", stats.overdue), Style::default().fg(if stats.overdue > 0 { CatppuccinFrappe::RED } else { CatppuccinFrappe::SUBTEXT0 })),
+                    Span::styled(format!("{:>9}", stats.done_30d), Style::default().fg(CatppuccinFrappe::COMPLETED)),
+                    Span::styled(format!("{:>7}", rate), Style::default().fg(CatppuccinFrappe::TEXT)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, table_chunks[1], &mut self.stats_list_state);
+
+        if trends_height > 0 {
+            self.draw_trends(f, chunks[2], now);
+        }
+    }
+
+    /// Open todos by the day they're due, each day headed by its estimated workload and
+    /// flagged when that goes over `[agenda] capacity_minutes`
+    fn draw_agenda(&mut self, f: &mut Frame, area: Rect) {
+        let today = Local::now().date_naive();
+        let capacity = self.config.agenda.capacity_minutes;
+        let days = agenda::agenda(&self.incomplete_todos, &self.estimates, today, self.config.agenda.days);
+
+        let mut lines = Vec::new();
+        let overbooked = days.iter().filter(|day| day.overbooked_by(capacity).is_some()).count();
+        if days.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!("Nothing due in the next {} days.", self.config.agenda.days),
+                Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+            )));
+        }
+        let mut week = days.first().map(|day| metrics::week_start(day.date, self.config.dates.week_start));
+        for day in &days {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            // Divide the days by week, starting on `[dates] week_start`
+            let this_week = metrics::week_start(day.date, self.config.dates.week_start);
+            if week != Some(this_week) {
+                week = Some(this_week);
+                lines.push(Line::from(Span::styled(
+                    format!("── Week of {} ──", this_week.format("%a %b %d")),
+                    Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+                )));
+                lines.push(Line::from(""));
+            }
+            let label = if day.date == today { format!("Today, {}", day.date.format("%a %b %d")) } else { day.date.format("%a %b %d").to_string() };
+            let mut header = vec![
+                Span::styled(format!("{:<18}", label), Style::default().fg(CatppuccinFrappe::BLUE).add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("{} of {}", agenda::format_estimate(day.estimated_minutes), agenda::format_estimate(capacity)),
+                    Style::default().fg(CatppuccinFrappe::SUBTEXT1),
+                ),
+            ];
+            if day.unestimated > 0 {
+                header.push(Span::styled(format!("  +{} unestimated", day.unestimated), Style::default().fg(CatppuccinFrappe::SUBTEXT0)));
+            }
+            if let Some(over) = day.overbooked_by(capacity) {
+                header.push(Span::styled(
+                    format!("  ⚠ Overbooked by {}", agenda::format_estimate(over)),
+                    Style::default().fg(CatppuccinFrappe::RED).add_modifier(Modifier::BOLD),
+                ));
+            }
+            lines.push(Line::from(header));
+            for id in &day.todo_ids {
+                let Some(todo) = self.tree_manager.get_todo_by_id(*id) else { continue };
+                let estimate = self.estimates.get(id).map_or("-".to_string(), |minutes| agenda::format_estimate(*minutes));
+                let mut row = vec![
+                    Span::styled(format!("  {:>6}  ", estimate), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+                    Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
+                ];
+                let path = self.path_titles(todo.parent_id);
+                if !path.is_empty() {
+                    row.push(Span::styled(format!("  ({})", path.join(" › ")), Style::default().fg(CatppuccinFrappe::SUBTEXT0)));
+                }
+                lines.push(Line::from(row));
+            }
+        }
+
+        let title = match overbooked {
+            0 => format!("Agenda{}", self.active_filter_title_suffix()),
+            n => format!("Agenda · {} overbooked day{}{}", n, if n == 1 { "" } else { "s" }, self.active_filter_title_suffix()),
+        };
+        let agenda = Paragraph::new(lines)
+            .scroll((self.agenda_scroll, 0))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom("j/k=Scroll, Esc=Close")
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(agenda, area);
+    }
+
+    /// Recorded changes, newest first; the activity feed names the todo on each line
+    fn draw_history(&mut self, f: &mut Frame, area: Rect) {
+        let mut lines = Vec::new();
+        for entry in &self.history_entries {
+            let color = match entry.action.as_str() {
+                "create" => CatppuccinFrappe::TEAL,
+                "complete" => CatppuccinFrappe::COMPLETED,
+                "delete" => CatppuccinFrappe::RED,
+                "move" => CatppuccinFrappe::LAVENDER,
+                _ => CatppuccinFrappe::TEXT,
+            };
+            let mut row = vec![Span::styled(
+                format!("{}  ", entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+                Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+            )];
+            if self.history_todo.is_none() {
+                row.push(Span::styled(format!("#{} {}  ", entry.todo_id, entry.title), Style::default().fg(CatppuccinFrappe::SUBTEXT1)));
+            }
+            row.push(Span::styled(history::describe(entry), Style::default().fg(color)));
+            lines.push(Line::from(row));
+        }
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled("No changes recorded yet.", Style::default().fg(CatppuccinFrappe::SUBTEXT0))));
+        }
+
+        let title = match self.history_todo {
+            Some(todo_id) => {
+                let title = self.history_entries.first().map_or(String::new(), |entry| entry.title.clone());
+                format!("History of #{} {}", todo_id, title)
+            }
+            None => "Activity".to_string(),
+        };
+        let hint = if self.history_todo.is_some() { "j/k=Scroll, a=All activity, Esc=Close" } else { "j/k=Scroll, a=Selected todo, Esc=Close" };
+        let history = Paragraph::new(lines)
+            .scroll((self.history_scroll, 0))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(hint)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(history, area);
+    }
+
+    /// The review's current todo: why it came up, where it sits, its dates and description
+    fn draw_review(&mut self, f: &mut Frame, area: Rect) {
+        // Subtasks completed after the prompt about the todo before them are skipped
+        self.skip_done_review_todos();
+        let Some((item, todo)) = self.review_todo() else {
+            let done = Paragraph::new("That was the last todo to review. Press any key to finish.")
+                .block(Block::default().borders(Borders::ALL).title("Review").border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+            f.render_widget(done, area);
+            return;
+        };
+        let now = Utc::now();
+        let label = |text: &str| Span::styled(text.to_string(), Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+        let date = |at: DateTime<Utc>| at.with_timezone(&Local).format("%Y-%m-%d").to_string();
+
+        let mut lines = vec![Line::from(Span::styled(
+            todo.title.clone(),
+            Style::default().fg(self.get_due_date_style(&todo)).add_modifier(Modifier::BOLD),
+        ))];
+        let path = self.path_titles(todo.parent_id);
+        if !path.is_empty() {
+            lines.push(Line::from(vec![label("In "), Span::raw(path.join(" › "))]));
+        }
+        lines.push(Line::from(""));
+        if let Some(due) = todo.due_by {
+            let late = (now - due).num_days();
+            let note = if due < now { format!(" ({}d overdue)", late) } else { String::new() };
+            lines.push(Line::from(vec![label("Due           "), Span::raw(format!("{}{}", date(due), note))]));
+        }
+        lines.push(Line::from(vec![
+            label("Last touched  "),
+            Span::raw(format!("{} ({}d ago)", date(item.last_touched), (now - item.last_touched).num_days())),
+        ]));
+        lines.push(Line::from(vec![label("Created       "), Span::raw(date(todo.created_at))]));
+        let open_subtasks = self.database.get_subtree(todo.id).map_or(0, |subtree| subtree.iter().filter(|t| t.id != todo.id && !t.is_completed()).count());
+        if open_subtasks > 0 {
+            lines.push(Line::from(vec![label("Open subtasks "), Span::raw(open_subtasks.to_string())]));
+        }
+        if !todo.description.trim().is_empty() {
+            lines.push(Line::from(""));
+            lines.extend(todo.description.lines().map(|line| Line::from(line.to_string())));
+        }
+
+        let (hint, hint_color) = if self.review_confirm_delete {
+            ("Delete this todo? y=Yes, any other key=No", CatppuccinFrappe::RED)
+        } else {
+            ("k=Keep s=Snooze c=Complete d=Delete h=Hide n=Skip p=Back Esc=Stop", CatppuccinFrappe::SUBTEXT1)
+        };
+        let review = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Review {}/{} · {}", self.review_index + 1, self.review_queue.len(), item.reason.label()))
+                .title_bottom(Line::from(Span::styled(hint, Style::default().fg(hint_color))))
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)));
+        f.render_widget(review, area);
+    }
+
+    /// Weekly totals of the sessions recorded in this database, newest week last
+    fn draw_trends(&self, f: &mut Frame, area: Rect, now: DateTime<Utc>) {
+        let since = now - chrono::Duration::weeks(TREND_WEEKS as i64);
+        let mut sessions = self.database.get_sessions_since(since).unwrap_or_default();
+        // The running session counts up to now, not to when it was last saved
+        if let Some(current) = &self.session
+            && let Some(stored) = sessions.iter_mut().find(|session| session.id == current.id) {
+            *stored = Session { ended_at: now, ..current.clone() };
+        }
+        let trends = metrics::weekly_trends(&sessions, now, TREND_WEEKS, self.config.dates.week_start);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Productivity trends")
+            .border_style(Style::default().fg(CatppuccinFrappe::BORDER));
+        let header = Line::from(Span::styled(
+            format!("  {:<12}{:>9}{:>10}{:>9}{:>7}", "Week of", "Sessions", "Time", "Created", "Done"),
+            Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+        ));
+        let mut lines = vec![header];
+        lines.extend(trends.iter().map(|trend| {
+            let quiet = trend.sessions == 0;
+            let color = if quiet { CatppuccinFrappe::SUBTEXT0 } else { CatppuccinFrappe::TEXT };
+            Line::from(vec![
+                Span::styled(format!("  {:<12}", trend.start.format("%b %d")), Style::default().fg(CatppuccinFrappe::SUBTEXT1)),
+                Span::styled(format!("{:>9}", trend.sessions), Style::default().fg(color)),
+                Span::styled(format!("{:>10}", metrics::format_duration(trend.time)), Style::default().fg(color)),
+                Span::styled(format!("{:>9}", trend.created), Style::default().fg(color)),
+                Span::styled(format!("{:>7}", trend.completed), Style::default().fg(if quiet { color } else { CatppuccinFrappe::COMPLETED })),
+            ])
+        }));
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_idmod_goto_view(&mut self, f: &mut Frame, area: Rect) {
+        // Split area to make room for goto input at bottom
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(area);
+
+        // Draw tree view with goto highlighting in the main area
+        self.draw_tree_view_with_goto_highlights(f, chunks[0]);
+
+        // Draw goto input at bottom
+        let title = if self.config.display.ids == IdDisplay::Code { "Goto ID or code (WEB-12)" } else { "Goto ID (digits only)" };
+        let goto_input = Paragraph::new(self.goto_query.as_str())
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE)))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(goto_input, chunks[1]);
+    }
+
+    fn draw_tree_view_with_goto_highlights(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let rendered_lines = self.tree_manager.get_rendered_lines();
+
+        let items: Vec<ListItem> = rendered_lines
+            .iter()
+            .map(|line| {
+                if let Some(todo) = self.tree_manager.get_todo_by_id(line.todo_id) {
+
+                    // Check if this todo matches the goto query
+                    let is_match = self.goto_matches.contains(&line.todo_id);
+                    let is_current_match = self.goto_current_match_index
+                        .and_then(|idx| self.goto_matches.get(idx))
+                        .map(|&match_id| match_id == line.todo_id)
+                        .unwrap_or(false);
+
+                    let (display_style, prefix_style) = if todo.hidden && self.show_hidden_items {
+                        // Hidden items shown with italic styling
+                        if todo.is_completed() {
+                            (
+                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::ITALIC),
+                                Style::default().fg(CatppuccinFrappe::SURFACE2).add_modifier(Modifier::ITALIC)
+                            )
+                        } else {
+                            (
+                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::ITALIC),
+                                Style::default().fg(CatppuccinFrappe::PARENT_INDICATOR).add_modifier(Modifier::ITALIC)
+                            )
+                        }
+                    } else if todo.is_completed() {
+                        (
+                            if is_current_match {
+                                // Current match - bright yellow and underlined
+                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
+                            } else if is_match {
+                                // Other matches - highlighted but less prominent
+                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT)
+                            },
+                            Style::default().fg(CatppuccinFrappe::SURFACE2)
+                        )
+                    } else {
+                        (
+                            if is_current_match {
+                                // Current match - bright yellow and underlined
+                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
+                            } else if is_match {
+                                // Other matches - yellow and bold
+                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default().fg(self.get_due_date_style(todo))
+                            },
+                            Style::default().fg(CatppuccinFrappe::PARENT_INDICATOR)
+                        )
+                    };
+
+                    ListItem::new(self.aligned_row(vec![
+                        Span::styled(&line.prefix, prefix_style),
+                        Span::styled(&line.display_text, display_style),
+                        Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                    ], todo, MetadataView::Tree, area.width))
+                } else {
+                    ListItem::new(Line::from(Span::styled(
+                        format!("{}ERROR: Todo not found", line.prefix),
+                        Style::default().fg(CatppuccinFrappe::ERROR)
+                    )))
+                }
+            })
+            .collect();
+
+        let title = if self.goto_query.is_empty() {
+            format!("{} - Goto Mode", self.view_title(ViewKind::Tree))
+        } else {
+            match self.goto_current_match_index {
+                Some(current_idx) if !self.goto_matches.is_empty() => {
+                    format!("Goto {} - Match {}/{} (n: next, N: prev)",
+                        self.goto_query, current_idx + 1, self.goto_matches.len())
+                }
+                _ => {
+                    format!("Goto {} - {} matches (n: next, N: prev)",
+                        self.goto_query, self.goto_matches.len())
+                }
             }
         };
+
         let list = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
@@ -2393,7 +6621,8 @@ impl App {
             .highlight_style(Style::default()
                 .bg(CatppuccinFrappe::SELECTED_BG)
                 .fg(CatppuccinFrappe::SELECTED))
-            .highlight_symbol("▶ ");
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
 
         // Split area to make room for scrollbar
         let chunks = Layout::default()
@@ -2414,70 +6643,52 @@ impl App {
         f.render_stateful_widget(scrollbar, chunks[1], &mut self.tree_scrollbar_state);
     }
 
-    fn draw_idmod_goto_view(&mut self, f: &mut Frame, area: Rect) {
-        // Split area to make room for goto input at bottom
+    fn draw_tree_search_view(&mut self, f: &mut Frame, area: Rect) {
+        // Split area to make room for search input at bottom
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(0), Constraint::Length(3)])
             .split(area);
 
-        // Draw tree view with goto highlighting in the main area
-        self.draw_tree_view_with_goto_highlights(f, chunks[0]);
+        // Draw tree view with highlighting in the main area
+        self.draw_tree_view_with_highlights(f, chunks[0]);
 
-        // Draw goto input at bottom
-        let goto_input = Paragraph::new(self.goto_query.as_str())
+        // Draw search input at bottom
+        let search_input = Paragraph::new(self.search_query.as_str())
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title("Goto ID (digits only)")
-                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE)))
+                .title("Tree Search")
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(goto_input, chunks[1]);
+        f.render_widget(search_input, chunks[1]);
     }
 
-    fn draw_tree_view_with_goto_highlights(&mut self, f: &mut Frame, area: Rect) {
+    fn draw_tree_view_with_highlights(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
         let rendered_lines = self.tree_manager.get_rendered_lines();
-
+        
         let items: Vec<ListItem> = rendered_lines
             .iter()
             .map(|line| {
                 if let Some(todo) = self.tree_manager.get_todo_by_id(line.todo_id) {
-                    let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                    let due_by_text = if let Some(due_by) = todo.due_by {
-                        format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                    } else {
-                        String::new()
-                    };
 
-                    // Check if this todo matches the goto query
-                    let is_match = self.goto_matches.contains(&line.todo_id);
-                    let is_current_match = self.goto_current_match_index
-                        .and_then(|idx| self.goto_matches.get(idx))
+                    // Check if this todo matches the search
+                    let is_match = self.search_matches.contains(&line.todo_id);
+                    let is_current_match = self.current_match_index
+                        .and_then(|idx| self.search_matches.get(idx))
                         .map(|&match_id| match_id == line.todo_id)
                         .unwrap_or(false);
-
-                    let (display_style, prefix_style) = if todo.hidden && self.show_hidden_items {
-                        // Hidden items shown with italic styling
-                        if todo.is_completed() {
-                            (
-                                Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::ITALIC),
-                                Style::default().fg(CatppuccinFrappe::SURFACE2).add_modifier(Modifier::ITALIC)
-                            )
-                        } else {
-                            (
-                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::ITALIC),
-                                Style::default().fg(CatppuccinFrappe::PARENT_INDICATOR).add_modifier(Modifier::ITALIC)
-                            )
-                        }
-                    } else if todo.is_completed() {
+                    
+                    let (display_style, prefix_style) = if todo.is_completed() {
                         (
                             if is_current_match {
                                 // Current match - bright yellow and underlined
-                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
+                                Style::default().fg(CatppuccinFrappe::RED).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
                             } else if is_match {
                                 // Other matches - highlighted but less prominent
                                 Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD)
                             } else {
-                                Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT)
+                                Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT)
                             },
                             Style::default().fg(CatppuccinFrappe::SURFACE2)
                         )
@@ -2496,12 +6707,11 @@ impl App {
                         )
                     };
 
-                    ListItem::new(Line::from(vec![
+                    ListItem::new(self.aligned_row(vec![
                         Span::styled(&line.prefix, prefix_style),
                         Span::styled(&line.display_text, display_style),
-                        Span::styled(format!(" | Created: {}{}", created_time, due_by_text),
-                                   Style::default().fg(CatppuccinFrappe::CREATION_TIME)),
-                    ]))
+                        Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                    ], todo, MetadataView::Tree, area.width))
                 } else {
                     ListItem::new(Line::from(Span::styled(
                         format!("{}ERROR: Todo not found", line.prefix),
@@ -2511,40 +6721,374 @@ impl App {
             })
             .collect();
 
-        let title = if self.goto_query.is_empty() {
-            "Todo Tree View - Goto Mode".to_string()
-        } else {
-            match self.goto_current_match_index {
-                Some(current_idx) if !self.goto_matches.is_empty() => {
-                    format!("Goto {} - Match {}/{} (n: next, N: prev)",
-                        self.goto_query, current_idx + 1, self.goto_matches.len())
-                }
-                _ => {
-                    format!("Goto {} - {} matches (n: next, N: prev)",
-                        self.goto_query, self.goto_matches.len())
-                }
-            }
-        };
+        let title = if self.search_query.is_empty() {
+            self.view_title(ViewKind::Tree).to_string()
+        } else {
+            match self.current_match_index {
+                Some(current_idx) if !self.search_matches.is_empty() => {
+                    format!("Tree Search - Match {}/{} (n: next, N: prev)", 
+                        current_idx + 1, self.search_matches.len())
+                }
+                _ => {
+                    format!("Tree Search - {} matches (n: next, N: prev)", self.search_matches.len())
+                }
+            }
+        };
+        
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        // Split area to make room for scrollbar
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        f.render_stateful_widget(list, chunks[0], &mut self.tree_list_state);
+
+        // Draw scrollbar
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.tree_scrollbar_state);
+    }
+
+    fn draw_completed_view(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let today = Local::now().date_naive();
+        let days = report::completed_days(&self.completed_todos);
+        let day_header = |day: usize| {
+            let (date, start) = days[day];
+            let count = days.get(day + 1).map_or(self.completed_todos.len(), |(_, next)| *next) - start;
+            Line::from(vec![
+                Span::styled(report::day_label(date, today), Style::default().fg(CatppuccinFrappe::BLUE).add_modifier(Modifier::BOLD)),
+                Span::styled(format!("  {} done", count), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+            ])
+        };
+        // Rows where a day starts, counted with the headers before them
+        let header_rows: Vec<usize> = days.iter().enumerate().map(|(day, (_, start))| start + day).collect();
+        let mut items: Vec<ListItem> = Vec::new();
+        for (i, todo) in self.completed_todos.iter().enumerate() {
+            if let Some(day) = days.iter().position(|(_, start)| *start == i) {
+                items.push(ListItem::new(day_header(day)));
+            }
+            items.push(ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()),
+                               Style::default().fg(self.get_due_date_style(todo))),
+                    Span::styled(
+                        todo.title.clone(),
+                        Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT)
+                    ),
+                    Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                ], todo, MetadataView::Completed, area.width)));
+        }
+
+        let mut title = self.view_title(ViewKind::Completed).to_string();
+        if self.completed_range != CompletedRange::All {
+            title.push_str(&format!(" · {}", self.completed_range.label()));
+        }
+        if let Some((_, project)) = &self.completed_root {
+            title.push_str(&format!(" · {}", project));
+        }
+        let title = format!("{} ({} total)", title, self.completed_total);
+        let highlight_style = Style::default()
+            .bg(CatppuccinFrappe::SELECTED_BG)
+            .fg(CatppuccinFrappe::SELECTED);
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(highlight_style)
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        // Split area to make room for scrollbar
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        let selected = self.completed_list_state.selected().map(|i| i + days.partition_point(|(_, start)| *start <= i));
+        self.completed_rows_state.select(selected);
+        f.render_stateful_widget(list, chunks[0], &mut self.completed_rows_state);
+
+        // Once its header scrolls off, the top day's header stays pinned above its todos
+        let top = self.completed_rows_state.offset();
+        if top < header_rows.len() + self.completed_todos.len() && !header_rows.contains(&top) && chunks[0].height > 2 {
+            let day = header_rows.partition_point(|row| *row <= top) - 1;
+            let pinned = Rect { x: chunks[0].x + 1, y: chunks[0].y + 1, width: chunks[0].width.saturating_sub(2), height: 1 };
+            let mut header = day_header(day);
+            // Line up with the rows below, which leave room for the highlight symbol
+            if selected.is_some() {
+                header.spans.insert(0, Span::raw("  "));
+            }
+            f.render_widget(Clear, pinned);
+            f.render_widget(Paragraph::new(header), pinned);
+        }
+
+        // Draw scrollbar
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.completed_scrollbar_state);
+    }
+
+
+
+    fn draw_waiting_view(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let now = Utc::now();
+        let items: Vec<ListItem> = self
+            .waiting_todos
+            .iter()
+            .map(|todo| {
+                let waiting = self.waiting.get(&todo.id);
+                let who = waiting.map_or(String::new(), |waiting| format!("  → {}", waiting.on));
+                let (follow_up, color) = match waiting.and_then(|waiting| waiting.follow_up) {
+                    Some(at) if at <= now => (format!("  follow up {} (due)", dates::format_start_date(at)), CatppuccinFrappe::RED),
+                    Some(at) => (format!("  follow up {}", dates::format_start_date(at)), CatppuccinFrappe::SUBTEXT0),
+                    None => (String::new(), CatppuccinFrappe::SUBTEXT0),
+                };
+                ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()),
+                               Style::default().fg(CatppuccinFrappe::INCOMPLETE)),
+                    Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
+                    Span::styled(who, Style::default().fg(CatppuccinFrappe::TEAL)),
+                    Span::styled(follow_up, Style::default().fg(color)),
+                ], todo, MetadataView::Waiting, area.width))
+            })
+            .collect();
+
+        let title = format!("{} ({} delegated) - >=Edit, <=Take back", self.view_title(ViewKind::Waiting), self.waiting_todos.len());
+        let highlight_style = Style::default()
+            .bg(CatppuccinFrappe::SELECTED_BG)
+            .fg(CatppuccinFrappe::SELECTED);
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(highlight_style)
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        f.render_stateful_widget(list, chunks[0], &mut self.waiting_list_state);
+
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.waiting_scrollbar_state);
+    }
+
+    fn draw_today_view(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let items: Vec<ListItem> = self
+            .today_todos
+            .iter()
+            .map(|todo| {
+                let title_style = if todo.is_completed() {
+                    Style::default().fg(self.get_due_date_style(todo)).add_modifier(Modifier::CROSSED_OUT)
+                } else {
+                    Style::default().fg(self.get_due_date_style(todo))
+                };
+                ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()),
+                               Style::default().fg(self.tree_manager.status_of(todo).color())),
+                    Span::styled(todo.title.clone(), title_style),
+                    Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                ], todo, MetadataView::Today, area.width))
+            })
+            .collect();
+
+        let done = self.today_todos.iter().filter(|todo| todo.is_completed()).count();
+        let title = format!(
+            "{} · {} ({} of {} done) - +=Take out, Space=Done",
+            self.view_title(ViewKind::Today),
+            Local::now().format("%a %Y-%m-%d"),
+            done,
+            self.today_todos.len()
+        );
+        let highlight_style = Style::default()
+            .bg(CatppuccinFrappe::SELECTED_BG)
+            .fg(CatppuccinFrappe::SELECTED);
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(highlight_style)
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        if self.today_todos.is_empty() && self.carry_over.is_none() {
+            let empty = Paragraph::new("Nothing planned yet. Press + on a todo in the tree or list to add it to today's plan.")
+                .block(Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.view_title(ViewKind::Today))
+                    .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+                .style(Style::default().fg(CatppuccinFrappe::SUBTEXT0))
+                .wrap(Wrap { trim: true });
+            f.render_widget(empty, chunks[0]);
+        } else {
+            f.render_stateful_widget(list, chunks[0], &mut self.today_list_state);
+        }
+
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.today_scrollbar_state);
+
+        if let Some((day, todos)) = &self.carry_over {
+            self.draw_carry_over(f, area, *day, todos);
+        }
+    }
+
+    /// Offer to copy what an earlier plan left unfinished into today's
+    fn draw_carry_over(&self, f: &mut Frame, area: Rect, day: NaiveDate, todos: &[Todo]) {
+        let popup_area = centered_rect(60, 40, area);
+        f.render_widget(Clear, popup_area);
+        let today = Local::now().date_naive();
+        let mut lines = vec![
+            Line::from(format!("{} unfinished from {}:", todos.len(), report::day_label(day, today))),
+            Line::from(""),
+        ];
+        for todo in todos {
+            lines.push(Line::from(Span::styled(format!("  {}", todo.title), Style::default().fg(self.get_due_date_style(todo)))));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("Press 'y' to carry them over to today, 'n' to start fresh"));
+        let block = Block::default()
+            .title("Carry Over")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+            .style(Style::default().bg(CatppuccinFrappe::BASE));
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .wrap(Wrap { trim: false });
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn draw_someday_view(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let items: Vec<ListItem> = self
+            .someday_todos
+            .iter()
+            .map(|todo| {
+                ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()),
+                               Style::default().fg(CatppuccinFrappe::INCOMPLETE)),
+                    Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
+                    Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                ], todo, MetadataView::Someday, area.width))
+            })
+            .collect();
+
+        let title = format!("{} ({} set aside) - p=Promote", self.view_title(ViewKind::Someday), self.someday_todos.len());
+        let highlight_style = Style::default()
+            .bg(CatppuccinFrappe::SELECTED_BG)
+            .fg(CatppuccinFrappe::SELECTED);
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+            .highlight_style(highlight_style)
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        f.render_stateful_widget(list, chunks[0], &mut self.someday_list_state);
+
+        let scrollbar = Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"))
+            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.someday_scrollbar_state);
+    }
+
+    fn draw_scheduled_view(&mut self, f: &mut Frame, area: Rect) {
+        self.viewport_rows = visible_rows(area);
+        let items: Vec<ListItem> = self
+            .scheduled_todos
+            .iter()
+            .map(|todo| {
+                ListItem::new(self.aligned_row(vec![
+                    Span::styled(format!("{} {} ", self.tree_manager.id_label(todo), self.tree_manager.status_of(todo).icon()),
+                               Style::default().fg(CatppuccinFrappe::INCOMPLETE)),
+                    Span::styled(todo.title.clone(), Style::default().fg(self.get_due_date_style(todo))),
+                    Span::styled(self.row_indicators(todo.id), Style::default().fg(CatppuccinFrappe::PEACH)),
+                ], todo, MetadataView::Scheduled, area.width))
+            })
+            .collect();
+
+        let title = format!("{} ({} deferred) - D=Defer, s=Start now", self.view_title(ViewKind::Scheduled), self.scheduled_todos.len());
+        let highlight_style = Style::default()
+            .bg(CatppuccinFrappe::SELECTED_BG)
+            .fg(CatppuccinFrappe::SELECTED);
 
         let list = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
                 .title(title)
                 .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
-            .highlight_style(Style::default()
-                .bg(CatppuccinFrappe::SELECTED_BG)
-                .fg(CatppuccinFrappe::SELECTED))
-            .highlight_symbol("▶ ");
+            .highlight_style(highlight_style)
+            .highlight_symbol("▶ ")
+            .scroll_padding(SCROLL_MARGIN);
 
-        // Split area to make room for scrollbar
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Min(0), Constraint::Length(1)])
             .split(area);
 
-        f.render_stateful_widget(list, chunks[0], &mut self.tree_list_state);
+        f.render_stateful_widget(list, chunks[0], &mut self.scheduled_list_state);
 
-        // Draw scrollbar
         let scrollbar = Scrollbar::default()
             .orientation(ScrollbarOrientation::VerticalRight)
             .begin_symbol(Some("↑"))
@@ -2552,501 +7096,1272 @@ impl App {
             .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
             .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
 
-        f.render_stateful_widget(scrollbar, chunks[1], &mut self.tree_scrollbar_state);
+        f.render_stateful_widget(scrollbar, chunks[1], &mut self.scheduled_scrollbar_state);
     }
 
-    fn draw_tree_search_view(&mut self, f: &mut Frame, area: Rect) {
-        // Split area to make room for search input at bottom
+    fn draw_create_mode(&self, f: &mut Frame, area: Rect) {
+        // Narrow terminals stack the two due date fields instead of squeezing them side by side
+        let stacked = area.width < NARROW_WIDTH;
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(if stacked { 6 } else { 3 }),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
             .split(area);
 
-        // Draw tree view with highlighting in the main area
-        self.draw_tree_view_with_highlights(f, chunks[0]);
+        // Title field
+        let title_style = if self.create_field_focus == CreateFieldFocus::Title {
+            Style::default().fg(CatppuccinFrappe::YELLOW)
+        } else {
+            Style::default().fg(CatppuccinFrappe::BORDER)
+        };
+        let title_display = if self.input_title.is_empty() {
+            "e.g., 'p0 Fix critical bug' (p0=highest priority)".to_string()
+        } else {
+            self.input_title.clone()
+        };
+        let title_input = Paragraph::new(title_display.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Title").border_style(title_style))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(title_input, chunks[0]);
 
-        // Draw search input at bottom
-        let search_input = Paragraph::new(self.search_query.as_str())
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title("Tree Search")
-                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW)))
+        // Due Date fields - two side-by-side boxes, or one above the other when narrow
+        let date_chunks = Layout::default()
+            .direction(if stacked { Direction::Vertical } else { Direction::Horizontal })
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+
+        // Relative date field (left)
+        let relative_style = if self.create_field_focus == CreateFieldFocus::DueDateRelative {
+            Style::default().fg(CatppuccinFrappe::YELLOW)
+        } else {
+            Style::default().fg(CatppuccinFrappe::BORDER)
+        };
+        let relative_display = if self.input_due_date_relative.is_empty() {
+            "e.g., '2' (2 days), '1w', '3h'".to_string()
+        } else {
+            self.input_due_date_relative.clone()
+        };
+        let relative_input = Paragraph::new(relative_display.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Relative (optional)").border_style(relative_style))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(search_input, chunks[1]);
+        f.render_widget(relative_input, date_chunks[0]);
+
+        // Absolute date field (right)
+        let absolute_style = if self.create_field_focus == CreateFieldFocus::DueDateAbsolute {
+            Style::default().fg(CatppuccinFrappe::YELLOW)
+        } else {
+            Style::default().fg(CatppuccinFrappe::BORDER)
+        };
+        let absolute_display = if self.input_due_date_absolute.is_empty() {
+            "e.g., '2025-10-20 14:30'".to_string()
+        } else {
+            self.input_due_date_absolute.clone()
+        };
+        let absolute_input = Paragraph::new(absolute_display.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Absolute (optional)").border_style(absolute_style))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(absolute_input, date_chunks[1]);
+
+        // Parent field  
+        let parent_style = if self.create_field_focus == CreateFieldFocus::Parent {
+            Style::default().fg(CatppuccinFrappe::YELLOW)
+        } else {
+            Style::default().fg(CatppuccinFrappe::BORDER)
+        };
+        let parent_display = if self.input_parent.is_empty() {
+            "Press Tab to focus, type to search for parent, 'r' to clear...".to_string()
+        } else {
+            self.input_parent.clone()
+        };
+        let parent_input = Paragraph::new(parent_display.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Parent (optional)").border_style(parent_style))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(parent_input, chunks[2]);
+
+        // Description field
+        let desc_style = if self.create_field_focus == CreateFieldFocus::Description {
+            Style::default().fg(CatppuccinFrappe::YELLOW)
+        } else {
+            Style::default().fg(CatppuccinFrappe::BORDER)
+        };
+        // Scrolled to keep the cursor's line in the box
+        let cursor_line = self.input_description[..self.create_cursor.min(self.input_description.len())].matches('\n').count() as u16;
+        let description_scroll = if self.create_field_focus == CreateFieldFocus::Description {
+            cursor_line.saturating_sub(chunks[3].height.saturating_sub(3))
+        } else {
+            0
+        };
+        let description_input = Paragraph::new(self.input_description.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Description (optional) - Enter=New Line, Ctrl+S=Save").border_style(desc_style))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .scroll((description_scroll, 0));
+        f.render_widget(description_input, chunks[3]);
+
+        // The cursor, in the focused field
+        let field = match self.create_field_focus {
+            CreateFieldFocus::Title => Some((chunks[0], &self.input_title)),
+            CreateFieldFocus::DueDateRelative => Some((date_chunks[0], &self.input_due_date_relative)),
+            CreateFieldFocus::DueDateAbsolute => Some((date_chunks[1], &self.input_due_date_absolute)),
+            CreateFieldFocus::Description => Some((chunks[3], &self.input_description)),
+            CreateFieldFocus::Parent => None,
+        };
+        if let Some((field_area, text)) = field
+            && field_area.width > 2
+            && field_area.height > 2 {
+            let cursor = self.create_cursor.min(text.len());
+            let (line_start, _) = text::line_bounds(text, cursor);
+            let column = text[line_start..cursor].width() as u16;
+            let line = text[..cursor].matches('\n').count() as u16 - if self.create_field_focus == CreateFieldFocus::Description { description_scroll } else { 0 };
+            f.set_cursor_position((
+                field_area.x + 1 + column.min(field_area.width - 3),
+                field_area.y + 1 + line.min(field_area.height - 3),
+            ));
+        }
+
+        // Tag completions drop down from the title over the fields below it
+        let below_title = chunks[0].y + chunks[0].height;
+        self.draw_tag_completions(f, Rect {
+            x: chunks[0].x + 2,
+            y: below_title,
+            width: chunks[0].width.saturating_sub(2),
+            height: (self.tag_completions.len() as u16 + 2).min((area.y + area.height).saturating_sub(below_title)),
+        });
     }
 
-    fn draw_tree_view_with_highlights(&mut self, f: &mut Frame, area: Rect) {
-        let rendered_lines = self.tree_manager.get_rendered_lines();
+    fn draw_confirm_delete(&self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(50, 20, area);
+        f.render_widget(Clear, popup_area);
         
-        let items: Vec<ListItem> = rendered_lines
+        let block = Block::default()
+            .title("Confirm Delete")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(CatppuccinFrappe::RED))
+            .style(Style::default().bg(CatppuccinFrappe::BASE));
+        
+        let paragraph = Paragraph::new("Are you sure you want to delete this todo?\n\nPress 'y' to confirm, 'n' to cancel")
+            .block(block)
+            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .wrap(Wrap { trim: true });
+        
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn draw_confirm_cascade(&self, f: &mut Frame, area: Rect) {
+        let Some(pending) = self.cascade_pending else {
+            return;
+        };
+        let popup_area = centered_rect(50, 20, area);
+        f.render_widget(Clear, popup_area);
+
+        let (title, question) = if !pending.single {
+            ("Open Subtasks", format!("This todo still has {} open subtask(s). Complete them all?", pending.count))
+        } else if pending.complete {
+            ("Complete Subtasks", format!("Also complete its {} open subtask(s)?", pending.count))
+        } else {
+            ("Reopen Parents", format!("Also reopen its {} completed parent(s), so it shows in the tree?", pending.count))
+        };
+        let keys = if pending.single {
+            "Press 'y' for all of them, 'n' for just this todo, Esc to cancel"
+        } else {
+            "Press 'y' to complete them all, Esc to leave it open"
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+            .style(Style::default().bg(CatppuccinFrappe::BASE));
+
+        let paragraph = Paragraph::new(format!("{}\n\n{}", question, keys))
+            .block(block)
+            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn draw_copy_menu(&self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(50, 20, area);
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Copy to Clipboard")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(CatppuccinFrappe::TEAL))
+            .style(Style::default().bg(CatppuccinFrappe::BASE));
+
+        let paragraph = Paragraph::new("t  Title\nm  Markdown (as opened in the editor)\ns  Subtree as a markdown task list\n\nEsc to cancel")
+            .block(block)
+            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn draw_context_picker(&self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(50, 50, area);
+        f.render_widget(Clear, popup_area);
+
+        let choice = |key: String, label: String, chosen: bool| {
+            Line::from(vec![
+                Span::styled(if chosen { "● " } else { "  " }, Style::default().fg(CatppuccinFrappe::GREEN)),
+                Span::styled(format!("{}  ", key), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+                Span::styled(label, Style::default().fg(CatppuccinFrappe::TEXT)),
+            ])
+        };
+        let mut lines: Vec<Line> = self.config.contexts.names.iter().take(9).enumerate()
+            .map(|(i, context)| choice((i + 1).to_string(), format!("@{}", context), self.now_context.as_ref() == Some(context)))
+            .collect();
+        lines.push(Line::from(""));
+        for energy in [Energy::Low, Energy::Medium, Energy::High] {
+            let key = energy.as_str()[..1].to_string();
+            lines.push(choice(key, format!("{} energy", energy.as_str()), self.now_energy == Some(energy)));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("What can I do now?")
+                .title_bottom("1-9=Context, l/m/h=Energy, x=Clear, Esc=Close")
+                .border_style(Style::default().fg(CatppuccinFrappe::TEAL))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)));
+        f.render_widget(picker, popup_area);
+    }
+
+    fn draw_profile_picker(&mut self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(60, 40, area);
+        f.render_widget(Clear, popup_area);
+
+        let items: Vec<ListItem> = self
+            .config
+            .profiles
             .iter()
-            .map(|line| {
-                if let Some(todo) = self.tree_manager.get_todo_by_id(line.todo_id) {
-                    let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                    let due_by_text = if let Some(due_by) = todo.due_by {
-                        format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                    } else {
-                        String::new()
-                    };
+            .map(|profile| {
+                let marker = if Some(&profile.name) == self.current_profile.as_ref() { "● " } else { "  " };
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::default().fg(CatppuccinFrappe::GREEN)),
+                    Span::styled(profile.name.clone(), Style::default().fg(CatppuccinFrappe::TEXT).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("  {}", profile.path), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+                ]))
+            })
+            .collect();
 
-                    // Check if this todo matches the search
-                    let is_match = self.search_matches.contains(&line.todo_id);
-                    let is_current_match = self.current_match_index
-                        .and_then(|idx| self.search_matches.get(idx))
-                        .map(|&match_id| match_id == line.todo_id)
-                        .unwrap_or(false);
-                    
-                    let (display_style, prefix_style) = if todo.is_completed() {
-                        (
-                            if is_current_match {
-                                // Current match - bright yellow and underlined
-                                Style::default().fg(CatppuccinFrappe::RED).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
-                            } else if is_match {
-                                // Other matches - highlighted but less prominent
-                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::CROSSED_OUT).add_modifier(Modifier::BOLD)
-                            } else {
-                                Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT)
-                            },
-                            Style::default().fg(CatppuccinFrappe::SURFACE2)
-                        )
-                    } else {
-                        (
-                            if is_current_match {
-                                // Current match - bright yellow and underlined
-                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::BOLD).add_modifier(Modifier::UNDERLINED)
-                            } else if is_match {
-                                // Other matches - yellow and bold
-                                Style::default().fg(CatppuccinFrappe::YELLOW).add_modifier(Modifier::BOLD)
-                            } else {
-                                Style::default().fg(self.get_due_date_style(todo))
-                            },
-                            Style::default().fg(CatppuccinFrappe::PARENT_INDICATOR)
-                        )
-                    };
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Profiles")
+                .title_bottom("Enter=Switch, Esc=Close")
+                .border_style(Style::default().fg(CatppuccinFrappe::MAUVE))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, popup_area, &mut self.profile_list_state);
+    }
+
+    fn draw_list_find_mode(&mut self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        // Search input box
+        let search_input = Paragraph::new(self.search_query.as_str())
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Search (regex supported)")
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE)))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(search_input, chunks[0]);
 
-                    ListItem::new(Line::from(vec![
-                        Span::styled(&line.prefix, prefix_style),
-                        Span::styled(&line.display_text, display_style),
-                        Span::styled(format!(" | Created: {}{}", created_time, due_by_text),
-                                   Style::default().fg(CatppuccinFrappe::CREATION_TIME)),
-                    ]))
+        // Search results
+        let items: Vec<ListItem> = self
+            .search_results
+            .iter()
+            .map(|todo| {
+
+                let status_icon = self.tree_manager.status_of(todo).icon();
+                let title_style = if todo.is_completed() {
+                    Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT)
                 } else {
-                    ListItem::new(Line::from(Span::styled(
-                        format!("{}ERROR: Todo not found", line.prefix),
-                        Style::default().fg(CatppuccinFrappe::ERROR)
-                    )))
-                }
+                    Style::default()
+                };
+
+                ListItem::new(self.aligned_row(vec![
+                    Span::raw(format!("{} {} ", self.tree_manager.id_label(todo), status_icon)),
+                    Span::styled(todo.title.clone(), title_style),
+                ], todo, MetadataView::Find, area.width))
             })
             .collect();
 
-        let title = if self.search_query.is_empty() {
-            "Todo Tree View".to_string()
-        } else {
-            match self.current_match_index {
-                Some(current_idx) if !self.search_matches.is_empty() => {
-                    format!("Tree Search - Match {}/{} (n: next, N: prev)", 
-                        current_idx + 1, self.search_matches.len())
-                }
-                _ => {
-                    format!("Tree Search - {} matches (n: next, N: prev)", self.search_matches.len())
-                }
-            }
-        };
-        
+        let results_title = format!("Search Results ({} found)", self.search_results.len());
         let list = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title(title)
+                .title(results_title)
                 .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
             .highlight_style(Style::default()
                 .bg(CatppuccinFrappe::SELECTED_BG)
                 .fg(CatppuccinFrappe::SELECTED))
             .highlight_symbol("▶ ");
 
-        // Split area to make room for scrollbar
+        f.render_stateful_widget(list, chunks[1], &mut self.search_list_state);
+    }
+
+    fn draw_parent_search_mode(&mut self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
             .split(area);
 
-        f.render_stateful_widget(list, chunks[0], &mut self.tree_list_state);
-
-        // Draw scrollbar
-        let scrollbar = Scrollbar::default()
-            .orientation(ScrollbarOrientation::VerticalRight)
-            .begin_symbol(Some("↑"))
-            .end_symbol(Some("↓"))
-            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
-            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
-
-        f.render_stateful_widget(scrollbar, chunks[1], &mut self.tree_scrollbar_state);
-    }
+        // Search input box
+        let search_input = Paragraph::new(self.search_query.as_str())
+            .block(Block::default().borders(Borders::ALL).title("Search for Parent Todo (regex supported)"));
+        f.render_widget(search_input, chunks[0]);
 
-    fn draw_completed_view(&mut self, f: &mut Frame, area: Rect) {
+        // Search results - same as regular search but with different title
         let items: Vec<ListItem> = self
-            .completed_todos
+            .search_results
             .iter()
             .map(|todo| {
-                let completed_time = if let Some(completed_at) = todo.completed_at {
-                    completed_at.with_timezone(&Local).format("%m/%d %H:%M").to_string()
+
+                let status_icon = self.tree_manager.status_of(todo).icon();
+                let title_style = if todo.is_completed() {
+                    Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT)
                 } else {
-                    "Unknown".to_string()
+                    Style::default()
                 };
-                let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                let due_by_text = if let Some(due_by) = todo.due_by {
-                    format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
+
+                ListItem::new(self.aligned_row(vec![
+                    Span::raw(format!("{} {} ", self.tree_manager.id_label(todo), status_icon)),
+                    Span::styled(todo.title.clone(), title_style),
+                ], todo, MetadataView::Find, area.width))
+            })
+            .collect();
+
+        let results_title = format!("Select Parent ({} found)", self.search_results.len());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(results_title))
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .highlight_symbol(">> ");
+
+        f.render_stateful_widget(list, chunks[1], &mut self.search_list_state);
+    }
+
+    /// `HELP_LINES` as this config has them: the statuses, contexts and pomodoro lengths
+    /// filled in, and any `[keys]` aliases listed with the keys they stand for
+    fn help_lines(&self) -> Vec<String> {
+        let statuses: Vec<&str> = self.config.statuses.cycle.iter().map(|status| status.as_str()).collect();
+        let contexts: Vec<String> = self.config.contexts.names.iter().map(|name| format!("@{}", name)).collect();
+        let pomodoro = format!("{} min work, {} min break", self.config.pomodoro.work_minutes, self.config.pomodoro.break_minutes);
+        HELP_LINES
+            .iter()
+            .map(|line| {
+                let line = line.replace("{statuses}", &statuses.join("/")).replace("{contexts}", &contexts.join(", ")).replace("{pomodoro}", &pomodoro);
+                let Some((keys, action)) = line.strip_prefix("  ").and_then(|entry| entry.split_once("  ")) else {
+                    return line;
                 };
-                let parent_title = self.database.get_parent_title(todo.parent_id)
-                    .unwrap_or(None)
-                    .unwrap_or_else(|| "null".to_string());
+                let shown = self.keys_with_aliases(keys);
+                if shown == keys {
+                    return line;
+                }
+                format!("  {:<14}  {}", shown, action.trim_start())
+            })
+            .collect()
+    }
+
+    fn draw_help_page(&mut self, f: &mut Frame, area: Rect) {
+        // Create a centered popup
+        let popup_area = centered_rect(80, 70, area);
+        
+        // Clear the background
+        f.render_widget(Clear, popup_area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("TodoDB Help")
+            .title_bottom("j/k=Scroll, PgUp/PgDn=Page, /=Search, a/Esc=Close")
+            .border_style(Style::default().fg(CatppuccinFrappe::BLUE))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        let inner = block.inner(popup_area);
+        f.render_widget(block, popup_area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(inner);
+        
+        let mut header = Line::from(Span::styled(
+            "Press / to search, a, Esc, or q to close this help",
+            Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+        ));
+        if self.help_search_input || !self.help_query.is_empty() {
+            let hint = if self.help_search_input { "▌  Enter=Keep, Esc=Clear" } else { "" };
+            header = Line::from(vec![
+                Span::styled("Search: ", Style::default().fg(CatppuccinFrappe::SAPPHIRE).add_modifier(Modifier::BOLD)),
+                Span::styled(self.help_query.as_str(), Style::default().fg(CatppuccinFrappe::TEXT)),
+                Span::styled(hint, Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+            ]);
+        }
+        f.render_widget(Paragraph::new(header), chunks[0]);
+
+        let help_lines = self.help_lines();
+        let help_lines: Vec<&str> = help_lines.iter().map(String::as_str).collect();
+        let matching = filter_help_lines(&help_lines, &self.help_query);
+        // Leave a column for the scrollbar
+        let width = (chunks[1].width as usize).saturating_sub(1);
+        let mut lines = Vec::new();
+        if matching.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!("No keybindings or commands match '{}'", self.help_query),
+                Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+            )));
+        }
+        for line in matching {
+            // Section headings stand out; entries have their keys picked out
+            if !line.is_empty() && !line.starts_with(' ') {
+                lines.push(Line::from(Span::styled(line.to_string(), Style::default().fg(CatppuccinFrappe::MAUVE).add_modifier(Modifier::BOLD))));
+                continue;
+            }
+            for (i, part) in wrap_help_line(line, width).into_iter().enumerate() {
+                match part.strip_prefix("  ").and_then(|entry| entry.split_once("  ")).filter(|_| i == 0) {
+                    Some((keys, action)) => lines.push(Line::from(vec![
+                        Span::styled(format!("  {}", keys), Style::default().fg(CatppuccinFrappe::YELLOW)),
+                        Span::raw(format!("  {}", action)),
+                    ])),
+                    None => lines.push(Line::from(part)),
+                }
+            }
+        }
+
+        self.help_height = chunks[1].height as usize;
+        let max_scroll = lines.len().saturating_sub(self.help_height);
+        self.help_scroll = self.help_scroll.min(max_scroll);
+        let body = Paragraph::new(lines).scroll((self.help_scroll.min(u16::MAX as usize) as u16, 0));
+        f.render_widget(body, chunks[1]);
+        if max_scroll > 0 {
+            let scrollbar = Scrollbar::default()
+                .orientation(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓"))
+                .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
+                .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+            let mut scrollbar_state = ScrollbarState::new(max_scroll).position(self.help_scroll);
+            f.render_stateful_widget(scrollbar, chunks[1], &mut scrollbar_state);
+        }
+    }
+
+    /// Breadcrumb of the hoisted todo, e.g. " [Website › Backend, Esc=Unhoist]"
+    fn hoist_title_suffix(&self) -> String {
+        let path = self.path_titles(self.tree_manager.root);
+        if path.is_empty() {
+            return String::new();
+        }
+        format!(" [{}, Esc=Unhoist]", path.join(" › "))
+    }
+
+    /// Titles from the root project down to `todo_id` itself, as far as the tree knows them
+    fn path_titles(&self, todo_id: Option<i64>) -> Vec<String> {
+        let mut path = Vec::new();
+        let mut current = todo_id;
+        while let Some(id) = current
+            && path.len() <= self.tree_manager.todos.len() {
+            let Some(todo) = self.tree_manager.get_todo_by_id(id) else { break };
+            path.push(todo.title.clone());
+            current = todo.parent_id;
+        }
+        path.reverse();
+        path
+    }
+
+    /// The footer breadcrumb: the selected todo's ancestors (dropping the topmost ones
+    /// if they don't fit in `width`), then the todo itself
+    fn breadcrumb(&self, width: usize) -> Option<Line<'static>> {
+        let todo = self.get_selected_todo()?;
+        let mut ancestors = self.path_titles(todo.parent_id);
+        // Rows show how far off the due date is; the exact date is here
+        let due = todo
+            .due_by
+            .filter(|_| self.config.display.relative_due)
+            .map(|due| format!("  due {}", due.with_timezone(&Local).format(&format!("%a %Y-%m-%d {}", self.config.dates.clock.format()))));
+        if ancestors.is_empty() && due.is_none() {
+            return None;
+        }
+        let due_width = due.as_ref().map_or(0, |due| due.width());
+        let fits = |ancestors: &[String]| ancestors.iter().map(|title| title.width() + 3).sum::<usize>() + todo.title.width() + due_width <= width;
+        let mut elided = false;
+        while !ancestors.is_empty() && !fits(&ancestors) {
+            ancestors.remove(0);
+            elided = true;
+        }
+        let mut trail = if elided { "… › ".to_string() } else { String::new() };
+        for title in ancestors {
+            trail.push_str(&title);
+            trail.push_str(" › ");
+        }
+        let mut spans = vec![
+            Span::styled(trail, Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+            Span::styled(todo.title.clone(), Style::default().fg(CatppuccinFrappe::TEXT).add_modifier(Modifier::BOLD)),
+        ];
+        if let Some(due) = due {
+            spans.push(Span::styled(due, Style::default().fg(self.get_due_date_style(todo))));
+        }
+        Some(Line::from(spans))
+    }
 
+    fn active_filter_title_suffix(&self) -> String {
+        match &self.active_filter {
+            Some((name, _)) => format!(" [Filter: {}]", name),
+            None => String::new(),
+        }
+    }
+
+    fn draw_onboarding(&mut self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(80, 60, area);
+        f.render_widget(Clear, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(0)])
+            .split(popup_area);
+
+        let intro = Paragraph::new(vec![
+            Line::from(Span::styled("Welcome to TodoDB!", Style::default().fg(CatppuccinFrappe::MAUVE).add_modifier(Modifier::BOLD))),
+            Line::from(Span::styled(
+                "Found todos from other tools. Import them now? (tododb import can do this later too)",
+                Style::default().fg(CatppuccinFrappe::TEXT),
+            )),
+        ])
+        .block(Block::default()
+            .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+            .title("Import")
+            .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE))
+            .style(Style::default().bg(CatppuccinFrappe::BASE)))
+        .wrap(Wrap { trim: true });
+        f.render_widget(intro, chunks[0]);
+
+        let items: Vec<ListItem> = self
+            .import_sources
+            .iter()
+            .map(|(source, checked)| {
+                let checkbox = if *checked { "[x] " } else { "[ ] " };
                 ListItem::new(Line::from(vec![
-                    Span::styled(format!("{} [✓] ", todo.id_mod()),
-                               Style::default().fg(CatppuccinFrappe::COMPLETED)),
-                    Span::styled(
-                        todo.title.clone(),
-                        Style::default().fg(CatppuccinFrappe::COMPLETED).add_modifier(Modifier::CROSSED_OUT)
-                    ),
-                    Span::styled(
-                        format!(" | Created: {} | Completed: {}{} | Parent: {}",
-                               created_time, completed_time, due_by_text, parent_title),
-                        Style::default().fg(CatppuccinFrappe::SUBTEXT0)
-                    ),
+                    Span::styled(checkbox, Style::default().fg(CatppuccinFrappe::GREEN)),
+                    Span::styled(format!("{:<28}", source.kind.label()), Style::default().fg(CatppuccinFrappe::TEXT)),
+                    Span::styled(source.path.display().to_string(), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
                 ]))
             })
             .collect();
 
-        let title = format!("All Completed Todos ({} total)", self.completed_todos.len());
-        let highlight_style = Style::default()
-            .bg(CatppuccinFrappe::SELECTED_BG)
-            .fg(CatppuccinFrappe::SELECTED);
-
         let list = List::new(items)
             .block(Block::default()
-                .borders(Borders::ALL)
-                .title(title)
-                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
-            .highlight_style(highlight_style)
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .title_bottom("Space=Toggle, Enter=Import checked, s/Esc=Skip")
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
             .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, chunks[1], &mut self.import_list_state);
+    }
 
-        // Split area to make room for scrollbar
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(0), Constraint::Length(1)])
-            .split(area);
+    /// A todo row: the title spans are padded or truncated to whatever width the
+    /// fixed metadata columns leave, so Created / Due / ... line up down the list
+    fn aligned_row<'a>(&self, title_spans: Vec<Span<'a>>, todo: &Todo, view: MetadataView, area_width: u16) -> Text<'a> {
+        let mut columns = self.metadata_density.get(&view).copied().unwrap_or_default().apply(self.configured_columns(view));
+        let compact = area_width < NARROW_WIDTH;
+        if compact {
+            columns.parent = false;
+        }
+        let date_format = self.config.dates.row_format(compact);
+        let mut date_width = dates::format_width(&date_format);
+        if self.config.display.relative_due {
+            date_width = date_width.max(if compact { COMPACT_RELATIVE_DUE_WIDTH } else { RELATIVE_DUE_WIDTH });
+        }
+        let format_date = |date: Option<DateTime<Utc>>| {
+            date.map_or_else(|| "-".to_string(), |date| date.with_timezone(&Local).format(&date_format).to_string())
+        };
+        let now = Utc::now();
+        let format_due = |due: Option<DateTime<Utc>>| match due {
+            Some(due) if self.config.display.relative_due => dates::format_relative_due(due, now, compact),
+            _ => format_date(due),
+        };
+        let date_style = Style::default().fg(CatppuccinFrappe::CREATION_TIME);
+        let due_style = if todo.due_by.is_some() { Style::default().fg(self.get_due_date_style(todo)) } else { date_style };
 
-        f.render_stateful_widget(list, chunks[0], &mut self.completed_list_state);
+        let mut cells = Vec::new();
+        if columns.priority {
+            let priority = TodoTreeManager::parse_priority(&todo.title).map_or_else(|| "-".to_string(), |priority| format!("P{}", priority));
+            cells.push(("Pri", priority, PRIORITY_COLUMN_WIDTH, Style::default().fg(CatppuccinFrappe::PEACH)));
+        }
+        if columns.tags {
+            let mut tags: Vec<&str> = tags::extract_tags(&todo.title);
+            for tag in tags::extract_tags(&todo.description) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            let tags = if tags.is_empty() { "-".to_string() } else { tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ") };
+            cells.push(("Tags", tags, self.config.columns.tags_width, Style::default().fg(CatppuccinFrappe::SAPPHIRE)));
+        }
+        if columns.start {
+            cells.push(("Starts", format_date(todo.start_at), date_width, Style::default().fg(CatppuccinFrappe::LAVENDER)));
+        }
+        if columns.created {
+            cells.push(("Created", format_date(Some(todo.created_at)), date_width, date_style));
+        }
+        if columns.due {
+            cells.push(("Due", format_due(todo.due_by), date_width, due_style));
+        }
+        if columns.completed {
+            cells.push(("Done", format_date(todo.completed_at), date_width, date_style));
+        }
+        if columns.parent {
+            let parent_title = self.database.get_parent_title(todo.parent_id)
+                .unwrap_or(None)
+                .unwrap_or_else(|| "-".to_string());
+            cells.push(("Parent", parent_title, self.config.columns.parent_width, date_style));
+        }
 
-        // Draw scrollbar
-        let scrollbar = Scrollbar::default()
-            .orientation(ScrollbarOrientation::VerticalRight)
-            .begin_symbol(Some("↑"))
-            .end_symbol(Some("↓"))
-            .style(Style::default().fg(CatppuccinFrappe::SURFACE2))
-            .thumb_style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
+        // Borders, highlight symbol and scrollbar take 5 columns; columns that would
+        // leave the title less than its minimum are dropped from the right
+        let row_width = (area_width as usize).saturating_sub(5);
+        let cell_width = |(label, _, width, _): &(&str, String, usize, Style)| label.len() + width + 4;
+        while !cells.is_empty() && cells.iter().map(cell_width).sum::<usize>() + MIN_TITLE_WIDTH > row_width {
+            cells.pop();
+        }
+        let metadata_width: usize = cells.iter().map(cell_width).sum();
+        let title_width = row_width.saturating_sub(metadata_width).max(MIN_TITLE_WIDTH);
+
+        let title_total: usize = title_spans.iter().map(Span::width).sum();
+        let (title_spans, continuation) = if self.config.display.wrap_titles && title_total > title_width {
+            // The second line starts under the title text, past the indentation and markers
+            let indent = title_spans.first().map_or(0, |span| span.width()).min(title_width / 2);
+            let (head, tail) = split_spans(title_spans, title_width);
+            let mut second = vec![Span::raw(" ".repeat(indent))];
+            second.extend(fit_spans(tail, title_width - indent));
+            (head, Some(Line::from(second)))
+        } else {
+            (title_spans, None)
+        };
 
-        f.render_stateful_widget(scrollbar, chunks[1], &mut self.completed_scrollbar_state);
+        let mut spans = fit_spans(title_spans, title_width);
+        for (label, value, width, style) in cells {
+            spans.push(Span::styled(format!(" │ {} ", label), Style::default().fg(CatppuccinFrappe::SURFACE2)));
+            let value = text::truncate_to_width(&value, width);
+            let padding = width.saturating_sub(value.width());
+            spans.push(Span::styled(format!("{}{}", value, " ".repeat(padding)), style));
+        }
+        let mut lines = vec![Line::from(spans)];
+        lines.extend(continuation);
+        Text::from(lines)
+    }
+
+    /// The view's columns with `[columns]` applied: extra ones added, hidden ones removed
+    fn configured_columns(&self, view: MetadataView) -> MetadataColumns {
+        let config = &self.config.columns;
+        let mut columns = view.columns();
+        columns.priority = config.show.contains(&Column::Priority);
+        columns.tags = config.show.contains(&Column::Tags);
+        for column in &config.hide {
+            match column {
+                Column::Created => columns.created = false,
+                Column::Due => columns.due = false,
+                Column::Completed => columns.completed = false,
+                Column::Parent => columns.parent = false,
+                Column::Start => columns.start = false,
+                Column::Priority => columns.priority = false,
+                Column::Tags => columns.tags = false,
+            }
+        }
+        columns
     }
 
+    /// Attachment and note markers shown after a todo's title
+    fn row_indicators(&self, todo_id: i64) -> String {
+        let mut indicators = String::new();
+        if let Some(days) = self.stale_ages.get(&todo_id) {
+            indicators.push_str(&format!(" {}d", days));
+        }
+        if let Some(minutes) = self.estimates.get(&todo_id) {
+            indicators.push_str(&format!(" ~{}", agenda::format_estimate(*minutes)));
+        }
+        if let Some(waiting) = self.waiting.get(&todo_id) {
+            indicators.push_str(&format!(" →{}", waiting.on));
+        }
+        if let Some(labels) = self.labels.get(&todo_id) {
+            if let Some(context) = &labels.context {
+                indicators.push_str(&format!(" @{}", context));
+            }
+            if let Some(energy) = labels.energy {
+                indicators.push_str(&format!(" ⚡{}", energy.as_str()));
+            }
+        }
+        for (counts, symbol) in [(&self.attachment_counts, "📎"), (&self.note_counts, "📝"), (&self.pomodoro_counts, "🍅")] {
+            match counts.get(&todo_id) {
+                Some(1) => indicators.push_str(&format!(" {}", symbol)),
+                Some(count) => indicators.push_str(&format!(" {}{}", symbol, count)),
+                None => {}
+            }
+        }
+        indicators
+    }
 
+    fn draw_attachments(&mut self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(70, 50, area);
+        f.render_widget(Clear, popup_area);
 
-    fn draw_create_mode(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)])
-            .split(area);
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(popup_area);
 
-        // Title field
-        let title_style = if self.create_field_focus == CreateFieldFocus::Title {
-            Style::default().fg(CatppuccinFrappe::YELLOW)
-        } else {
-            Style::default().fg(CatppuccinFrappe::BORDER)
-        };
-        let title_display = if self.input_title.is_empty() {
-            "e.g., 'p0 Fix critical bug' (p0=highest priority)".to_string()
-        } else {
-            self.input_title.clone()
-        };
-        let title_input = Paragraph::new(title_display.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Title").border_style(title_style))
-            .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(title_input, chunks[0]);
+        let items: Vec<ListItem> = self
+            .attachments
+            .iter()
+            .map(|attachment| {
+                let (kind, color) = if attachment.is_url() {
+                    ("url  ", CatppuccinFrappe::SAPPHIRE)
+                } else {
+                    ("file ", CatppuccinFrappe::PEACH)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(kind, Style::default().fg(color)),
+                    Span::styled(attachment.target.clone(), Style::default().fg(CatppuccinFrappe::TEXT)),
+                    Span::styled(
+                        format!("  {}", attachment.created_at.with_timezone(&Local).format(&self.config.dates.row_format(false))),
+                        Style::default().fg(CatppuccinFrappe::CREATION_TIME),
+                    ),
+                ]))
+            })
+            .collect();
 
-        // Due Date fields - split into two side-by-side boxes
-        let date_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(chunks[1]);
+        let todo_title = self.attachment_todo.as_ref().map_or("", |(_, title)| title.as_str());
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Attachments: {} - Enter=Open, n=Add, d=Remove, Esc=Close", todo_title))
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, chunks[0], &mut self.attachment_list_state);
 
-        // Relative date field (left)
-        let relative_style = if self.create_field_focus == CreateFieldFocus::DueDateRelative {
-            Style::default().fg(CatppuccinFrappe::YELLOW)
+        let (input_text, input_style) = if self.search_input_mode {
+            (self.attachment_input.clone(), Style::default().fg(CatppuccinFrappe::TEXT))
         } else {
-            Style::default().fg(CatppuccinFrappe::BORDER)
+            ("Press n to attach a file path or URL".to_string(), Style::default().fg(CatppuccinFrappe::SUBTEXT0))
         };
-        let relative_display = if self.input_due_date_relative.is_empty() {
-            "e.g., '2' (2 days), '1w', '3h'".to_string()
-        } else {
-            self.input_due_date_relative.clone()
+        let input = Paragraph::new(input_text)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("New Attachment (path or URL)")
+                .border_style(Style::default().fg(if self.search_input_mode { CatppuccinFrappe::YELLOW } else { CatppuccinFrappe::BORDER }))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .style(input_style);
+        f.render_widget(input, chunks[1]);
+    }
+
+    fn draw_filter_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
         };
-        let relative_input = Paragraph::new(relative_display.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Relative (optional)").border_style(relative_style))
+        f.render_widget(Clear, prompt_area);
+
+        let input = Paragraph::new(format!(":{}", self.filter_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Filter - e.g. due<7d status:open parent:12 /regex/, or id 1234 to go there (Enter=Apply, empty=Clear, Esc=Cancel)")
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(relative_input, date_chunks[0]);
+        f.render_widget(input, prompt_area);
+    }
 
-        // Absolute date field (right)
-        let absolute_style = if self.create_field_focus == CreateFieldFocus::DueDateAbsolute {
-            Style::default().fg(CatppuccinFrappe::YELLOW)
-        } else {
-            Style::default().fg(CatppuccinFrappe::BORDER)
-        };
-        let absolute_display = if self.input_due_date_absolute.is_empty() {
-            "e.g., '2025-10-20 14:30'".to_string()
-        } else {
-            self.input_due_date_absolute.clone()
+    fn draw_defer_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
         };
-        let absolute_input = Paragraph::new(absolute_display.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Absolute (optional)").border_style(absolute_style))
+        f.render_widget(Clear, prompt_area);
+
+        let input = Paragraph::new(format!("Start: {}", self.defer_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Defer until - e.g. 2d, 1w, 2025-03-03 (Enter=Set, empty=Start now, Esc=Cancel)")
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(absolute_input, date_chunks[1]);
+        f.render_widget(input, prompt_area);
+    }
 
-        // Parent field  
-        let parent_style = if self.create_field_focus == CreateFieldFocus::Parent {
-            Style::default().fg(CatppuccinFrappe::YELLOW)
-        } else {
-            Style::default().fg(CatppuccinFrappe::BORDER)
+    fn draw_delegate_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
         };
-        let parent_display = if self.input_parent.is_empty() {
-            "Press Tab to focus, type to search for parent, 'r' to clear...".to_string()
-        } else {
-            self.input_parent.clone()
+        f.render_widget(Clear, prompt_area);
+
+        let input = Paragraph::new(format!("Waiting on: {}", self.delegate_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Delegate - e.g. Alice, or Alice, 3d to follow up (Enter=Set, empty=Take back, Esc=Cancel)")
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(input, prompt_area);
+    }
+
+    fn draw_note_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
         };
-        let parent_input = Paragraph::new(parent_display.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Parent (optional)").border_style(parent_style))
+        f.render_widget(Clear, prompt_area);
+
+        let title = self.get_selected_todo().map_or(String::new(), |todo| todo.title.clone());
+        let input = Paragraph::new(format!("Note: {}", self.note_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Add a note to '{}' (Enter=Add, Esc=Cancel)", title))
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(parent_input, chunks[2]);
+        f.render_widget(input, prompt_area);
+    }
 
-        // Description field
-        let desc_style = if self.create_field_focus == CreateFieldFocus::Description {
-            Style::default().fg(CatppuccinFrappe::YELLOW)
-        } else {
-            Style::default().fg(CatppuccinFrappe::BORDER)
+    fn draw_quick_edit_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
         };
-        let description_input = Paragraph::new(self.input_description.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Description (optional)").border_style(desc_style))
+        f.render_widget(Clear, prompt_area);
+
+        let (label, title) = match self.quick_edit_field {
+            QuickEditField::Title => ("Title", "Rename (Enter=Save, Esc=Cancel)"),
+            QuickEditField::Due => ("Due", "Due date - e.g. 2d, 1w, 2025-03-03 17:00 (Enter=Set, empty=Clear, Esc=Cancel)"),
+            QuickEditField::Tags => ("Tags", "Tags in the title - e.g. #work #urgent (Enter=Set, empty=Remove all, Esc=Cancel)"),
+            QuickEditField::Estimate => ("Estimate", "Time it will take - e.g. 45m, 2h, 1h30m (Enter=Set, empty=Clear, Esc=Cancel)"),
+            QuickEditField::Flag => ("Flag", "A color - red, orange, yellow, green, teal, blue, purple, pink - or an emoji (Enter=Set, empty=Clear, Esc=Cancel)"),
+        };
+        let input = Paragraph::new(format!("{}: {}", label, self.quick_edit_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::YELLOW))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(description_input, chunks[3]);
+        f.render_widget(input, prompt_area);
+
+        let popup_height = self.tag_completions.len() as u16 + 2;
+        self.draw_tag_completions(f, Rect {
+            x: prompt_area.x + 2,
+            y: prompt_area.y.saturating_sub(popup_height),
+            width: prompt_area.width.saturating_sub(2),
+            height: popup_height.min(prompt_area.y),
+        });
     }
 
-    fn draw_confirm_delete(&self, f: &mut Frame, area: Rect) {
-        let popup_area = centered_rect(50, 20, area);
+    /// The open tag completion list, at most `area` in size
+    fn draw_tag_completions(&self, f: &mut Frame, area: Rect) {
+        if self.tag_completions.is_empty() {
+            return;
+        }
+        let items: Vec<ListItem> = self
+            .tag_completions
+            .iter()
+            .map(|(tag, count)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("#{}", tag), Style::default().fg(CatppuccinFrappe::TEAL)),
+                    Span::styled(format!("  {}", count), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
+                ]))
+            })
+            .collect();
+        let width = self
+            .tag_completions
+            .iter()
+            .map(|(tag, count)| tag.width() + count.to_string().len() + 7)
+            .max()
+            .unwrap_or(0)
+            .max(24) as u16;
+        let popup_area = Rect { width: width.min(area.width), ..area };
         f.render_widget(Clear, popup_area);
-        
-        let block = Block::default()
-            .title("Confirm Delete")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(CatppuccinFrappe::RED))
-            .style(Style::default().bg(CatppuccinFrappe::BASE));
-        
-        let paragraph = Paragraph::new("Are you sure you want to delete this todo?\n\nPress 'y' to confirm, 'n' to cancel")
-            .block(block)
-            .style(Style::default().fg(CatppuccinFrappe::TEXT))
-            .wrap(Wrap { trim: true });
-        
-        f.render_widget(paragraph, popup_area);
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Tags")
+                .border_style(Style::default().fg(CatppuccinFrappe::TEAL))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ");
+        let mut state = self.tag_completion_state.clone();
+        f.render_stateful_widget(list, popup_area, &mut state);
     }
 
-    fn draw_list_find_mode(&mut self, f: &mut Frame, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)])
-            .split(area);
+    fn draw_capture_prompt(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
+        };
+        f.render_widget(Clear, prompt_area);
 
-        // Search input box
-        let search_input = Paragraph::new(self.search_query.as_str())
+        let target = match &self.capture_parent {
+            Some((_, title)) => format!("under '{}'", title),
+            None => "at top level".to_string(),
+        };
+        let title = match self.capture_confirm {
+            Some(count) => format!("Add {} todos {}? (Enter=Add all, type to edit, Esc=Cancel)", count, target),
+            None => format!("Capture {} ({} added) - !p1 @tag due:2d, ; between todos (Enter=Add, Esc=Done)", target, self.capture_count),
+        };
+        // Pasted lines are shown on one line, the way they're split
+        let input = Paragraph::new(format!("+ {}", self.capture_input.replace('\n', "; ")))
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title("Search (regex supported)")
-                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE)))
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::GREEN))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .style(Style::default().fg(CatppuccinFrappe::TEXT));
-        f.render_widget(search_input, chunks[0]);
+        f.render_widget(input, prompt_area);
+
+        let popup_height = self.tag_completions.len() as u16 + 2;
+        self.draw_tag_completions(f, Rect {
+            x: prompt_area.x + 2,
+            y: prompt_area.y.saturating_sub(popup_height),
+            width: prompt_area.width.saturating_sub(2),
+            height: popup_height.min(prompt_area.y),
+        });
+    }
 
-        // Search results
-        let items: Vec<ListItem> = self
-            .search_results
-            .iter()
-            .map(|todo| {
-                let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                let completed_time = if let Some(completed_at) = todo.completed_at {
-                    format!(" | Completed: {}", completed_at.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
-                };
-                let due_by_text = if let Some(due_by) = todo.due_by {
-                    format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
-                };
-                let parent_title = self.database.get_parent_title(todo.parent_id)
-                    .unwrap_or(None)
-                    .unwrap_or_else(|| "null".to_string());
+    fn draw_quick_add(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.y + area.height.saturating_sub(3),
+            width: area.width,
+            height: area.height.min(3),
+        };
+        f.render_widget(Clear, prompt_area);
 
-                let status_icon = if todo.is_completed() { "[✓]" } else { "[ ]" };
-                let title_style = if todo.is_completed() {
-                    Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT)
-                } else {
-                    Style::default()
-                };
+        let (_, search) = capture::split_parent_search(&self.quick_add_input);
+        let target = match (search, self.quick_add_parent()) {
+            (Some(_), Some(parent)) => format!("under '{}'", parent.title),
+            (Some(search), None) => format!("no todo matches '{}'", search),
+            (None, _) => "at top level".to_string(),
+        };
+        let title = format!("Quick add {} - due:2d #tag !p1 >parent (Enter=Add, Esc=Cancel)", target);
+        let input = Paragraph::new(format!("+ {}", self.quick_add_input))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(CatppuccinFrappe::GREEN))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .style(Style::default().fg(CatppuccinFrappe::TEXT));
+        f.render_widget(input, prompt_area);
+
+        let popup_height = self.tag_completions.len() as u16 + 2;
+        self.draw_tag_completions(f, Rect {
+            x: prompt_area.x + 2,
+            y: prompt_area.y.saturating_sub(popup_height),
+            width: prompt_area.width.saturating_sub(2),
+            height: popup_height.min(prompt_area.y),
+        });
+    }
+
+    fn draw_filter_picker(&mut self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(70, 60, area);
+        f.render_widget(Clear, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(popup_area);
 
+        let active_name = self.active_filter.as_ref().map(|(name, _)| name.as_str());
+        let items: Vec<ListItem> = self
+            .saved_filters
+            .iter()
+            .map(|saved| {
+                let marker = if Some(saved.name.as_str()) == active_name { "● " } else { "  " };
                 ListItem::new(Line::from(vec![
-                    Span::raw(format!("{} {} ", todo.id_mod(), status_icon)),
-                    Span::styled(todo.title.clone(), title_style),
-                    Span::raw(format!(" | Created: {}{}{} | Parent: {}", created_time, due_by_text, completed_time, parent_title)),
+                    Span::styled(marker, Style::default().fg(CatppuccinFrappe::GREEN)),
+                    Span::styled(saved.name.clone(), Style::default().fg(CatppuccinFrappe::TEXT).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("  {}", saved.query), Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
                 ]))
             })
             .collect();
 
-        let results_title = format!("Search Results ({} found)", self.search_results.len());
         let list = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title(results_title)
-                .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
+                .title("Saved Filters - Enter=Apply, n=New, d=Delete, x=Clear, Esc=Close")
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
             .highlight_style(Style::default()
                 .bg(CatppuccinFrappe::SELECTED_BG)
                 .fg(CatppuccinFrappe::SELECTED))
             .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, chunks[0], &mut self.filter_list_state);
 
-        f.render_stateful_widget(list, chunks[1], &mut self.search_list_state);
+        let (input_text, input_style) = if self.search_input_mode {
+            (self.filter_input.clone(), Style::default().fg(CatppuccinFrappe::TEXT))
+        } else {
+            (
+                "e.g. 'work week: due<7d AND tag:work -status:done'".to_string(),
+                Style::default().fg(CatppuccinFrappe::SUBTEXT0),
+            )
+        };
+        let input = Paragraph::new(input_text)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("New Filter (name: expression)")
+                .border_style(Style::default().fg(if self.search_input_mode { CatppuccinFrappe::YELLOW } else { CatppuccinFrappe::BORDER }))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .style(input_style);
+        f.render_widget(input, chunks[1]);
     }
 
-    fn draw_parent_search_mode(&mut self, f: &mut Frame, area: Rect) {
+    fn draw_template_picker(&mut self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(70, 60, area);
+        f.render_widget(Clear, popup_area);
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)])
-            .split(area);
-
-        // Search input box
-        let search_input = Paragraph::new(self.search_query.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Search for Parent Todo (regex supported)"));
-        f.render_widget(search_input, chunks[0]);
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(popup_area);
 
-        // Search results - same as regular search but with different title
         let items: Vec<ListItem> = self
-            .search_results
+            .saved_templates
             .iter()
-            .map(|todo| {
-                let created_time = todo.created_at.with_timezone(&Local).format("%m/%d %H:%M").to_string();
-                let completed_time = if let Some(completed_at) = todo.completed_at {
-                    format!(" | Completed: {}", completed_at.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
-                };
-                let due_by_text = if let Some(due_by) = todo.due_by {
-                    format!(" | Due: {}", due_by.with_timezone(&Local).format("%m/%d %H:%M"))
-                } else {
-                    String::new()
-                };
-                let parent_title = self.database.get_parent_title(todo.parent_id)
-                    .unwrap_or(None)
-                    .unwrap_or_else(|| "null".to_string());
-
-                let status_icon = if todo.is_completed() { "[✓]" } else { "[ ]" };
-                let title_style = if todo.is_completed() {
-                    Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT)
-                } else {
-                    Style::default()
+            .map(|saved| {
+                let summary = match template::parse_items(saved) {
+                    Ok(root) => format!("  {} todos: {}", root.count(), root.title),
+                    Err(_) => "  (unreadable)".to_string(),
                 };
-
                 ListItem::new(Line::from(vec![
-                    Span::raw(format!("{} {} ", todo.id_mod(), status_icon)),
-                    Span::styled(todo.title.clone(), title_style),
-                    Span::raw(format!(" | Created: {}{}{} | Parent: {}", created_time, due_by_text, completed_time, parent_title)),
+                    Span::styled(saved.name.clone(), Style::default().fg(CatppuccinFrappe::TEXT).add_modifier(Modifier::BOLD)),
+                    Span::styled(summary, Style::default().fg(CatppuccinFrappe::SUBTEXT0)),
                 ]))
             })
             .collect();
 
-        let results_title = format!("Select Parent ({} found)", self.search_results.len());
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title(results_title))
-            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
-            .highlight_symbol(">> ");
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Templates - Enter=Create under selected, r=Create at top level, s=Save, d=Delete, Esc=Close")
+                .border_style(Style::default().fg(CatppuccinFrappe::SAPPHIRE))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .highlight_style(Style::default()
+                .bg(CatppuccinFrappe::SELECTED_BG)
+                .fg(CatppuccinFrappe::SELECTED))
+            .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, chunks[0], &mut self.template_list_state);
 
-        f.render_stateful_widget(list, chunks[1], &mut self.search_list_state);
+        let (input_text, input_style) = if self.search_input_mode {
+            (self.template_input.clone(), Style::default().fg(CatppuccinFrappe::TEXT))
+        } else {
+            let hint = match &self.template_target {
+                Some((_, title)) => format!("Press s to save '{}' and its subtasks as a template", title),
+                None => "Select a todo before opening templates to save it as one".to_string(),
+            };
+            (hint, Style::default().fg(CatppuccinFrappe::SUBTEXT0))
+        };
+        let input = Paragraph::new(input_text)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("New Template Name")
+                .border_style(Style::default().fg(if self.search_input_mode { CatppuccinFrappe::YELLOW } else { CatppuccinFrappe::BORDER }))
+                .style(Style::default().bg(CatppuccinFrappe::BASE)))
+            .style(input_style);
+        f.render_widget(input, chunks[1]);
     }
 
-    fn draw_help_page(&self, f: &mut Frame, area: Rect) {
-        // Create a centered popup
-        let popup_area = centered_rect(80, 70, area);
-        
-        // Clear the background
+    fn draw_lock_screen(&self, f: &mut Frame, area: Rect) {
+        f.render_widget(Block::default().style(Style::default().bg(CatppuccinFrappe::BASE)), area);
+
+        let popup_area = centered_rect(50, 30, area);
         f.render_widget(Clear, popup_area);
-        
-        let help_content = vec![
-            "NAVIGATION".to_string(),
-            "  j/k or ↑/↓      Navigate todos".to_string(),
-            "  Ctrl+d/Ctrl+u   Half-page scroll down/up".to_string(),
-            "  h/l or ←/→      Navigate hierarchy levels".to_string(),
-            "  t               Expand/Collapse tree nodes".to_string(),
-            "".to_string(),
-            "ACTIONS".to_string(),
-            "  Space           Toggle completion status".to_string(),
-            "  Enter           View/Edit todo in $EDITOR".to_string(),
-            "  n               Create new todo".to_string(),
-            "  d               Delete selected todo".to_string(),
-            "  m               Move todo (tree view only)".to_string(),
-            "  c               Show/hide completed todos".to_string(),
-            "  h               Toggle hidden status (tree view only)".to_string(),
-            "  H               Toggle showing/hiding hidden todos (tree view only)".to_string(),
-            "".to_string(),
-            "SEARCH & MODES".to_string(),
-            "  /               Tree search with live highlighting".to_string(),
-            "  f               List search (flat view)".to_string(),
-            "  g               Goto ID mode (tree view only)".to_string(),
-            "  n/N             Navigate search matches (in search/goto mode)".to_string(),
-            "".to_string(),
-            "GENERAL".to_string(),
-            "  a               Show/hide this help page".to_string(),
-            "  q               Quit application".to_string(),
-            "  Esc             Cancel current operation".to_string(),
-            "".to_string(),
-            "Press a, Esc, or q to close this help".to_string(),
+
+        let masked_input = "*".repeat(self.lock_input.chars().count());
+        let mut lines = vec![
+            Line::from(Span::styled("TodoDB is locked", Style::default().fg(CatppuccinFrappe::LAVENDER).add_modifier(Modifier::BOLD))),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Passphrase: ", Style::default().fg(CatppuccinFrappe::SUBTEXT1)),
+                Span::styled(masked_input, Style::default().fg(CatppuccinFrappe::TEXT)),
+            ]),
+            Line::from(""),
+            Line::from(Span::styled("Enter to unlock, Esc to clear", Style::default().fg(CatppuccinFrappe::SUBTEXT0))),
         ];
-        
-        let help_text = help_content.join("\n");
-        
-        let help_block = Paragraph::new(help_text)
+        if let Some(error) = &self.error_message {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(error.as_str(), Style::default().fg(CatppuccinFrappe::ERROR))));
+        }
+
+        let lock_block = Paragraph::new(lines)
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title("TodoDB Help")
+                .title("Locked")
                 .border_style(Style::default().fg(CatppuccinFrappe::BLUE)))
-            .style(Style::default().fg(CatppuccinFrappe::TEXT))
+            .style(Style::default().bg(CatppuccinFrappe::BASE))
             .wrap(Wrap { trim: true });
-        
-        f.render_widget(help_block, popup_area);
+
+        f.render_widget(lock_block, popup_area);
     }
 
-    fn draw_help(&self, f: &mut Frame, area: Rect) {
-        let help_text = "Press a for help | q to quit";
+    fn draw_tab_bar(&self, f: &mut Frame, area: Rect) {
+        let current = self.current_view();
+        let mut spans = Vec::new();
+        for (i, &view) in self.tabs.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled("│", Style::default().fg(CatppuccinFrappe::SURFACE2)));
+            }
+            let style = if view == current {
+                Style::default().bg(CatppuccinFrappe::SELECTED_BG).fg(CatppuccinFrappe::SELECTED).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(CatppuccinFrappe::SUBTEXT0)
+            };
+            spans.push(Span::styled(format!(" {} {} ", self.view_icon(view), self.view_title(view)), style));
+        }
+        spans.push(Span::styled("  Tab=Switch", Style::default().fg(CatppuccinFrappe::SURFACE2)));
+        f.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    /// The keys the footer suggests for the current mode, most useful first, as the
+    /// mode's key handler reads them
+    fn key_hints(&self) -> Vec<(&'static str, &'static str)> {
+        let search = |typing: bool| if typing { vec![("Enter", "Done typing"), ("Esc", "Cancel")] } else { vec![("n/N", "Next/prev match"), ("Enter", "Edit"), ("Esc", "Clear")] };
+        let prompt = vec![("Enter", "Save"), ("Esc", "Cancel")];
+        let mut hints = match self.mode {
+            AppMode::List if self.use_tree_view => vec![
+                ("Space", "Done"), ("n", "New"), ("e", "Due"), ("r", "Rename"), ("d", "Delete"), ("m", "Move"),
+                ("/", "Search"), (":", "Filter"), ("Tab", "Views"), ("q", "Quit"),
+            ],
+            AppMode::List => vec![
+                ("Space", "Done"), ("n", "New"), ("e", "Due"), ("r", "Rename"), ("d", "Delete"),
+                ("f", "Find"), (":", "Filter"), ("Tab", "Views"), ("q", "Quit"),
+            ],
+            AppMode::CompletedView => vec![("Space", "Reopen"), ("t", "Range"), ("p", "This project"), ("X", "Cancel"), ("Enter", "Edit"), ("q", "Back")],
+            AppMode::ScheduledView => vec![("s", "Start now"), ("D", "Defer"), ("Space", "Done"), ("e", "Due"), ("Enter", "Edit"), ("q", "Back")],
+            AppMode::Someday => vec![("p", "Promote"), ("Space", "Done"), ("e", "Due"), ("Enter", "Edit"), ("q", "Back")],
+            AppMode::Waiting => vec![(">", "Delegate"), ("<", "Take back"), ("Space", "Done"), ("e", "Due"), ("Enter", "Edit"), ("q", "Back")],
+            AppMode::Today if self.carry_over.is_some() => vec![("y", "Carry over"), ("n", "Start fresh")],
+            AppMode::Today => vec![("Space", "Done"), ("x", "Take out of plan"), ("e", "Due"), ("E", "Estimate"), ("Enter", "Edit"), ("q", "Back")],
+            AppMode::Create => vec![("Ctrl+S", "Save"), ("Tab", "Next field"), ("Esc", "Cancel")],
+            AppMode::ConfirmDelete | AppMode::ConfirmCascade => vec![("y", "Yes"), ("n", "No")],
+            AppMode::ListFind | AppMode::TreeSearch | AppMode::IdModGoto => search(self.search_input_mode),
+            AppMode::ParentSearch => vec![("↑/↓", "Choose"), ("Enter", "Select"), ("Esc", "Cancel")],
+            AppMode::ProfilePicker => vec![("j/k", "Choose"), ("Enter", "Switch"), ("Esc", "Cancel")],
+            AppMode::Move => vec![("j/k", "Target"), ("Enter", "Move here"), ("Tab", "Under/after"), ("Esc", "Cancel")],
+            AppMode::FilterPicker if self.search_input_mode => prompt,
+            AppMode::FilterPicker => vec![("Enter", "Apply"), ("n", "New"), ("d", "Delete"), ("x", "Clear filter"), ("Esc", "Close")],
+            AppMode::FilterPrompt | AppMode::DeferPrompt | AppMode::DelegatePrompt | AppMode::NotePrompt | AppMode::QuickEdit => prompt,
+            AppMode::Capture | AppMode::QuickAdd => vec![("Enter", "Add"), ("Tab", "Complete tag"), ("Esc", "Cancel")],
+            AppMode::Attachments => vec![("Enter", "Open"), ("n", "Add"), ("d", "Remove"), ("Esc", "Close")],
+            AppMode::Onboarding => vec![("Space", "Toggle"), ("Enter", "Import checked"), ("s", "Skip")],
+            AppMode::TemplatePicker => vec![("Enter", "Create here"), ("r", "Create at top"), ("s", "Save subtree"), ("d", "Delete"), ("Esc", "Close")],
+            AppMode::CopyMenu => vec![("t", "Title"), ("m", "Markdown"), ("s", "Subtree"), ("Esc", "Cancel")],
+            AppMode::Stats => vec![("j/k", "Choose"), ("l", "Drill in"), ("h", "Back out"), ("q", "Close")],
+            AppMode::Review if self.review_confirm_delete => vec![("y", "Delete"), ("n", "Keep")],
+            AppMode::Review => vec![("c", "Done"), ("k", "Keep"), ("s", "Snooze"), ("h", "Hide"), ("d", "Delete"), ("n/p", "Next/prev"), ("q", "End")],
+            AppMode::Agenda => vec![("j/k", "Scroll"), ("q", "Close")],
+            AppMode::History => vec![("a", "This todo/all"), ("j/k", "Scroll"), ("q", "Close")],
+            AppMode::ContextPicker => vec![("1-9", "Context"), ("l/m/h", "Energy"), ("x", "Clear"), ("Enter", "Done")],
+            AppMode::Help | AppMode::Locked => Vec::new(),
+        };
+        if !self.is_in_text_input_mode() {
+            hints.push(("a", "Help"));
+        }
+        hints
+    }
+
+    /// The footer's hint bar: as many of the mode's hints as fit in `width`, keeping the
+    /// last one (help, where it works)
+    fn hint_bar(&self, width: usize) -> Line<'static> {
+        let hints: Vec<(String, &str)> = self.key_hints().into_iter().map(|(keys, action)| (self.keys_with_aliases(keys), action)).collect();
+        let hint_width = |(keys, action): &(String, &str)| keys.width() + action.width() + 3;
+        let Some((last, rest)) = hints.split_last() else {
+            return Line::default();
+        };
+        let mut used = hint_width(last);
+        let mut shown: Vec<&(String, &str)> = rest
+            .iter()
+            .take_while(|hint| {
+                used += hint_width(hint);
+                used <= width
+            })
+            .collect();
+        shown.push(last);
+        let mut spans = Vec::new();
+        for (keys, action) in shown {
+            spans.push(Span::styled(format!(" {}", keys), Style::default().fg(CatppuccinFrappe::SAPPHIRE).add_modifier(Modifier::BOLD)));
+            spans.push(Span::styled(format!(" {} ", action), Style::default().fg(CatppuccinFrappe::SUBTEXT0)));
+        }
+        Line::from(spans)
+    }
 
-        let help = Paragraph::new(help_text)
+    fn draw_help(&self, f: &mut Frame, area: Rect) {
+        let title = match &self.current_profile {
+            Some(profile) => format!("Help - Profile: {}", profile),
+            None => "Help".to_string(),
+        };
+        // The pomodoro countdown and where the selected todo sits; the keys to press go
+        // in the bottom border
+        let countdown = self.pomodoro.as_ref().map(|pomodoro| {
+            let (icon, color) = match pomodoro.phase {
+                Phase::Work => ("🍅", CatppuccinFrappe::RED),
+                Phase::Break => ("☕", CatppuccinFrappe::GREEN),
+            };
+            let title = self.tree_manager.get_todo_by_id(pomodoro.todo_id).map(|todo| todo.title.as_str()).unwrap_or_default();
+            let text = format!("{} {} {} | ", icon, pomodoro::format_countdown(pomodoro.remaining(Utc::now())), text::truncate_to_width(title, 24));
+            Span::styled(text, Style::default().fg(color))
+        });
+        let countdown_width = countdown.as_ref().map_or(0, |span| span.width());
+        let mut line = self.breadcrumb((area.width as usize).saturating_sub(2 + countdown_width)).unwrap_or_default();
+        if let Some(countdown) = countdown {
+            line.spans.insert(0, countdown);
+        }
+        let help = Paragraph::new(line)
             .block(Block::default()
                 .borders(Borders::ALL)
-                .title("Help")
+                .title(title)
+                .title_bottom(self.hint_bar((area.width as usize).saturating_sub(4)))
                 .border_style(Style::default().fg(CatppuccinFrappe::BORDER)))
             .style(Style::default().fg(CatppuccinFrappe::SUBTEXT1));
         
         let mut help_area = area;
-        if let Some(error) = &self.error_message {
+        let message = match (&self.error_message, &self.status_message) {
+            (Some(error), _) => Some((error, CatppuccinFrappe::ERROR)),
+            (None, Some(status)) => Some((status, CatppuccinFrappe::GREEN)),
+            (None, None) => None,
+        };
+        if let Some((text, color)) = message {
             let error_chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Length(1), Constraint::Length(2)])
                 .split(area);
             
-            let error_paragraph = Paragraph::new(error.as_str())
-                .style(Style::default().fg(CatppuccinFrappe::ERROR));
+            let error_paragraph = Paragraph::new(text.as_str())
+                .style(Style::default().fg(color));
             f.render_widget(error_paragraph, error_chunks[0]);
             
             help_area = error_chunks[1];
@@ -3056,22 +8371,141 @@ impl App {
     }
 }
 
+/// Select `new_row` where `old_row` was selected, scrolling by the same amount so
+/// the selected todo stays on the same screen row
+fn follow_selection(state: &mut ListState, old_row: usize, new_row: usize) {
+    let offset = (state.offset() + new_row).saturating_sub(old_row);
+    state.select(Some(new_row));
+    *state.offset_mut() = offset;
+}
+
+/// The todo on `row` followed by the rows after it, then the ones before it, nearest
+/// first: where the selection goes, in order, as todos leave the list
+fn selection_candidates(todos: &[Todo], row: usize) -> Vec<i64> {
+    let row = row.min(todos.len());
+    todos[row..].iter().chain(todos[..row].iter().rev()).map(|todo| todo.id).collect()
+}
+
+/// Select the first of `selected`'s candidates still in `todos`, keeping it on screen
+/// where it was
+fn follow_todo(state: &mut ListState, selected: Option<(usize, Vec<i64>)>, todos: &[Todo]) {
+    let Some((row, candidates)) = selected else {
+        return;
+    };
+    let rows: HashMap<i64, usize> = todos.iter().enumerate().map(|(new_row, todo)| (todo.id, new_row)).collect();
+    if let Some(&new_row) = candidates.iter().find_map(|id| rows.get(id)) {
+        follow_selection(state, row, new_row);
+    }
+}
+
+/// Move target annotation: " · 3 subtasks, next due 10/20", empty for a bare leaf
+/// A help line cut at spaces to fit `width` columns, later parts lined up under the
+/// description. Lines too long to wrap that narrow are left for the border to clip.
+fn wrap_help_line(line: &str, width: usize) -> Vec<String> {
+    const INDENT: &str = "                  ";
+    let mut parts = Vec::new();
+    let mut rest = line.to_string();
+    while rest.width() > width && width > INDENT.len() + 8 {
+        let (fits, _) = text::split_at_width(&rest, width);
+        let cut = fits.rfind(' ').filter(|&i| i > INDENT.len()).unwrap_or(fits.len());
+        parts.push(rest[..cut].trim_end().to_string());
+        rest = format!("{}{}", INDENT, rest[cut..].trim_start());
+    }
+    parts.push(rest);
+    parts
+}
+
+/// " (+3 subtasks)" for a todo moved along with its descendants, empty for a leaf
+fn subtask_count_suffix(count: usize) -> String {
+    match count {
+        0 => String::new(),
+        1 => " (+1 subtask)".to_string(),
+        n => format!(" (+{} subtasks)", n),
+    }
+}
+
+fn format_subtree_summary(summary: SubtreeSummary, date_format: &str) -> String {
+    let mut parts = Vec::new();
+    match summary.children {
+        0 => {}
+        1 => parts.push("1 subtask".to_string()),
+        n => parts.push(format!("{} subtasks", n)),
+    }
+    if let Some(due) = summary.next_due {
+        parts.push(format!("next due {}", due.with_timezone(&Local).format(date_format)));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" · {}", parts.join(", "))
+    }
+}
+
+/// Split styled spans after `width` columns, at a space where one is in reach:
+/// the spans that fit, and the rest
+fn split_spans(spans: Vec<Span<'_>>, width: usize) -> (Vec<Span<'_>>, Vec<Span<'_>>) {
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+    let mut used = 0;
+    for span in spans {
+        if !tail.is_empty() {
+            tail.push(span);
+        } else if used + span.width() <= width {
+            used += span.width();
+            head.push(span);
+        } else {
+            let (mut fits, mut rest) = text::split_at_width(&span.content, width - used);
+            // Break before the word that doesn't fit rather than inside it
+            if !rest.starts_with(' ')
+                && let Some(space) = fits.rfind(' ')
+            {
+                (fits, rest) = (&span.content[..space], &span.content[space + 1..]);
+            }
+            used += fits.width();
+            head.push(Span::styled(fits.to_string(), span.style));
+            tail.push(Span::styled(rest.trim_start().to_string(), span.style));
+        }
+    }
+    (head, tail)
+}
+
+/// Pad or truncate styled spans to exactly `width` columns
+fn fit_spans(spans: Vec<Span<'_>>, width: usize) -> Vec<Span<'_>> {
+    let mut result = Vec::new();
+    let mut used = 0;
+    for span in spans {
+        let remaining = width - used;
+        if span.width() <= remaining {
+            used += span.width();
+            result.push(span);
+        } else {
+            let fitted = text::truncate_to_width(&span.content, remaining);
+            used += fitted.width();
+            result.push(Span::styled(fitted, span.style));
+            break;
+        }
+    }
+    if used < width {
+        result.push(Span::raw(" ".repeat(width - used)));
+    }
+    result
+}
+
+/// Rows of a bordered list drawn in `area`
+fn visible_rows(area: Rect) -> usize {
+    area.height.saturating_sub(2) as usize
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+    // Narrow terminals get (nearly) full-width popups so their text wraps instead of clipping
+    let percent_x = if r.width < NARROW_WIDTH { percent_x.max(96) } else { percent_x };
+    let width = (r.width as u32 * percent_x as u32 / 100) as u16;
+    let height = ((r.height as u32 * percent_y as u32 / 100) as u16).max(MIN_POPUP_HEIGHT).min(r.height);
+
+    Rect {
+        x: r.x + (r.width - width) / 2,
+        y: r.y + (r.height - height) / 2,
+        width,
+        height,
+    }
 }
\ No newline at end of file
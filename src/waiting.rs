@@ -0,0 +1,35 @@
+use crate::dates;
+use chrono::{DateTime, Utc};
+
+/// A todo delegated to someone else: who it's waiting on and when to chase them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waiting {
+    pub on: String,
+    pub follow_up: Option<DateTime<Utc>>,
+}
+
+/// Parse the delegate prompt: `Alice`, or `Alice, 3d` / `ACME support, 2025-03-10` with a
+/// follow-up date after the last comma. Without one, `default_follow_up` (`[waiting]
+/// follow_up`, empty for none) is used.
+pub fn parse_delegation(input: &str, default_follow_up: &str) -> Result<Waiting, String> {
+    let (on, follow_up) = match input.rsplit_once(',') {
+        Some((on, follow_up)) => (on.trim(), follow_up.trim()),
+        None => (input.trim(), default_follow_up.trim()),
+    };
+    if on.is_empty() {
+        return Err("Who is it waiting on?".to_string());
+    }
+    let follow_up = match follow_up {
+        "" | "none" => None,
+        date => Some(dates::parse_start_date(date).ok_or_else(|| format!("Invalid follow-up date '{}': use 3d, 1w or YYYY-MM-DD", date))?),
+    };
+    Ok(Waiting { on: on.to_string(), follow_up })
+}
+
+/// The delegate prompt's text for an existing delegation, so it can be edited
+pub fn format_delegation(waiting: &Waiting) -> String {
+    match waiting.follow_up {
+        Some(follow_up) => format!("{}, {}", waiting.on, dates::format_start_date(follow_up)),
+        None => waiting.on.clone(),
+    }
+}